@@ -0,0 +1,259 @@
+//! DuckDB through its command-line client
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::{string_literal, AccessMode, Adapter, QueryResult, SessionStatements};
+use crate::config::SqlDialect;
+use crate::profiling;
+use crate::sql_engine::quoting;
+
+/// Environment variable overriding the `duckdb` executable
+pub const DUCKDB_BIN_ENV: &str = "FF_DUCKDB_BIN";
+
+/// Session parameters of other warehouses, which DuckDB has no equivalent for
+const FOREIGN_PARAMETERS: [&str; 2] = ["query_tag", "warehouse"];
+
+pub struct DuckDbAdapter {
+    database: PathBuf,
+    binary: String,
+    mode: AccessMode,
+}
+
+impl DuckDbAdapter {
+    pub fn new(database: &Path, mode: AccessMode) -> Result<Self> {
+        if !database.exists() {
+            bail!("DuckDB database not found: {}", database.display());
+        }
+
+        Ok(Self {
+            database: database.to_path_buf(),
+            binary: env::var(DUCKDB_BIN_ENV).unwrap_or_else(|_| "duckdb".to_string()),
+            mode,
+        })
+    }
+
+    /// Run `sql` with `-bail` so the first failing statement stops the batch
+    ///
+    /// The CLI runs in its own process group: Ctrl-C signals the terminal's whole foreground
+    /// group, and the first one must leave the running statement to finish.
+    fn run(&self, sql: &str) -> Result<String> {
+        let mut command = Command::new(&self.binary);
+        command.arg("-bail").arg("-csv");
+        if self.mode == AccessMode::ReadOnly {
+            command.arg("-readonly");
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        let output = command
+            .arg(&self.database)
+            .arg(sql)
+            .output()
+            .with_context(|| {
+                format!(
+                    "Failed to run '{}'. Install the DuckDB CLI or set {}",
+                    self.binary, DUCKDB_BIN_ENV
+                )
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("{}", stderr.trim()))
+                .with_context(|| format!("DuckDB query failed on {}", self.database.display()));
+        }
+
+        String::from_utf8(output.stdout).context("DuckDB returned non UTF-8 output")
+    }
+}
+
+impl Adapter for DuckDbAdapter {
+    fn query(&self, sql: &str) -> Result<QueryResult> {
+        let _span = profiling::span!("query", "{:.120}", sql.trim());
+        parse_csv_output(&self.run(sql)?)
+    }
+
+    /// Every query runs in a fresh CLI session, so the `USE` is sent along with it
+    fn schema_context(&self, database: Option<&str>, schema: &str) -> Option<String> {
+        let quote = |name| quoting::identifier(name, SqlDialect::DuckDb);
+        Some(match database {
+            Some(database) => format!("USE {}.{}", quote(database), quote(schema)),
+            None => format!("USE {}", quote(schema)),
+        })
+    }
+
+    /// A `SET` per parameter. The CLI session ends with the query, taking the settings with
+    /// it, so there is nothing to reset.
+    fn session(&self, parameters: &BTreeMap<String, Value>) -> Result<SessionStatements> {
+        let mut apply = Vec::new();
+        for (name, value) in parameters {
+            if FOREIGN_PARAMETERS.contains(&name.as_str()) {
+                continue;
+            }
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                bail!("Invalid DuckDB setting name '{}'", name);
+            }
+            let value = match value {
+                Value::String(text) => string_literal(text),
+                Value::Number(number) => number.to_string(),
+                Value::Bool(flag) => flag.to_string(),
+                other => bail!(
+                    "DuckDB setting '{}' must be a string, number or boolean, not {}",
+                    name,
+                    other
+                ),
+            };
+            apply.push(format!("SET {} = {}", name, value));
+        }
+        Ok(SessionStatements {
+            apply,
+            reset: Vec::new(),
+        })
+    }
+}
+
+/// Parse the CLI's `-csv` output; the first record is the header
+fn parse_csv_output(output: &str) -> Result<QueryResult> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(output.as_bytes());
+
+    let columns = reader
+        .headers()
+        .context("Failed to read DuckDB output header")?
+        .iter()
+        .map(str::to_string)
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.context("Failed to read DuckDB output")?;
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+
+    Ok(QueryResult { columns, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_output() {
+        let result =
+            parse_csv_output("id,name\n1,\"Smith, Jane\"\n2,\"say \"\"hi\"\"\"\n").unwrap();
+
+        assert_eq!(result.columns, vec!["id", "name"]);
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0], vec!["1", "Smith, Jane"]);
+        assert_eq!(result.rows[1][1], "say \"hi\"");
+    }
+
+    #[test]
+    fn test_parse_empty_output() {
+        assert_eq!(parse_csv_output("").unwrap(), QueryResult::default());
+    }
+
+    #[test]
+    fn test_session_sets_duckdb_parameters() {
+        let database = tempfile::NamedTempFile::new().unwrap();
+        let adapter = DuckDbAdapter::new(database.path(), AccessMode::ReadWrite).unwrap();
+        let parameters: BTreeMap<String, Value> = serde_yaml::from_str(
+            "memory_limit: 8GB\nthreads: 4\nquery_tag: nightly\npreserve_insertion_order: false\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            adapter.session(&parameters).unwrap().apply,
+            vec![
+                "SET memory_limit = '8GB'",
+                "SET preserve_insertion_order = false",
+                "SET threads = 4",
+            ]
+        );
+
+        let invalid: BTreeMap<String, Value> =
+            serde_yaml::from_str("\"threads; DROP TABLE x\": 1\n").unwrap();
+        assert!(adapter.session(&invalid).is_err());
+    }
+
+    /// Started by `test_interrupt_lets_the_query_finish` in a process group of its own
+    #[test]
+    #[ignore = "interrupts its process group; run by test_interrupt_lets_the_query_finish"]
+    fn interrupted_query() {
+        crate::run::interrupt::install();
+        let database = tempfile::NamedTempFile::new().unwrap();
+        let adapter = DuckDbAdapter::new(database.path(), AccessMode::ReadWrite).unwrap();
+
+        let result = adapter.query("SELECT 42 AS answer").unwrap();
+        assert!(crate::run::interrupt::requested());
+        assert_eq!(result.rows, vec![vec!["42"]]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interrupt_lets_the_query_finish() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::process::CommandExt;
+        use std::time::{Duration, Instant};
+
+        let dir = tempfile::tempdir().unwrap();
+        let started = dir.path().join("started");
+        let binary = dir.path().join("duckdb");
+        std::fs::write(
+            &binary,
+            format!(
+                "#!/bin/sh\ntouch '{}'\nsleep 1\nprintf 'answer\\n42\\n'\n",
+                started.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut child = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "adapters::duckdb::tests::interrupted_query"])
+            .args(["--ignored", "--nocapture"])
+            .env(DUCKDB_BIN_ENV, &binary)
+            .process_group(0)
+            .spawn()
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while !started.exists() {
+            assert!(Instant::now() < deadline, "the query never started");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        // Ctrl-C in a terminal signals the whole foreground process group
+        let signalled = Command::new("kill")
+            .args(["-INT", "--", &format!("-{}", child.id())])
+            .status()
+            .unwrap();
+        assert!(signalled.success());
+        assert!(child.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_schema_context_uses_the_schema() {
+        let database = tempfile::NamedTempFile::new().unwrap();
+        let adapter = DuckDbAdapter::new(database.path(), AccessMode::ReadWrite).unwrap();
+
+        assert_eq!(
+            crate::adapters::in_schema(&adapter, None, "staging", "SELECT * FROM stg_orders"),
+            "USE \"staging\";\nSELECT * FROM stg_orders"
+        );
+        assert_eq!(
+            adapter
+                .schema_context(Some("analytics"), "marts\"; DROP TABLE x; --")
+                .as_deref(),
+            Some("USE \"analytics\".\"marts\"\"; DROP TABLE x; --\"")
+        );
+    }
+}
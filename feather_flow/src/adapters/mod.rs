@@ -0,0 +1,237 @@
+//! Warehouse adapters that execute compiled SQL
+//!
+//! DuckDB is the only supported warehouse. It is driven through the `duckdb` command-line
+//! client, so `ff` does not link a native database library.
+
+pub mod duckdb;
+pub mod read_only;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::config::SqlDialect;
+use crate::sql_engine::quoting;
+use crate::sql_engine::sql_model::SqlModel;
+use crate::sql_engine::tables::ColumnDef;
+use crate::sql_engine::template::DEFAULT_SCHEMA;
+
+/// Rows returned by a query, with every value in its text form
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl QueryResult {
+    /// Ok when the result is exactly one row whose first value is true, else why it is not;
+    /// how `ff assert` and SQL `await:` predicates read their queries
+    pub fn truth(&self) -> std::result::Result<(), String> {
+        let [row] = self.rows.as_slice() else {
+            return Err(format!(
+                "expected a single row with a boolean, got {} rows",
+                self.rows.len()
+            ));
+        };
+
+        match row
+            .first()
+            .map(|value| value.trim().to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("true" | "t" | "1") => Ok(()),
+            Some("false" | "f" | "0") => Err("returned false".to_string()),
+            Some("") => Err("returned NULL".to_string()),
+            Some(other) => Err(format!("expected a boolean, got '{}'", other)),
+            None => Err("returned no columns".to_string()),
+        }
+    }
+}
+
+/// A connection to a warehouse
+pub trait Adapter {
+    /// Run a single query and return its rows
+    fn query(&self, sql: &str) -> Result<QueryResult>;
+
+    /// Statement making `[database.]schema` where unqualified table names resolve, for
+    /// warehouses that resolve them against the connection's defaults
+    fn schema_context(&self, _database: Option<&str>, _schema: &str) -> Option<String> {
+        None
+    }
+
+    /// Statements setting a model's `session:` parameters before its SQL and resetting them
+    /// after; parameters the warehouse has no equivalent for are ignored
+    fn session(&self, _parameters: &BTreeMap<String, Value>) -> Result<SessionStatements> {
+        Ok(SessionStatements::default())
+    }
+}
+
+/// Statements sent around one model's SQL for its session parameters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStatements {
+    pub apply: Vec<String>,
+    pub reset: Vec<String>,
+}
+
+/// Whether an adapter may write to the warehouse
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessMode {
+    #[default]
+    ReadWrite,
+    /// Only metadata queries and SELECTs are allowed (`--read-only`)
+    ReadOnly,
+}
+
+/// Connect to the database at `database`
+pub fn connect(database: &Path, mode: AccessMode) -> Result<Box<dyn Adapter>> {
+    let adapter = Box::new(duckdb::DuckDbAdapter::new(database, mode)?);
+    Ok(match mode {
+        AccessMode::ReadWrite => adapter,
+        AccessMode::ReadOnly => Box::new(read_only::ReadOnlyAdapter::new(adapter)),
+    })
+}
+
+/// Whether the relation `[database.]schema.name` exists in the warehouse behind `adapter`
+pub fn relation_exists(
+    adapter: &dyn Adapter,
+    database: Option<&str>,
+    schema: &str,
+    name: &str,
+) -> Result<bool> {
+    let mut sql = format!(
+        "SELECT 1 FROM information_schema.tables WHERE table_schema = {} AND table_name = {}",
+        string_literal(schema),
+        string_literal(name)
+    );
+    if let Some(database) = database {
+        sql.push_str(&format!(
+            " AND table_catalog = {}",
+            string_literal(database)
+        ));
+    }
+
+    Ok(!adapter.query(&sql)?.rows.is_empty())
+}
+
+/// Name and data type of each column of `[database.]schema.name`, in order; empty when the
+/// relation does not exist
+pub fn relation_columns(
+    adapter: &dyn Adapter,
+    database: Option<&str>,
+    schema: &str,
+    name: &str,
+) -> Result<Vec<(String, String)>> {
+    let mut sql = format!(
+        "SELECT column_name, data_type FROM information_schema.columns WHERE table_schema = {} AND table_name = {}",
+        string_literal(schema),
+        string_literal(name)
+    );
+    if let Some(database) = database {
+        sql.push_str(&format!(
+            " AND table_catalog = {}",
+            string_literal(database)
+        ));
+    }
+    sql.push_str(" ORDER BY ordinal_position");
+
+    Ok(adapter
+        .query(&sql)?
+        .rows
+        .into_iter()
+        .filter_map(|row| {
+            let mut values = row.into_iter();
+            Some((values.next()?, values.next()?))
+        })
+        .collect())
+}
+
+/// A column of a relation in one of the queried schemas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaColumn {
+    pub database: String,
+    pub schema: String,
+    pub table: String,
+    pub column: ColumnDef,
+}
+
+/// Every column of every relation in `schemas`, from one `information_schema.columns` query,
+/// in ordinal order within each relation
+pub fn schema_columns(
+    adapter: &dyn Adapter,
+    schemas: &BTreeSet<String>,
+) -> Result<Vec<SchemaColumn>> {
+    if schemas.is_empty() {
+        return Ok(Vec::new());
+    }
+    let schemas: Vec<String> = schemas
+        .iter()
+        .map(|schema| string_literal(schema))
+        .collect();
+    let sql = format!(
+        "SELECT table_catalog, table_schema, table_name, column_name, data_type, is_nullable \
+         FROM information_schema.columns WHERE table_schema IN ({}) \
+         ORDER BY table_catalog, table_schema, table_name, ordinal_position",
+        schemas.join(", ")
+    );
+
+    Ok(adapter
+        .query(&sql)?
+        .rows
+        .into_iter()
+        .filter_map(|row| {
+            let [database, schema, table, name, data_type, nullable] =
+                <[String; 6]>::try_from(row).ok()?;
+            Some(SchemaColumn {
+                database,
+                schema,
+                table,
+                column: ColumnDef {
+                    name,
+                    data_type,
+                    nullable: !nullable.eq_ignore_ascii_case("no"),
+                },
+            })
+        })
+        .collect())
+}
+
+/// `sql` preceded by the adapter's schema context for `[database.]schema`, so its unqualified
+/// table names resolve there whatever the connection defaults to
+pub fn in_schema(adapter: &dyn Adapter, database: Option<&str>, schema: &str, sql: &str) -> String {
+    match adapter.schema_context(database, schema) {
+        Some(context) => format!("{};\n{}", context, sql),
+        None => sql.to_string(),
+    }
+}
+
+/// `sql` run for `model`, in the model's resolved schema when it reads unqualified tables
+pub fn in_model_schema(adapter: &dyn Adapter, model: &SqlModel, sql: String) -> String {
+    if model.unqualified_tables.is_empty() {
+        return sql;
+    }
+    in_schema(
+        adapter,
+        model.database.as_deref(),
+        model.schema.as_deref().unwrap_or(DEFAULT_SCHEMA),
+        &sql,
+    )
+}
+
+/// `sql` run for `model` with its session parameters in effect, reset again afterwards
+pub fn in_model_session(adapter: &dyn Adapter, model: &SqlModel, sql: String) -> Result<String> {
+    if model.session.is_empty() {
+        return Ok(sql);
+    }
+    let session = adapter.session(&model.session)?;
+    let mut batch = session.apply;
+    batch.push(sql);
+    batch.extend(session.reset);
+    Ok(batch.join(";\n"))
+}
+
+/// `value` as a single-quoted DuckDB string literal
+pub fn string_literal(value: &str) -> String {
+    quoting::string_literal(value, SqlDialect::DuckDb)
+}
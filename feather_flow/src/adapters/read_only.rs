@@ -0,0 +1,130 @@
+//! Read-only enforcement for `--read-only`
+//!
+//! Every query is parsed before it reaches the warehouse, and anything other than a SELECT,
+//! EXPLAIN/DESCRIBE of a read, SHOW, or session-scoped USE and SET is refused. SQL that cannot
+//! be parsed is refused as well, since it cannot be shown to be harmless. Adapters additionally
+//! open their connection read-only where the warehouse supports it.
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+use sqlparser::ast::{Query, SetExpr, Statement};
+use sqlparser::dialect::DuckDbDialect;
+use std::collections::BTreeMap;
+
+use super::{Adapter, QueryResult, SessionStatements};
+use crate::sql_engine::statements;
+
+/// Wraps an adapter so it only runs queries that cannot write
+pub struct ReadOnlyAdapter {
+    inner: Box<dyn Adapter>,
+}
+
+impl ReadOnlyAdapter {
+    pub fn new(inner: Box<dyn Adapter>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Adapter for ReadOnlyAdapter {
+    fn query(&self, sql: &str) -> Result<QueryResult> {
+        check_read_only(sql)?;
+        self.inner.query(sql)
+    }
+
+    fn schema_context(&self, database: Option<&str>, schema: &str) -> Option<String> {
+        self.inner.schema_context(database, schema)
+    }
+
+    fn session(&self, parameters: &BTreeMap<String, Value>) -> Result<SessionStatements> {
+        self.inner.session(parameters)
+    }
+}
+
+/// Fail unless every statement in `sql` only reads
+pub fn check_read_only(sql: &str) -> Result<()> {
+    let (parsed, errors) = statements::parse_statements(&DuckDbDialect {}, sql);
+
+    if let Some(error) = errors.first() {
+        bail!(
+            "Refusing to run SQL in read-only mode: {} could not be parsed to verify it only reads",
+            error
+        );
+    }
+
+    for statement in &parsed {
+        if !is_read_only(statement) {
+            let text = statement.to_string();
+            let keyword = text.split_whitespace().next().unwrap_or_default();
+            bail!(
+                "Refusing to run {} statement in read-only mode; only SELECT, EXPLAIN/DESCRIBE of a \
+                 read, SHOW, USE and SET are allowed",
+                keyword.to_uppercase()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn is_read_only(statement: &Statement) -> bool {
+    match statement {
+        Statement::Query(query) => query_is_read_only(query),
+        // EXPLAIN ANALYZE runs the statement it explains
+        Statement::Explain { statement, .. } => is_read_only(statement),
+        Statement::ExplainTable { .. }
+        | Statement::ShowTables { .. }
+        // Only changes where unqualified names resolve, for this session
+        | Statement::Use(_)
+        // A model's session parameters, such as memory_limit, arrive as SET ahead of its
+        // query. Settings change how the session runs, not what is stored, and the DuckDB CLI
+        // session ends with the query.
+        | Statement::SetVariable { .. } => true,
+        _ => false,
+    }
+}
+
+/// Whether `query` and every CTE it defines only read. Data-modifying bodies, in the query or
+/// in a CTE, are parsed as queries too.
+fn query_is_read_only(query: &Query) -> bool {
+    let ctes_read = query.with.as_ref().is_none_or(|with| {
+        with.cte_tables
+            .iter()
+            .all(|cte| query_is_read_only(&cte.query))
+    });
+    ctes_read && !matches!(*query.body, SetExpr::Insert(_) | SetExpr::Update(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_reads() {
+        assert!(check_read_only("SELECT * FROM staging.stg_orders LIMIT 10").is_ok());
+        assert!(check_read_only("WITH o AS (SELECT 1 AS id) SELECT id FROM o").is_ok());
+        assert!(check_read_only("DESCRIBE SELECT 1 AS id").is_ok());
+        assert!(check_read_only("EXPLAIN ANALYZE SELECT 1 AS id").is_ok());
+        assert!(check_read_only("USE staging;\nSELECT * FROM stg_orders").is_ok());
+        assert!(check_read_only("SET threads = 4;\nSELECT 1 AS id").is_ok());
+    }
+
+    #[test]
+    fn test_refuses_writes_and_unparsable_sql() {
+        for sql in [
+            "DROP TABLE marts.orders",
+            "INSERT INTO marts.orders SELECT 1",
+            "SELECT 1; DELETE FROM marts.orders",
+            "CREATE TABLE x AS SELECT 1",
+            "EXPLAIN ANALYZE DELETE FROM marts.orders",
+            "WITH moved AS (INSERT INTO marts.orders SELECT 1) SELECT 1",
+        ] {
+            let err = check_read_only(sql).unwrap_err().to_string();
+            assert!(err.contains("read-only mode"), "{}: {}", sql, err);
+        }
+
+        assert!(check_read_only("SELEC 1")
+            .unwrap_err()
+            .to_string()
+            .contains("could not be parsed"));
+    }
+}
@@ -0,0 +1,173 @@
+//! `ff analyze`: dependencies, column lineage and lint findings for a single SQL text
+//!
+//! Works without a project on disk. `ff analyze -` reads the SQL from stdin, which suits
+//! editor integrations and review bots.
+
+use colored::Colorize;
+use serde::Serialize;
+use sqlparser::dialect::DuckDbDialect;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::sql_engine::lint::{self, LintFinding};
+use crate::sql_engine::template::{
+    self, TargetContext, TemplateContext, DEFAULT_SCHEMA, DEFAULT_TARGET,
+};
+use crate::sql_engine::{extractors, lineage, statements};
+
+type AnalyzeResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug, Serialize)]
+struct Analysis {
+    dependencies: Vec<String>,
+    lineage: Vec<LineageEntry>,
+    findings: Vec<LintFinding>,
+}
+
+#[derive(Debug, Serialize)]
+struct LineageEntry {
+    column: String,
+    transformation: String,
+    sources: Vec<String>,
+}
+
+/// Analyze the SQL in `input`, a file path or `-` for stdin
+pub fn analyze_command(input: &str, format: &str) -> AnalyzeResult<()> {
+    let sql = if input == "-" {
+        let mut sql = String::new();
+        io::stdin().read_to_string(&mut sql)?;
+        sql
+    } else {
+        fs::read_to_string(Path::new(input))
+            .map_err(|err| format!("Failed to read {}: {}", input, err))?
+    };
+
+    let analysis = analyze(&sql)?;
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&analysis)?),
+        _ => print_text(&analysis),
+    }
+
+    Ok(())
+}
+
+fn analyze(sql: &str) -> AnalyzeResult<Analysis> {
+    let sql = if template::has_template_expressions(sql) {
+        let target = TargetContext::new(DEFAULT_TARGET, "duckdb");
+        let context = TemplateContext {
+            this: format!("{}.model", DEFAULT_SCHEMA),
+            target: &target,
+        };
+        template::render(sql, &context)?
+    } else {
+        sql.to_string()
+    };
+
+    let (parsed, errors) = statements::parse_statements(&DuckDbDialect {}, &sql);
+
+    let mut dependencies: Vec<String> = extractors::get_external_table_deps_set(&parsed)
+        .into_iter()
+        .collect();
+    dependencies.sort();
+
+    let mut findings: Vec<LintFinding> = errors
+        .iter()
+        .map(|error| LintFinding {
+            rule: "syntax",
+            message: error.to_string(),
+        })
+        .collect();
+    findings.extend(lint::lint_statements(&parsed));
+
+    // Lineage needs the whole text to parse
+    let lineage = if errors.is_empty() {
+        lineage::extract_column_lineage(&sql)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| LineageEntry {
+                column: entry.target.column,
+                transformation: entry.transformation,
+                sources: entry.sources.iter().map(ToString::to_string).collect(),
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(Analysis {
+        dependencies,
+        lineage,
+        findings,
+    })
+}
+
+fn print_text(analysis: &Analysis) {
+    println!("{}", "Dependencies:".bold());
+    if analysis.dependencies.is_empty() {
+        println!("  (none)");
+    }
+    for dependency in &analysis.dependencies {
+        println!("  {}", dependency);
+    }
+
+    println!("\n{}", "Column lineage:".bold());
+    if analysis.lineage.is_empty() {
+        println!("  (none)");
+    }
+    for entry in &analysis.lineage {
+        println!(
+            "  {} <- {} [{}]",
+            entry.column,
+            if entry.sources.is_empty() {
+                "-".to_string()
+            } else {
+                entry.sources.join(", ")
+            },
+            entry.transformation
+        );
+    }
+
+    println!("\n{}", "Lint findings:".bold());
+    if analysis.findings.is_empty() {
+        println!("  (none)");
+    }
+    for finding in &analysis.findings {
+        println!(
+            "  {} {}",
+            format!("[{}]", finding.rule).yellow(),
+            finding.message
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_reports_dependencies_and_findings() {
+        let analysis = analyze(
+            "WITH recent AS (SELECT * FROM raw.orders)\nSELECT r.id, c.name FROM recent r JOIN customers c ON r.customer_id = c.id",
+        )
+        .unwrap();
+
+        assert_eq!(analysis.dependencies, vec!["raw.orders".to_string()]);
+        let rules: Vec<&str> = analysis.findings.iter().map(|f| f.rule).collect();
+        assert_eq!(rules, vec!["unqualified_table"]);
+        assert!(analysis.findings[0].message.contains("'customers'"));
+        assert_eq!(analysis.lineage.len(), 2);
+    }
+
+    #[test]
+    fn test_analyze_localizes_syntax_errors() {
+        let analysis = analyze("SELECT * FROM raw.a;\nSELECT FROM WHERE;").unwrap();
+
+        assert_eq!(analysis.dependencies, vec!["raw.a".to_string()]);
+        assert!(analysis
+            .findings
+            .iter()
+            .any(|f| f.rule == "syntax" && f.message.starts_with("statement 2 (line 2)")));
+        assert!(analysis.findings.iter().any(|f| f.rule == "select_star"));
+    }
+}
@@ -0,0 +1,137 @@
+//! `ff assert`: one-off SQL assertions for CI scripts
+//!
+//! An assertion is a query returning a single boolean, e.g.
+//! `SELECT count(*) = 0 FROM staging.stg_customers WHERE email IS NULL`. Assertions can be
+//! given on the command line or loaded from a YAML file:
+//!
+//! ```yaml
+//! assertions:
+//!   - name: customers_have_email
+//!     sql: SELECT count(*) = 0 FROM staging.stg_customers WHERE email IS NULL
+//! ```
+//!
+//! Every assertion runs; the command fails if any of them does not hold.
+
+use colored::Colorize;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::adapters::{self, AccessMode};
+use crate::display::{self, status};
+
+type AssertResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Assertion {
+    pub name: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssertionFile {
+    assertions: Vec<Assertion>,
+}
+
+pub fn assert_command(
+    database: &Path,
+    queries: &[String],
+    file: Option<&Path>,
+    mode: AccessMode,
+) -> AssertResult<()> {
+    let mut assertions: Vec<Assertion> = queries
+        .iter()
+        .enumerate()
+        .map(|(index, sql)| Assertion {
+            name: format!("assertion_{}", index + 1),
+            sql: sql.clone(),
+        })
+        .collect();
+    if let Some(file) = file {
+        assertions.extend(load_assertions(file)?);
+    }
+
+    if assertions.is_empty() {
+        return Err("No assertions given. Pass SQL arguments or --file <assertions.yml>.".into());
+    }
+
+    let adapter = adapters::connect(database, mode)?;
+    let mut failed = 0;
+
+    for assertion in &assertions {
+        let outcome = adapter
+            .query(&assertion.sql)
+            .map_err(|err| format!("{:#}", err))
+            .and_then(|result| result.truth());
+
+        if outcome.is_err() {
+            failed += 1;
+        }
+        match (outcome, display::porcelain()) {
+            (Ok(()), true) => display::record(&["PASS", &assertion.name, ""]),
+            (Err(reason), true) => display::record(&["FAIL", &assertion.name, &reason]),
+            (Ok(()), false) => println!("{} {}", "PASS".green(), assertion.name),
+            (Err(reason), false) => println!("{} {}: {}", "FAIL".red(), assertion.name, reason),
+        }
+    }
+
+    status!("\n{} passed, {} failed", assertions.len() - failed, failed);
+
+    if failed > 0 {
+        return Err(format!("{} assertion(s) failed", failed).into());
+    }
+    Ok(())
+}
+
+fn load_assertions(file: &Path) -> AssertResult<Vec<Assertion>> {
+    let content = fs::read_to_string(file)
+        .map_err(|err| format!("Failed to read {}: {}", file.display(), err))?;
+    let parsed: AssertionFile = serde_yaml::from_str(&content)
+        .map_err(|err| format!("Failed to parse {}: {}", file.display(), err))?;
+    Ok(parsed.assertions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::QueryResult;
+    use tempfile::tempdir;
+
+    fn result(rows: &[&str]) -> QueryResult {
+        QueryResult {
+            columns: vec!["ok".to_string()],
+            rows: rows.iter().map(|value| vec![value.to_string()]).collect(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert!(result(&["true"]).truth().is_ok());
+        assert!(result(&["1"]).truth().is_ok());
+        assert_eq!(result(&["false"]).truth().unwrap_err(), "returned false");
+        assert_eq!(result(&[""]).truth().unwrap_err(), "returned NULL");
+        assert!(result(&["42"])
+            .truth()
+            .unwrap_err()
+            .contains("expected a boolean"));
+        assert!(result(&["true", "true"])
+            .truth()
+            .unwrap_err()
+            .contains("got 2 rows"));
+    }
+
+    #[test]
+    fn test_load_assertions() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("assertions.yml");
+        fs::write(
+            &file,
+            "assertions:\n  - name: no_null_emails\n    sql: SELECT count(*) = 0 FROM staging.stg_customers WHERE email IS NULL\n",
+        )
+        .unwrap();
+
+        let assertions = load_assertions(&file).unwrap();
+        assert_eq!(assertions.len(), 1);
+        assert_eq!(assertions[0].name, "no_null_emails");
+    }
+}
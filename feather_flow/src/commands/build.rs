@@ -0,0 +1,966 @@
+//! `ff build`: load seeds, build models and snapshots, and run their tests in one pass
+//!
+//! `ff snapshot` runs the same build limited to the selected snapshots.
+//!
+//! Nodes run in dependency order with each test directly after the model it checks. When a
+//! node fails, or a test of it fails, everything downstream is skipped. Outcomes are written
+//! to `target/run_results.json`, and a Gantt chart of the run to `target/timeline.html`.
+//!
+//! A model's criticality tier can tighten or relax this: before anything runs, selected
+//! models must meet their tier's `require` list; tiers with `enforce_contract` have their
+//! built columns checked against the declared ones; and a failure of the model or its tests
+//! may instead abort the run (`abort`) or only warn (`warn`).
+//!
+//! Nor does anything run while a directory of the project has fewer tested models than its
+//! `require_tests` threshold; the models it lacks tests on are listed instead.
+//!
+//! While it runs, `target/run_graph.html` and `target/run_graph.dot` show the selected nodes
+//! colored by status, refreshed as nodes start and finish (see `run::snapshot`).
+//!
+//! A model that fails leaves a debug bundle under `target/failures/<model>/`, see
+//! `run::failures`.
+//!
+//! Progress is recorded after every node (see `state::build_progress`). With `--resume` the
+//! nodes an interrupted or failed build completed are reused instead of run again, unless
+//! they or anything they depend on changed since. Ctrl-C finishes the current node first.
+//!
+//! A node reading a source with an `await:` signal first waits for it, see `run::awaits`.
+//!
+//! `--fail-stale-sources` and `--skip-stale-subgraphs` first check the freshness of the
+//! sources upstream of the selection (see `run::freshness`). The first fails the build when
+//! any is past its `error_after`; the second reuses each node whose sources have not been
+//! loaded since it last built successfully and whose upstream nodes did not run, so
+//! scheduled builds that find no new data cost nothing (see `state::source_watermarks`).
+
+use chrono::Utc;
+use colored::Colorize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::time::Duration;
+
+use super::project::{load_project, project_root, require_duckdb};
+use crate::adapters::{self, AccessMode, Adapter};
+use crate::config::criticality::{CriticalityConfig, OnFailure};
+use crate::config::freshness::FreshnessStatus;
+use crate::config::materializations::MaterializationRegistry;
+use crate::display::{self, status};
+use crate::run::anomalies;
+use crate::run::awaits;
+use crate::run::contract;
+use crate::run::data_tests::{self, TestOutcome};
+use crate::run::failures::{self, LOG_LINES};
+use crate::run::freshness::{self, FreshnessResults, SourceFreshness, SOURCES_FILE};
+use crate::run::interrupt;
+use crate::run::plan::{BuildPlan, PlanNode};
+use crate::run::results::{RunResult, RunResults, RUN_RESULTS_FILE};
+use crate::run::selection::{self, Selector};
+use crate::run::snapshot::{RunGraphSnapshot, SNAPSHOT_DIR, SNAPSHOT_INTERVAL};
+use crate::run::timeline::{self, TIMELINE_FILE};
+use crate::run::ModelRunStatus;
+use crate::sql_engine::checksum::{ChecksumMode, HashAlgorithm};
+use crate::sql_engine::nodes::{Node, ResourceType, SNAPSHOT_AT_COLUMN, SNAPSHOT_MATERIALIZATION};
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+use crate::sql_engine::template::DEFAULT_SCHEMA;
+use crate::state::build_progress::BuildProgress;
+use crate::state::lock::StateLock;
+use crate::state::run_history::RunHistory;
+use crate::state::source_watermarks::{NodeWatermark, SourceWatermarks};
+use crate::state::state_dir;
+use crate::stats::PhaseTimer;
+use crate::validators::{coverage, policies};
+
+type BuildResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+pub struct BuildArgs<'a> {
+    pub model_path: &'a Path,
+    pub database: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    pub select: Option<&'a str>,
+    /// Name of a selector in `selectors.yml`, used instead of `select`
+    pub selector: Option<&'a str>,
+    pub exclude: Option<&'a str>,
+    /// Reuse the nodes the previous, unfinished build completed
+    pub resume: bool,
+    pub mode: AccessMode,
+    /// Print how long each phase took
+    pub stats: bool,
+    /// Run only the selected nodes of this type, as `ff snapshot` does with snapshots
+    pub only: Option<ResourceType>,
+    /// Fail before running anything when a source upstream of the selection is stale
+    pub fail_stale_sources: bool,
+    /// Skip nodes whose upstream sources were not loaded since they last built successfully
+    pub skip_stale_subgraphs: bool,
+}
+
+pub fn build_command(args: &BuildArgs) -> BuildResult<()> {
+    if args.mode == AccessMode::ReadOnly {
+        return Err("ff build writes to the warehouse and cannot run with --read-only".into());
+    }
+
+    let mut timer = PhaseTimer::new();
+    timer.phase("parse");
+    let project = load_project(args.model_path, args.target, args.vars)?;
+    timer.phase("plan");
+    let selection = project.selection(args.model_path, args.select, args.selector)?;
+    let select = selection.as_deref().map(Selector::parse).transpose()?;
+    let exclude = args.exclude.map(Selector::parse).transpose()?;
+    require_duckdb(&project.config, "ff build")?;
+    let root = project_root(&project.config, args.model_path);
+
+    let plan = BuildPlan::new(&project.models);
+    let mut selected = selection::resolve(&plan, select.as_ref(), exclude.as_ref());
+    selected.retain(|id| {
+        plan.get(id).is_some_and(|node| {
+            node.is_executable() && args.only.is_none_or(|only| node.resource_type() == only)
+        })
+    });
+    if selected.is_empty() {
+        return Err(match args.only {
+            Some(only) => format!(
+                "Nothing to build: the selection matched no {} nodes",
+                only.as_str()
+            ),
+            None => "Nothing to build: the selection matched no seeds, models, snapshots or tests"
+                .to_string(),
+        }
+        .into());
+    }
+    let order = plan.order(&selected)?;
+
+    let violations = tier_violations(&project.config.criticality, &order);
+    if !violations.is_empty() {
+        for violation in &violations {
+            eprintln!("  {}", violation);
+        }
+        return Err(format!(
+            "{} model(s) do not meet the rules of their criticality tier",
+            violations.len()
+        )
+        .into());
+    }
+
+    let uncovered: Vec<_> = coverage::evaluate(&project.config.require_tests, &project.models)?
+        .into_iter()
+        .filter(|coverage| !coverage.passes())
+        .collect();
+    if !uncovered.is_empty() {
+        for coverage in &uncovered {
+            eprintln!("  {}", coverage.message());
+        }
+        return Err(format!(
+            "Test coverage is below require_tests in {} directory(ies)",
+            uncovered.len()
+        )
+        .into());
+    }
+
+    let state_dir = state_dir(&root);
+    let lock_timeout = Duration::from_secs(project.config.state.lock_timeout_secs);
+    let checksum = project.config.state.checksum;
+    let mut progress = BuildProgress::new(
+        args.database,
+        args.target,
+        selection.as_deref(),
+        args.exclude,
+    );
+    let previous = if args.resume {
+        resumable(&state_dir, &progress, &order, checksum, project.hash)?
+    } else {
+        None
+    };
+    interrupt::install();
+
+    timer.phase("execute");
+    let adapter = adapters::connect(args.database, args.mode)?;
+    let checks_sources = args.fail_stale_sources || args.skip_stale_subgraphs;
+    let (loads, mut watermarks) = if checks_sources {
+        (
+            check_sources(
+                adapter.as_ref(),
+                &plan,
+                &order,
+                args.fail_stale_sources,
+                &root,
+            )?,
+            SourceWatermarks::load(&state_dir)?,
+        )
+    } else {
+        (HashMap::new(), SourceWatermarks::default())
+    };
+    let mut history = RunHistory::load(&state_dir)?;
+    let registry = MaterializationRegistry::from_config(&project.config);
+    // Whether each source with an `await:` became ready, by unique id
+    let mut awaited: HashMap<String, Result<(), String>> = HashMap::new();
+    let mut statuses: HashMap<&str, ModelRunStatus> = HashMap::new();
+    // Nodes with a failing test; their dependents are skipped like those of failed nodes
+    let mut failed_tests_of: HashSet<&str> = HashSet::new();
+    // Set once a node whose tier aborts on failure has failed
+    let mut aborted_by: Option<&str> = None;
+    // Nodes run by this invocation; nodes downstream of them cannot be reused
+    let mut ran: HashSet<&str> = HashSet::new();
+    let mut interrupted = false;
+    let mut results = Vec::with_capacity(order.len());
+    let mut snapshot = RunGraphSnapshot::new(&root.join(SNAPSHOT_DIR), SNAPSHOT_INTERVAL);
+    // Status of every selected node as the live run graph shows it
+    let mut live: HashMap<String, ModelRunStatus> = order
+        .iter()
+        .map(|node| (node.unique_id().to_string(), ModelRunStatus::Pending))
+        .collect();
+    // Recent result lines, kept for failure bundles
+    let mut log: VecDeque<String> = VecDeque::with_capacity(LOG_LINES + 1);
+
+    for node in order {
+        let started_at = Utc::now();
+        let governing = governing_model(&project.models, node);
+        let rules = project
+            .config
+            .criticality
+            .rules(governing.and_then(SqlModel::criticality));
+        let blocked = node.depends_on.iter().find(|dep| {
+            failed_tests_of.contains(dep.as_str())
+                || matches!(
+                    statuses.get(dep.as_str()),
+                    Some(ModelRunStatus::Failed | ModelRunStatus::Skipped)
+                )
+        });
+
+        let fingerprint = fingerprint(node, checksum, project.hash);
+        let reusable = previous.as_ref().is_some_and(|previous| {
+            previous.completed.get(node.unique_id()) == Some(&fingerprint)
+                && !node.depends_on.iter().any(|dep| ran.contains(dep.as_str()))
+        });
+        let watermark = source_loads(&plan, node, &loads).map(|sources| NodeWatermark {
+            database: args.database.to_path_buf(),
+            target: args.target.map(str::to_string),
+            fingerprint: fingerprint.clone(),
+            sources,
+            built_at: Utc::now(),
+        });
+        let unchanged = args.skip_stale_subgraphs
+            && watermark
+                .as_ref()
+                .is_some_and(|current| watermarks.unchanged(node.unique_id(), current))
+            && !node.depends_on.iter().any(|dep| ran.contains(dep.as_str()));
+        interrupted |= interrupt::requested();
+
+        let (status, message, rows) = match (aborted_by, blocked) {
+            _ if interrupted => (
+                ModelRunStatus::Skipped,
+                Some("build interrupted".to_string()),
+                None,
+            ),
+            (None, None) if reusable => (
+                ModelRunStatus::Success,
+                Some("completed by the previous build".to_string()),
+                None,
+            ),
+            (None, None) if unchanged => (
+                ModelRunStatus::Success,
+                Some("sources unchanged since its last successful build".to_string()),
+                None,
+            ),
+            (Some(id), _) => (
+                ModelRunStatus::Skipped,
+                Some(format!("run aborted after {} failed", id)),
+                None,
+            ),
+            (None, Some(dep)) => (
+                ModelRunStatus::Skipped,
+                Some(format!("upstream {} did not succeed", dep)),
+                None,
+            ),
+            (None, None) => {
+                live.insert(node.unique_id().to_string(), ModelRunStatus::Running);
+                refresh_graph(&mut snapshot, &project.models, &live, false);
+                let outcome = await_sources(&mut awaited, adapter.as_ref(), &plan, node, &root)
+                    .and_then(|()| {
+                        execute(
+                            adapter.as_ref(),
+                            &project.models,
+                            &registry,
+                            node,
+                            rules.enforce_contract,
+                        )
+                    });
+                match outcome {
+                    Ok(Outcome::Done(message)) => (ModelRunStatus::Success, message, None),
+                    Ok(Outcome::Loaded(rows)) => (ModelRunStatus::Success, None, Some(rows)),
+                    Ok(Outcome::Unsupported(message)) => {
+                        (ModelRunStatus::Skipped, Some(message), None)
+                    }
+                    Err(err) => (ModelRunStatus::Failed, Some(format!("{:#}", err)), None),
+                }
+            }
+        };
+        let status = match (status, rules.on_failure) {
+            (ModelRunStatus::Failed, OnFailure::Warn) => ModelRunStatus::Warned,
+            (ModelRunStatus::Failed, OnFailure::Abort) => {
+                aborted_by = Some(governing.map_or(node.unique_id(), |model| &model.unique_id));
+                ModelRunStatus::Failed
+            }
+            (status, _) => status,
+        };
+
+        if node.resource_type() == ResourceType::Test && status == ModelRunStatus::Failed {
+            failed_tests_of.extend(node.depends_on.iter().map(String::as_str));
+        }
+        statuses.insert(node.unique_id(), status);
+        live.insert(node.unique_id().to_string(), status);
+        refresh_graph(&mut snapshot, &project.models, &live, false);
+        if !interrupted && !reusable && !unchanged {
+            ran.insert(node.unique_id());
+            if let Some(watermark) = watermark.filter(|_| status == ModelRunStatus::Success) {
+                watermarks
+                    .nodes
+                    .insert(node.unique_id().to_string(), watermark);
+            }
+        }
+        if status == ModelRunStatus::Success {
+            progress
+                .completed
+                .insert(node.unique_id().to_string(), fingerprint);
+            save_progress(&state_dir, lock_timeout, &progress);
+        }
+
+        let mut result = RunResult {
+            unique_id: node.unique_id().to_string(),
+            status,
+            thread_id: 0,
+            started_at,
+            completed_at: Utc::now(),
+            message,
+            rows,
+            anomaly: None,
+        };
+        if status == ModelRunStatus::Success && ran.contains(node.unique_id()) {
+            let secs = result.duration_secs();
+            let expected = node
+                .node
+                .as_model()
+                .and_then(|model| model.expected_runtime.as_deref())
+                .and_then(|runtime| anomalies::parse_runtime(runtime).ok());
+            result.anomaly = anomalies::check(node.unique_id(), expected, &history, secs);
+            history.record(node.unique_id(), secs);
+        }
+        print_result(node, &result);
+        log.push_back(log_line(node, &result));
+        if log.len() > LOG_LINES {
+            log.pop_front();
+        }
+        if let Some(model) = node.node.as_model() {
+            record_outcome(
+                &root,
+                adapter.as_ref(),
+                &project.models,
+                model,
+                &result,
+                log.make_contiguous(),
+            );
+        }
+        results.push(result);
+    }
+
+    refresh_graph(&mut snapshot, &project.models, &live, true);
+    timer.end();
+    let run_results = RunResults {
+        generated_at: Utc::now(),
+        results,
+        phases: timer.timings(),
+    };
+    run_results.save(&root.join(RUN_RESULTS_FILE))?;
+    if let Err(err) = timeline::write_timeline(&run_results, &root.join(TIMELINE_FILE)) {
+        eprintln!("{} {:#}", "Warning:".yellow(), err);
+    }
+    if let Err(err) =
+        StateLock::acquire(&state_dir, lock_timeout).and_then(|_lock| history.save(&state_dir))
+    {
+        eprintln!(
+            "{} Failed to record node runtimes: {:#}",
+            "Warning:".yellow(),
+            err
+        );
+    }
+    if checks_sources {
+        if let Err(err) = StateLock::acquire(&state_dir, lock_timeout)
+            .and_then(|_lock| watermarks.save(&state_dir))
+        {
+            eprintln!(
+                "{} Failed to record source watermarks: {:#}",
+                "Warning:".yellow(),
+                err
+            );
+        }
+    }
+
+    let count = |status: ModelRunStatus| statuses.values().filter(|s| **s == status).count();
+    let failed = count(ModelRunStatus::Failed);
+    status!(
+        "\n{} succeeded, {} failed, {} warned, {} skipped",
+        count(ModelRunStatus::Success),
+        failed,
+        count(ModelRunStatus::Warned),
+        count(ModelRunStatus::Skipped)
+    );
+    let slow: Vec<&RunResult> = run_results
+        .results
+        .iter()
+        .filter(|result| result.anomaly.is_some())
+        .collect();
+    if !slow.is_empty() {
+        eprintln!(
+            "{} {} node(s) ran more than {}x slower than expected:",
+            "Warning:".yellow(),
+            slow.len(),
+            anomalies::SLOWDOWN_FACTOR
+        );
+        for result in slow {
+            eprintln!(
+                "  {} {}",
+                result.unique_id,
+                result.anomaly.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    if args.stats {
+        timer.print();
+    }
+
+    if interrupted {
+        return Err("Build interrupted; run it again with --resume to continue".into());
+    }
+    if let Some(id) = aborted_by {
+        return Err(format!("Run aborted after {} failed", id).into());
+    }
+    if failed > 0 {
+        return Err(format!("{} node(s) failed", failed).into());
+    }
+    if let Err(err) = StateLock::acquire(&state_dir, lock_timeout)
+        .and_then(|_lock| BuildProgress::remove(&state_dir))
+    {
+        eprintln!("{} {:#}", "Warning:".yellow(), err);
+    }
+    Ok(())
+}
+
+/// Write the live run graph, at most every `SNAPSHOT_INTERVAL` unless `force`d; a graph that
+/// cannot be written is a warning, not a failed build
+fn refresh_graph(
+    snapshot: &mut RunGraphSnapshot,
+    models: &SqlModelCollection,
+    statuses: &HashMap<String, ModelRunStatus>,
+    force: bool,
+) {
+    let written = if force {
+        snapshot.write(models, statuses)
+    } else {
+        snapshot.maybe_write(models, statuses).map(|_| ())
+    };
+    if let Err(err) = written {
+        eprintln!("{} {:#}", "Warning:".yellow(), err);
+    }
+}
+
+/// The progress of the previous build if there is one to resume, warning about every way the
+/// project or the arguments changed since
+fn resumable(
+    state_dir: &Path,
+    progress: &BuildProgress,
+    order: &[&PlanNode],
+    checksum: ChecksumMode,
+    hash: &dyn HashAlgorithm,
+) -> BuildResult<Option<BuildProgress>> {
+    let Some(previous) = BuildProgress::load(state_dir)? else {
+        status!(
+            "{} No unfinished build to resume; building everything selected",
+            "Warning:".yellow()
+        );
+        return Ok(None);
+    };
+
+    let fingerprints: HashMap<&str, String> = order
+        .iter()
+        .map(|node| (node.unique_id(), fingerprint(node, checksum, hash)))
+        .collect();
+    let changed: Vec<&str> = previous
+        .completed
+        .iter()
+        .filter(|(id, recorded)| {
+            fingerprints
+                .get(id.as_str())
+                .is_some_and(|current| current != *recorded)
+        })
+        .map(|(id, _)| id.as_str())
+        .collect();
+    let changes = previous.argument_changes(progress);
+
+    status!(
+        "Resuming the build started at {}: {} node(s) already completed",
+        previous.started_at.to_rfc3339(),
+        previous.completed.len()
+    );
+    if !changes.is_empty() {
+        eprintln!(
+            "{} The build is resumed with different arguments: {}",
+            "Warning:".yellow(),
+            changes.join("; ")
+        );
+    }
+    if !changed.is_empty() {
+        eprintln!(
+            "{} The project changed since the previous build; these nodes run again, with everything downstream: {}",
+            "Warning:".yellow(),
+            changed.join(", ")
+        );
+    }
+    Ok(Some(previous))
+}
+
+/// Hash of what a node runs, to tell whether one the previous build completed is unchanged.
+/// Models' SQL is compared by the `checksum` mode, and everything is digested by `hash`.
+fn fingerprint(node: &PlanNode, checksum: ChecksumMode, hash: &dyn HashAlgorithm) -> String {
+    match node.node {
+        Node::Model(model) | Node::Snapshot(model) => BuildProgress::fingerprint(
+            &[
+                &model.qualified_relation(),
+                model.materialized.as_deref().unwrap_or("view"),
+                &checksum.checksum(
+                    model.compiled_sql.as_deref().unwrap_or(&model.raw_sql),
+                    &model.dialect,
+                    hash,
+                ),
+                model.yaml_checksum.as_deref().unwrap_or_default(),
+            ],
+            hash,
+        ),
+        Node::Seed(seed) => BuildProgress::fingerprint(
+            &[
+                &seed.relation(),
+                &std::fs::read_to_string(&seed.path).unwrap_or_default(),
+            ],
+            hash,
+        ),
+        Node::Test(test) => BuildProgress::fingerprint(
+            &[
+                &test.unique_id,
+                &serde_json::to_string(&test.args).unwrap_or_default(),
+            ],
+            hash,
+        ),
+        Node::Source(_) | Node::Exposure(_) => {
+            BuildProgress::fingerprint(&[node.unique_id()], hash)
+        }
+    }
+}
+
+/// Check the freshness of every source upstream of `order` that declares a
+/// `loaded_at_field`, saving the results like `ff source freshness`, and return the latest
+/// load of each source where it is known. With `fail_stale`, any source past its
+/// `error_after` or that could not be checked fails the build; otherwise they only warn.
+fn check_sources(
+    adapter: &dyn Adapter,
+    plan: &BuildPlan,
+    order: &[&PlanNode],
+    fail_stale: bool,
+    root: &Path,
+) -> BuildResult<HashMap<String, String>> {
+    let upstream: BTreeSet<String> = order
+        .iter()
+        .flat_map(|node| plan.ancestors(node.unique_id()))
+        .collect();
+    let results: Vec<SourceFreshness> = upstream
+        .iter()
+        .filter_map(|id| match plan.get(id)?.node {
+            Node::Source(source) => freshness::check_source(adapter, source),
+            _ => None,
+        })
+        .collect();
+    FreshnessResults {
+        generated_at: Utc::now(),
+        results: results.clone(),
+    }
+    .save(&root.join(SOURCES_FILE))?;
+
+    let mut stale = 0;
+    for result in &results {
+        let message = result.message.as_deref().unwrap_or_default();
+        match result.status {
+            FreshnessStatus::Pass => continue,
+            FreshnessStatus::Error | FreshnessStatus::RuntimeError if fail_stale => {
+                stale += 1;
+                eprintln!("  {}: {}", result.unique_id, message);
+            }
+            _ => status!(
+                "{} Source {} is {}: {}",
+                "Warning:".yellow(),
+                result.unique_id,
+                result.status.as_str(),
+                message
+            ),
+        }
+    }
+    if stale > 0 {
+        return Err(format!(
+            "{} source(s) upstream of the selection are stale or could not be checked",
+            stale
+        )
+        .into());
+    }
+
+    Ok(results
+        .into_iter()
+        .filter(|result| result.status != FreshnessStatus::RuntimeError)
+        .filter_map(|result| Some((result.unique_id, result.max_loaded_at?)))
+        .collect())
+}
+
+/// Wait for the `await:` signals of the sources `node` reads, each once per build
+fn await_sources(
+    awaited: &mut HashMap<String, Result<(), String>>,
+    adapter: &dyn Adapter,
+    plan: &BuildPlan,
+    node: &PlanNode,
+    root: &Path,
+) -> anyhow::Result<()> {
+    for dep in &node.depends_on {
+        let Some(Node::Source(source)) = plan.get(dep).map(|dep| dep.node) else {
+            continue;
+        };
+        let ready = awaited
+            .entry(source.unique_id.clone())
+            .or_insert_with(|| awaits::wait_for(adapter, root, source));
+        if let Err(reason) = ready {
+            anyhow::bail!("{} is not ready: {}", source.unique_id, reason);
+        }
+    }
+    Ok(())
+}
+
+/// The latest load of every source upstream of `node`, from `loads`; None when it reads no
+/// source or any of their loads is unknown
+fn source_loads(
+    plan: &BuildPlan,
+    node: &PlanNode,
+    loads: &HashMap<String, String>,
+) -> Option<BTreeMap<String, String>> {
+    let sources: Vec<String> = plan
+        .ancestors(node.unique_id())
+        .into_iter()
+        .filter(|id| {
+            plan.get(id)
+                .is_some_and(|node| node.resource_type() == ResourceType::Source)
+        })
+        .collect();
+    if sources.is_empty() {
+        return None;
+    }
+    sources
+        .into_iter()
+        .map(|id| {
+            let load = loads.get(&id)?.clone();
+            Some((id, load))
+        })
+        .collect()
+}
+
+/// Record the nodes completed so far; a failure to is reported but does not fail the build
+fn save_progress(state_dir: &Path, lock_timeout: Duration, progress: &BuildProgress) {
+    if let Err(err) =
+        StateLock::acquire(state_dir, lock_timeout).and_then(|_lock| progress.save(state_dir))
+    {
+        eprintln!(
+            "{} Failed to record build progress: {:#}",
+            "Warning:".yellow(),
+            err
+        );
+    }
+}
+
+/// Write the debug bundle of a model that failed, or remove the stale bundle of one that
+/// succeeded. Problems with bundles are reported but do not fail the build.
+fn record_outcome(
+    root: &Path,
+    adapter: &dyn Adapter,
+    models: &SqlModelCollection,
+    model: &SqlModel,
+    result: &RunResult,
+    log: &[String],
+) {
+    let outcome = match result.status {
+        ModelRunStatus::Failed | ModelRunStatus::Warned => failures::write_bundle(
+            root,
+            adapter,
+            models,
+            model,
+            result.message.as_deref().unwrap_or_default(),
+            log,
+        )
+        .map(|dir| status!("  debug bundle written to {}", dir.display())),
+        ModelRunStatus::Success => failures::clear_bundle(root, model),
+        _ => Ok(()),
+    };
+    if let Err(err) = outcome {
+        eprintln!(
+            "{} Failed to update the debug bundle of {}: {:#}",
+            "Warning:".yellow(),
+            model.unique_id,
+            err
+        );
+    }
+}
+
+/// Selected models and snapshots missing what their tier requires before the build
+fn tier_violations(criticality: &CriticalityConfig, order: &[&PlanNode]) -> Vec<String> {
+    let mut violations = Vec::new();
+    for model in order.iter().filter_map(|node| node.node.as_model()) {
+        let tier = model.criticality();
+        let rules = criticality.rules(tier);
+        let tier = tier.map_or("untiered", |tier| tier.as_str());
+        for requirement in &rules.require {
+            if !policies::meets(model, *requirement) {
+                violations.push(format!(
+                    "Model '{}' ({}) requires {}",
+                    model.name,
+                    tier,
+                    requirement.as_str()
+                ));
+            }
+        }
+        if rules.enforce_contract && model.columns.is_empty() {
+            violations.push(format!(
+                "Model '{}' ({}) enforces a contract but declares no columns",
+                model.name, tier
+            ));
+        }
+    }
+    violations
+}
+
+/// The model whose tier applies to `node`: the model itself, or the one a test checks
+fn governing_model<'a>(
+    models: &'a SqlModelCollection,
+    node: &PlanNode<'a>,
+) -> Option<&'a SqlModel> {
+    match node.node {
+        Node::Test(test) => models.get_model(&test.model_id),
+        other => other.as_model(),
+    }
+}
+
+enum Outcome {
+    /// The node ran, with an optional note
+    Done(Option<String>),
+    /// The node ran and its relation now holds this many rows
+    Loaded(u64),
+    /// The node cannot be run by `ff build`
+    Unsupported(String),
+}
+
+fn execute(
+    adapter: &dyn Adapter,
+    models: &SqlModelCollection,
+    registry: &MaterializationRegistry,
+    node: &PlanNode,
+    enforce_contract: bool,
+) -> anyhow::Result<Outcome> {
+    match node.node {
+        Node::Seed(seed) => {
+            create_schema(adapter, &seed.relation())?;
+            adapter.query(&format!(
+                "CREATE OR REPLACE TABLE {} AS SELECT * FROM read_csv_auto({})",
+                seed.relation(),
+                adapters::string_literal(&seed.path.to_string_lossy())
+            ))?;
+            Ok(Outcome::Loaded(row_count(adapter, &seed.relation())?))
+        }
+        Node::Model(model) | Node::Snapshot(model) => {
+            let outcome = materialize(adapter, registry, model)?;
+            let built = !matches!(outcome, Outcome::Unsupported(_))
+                && model.materialized.as_deref() != Some("ephemeral");
+            if enforce_contract && built {
+                check_contract(adapter, model)?;
+            }
+            Ok(outcome)
+        }
+        Node::Test(test) => match data_tests::run_column_test(adapter, models, test)? {
+            TestOutcome::Passed => Ok(Outcome::Done(None)),
+            TestOutcome::Failed(failures) => anyhow::bail!("{} failing row(s)", failures),
+            TestOutcome::Unsupported(reason) => Ok(Outcome::Unsupported(reason)),
+        },
+        Node::Source(_) | Node::Exposure(_) => Ok(Outcome::Done(None)),
+    }
+}
+
+fn materialize(
+    adapter: &dyn Adapter,
+    registry: &MaterializationRegistry,
+    model: &SqlModel,
+) -> anyhow::Result<Outcome> {
+    let this = model.qualified_relation();
+    let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    let sql = compiled.trim().trim_end_matches(';').trim_end();
+    let materialized = model.materialized.as_deref().unwrap_or("view");
+
+    let exists = || {
+        adapters::relation_exists(
+            adapter,
+            model.database.as_deref(),
+            model.schema.as_deref().unwrap_or(DEFAULT_SCHEMA),
+            model.relation_name(),
+        )
+    };
+
+    let statement = match materialized {
+        "ephemeral" => {
+            return Ok(Outcome::Done(Some(
+                "ephemeral; nothing to build".to_string(),
+            )))
+        }
+        "view" => format!("CREATE OR REPLACE VIEW {} AS {}", this, sql),
+        "table" => format!("CREATE OR REPLACE TABLE {} AS {}", this, sql),
+        "incremental" if exists()? => format!("INSERT INTO {} {}", this, sql),
+        "incremental" => format!("CREATE TABLE {} AS {}", this, sql),
+        SNAPSHOT_MATERIALIZATION => match &model.snapshot {
+            Some(history) => {
+                let errors = history.validate();
+                if !errors.is_empty() {
+                    anyhow::bail!("invalid snapshot config: {}", errors.join("; "));
+                }
+                if exists()? {
+                    history.update_sql(&this, sql)
+                } else {
+                    history.create_sql(&this, sql)
+                }
+            }
+            // Appends the source rows that differ from every captured row
+            None if exists()? => format!(
+                "INSERT INTO {this} SELECT *, now() AS {at} FROM ({sql} EXCEPT SELECT * EXCLUDE ({at}) FROM {this}) AS changed",
+                this = this,
+                sql = sql,
+                at = SNAPSHOT_AT_COLUMN
+            ),
+            None => format!(
+                "CREATE TABLE {} AS SELECT *, now() AS {} FROM ({}) AS snapshot_source",
+                this, SNAPSHOT_AT_COLUMN, sql
+            ),
+        },
+        custom => match registry.get_custom(custom) {
+            Some(recipe) => recipe.render(&this, sql),
+            None => {
+                return Ok(Outcome::Unsupported(format!(
+                    "unknown materialization '{}'",
+                    custom
+                )))
+            }
+        },
+    };
+
+    create_schema(adapter, &this)?;
+    let statement = adapters::in_model_schema(adapter, model, statement);
+    adapter.query(&adapters::in_model_session(adapter, model, statement)?)?;
+    match materialized {
+        // Counting a view would run its query again
+        "table" | "incremental" | SNAPSHOT_MATERIALIZATION => {
+            Ok(Outcome::Loaded(row_count(adapter, &this)?))
+        }
+        _ => Ok(Outcome::Done(None)),
+    }
+}
+
+/// Fails when the built relation's columns differ from the ones `model` declares
+fn check_contract(adapter: &dyn Adapter, model: &SqlModel) -> anyhow::Result<()> {
+    let built = adapters::relation_columns(
+        adapter,
+        model.database.as_deref(),
+        model.schema.as_deref().unwrap_or(DEFAULT_SCHEMA),
+        model.relation_name(),
+    )?;
+    let violations = contract::violations(&model.columns, &built);
+    if !violations.is_empty() {
+        anyhow::bail!("contract violated: {}", violations.join("; "));
+    }
+    Ok(())
+}
+
+/// Rows in `relation`, recorded in the run results for `ff report cost-estimate`
+fn row_count(adapter: &dyn Adapter, relation: &str) -> anyhow::Result<u64> {
+    let result = adapter.query(&format!("SELECT count(*) FROM {}", relation))?;
+    result
+        .rows
+        .first()
+        .and_then(|row| row.first())
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("Could not count the rows of {}", relation))
+}
+
+/// Create the schema of `relation` (`[database.]schema.name`) if it does not exist
+fn create_schema(adapter: &dyn Adapter, relation: &str) -> anyhow::Result<()> {
+    if let Some((schema, _)) = relation.rsplit_once('.') {
+        adapter.query(&format!("CREATE SCHEMA IF NOT EXISTS {}", schema))?;
+    }
+    Ok(())
+}
+
+/// Status shown for a node's result, e.g. PASS for a passing test
+fn label(node: &PlanNode, status: ModelRunStatus) -> &'static str {
+    let is_test = node.resource_type() == ResourceType::Test;
+    match status {
+        ModelRunStatus::Success if is_test => "PASS",
+        ModelRunStatus::Success => "OK",
+        ModelRunStatus::Failed if is_test => "FAIL",
+        ModelRunStatus::Failed => "ERROR",
+        ModelRunStatus::Warned => "WARN",
+        _ => "SKIP",
+    }
+}
+
+/// Uncolored result line recorded in failure bundles
+fn log_line(node: &PlanNode, result: &RunResult) -> String {
+    let mut line = format!(
+        "{} {} {} {} ({:.2}s)",
+        result.started_at.to_rfc3339(),
+        label(node, result.status),
+        node.resource_type().as_str(),
+        node.unique_id(),
+        result.duration_secs()
+    );
+    if let Some(message) = &result.message {
+        line.push_str(&format!(": {}", message));
+    }
+    line
+}
+
+/// One line per node; with `--porcelain` the record is
+/// `status, resource type, unique id, seconds, message`
+fn print_result(node: &PlanNode, result: &RunResult) {
+    let label = label(node, result.status);
+
+    if display::porcelain() {
+        display::record(&[
+            label,
+            node.resource_type().as_str(),
+            node.unique_id(),
+            &format!("{:.2}", result.duration_secs()),
+            result.message.as_deref().unwrap_or_default(),
+        ]);
+        return;
+    }
+
+    let status = match result.status {
+        ModelRunStatus::Success => format!("{:<5}", label).green(),
+        ModelRunStatus::Failed => format!("{:<5}", label).red(),
+        _ => format!("{:<5}", label).yellow(),
+    };
+
+    let mut line = format!(
+        "{} {:<8} {} ({:.2}s)",
+        status,
+        node.resource_type().as_str(),
+        node.unique_id(),
+        result.duration_secs()
+    );
+    if let Some(message) = &result.message {
+        line.push_str(&format!(": {}", message));
+    }
+    println!("{}", line);
+}
@@ -0,0 +1,99 @@
+//! `ff clean`: remove local state and, with `--schemas`, this developer's isolated schemas
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::project::{load_project, project_root};
+use crate::adapters::{self, AccessMode};
+use crate::config::FeatherFlowConfig;
+use crate::display::status;
+use crate::sql_engine::template::DEFAULT_TARGET;
+use crate::state::lock::StateLock;
+use crate::state::parse_cache::PARSE_CACHE_FILE;
+use crate::state::state_dir;
+
+type CleanResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+pub struct CleanArgs<'a> {
+    pub model_path: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    /// Also drop the isolated schemas of the target
+    pub schemas: bool,
+    pub database: Option<&'a Path>,
+    pub mode: AccessMode,
+}
+
+pub fn clean_command(args: &CleanArgs) -> CleanResult<()> {
+    let config = FeatherFlowConfig::discover(args.model_path)?.unwrap_or_default();
+    let state_dir = state_dir(&project_root(&config, args.model_path));
+
+    let cache_dir = state_dir.join("preview_cache");
+    let parse_cache = state_dir.join(PARSE_CACHE_FILE);
+    if cache_dir.exists() || parse_cache.exists() {
+        let _lock = StateLock::acquire(
+            &state_dir,
+            Duration::from_secs(config.state.lock_timeout_secs),
+        )?;
+        if cache_dir.exists() {
+            fs::remove_dir_all(&cache_dir)
+                .map_err(|err| format!("Failed to remove {}: {}", cache_dir.display(), err))?;
+            status!("Removed {}", cache_dir.display());
+        }
+        if parse_cache.exists() {
+            fs::remove_file(&parse_cache)
+                .map_err(|err| format!("Failed to remove {}: {}", parse_cache.display(), err))?;
+            status!("Removed {}", parse_cache.display());
+        }
+    }
+
+    if args.schemas {
+        drop_isolated_schemas(args, &config)?;
+    }
+    Ok(())
+}
+
+fn drop_isolated_schemas(args: &CleanArgs, config: &FeatherFlowConfig) -> CleanResult<()> {
+    let target = config.target(args.target);
+    let target = target.as_deref().unwrap_or(DEFAULT_TARGET);
+    let isolated = match &config.schema_isolation {
+        Some(isolation) => isolation
+            .suffix_for(target, &project_root(config, args.model_path))?
+            .is_some(),
+        None => false,
+    };
+    if !isolated {
+        return Err(format!(
+            "Target '{}' does not use schema isolation; see 'schema_isolation' in featherflow_project.yaml",
+            target
+        )
+        .into());
+    }
+    let database = args
+        .database
+        .ok_or("--schemas needs --db to know where the schemas live")?;
+
+    // Loading the project applies the isolation, so model schemas are the isolated ones
+    let project = load_project(args.model_path, args.target, args.vars)?;
+    let schemas: BTreeSet<String> = project
+        .models
+        .get_execution_order()?
+        .into_iter()
+        .map(|model| {
+            let schema = model.schema.clone().unwrap_or_default();
+            match &model.database {
+                Some(database) => format!("{}.{}", database, schema),
+                None => schema,
+            }
+        })
+        .collect();
+
+    let adapter = adapters::connect(database, args.mode)?;
+    for schema in &schemas {
+        adapter.query(&format!("DROP SCHEMA IF EXISTS {} CASCADE", schema))?;
+        status!("Dropped schema {}", schema);
+    }
+    Ok(())
+}
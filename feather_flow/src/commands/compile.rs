@@ -0,0 +1,242 @@
+//! `ff compile`: print the DDL creating every model, in the project's dialect
+//!
+//! Nothing is executed. For dialects FeatherFlow cannot run (BigQuery), this is how models
+//! get deployed: apply the emitted statements with the warehouse's own tooling. Each model's
+//! compiled SELECT, with templates rendered and references pointing at the target's
+//! relations, is also written to `target/compiled/`, mirroring the model tree.
+//!
+//! With `--format` the files in `target/compiled/` hold SQL rendered from the parsed
+//! statements instead, with the model's header comments kept.
+//!
+//! With `--artifact-store <dir>` each compiled SELECT is also kept in a content-addressed
+//! store shared between compiles, see `state::artifact_store`.
+//!
+//! With `--at <rev>` the project is compiled as it was at a git revision, read from the
+//! repository without checking it out, and that revision's manifest is written alongside
+//! for comparisons with today's (`ff parse -f json`).
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use super::parse::write_manifest;
+use super::project::{load_project, load_project_with_ast, project_root};
+use crate::config::materializations::MaterializationRegistry;
+use crate::config::FeatherFlowConfig;
+use crate::display::{self, status};
+use crate::sql_engine::ddl::{self, Ddl};
+use crate::sql_engine::manifest_writer::ManifestFormat;
+use crate::sql_engine::sql_model::SqlModelCollection;
+use crate::state;
+use crate::state::artifact_store::ArtifactStore;
+use crate::state::revision::{self, Revision};
+use crate::stats::PhaseTimer;
+
+type CompileResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Directory, relative to the project root, holding each model's compiled SQL
+pub const COMPILED_DIR: &str = "target/compiled";
+
+/// File name of a revision's manifest in its state directory
+const MANIFEST_FILE: &str = "manifest.json";
+
+pub fn compile_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    at: Option<&str>,
+    artifact_store: Option<&Path>,
+    format: bool,
+    stats: bool,
+) -> CompileResult<()> {
+    let mut timer = PhaseTimer::new();
+    if at.is_some() {
+        timer.phase("checkout");
+    }
+    let revision = at.map(|rev| at_revision(model_path, rev)).transpose()?;
+    let model_path = revision
+        .as_ref()
+        .map_or(model_path, |(_, path)| path.as_path());
+
+    timer.phase("parse");
+    // Formatting renders the parsed statements, which the parse cache does not keep
+    let project = if format {
+        load_project_with_ast(model_path, target, vars)?
+    } else {
+        load_project(model_path, target, vars)?
+    };
+    let dialect = project.config.dialect;
+
+    let errors = project.models.get_partitioning_report();
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("{}", error);
+        }
+        return Err(
+            "Invalid partition_by/cluster_by configs detected. Fix them in the models' YAML."
+                .into(),
+        );
+    }
+
+    timer.phase("compile");
+    let registry = MaterializationRegistry::from_config(&project.config);
+    let mut emitted = 0;
+    for model in project.models.get_execution_order()? {
+        match ddl::create_statement(model, dialect, &registry) {
+            Ddl::Statement(statement) if display::porcelain() => {
+                display::record(&[&model.unique_id, &statement]);
+                emitted += 1;
+            }
+            Ddl::Statement(statement) => {
+                println!("-- {}\n{};\n", model.unique_id, statement);
+                emitted += 1;
+            }
+            Ddl::Skipped(reason) => status!("Skipping {}: {}", model.unique_id, reason),
+        }
+    }
+
+    status!("Compiled {} statement(s) for {}", emitted, dialect.as_str());
+
+    timer.phase("write");
+    let compiled_dir = match &revision {
+        Some((revision, _)) => revision.state_dir().join("compiled"),
+        None => project.root_dir(model_path).join(COMPILED_DIR),
+    };
+    let written = write_compiled_sql(&project.models, &compiled_dir, format)?;
+    status!(
+        "Wrote compiled SQL of {} model(s) to {}",
+        written,
+        compiled_dir.display()
+    );
+    if let Some(root) = artifact_store {
+        let (stored, new) = store_artifacts(
+            &project.models,
+            &ArtifactStore::new(root, project.hash),
+            &compiled_dir,
+        )?;
+        status!(
+            "Stored {} artifact(s) in {} ({} new)",
+            stored,
+            root.display(),
+            new
+        );
+    }
+
+    if let Some((revision, _)) = &revision {
+        let path = revision.state_dir().join(MANIFEST_FILE);
+        fs::create_dir_all(revision.state_dir())?;
+        write_manifest(
+            &project.models,
+            ManifestFormat::Json,
+            BufWriter::new(File::create(&path)?),
+        )?;
+        status!(
+            "Manifest of {} written to {}",
+            revision.short_id(),
+            path.display()
+        );
+    }
+    timer.end();
+    if stats {
+        timer.print();
+    }
+    Ok(())
+}
+
+/// The project exported at `rev` and where `model_path` is within the export
+fn at_revision(model_path: &Path, rev: &str) -> CompileResult<(Revision, PathBuf)> {
+    let config = FeatherFlowConfig::discover(model_path)?.unwrap_or_default();
+    let root = project_root(&config, model_path);
+    let relative = model_path
+        .canonicalize()?
+        .strip_prefix(root.canonicalize()?)?
+        .to_path_buf();
+
+    let revision = revision::export(&root, rev, &state::state_dir(&root))?;
+    status!("Compiling {} at {}", rev, revision.short_id());
+    let model_path = revision.root.join(relative);
+    Ok((revision, model_path))
+}
+
+/// Replace the contents of `dir` with each model's compiled SQL at its path in the model tree,
+/// or with its formatted SQL for `format`. SQL that did not parse is written as compiled.
+fn write_compiled_sql(
+    models: &SqlModelCollection,
+    dir: &Path,
+    format: bool,
+) -> CompileResult<usize> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)
+            .map_err(|err| format!("Failed to remove {}: {}", dir.display(), err))?;
+    }
+
+    let mut written = 0;
+    for model in models.models() {
+        let path = dir.join(&model.relative_file_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let sql = if format && !model.ast.is_empty() {
+            model.formatted_sql()
+        } else {
+            model
+                .compiled_sql
+                .clone()
+                .unwrap_or_else(|| model.raw_sql.clone())
+        };
+        fs::write(&path, sql)
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Put each model's compiled SQL in `store` and list the digests in `dir`, returning how many
+/// artifacts there are and how many the store did not have yet
+fn store_artifacts(
+    models: &SqlModelCollection,
+    store: &ArtifactStore,
+    dir: &Path,
+) -> CompileResult<(usize, usize)> {
+    let mut index = store.index();
+    let mut new = 0;
+    for model in models.models() {
+        let sql = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+        let (digest, added) = store.put(sql.as_bytes())?;
+        if added {
+            new += 1;
+        }
+        index.artifacts.insert(
+            model.relative_file_path.to_string_lossy().to_string(),
+            digest,
+        );
+    }
+    index.save(dir)?;
+    Ok((index.artifacts.len(), new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_format_survives_a_cached_parse() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("models");
+        fs::create_dir_all(models.join("orders")).unwrap();
+        fs::write(
+            models.join("orders/orders.sql"),
+            "-- One row per order\nselect   id\nfrom raw_orders",
+        )
+        .unwrap();
+
+        for _ in 0..2 {
+            compile_command(&models, None, None, None, None, true, false).unwrap();
+            let compiled =
+                fs::read_to_string(dir.path().join(COMPILED_DIR).join("orders/orders.sql"))
+                    .unwrap();
+            assert_eq!(compiled, "-- One row per order\nSELECT id FROM raw_orders");
+        }
+    }
+}
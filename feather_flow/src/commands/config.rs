@@ -0,0 +1,316 @@
+//! `ff config`: the effective configuration and where each setting comes from
+//!
+//! Settings are layered, later layers winning: built-in defaults, the user-level file
+//! (`FF_USER_CONFIG`, else `~/.config/featherflow/config.yaml`), `featherflow_project.yaml`,
+//! environment variables and command-line flags. `list` and `get` show each setting's value
+//! with the layer it came from; `set` writes a setting to the project or user file, keeping
+//! the file's comments and layout.
+
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::isolation::SCHEMA_SUFFIX_ENV;
+use crate::config::{self, FeatherFlowConfig, PROJECT_CONFIG_FILE, TARGET_ENV};
+use crate::display::{self, status};
+use crate::sql_engine::template::DEFAULT_TARGET;
+use crate::yaml_edit::{Step, YamlDocument};
+
+type ConfigResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// A setting's effective value and the layer it came from
+#[derive(Debug, Clone, PartialEq)]
+struct Setting {
+    value: String,
+    source: String,
+}
+
+pub fn config_list_command(model_path: &Path, target: Option<&str>) -> ConfigResult<()> {
+    let settings = effective_settings(model_path, target, config::user_config_path())?;
+    print_settings(&settings);
+    Ok(())
+}
+
+/// Print the setting `key`, or every setting under it when it names a section
+pub fn config_get_command(model_path: &Path, target: Option<&str>, key: &str) -> ConfigResult<()> {
+    let settings = effective_settings(model_path, target, config::user_config_path())?;
+    if let Some(setting) = settings.get(key) {
+        if display::porcelain() {
+            display::record(&[key, &setting.value, &setting.source]);
+        } else {
+            println!("{}", setting.value);
+            status!("from {}", setting.source);
+        }
+        return Ok(());
+    }
+
+    let prefix = format!("{}.", key);
+    let section: BTreeMap<String, Setting> = settings
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(&prefix))
+        .collect();
+    if section.is_empty() {
+        return Err(format!(
+            "Unknown setting '{}'. 'ff config list' shows every setting.",
+            key
+        )
+        .into());
+    }
+    print_settings(&section);
+    Ok(())
+}
+
+/// Write `key: value` to the project file, or to the user-level file with `user`. The value
+/// is read as YAML, so `30`, `true` and `[dev, ci]` keep their types.
+pub fn config_set_command(
+    model_path: Option<&Path>,
+    key: &str,
+    value: &str,
+    user: bool,
+) -> ConfigResult<()> {
+    let file = if user {
+        config::user_config_path().ok_or("No user config location: set FF_USER_CONFIG or HOME")?
+    } else {
+        let model_path = model_path.ok_or("--model-path is needed to find the project file")?;
+        project_file(model_path)?
+    };
+    set_in_file(&file, key, value)?;
+    status!("Set {} to {} in {}", key, value.trim(), file.display());
+    Ok(())
+}
+
+fn print_settings(settings: &BTreeMap<String, Setting>) {
+    if display::porcelain() {
+        for (key, setting) in settings {
+            display::record(&[key, &setting.value, &setting.source]);
+        }
+        return;
+    }
+    let key_width = settings.keys().map(String::len).max().unwrap_or(0);
+    let value_width = settings
+        .values()
+        .map(|setting| setting.value.len())
+        .max()
+        .unwrap_or(0);
+    for (key, setting) in settings {
+        println!(
+            "{:key_width$}  {:value_width$}  ({})",
+            key, setting.value, setting.source
+        );
+    }
+}
+
+/// The project file governing `model_path`
+fn project_file(model_path: &Path) -> ConfigResult<PathBuf> {
+    let start = model_path
+        .canonicalize()
+        .unwrap_or_else(|_| model_path.to_path_buf());
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| {
+            format!(
+                "No {} found in {} or above it",
+                PROJECT_CONFIG_FILE,
+                model_path.display()
+            )
+            .into()
+        })
+}
+
+/// Every setting by dotted key, e.g. `state.lock_timeout_secs`. Sections are expanded down
+/// to scalars and lists; a list is one setting.
+fn effective_settings(
+    model_path: &Path,
+    target: Option<&str>,
+    user_file: Option<PathBuf>,
+) -> ConfigResult<BTreeMap<String, Setting>> {
+    let user_file = user_file.filter(|file| file.is_file());
+    let project_file = project_file(model_path).ok();
+
+    let effective = match &project_file {
+        Some(path) => FeatherFlowConfig::load_layered(path, user_file.as_deref())?,
+        None => FeatherFlowConfig::default(),
+    };
+    let layer_keys =
+        |path: &Option<PathBuf>, what: &str| -> ConfigResult<BTreeMap<String, String>> {
+            let mut keys = BTreeMap::new();
+            if let Some(path) = path {
+                flatten("", &config::read_layer(path, what)?, &mut keys);
+            }
+            Ok(keys)
+        };
+    let user_keys = layer_keys(&user_file, "user config")?;
+    let project_keys = layer_keys(&project_file, "project config")?;
+
+    let mut values = BTreeMap::new();
+    flatten("", &serde_yaml::to_value(&effective)?, &mut values);
+
+    let mut settings: BTreeMap<String, Setting> = values
+        .into_iter()
+        .map(|(key, value)| {
+            let source = if project_keys.contains_key(&key) {
+                format!("project file {}", project_file.as_ref().unwrap().display())
+            } else if user_keys.contains_key(&key) {
+                format!("user file {}", user_file.as_ref().unwrap().display())
+            } else {
+                "default".to_string()
+            };
+            (key, Setting { value, source })
+        })
+        .collect();
+
+    if effective.schema_isolation.is_some() {
+        if let Ok(suffix) = env::var(SCHEMA_SUFFIX_ENV) {
+            settings.insert(
+                "schema_isolation.suffix".to_string(),
+                Setting {
+                    value: suffix,
+                    source: format!("env {}", SCHEMA_SUFFIX_ENV),
+                },
+            );
+        }
+    }
+    let target = match (target, env::var(TARGET_ENV).ok().filter(|t| !t.is_empty())) {
+        (Some(target), _) => Setting {
+            value: target.to_string(),
+            source: "--target flag".to_string(),
+        },
+        (None, Some(target)) => Setting {
+            value: target,
+            source: format!("env {}", TARGET_ENV),
+        },
+        (None, None) => match settings.get("default_target") {
+            Some(default) if effective.default_target.is_some() => default.clone(),
+            _ => Setting {
+                value: DEFAULT_TARGET.to_string(),
+                source: "default".to_string(),
+            },
+        },
+    };
+    settings.insert("target".to_string(), target);
+    Ok(settings)
+}
+
+/// Collect the leaves of `value` under `prefix`. Empty sections are kept as one setting so
+/// they still show up.
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (key, value) in mapping {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    other => render(other),
+                };
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, out);
+            }
+        }
+        _ => {
+            out.insert(prefix.to_string(), render(value));
+        }
+    }
+}
+
+/// A value on one line, in YAML flow style
+fn render(value: &Value) -> String {
+    match value {
+        Value::Null => "~".to_string(),
+        Value::String(string) => string.clone(),
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+/// Set `key` in the YAML file at `file`, creating the file if needed, and refuse changes that
+/// would leave the configuration invalid
+fn set_in_file(file: &Path, key: &str, value: &str) -> ConfigResult<()> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(format!("Invalid setting '{}'", key).into());
+    }
+    let known = match serde_yaml::to_value(FeatherFlowConfig::default())? {
+        Value::Mapping(mapping) => mapping,
+        _ => unreachable!("the configuration serializes to a mapping"),
+    };
+    if !known.contains_key(parts[0]) {
+        return Err(format!(
+            "Unknown setting '{}'. 'ff config list' shows every setting.",
+            key
+        )
+        .into());
+    }
+    let value: Value =
+        serde_yaml::from_str(value).map_err(|err| format!("Invalid value for {}: {}", key, err))?;
+
+    let content = if file.exists() {
+        fs::read_to_string(file)?
+    } else {
+        String::new()
+    };
+    let mut doc = YamlDocument::parse(&content)?;
+    let (last, path) = parts.split_last().expect("split yields at least one part");
+    let path: Vec<Step> = path.iter().map(|part| Step::Key(part)).collect();
+    doc.set(&path, last, &value)?;
+    let updated = doc.to_string();
+
+    serde_yaml::from_str::<FeatherFlowConfig>(&updated)
+        .map_err(|err| format!("{} would make {} invalid: {}", key, file.display(), err))?;
+    if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, updated)
+        .map_err(|err| format!("Failed to write {}: {}", file.display(), err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_settings_show_their_layer_and_set_keeps_comments() {
+        let dir = tempdir().unwrap();
+        let user_file = dir.path().join("user/config.yaml");
+        fs::create_dir_all(dir.path().join("models")).unwrap();
+        let project = dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&project, "# Analytics project\nname: analytics\n").unwrap();
+
+        set_in_file(&user_file, "state.lock_timeout_secs", "5").unwrap();
+        set_in_file(&user_file, "default_target", "dev").unwrap();
+        set_in_file(&project, "dialect", "bigquery").unwrap();
+        assert!(set_in_file(&project, "dialect", "oracle").is_err());
+        assert!(set_in_file(&project, "no_such_setting", "1").is_err());
+        assert_eq!(
+            fs::read_to_string(&project).unwrap(),
+            "# Analytics project\nname: analytics\ndialect: bigquery\n"
+        );
+
+        let settings =
+            effective_settings(&dir.path().join("models"), Some("prod"), Some(user_file)).unwrap();
+        let setting = |key: &str| settings.get(key).unwrap();
+        assert_eq!(setting("dialect").value, "bigquery");
+        assert!(setting("dialect").source.starts_with("project file"));
+        assert_eq!(setting("state.lock_timeout_secs").value, "5");
+        assert!(setting("state.lock_timeout_secs")
+            .source
+            .starts_with("user file"));
+        assert_eq!(setting("criticality.p2.on_failure").source, "default");
+        assert_eq!(
+            setting("target"),
+            &Setting {
+                value: "prod".to_string(),
+                source: "--target flag".to_string(),
+            }
+        );
+        assert_eq!(setting("default_target").value, "dev");
+        assert!(setting("default_target").source.starts_with("user file"));
+    }
+}
@@ -0,0 +1,49 @@
+//! `ff debug`: show which project, profile and target a command would run as and check that
+//! the target's connection works
+
+use colored::Colorize;
+use std::path::Path;
+
+use super::project::project_root;
+use crate::adapters::{self, AccessMode};
+use crate::config::profiles;
+use crate::config::{FeatherFlowConfig, PROJECT_CONFIG_FILE};
+
+type DebugResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+pub fn debug_command(model_path: &Path, target: Option<&str>) -> DebugResult<()> {
+    println!("ff version: {}", env!("CARGO_PKG_VERSION"));
+    let config = FeatherFlowConfig::discover(model_path)?;
+    let root = match &config {
+        Some(config) => {
+            let root = project_root(config, model_path);
+            println!("Project: {}", root.join(PROJECT_CONFIG_FILE).display());
+            root
+        }
+        None => {
+            println!("Project: no {} found", PROJECT_CONFIG_FILE);
+            project_root(&FeatherFlowConfig::default(), model_path)
+        }
+    };
+
+    let active = profiles::active(&config.unwrap_or_default(), &root, target)
+        .map_err(|err| format!("{:#}", err))?;
+    println!("Profiles: {}", active.file.display());
+    println!("Profile: {}", active.profile);
+    println!("Target: {}", active.target);
+    println!("Connection type: {}", active.connection.adapter_type());
+    println!("Database: {}", active.connection.database().display());
+
+    let checked = adapters::connect(active.connection.database(), AccessMode::ReadOnly)
+        .and_then(|adapter| adapter.query("SELECT 1"));
+    match checked {
+        Ok(_) => {
+            println!("Connection test: {}", "OK".green());
+            Ok(())
+        }
+        Err(err) => {
+            println!("Connection test: {}", "FAILED".red());
+            Err(format!("Could not connect to target '{}': {:#}", active.target, err).into())
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! `ff verify-determinism`: compile the project several times and fail if any artifact differs
+//!
+//! State comparison, deferral and `ff compile --at` rely on the same files always producing
+//! byte-identical output. Every pass loads the project from scratch on a thread of its own,
+//! so each pass's hash maps are seeded differently; iteration order leaking into an artifact
+//! shows up as a difference. The artifacts are the JSON and YAML manifests, the DDL of
+//! `ff compile` and each model's compiled SQL.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::thread;
+
+use super::parse::write_manifest;
+use super::project::load_project_with_ast;
+use crate::config::materializations::MaterializationRegistry;
+use crate::display::{self, status};
+use crate::sql_engine::ddl::{self, Ddl};
+use crate::sql_engine::manifest_writer::ManifestFormat;
+
+type DeterminismResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Artifact name to content, e.g. `manifest.json` or `compiled/staging/stg_a/stg_a.sql`
+type Artifacts = BTreeMap<String, String>;
+
+pub fn verify_determinism_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    runs: usize,
+) -> DeterminismResult<()> {
+    if runs < 2 {
+        return Err("--runs must be at least 2 to have something to compare".into());
+    }
+
+    // Passes run concurrently; errors become strings to cross the thread boundary
+    let passes: Vec<Result<Artifacts, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..runs)
+            .map(|_| {
+                scope.spawn(|| artifacts(model_path, target, vars).map_err(|err| err.to_string()))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("compilation pass panicked".to_string()))
+            })
+            .collect()
+    });
+    let passes = passes.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+    let first = &passes[0];
+    let mut differing = 0;
+    for (run, pass) in passes.iter().enumerate().skip(1) {
+        for difference in compare(first, pass) {
+            differing += 1;
+            report(run + 1, &difference);
+        }
+    }
+
+    status!(
+        "Compared {} artifact(s) across {} runs: {} difference(s)",
+        first.len(),
+        runs,
+        differing
+    );
+    if differing > 0 {
+        return Err(format!(
+            "Compilation is not deterministic: {} artifact difference(s)",
+            differing
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Everything one compilation of the project produces
+fn artifacts(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+) -> DeterminismResult<Artifacts> {
+    let project = load_project_with_ast(model_path, target, vars)?;
+    let mut artifacts = Artifacts::new();
+
+    for (name, format) in [
+        ("manifest.json", ManifestFormat::Json),
+        ("manifest.yaml", ManifestFormat::Yaml),
+    ] {
+        let mut out = Vec::new();
+        write_manifest(&project.models, format, &mut out)?;
+        artifacts.insert(name.to_string(), String::from_utf8_lossy(&out).into_owned());
+    }
+
+    let registry = MaterializationRegistry::from_config(&project.config);
+    let mut statements = String::new();
+    for model in project.models.get_execution_order()? {
+        if let Ddl::Statement(statement) =
+            ddl::create_statement(model, project.config.dialect, &registry)
+        {
+            statements.push_str(&format!("-- {}\n{};\n\n", model.unique_id, statement));
+        }
+        artifacts.insert(
+            format!("compiled/{}", model.relative_file_path.display()),
+            model
+                .compiled_sql
+                .clone()
+                .unwrap_or_else(|| model.raw_sql.clone()),
+        );
+    }
+    artifacts.insert("ddl.sql".to_string(), statements);
+
+    Ok(artifacts)
+}
+
+/// An artifact that differs from the first run's, at its first differing line
+#[derive(Debug, PartialEq)]
+struct Difference {
+    artifact: String,
+    /// 1-based; past the end of the shorter content when one is a prefix of the other
+    line: usize,
+    expected: Option<String>,
+    actual: Option<String>,
+}
+
+fn compare(first: &Artifacts, other: &Artifacts) -> Vec<Difference> {
+    let names: BTreeSet<&String> = first.keys().chain(other.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (expected, actual) = (first.get(name), other.get(name));
+            if expected == actual {
+                return None;
+            }
+            let expected_lines: Vec<&str> = expected.map_or(Vec::new(), |c| c.lines().collect());
+            let actual_lines: Vec<&str> = actual.map_or(Vec::new(), |c| c.lines().collect());
+            let index = expected_lines
+                .iter()
+                .zip(&actual_lines)
+                .position(|(a, b)| a != b)
+                .unwrap_or(expected_lines.len().min(actual_lines.len()));
+            Some(Difference {
+                artifact: name.clone(),
+                line: index + 1,
+                expected: expected_lines.get(index).map(|line| line.to_string()),
+                actual: actual_lines.get(index).map(|line| line.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// One difference; with `--porcelain` the record is
+/// `run, artifact, line, first run's line, this run's line`
+fn report(run: usize, difference: &Difference) {
+    let expected = difference.expected.as_deref().unwrap_or("<missing>");
+    let actual = difference.actual.as_deref().unwrap_or("<missing>");
+    if display::porcelain() {
+        display::record(&[
+            &run.to_string(),
+            &difference.artifact,
+            &difference.line.to_string(),
+            expected,
+            actual,
+        ]);
+        return;
+    }
+    println!(
+        "{} differs in run {} at line {}\n  run 1: {}\n  run {}: {}",
+        difference.artifact, run, difference.line, expected, run, actual
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_reports_first_differing_line() {
+        let first: Artifacts = [
+            ("a.sql".to_string(), "SELECT 1\nFROM x\n".to_string()),
+            ("b.sql".to_string(), "same".to_string()),
+            ("c.sql".to_string(), "only first".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let other: Artifacts = [
+            ("a.sql".to_string(), "SELECT 1\nFROM y\n".to_string()),
+            ("b.sql".to_string(), "same".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            compare(&first, &other),
+            vec![
+                Difference {
+                    artifact: "a.sql".to_string(),
+                    line: 2,
+                    expected: Some("FROM x".to_string()),
+                    actual: Some("FROM y".to_string()),
+                },
+                Difference {
+                    artifact: "c.sql".to_string(),
+                    line: 1,
+                    expected: Some("only first".to_string()),
+                    actual: None,
+                },
+            ]
+        );
+    }
+}
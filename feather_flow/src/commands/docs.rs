@@ -0,0 +1,274 @@
+//! `ff docs`: documentation generated from the project
+//!
+//! `ff docs erd --dir models/marts/finance` renders the models of one directory as a Mermaid
+//! ER diagram: each model is an entity with its YAML columns as attributes, and dependencies
+//! between models are relationships, labelled with the `_id` columns the two models share.
+//! The diagram is written into a marked section of the directory's `README.md`, leaving
+//! anything written around it untouched.
+//!
+//! `ff docs generate` writes a static HTML site of the whole project to `target/docs`; see
+//! `docs_site` for what it holds.
+
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::docs_site::DocsSite;
+use super::project::{load_project, project_root};
+use crate::display::status;
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+
+type DocsResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// File the diagram is written to when no output is given
+pub const DEFAULT_ERD_FILE: &str = "README.md";
+
+/// Directory the site is written to when no output is given, relative to the project root
+pub const DEFAULT_DOCS_DIR: &str = "target/docs";
+
+const ERD_START_MARKER: &str = "<!-- ff:erd:start -->";
+const ERD_END_MARKER: &str = "<!-- ff:erd:end -->";
+
+pub fn docs_erd_command(model_path: &Path, dir: &Path, output: Option<&Path>) -> DocsResult<()> {
+    let dir = dir
+        .canonicalize()
+        .map_err(|err| format!("Directory {} not found: {}", dir.display(), err))?;
+
+    let project = load_project(model_path, None, None)?;
+    let models = models_in_directory(&project.models, &dir);
+    if models.is_empty() {
+        return Err(format!("No models found under {}", dir.display()).into());
+    }
+
+    let diagram = render_erd(&project.models, &models);
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| dir.join(DEFAULT_ERD_FILE));
+    write_erd_section(&output, &dir, &diagram)?;
+
+    status!(
+        "{} ER diagram of {} model(s) written to {}",
+        "OK:".green(),
+        models.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+pub fn docs_generate_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    output: Option<&Path>,
+) -> DocsResult<()> {
+    let project = load_project(model_path, target, vars)?;
+    let output: PathBuf = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| project_root(&project.config, model_path).join(DEFAULT_DOCS_DIR));
+
+    let name = project.config.name.clone().unwrap_or_else(|| {
+        directory_title(
+            &model_path
+                .canonicalize()
+                .unwrap_or(model_path.to_path_buf()),
+        )
+    });
+    let site = DocsSite::new(&name, &project.models);
+
+    fs::create_dir_all(&output)
+        .map_err(|err| format!("Failed to create {}: {}", output.display(), err))?;
+    let index = output.join("index.html");
+    fs::write(&index, site.render()?)
+        .map_err(|err| format!("Failed to write {}: {}", index.display(), err))?;
+
+    status!(
+        "{} Documentation of {} model(s) written to {}",
+        "OK:".green(),
+        project.models.models().count(),
+        index.display()
+    );
+    Ok(())
+}
+
+fn models_in_directory<'a>(collection: &'a SqlModelCollection, dir: &Path) -> Vec<&'a SqlModel> {
+    let mut models: Vec<&SqlModel> = collection
+        .get_execution_order()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|model| {
+            model
+                .fully_qualified_file_path
+                .canonicalize()
+                .is_ok_and(|path| path.starts_with(dir))
+        })
+        .collect();
+
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    models
+}
+
+/// Mermaid `erDiagram` for `models`; upstream models outside the set are drawn without
+/// attributes so every relationship has both ends
+fn render_erd(collection: &SqlModelCollection, models: &[&SqlModel]) -> String {
+    let included: BTreeSet<&str> = models
+        .iter()
+        .map(|model| model.unique_id.as_str())
+        .collect();
+    let mut lines = vec!["erDiagram".to_string()];
+    let mut relationships = Vec::new();
+    let mut outside = BTreeSet::new();
+
+    for model in models {
+        let mut columns: Vec<_> = model.columns.values().collect();
+        columns.sort_by(|a, b| a.name.cmp(&b.name));
+
+        lines.push(format!("    {} {{", entity_name(&model.name)));
+        for column in columns {
+            lines.push(format!(
+                "        {} {}",
+                attribute_type(column.data_type.as_deref()),
+                entity_name(&column.name)
+            ));
+        }
+        lines.push("    }".to_string());
+
+        let mut upstream: Vec<&String> = model.upstream_models.iter().collect();
+        upstream.sort();
+        for parent_id in upstream {
+            let Some(parent) = collection.get_model(parent_id) else {
+                continue;
+            };
+            if !included.contains(parent_id.as_str()) {
+                outside.insert(parent.name.clone());
+            }
+            relationships.push(format!(
+                "    {} ||--o{{ {} : \"{}\"",
+                entity_name(&parent.name),
+                entity_name(&model.name),
+                relationship_label(parent, model)
+            ));
+        }
+    }
+
+    for name in outside {
+        lines.push(format!("    {} {{", entity_name(&name)));
+        lines.push("    }".to_string());
+    }
+
+    lines.extend(relationships);
+    lines.join("\n") + "\n"
+}
+
+/// Shared `_id` columns suggest the join key; otherwise a plain dependency
+fn relationship_label(parent: &SqlModel, child: &SqlModel) -> String {
+    let mut keys: Vec<&str> = child
+        .columns
+        .keys()
+        .filter(|name| name.ends_with("_id") && parent.columns.contains_key(*name))
+        .map(String::as_str)
+        .collect();
+    keys.sort();
+
+    if keys.is_empty() {
+        "feeds".to_string()
+    } else {
+        keys.join(", ")
+    }
+}
+
+/// Mermaid identifiers allow letters, digits, `_` and `-`
+fn entity_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn attribute_type(data_type: Option<&str>) -> String {
+    let data_type = data_type.unwrap_or("unknown");
+    data_type
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '(' | ')' | '[' | ']') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Replace the marked ERD section of `output`, appending one if the file has none
+fn write_erd_section(output: &Path, dir: &Path, diagram: &str) -> DocsResult<()> {
+    let section = format!(
+        "{}\n```mermaid\n{}```\n{}",
+        ERD_START_MARKER, diagram, ERD_END_MARKER
+    );
+
+    let content = match fs::read_to_string(output) {
+        Ok(existing) => replace_section(&existing, &section),
+        Err(_) => format!("# {}\n\n{}\n", directory_title(dir), section),
+    };
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(output, content)?;
+    Ok(())
+}
+
+fn replace_section(existing: &str, section: &str) -> String {
+    let start = existing.find(ERD_START_MARKER);
+    let end = existing
+        .find(ERD_END_MARKER)
+        .map(|end| end + ERD_END_MARKER.len());
+
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => {
+            format!("{}{}{}", &existing[..start], section, &existing[end..])
+        }
+        _ => format!("{}\n\n{}\n", existing.trim_end(), section),
+    }
+}
+
+fn directory_title(dir: &Path) -> String {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| dir.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_section_keeps_surrounding_text() {
+        let existing = format!(
+            "# Finance\n\nHand-written intro.\n\n{}\nold\n{}\n\nFooter\n",
+            ERD_START_MARKER, ERD_END_MARKER
+        );
+        let section = format!("{}\nnew\n{}", ERD_START_MARKER, ERD_END_MARKER);
+
+        let updated = replace_section(&existing, &section);
+        assert!(updated.contains("Hand-written intro."));
+        assert!(updated.contains("\nnew\n"));
+        assert!(!updated.contains("old"));
+        assert!(updated.ends_with("Footer\n"));
+
+        let appended = replace_section("# Finance\n", &section);
+        assert_eq!(appended, format!("# Finance\n\n{}\n", section));
+    }
+
+    #[test]
+    fn test_mermaid_identifiers_are_sanitized() {
+        assert_eq!(entity_name("daily trends.v2"), "daily_trends_v2");
+        assert_eq!(attribute_type(Some("decimal(10,2)")), "decimal(10_2)");
+        assert_eq!(attribute_type(None), "unknown");
+    }
+}
@@ -0,0 +1,238 @@
+//! Static HTML documentation site for `ff docs generate`
+//!
+//! The site is a single `index.html` with no external assets: the parsed project is embedded
+//! as JSON, and the page's script renders the searchable model index, each model's page
+//! (description, columns, tags, dependencies, raw and compiled SQL) and the DAG view from it.
+//! Each column shows why it can be NULL, when its model's SQL lets NULLs into it.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::sql_engine::lineage;
+use crate::sql_engine::nullability::NullReason;
+use crate::sql_engine::projection::Projector;
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+
+/// Page the data is rendered into; `__FF_DOCS_TITLE__` and `__FF_DOCS_DATA__` are replaced
+const TEMPLATE: &str = include_str!("docs_site.html");
+
+/// Everything the site shows, serialized into the page
+#[derive(Debug, Serialize)]
+pub struct DocsSite {
+    pub project: String,
+    pub generated_at: String,
+    pub nodes: Vec<DocsNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocsNode {
+    pub unique_id: String,
+    pub name: String,
+    /// `model`, or `source` for tables no model builds
+    pub resource_type: &'static str,
+    pub path: Option<String>,
+    pub description: Option<String>,
+    pub materialized: Option<String>,
+    pub relation: Option<String>,
+    pub tags: Vec<String>,
+    pub depth: Option<usize>,
+    pub columns: Vec<DocsColumn>,
+    pub depends_on: Vec<String>,
+    pub referenced_by: Vec<String>,
+    pub raw_sql: Option<String>,
+    pub compiled_sql: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocsColumn {
+    pub name: String,
+    pub description: Option<String>,
+    pub data_type: Option<String>,
+    pub tests: Vec<String>,
+    /// Upstream columns the column is computed from, as `table.column`
+    pub derived_from: Vec<String>,
+    /// Why the column can be NULL, when its model's SQL lets NULLs into it
+    pub nullable: Option<String>,
+}
+
+impl DocsSite {
+    pub fn new(project: &str, collection: &SqlModelCollection) -> Self {
+        let models = collection.models_by_depth();
+        let mut projector = Projector::new(collection);
+        let mut sources: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut nodes = Vec::new();
+
+        for model in &models {
+            let mut depends_on: Vec<String> = model.upstream_models.iter().cloned().collect();
+            for table in &model.external_sources {
+                let id = source_id(table);
+                sources
+                    .entry(id.clone())
+                    .or_default()
+                    .insert(model.unique_id.clone());
+                depends_on.push(id);
+            }
+            depends_on.sort();
+            let mut referenced_by: Vec<String> = model.downstream_models.iter().cloned().collect();
+            referenced_by.sort();
+
+            nodes.push(DocsNode {
+                unique_id: model.unique_id.clone(),
+                name: model.name.clone(),
+                resource_type: "model",
+                path: Some(model.relative_file_path.display().to_string()),
+                description: model.rendered_description(),
+                materialized: model.materialized.clone(),
+                relation: Some(model.qualified_relation()),
+                tags: model.tags.clone(),
+                depth: model.depth,
+                columns: columns(
+                    model,
+                    &projector
+                        .model(&model.unique_id)
+                        .map(|projection| projection.nullable)
+                        .unwrap_or_default(),
+                ),
+                depends_on,
+                referenced_by,
+                raw_sql: Some(model.raw_sql.clone()),
+                compiled_sql: model.compiled_sql.clone(),
+            });
+        }
+
+        for (id, readers) in sources {
+            nodes.push(DocsNode {
+                name: id.trim_start_matches("source.").to_string(),
+                unique_id: id,
+                resource_type: "source",
+                path: None,
+                description: None,
+                materialized: None,
+                relation: None,
+                tags: Vec::new(),
+                depth: None,
+                columns: Vec::new(),
+                depends_on: Vec::new(),
+                referenced_by: readers.into_iter().collect(),
+                raw_sql: None,
+                compiled_sql: None,
+            });
+        }
+
+        Self {
+            project: project.to_string(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            nodes,
+        }
+    }
+
+    /// The self-contained page
+    pub fn render(&self) -> serde_json::Result<String> {
+        Ok(TEMPLATE
+            .replace("__FF_DOCS_TITLE__", &escape_html(&self.project))
+            .replace("__FF_DOCS_DATA__", &script_json(self)?))
+    }
+}
+
+fn source_id(table: &str) -> String {
+    format!("source.{}", table)
+}
+
+/// YAML columns by name, followed by columns only the SQL defines; what each is derived
+/// from comes from the lineage of the compiled SQL, and why it can be NULL from `nullable`
+fn columns(model: &SqlModel, nullable: &BTreeMap<String, NullReason>) -> Vec<DocsColumn> {
+    let null_reason = |name: &str| nullable.get(&name.to_lowercase()).map(ToString::to_string);
+    let sql = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    let mut derived: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in lineage::extract_column_lineage(sql).unwrap_or_default() {
+        let sources = entry
+            .sources
+            .iter()
+            .map(ToString::to_string)
+            .collect::<BTreeSet<_>>();
+        derived
+            .entry(entry.target.column.to_lowercase())
+            .or_default()
+            .extend(sources);
+    }
+
+    let mut names: Vec<&String> = model.columns.keys().collect();
+    names.sort();
+    let mut columns: Vec<DocsColumn> = names
+        .into_iter()
+        .map(|name| {
+            let column = &model.columns[name];
+            DocsColumn {
+                name: column.name.clone(),
+                description: column.description.clone(),
+                data_type: column.data_type.clone(),
+                tests: column.tests.clone(),
+                derived_from: derived.remove(&name.to_lowercase()).unwrap_or_default(),
+                nullable: null_reason(name),
+            }
+        })
+        .collect();
+
+    columns.extend(derived.into_iter().map(|(name, derived_from)| DocsColumn {
+        nullable: null_reason(&name),
+        name,
+        description: None,
+        data_type: None,
+        tests: Vec::new(),
+        derived_from,
+    }));
+    columns
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// JSON safe to embed in a `<script>` element: `</script>` or `<!--` in a description or SQL
+/// must not end the element early
+fn script_json<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    Ok(serde_json::to_string(value)?
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_embeds_escaped_data() {
+        let site = DocsSite {
+            project: "<shop>".to_string(),
+            generated_at: "2024-01-01T00:00:00Z".to_string(),
+            nodes: vec![DocsNode {
+                unique_id: "model.orders".to_string(),
+                name: "orders".to_string(),
+                resource_type: "model",
+                path: Some("orders.sql".to_string()),
+                description: Some("Ends here </script><script>alert(1)</script>".to_string()),
+                materialized: Some("table".to_string()),
+                relation: Some("main.orders".to_string()),
+                tags: vec!["core".to_string()],
+                depth: Some(0),
+                columns: Vec::new(),
+                depends_on: vec!["source.raw_orders".to_string()],
+                referenced_by: Vec::new(),
+                raw_sql: Some("SELECT 1 WHERE 1 < 2".to_string()),
+                compiled_sql: None,
+            }],
+        };
+
+        let html = site.render().unwrap();
+        assert!(html.contains("<title>&lt;shop&gt; · FeatherFlow docs</title>"));
+        assert!(html.contains("\"unique_id\":\"model.orders\""));
+        assert!(html.contains("Ends here \\u003c/script\\u003e"));
+        assert_eq!(html.matches("</script>").count(), 2);
+        assert!(!html.contains("__FF_DOCS_DATA__"));
+    }
+}
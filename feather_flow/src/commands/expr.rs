@@ -0,0 +1,32 @@
+//! `ff expr`: evaluate feather_lang, the language behind `{% if %}` conditions in model SQL
+
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+use crate::config;
+use crate::feather_lang::{self, object::Environment, repl};
+
+type ExprResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// File name of the REPL history, kept next to the user-level configuration
+const HISTORY_FILE: &str = "repl_history";
+
+/// Print the value of `expression`, or start the REPL when there is none: with line editing
+/// and history on a terminal, reading lines as they come otherwise
+pub fn expr_command(expression: Option<&str>) -> ExprResult<()> {
+    match expression {
+        Some(source) => {
+            let value = feather_lang::eval(source, &Environment::new())?;
+            println!("{}", value);
+        }
+        None if io::stdin().is_terminal() => {
+            repl::start_interactive(Environment::new(), history_path().as_deref())?
+        }
+        None => repl::start(io::stdin().lock(), &mut io::stdout(), Environment::new())?,
+    }
+    Ok(())
+}
+
+fn history_path() -> Option<PathBuf> {
+    Some(config::user_config_path()?.parent()?.join(HISTORY_FILE))
+}
@@ -0,0 +1,106 @@
+//! `ff help`: help for a command, or for a topic that spans commands such as templating
+
+use clap::Command;
+use std::fmt::Write;
+
+use crate::feather_lang::stdlib::FUNCTIONS;
+
+type HelpResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Topics `ff help` explains besides the commands
+const TOPICS: &[&str] = &["templating"];
+
+const TEMPLATING: &str = "\
+Model SQL is rendered before it is parsed. `{{ expression }}` inserts a value and
+`{% if condition %} ... {% elif condition %} ... {% else %} ... {% endif %}` keeps or drops
+the SQL between the tags. Render errors name the line they are on.
+
+Values:
+  this                             The model's own relation ([database.]schema.relation)
+  target.name, target.schema, target.type
+                                   The active target
+  var('name')                      A project variable declared under `vars:`
+  env_var('NAME'[, 'default'])     An environment variable; unset without a default is an error
+  ref('model')                     The relation of a model or seed, recorded as a dependency
+  source('source', 'table')        A table declared under `sources:`
+  literal(value)                   A value as a SQL literal, escaped for the target's dialect
+  identifier(value)                A value as a quoted identifier, escaped for the target's dialect
+
+`var()` and `env_var()` insert their values as is, so wrap a value that can hold any text:
+`WHERE customer = {{ literal(var('customer')) }}`. `ff validate` warns about environment
+variables and string variables without `allowed_values` that are not wrapped (FF050).
+
+Expressions and conditions are feather_lang (try them with `ff expr`): strings, integers,
+booleans, `+ - * / ! == != < > <= >= && ||`, and the functions below. Integer variables are
+integers, the others strings; dates are 'YYYY-MM-DD' strings.
+
+A model's YAML `description:` is a template too, over `meta` (the model's meta values) and
+`name`, e.g. \"Daily trends for {{ meta.domain }} team\". It is rendered in the manifest,
+`ff parse` output and `ff docs generate`.
+";
+
+const TEMPLATING_EXAMPLES: &str = "
+Examples:
+  SELECT * FROM {{ format('events_{}', lower(var('region'))) }}
+  WHERE event_date >= '{{ date_add(var('start_date'), -7) }}'
+  {% if target.name == 'prod' && coalesce(env_var('FF_SAMPLE', ''), 'no') == 'no' %}
+    AND NOT is_test
+  {% endif %}
+";
+
+/// Print help for `topic`: the CLI's own help without one, a topic's text, or a command's help
+pub fn help_command(topic: Option<&str>, mut cli: Command) -> HelpResult<()> {
+    let Some(topic) = topic else {
+        cli.print_help()?;
+        return Ok(());
+    };
+    if topic == "templating" {
+        print!("{}", templating());
+        return Ok(());
+    }
+
+    cli.build();
+    match cli.find_subcommand_mut(topic) {
+        Some(command) => command.print_help()?,
+        None => {
+            return Err(format!(
+                "No command or help topic '{}' (topics: {})",
+                topic,
+                TOPICS.join(", ")
+            )
+            .into())
+        }
+    }
+    Ok(())
+}
+
+fn templating() -> String {
+    let mut text = String::from(TEMPLATING);
+    text.push_str("\nFunctions:\n");
+    for function in FUNCTIONS {
+        let _ = writeln!(
+            text,
+            "  {:<32} {}",
+            function.signature, function.description
+        );
+    }
+    text.push_str(TEMPLATING_EXAMPLES);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templating_lists_every_function() {
+        let text = templating();
+        for function in FUNCTIONS {
+            assert!(
+                text.contains(function.signature),
+                "{} is not documented",
+                function.name
+            );
+        }
+    }
+}
@@ -0,0 +1,561 @@
+//! `ff init`: scaffold a new project
+//!
+//! `ff init sales_analytics` creates the project in `sales_analytics/`. At a terminal it asks
+//! for the project name and SQL dialect unless `--name` and `--dialect` give them; otherwise
+//! the name defaults to the directory's and the dialect to DuckDB. Every template also
+//! writes a `.gitignore` keeping `target/` out of version control, or adds that line to an
+//! existing one.
+//!
+//! The `basic` template lays out `models/staging` and `models/marts`, configured in the
+//! project file, with an example model each reading a template source under
+//! `models/imports`. The `medallion` template lays out bronze, silver and gold layers, each
+//! with its schema, materialization and tag set under `models:`, naming rules for its model
+//! names, and policies keeping each layer reading only from the one below. One example model
+//! per layer shows the conventions in use. Files go through the `FileWriter`, so running it
+//! again over an existing project merges or asks rather than overwriting edits. An existing
+//! project file gets the template's settings it lacks added in place, so a project can adopt
+//! the medallion layout without losing its own configuration or comments.
+
+use serde_yaml::Value;
+use std::fs;
+use std::io::{self, BufRead, Write as _};
+use std::path::Path;
+
+use super::writer::{FileWriter, WriteOptions};
+use crate::config::{SqlDialect, PROJECT_CONFIG_FILE};
+use crate::display::status;
+use crate::yaml_edit::{Step, YamlDocument};
+
+type InitResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// What every project's `.gitignore` needs: build output and local state stay out of git
+const GITIGNORE_LINE: &str = "target/";
+
+/// Project layouts `ff init` can generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    Basic,
+    Medallion,
+}
+
+impl Template {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "basic" => Some(Template::Basic),
+            "medallion" => Some(Template::Medallion),
+            _ => None,
+        }
+    }
+
+    /// Files to write, relative to the project directory
+    fn files(self, name: &str, dialect: SqlDialect) -> Vec<(&'static str, String)> {
+        match self {
+            Template::Basic => vec![
+                (
+                    PROJECT_CONFIG_FILE,
+                    BASIC_PROJECT
+                        .replace("{name}", name)
+                        .replace("{dialect}", dialect.as_str()),
+                ),
+                ("models/imports/raw/raw.yml", BASIC_SOURCES.to_string()),
+                (
+                    "models/staging/stg_orders/stg_orders.sql",
+                    "SELECT\n    id AS order_id,\n    customer_id,\n    amount,\n    ordered_at\nFROM raw.orders\n"
+                        .to_string(),
+                ),
+                (
+                    "models/staging/stg_orders/stg_orders.yml",
+                    BASIC_STAGING_MODEL.to_string(),
+                ),
+                (
+                    "models/marts/customer_orders/customer_orders.sql",
+                    "SELECT\n    customer_id,\n    count(*) AS order_count,\n    sum(amount) AS revenue\nFROM staging.stg_orders\nGROUP BY customer_id\n"
+                        .to_string(),
+                ),
+                (
+                    "models/marts/customer_orders/customer_orders.yml",
+                    BASIC_MARTS_MODEL.to_string(),
+                ),
+            ],
+            Template::Medallion => vec![
+                (
+                    PROJECT_CONFIG_FILE,
+                    MEDALLION_PROJECT
+                        .replace("{name}", name)
+                        .replace("{dialect}", dialect.as_str()),
+                ),
+                ("models/imports/raw/raw.yml", MEDALLION_SOURCES.to_string()),
+                (
+                    "models/bronze/brz_orders/brz_orders.sql",
+                    "SELECT\n    id,\n    customer_id,\n    amount,\n    ordered_at\nFROM raw.orders\n"
+                        .to_string(),
+                ),
+                (
+                    "models/bronze/brz_orders/brz_orders.yml",
+                    MEDALLION_BRONZE_MODEL.to_string(),
+                ),
+                (
+                    "models/silver/slv_orders/slv_orders.sql",
+                    "SELECT\n    id AS order_id,\n    customer_id,\n    CAST(amount AS DECIMAL(18, 2)) AS amount,\n    CAST(ordered_at AS DATE) AS order_date\nFROM bronze.brz_orders\nWHERE id IS NOT NULL\n"
+                        .to_string(),
+                ),
+                (
+                    "models/silver/slv_orders/slv_orders.yml",
+                    MEDALLION_SILVER_MODEL.to_string(),
+                ),
+                (
+                    "models/gold/fct_customer_revenue/fct_customer_revenue.sql",
+                    "SELECT\n    customer_id,\n    count(*) AS order_count,\n    sum(amount) AS revenue\nFROM silver.slv_orders\nGROUP BY customer_id\n"
+                        .to_string(),
+                ),
+                (
+                    "models/gold/fct_customer_revenue/fct_customer_revenue.yml",
+                    MEDALLION_GOLD_MODEL.to_string(),
+                ),
+            ],
+        }
+    }
+}
+
+const BASIC_PROJECT: &str = r#"name: {name}
+dialect: {dialect}
+
+# Folder defaults; a model's own YAML wins over them
+models:
+  staging:
+    materialized: view
+    schema: staging
+  marts:
+    materialized: table
+    schema: marts
+"#;
+
+const BASIC_SOURCES: &str = r#"version: 2
+
+sources:
+  - name: raw
+    description: Raw data as loaded into the warehouse
+    database: raw
+    tables:
+      - name: orders
+        description: Orders from the order service
+        columns:
+          - name: id
+          - name: customer_id
+          - name: amount
+          - name: ordered_at
+"#;
+
+const BASIC_STAGING_MODEL: &str = r#"version: 2
+
+models:
+  - name: stg_orders
+    description: Orders renamed and typed, one row per order
+    columns:
+      - name: order_id
+        tests: [not_null, unique]
+      - name: customer_id
+      - name: amount
+      - name: ordered_at
+"#;
+
+const BASIC_MARTS_MODEL: &str = r#"version: 2
+
+models:
+  - name: customer_orders
+    description: Order count and revenue per customer
+    columns:
+      - name: customer_id
+        tests: [not_null, unique]
+      - name: order_count
+      - name: revenue
+"#;
+
+const MEDALLION_PROJECT: &str = r#"name: {name}
+dialect: {dialect}
+
+# Layer defaults; a model's own YAML wins over them
+models:
+  bronze:
+    materialized: view
+    schema: bronze
+    tags: [bronze]
+  silver:
+    materialized: table
+    schema: silver
+    tags: [silver]
+  gold:
+    materialized: table
+    schema: gold
+    tags: [gold]
+
+naming_rules:
+  - path: bronze
+    models: "^brz_"
+  - path: silver
+    models: "^slv_"
+  - path: gold
+    models: "^(dim|fct|agg)_"
+  - columns: "^[a-z][a-z0-9_]*$"
+
+policies:
+  - name: bronze_reads_sources_only
+    description: Bronze models land raw sources as they are and read no other models
+    path: bronze
+    allowed_upstream_paths: []
+  - name: silver_reads_bronze
+    description: Silver models clean and conform bronze data
+    path: silver
+    allowed_upstream_paths: [bronze, silver]
+  - name: gold_reads_silver
+    description: Gold models serve consumers from conformed silver data only
+    path: gold
+    allowed_upstream_paths: [silver, gold]
+  - name: gold_is_owned_and_documented
+    path: gold
+    level: warn
+    require: [owner, description]
+"#;
+
+const MEDALLION_SOURCES: &str = r#"version: 2
+
+sources:
+  - name: raw
+    description: Raw data as loaded into the warehouse
+    tables:
+      - name: orders
+        description: Orders from the order service
+        columns:
+          - name: id
+          - name: customer_id
+          - name: amount
+          - name: ordered_at
+"#;
+
+const MEDALLION_BRONZE_MODEL: &str = r#"version: 2
+
+models:
+  - name: brz_orders
+    description: Raw orders, one row per order event as loaded
+"#;
+
+const MEDALLION_SILVER_MODEL: &str = r#"version: 2
+
+models:
+  - name: slv_orders
+    description: Orders with conformed types, one row per order
+    columns:
+      - name: order_id
+        data_type: bigint
+        tests: [not_null, unique]
+      - name: customer_id
+        data_type: bigint
+      - name: amount
+        data_type: decimal(18, 2)
+      - name: order_date
+        data_type: date
+"#;
+
+const MEDALLION_GOLD_MODEL: &str = r#"version: 2
+
+models:
+  - name: fct_customer_revenue
+    description: Order count and revenue per customer
+    meta:
+      owner: analytics
+    columns:
+      - name: customer_id
+        data_type: bigint
+        tests: [not_null, unique]
+      - name: order_count
+        data_type: bigint
+      - name: revenue
+        data_type: decimal(18, 2)
+"#;
+
+pub fn init_command(
+    dir: &Path,
+    name: Option<&str>,
+    dialect: Option<&str>,
+    template: &str,
+    options: WriteOptions,
+) -> InitResult<()> {
+    let template = Template::from_name(template)
+        .ok_or_else(|| format!("Unknown template '{}'. Use basic or medallion.", template))?;
+    let dialect = dialect
+        .map(|dialect| {
+            SqlDialect::from_name(dialect)
+                .ok_or_else(|| format!("Unknown dialect '{}'. Use duckdb or bigquery.", dialect))
+        })
+        .transpose()?;
+    // Released before writing, where conflicts may be resolved at the terminal too
+    let (name, dialect) = {
+        let mut input = io::stdin().lock();
+        let name = match name {
+            Some(name) => name.to_string(),
+            None if options.interactive => prompt(&mut input, "Project name", &project_name(dir))?,
+            None => project_name(dir),
+        };
+        let dialect = match dialect {
+            Some(dialect) => dialect,
+            None if options.interactive => ask_dialect(&mut input)?,
+            None => SqlDialect::default(),
+        };
+        (name, dialect)
+    };
+
+    let mut writer = FileWriter::new(dir, options);
+    for (path, content) in template.files(&name, dialect) {
+        if path == PROJECT_CONFIG_FILE && dir.join(path).exists() {
+            writer.update_yaml(Path::new(path), |doc| add_missing_settings(doc, &content))?;
+        } else {
+            writer.write(Path::new(path), &content)?;
+        }
+    }
+    writer.ensure_line(Path::new(".gitignore"), GITIGNORE_LINE)?;
+    writer.finish()?;
+    if options.dry_run {
+        return Ok(());
+    }
+
+    fs::create_dir_all(dir.join("models"))?;
+    status!(
+        "Initialized project '{}'. Run ff parse -m {} to check it.",
+        name,
+        dir.join("models").display()
+    );
+    Ok(())
+}
+
+/// Add the top-level settings of `template` that `doc` lacks: missing keys, missing entries
+/// of mappings such as `models:`, and missing items of lists such as `policies:`. Settings
+/// the project already has, its name included, are left as they are.
+fn add_missing_settings(doc: &mut YamlDocument, template: &str) -> anyhow::Result<()> {
+    let existing: Value = serde_yaml::from_str(&doc.to_string())?;
+    let Value::Mapping(settings) = serde_yaml::from_str(template)? else {
+        return Ok(());
+    };
+    for (key, value) in settings {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        match (existing.get(key), value) {
+            (None, value) => doc.set(&[], key, &value)?,
+            (Some(Value::Mapping(current)), Value::Mapping(entries)) => {
+                for (entry, value) in entries {
+                    if let Some(entry) =
+                        entry.as_str().filter(|entry| !current.contains_key(*entry))
+                    {
+                        doc.set(&[Step::Key(key)], entry, &value)?;
+                    }
+                }
+            }
+            (Some(Value::Sequence(current)), Value::Sequence(items)) => {
+                for item in items.into_iter().filter(|item| !current.contains(item)) {
+                    doc.append(&[], key, &item)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The answer to `question`, or `default` when it is left empty
+fn prompt(input: &mut impl BufRead, question: &str, default: &str) -> InitResult<String> {
+    eprint!("{} [{}]: ", question, default);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Ask for a dialect until the answer names one
+fn ask_dialect(input: &mut impl BufRead) -> InitResult<SqlDialect> {
+    loop {
+        let answer = prompt(
+            input,
+            "SQL dialect (duckdb, bigquery)",
+            SqlDialect::default().as_str(),
+        )?;
+        match SqlDialect::from_name(&answer.to_lowercase()) {
+            Some(dialect) => return Ok(dialect),
+            None => eprintln!("Unknown dialect '{}'", answer),
+        }
+    }
+}
+
+/// Directory name as a project name, e.g. `sales_analytics`. The directory need not exist
+/// yet.
+fn project_name(dir: &Path) -> String {
+    dir.canonicalize()
+        .or_else(|_| std::path::absolute(dir))
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .map(|name| name.replace(['-', ' '], "_").to_lowercase())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "featherflow_project".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FeatherFlowConfig;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_medallion_template_configures_layers() {
+        let temp_dir = tempdir().unwrap();
+        init_command(
+            temp_dir.path(),
+            Some("lakehouse"),
+            None,
+            "medallion",
+            WriteOptions::default(),
+        )
+        .unwrap();
+
+        let config = FeatherFlowConfig::load(&temp_dir.path().join(PROJECT_CONFIG_FILE)).unwrap();
+        assert_eq!(config.name.as_deref(), Some("lakehouse"));
+        assert_eq!(
+            config.models.keys().collect::<Vec<_>>(),
+            vec!["bronze", "gold", "silver"]
+        );
+        assert_eq!(config.models["silver"].schema.as_deref(), Some("silver"));
+        assert_eq!(config.naming_rules.len(), 4);
+        assert_eq!(config.policies[0].allowed_upstream_paths, Some(Vec::new()));
+        assert!(temp_dir
+            .path()
+            .join("models/gold/fct_customer_revenue/fct_customer_revenue.sql")
+            .is_file());
+
+        // Rerunning leaves a project whose files were not edited as it is
+        init_command(
+            temp_dir.path(),
+            Some("lakehouse"),
+            None,
+            "medallion",
+            WriteOptions::default(),
+        )
+        .unwrap();
+
+        // but will not overwrite a conflicting edit without a prompt or --force
+        let sql = temp_dir
+            .path()
+            .join("models/bronze/brz_orders/brz_orders.sql");
+        fs::write(&sql, "SELECT * FROM raw.orders\n").unwrap();
+        let again = init_command(
+            temp_dir.path(),
+            Some("lakehouse"),
+            None,
+            "medallion",
+            WriteOptions::default(),
+        )
+        .unwrap_err();
+        assert!(again.to_string().contains("brz_orders.sql"));
+        assert_eq!(
+            fs::read_to_string(&sql).unwrap(),
+            "SELECT * FROM raw.orders\n"
+        );
+    }
+
+    #[test]
+    fn test_medallion_template_extends_an_existing_project_file() {
+        let temp_dir = tempdir().unwrap();
+        let project_file = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(
+            &project_file,
+            "# Shop analytics\nname: shop  # do not rename\nnaming_rules:\n  - path: staging\n    models: \"^stg_\"\n",
+        )
+        .unwrap();
+
+        init_command(
+            temp_dir.path(),
+            None,
+            None,
+            "medallion",
+            WriteOptions::default(),
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(&project_file).unwrap();
+        assert!(content.starts_with("# Shop analytics\nname: shop  # do not rename\n"));
+        let config = FeatherFlowConfig::load(&project_file).unwrap();
+        assert_eq!(config.name.as_deref(), Some("shop"));
+        assert_eq!(config.models.len(), 3);
+        assert_eq!(config.naming_rules.len(), 5);
+        assert_eq!(config.policies.len(), 4);
+    }
+
+    #[test]
+    fn test_basic_template_scaffolds_a_project() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path().join("sales-analytics");
+        init_command(
+            &dir,
+            None,
+            Some("bigquery"),
+            "basic",
+            WriteOptions::default(),
+        )
+        .unwrap();
+
+        let config = FeatherFlowConfig::load(&dir.join(PROJECT_CONFIG_FILE)).unwrap();
+        assert_eq!(config.name.as_deref(), Some("sales_analytics"));
+        assert_eq!(config.dialect, SqlDialect::BigQuery);
+        assert_eq!(config.models["marts"].schema.as_deref(), Some("marts"));
+        for path in [
+            "models/imports/raw/raw.yml",
+            "models/staging/stg_orders/stg_orders.sql",
+            "models/marts/customer_orders/customer_orders.yml",
+        ] {
+            assert!(dir.join(path).is_file(), "{} is missing", path);
+        }
+        assert_eq!(
+            fs::read_to_string(dir.join(".gitignore")).unwrap(),
+            "target/\n"
+        );
+
+        // An existing .gitignore gets the line once
+        fs::write(dir.join(".gitignore"), "*.duckdb").unwrap();
+        init_command(&dir, None, None, "basic", WriteOptions::default()).unwrap();
+        init_command(&dir, None, None, "basic", WriteOptions::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(".gitignore")).unwrap(),
+            "*.duckdb\ntarget/\n"
+        );
+        assert!(
+            init_command(&dir, None, Some("oracle"), "basic", WriteOptions::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_prompts_default_to_an_empty_answer() {
+        let mut input = io::Cursor::new("\nshop\npostgres\nBigQuery\n");
+        assert_eq!(
+            prompt(&mut input, "Project name", "sales").unwrap(),
+            "sales"
+        );
+        assert_eq!(prompt(&mut input, "Project name", "sales").unwrap(), "shop");
+        assert_eq!(ask_dialect(&mut input).unwrap(), SqlDialect::BigQuery);
+        assert_eq!(ask_dialect(&mut input).unwrap(), SqlDialect::DuckDb);
+    }
+
+    #[test]
+    fn test_unknown_template() {
+        let temp_dir = tempdir().unwrap();
+        assert!(init_command(
+            temp_dir.path(),
+            None,
+            None,
+            "kimball",
+            WriteOptions::default()
+        )
+        .is_err());
+        assert!(!temp_dir.path().join(PROJECT_CONFIG_FILE).exists());
+    }
+}
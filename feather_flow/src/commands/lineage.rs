@@ -0,0 +1,135 @@
+//! `ff lineage`: column-level lineage of one model
+//!
+//! Traces each column the model's compiled SQL selects back to the source columns it is
+//! computed from. The dot format is for Graphviz:
+//! `ff lineage -m models orders -f dot | dot -Tsvg`.
+
+use colored::Colorize;
+use serde::Serialize;
+use std::path::Path;
+
+use super::project::{find_model, load_project};
+use crate::display::{self, status};
+use crate::sql_engine::lineage::{self, ColumnLineage};
+
+type LineageResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug, Serialize)]
+struct ModelLineage<'a> {
+    model: &'a str,
+    columns: Vec<LineageEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct LineageEntry {
+    column: String,
+    transformation: String,
+    sources: Vec<String>,
+}
+
+pub fn lineage_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    model: &str,
+    format: &str,
+) -> LineageResult<()> {
+    let project = load_project(model_path, target, vars)?;
+    let model = find_model(&project.models, model)?;
+
+    let sql = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    let lineage = lineage::extract_column_lineage(sql)
+        .map_err(|err| format!("Failed to extract lineage of {}: {}", model.unique_id, err))?;
+
+    match format {
+        "dot" => print!("{}", lineage::generate_lineage_graph(&lineage)),
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&model_lineage(&model.unique_id, &lineage))?
+        ),
+        "text" => print_text(&model_lineage(&model.unique_id, &lineage)),
+        other => {
+            return Err(
+                format!("Unknown lineage format '{}' (use text, dot or json)", other).into(),
+            )
+        }
+    }
+
+    Ok(())
+}
+
+fn model_lineage<'a>(model: &'a str, lineage: &[ColumnLineage]) -> ModelLineage<'a> {
+    ModelLineage {
+        model,
+        columns: lineage
+            .iter()
+            .map(|entry| LineageEntry {
+                column: entry.target.column.clone(),
+                transformation: entry.transformation.clone(),
+                sources: entry.sources.iter().map(ToString::to_string).collect(),
+            })
+            .collect(),
+    }
+}
+
+fn print_text(lineage: &ModelLineage) {
+    // One record per column: column, transformation, comma-separated sources
+    if display::porcelain() {
+        for entry in &lineage.columns {
+            display::record(&[
+                &entry.column,
+                &entry.transformation,
+                &entry.sources.join(","),
+            ]);
+        }
+        return;
+    }
+
+    status!("{}", format!("Column lineage of {}:", lineage.model).bold());
+    if lineage.columns.is_empty() {
+        println!("  (none)");
+    }
+    for entry in &lineage.columns {
+        println!(
+            "  {} <- {} [{}]",
+            entry.column,
+            if entry.sources.is_empty() {
+                "-".to_string()
+            } else {
+                entry.sources.join(", ")
+            },
+            entry.transformation
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::lineage::ColumnRef;
+
+    #[test]
+    fn test_model_lineage_names_sources() {
+        let lineage = vec![ColumnLineage {
+            target: ColumnRef::new(None, "total".to_string()),
+            sources: vec![
+                ColumnRef::new(Some("o".to_string()), "amount".to_string()),
+                ColumnRef::new(None, "tax".to_string()),
+            ],
+            transformation: "expression".to_string(),
+        }];
+
+        let json = serde_json::to_value(model_lineage("model.orders", &lineage)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "model": "model.orders",
+                "columns": [{
+                    "column": "total",
+                    "transformation": "expression",
+                    "sources": ["o.amount", "tax"],
+                }],
+            })
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! `ff ls`: list the project's nodes
+//!
+//! Lists every seed, model, snapshot, source, test and exposure, or those a `--select`
+//! expression or `--selector` matches, ordered by unique id. Columns: `unique_id`,
+//! `resource_type`, `name`, `path` (the relative path of the file defining the node, empty
+//! for nodes without one) and `tags` (comma-separated).
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use super::project::{load_project, project_root};
+use crate::display::status;
+use crate::run::plan::BuildPlan;
+use crate::run::selection::{self, Selector};
+use crate::sql_engine::nodes::Node;
+use crate::table::{Table, TableFormat};
+
+type LsResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Columns of `ff ls`, in output order
+pub const LS_COLUMNS: [&str; 5] = ["unique_id", "resource_type", "name", "path", "tags"];
+
+pub struct LsArgs<'a> {
+    pub model_path: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    pub select: Option<&'a str>,
+    /// Name of a selector in `selectors.yml`, used instead of `select`
+    pub selector: Option<&'a str>,
+    pub exclude: Option<&'a str>,
+    pub format: &'a str,
+}
+
+pub fn ls_command(args: &LsArgs) -> LsResult<()> {
+    let format = TableFormat::parse(args.format)?;
+    let project = load_project(args.model_path, args.target, args.vars)?;
+    let selection = project.selection(args.model_path, args.select, args.selector)?;
+    let select = selection.as_deref().map(Selector::parse).transpose()?;
+    let exclude = args.exclude.map(Selector::parse).transpose()?;
+    let root = project_root(&project.config, args.model_path);
+
+    let plan = BuildPlan::new(&project.models);
+    let selected = selection::resolve(&plan, select.as_ref(), exclude.as_ref());
+
+    ls_table(&plan, &selected, &root).print(format)?;
+
+    status!("{} node(s)", selected.len());
+    Ok(())
+}
+
+/// One row per selected node, in unique id order
+fn ls_table(plan: &BuildPlan, selected: &BTreeSet<String>, root: &Path) -> Table {
+    let mut table = Table::new(LS_COLUMNS);
+    for node in selected
+        .iter()
+        .filter_map(|id| plan.get(id))
+        .map(|node| node.node)
+    {
+        let path = match node {
+            Node::Model(model) | Node::Snapshot(model) => {
+                model.relative_file_path.display().to_string()
+            }
+            Node::Seed(seed) => seed
+                .path
+                .strip_prefix(root)
+                .unwrap_or(&seed.path)
+                .display()
+                .to_string(),
+            _ => String::new(),
+        };
+        table.push([
+            node.unique_id().to_string(),
+            node.resource_type().to_string(),
+            node.name().to_string(),
+            path,
+            node.tags().join(","),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_ls_lists_selected_nodes_as_csv() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("models");
+        for (name, sql, tags) in [
+            ("stg_orders", "SELECT 1 AS id", "[staging]"),
+            (
+                "fct_orders",
+                "SELECT id FROM public.stg_orders",
+                "[mart, finance]",
+            ),
+        ] {
+            fs::create_dir_all(models.join(name)).unwrap();
+            fs::write(models.join(name).join(format!("{}.sql", name)), sql).unwrap();
+            fs::write(
+                models.join(name).join(format!("{}.yml", name)),
+                format!(
+                    "version: 2\nmodels:\n  - name: {}\n    meta:\n      tags: {}\n",
+                    name, tags
+                ),
+            )
+            .unwrap();
+        }
+
+        let project = load_project(&models, None, None).unwrap();
+        let plan = BuildPlan::new(&project.models);
+        let select = Selector::parse("tag:finance").unwrap();
+        let selected = selection::resolve(&plan, Some(&select), None);
+        let csv = ls_table(&plan, &selected, &project_root(&project.config, &models))
+            .render(TableFormat::Csv)
+            .unwrap();
+        assert_eq!(
+            csv,
+            "unique_id,resource_type,name,path,tags\n\
+             model.fct_orders.fct_orders,model,fct_orders,fct_orders/fct_orders.sql,\"mart,finance\"\n"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! `ff plan-migration`: ALTER statements that bring an existing table in line with a model
+//!
+//! The columns of the deployed relation are compared with the columns the model's compiled
+//! SQL now produces. Nothing is executed; the statements are printed for review so a table
+//! can be migrated in place instead of being rebuilt.
+
+use colored::Colorize;
+use std::path::Path;
+
+use super::project::{find_model, load_project, require_duckdb};
+use crate::adapters::{self, AccessMode, Adapter};
+use crate::display::status;
+use crate::sql_engine::sql_model::SqlModel;
+use crate::sql_engine::template::DEFAULT_SCHEMA;
+
+type MigrationResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Materializations whose relation persists between runs
+const PERSISTED_MATERIALIZATIONS: &[&str] = &["table", "incremental"];
+
+/// A column name and its type as reported by the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: String,
+}
+
+pub fn plan_migration_command(
+    model_path: &Path,
+    database: &Path,
+    select: &str,
+    target: Option<&str>,
+    vars: Option<&str>,
+    mode: AccessMode,
+) -> MigrationResult<()> {
+    let project = load_project(model_path, target, vars)?;
+    require_duckdb(&project.config, "ff plan-migration")?;
+    let model = find_model(&project.models, select)?;
+
+    let materialized = model.materialized.as_deref().unwrap_or("view");
+    if !PERSISTED_MATERIALIZATIONS.contains(&materialized) {
+        return Err(format!(
+            "Model '{}' is materialized as '{}'; only table and incremental models are migrated",
+            model.name, materialized
+        )
+        .into());
+    }
+
+    let adapter = adapters::connect(database, mode)?;
+    let relation = model.qualified_relation();
+    let existing = existing_columns(adapter.as_ref(), model)?;
+
+    if existing.is_empty() {
+        status!(
+            "{} does not exist yet; it will be created on the next run",
+            relation
+        );
+        return Ok(());
+    }
+
+    let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    let planned = output_columns(adapter.as_ref(), compiled)?;
+    let statements = plan_alters(&relation, &existing, &planned);
+
+    if statements.is_empty() {
+        status!("{} already matches model '{}'", relation, model.name);
+        return Ok(());
+    }
+
+    status!(
+        "{}",
+        format!("-- Migration for {} ({})", relation, model.name).dimmed()
+    );
+    for statement in &statements {
+        println!("{}", statement);
+    }
+    Ok(())
+}
+
+/// Columns of the model's deployed relation, empty when it does not exist
+fn existing_columns(adapter: &dyn Adapter, model: &SqlModel) -> MigrationResult<Vec<ColumnDef>> {
+    let columns = adapters::relation_columns(
+        adapter,
+        model.database.as_deref(),
+        model.schema.as_deref().unwrap_or(DEFAULT_SCHEMA),
+        model.relation_name(),
+    )?;
+    Ok(columns
+        .into_iter()
+        .map(|(name, data_type)| ColumnDef { name, data_type })
+        .collect())
+}
+
+/// Columns the compiled SQL produces, without running it
+fn output_columns(adapter: &dyn Adapter, compiled: &str) -> MigrationResult<Vec<ColumnDef>> {
+    let query = compiled.trim().trim_end_matches(';').trim_end();
+    let sql = format!("DESCRIBE SELECT * FROM (\n{}\n) AS planned", query);
+    Ok(column_defs(adapter.query(&sql)?.rows))
+}
+
+fn column_defs(rows: Vec<Vec<String>>) -> Vec<ColumnDef> {
+    rows.into_iter()
+        .filter_map(|row| {
+            let mut values = row.into_iter();
+            Some(ColumnDef {
+                name: values.next()?,
+                data_type: values.next()?,
+            })
+        })
+        .collect()
+}
+
+/// ADD, DROP and ALTER COLUMN statements turning `existing` into `planned`. Column names are
+/// compared case-insensitively, as DuckDB does.
+pub fn plan_alters(relation: &str, existing: &[ColumnDef], planned: &[ColumnDef]) -> Vec<String> {
+    let find = |columns: &[ColumnDef], name: &str| {
+        columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(name))
+            .cloned()
+    };
+    let mut statements = Vec::new();
+
+    for column in planned {
+        match find(existing, &column.name) {
+            None => statements.push(format!(
+                "ALTER TABLE {} ADD COLUMN {} {};",
+                relation, column.name, column.data_type
+            )),
+            Some(current) if !current.data_type.eq_ignore_ascii_case(&column.data_type) => {
+                statements.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} TYPE {};",
+                    relation, column.name, column.data_type
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for column in existing {
+        if find(planned, &column.name).is_none() {
+            statements.push(format!(
+                "ALTER TABLE {} DROP COLUMN {};",
+                relation, column.name
+            ));
+        }
+    }
+
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(defs: &[(&str, &str)]) -> Vec<ColumnDef> {
+        defs.iter()
+            .map(|(name, data_type)| ColumnDef {
+                name: name.to_string(),
+                data_type: data_type.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_alters() {
+        let existing = columns(&[
+            ("id", "INTEGER"),
+            ("amount", "INTEGER"),
+            ("legacy_flag", "BOOLEAN"),
+        ]);
+        let planned = columns(&[
+            ("ID", "integer"),
+            ("amount", "DECIMAL(18,2)"),
+            ("currency", "VARCHAR"),
+        ]);
+
+        assert_eq!(
+            plan_alters("marts.orders", &existing, &planned),
+            vec![
+                "ALTER TABLE marts.orders ALTER COLUMN amount TYPE DECIMAL(18,2);",
+                "ALTER TABLE marts.orders ADD COLUMN currency VARCHAR;",
+                "ALTER TABLE marts.orders DROP COLUMN legacy_flag;",
+            ]
+        );
+        assert!(plan_alters("marts.orders", &existing, &existing).is_empty());
+    }
+}
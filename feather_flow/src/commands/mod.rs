@@ -1,6 +1,35 @@
 //! CLI commands for FeatherFlow
 
+pub mod analyze;
+pub mod assertions;
+pub mod build;
+pub mod clean;
+pub mod compile;
+pub mod config;
+pub mod debug;
+pub mod determinism;
+pub mod docs;
+mod docs_site;
+pub mod expr;
+pub mod help;
+pub mod init;
+pub mod lineage;
+pub mod ls;
+pub mod migration;
 pub mod parse;
+pub mod preview;
+pub mod project;
+pub mod report;
+pub mod source;
+pub mod suggest;
+pub mod sync_schemas;
+pub mod test;
+pub mod unlock;
+pub mod validate;
+mod watch;
+pub mod why;
+pub mod workflow;
+pub mod writer;
 
 #[cfg(test)]
 mod tests;
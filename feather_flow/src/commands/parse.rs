@@ -1,60 +1,195 @@
 use colored::Colorize;
-use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use sqlparser::dialect::DuckDbDialect;
 use walkdir::WalkDir;
 
+use super::project::{project_root, save_parse_cache};
+use super::watch;
+use crate::config::materializations::MaterializationRegistry;
+use crate::config::FeatherFlowConfig;
+use crate::display::{status, Marker};
+use crate::error::FeatherFlowError;
+use crate::sql_engine::manifest_writer::{ManifestFormat, ManifestWriter};
+use crate::sql_engine::nodes::SEEDS_DIR;
 use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+use crate::sql_engine::template::{TargetContext, DEFAULT_TARGET};
+use crate::state::parse_cache::ParseCache;
+use crate::state::state_dir;
+use crate::stats::PhaseTimer;
+use crate::validators::meta::MetaSchema;
+use crate::validators::naming::{NamingRules, NamingViolation};
+use crate::validators::policies::{self, PolicyStatus};
+use crate::validators::yaml_schema;
 
 type ParseResult<T> = Result<T, Box<dyn std::error::Error>>;
 
-pub fn parse_command(
-    model_path: &Path,
-    format: &str,
-    validate: bool,
-    output_file: Option<&str>,
-) -> ParseResult<()> {
-    let start_time = Instant::now();
+/// How dependencies created by `schema.name` matching (rather than an explicit ref) are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImplicitRefPolicy {
+    #[default]
+    Allow,
+    Warn,
+    Error,
+}
+
+pub struct ParseArgs<'a> {
+    pub model_path: &'a Path,
+    pub format: &'a str,
+    pub validate: bool,
+    pub output_file: Option<&'a str>,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    pub implicit_refs: ImplicitRefPolicy,
+    /// Keep running and parse again whenever a model or the project config changes
+    pub watch: bool,
+    /// Print how long each phase took
+    pub stats: bool,
+    /// Fail on model and source YAML that does not match the structure FeatherFlow reads
+    pub strict_yaml: bool,
+}
 
+pub fn parse_command(args: &ParseArgs) -> ParseResult<()> {
     // Display welcome ASCII art
     crate::display::display_parse_welcome();
 
-    println!(
+    if !args.watch {
+        return parse_project(args);
+    }
+
+    // The output file may live under the model path; writing it is not a change to parse
+    watch::watch(args.model_path, args.output_file.map(Path::new), || {
+        if let Err(err) = parse_project(args) {
+            eprintln!("{} {}", Marker::Error, err);
+        }
+    })
+}
+
+fn parse_project(args: &ParseArgs) -> ParseResult<()> {
+    let start_time = Instant::now();
+    let ParseArgs {
+        model_path,
+        format,
+        validate,
+        output_file,
+        target,
+        vars,
+        implicit_refs,
+        watch: _,
+        stats,
+        strict_yaml,
+    } = *args;
+    let mut timer = PhaseTimer::new();
+    timer.phase("discovery");
+
+    status!(
         "{}",
         format!("Parsing SQL files in: {}", model_path.display()).green()
     );
 
+    let project_config = FeatherFlowConfig::discover(model_path)?.unwrap_or_default();
+    let hash = project_config.state.hash.algorithm()?;
+    let target = project_config.target(target);
+    let target = target.as_deref();
+    let vars = project_config.resolve_vars(vars)?;
+
     let sql_files = find_sql_files(model_path)?;
-    println!("Found {} SQL files", sql_files.len());
+    status!("Found {} SQL files", sql_files.len());
 
-    let mut model_collection = parse_sql_files(&sql_files, model_path, validate)?;
-    process_model_collection(&mut model_collection, model_path, validate)?;
+    if strict_yaml {
+        check_yaml_schema(model_path)?;
+    }
+
+    let state_dir = state_dir(&project_root(&project_config, model_path));
+    let mut cache = ParseCache::load(&state_dir, hash);
+
+    let dialect_name = project_config.dialect.as_str();
+    timer.phase("parse");
+    let mut model_collection =
+        parse_sql_files(&sql_files, model_path, dialect_name, validate, &mut cache)?;
+    model_collection.apply_directory_configs(&project_config.models);
+    if let Some(target) = target {
+        status!("Resolving model locations for target: {}", target);
+        model_collection.apply_target(target);
+    }
+    timer.phase("yaml");
+    if let Err(err) = model_collection.load_source_definitions(model_path) {
+        eprintln!(
+            "{} Failed to load source definitions: {}",
+            "Warning:".yellow(),
+            err
+        );
+    }
+    let seeds_dir = project_root(&project_config, model_path).join(SEEDS_DIR);
+    if let Err(err) = model_collection.load_seeds(&seeds_dir) {
+        eprintln!("{} Failed to load seeds: {:#}", "Warning:".yellow(), err);
+    }
+    timer.phase("compile");
+    let context =
+        TargetContext::new(target.unwrap_or(DEFAULT_TARGET), dialect_name).with_vars(vars);
+    model_collection
+        .compile_with_cache(&context, &mut cache)
+        .map_err(|err| format!("{:#}", err))?;
+    report_parse_cache(&cache, &state_dir, &project_config);
+
+    timer.phase("graph");
+    process_model_collection(
+        &mut model_collection,
+        model_path,
+        &project_config,
+        validate,
+        implicit_refs,
+    )?;
+    timer.phase("serialize");
     output_results(&model_collection, format, output_file)?;
+    timer.end();
 
-    println!(
+    status!(
         "Successfully parsed {} out of {} SQL files in {:.2?}",
         model_collection.models_count(),
         sql_files.len(),
         start_time.elapsed()
     );
+    if stats {
+        timer.print();
+    }
 
     Ok(())
 }
 
+/// Write the parse cache and report how much of it was reused
+fn report_parse_cache(cache: &ParseCache, state_dir: &Path, project_config: &FeatherFlowConfig) {
+    let summary = cache.summary();
+    status!(
+        "Parse cache: {} unchanged, {} with YAML-only changes, {} reparsed",
+        summary.unchanged,
+        summary.metadata_refreshed,
+        summary.reparsed
+    );
+    save_parse_cache(cache, state_dir, project_config);
+}
+
 fn parse_sql_files(
     sql_files: &[PathBuf],
     model_path: &Path,
+    dialect_name: &str,
     validate: bool,
+    cache: &mut ParseCache,
 ) -> ParseResult<SqlModelCollection> {
-    let dialect = DuckDbDialect {};
     let mut model_collection = SqlModelCollection::new();
 
     for file_path in sql_files {
-        match parse_single_sql_file(file_path.as_path(), model_path, &dialect, validate)? {
+        match parse_single_sql_file(
+            file_path.as_path(),
+            model_path,
+            dialect_name,
+            validate,
+            cache,
+        )? {
             Some(model) => {
-                println!("Successfully parsed: {}", file_path.display());
+                status!("Successfully parsed: {}", file_path.display());
                 model_collection.add_model(model);
             }
             None => continue,
@@ -68,10 +203,11 @@ fn parse_sql_files(
 fn parse_single_sql_file(
     file_path: &Path,
     model_path: &Path,
-    dialect: &DuckDbDialect,
+    dialect_name: &str,
     validate: bool,
+    cache: &mut ParseCache,
 ) -> ParseResult<Option<SqlModel>> {
-    match SqlModel::from_path(file_path, model_path, "duckdb", dialect) {
+    match SqlModel::from_path_with_cache(file_path, model_path, dialect_name, cache) {
         Ok(mut model) => {
             if validate {
                 validate_model_structure(&model, file_path, model_path)?;
@@ -84,10 +220,10 @@ fn parse_single_sql_file(
             Ok(Some(model))
         }
         Err(err) => handle_model_creation_error(
-            Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Model creation error: {}", err),
-            )),
+            Box::new(std::io::Error::other(format!(
+                "Model creation error: {:#}",
+                err
+            ))),
             file_path,
             model_path,
             validate,
@@ -125,10 +261,10 @@ fn extract_model_dependencies(model: &mut SqlModel, file_path: &Path) -> ParseRe
             file_path.display(),
             err
         );
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to extract dependencies: {}", err),
-        )));
+        return Err(Box::new(std::io::Error::other(format!(
+            "Failed to extract dependencies: {}",
+            err
+        ))));
     }
 
     Ok(())
@@ -163,60 +299,341 @@ fn handle_model_creation_error(
 fn process_model_collection(
     model_collection: &mut SqlModelCollection,
     model_path: &Path,
+    project_config: &FeatherFlowConfig,
     validate: bool,
+    implicit_refs: ImplicitRefPolicy,
 ) -> ParseResult<()> {
-    if let Err(err) = model_collection.load_source_definitions(model_path) {
-        eprintln!(
-            "{} Failed to load source definitions: {}",
-            "Warning:".yellow(),
-            err
-        );
+    model_collection.build_dependency_graph();
+    model_collection.rewrite_relocated_references();
+
+    let self_reference_errors = model_collection.get_invalid_self_references_report();
+    if !self_reference_errors.is_empty() {
+        eprintln!("\n--- {} ---", "Invalid Self-References Detected".red());
+        for error in &self_reference_errors {
+            eprintln!("{}", error);
+        }
+        return Err("Invalid self-references detected. Set 'materialized: incremental' or select from a different relation.".into());
     }
 
-    model_collection.build_dependency_graph();
+    check_implicit_dependencies(model_collection, implicit_refs)?;
+
+    for warning in model_collection.get_test_annotation_report() {
+        eprintln!("{} {}", "Warning:".yellow(), warning);
+    }
+
+    let statement_errors = model_collection.get_statement_error_report();
+    for error in &statement_errors {
+        eprintln!("{} {}", "Error:".red(), error);
+    }
+    if validate && !statement_errors.is_empty() {
+        return Err(format!(
+            "{} statement(s) failed to parse. Fix the syntax errors above.",
+            statement_errors.len()
+        )
+        .into());
+    }
 
     if validate && model_collection.has_missing_sources() {
-        println!("\n--- {} ---", "Missing External Imports Detected".red());
+        eprintln!("\n--- {} ---", "Missing External Imports Detected".red());
         for error in model_collection.get_missing_sources_report() {
-            println!("{}", error);
+            eprintln!("{}", error);
         }
         return Err("Missing external imports detected. Add import definitions to imports directory or check for typos in import references.".into());
     }
 
+    // Unknown materializations cannot be built, so they fail the parse even without validation
+    validate_materializations(model_collection, project_config)?;
+
+    if validate {
+        validate_partitioning(model_collection)?;
+        validate_snapshots(model_collection)?;
+        validate_expected_runtimes(model_collection)?;
+        validate_meta(model_collection, project_config)?;
+        validate_descriptions(model_collection)?;
+        validate_naming_conventions(model_path, project_config)?;
+    }
+
+    check_policies(model_collection, project_config)?;
+
     let cycles = model_collection.detect_cycles();
     if !cycles.is_empty() {
-        println!("\n--- {} ---", "Circular Dependencies Detected".red());
+        eprintln!("\n--- {} ---", "Circular Dependencies Detected".red());
         for (i, cycle) in cycles.iter().enumerate() {
-            println!("Cycle {}: {}", i + 1, cycle.join(" → "));
+            // Back to the first model, closing the loop
+            let closed: Vec<&str> = cycle
+                .iter()
+                .chain(cycle.first())
+                .map(String::as_str)
+                .collect();
+            eprintln!(
+                "Cycle {}: {}",
+                i + 1,
+                closed.join(&format!(" {} ", Marker::Arrow))
+            );
         }
+        return Err(FeatherFlowError::DependencyCycle { cycles }.into());
     }
 
     Ok(())
 }
 
+fn check_implicit_dependencies(
+    model_collection: &SqlModelCollection,
+    implicit_refs: ImplicitRefPolicy,
+) -> ParseResult<()> {
+    if implicit_refs == ImplicitRefPolicy::Allow {
+        return Ok(());
+    }
+
+    let report = model_collection.get_implicit_dependencies_report();
+    if report.is_empty() {
+        return Ok(());
+    }
+
+    if implicit_refs == ImplicitRefPolicy::Warn {
+        for warning in &report {
+            eprintln!("{} {}", "Warning:".yellow(), warning);
+        }
+        return Ok(());
+    }
+
+    eprintln!("\n--- {} ---", "Implicit Dependencies Detected".red());
+    for error in &report {
+        eprintln!("{}", error);
+    }
+    Err("Implicit dependencies detected. Reference upstream models explicitly or drop --require-explicit-refs.".into())
+}
+
+fn validate_materializations(
+    model_collection: &SqlModelCollection,
+    project_config: &FeatherFlowConfig,
+) -> ParseResult<()> {
+    let registry = MaterializationRegistry::from_config(project_config);
+
+    let mut errors = registry.validate_recipes();
+    errors.extend(model_collection.get_unknown_materializations_report(&registry));
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("\n--- {} ---", "Invalid Materializations Detected".red());
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+
+    Err("Invalid materializations detected. Define custom materializations under 'materializations:' in featherflow_project.yaml.".into())
+}
+
+fn validate_partitioning(model_collection: &SqlModelCollection) -> ParseResult<()> {
+    let errors = model_collection.get_partitioning_report();
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("\n--- {} ---", "Invalid Partitioning Detected".red());
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+
+    Err("Invalid partition_by/cluster_by configs detected. Fix them in the models' YAML.".into())
+}
+
+fn validate_snapshots(model_collection: &SqlModelCollection) -> ParseResult<()> {
+    let errors = model_collection.get_snapshot_report();
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("\n--- {} ---", "Invalid Snapshots Detected".red());
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+
+    Err("Invalid snapshot configs detected. Fix unique_key/strategy in the models' YAML.".into())
+}
+
+fn validate_expected_runtimes(model_collection: &SqlModelCollection) -> ParseResult<()> {
+    let errors = model_collection.get_expected_runtime_report();
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("\n--- {} ---", "Invalid Expected Runtimes Detected".red());
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+
+    Err("Invalid expected_runtime values detected. Use a duration such as 90s, 2m or 1h.".into())
+}
+
+fn check_yaml_schema(model_path: &Path) -> ParseResult<()> {
+    let issues = yaml_schema::check_models_directory(model_path);
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("\n--- {} ---", "Invalid Model YAML Detected".red());
+    for issue in &issues {
+        eprintln!("[{}] {}", issue.kind.code(), issue);
+    }
+
+    Err(format!(
+        "{} YAML schema error(s) detected. Fix the keys and values in the models' YAML.",
+        issues.len()
+    )
+    .into())
+}
+
+fn validate_meta(
+    model_collection: &SqlModelCollection,
+    project_config: &FeatherFlowConfig,
+) -> ParseResult<()> {
+    let schema = MetaSchema::compile(&project_config.meta_schema)?;
+    let errors = model_collection.get_meta_report(&schema);
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("\n--- {} ---", "Invalid Meta Values Detected".red());
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+
+    Err("Invalid meta values detected. Fix them in the models' YAML or adjust 'meta_schema' in featherflow_project.yaml.".into())
+}
+
+fn validate_descriptions(model_collection: &SqlModelCollection) -> ParseResult<()> {
+    let errors = model_collection.get_description_report();
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "\n--- {} ---",
+        "Invalid Description Templates Detected".red()
+    );
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+
+    Err("Invalid description templates detected. Set the meta keys they use or fix the expressions in the models' YAML.".into())
+}
+
+fn validate_naming_conventions(
+    model_path: &Path,
+    project_config: &FeatherFlowConfig,
+) -> ParseResult<()> {
+    let rules = NamingRules::compile(&project_config.naming_rules)?;
+    let violations = rules.check_models_directory(model_path);
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "\n--- {} ---",
+        "Naming Convention Violations Detected".red()
+    );
+    for violation in &violations {
+        print_naming_violation(violation);
+    }
+
+    Err("Naming convention violations detected. Rename the models/columns or adjust 'naming_rules' in featherflow_project.yaml.".into())
+}
+
+fn check_policies(
+    model_collection: &SqlModelCollection,
+    project_config: &FeatherFlowConfig,
+) -> ParseResult<()> {
+    if project_config.policies.is_empty() {
+        return Ok(());
+    }
+
+    let results = policies::evaluate(&project_config.policies, model_collection);
+    let mut failed = 0;
+    for result in &results {
+        let label = match result.status() {
+            PolicyStatus::Pass => continue,
+            PolicyStatus::Warn => "Warning:".yellow(),
+            PolicyStatus::Fail => {
+                failed += 1;
+                "Error:".red()
+            }
+        };
+        for violation in &result.violations {
+            eprintln!(
+                "{} policy '{}': {} {}",
+                label,
+                result.name,
+                violation.path.display(),
+                violation.message
+            );
+        }
+    }
+
+    let passed = results
+        .iter()
+        .filter(|result| result.status() == PolicyStatus::Pass)
+        .count();
+    status!(
+        "Policies: {} passed, {} warned, {} failed",
+        passed,
+        results.len() - passed - failed,
+        failed
+    );
+
+    if failed > 0 {
+        return Err(format!(
+            "{} policy(ies) failed. Fix the models above or adjust 'policies' in featherflow_project.yaml.",
+            failed
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn print_naming_violation(violation: &NamingViolation) {
+    match &violation.suggestion {
+        Some(suggestion) => eprintln!(
+            "{}: {} (suggestion: rename to '{}')",
+            violation.path.display(),
+            violation.message,
+            suggestion
+        ),
+        None => eprintln!("{}: {}", violation.path.display(), violation.message),
+    }
+}
+
 fn output_results(
     model_collection: &SqlModelCollection,
     format: &str,
     output_file: Option<&str>,
 ) -> ParseResult<()> {
     if let Some(output_path) = output_file {
-        match format {
-            "yaml" => write_yaml_to_file(model_collection, output_path)?,
+        let format = match format {
+            "json" => ManifestFormat::Json,
+            "yaml" => ManifestFormat::Yaml,
             _ => {
-                println!(
-                    "When using --output-file, only 'yaml' format is supported. Using yaml format."
+                eprintln!(
+                    "When using --output-file, only 'json' and 'yaml' formats are supported. Using yaml format."
                 );
-                write_yaml_to_file(model_collection, output_path)?;
+                ManifestFormat::Yaml
             }
-        }
+        };
+        let file = File::create(output_path)
+            .map_err(|err| format!("Failed to create {}: {}", output_path, err))?;
+        write_manifest(model_collection, format, BufWriter::new(file))?;
+        status!("Model graph data written to {}", output_path);
     } else {
+        let stdout = || BufWriter::new(io::stdout().lock());
         match format {
             "text" => output_text_format(model_collection),
             "dot" => println!("{}", model_collection.to_dot_graph()),
-            "json" => output_json_format(model_collection)?,
-            "yaml" => output_yaml_format(model_collection)?,
+            "json" => write_manifest(model_collection, ManifestFormat::Json, stdout())?,
+            "yaml" => write_manifest(model_collection, ManifestFormat::Yaml, stdout())?,
             _ => {
-                println!(
+                eprintln!(
                     "Unsupported output format: {}. Using text format instead.",
                     format
                 );
@@ -240,7 +657,7 @@ fn output_text_format(model_collection: &SqlModelCollection) {
             }
         }
         Err(err) => {
-            println!("Error determining execution order: {}", err);
+            eprintln!("Error determining execution order: {}", err);
         }
     }
 }
@@ -254,7 +671,7 @@ fn print_model_summary(model: &SqlModel) {
 }
 
 fn print_model_details(model: &SqlModel) {
-    if let Some(ref description) = model.description {
+    if let Some(ref description) = model.rendered_description() {
         println!("  Description: {}", description);
     }
 
@@ -264,12 +681,11 @@ fn print_model_details(model: &SqlModel) {
 
     if let Some(ref schema) = model.schema {
         let db = model.database.as_deref().unwrap_or("default");
-        println!(
-            "  Location: {}.{}.{}",
-            db,
-            schema,
-            model.object_name.as_deref().unwrap_or(&model.name)
-        );
+        println!("  Location: {}.{}.{}", db, schema, model.relation_name());
+    }
+
+    if let Some(ref loaded_at_field) = model.loaded_at_field {
+        println!("  Loaded at: {}", loaded_at_field);
     }
 
     if !model.tags.is_empty() {
@@ -284,7 +700,7 @@ fn print_model_columns(model: &SqlModel) {
         println!("  Columns:");
         for column in model.columns.values() {
             let data_type = column.data_type.as_deref().unwrap_or("unknown");
-            print!("    • {} [{}]", column.name, data_type);
+            print!("    {} {} [{}]", Marker::Bullet, column.name, data_type);
 
             if let Some(ref desc) = column.description {
                 print!(": {}", desc);
@@ -299,66 +715,52 @@ fn print_model_dependencies(model: &SqlModel) {
     if !external_sources.is_empty() {
         println!("  External sources:");
         for source in external_sources {
-            println!("    • {}", source);
+            println!("    {} {}", Marker::Bullet, source);
         }
     }
 
     if !model.upstream_models.is_empty() {
         println!("  Depends on models:");
         for upstream in &model.upstream_models {
-            println!("    • {}", upstream);
+            println!("    {} {}", Marker::Bullet, upstream);
         }
     }
 
     if !model.downstream_models.is_empty() {
         println!("  Used by models:");
         for downstream in &model.downstream_models {
-            println!("    • {}", downstream);
+            println!("    {} {}", Marker::Bullet, downstream);
         }
     }
 }
 
-fn output_json_format(model_collection: &SqlModelCollection) -> ParseResult<()> {
-    // Use types directly from the module
-    use output_json_format;
-
-    let json_models = build_json_models(model_collection)?;
-
-    let output = output_json_format::JsonOutput {
-        models: json_models,
-    };
-
-    let json = serde_json::to_string_pretty(&output)?;
-    println!("{}", json);
-
-    Ok(())
-}
-
-fn build_json_models(
+/// Stream the manifest to `out`, serializing one model at a time
+pub fn write_manifest(
     model_collection: &SqlModelCollection,
-) -> ParseResult<HashMap<String, output_json_format::JsonModel>> {
-    let mut json_models = HashMap::new();
-
-    match model_collection.get_execution_order() {
-        Ok(models) => {
-            for model in models {
-                json_models.insert(model.unique_id.clone(), convert_model_to_json(model));
+    format: ManifestFormat,
+    out: impl Write,
+) -> ParseResult<()> {
+    match format {
+        ManifestFormat::Yaml => {
+            model_collection.write_yaml(out)?;
+        }
+        ManifestFormat::Json => {
+            let mut writer = ManifestWriter::new(out, format)?;
+            for model in model_collection.models_by_depth() {
+                writer.write_model(&model.unique_id, &convert_model_to_json(model))?;
             }
-            Ok(json_models)
+            writer.finish()?;
         }
-        Err(err) => Err(format!("Error determining execution order: {}", err).into()),
     }
+    Ok(())
 }
 
 // Define all JSON output types in a proper module with public exports
 mod output_json_format {
     use serde::Serialize;
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
-    #[derive(Serialize)]
-    pub struct JsonOutput {
-        pub models: HashMap<String, JsonModel>,
-    }
+    use crate::sql_engine::references::SourceLocation;
 
     #[derive(Serialize)]
     pub struct JsonModel {
@@ -369,11 +771,20 @@ mod output_json_format {
         pub database: Option<String>,
         pub schema: Option<String>,
         pub object_name: Option<String>,
+        pub alias: Option<String>,
+        pub loaded_at_field: Option<String>,
+        pub checksum: String,
+        pub normalized_checksum: String,
         pub tags: Vec<String>,
+        /// Where each of the model's settings was set, for debugging inherited config
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        pub config_sources: BTreeMap<String, String>,
         pub columns: Vec<JsonColumn>,
         pub depends_on: Vec<String>,
         pub referenced_by: Vec<String>,
         pub external_sources: Vec<String>,
+        /// Where each dependency and external source is referenced in the compiled SQL
+        pub references: BTreeMap<String, Vec<SourceLocation>>,
         pub depth: Option<usize>,
     }
 
@@ -386,7 +797,7 @@ mod output_json_format {
 }
 
 fn convert_model_to_json(model: &SqlModel) -> output_json_format::JsonModel {
-    let columns: Vec<output_json_format::JsonColumn> = model
+    let mut columns: Vec<output_json_format::JsonColumn> = model
         .columns
         .values()
         .map(|col| output_json_format::JsonColumn {
@@ -395,6 +806,8 @@ fn convert_model_to_json(model: &SqlModel) -> output_json_format::JsonModel {
             data_type: col.data_type.clone(),
         })
         .collect();
+    // Sort for deterministic output
+    columns.sort_by(|a, b| a.name.cmp(&b.name));
 
     let mut external_sources: Vec<String> = model.get_external_sources().iter().cloned().collect();
     external_sources.sort();
@@ -411,43 +824,26 @@ fn convert_model_to_json(model: &SqlModel) -> output_json_format::JsonModel {
     output_json_format::JsonModel {
         name: model.name.clone(),
         path: model.relative_file_path.to_string_lossy().to_string(),
-        description: model.description.clone(),
+        description: model.rendered_description(),
         materialized: model.materialized.clone(),
         database: model.database.clone(),
         schema: model.schema.clone(),
         object_name: model.object_name.clone(),
+        alias: model.alias.clone(),
+        loaded_at_field: model.loaded_at_field.clone(),
+        checksum: model.checksum.clone(),
+        normalized_checksum: model.normalized_checksum.clone(),
         tags,
+        config_sources: model.config_sources.clone(),
         columns,
         depends_on,
         referenced_by,
         external_sources,
+        references: model.reference_locations.clone(),
         depth: model.depth,
     }
 }
 
-fn output_yaml_format(model_collection: &SqlModelCollection) -> ParseResult<()> {
-    let yaml = generate_yaml(model_collection)?;
-    println!("{}", yaml);
-    Ok(())
-}
-
-fn write_yaml_to_file(model_collection: &SqlModelCollection, file_path: &str) -> ParseResult<()> {
-    let yaml = generate_yaml(model_collection)?;
-    std::fs::write(file_path, yaml)?;
-    println!("Model graph data written to {}", file_path);
-    Ok(())
-}
-
-fn generate_yaml(model_collection: &SqlModelCollection) -> ParseResult<String> {
-    match model_collection.to_yaml() {
-        Ok(yaml_output) => {
-            let yaml = serde_yaml::to_string(&yaml_output)?;
-            Ok(yaml)
-        }
-        Err(err) => Err(format!("Error generating YAML: {}", err).into()),
-    }
-}
-
 fn is_imports_directory(path: &Path) -> bool {
     let has_imports_in_path = path.to_string_lossy().contains("/imports/");
     let is_imports_dir = path
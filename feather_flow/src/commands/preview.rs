@@ -0,0 +1,201 @@
+//! `ff preview`: run a model's compiled SQL and show the first rows
+//!
+//! Results are cached in the state directory, keyed by the compiled SQL and the checksums of
+//! every upstream model, so previewing an unchanged model again does not re-run the query.
+//! With `state.checksum: normalized`, reformatting the model or an upstream one keeps the
+//! cached result.
+//!
+//! With `--state <manifest>`, upstream models missing from the target database are read from
+//! the relations recorded in the manifest instead; `--favor-state` reads every upstream model
+//! from the manifest, whether or not it exists in the target.
+
+use colored::Colorize;
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+use super::project::{find_model, load_project, require_duckdb};
+use crate::adapters::{self, AccessMode, Adapter, QueryResult};
+use crate::display::status;
+use crate::sql_engine::checksum::ChecksumMode;
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+use crate::sql_engine::template::DEFAULT_SCHEMA;
+use crate::state::lock::StateLock;
+use crate::state::manifest::StateManifest;
+use crate::state::preview_cache::PreviewCache;
+use crate::state::state_dir;
+use crate::table::{Table, TableFormat};
+
+type PreviewResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+pub struct PreviewArgs<'a> {
+    pub model_path: &'a Path,
+    /// Model name or unique id
+    pub model: &'a str,
+    pub database: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    pub limit: usize,
+    pub no_cache: bool,
+    pub cache_ttl_secs: u64,
+    /// Overrides `state.lock_timeout_secs` from the project configuration
+    pub lock_timeout_secs: Option<u64>,
+    pub mode: AccessMode,
+    /// Manifest (`ff parse -f json` output) of the environment to defer to
+    pub state: Option<&'a Path>,
+    /// Defer every upstream model to the manifest, not only those missing from the target
+    pub favor_state: bool,
+    /// `table`, `csv` or `json`
+    pub format: &'a str,
+}
+
+pub fn preview_command(args: &PreviewArgs) -> PreviewResult<()> {
+    let format = TableFormat::parse(args.format)?;
+    let mut project = load_project(args.model_path, args.target, args.vars)?;
+    require_duckdb(&project.config, "ff preview")?;
+    let adapter = adapters::connect(args.database, args.mode)?;
+
+    if let Some(state) = args.state {
+        let manifest = StateManifest::load(state)?;
+        let selected = HashSet::from([find_model(&project.models, args.model)?.unique_id.clone()]);
+        let deferred = project
+            .models
+            .defer_to_state(&selected, &manifest, |upstream| {
+                args.favor_state || !relation_exists(adapter.as_ref(), upstream)
+            });
+        for id in &deferred {
+            status!(
+                "{}",
+                format!(
+                    "Deferring {} to {}",
+                    id,
+                    manifest.relation(id).unwrap_or_default()
+                )
+                .dimmed()
+            );
+        }
+    }
+
+    let model = find_model(&project.models, args.model)?;
+
+    let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    let sql = adapters::in_model_schema(adapter.as_ref(), model, preview_sql(compiled, args.limit));
+    let sql = adapters::in_model_session(adapter.as_ref(), model, sql)?;
+
+    let state_dir = state_dir(&project.root_dir(args.model_path));
+    let cache = PreviewCache::new(&state_dir, args.cache_ttl_secs);
+    let database = args
+        .database
+        .canonicalize()
+        .unwrap_or_else(|_| args.database.to_path_buf());
+    let mode = project.config.state.checksum;
+    let query = mode.checksum(&sql, &model.dialect, project.hash);
+    let upstream = upstream_checksums(&project.models, model, mode);
+
+    let mut key_parts = vec![query.as_str()];
+    let database_key = database.to_string_lossy();
+    key_parts.push(&database_key);
+    key_parts.extend(upstream.iter().map(String::as_str));
+    let key = PreviewCache::key(&key_parts, project.hash);
+
+    if !args.no_cache {
+        if let Some(cached) = cache.get(&key) {
+            print_result(&cached.result, format)?;
+            status!(
+                "{}",
+                format!(
+                    "({} row(s) from cache, {}s old; use --no-cache to re-run)",
+                    cached.result.rows.len(),
+                    (chrono::Utc::now() - cached.cached_at).num_seconds()
+                )
+                .dimmed()
+            );
+            return Ok(());
+        }
+    }
+
+    let result = adapter.query(&sql)?;
+    let lock_timeout = Duration::from_secs(
+        args.lock_timeout_secs
+            .unwrap_or(project.config.state.lock_timeout_secs),
+    );
+    let cached =
+        StateLock::acquire(&state_dir, lock_timeout).and_then(|_lock| cache.put(&key, &result));
+    if let Err(err) = cached {
+        eprintln!("{} {:#}", "Warning:".yellow(), err);
+    }
+
+    print_result(&result, format)?;
+    status!("{}", format!("({} row(s))", result.rows.len()).dimmed());
+    Ok(())
+}
+
+/// Whether `model`'s relation exists in the database behind `adapter`. Errors count as
+/// missing, so the model is deferred.
+fn relation_exists(adapter: &dyn Adapter, model: &SqlModel) -> bool {
+    adapters::relation_exists(
+        adapter,
+        model.database.as_deref(),
+        model.schema.as_deref().unwrap_or(DEFAULT_SCHEMA),
+        model.relation_name(),
+    )
+    .unwrap_or(false)
+}
+
+fn preview_sql(compiled: &str, limit: usize) -> String {
+    let query = compiled.trim().trim_end_matches(';').trim_end();
+    format!("SELECT * FROM (\n{}\n) AS preview LIMIT {}", query, limit)
+}
+
+/// `unique_id:checksum` of every model `model` reads from, directly or indirectly
+fn upstream_checksums(
+    models: &SqlModelCollection,
+    model: &SqlModel,
+    mode: ChecksumMode,
+) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut pending: Vec<&String> = model.upstream_models.iter().collect();
+
+    while let Some(id) = pending.pop() {
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        if let Some(parent) = models.get_model(id) {
+            pending.extend(parent.upstream_models.iter());
+        }
+    }
+
+    seen.into_iter()
+        .map(|id| {
+            let checksum = models
+                .get_model(&id)
+                .map(|parent| parent.checksum_for(mode))
+                .unwrap_or_default();
+            format!("{}:{}", id, checksum)
+        })
+        .collect()
+}
+
+/// Rows in the chosen format; with `--porcelain`, a header record followed by one record
+/// per row
+fn print_result(result: &QueryResult, format: TableFormat) -> PreviewResult<()> {
+    let mut table = Table::new(&result.columns).with_porcelain_headers();
+    for row in &result.rows {
+        table.push(row.iter().map(String::as_str));
+    }
+    table.print(format)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_sql_wraps_compiled_query() {
+        assert_eq!(
+            preview_sql("SELECT id FROM raw.orders -- trailing comment\n;\n", 5),
+            "SELECT * FROM (\nSELECT id FROM raw.orders -- trailing comment\n) AS preview LIMIT 5"
+        );
+    }
+}
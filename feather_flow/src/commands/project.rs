@@ -0,0 +1,341 @@
+//! Quiet project loading shared by commands other than `parse`
+
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use sqlparser::dialect::DuckDbDialect;
+use walkdir::WalkDir;
+
+use crate::config::profiles;
+use crate::config::selectors::SelectorsFile;
+use crate::config::{FeatherFlowConfig, SqlDialect, PROJECT_CONFIG_FILE};
+use crate::sql_engine::checksum::HashAlgorithm;
+use crate::sql_engine::nodes::SEEDS_DIR;
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+use crate::sql_engine::tables::TableManager;
+use crate::sql_engine::template::{TargetContext, DEFAULT_TARGET};
+use crate::state::lock::StateLock;
+use crate::state::parse_cache::ParseCache;
+use crate::state::state_dir;
+
+type ProjectResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// A project's configuration and its parsed, linked models
+pub struct LoadedProject {
+    pub config: FeatherFlowConfig,
+    pub models: SqlModelCollection,
+    /// The algorithm `state.hash` names, which every checksum and cache key is digested by
+    pub hash: &'static dyn HashAlgorithm,
+}
+
+impl LoadedProject {
+    /// Directory holding project-level files such as `tests/` and `target/`: the directory of
+    /// `featherflow_project.yaml`, or the parent of the model path without one
+    pub fn root_dir(&self, model_path: &Path) -> PathBuf {
+        project_root(&self.config, model_path)
+    }
+
+    /// The selection to apply: `select` itself, or the expression a `--selector` stands for
+    /// in the project's `selectors.yml`
+    pub fn selection(
+        &self,
+        model_path: &Path,
+        select: Option<&str>,
+        selector: Option<&str>,
+    ) -> ProjectResult<Option<String>> {
+        match selector {
+            Some(_) if select.is_some() => {
+                Err("Use either a selection expression or a selector, not both".into())
+            }
+            Some(name) => {
+                let selectors = SelectorsFile::load(&self.root_dir(model_path))?;
+                Ok(Some(
+                    selectors
+                        .expression(name)
+                        .map_err(|err| format!("{:#}", err))?,
+                ))
+            }
+            None => Ok(select.map(str::to_string)),
+        }
+    }
+}
+
+/// Project root for `config`, falling back to the parent of the model path when the project
+/// has no configuration file
+pub fn project_root(config: &FeatherFlowConfig, model_path: &Path) -> PathBuf {
+    if !config.project_root.as_os_str().is_empty() {
+        return config.project_root.clone();
+    }
+
+    model_path
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `model_path` when given, else the model directory of the project found by walking up from
+/// `start`, usually the current directory
+pub fn model_path_or_default(model_path: Option<PathBuf>, start: &Path) -> ProjectResult<PathBuf> {
+    if let Some(model_path) = model_path {
+        return Ok(model_path);
+    }
+    match FeatherFlowConfig::discover(start)? {
+        Some(config) => Ok(config.models_dir()),
+        None => Err(format!(
+            "No --model-path given and no {} found in {} or its parents",
+            PROJECT_CONFIG_FILE,
+            start.display()
+        )
+        .into()),
+    }
+}
+
+/// The database to connect to and the target to run as: `db` and `target` as given, else the
+/// database of the active target in the `profiles.yml` of the project found from `start`,
+/// and that target, so its schema overrides apply too
+pub fn resolve_connection(
+    db: Option<PathBuf>,
+    start: &Path,
+    target: Option<String>,
+) -> ProjectResult<(PathBuf, Option<String>)> {
+    if let Some(db) = db {
+        return Ok((db, target));
+    }
+    let config = FeatherFlowConfig::discover(start)?.unwrap_or_default();
+    let active = profiles::active(&config, &project_root(&config, start), target.as_deref())
+        .map_err(|err| format!("No --db given: {:#}", err))?;
+    Ok((
+        active.connection.database().to_path_buf(),
+        Some(active.target),
+    ))
+}
+
+/// Parse every model under `model_path` and build the dependency graph without printing
+/// progress. Unlike `ff parse`, any model that fails to parse is an error. Parse results are
+/// reused from the parse cache for unchanged SQL, so models restored from it have no AST;
+/// commands that inspect the statements use `load_project_with_ast`.
+pub fn load_project(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+) -> ProjectResult<LoadedProject> {
+    let config = FeatherFlowConfig::discover(model_path)?.unwrap_or_default();
+    let state_dir = state_dir(&project_root(&config, model_path));
+    let mut cache = ParseCache::load(&state_dir, config.state.hash.algorithm()?);
+    let project = load(config, model_path, target, vars, Some(&mut cache))?;
+    save_parse_cache(&cache, &state_dir, &project.config);
+    Ok(project)
+}
+
+/// Like `load_project`, parsing every model from scratch so each keeps its AST
+pub fn load_project_with_ast(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+) -> ProjectResult<LoadedProject> {
+    let config = FeatherFlowConfig::discover(model_path)?.unwrap_or_default();
+    load(config, model_path, target, vars, None)
+}
+
+/// Write the parse cache under the state lock. Failing to write it only costs the next run
+/// a full parse.
+pub fn save_parse_cache(cache: &ParseCache, state_dir: &Path, config: &FeatherFlowConfig) {
+    let lock_timeout = Duration::from_secs(config.state.lock_timeout_secs);
+    if let Err(err) = StateLock::acquire(state_dir, lock_timeout).and_then(|_lock| cache.save()) {
+        eprintln!(
+            "{} Failed to write the parse cache: {:#}",
+            "Warning:".yellow(),
+            err
+        );
+    }
+}
+
+fn load(
+    config: FeatherFlowConfig,
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    mut cache: Option<&mut ParseCache>,
+) -> ProjectResult<LoadedProject> {
+    let hash = config.state.hash.algorithm()?;
+    let target = config.target(target);
+    let target = target.as_deref();
+    let vars = config.resolve_vars(vars)?;
+    let dialect = DuckDbDialect {};
+    let dialect_name = config.dialect.as_str();
+    let mut models = SqlModelCollection::new();
+
+    for sql_file in find_model_files(model_path) {
+        let model = match cache.as_deref_mut() {
+            Some(cache) => {
+                SqlModel::from_path_with_cache(&sql_file, model_path, dialect_name, cache)
+            }
+            None => SqlModel::from_path(&sql_file, model_path, dialect_name, &dialect, hash),
+        };
+        let mut model = model.map_err(|err| format!("{}: {:#}", sql_file.display(), err))?;
+        model.extract_dependencies()?;
+        models.add_model(model);
+    }
+
+    models.apply_directory_configs(&config.models);
+    if let Some(target) = target {
+        models.apply_target(target);
+    }
+    models.load_source_definitions(model_path)?;
+    models.load_seeds(&project_root(&config, model_path).join(SEEDS_DIR))?;
+    match TableManager::load(&state_dir(&project_root(&config, model_path))) {
+        Ok(table_schemas) => models.set_table_schemas(table_schemas),
+        Err(err) => eprintln!(
+            "{} Ignoring the synced warehouse schemas: {:#}",
+            "Warning:".yellow(),
+            err
+        ),
+    }
+    let context =
+        TargetContext::new(target.unwrap_or(DEFAULT_TARGET), dialect_name).with_vars(vars);
+    match cache {
+        Some(cache) => models.compile_with_cache(&context, cache),
+        None => models.compile(&context),
+    }
+    .map_err(|err| format!("{:#}", err))?;
+
+    if let Some(error) = models.get_statement_error_report().first() {
+        return Err(error.clone().into());
+    }
+
+    models.build_dependency_graph();
+    models.rewrite_relocated_references();
+
+    if let Some(isolation) = &config.schema_isolation {
+        let suffix = isolation.suffix_for(
+            target.unwrap_or(DEFAULT_TARGET),
+            &project_root(&config, model_path),
+        )?;
+        if let Some(suffix) = suffix {
+            models.isolate_schemas(&suffix);
+        }
+    }
+
+    Ok(LoadedProject {
+        config,
+        models,
+        hash,
+    })
+}
+
+/// Refuse to run model SQL through DuckDB when the project is written for another dialect
+pub fn require_duckdb(config: &FeatherFlowConfig, command: &str) -> ProjectResult<()> {
+    if config.dialect == SqlDialect::DuckDb {
+        return Ok(());
+    }
+    Err(format!(
+        "{} runs against DuckDB, but this project's dialect is {}. Use ff compile to emit its DDL.",
+        command,
+        config.dialect.as_str()
+    )
+    .into())
+}
+
+/// Look up a model by unique id, falling back to its name
+pub fn find_model<'a>(models: &'a SqlModelCollection, name: &str) -> ProjectResult<&'a SqlModel> {
+    if let Some(model) = models.get_model(name) {
+        return Ok(model);
+    }
+
+    models
+        .get_execution_order()?
+        .into_iter()
+        .find(|model| model.name == name)
+        .ok_or_else(|| format!("Model '{}' not found", name).into())
+}
+
+fn find_model_files(model_path: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(model_path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "sql"))
+        .collect();
+
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_project_reuses_parse_results() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("models");
+        fs::create_dir_all(models.join("orders")).unwrap();
+        fs::write(
+            models.join("orders/orders.sql"),
+            "SELECT id FROM raw_orders",
+        )
+        .unwrap();
+        fs::write(
+            models.join("orders/orders.yml"),
+            "version: 2\nmodels:\n  - name: orders\n",
+        )
+        .unwrap();
+
+        let first = load_project(&models, None, None).unwrap();
+        let orders = first.models.get_model("model.orders.orders").unwrap();
+        assert!(!orders.from_parse_cache);
+        assert_eq!(orders.unqualified_tables, vec!["raw_orders".to_string()]);
+
+        let second = load_project(&models, None, None).unwrap();
+        let orders = second.models.get_model("model.orders.orders").unwrap();
+        assert!(orders.from_parse_cache);
+        assert_eq!(orders.unqualified_tables, vec!["raw_orders".to_string()]);
+
+        let fresh = load_project_with_ast(&models, None, None).unwrap();
+        let orders = fresh.models.get_model("model.orders.orders").unwrap();
+        assert!(!orders.from_parse_cache);
+        assert_eq!(orders.ast.len(), 1);
+    }
+
+    #[test]
+    fn test_project_config_locates_and_filters_models() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("sql");
+        for name in ["orders", "legacy/old_orders"] {
+            let model_dir = models.join(name);
+            fs::create_dir_all(&model_dir).unwrap();
+            let file = model_dir.file_name().unwrap().to_str().unwrap().to_string();
+            fs::write(model_dir.join(format!("{}.sql", file)), "SELECT 1 AS id").unwrap();
+        }
+        assert!(model_path_or_default(None, &models)
+            .unwrap_err()
+            .to_string()
+            .contains("No --model-path given"));
+
+        fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "name: shop\nmodels_path: sql\nmodels:\n  legacy:\n    enabled: false\n  \
+             orders:\n    schema: sales\n",
+        )
+        .unwrap();
+        let model_path = model_path_or_default(None, &models.join("orders")).unwrap();
+        assert_eq!(model_path, dir.path().canonicalize().unwrap().join("sql"));
+        assert_eq!(
+            model_path_or_default(Some(models.clone()), dir.path()).unwrap(),
+            models
+        );
+
+        let project = load_project(&model_path, None, None).unwrap();
+        let orders = project.models.get_model("model.orders.orders").unwrap();
+        assert_eq!(orders.schema.as_deref(), Some("sales"));
+        assert_eq!(orders.config_sources["schema"], "models: orders");
+        assert!(project
+            .models
+            .get_model("model.legacy.old_orders.old_orders")
+            .is_none());
+    }
+}
@@ -0,0 +1,722 @@
+//! `ff report`: reports on the project as a whole
+//!
+//! `ff report duplicates` lists pairs of models whose normalized SQL is nearly the same,
+//! typically staging models copied from one another, as candidates for consolidation.
+//!
+//! `ff report column-usage raw_data.transactions` lists each column of a source, seed or
+//! model with the downstream models using it, so never-used columns of wide tables stand out.
+//!
+//! `ff report dead-models` lists models nothing reads: no downstream model or snapshot and no
+//! exposure. Given `--query-history`, a text export of queries run against the warehouse,
+//! models whose relation appears in it are left out as still in use.
+//!
+//! `ff report policies` evaluates every governance policy from `featherflow_project.yaml`
+//! and lists each with its outcome and violations, for audit.
+//!
+//! `ff report test-coverage` lists each `require_tests` directory with its share of tested
+//! models against the threshold, and the untested models of those below it.
+//!
+//! `ff report owners` lists each model's owner, team, domain, criticality and Slack channel
+//! from its `meta`, most critical first, so unowned important models stand out.
+//!
+//! `ff report cost-estimate` flags models whose joins are likely to explode in size (cross
+//! joins, many-to-many joins without aggregation), with an estimate of their size from the
+//! row counts of the last `ff build` and the declared column types.
+//!
+//! Every report takes `--format table|csv|json`. `table` is the report as shown here; `csv`
+//! and `json` hold one row per line of the report, with the columns named by the report's
+//! `*_COLUMNS` constant in that order. Under `--porcelain`, `table` prints the same rows as
+//! tab-separated records.
+
+use colored::Colorize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use super::project::{find_model, load_project, load_project_with_ast, project_root};
+use crate::config::criticality::Criticality;
+use crate::display::{self, status};
+use crate::run::results::{RunResults, RUN_RESULTS_FILE};
+use crate::sql_engine::column_usage;
+use crate::sql_engine::cost::{self, CostEstimate};
+use crate::sql_engine::nodes::{Node, ResourceType};
+use crate::sql_engine::similarity::{self, Fingerprint};
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+use crate::table::{Table, TableFormat};
+use crate::validators::coverage;
+use crate::validators::policies::{self, PolicyStatus};
+
+type ReportResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Similarity from which `ff report duplicates` reports a pair
+pub const DEFAULT_DUPLICATE_THRESHOLD: f64 = 0.8;
+
+/// Columns of each report's CSV and JSON rows, in output order
+pub const DUPLICATES_COLUMNS: [&str; 3] = ["similarity", "first", "second"];
+pub const COLUMN_USAGE_COLUMNS: [&str; 4] = ["column", "used_by_count", "used_by", "declared"];
+pub const DEAD_MODELS_COLUMNS: [&str; 2] = ["unique_id", "path"];
+pub const POLICIES_COLUMNS: [&str; 4] = ["policy", "status", "checked", "violating_models"];
+pub const TEST_COVERAGE_COLUMNS: [&str; 6] = [
+    "path",
+    "status",
+    "required",
+    "coverage",
+    "models",
+    "untested_models",
+];
+pub const OWNERS_COLUMNS: [&str; 6] = [
+    "unique_id",
+    "owner",
+    "team",
+    "domain",
+    "criticality",
+    "slack_channel",
+];
+pub const COST_ESTIMATE_COLUMNS: [&str; 4] = ["unique_id", "rows", "bytes", "findings"];
+
+/// Whether the report is printed for reading rather than as rows of its columns
+fn readable(format: TableFormat) -> bool {
+    format == TableFormat::Table && !display::porcelain()
+}
+
+pub fn report_duplicates_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    threshold: f64,
+    format: &str,
+) -> ReportResult<()> {
+    let format = TableFormat::parse(format)?;
+    if threshold.is_nan() || threshold <= 0.0 || threshold > 1.0 {
+        return Err(format!(
+            "--threshold must be greater than 0 and at most 1, got {}",
+            threshold
+        )
+        .into());
+    }
+
+    let project = load_project_with_ast(model_path, target, vars)?;
+
+    let mut fingerprints = Vec::new();
+    for model in project.models.get_execution_order()? {
+        match Fingerprint::of_statements(&model.ast) {
+            Ok(Some(fingerprint)) => fingerprints.push((model.unique_id.clone(), fingerprint)),
+            Ok(None) => {}
+            Err(err) => eprintln!("Warning: skipping {}: {:#}", model.unique_id, err),
+        }
+    }
+
+    let pairs = similarity::similar_pairs(&fingerprints, threshold);
+    if readable(format) {
+        for pair in &pairs {
+            let percent = format!("{:.0}%", pair.similarity * 100.0);
+            println!("{:>4}  {}  {}", percent, pair.first, pair.second);
+        }
+    } else {
+        let mut table = Table::new(DUPLICATES_COLUMNS);
+        for pair in &pairs {
+            table.push([
+                format!("{:.3}", pair.similarity),
+                pair.first.clone(),
+                pair.second.clone(),
+            ]);
+        }
+        table.print(format)?;
+    }
+
+    status!(
+        "{} similar pair(s) among {} model(s) at {:.0}% similarity or more",
+        pairs.len(),
+        fingerprints.len(),
+        threshold * 100.0
+    );
+    Ok(())
+}
+
+pub fn report_column_usage_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    node: &str,
+    format: &str,
+) -> ReportResult<()> {
+    let format = TableFormat::parse(format)?;
+    let project = load_project(model_path, target, vars)?;
+    let node_id = find_node_id(&project.models, node)?;
+    let usage = column_usage::column_usage(&project.models, &node_id)?;
+    if usage.is_empty() {
+        return Err(format!(
+            "No columns known for {}. Declare them under 'columns:' in its YAML.",
+            node_id
+        )
+        .into());
+    }
+
+    let width = usage
+        .iter()
+        .map(|entry| entry.column.len())
+        .max()
+        .unwrap_or(0);
+    let mut table = Table::new(COLUMN_USAGE_COLUMNS);
+    for entry in &usage {
+        let used_by: Vec<&str> = entry.used_by.iter().map(String::as_str).collect();
+        if !readable(format) {
+            table.push([
+                entry.column.clone(),
+                used_by.len().to_string(),
+                used_by.join(","),
+                entry.declared.to_string(),
+            ]);
+            continue;
+        }
+
+        let models = if used_by.is_empty() {
+            "never used".yellow().to_string()
+        } else {
+            used_by.join(", ")
+        };
+        let mut line = format!(
+            "{:<width$}  {:>3}  {}",
+            entry.column,
+            used_by.len(),
+            models,
+            width = width
+        );
+        if !entry.declared {
+            line.push_str(&format!(" {}", "(not declared)".dimmed()));
+        }
+        println!("{}", line);
+    }
+    if !readable(format) {
+        table.print(format)?;
+    }
+
+    let unused = usage
+        .iter()
+        .filter(|entry| entry.used_by.is_empty())
+        .count();
+    status!(
+        "{} of {} column(s) of {} never used downstream",
+        unused,
+        usage.len(),
+        node_id
+    );
+    Ok(())
+}
+
+/// Unique id of the model (name or unique id), source (`schema.table`) or seed named `name`
+fn find_node_id(models: &SqlModelCollection, name: &str) -> ReportResult<String> {
+    if models.get_node(name).is_some() {
+        return Ok(name.to_string());
+    }
+    if let Ok(model) = find_model(models, name) {
+        return Ok(model.unique_id.clone());
+    }
+
+    models
+        .nodes()
+        .find(|node| match node {
+            Node::Source(source) => source.relation.eq_ignore_ascii_case(name),
+            Node::Seed(seed) => seed.name.eq_ignore_ascii_case(name),
+            _ => false,
+        })
+        .map(|node| node.unique_id().to_string())
+        .ok_or_else(|| format!("No model, source or seed named '{}'", name).into())
+}
+
+pub fn report_dead_models_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    query_history: Option<&Path>,
+    format: &str,
+) -> ReportResult<()> {
+    let format = TableFormat::parse(format)?;
+    let history = query_history
+        .map(|path| {
+            fs::read_to_string(path)
+                .map(|content| history_words(&content))
+                .map_err(|err| format!("Failed to read {}: {}", path.display(), err))
+        })
+        .transpose()?;
+
+    let project = load_project(model_path, target, vars)?;
+    let terminal = terminal_models(&project.models);
+    let (queried, dead): (Vec<&SqlModel>, Vec<&SqlModel>) = terminal.iter().partition(|model| {
+        history
+            .as_ref()
+            .is_some_and(|words| words.contains(&model.relation_name().to_lowercase()))
+    });
+
+    if readable(format) {
+        for model in &dead {
+            let path = model.relative_file_path.to_string_lossy();
+            println!("{}  {}", model.unique_id, path.dimmed());
+        }
+    } else {
+        let mut table = Table::new(DEAD_MODELS_COLUMNS);
+        for model in &dead {
+            table.push([
+                model.unique_id.clone(),
+                model.relative_file_path.to_string_lossy().to_string(),
+            ]);
+        }
+        table.print(format)?;
+    }
+
+    status!(
+        "{} of {} model(s) have no downstream models or exposures",
+        terminal.len(),
+        project.models.get_execution_order()?.len()
+    );
+    if history.is_some() {
+        status!(
+            "{} of them appear in the query history, leaving {} candidate(s) for deletion",
+            queried.len(),
+            dead.len()
+        );
+    }
+    Ok(())
+}
+
+pub fn report_policies_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    format: &str,
+) -> ReportResult<()> {
+    let format = TableFormat::parse(format)?;
+    let project = load_project(model_path, target, vars)?;
+    if project.config.policies.is_empty() {
+        status!("No policies defined. Declare them under 'policies:' in featherflow_project.yaml.");
+        if !readable(format) {
+            Table::new(POLICIES_COLUMNS).print(format)?;
+        }
+        return Ok(());
+    }
+
+    let results = policies::evaluate(&project.config.policies, &project.models);
+    let mut table = Table::new(POLICIES_COLUMNS);
+    for result in &results {
+        let status = result.status();
+        if !readable(format) {
+            let mut models: Vec<&str> = result
+                .violations
+                .iter()
+                .map(|violation| violation.model_id.as_str())
+                .collect();
+            models.dedup();
+            table.push([
+                result.name.clone(),
+                status.as_str().to_string(),
+                result.checked.to_string(),
+                models.join(","),
+            ]);
+            continue;
+        }
+
+        let label = match status {
+            PolicyStatus::Pass => "PASS".green(),
+            PolicyStatus::Warn => "WARN".yellow(),
+            PolicyStatus::Fail => "FAIL".red(),
+        };
+        println!(
+            "{}  {} ({} model(s) checked)",
+            label,
+            result.name.bold(),
+            result.checked
+        );
+        for violation in &result.violations {
+            println!(
+                "      {} {}",
+                violation.path.display().to_string().dimmed(),
+                violation.message
+            );
+        }
+    }
+    if !readable(format) {
+        table.print(format)?;
+    }
+
+    let count = |wanted: PolicyStatus| {
+        results
+            .iter()
+            .filter(|result| result.status() == wanted)
+            .count()
+    };
+    let failed = count(PolicyStatus::Fail);
+    status!(
+        "{} policy(ies): {} passed, {} warned, {} failed",
+        results.len(),
+        count(PolicyStatus::Pass),
+        count(PolicyStatus::Warn),
+        failed
+    );
+
+    if failed > 0 {
+        return Err(format!("{} policy(ies) failed", failed).into());
+    }
+    Ok(())
+}
+
+pub fn report_test_coverage_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    format: &str,
+) -> ReportResult<()> {
+    let format = TableFormat::parse(format)?;
+    let project = load_project(model_path, target, vars)?;
+    if project.config.require_tests.is_empty() {
+        status!("No thresholds defined. Declare them under 'require_tests:' in featherflow_project.yaml.");
+        if !readable(format) {
+            Table::new(TEST_COVERAGE_COLUMNS).print(format)?;
+        }
+        return Ok(());
+    }
+
+    let results = coverage::evaluate(&project.config.require_tests, &project.models)?;
+    let mut table = Table::new(TEST_COVERAGE_COLUMNS);
+    for result in &results {
+        let status = if result.passes() { "pass" } else { "fail" };
+        if !readable(format) {
+            table.push([
+                result.path.clone(),
+                status.to_string(),
+                format!("{:.2}", result.required),
+                format!("{:.2}", result.coverage()),
+                result.models.to_string(),
+                result
+                    .untested
+                    .iter()
+                    .map(|model| model.model_id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ]);
+            continue;
+        }
+
+        let label = if result.passes() {
+            "PASS".green()
+        } else {
+            "FAIL".red()
+        };
+        println!(
+            "{}  {} {:.0}% tested ({} of {} models), requires {:.0}%",
+            label,
+            result.path.bold(),
+            result.coverage() * 100.0,
+            result.tested(),
+            result.models,
+            result.required * 100.0
+        );
+        if result.passes() {
+            continue;
+        }
+        for model in &result.untested {
+            println!(
+                "      {} has no tests",
+                model.path.display().to_string().dimmed()
+            );
+        }
+    }
+    if !readable(format) {
+        table.print(format)?;
+    }
+
+    let failed = results.iter().filter(|result| !result.passes()).count();
+    status!(
+        "{} directory(ies): {} passed, {} below require_tests",
+        results.len(),
+        results.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        return Err(format!(
+            "Test coverage is below require_tests in {} directory(ies)",
+            failed
+        )
+        .into());
+    }
+    Ok(())
+}
+
+pub fn report_owners_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    format: &str,
+) -> ReportResult<()> {
+    let format = TableFormat::parse(format)?;
+    let project = load_project(model_path, target, vars)?;
+    let mut models = project.models.get_execution_order()?;
+    models.sort_by(|a, b| {
+        let tier = |model: &SqlModel| (model.criticality().is_none(), model.criticality());
+        tier(a)
+            .cmp(&tier(b))
+            .then_with(|| a.owner().cmp(&b.owner()))
+            .then_with(|| a.unique_id.cmp(&b.unique_id))
+    });
+
+    let width = models
+        .iter()
+        .map(|model| model.unique_id.len())
+        .max()
+        .unwrap_or(0);
+    let mut table = Table::new(OWNERS_COLUMNS);
+    for model in &models {
+        if !readable(format) {
+            table.push([
+                model.unique_id.as_str(),
+                model.owner().unwrap_or(""),
+                model.team().unwrap_or(""),
+                model.domain().unwrap_or(""),
+                model.criticality().map_or("", Criticality::as_str),
+                model.slack_channel().unwrap_or(""),
+            ]);
+            continue;
+        }
+
+        let criticality = model.criticality().map_or("-", Criticality::as_str);
+        let owner = match model.owner() {
+            Some(owner) => owner.normal(),
+            None => "no owner".red(),
+        };
+        let details: Vec<&str> = [model.team(), model.domain(), model.slack_channel()]
+            .into_iter()
+            .flatten()
+            .collect();
+        println!(
+            "{:<width$}  {:<8}  {}  {}",
+            model.unique_id,
+            criticality,
+            owner,
+            details.join(", ").dimmed(),
+            width = width
+        );
+    }
+    if !readable(format) {
+        table.print(format)?;
+    }
+
+    let unowned = models.iter().filter(|model| model.owner().is_none());
+    let important_unowned = unowned
+        .clone()
+        .filter(|model| model.criticality() == Some(Criticality::P1))
+        .count();
+    status!(
+        "{} model(s): {} without an owner ({} of them p1), {} without a criticality",
+        models.len(),
+        unowned.count(),
+        important_unowned,
+        models
+            .iter()
+            .filter(|model| model.criticality().is_none())
+            .count()
+    );
+    Ok(())
+}
+
+pub fn report_cost_estimate_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    run_results: Option<&Path>,
+    format: &str,
+) -> ReportResult<()> {
+    let format = TableFormat::parse(format)?;
+    let project = load_project_with_ast(model_path, target, vars)?;
+    let results_path = run_results.map_or_else(
+        || project_root(&project.config, model_path).join(RUN_RESULTS_FILE),
+        Path::to_path_buf,
+    );
+    let row_counts: HashMap<String, u64> = if results_path.exists() {
+        RunResults::load(&results_path)?
+            .results
+            .into_iter()
+            .filter_map(|result| Some((result.unique_id, result.rows?)))
+            .collect()
+    } else {
+        status!(
+            "No run results at {}; run ff build to record row counts for size estimates",
+            results_path.display()
+        );
+        HashMap::new()
+    };
+
+    let estimates = cost::estimate_costs(&project.models, &row_counts);
+    let mut flagged: Vec<&CostEstimate> = estimates
+        .iter()
+        .filter(|estimate| !estimate.risky_joins.is_empty())
+        .collect();
+    // Largest first; unknown sizes last
+    flagged.sort_by(|a, b| {
+        b.bytes()
+            .cmp(&a.bytes())
+            .then(b.rows.cmp(&a.rows))
+            .then(a.model_id.cmp(&b.model_id))
+    });
+
+    let mut table = Table::new(COST_ESTIMATE_COLUMNS);
+    for estimate in &flagged {
+        let findings: Vec<String> = estimate
+            .risky_joins
+            .iter()
+            .map(|join| join.describe())
+            .collect();
+        if !readable(format) {
+            table.push([
+                estimate.model_id.clone(),
+                estimate
+                    .rows
+                    .map(|rows| rows.to_string())
+                    .unwrap_or_default(),
+                estimate
+                    .bytes()
+                    .map(|bytes| bytes.to_string())
+                    .unwrap_or_default(),
+                findings.join("; "),
+            ]);
+            continue;
+        }
+
+        let size = match (estimate.rows, estimate.bytes()) {
+            (Some(rows), Some(bytes)) => {
+                format!("up to {} rows, {}", format_count(rows), format_bytes(bytes))
+            }
+            (Some(rows), None) => format!("up to {} rows", format_count(rows)),
+            (None, _) => "size unknown".to_string(),
+        };
+        println!("{}  {}", estimate.model_id.bold(), size.yellow());
+        for finding in findings {
+            println!("      {}", finding);
+        }
+    }
+    if !readable(format) {
+        table.print(format)?;
+    }
+
+    status!(
+        "{} of {} model(s) have joins likely to explode in size; row counts known for {} node(s)",
+        flagged.len(),
+        estimates.len(),
+        row_counts.len()
+    );
+    Ok(())
+}
+
+/// `1234567` as `1.2M`
+fn format_count(count: u64) -> String {
+    scaled(count, 1000.0, &["", "K", "M", "B", "T"])
+}
+
+/// `1234567` as `1.2 MB`
+fn format_bytes(bytes: u64) -> String {
+    scaled(bytes, 1024.0, &[" B", " KB", " MB", " GB", " TB", " PB"])
+}
+
+fn scaled(value: u64, step: f64, units: &[&str]) -> String {
+    let mut scaled = value as f64;
+    let mut unit = 0;
+    while scaled >= step && unit + 1 < units.len() {
+        scaled /= step;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", value, units[0])
+    } else {
+        format!("{:.1}{}", scaled, units[unit])
+    }
+}
+
+/// Models (not snapshots, which are terminal by design) that no model, snapshot or exposure
+/// depends on, ordered by unique id
+fn terminal_models(models: &SqlModelCollection) -> Vec<&SqlModel> {
+    let exposed: BTreeSet<String> = models
+        .nodes()
+        .filter(|node| node.resource_type() == ResourceType::Exposure)
+        .flat_map(|exposure| models.node_dependencies(exposure.unique_id()))
+        .collect();
+
+    let mut terminal: Vec<&SqlModel> = models
+        .nodes()
+        .filter_map(|node| match node {
+            Node::Model(model) => Some(model),
+            _ => None,
+        })
+        .filter(|model| model.downstream_models.is_empty() && !exposed.contains(&model.unique_id))
+        .collect();
+    terminal.sort_by(|a, b| a.unique_id.cmp(&b.unique_id));
+    terminal
+}
+
+/// Every identifier-like word in a query history export, lowercased
+fn history_words(history: &str) -> HashSet<String> {
+    history
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use sqlparser::dialect::DuckDbDialect;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_format_sizes() {
+        assert_eq!(format_count(950), "950");
+        assert_eq!(format_count(1_250_000), "1.2M");
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_history_words() {
+        let words = history_words(
+            "2024-05-01,alice,\"SELECT * FROM analytics.\"\"fct_Orders\"\" LIMIT 10\"",
+        );
+        assert!(words.contains("fct_orders"));
+        assert!(words.contains("analytics"));
+        assert!(!words.contains("fct"));
+    }
+
+    #[test]
+    fn test_terminal_models_skip_exposed_and_read_models() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let mut collection = SqlModelCollection::new();
+        for (name, sql) in [
+            ("stg_orders", "SELECT 1 AS id"),
+            ("fct_orders", "SELECT id FROM public.stg_orders"),
+            ("old_report", "SELECT id FROM public.stg_orders"),
+        ] {
+            let dir = root.join(name);
+            fs::create_dir(&dir).unwrap();
+            let file = dir.join(format!("{}.sql", name));
+            fs::write(&file, sql).unwrap();
+            let mut model =
+                SqlModel::from_path(&file, root, "duckdb", &DuckDbDialect {}, &Sha256Hash).unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+        let imports_dir = root.join("models").join("imports");
+        fs::create_dir_all(&imports_dir).unwrap();
+        fs::write(
+            imports_dir.join("exposures.yml"),
+            "version: 2\nexposures:\n  - name: revenue_dashboard\n    depends_on: [fct_orders]\n",
+        )
+        .unwrap();
+        collection.load_source_definitions(root).unwrap();
+        collection.build_dependency_graph();
+
+        let ids: Vec<&str> = terminal_models(&collection)
+            .into_iter()
+            .map(|model| model.unique_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["model.old_report.old_report"]);
+    }
+}
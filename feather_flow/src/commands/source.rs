@@ -0,0 +1,137 @@
+//! `ff source freshness`: how recently each source table was loaded
+//!
+//! Queries the latest `loaded_at_field` value of every selected source table that declares
+//! one and compares its age with the table's `freshness:` thresholds, like dbt's
+//! `source freshness`. Selected models and snapshots that declare a `loaded_at_field` are
+//! queried too, to show when they were last updated; they have no thresholds. Results are
+//! printed as a table and written to `target/sources.json`. Stale tables past `warn_after`
+//! only warn; the command fails when any table is past `error_after` or could not be
+//! checked.
+
+use colored::Color;
+use std::path::Path;
+
+use super::project::{load_project, project_root, require_duckdb};
+use crate::adapters::{self, AccessMode};
+use crate::config::freshness::FreshnessStatus;
+use crate::display::status;
+use crate::run::freshness::{self, FreshnessResults, SourceFreshness, SOURCES_FILE};
+use crate::run::plan::BuildPlan;
+use crate::run::selection::{self, Selector};
+use crate::sql_engine::nodes::Node;
+use crate::table::{Cell, Table, TableFormat};
+
+type SourceResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Columns of `ff source freshness`, in output order
+pub const FRESHNESS_COLUMNS: [&str; 7] = [
+    "unique_id",
+    "status",
+    "max_loaded_at",
+    "age_seconds",
+    "warn_after",
+    "error_after",
+    "message",
+];
+
+pub struct FreshnessArgs<'a> {
+    pub model_path: &'a Path,
+    pub database: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    pub select: Option<&'a str>,
+    pub exclude: Option<&'a str>,
+    pub format: &'a str,
+    pub mode: AccessMode,
+}
+
+pub fn source_freshness_command(args: &FreshnessArgs) -> SourceResult<()> {
+    let format = TableFormat::parse(args.format)?;
+    let project = load_project(args.model_path, args.target, args.vars)?;
+    require_duckdb(&project.config, "ff source freshness")?;
+    let select = args.select.map(Selector::parse).transpose()?;
+    let exclude = args.exclude.map(Selector::parse).transpose()?;
+    let root = project_root(&project.config, args.model_path);
+
+    let plan = BuildPlan::new(&project.models);
+    let selected = selection::resolve(&plan, select.as_ref(), exclude.as_ref());
+    let checked: Vec<&Node> = selected
+        .iter()
+        .filter_map(|id| plan.get(id))
+        .map(|node| node.node)
+        .filter(|node| match node {
+            Node::Source(source) => source.loaded_at_field.is_some(),
+            Node::Model(model) | Node::Snapshot(model) => model.loaded_at_field.is_some(),
+            _ => false,
+        })
+        .collect();
+    if checked.is_empty() {
+        return Err(
+            "No selected source or model declares a loaded_at_field. Set one on the source or table in models/imports/, or in the model's YAML."
+                .into(),
+        );
+    }
+
+    let adapter = adapters::connect(args.database, args.mode)?;
+    let results: Vec<SourceFreshness> = checked
+        .into_iter()
+        .filter_map(|node| match node {
+            Node::Source(source) => freshness::check_source(adapter.as_ref(), source),
+            Node::Model(model) | Node::Snapshot(model) => {
+                freshness::check_model(adapter.as_ref(), model)
+            }
+            _ => None,
+        })
+        .collect();
+
+    freshness_table(&results).print(format)?;
+    FreshnessResults {
+        generated_at: chrono::Utc::now(),
+        results: results.clone(),
+    }
+    .save(&root.join(SOURCES_FILE))?;
+
+    let count = |status: FreshnessStatus| {
+        results
+            .iter()
+            .filter(|result| result.status == status)
+            .count()
+    };
+    let failed = count(FreshnessStatus::Error) + count(FreshnessStatus::RuntimeError);
+    status!(
+        "\n{} passed, {} warned, {} failed",
+        count(FreshnessStatus::Pass),
+        count(FreshnessStatus::Warn),
+        failed
+    );
+    if failed > 0 {
+        return Err(format!(
+            "{} source(s) or model(s) are stale or could not be checked",
+            failed
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn freshness_table(results: &[SourceFreshness]) -> Table {
+    let mut table = Table::new(FRESHNESS_COLUMNS);
+    for result in results {
+        let color = match result.status {
+            FreshnessStatus::Pass => Color::Green,
+            FreshnessStatus::Warn => Color::Yellow,
+            FreshnessStatus::Error | FreshnessStatus::RuntimeError => Color::Red,
+        };
+        let text = |value: Option<String>| Cell::new(value.unwrap_or_default());
+        table.push([
+            Cell::new(result.unique_id.clone()),
+            Cell::colored(result.status.as_str(), color),
+            text(result.max_loaded_at.clone()),
+            text(result.age_seconds.map(|age| age.to_string())),
+            text(result.warn_after.as_ref().map(ToString::to_string)),
+            text(result.error_after.as_ref().map(ToString::to_string)),
+            text(result.message.clone()),
+        ]);
+    }
+    table
+}
@@ -0,0 +1,319 @@
+//! `ff suggest tests`: tests the models' key columns are probably missing
+//!
+//! Each model's probable key gets `unique` and `not_null`, and columns matching another
+//! model's key get `relationships` (see `sql_engine::keys` for how keys are found). The
+//! suggestions are printed as YAML to paste into each model's YAML file, with why each
+//! column was picked as a comment. `--apply` adds them to those files in place instead,
+//! through the `FileWriter`, keeping their comments and formatting.
+
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::project::{load_project_with_ast, project_root};
+use super::writer::{FileWriter, WriteOptions};
+use crate::display::{self, status};
+use crate::run::plan::BuildPlan;
+use crate::run::selection::{self, Selector};
+use crate::sql_engine::keys::{self, SuggestedTest, TestSuggestion};
+use crate::sql_engine::sql_model::SqlModel;
+use crate::yaml_edit::{Step, YamlDocument};
+
+type SuggestResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+pub struct SuggestTestsArgs<'a> {
+    pub model_path: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    pub select: Option<&'a str>,
+    pub exclude: Option<&'a str>,
+    /// Write the tests into the models' YAML files instead of printing them
+    pub apply: bool,
+    pub options: WriteOptions,
+}
+
+pub fn suggest_tests_command(args: &SuggestTestsArgs) -> SuggestResult<()> {
+    let project = load_project_with_ast(args.model_path, args.target, args.vars)?;
+    let select = args.select.map(Selector::parse).transpose()?;
+    let exclude = args.exclude.map(Selector::parse).transpose()?;
+    let plan = BuildPlan::new(&project.models);
+    let selected = selection::resolve(&plan, select.as_ref(), exclude.as_ref());
+
+    // Keys are inferred over the whole project so relationships can point outside the
+    // selection
+    let mut by_model: BTreeMap<&str, Vec<TestSuggestion>> = BTreeMap::new();
+    for suggestion in keys::suggest_tests(&project.models) {
+        if !selected.contains(&suggestion.model_id) {
+            continue;
+        }
+        let Some(model) = project.models.get_model(&suggestion.model_id) else {
+            continue;
+        };
+        by_model
+            .entry(&model.unique_id)
+            .or_default()
+            .push(suggestion);
+    }
+    if by_model.is_empty() {
+        status!("No tests to suggest: every key column found is already tested.");
+        return Ok(());
+    }
+    let count: usize = by_model.values().map(Vec::len).sum();
+
+    let root = project_root(&project.config, args.model_path);
+    if args.apply {
+        let mut writer = FileWriter::new(&root, args.options);
+        for (id, suggestions) in &by_model {
+            let model = project.models.get_model(id).expect("suggested for a model");
+            writer.update_yaml(&yaml_path(model, &root), |doc| {
+                add_tests(doc, &model.name, suggestions)
+            })?;
+        }
+        writer.finish()?;
+        if !args.options.dry_run {
+            status!("Added {} test(s) to {} model(s).", count, by_model.len());
+        }
+        return Ok(());
+    }
+
+    for (id, suggestions) in &by_model {
+        if display::porcelain() {
+            for suggestion in suggestions {
+                display::record(&[
+                    id,
+                    &suggestion.column,
+                    suggestion.test.name(),
+                    &suggestion.reason,
+                ]);
+            }
+            continue;
+        }
+        let model = project.models.get_model(id).expect("suggested for a model");
+        println!("# {}", yaml_path(model, &root).display());
+        print!("{}", model_yaml(&model.name, suggestions));
+        println!();
+    }
+    status!(
+        "{} test(s) suggested for {} model(s). Add them with --apply.",
+        count,
+        by_model.len()
+    );
+    Ok(())
+}
+
+/// The model's YAML file, relative to the project root when it is inside it
+fn yaml_path(model: &SqlModel, root: &Path) -> PathBuf {
+    let path = model.parent_dir.join(format!("{}.yml", model.name));
+    let absolute = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    match absolute(&model.parent_dir).strip_prefix(absolute(root)) {
+        Ok(dir) => dir.join(format!("{}.yml", model.name)),
+        Err(_) => path,
+    }
+}
+
+/// Suggestions of one model grouped by column, in the order first suggested
+fn by_column(suggestions: &[TestSuggestion]) -> Vec<(&str, Vec<&TestSuggestion>)> {
+    let mut columns: Vec<(&str, Vec<&TestSuggestion>)> = Vec::new();
+    for suggestion in suggestions {
+        match columns
+            .iter_mut()
+            .find(|(column, _)| *column == suggestion.column)
+        {
+            Some((_, tests)) => tests.push(suggestion),
+            None => columns.push((&suggestion.column, vec![suggestion])),
+        }
+    }
+    columns
+}
+
+/// Ready-to-paste `models:` entry holding the suggested tests, each column commented with
+/// why it was picked
+fn model_yaml(model: &str, suggestions: &[TestSuggestion]) -> String {
+    let mut yaml = format!("models:\n  - name: {}\n    columns:\n", model);
+    for (column, tests) in by_column(suggestions) {
+        let mut reasons: Vec<&str> = tests.iter().map(|test| test.reason.as_str()).collect();
+        reasons.dedup();
+        yaml.push_str(&format!(
+            "      - name: {}  # {}\n        tests:\n",
+            column,
+            reasons.join("; ")
+        ));
+        for test in tests {
+            match &test.test {
+                SuggestedTest::Relationships { to, field } => yaml.push_str(&format!(
+                    "          - relationships:\n              to: ref('{}')\n              field: {}\n",
+                    to, field
+                )),
+                other => yaml.push_str(&format!("          - {}\n", other.name())),
+            }
+        }
+    }
+    yaml
+}
+
+/// Add the suggested tests to the model's entry in `doc`, adding the entry or its columns
+/// when missing
+fn add_tests(
+    doc: &mut YamlDocument,
+    model: &str,
+    suggestions: &[TestSuggestion],
+) -> anyhow::Result<()> {
+    let existing: Value = serde_yaml::from_str(&doc.to_string())?;
+    if existing.get("version").is_none() {
+        doc.set(&[], "version", &2)?;
+    }
+    let entry = existing
+        .get("models")
+        .and_then(Value::as_sequence)
+        .and_then(|models| models.iter().find(|entry| name_of(entry) == Some(model)));
+    let Some(entry) = entry else {
+        let columns: Vec<Value> = by_column(suggestions)
+            .into_iter()
+            .map(|(column, tests)| column_entry(column, &tests))
+            .collect();
+        let mut new_entry = serde_yaml::Mapping::new();
+        new_entry.insert("name".into(), model.into());
+        new_entry.insert("columns".into(), columns.into());
+        return doc.append(&[], "models", &new_entry);
+    };
+
+    let documented = entry.get("columns").and_then(Value::as_sequence);
+    for (column, tests) in by_column(suggestions) {
+        let column_yaml = documented.and_then(|columns| {
+            columns
+                .iter()
+                .find(|entry| name_of(entry).is_some_and(|name| name.eq_ignore_ascii_case(column)))
+        });
+        let Some(column_yaml) = column_yaml else {
+            doc.append(
+                &[Step::Key("models"), Step::Named(model)],
+                "columns",
+                &column_entry(column, &tests),
+            )?;
+            continue;
+        };
+
+        let name = name_of(column_yaml).unwrap_or(column);
+        let path = [
+            Step::Key("models"),
+            Step::Named(model),
+            Step::Key("columns"),
+            Step::Named(name),
+        ];
+        let mut declared = column_yaml
+            .get("tests")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        if tests.iter().any(|test| !test.test.to_yaml().is_string()) && !declared.is_empty() {
+            // A flow sequence such as `[unique]` only takes scalars, so the list is rewritten
+            declared.extend(tests.iter().map(|test| test.test.to_yaml()));
+            doc.set(&path, "tests", &declared)?;
+        } else {
+            for test in tests {
+                doc.append(&path, "tests", &test.test.to_yaml())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn name_of(entry: &Value) -> Option<&str> {
+    entry.get("name").and_then(Value::as_str)
+}
+
+fn column_entry(column: &str, tests: &[&TestSuggestion]) -> Value {
+    let mut entry = serde_yaml::Mapping::new();
+    entry.insert("name".into(), column.into());
+    entry.insert(
+        "tests".into(),
+        tests
+            .iter()
+            .map(|test| test.test.to_yaml())
+            .collect::<Vec<_>>()
+            .into(),
+    );
+    entry.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(column: &str, test: SuggestedTest, reason: &str) -> TestSuggestion {
+        TestSuggestion {
+            model_id: "model.orders.orders".to_string(),
+            column: column.to_string(),
+            test,
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_adds_suggestions_to_yaml() {
+        let suggestions = vec![
+            suggestion("order_id", SuggestedTest::Unique, "named after the model"),
+            suggestion("order_id", SuggestedTest::NotNull, "named after the model"),
+            suggestion(
+                "customer_id",
+                SuggestedTest::Relationships {
+                    to: "customers".to_string(),
+                    field: "customer_id".to_string(),
+                },
+                "matches customers.customer_id, the key of customers",
+            ),
+        ];
+        assert_eq!(
+            model_yaml("orders", &suggestions),
+            "models:
+  - name: orders
+    columns:
+      - name: order_id  # named after the model
+        tests:
+          - unique
+          - not_null
+      - name: customer_id  # matches customers.customer_id, the key of customers
+        tests:
+          - relationships:
+              to: ref('customers')
+              field: customer_id
+"
+        );
+
+        let mut doc = YamlDocument::parse(
+            "version: 2
+models:
+  - name: orders
+    description: Orders  # kept
+    columns:
+      - name: order_id
+        tests: [unique]
+      - name: customer_id
+        tests: [not_null]
+",
+        )
+        .unwrap();
+        add_tests(&mut doc, "orders", &suggestions[1..]).unwrap();
+        let written: Value = serde_yaml::from_str(&doc.to_string()).unwrap();
+        let columns = &written["models"][0]["columns"];
+        assert_eq!(
+            serde_yaml::to_string(&columns[0]["tests"]).unwrap(),
+            "- unique\n- not_null\n"
+        );
+        assert_eq!(
+            serde_yaml::to_string(&columns[1]["tests"]).unwrap(),
+            "- not_null\n- relationships:\n    to: ref('customers')\n    field: customer_id\n"
+        );
+        assert!(doc.to_string().contains("description: Orders  # kept"));
+
+        let mut empty = YamlDocument::parse("").unwrap();
+        add_tests(&mut empty, "orders", &suggestions[..1]).unwrap();
+        let written: Value = serde_yaml::from_str(&empty.to_string()).unwrap();
+        assert_eq!(written["version"], Value::from(2));
+        assert_eq!(written["models"][0]["name"], Value::from("orders"));
+        assert_eq!(
+            written["models"][0]["columns"][0]["tests"][0],
+            Value::from("unique")
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! `ff sync-schemas`: record the warehouse columns of the project's relations
+//!
+//! Reads `information_schema.columns` for the schemas of every source table, model and
+//! snapshot in one query and records each relation found in `target/state/table_schemas.json`
+//! (see `sql_engine::tables`). Relations the warehouse does not have, such as models not built
+//! yet, are listed and any schema recorded for them earlier is dropped.
+
+use chrono::Utc;
+use colored::Colorize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::Duration;
+
+use super::project::{load_project, require_duckdb};
+use crate::adapters::{self, AccessMode, SchemaColumn};
+use crate::display::status;
+use crate::sql_engine::nodes::Node;
+use crate::sql_engine::sql_model::SqlModelCollection;
+use crate::sql_engine::tables::{TableManager, TableSchema};
+use crate::sql_engine::template::DEFAULT_SCHEMA;
+use crate::state::lock::StateLock;
+use crate::state::state_dir;
+
+type SyncResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+pub struct SyncSchemasArgs<'a> {
+    pub model_path: &'a Path,
+    pub database: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+}
+
+/// Where a relation lives: database (when qualified), schema and name
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Location {
+    database: Option<String>,
+    schema: String,
+    name: String,
+}
+
+pub fn sync_schemas_command(args: &SyncSchemasArgs) -> SyncResult<()> {
+    let project = load_project(args.model_path, args.target, args.vars)?;
+    require_duckdb(&project.config, "ff sync-schemas")?;
+
+    let relations = relations(&project.models);
+    let schemas: BTreeSet<String> = relations
+        .values()
+        .map(|location| location.schema.clone())
+        .collect();
+    // Only metadata is read, whatever --read-only says
+    let adapter = adapters::connect(args.database, AccessMode::ReadOnly)?;
+    let columns = adapters::schema_columns(adapter.as_ref(), &schemas)?;
+
+    let mut tables = project.models.table_schemas().clone();
+    let missing = sync(&mut tables, &relations, &columns);
+    tables.synced_at = Some(Utc::now());
+
+    let state_dir = state_dir(&project.root_dir(args.model_path));
+    let lock_timeout = Duration::from_secs(project.config.state.lock_timeout_secs);
+    let _lock = StateLock::acquire(&state_dir, lock_timeout)?;
+    tables.save(&state_dir)?;
+
+    status!(
+        "Synced {} of {} relation(s) to {}",
+        relations.len() - missing.len(),
+        relations.len(),
+        TableManager::path(&state_dir).display()
+    );
+    if !missing.is_empty() {
+        eprintln!(
+            "{} {} relation(s) are not in the warehouse and were not synced:",
+            "Warning:".yellow(),
+            missing.len()
+        );
+        for relation in &missing {
+            eprintln!("   - {}", relation);
+        }
+    }
+    Ok(())
+}
+
+/// Relation of every source table, model and snapshot, by the name models reference it by
+fn relations(models: &SqlModelCollection) -> BTreeMap<String, Location> {
+    let mut relations = BTreeMap::new();
+    for node in models.nodes() {
+        match node {
+            Node::Model(model) | Node::Snapshot(model) => {
+                relations.insert(
+                    model.qualified_relation(),
+                    Location {
+                        database: model.database.clone(),
+                        schema: model
+                            .schema
+                            .clone()
+                            .unwrap_or_else(|| DEFAULT_SCHEMA.to_string()),
+                        name: model.relation_name().to_string(),
+                    },
+                );
+            }
+            Node::Source(source) => {
+                let parts: Vec<&str> = source.relation.split('.').collect();
+                let (database, schema, name) = match parts.as_slice() {
+                    [database, schema, name] => (Some(database.to_string()), *schema, *name),
+                    [schema, name] => (None, *schema, *name),
+                    _ => continue,
+                };
+                relations.insert(
+                    source.relation.clone(),
+                    Location {
+                        database,
+                        schema: schema.to_string(),
+                        name: name.to_string(),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+    relations
+}
+
+/// Record the columns of each relation in `tables`, returning the relations without any.
+/// An unqualified relation takes the first database that has it.
+fn sync(
+    tables: &mut TableManager,
+    relations: &BTreeMap<String, Location>,
+    columns: &[SchemaColumn],
+) -> Vec<String> {
+    let mut missing = Vec::new();
+    for (relation, location) in relations {
+        let database = columns
+            .iter()
+            .find(|column| matches(column, location, None))
+            .map(|column| column.database.clone());
+        let found: Vec<_> = columns
+            .iter()
+            .filter(|column| matches(column, location, database.as_deref()))
+            .map(|column| column.column.clone())
+            .collect();
+        if found.is_empty() {
+            tables.remove_schema(relation);
+            missing.push(relation.clone());
+        } else {
+            tables.register_schema(TableSchema {
+                name: relation.clone(),
+                columns: found,
+            });
+        }
+    }
+    missing
+}
+
+/// Whether `column` belongs to the relation at `location`, in `database` when given
+fn matches(column: &SchemaColumn, location: &Location, database: Option<&str>) -> bool {
+    let database = database.or(location.database.as_deref());
+    column.schema.eq_ignore_ascii_case(&location.schema)
+        && column.table.eq_ignore_ascii_case(&location.name)
+        && database.is_none_or(|database| column.database.eq_ignore_ascii_case(database))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::tables::ColumnDef;
+
+    fn column(database: &str, schema: &str, table: &str, name: &str) -> SchemaColumn {
+        SchemaColumn {
+            database: database.to_string(),
+            schema: schema.to_string(),
+            table: table.to_string(),
+            column: ColumnDef {
+                name: name.to_string(),
+                data_type: "INTEGER".to_string(),
+                nullable: true,
+            },
+        }
+    }
+
+    #[test]
+    fn test_sync_records_found_relations_and_drops_missing_ones() {
+        let location = |database: Option<&str>, schema: &str, name: &str| Location {
+            database: database.map(str::to_string),
+            schema: schema.to_string(),
+            name: name.to_string(),
+        };
+        let relations = BTreeMap::from([
+            ("raw.orders".to_string(), location(None, "raw", "orders")),
+            (
+                "other.raw.customers".to_string(),
+                location(Some("other"), "raw", "customers"),
+            ),
+            ("marts.fct".to_string(), location(None, "marts", "fct")),
+        ]);
+        let columns = vec![
+            column("warehouse", "raw", "customers", "id"),
+            column("warehouse", "raw", "orders", "id"),
+            column("warehouse", "raw", "orders", "amount"),
+            column("other", "raw", "customers", "customer_id"),
+            column("other", "raw", "orders", "ignored"),
+        ];
+        let mut tables = TableManager::new();
+        tables.register_schema(TableSchema {
+            name: "marts.fct".to_string(),
+            columns: Vec::new(),
+        });
+
+        let missing = sync(&mut tables, &relations, &columns);
+        assert_eq!(missing, vec!["marts.fct".to_string()]);
+        assert_eq!(
+            tables.get_column_names("raw.orders"),
+            Some(vec!["id".to_string(), "amount".to_string()])
+        );
+        assert_eq!(
+            tables.get_column_names("other.raw.customers"),
+            Some(vec!["customer_id".to_string()])
+        );
+        assert!(tables.get_schema("marts.fct").is_none());
+    }
+}
@@ -0,0 +1,346 @@
+//! `ff test`: data tests and regression checks for a project
+//!
+//! With `--db`, the column tests declared in model YAML (`not_null`, `unique`,
+//! `accepted_values`, `relationships`) and in `-- ff:test` annotations run against a DuckDB
+//! database as queries counting failing rows, without building anything first. `--select`
+//! and `--exclude` narrow them down with the `ff build` selection syntax; the tests of the
+//! selected models run.
+//!
+//! With `--compiled-snapshots`, the compiled SQL of every model is compared against the
+//! canonical copy stored under `tests/snapshots/` (mirroring the model tree), so changes to
+//! templating or compilation that alter a model's SQL are caught. `--update-snapshots`
+//! accepts the current output as the new canonical copy.
+
+use colored::{Color, Colorize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use super::project::{load_project, require_duckdb, LoadedProject};
+use crate::adapters::{self, AccessMode};
+use crate::display::{self, status};
+use crate::run::data_tests::{self, TestOutcome};
+use crate::run::plan::BuildPlan;
+use crate::run::selection::{self, Selector};
+use crate::sql_engine::nodes::{Node, ResourceType};
+
+type TestResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Directory, relative to the project root, holding compiled SQL snapshots
+pub const SNAPSHOT_DIR: &str = "tests/snapshots";
+
+#[derive(Debug, PartialEq)]
+enum SnapshotOutcome {
+    Unchanged,
+    New,
+    Changed { line: usize },
+}
+
+pub struct TestArgs<'a> {
+    pub model_path: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    /// DuckDB database the data tests run against
+    pub database: Option<&'a Path>,
+    pub select: Option<&'a str>,
+    /// Name of a selector in `selectors.yml`, used instead of `select`
+    pub selector: Option<&'a str>,
+    pub exclude: Option<&'a str>,
+    pub compiled_snapshots: bool,
+    pub update_snapshots: bool,
+    pub mode: AccessMode,
+}
+
+pub fn test_command(args: &TestArgs) -> TestResult<()> {
+    if args.database.is_none() && !args.compiled_snapshots {
+        return Err("Nothing to test. Pass --db to run the data tests declared in model YAML, or --compiled-snapshots to check compiled SQL against stored snapshots.".into());
+    }
+
+    let project = load_project(args.model_path, args.target, args.vars)?;
+    let selection = project.selection(args.model_path, args.select, args.selector)?;
+    let select = selection.as_deref().map(Selector::parse).transpose()?;
+    let exclude = args.exclude.map(Selector::parse).transpose()?;
+
+    let mut problems = Vec::new();
+    if args.compiled_snapshots {
+        let failures = check_snapshots(&project, args.model_path, args.update_snapshots)?;
+        if failures > 0 {
+            problems.push(format!(
+                "{} compiled SQL snapshot(s) out of date. Re-run with --update-snapshots to accept the changes.",
+                failures
+            ));
+        }
+    }
+    if let Some(database) = args.database {
+        require_duckdb(&project.config, "ff test")?;
+        let failures = run_data_tests(
+            &project,
+            database,
+            select.as_ref(),
+            exclude.as_ref(),
+            args.mode,
+        )?;
+        if failures > 0 {
+            problems.push(format!("{} data test(s) did not pass", failures));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(problems.join("\n").into());
+    }
+    Ok(())
+}
+
+/// Run the selected column tests, printing one line per test and a summary. Returns how many
+/// failed or errored.
+fn run_data_tests(
+    project: &LoadedProject,
+    database: &Path,
+    select: Option<&Selector>,
+    exclude: Option<&Selector>,
+    mode: AccessMode,
+) -> TestResult<usize> {
+    let plan = BuildPlan::new(&project.models);
+    let mut selected = selection::resolve(&plan, select, exclude);
+    selected.retain(|id| {
+        plan.get(id)
+            .is_some_and(|node| node.resource_type() == ResourceType::Test)
+    });
+    if selected.is_empty() {
+        status!("No data tests selected; declare them under a column's tests: in model YAML");
+        return Ok(0);
+    }
+
+    let adapter = adapters::connect(database, mode)?;
+    let (mut passed, mut failed, mut errored, mut skipped) = (0, 0, 0, 0);
+    for node in plan.order(&selected)? {
+        let Node::Test(test) = node.node else {
+            continue;
+        };
+        match data_tests::run_column_test(adapter.as_ref(), &project.models, test) {
+            Ok(TestOutcome::Passed) => {
+                passed += 1;
+                report_test("PASS", Color::Green, &test.unique_id, 0, "");
+            }
+            Ok(TestOutcome::Failed(rows)) => {
+                failed += 1;
+                report_test(
+                    "FAIL",
+                    Color::Red,
+                    &test.unique_id,
+                    rows,
+                    &format!("{} failing row(s)", rows),
+                );
+            }
+            Ok(TestOutcome::Unsupported(reason)) => {
+                skipped += 1;
+                report_test("SKIP", Color::Yellow, &test.unique_id, 0, &reason);
+            }
+            Err(err) => {
+                errored += 1;
+                report_test(
+                    "ERROR",
+                    Color::Red,
+                    &test.unique_id,
+                    0,
+                    &format!("{:#}", err),
+                );
+            }
+        }
+    }
+
+    status!(
+        "\n{} passed, {} failed, {} errored, {} skipped",
+        passed,
+        failed,
+        errored,
+        skipped
+    );
+    Ok(failed + errored)
+}
+
+/// One line per data test; with `--porcelain` the record is
+/// `status, unique id, failing rows, message`
+fn report_test(label: &str, color: Color, unique_id: &str, rows: u64, message: &str) {
+    if display::porcelain() {
+        display::record(&[label, unique_id, &rows.to_string(), message]);
+        return;
+    }
+
+    let mut line = format!("{} {}", format!("{:<5}", label).color(color), unique_id);
+    if !message.is_empty() {
+        line.push_str(&format!(": {}", message));
+    }
+    println!("{}", line);
+}
+
+/// Compare every model's compiled SQL with its snapshot, or update the snapshots. Returns
+/// how many are out of date.
+fn check_snapshots(
+    project: &LoadedProject,
+    model_path: &Path,
+    update_snapshots: bool,
+) -> TestResult<usize> {
+    let snapshot_dir = project.root_dir(model_path).join(SNAPSHOT_DIR);
+
+    let mut failures = 0;
+    let mut expected_files = BTreeSet::new();
+
+    for model in project.models.get_execution_order()? {
+        let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+        let snapshot_path = snapshot_dir.join(&model.relative_file_path);
+        expected_files.insert(snapshot_path.clone());
+
+        let outcome = compare_snapshot(&snapshot_path, compiled)?;
+        if outcome == SnapshotOutcome::Unchanged {
+            continue;
+        }
+
+        if update_snapshots {
+            write_snapshot(&snapshot_path, compiled)?;
+            report("Updated", Color::Green, &model.relative_file_path, "");
+            continue;
+        }
+
+        failures += 1;
+        match outcome {
+            SnapshotOutcome::New => report(
+                "New",
+                Color::Yellow,
+                &model.relative_file_path,
+                &format!("no snapshot at {}", snapshot_path.display()),
+            ),
+            SnapshotOutcome::Changed { line } => report(
+                "Changed",
+                Color::Red,
+                &model.relative_file_path,
+                &format!("first difference at line {}", line),
+            ),
+            SnapshotOutcome::Unchanged => {}
+        }
+    }
+
+    for stale in find_stale_snapshots(&snapshot_dir, &expected_files) {
+        if update_snapshots {
+            fs::remove_file(&stale)?;
+            report("Removed", Color::Green, &stale, "");
+        } else {
+            failures += 1;
+            report("Stale", Color::Yellow, &stale, "model no longer exists");
+        }
+    }
+
+    if failures == 0 {
+        status!(
+            "{} {} compiled SQL snapshot(s) up to date",
+            "OK:".green(),
+            project.models.models_count()
+        );
+    }
+    Ok(failures)
+}
+
+/// One line per snapshot; with `--porcelain` the record is `label, path, detail`
+fn report(label: &str, color: Color, path: &Path, detail: &str) {
+    if display::porcelain() {
+        display::record(&[label, &path.to_string_lossy(), detail]);
+        return;
+    }
+
+    let mut line = format!("{} {}", format!("{}:", label).color(color), path.display());
+    if !detail.is_empty() {
+        line.push_str(&format!(" ({})", detail));
+    }
+    println!("{}", line);
+}
+
+fn normalize(sql: &str) -> String {
+    let lines: Vec<&str> = sql.trim().lines().map(str::trim_end).collect();
+    lines.join("\n") + "\n"
+}
+
+fn compare_snapshot(snapshot_path: &Path, compiled: &str) -> TestResult<SnapshotOutcome> {
+    if !snapshot_path.exists() {
+        return Ok(SnapshotOutcome::New);
+    }
+
+    let stored = normalize(&fs::read_to_string(snapshot_path)?);
+    let current = normalize(compiled);
+    if stored == current {
+        return Ok(SnapshotOutcome::Unchanged);
+    }
+
+    let mut stored_lines = stored.lines();
+    let mut current_lines = current.lines();
+    let mut line = 1;
+    while stored_lines.next() == current_lines.next() {
+        line += 1;
+    }
+
+    Ok(SnapshotOutcome::Changed { line })
+}
+
+fn write_snapshot(snapshot_path: &Path, compiled: &str) -> TestResult<()> {
+    if let Some(parent) = snapshot_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(snapshot_path, normalize(compiled))?;
+    Ok(())
+}
+
+fn find_stale_snapshots(snapshot_dir: &Path, expected_files: &BTreeSet<PathBuf>) -> Vec<PathBuf> {
+    let mut stale: Vec<PathBuf> = WalkDir::new(snapshot_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .filter(|path| !expected_files.contains(path))
+        .collect();
+
+    stale.sort();
+    stale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compare_snapshot_outcomes() {
+        let temp_dir = tempdir().unwrap();
+        let snapshot_path = temp_dir.path().join("staging").join("orders.sql");
+
+        assert_eq!(
+            compare_snapshot(&snapshot_path, "SELECT 1").unwrap(),
+            SnapshotOutcome::New
+        );
+
+        write_snapshot(&snapshot_path, "SELECT id\nFROM raw.orders   \n").unwrap();
+        assert_eq!(
+            compare_snapshot(&snapshot_path, "SELECT id\nFROM raw.orders\n\n").unwrap(),
+            SnapshotOutcome::Unchanged
+        );
+        assert_eq!(
+            compare_snapshot(&snapshot_path, "SELECT id\nFROM staging.orders").unwrap(),
+            SnapshotOutcome::Changed { line: 2 }
+        );
+    }
+
+    #[test]
+    fn test_stale_snapshots() {
+        let temp_dir = tempdir().unwrap();
+        let kept = temp_dir.path().join("a").join("a.sql");
+        let stale = temp_dir.path().join("b").join("b.sql");
+        write_snapshot(&kept, "SELECT 1").unwrap();
+        write_snapshot(&stale, "SELECT 2").unwrap();
+
+        let expected = BTreeSet::from([kept]);
+        assert_eq!(
+            find_stale_snapshots(temp_dir.path(), &expected),
+            vec![stale]
+        );
+    }
+}
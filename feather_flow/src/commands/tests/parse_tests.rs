@@ -5,6 +5,7 @@ use sqlparser::parser::Parser as SqlParser;
 use std::collections::HashSet;
 use std::path::PathBuf;
 
+use crate::sql_engine::checksum::Sha256Hash;
 use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
 
 use crate::sql_engine::extractors::{get_external_table_deps, get_table_names};
@@ -75,7 +76,7 @@ fn parse_dependencies(file_path: &str) -> Result<Vec<String>, Box<dyn std::error
     let project_root = find_project_root()?;
 
     // Get the directory part and the file name
-    let base_name = file_path.split('/').last().unwrap_or(file_path);
+    let base_name = file_path.split('/').next_back().unwrap_or(file_path);
     let directory = file_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
 
     // Remove the .sql extension from the base_name for the directory name
@@ -474,7 +475,7 @@ fn test_yaml_loading_integration() {
     );
 
     for file_path in &sql_files {
-        match SqlModel::from_path(file_path, &models_dir, "duckdb", &dialect) {
+        match SqlModel::from_path(file_path, &models_dir, "duckdb", &dialect, &Sha256Hash) {
             Ok(mut model) => {
                 // Extract dependencies
                 if let Err(err) = model.extract_dependencies() {
@@ -626,22 +627,19 @@ fn collect_sql_files(dir: &PathBuf) -> Vec<PathBuf> {
         return sql_files;
     }
 
-    match fs::read_dir(dir) {
-        Ok(entries) => {
-            for entry in entries.flatten() {
-                let path = entry.path();
-
-                if path.is_dir() {
-                    // Recursively search subdirectories
-                    sql_files.extend(collect_sql_files(&path));
-                } else if let Some(extension) = path.extension() {
-                    if extension == "sql" {
-                        sql_files.push(path);
-                    }
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.is_dir() {
+                // Recursively search subdirectories
+                sql_files.extend(collect_sql_files(&path));
+            } else if let Some(extension) = path.extension() {
+                if extension == "sql" {
+                    sql_files.push(path);
                 }
             }
         }
-        Err(_) => {}
     }
 
     sql_files
@@ -0,0 +1,28 @@
+//! `ff unlock`: clear a state lock left behind by an interrupted invocation
+
+use std::path::Path;
+
+use super::project::project_root;
+use crate::config::FeatherFlowConfig;
+use crate::display::status;
+use crate::state::{lock, state_dir};
+
+type UnlockResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+pub fn unlock_command(model_path: &Path) -> UnlockResult<()> {
+    let config = FeatherFlowConfig::discover(model_path)?.unwrap_or_default();
+    let state_dir = state_dir(&project_root(&config, model_path));
+
+    // An unreadable lock file is still cleared; the owner is only informational
+    let owner = lock::read_owner(&state_dir).unwrap_or(None);
+    if !lock::force_unlock(&state_dir)? {
+        status!("{} is not locked", state_dir.display());
+        return Ok(());
+    }
+
+    match owner {
+        Some(owner) => status!("Removed lock on {} held by {}", state_dir.display(), owner),
+        None => status!("Removed lock on {}", state_dir.display()),
+    }
+    Ok(())
+}
@@ -0,0 +1,713 @@
+//! `ff validate`: model file structure, naming rules and directory hygiene
+//!
+//! With `--diagnostics-file` every finding is also written as JSON for editor plugins and
+//! TUIs to poll. With `--watch` the checks rerun whenever a model's SQL or YAML or the project
+//! config changes, keeping that file current until the command is interrupted.
+//!
+//! `--format json` prints the same document as the diagnostics file to stdout, and
+//! `--format sarif` prints a SARIF 2.1.0 log for code scanning in CI. Every finding carries
+//! a stable code from `error::codes`, which SARIF reports as the rule id.
+//!
+//! `--strict-yaml` also checks every model and source YAML file against the structure
+//! FeatherFlow reads (`validators::yaml_schema`), and `--check-columns` parses the project to
+//! compare each model's documented columns with those its SQL selects
+//! (`validators::columns`). What either finds fails validation.
+//!
+//! A project with `require_tests` thresholds is also parsed to check each directory's share of
+//! models with tests (`validators::coverage`); a directory below its threshold fails
+//! validation and lists the models to add tests to.
+//!
+//! Environment variables and free-form string variables written into model SQL without
+//! `literal()` or `identifier()` are reported as warnings (`validators::interpolation`).
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::project::load_project;
+use super::watch::{self, absolute, config_path};
+use crate::config::FeatherFlowConfig;
+use crate::display::{self, status, Marker};
+use crate::error::{self, codes};
+use crate::validators::columns::{self, ColumnMismatch};
+use crate::validators::coverage::{self, TestCoverage};
+use crate::validators::hygiene::{self, HygieneIssue};
+use crate::validators::interpolation::{self, InterpolationIssue};
+use crate::validators::naming::{NamingRules, NamingViolation};
+use crate::validators::yaml_schema::{self, YamlIssue};
+use crate::validators::{self, ValidationResult};
+
+type ValidateResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// How findings are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+impl OutputFormat {
+    fn parse(format: &str) -> ValidateResult<Self> {
+        match format {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "sarif" => Ok(OutputFormat::Sarif),
+            other => Err(format!(
+                "Unknown validate format '{}' (use text, json or sarif)",
+                other
+            )
+            .into()),
+        }
+    }
+}
+
+/// Checks that only run when asked for
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Checks {
+    pub strict_yaml: bool,
+    pub columns: bool,
+}
+
+pub fn validate_command(
+    model_path: &Path,
+    quiet: bool,
+    watch: bool,
+    diagnostics_file: Option<&Path>,
+    format: &str,
+    checks: Checks,
+) -> ValidateResult<()> {
+    let format = OutputFormat::parse(format)?;
+    if !watch {
+        let checked = Report::check(model_path, checks);
+        let diagnostics = match &checked {
+            Ok(report) => report.diagnostics(model_path),
+            Err(err) => vec![config_diagnostic(model_path, err)],
+        };
+        match (&checked, format) {
+            (Ok(report), OutputFormat::Text) => report.print(quiet),
+            // Reported as the command's error
+            (Err(_), OutputFormat::Text) => {}
+            _ => print_document(format, &diagnostics)?,
+        }
+        if let Some(path) = diagnostics_file {
+            write_diagnostics(path, &diagnostics)?;
+        }
+        return match checked {
+            Err(err) => Err(format!("{:#}", err).into()),
+            Ok(report) if report.failed() => {
+                let mut message = format!(
+                    "{} invalid model(s) and {} naming violation(s)",
+                    report.invalid_count(),
+                    report.naming.len()
+                );
+                if !report.yaml.is_empty() {
+                    message.push_str(&format!(", {} YAML schema error(s)", report.yaml.len()));
+                }
+                if !report.columns.is_empty() {
+                    message.push_str(&format!(", {} column mismatch(es)", report.columns.len()));
+                }
+                if report.uncovered_count() > 0 {
+                    message.push_str(&format!(
+                        ", {} directory(ies) below require_tests",
+                        report.uncovered_count()
+                    ));
+                }
+                Err(message.into())
+            }
+            Ok(_) => Ok(()),
+        };
+    }
+
+    watch::watch(model_path, diagnostics_file, || {
+        // A broken project config is reported like any other finding instead of ending the
+        // watch
+        let checked = Report::check(model_path, checks);
+        let diagnostics = match &checked {
+            Ok(report) => report.diagnostics(model_path),
+            Err(err) => vec![config_diagnostic(model_path, err)],
+        };
+        match (&checked, format) {
+            (Ok(report), OutputFormat::Text) => report.print(quiet),
+            (Err(err), OutputFormat::Text) => eprintln!("{} {:#}", Marker::Error, err),
+            _ => {
+                if let Err(err) = print_document(format, &diagnostics) {
+                    eprintln!("{} {}", Marker::Warning, err);
+                }
+            }
+        }
+        if let Some(path) = diagnostics_file {
+            if let Err(err) = write_diagnostics(path, &diagnostics) {
+                eprintln!("{} {}", Marker::Warning, err);
+            }
+        }
+    })
+}
+
+fn config_diagnostic(model_path: &Path, err: &anyhow::Error) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        code: error::code_of(err.as_ref()).unwrap_or(codes::INVALID_PROJECT_CONFIG),
+        check: "config",
+        path: absolute(&config_path(model_path).unwrap_or_else(|| model_path.to_path_buf())),
+        line: None,
+        message: format!("{:#}", err),
+    }
+}
+
+/// Results of every check over one model path
+struct Report {
+    structure: Vec<ValidationResult>,
+    naming: Vec<NamingViolation>,
+    /// Only checked with `--strict-yaml`
+    yaml: Vec<YamlIssue>,
+    /// Only checked with `--check-columns`
+    columns: Vec<ColumnMismatch>,
+    /// Only checked when the project config has `require_tests`
+    coverage: Vec<TestCoverage>,
+    /// Leftovers are reported but do not fail validation
+    hygiene: Vec<HygieneIssue>,
+    /// Unquoted untrusted values are reported but do not fail validation
+    interpolation: Vec<InterpolationIssue>,
+}
+
+impl Report {
+    fn check(model_path: &Path, checks: Checks) -> anyhow::Result<Self> {
+        let config = FeatherFlowConfig::discover(model_path)?.unwrap_or_default();
+        let rules = NamingRules::compile(&config.naming_rules)?;
+        let project = if checks.columns || !config.require_tests.is_empty() {
+            Some(load_project(model_path, None, None).map_err(|err| anyhow::anyhow!("{}", err))?)
+        } else {
+            None
+        };
+        Ok(Self {
+            structure: validators::validate_models_directory(model_path),
+            naming: rules.check_models_directory(model_path),
+            yaml: if checks.strict_yaml {
+                yaml_schema::check_models_directory(model_path)
+            } else {
+                Vec::new()
+            },
+            columns: match &project {
+                Some(project) if checks.columns => columns::check_columns(&project.models),
+                _ => Vec::new(),
+            },
+            coverage: match &project {
+                Some(project) => coverage::evaluate(&config.require_tests, &project.models)?,
+                None => Vec::new(),
+            },
+            hygiene: hygiene::check_models_directory(model_path),
+            interpolation: interpolation::check_models_directory(model_path, &config.vars),
+        })
+    }
+
+    fn invalid_count(&self) -> usize {
+        self.structure.iter().filter(|r| !r.is_valid).count()
+    }
+
+    fn uncovered_count(&self) -> usize {
+        self.coverage.iter().filter(|c| !c.passes()).count()
+    }
+
+    fn failed(&self) -> bool {
+        self.invalid_count() > 0
+            || !self.naming.is_empty()
+            || !self.yaml.is_empty()
+            || !self.columns.is_empty()
+            || self.uncovered_count() > 0
+    }
+
+    fn print(&self, quiet: bool) {
+        let porcelain = display::porcelain();
+        for result in &self.structure {
+            let path = result.path.to_string_lossy();
+            if result.is_valid {
+                if porcelain {
+                    display::record(&["valid", &path, "", ""]);
+                } else if !quiet {
+                    println!("{} Valid model structure: {}", Marker::Ok, path);
+                }
+                continue;
+            }
+            if porcelain {
+                for error in &result.errors {
+                    display::record(&["invalid", &path, &error.to_string(), error.code()]);
+                }
+                continue;
+            }
+            eprintln!("{} Invalid model structure: {}", Marker::Error, path);
+            for error in &result.errors {
+                eprintln!("   - {}", error);
+            }
+        }
+
+        for violation in &self.naming {
+            if porcelain {
+                display::record(&[
+                    "naming",
+                    &violation.path.to_string_lossy(),
+                    &violation.message,
+                    codes::NAMING_VIOLATION,
+                ]);
+                continue;
+            }
+            eprintln!(
+                "{} Naming violation: {}",
+                Marker::Error,
+                violation.path.display()
+            );
+            eprintln!("   - {}", violation.message);
+            if let Some(suggestion) = &violation.suggestion {
+                eprintln!("   - Suggested name: {}", suggestion);
+            }
+        }
+
+        for issue in &self.yaml {
+            if porcelain {
+                display::record(&[
+                    "yaml",
+                    &issue.path.to_string_lossy(),
+                    &yaml_message(issue),
+                    issue.kind.code(),
+                ]);
+                continue;
+            }
+            eprintln!("{} Invalid YAML: {}", Marker::Error, issue.path.display());
+            eprintln!("   - {}", yaml_message(issue));
+        }
+
+        for mismatch in &self.columns {
+            if porcelain {
+                display::record(&[
+                    "columns",
+                    &mismatch.path.to_string_lossy(),
+                    &mismatch.message(),
+                    mismatch.kind.code(),
+                ]);
+                continue;
+            }
+            eprintln!(
+                "{} Column mismatch: {}",
+                Marker::Error,
+                mismatch.path.display()
+            );
+            eprintln!("   - {}", mismatch.message());
+        }
+
+        for coverage in self.coverage.iter().filter(|c| !c.passes()) {
+            if porcelain {
+                display::record(&[
+                    "coverage",
+                    &coverage.path,
+                    &coverage.message(),
+                    codes::TEST_COVERAGE,
+                ]);
+                continue;
+            }
+            eprintln!(
+                "{} Test coverage below require_tests: {}",
+                Marker::Error,
+                coverage.path
+            );
+            eprintln!("   - {}", coverage.message());
+        }
+
+        for issue in &self.hygiene {
+            if porcelain {
+                display::record(&[
+                    "hygiene",
+                    &issue.path.to_string_lossy(),
+                    &issue.message,
+                    issue.kind.code(),
+                ]);
+                continue;
+            }
+            eprintln!(
+                "{} {}: {}",
+                Marker::Warning,
+                issue.kind.label(),
+                issue.path.display()
+            );
+            eprintln!("   - {}", issue.message);
+        }
+
+        for issue in &self.interpolation {
+            if porcelain {
+                display::record(&[
+                    "interpolation",
+                    &issue.path.to_string_lossy(),
+                    &issue.message(),
+                    codes::RAW_INTERPOLATION,
+                ]);
+                continue;
+            }
+            eprintln!(
+                "{} Raw interpolation: {}:{}",
+                Marker::Warning,
+                issue.path.display(),
+                issue.raw.line
+            );
+            eprintln!("   - {}", issue.message());
+        }
+
+        let invalid = self.invalid_count();
+        if !quiet || self.failed() {
+            status!("\nValidation summary:");
+            status!("  Valid models: {}", self.structure.len() - invalid);
+            status!("  Invalid models: {}", invalid);
+            status!("  Naming violations: {}", self.naming.len());
+            if !self.yaml.is_empty() {
+                status!("  YAML schema errors: {}", self.yaml.len());
+            }
+            if !self.columns.is_empty() {
+                status!("  Column mismatches: {}", self.columns.len());
+            }
+            if self.uncovered_count() > 0 {
+                status!(
+                    "  Directories below require_tests: {}",
+                    self.uncovered_count()
+                );
+            }
+            status!("  Hygiene issues: {}", self.hygiene.len());
+            if !self.interpolation.is_empty() {
+                status!("  Raw interpolations: {}", self.interpolation.len());
+            }
+            status!("  Total models checked: {}", self.structure.len());
+        }
+    }
+
+    /// Every finding, errors before warnings, each group ordered by path
+    fn diagnostics(&self, model_path: &Path) -> Vec<Diagnostic> {
+        let structure = self.structure.iter().flat_map(|result| {
+            result.errors.iter().map(|error| Diagnostic {
+                severity: Severity::Error,
+                code: error.code(),
+                check: "structure",
+                path: absolute(&result.path),
+                line: None,
+                message: error.to_string(),
+            })
+        });
+        let naming = self.naming.iter().map(|violation| Diagnostic {
+            severity: Severity::Error,
+            code: codes::NAMING_VIOLATION,
+            check: "naming",
+            path: absolute(&violation.path),
+            line: None,
+            message: match &violation.suggestion {
+                Some(suggestion) => {
+                    format!("{} (suggested name: {})", violation.message, suggestion)
+                }
+                None => violation.message.clone(),
+            },
+        });
+        let yaml = self.yaml.iter().map(|issue| Diagnostic {
+            severity: Severity::Error,
+            code: issue.kind.code(),
+            check: "yaml",
+            path: absolute(&issue.path),
+            line: issue.line,
+            message: issue.message.clone(),
+        });
+        let columns = self.columns.iter().map(|mismatch| Diagnostic {
+            severity: Severity::Error,
+            code: mismatch.kind.code(),
+            check: "columns",
+            path: absolute(&mismatch.path),
+            line: mismatch.line,
+            message: mismatch.message(),
+        });
+        let coverage = self
+            .coverage
+            .iter()
+            .filter(|c| !c.passes())
+            .map(|coverage| Diagnostic {
+                severity: Severity::Error,
+                code: codes::TEST_COVERAGE,
+                check: "coverage",
+                path: absolute(&model_path.join(&coverage.path)),
+                line: None,
+                message: coverage.message(),
+            });
+        let hygiene = self.hygiene.iter().map(|issue| Diagnostic {
+            severity: Severity::Warning,
+            code: issue.kind.code(),
+            check: "hygiene",
+            path: absolute(&issue.path),
+            line: None,
+            message: format!("{}: {}", issue.kind.label(), issue.message),
+        });
+        let interpolation = self.interpolation.iter().map(|issue| Diagnostic {
+            severity: Severity::Warning,
+            code: codes::RAW_INTERPOLATION,
+            check: "interpolation",
+            path: absolute(&issue.path),
+            line: Some(issue.raw.line),
+            message: issue.message(),
+        });
+
+        let mut diagnostics: Vec<Diagnostic> = structure
+            .chain(naming)
+            .chain(yaml)
+            .chain(columns)
+            .chain(coverage)
+            .chain(hygiene)
+            .chain(interpolation)
+            .collect();
+        diagnostics.sort_by(|a, b| (a.severity, &a.path).cmp(&(b.severity, &b.path)));
+        diagnostics
+    }
+}
+
+/// A YAML finding's message, led by its line when known
+fn yaml_message(issue: &YamlIssue) -> String {
+    match issue.line {
+        Some(line) => format!("line {}: {}", line, issue.message),
+        None => issue.message.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// One finding as editors consume it
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    severity: Severity,
+    /// Stable code of the finding, e.g. `FF001`
+    code: &'static str,
+    /// `structure`, `naming`, `yaml`, `columns`, `coverage`, `hygiene`, `interpolation` or
+    /// `config`
+    check: &'static str,
+    /// Absolute path of the model directory or file the finding is about
+    path: PathBuf,
+    /// Line in the file, counting from 1, for findings that point into one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct DiagnosticsFile<'a> {
+    errors: usize,
+    warnings: usize,
+    diagnostics: &'a [Diagnostic],
+}
+
+impl<'a> DiagnosticsFile<'a> {
+    fn new(diagnostics: &'a [Diagnostic]) -> Self {
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        Self {
+            errors,
+            warnings: diagnostics.len() - errors,
+            diagnostics,
+        }
+    }
+}
+
+fn print_document(format: OutputFormat, diagnostics: &[Diagnostic]) -> ValidateResult<()> {
+    let document = match format {
+        OutputFormat::Sarif => sarif_log(diagnostics),
+        _ => serde_json::to_value(DiagnosticsFile::new(diagnostics))?,
+    };
+    println!("{}", serde_json::to_string_pretty(&document)?);
+    Ok(())
+}
+
+/// SARIF 2.1.0 log with one rule per code that was found
+fn sarif_log(diagnostics: &[Diagnostic]) -> Value {
+    let rules: BTreeMap<&str, &str> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.code, diagnostic.check))
+        .collect();
+    let rules: Vec<Value> = rules
+        .into_iter()
+        .map(|(code, check)| {
+            let (name, description) = codes::describe(code);
+            json!({
+                "id": code,
+                "name": name,
+                "shortDescription": {"text": description},
+                "properties": {"check": check},
+            })
+        })
+        .collect();
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut location = json!({"artifactLocation": {"uri": file_uri(&diagnostic.path)}});
+            if let Some(line) = diagnostic.line {
+                location["region"] = json!({"startLine": line});
+            }
+            json!({
+                "ruleId": diagnostic.code,
+                "level": match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                "message": {"text": diagnostic.message},
+                "locations": [{"physicalLocation": location}],
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {"driver": {
+                "name": "FeatherFlow",
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": rules,
+            }},
+            "results": results,
+        }],
+    })
+}
+
+/// `file://` URI of an absolute path, escaping what URIs reserve
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for c in path.to_string_lossy().replace('\\', "/").chars() {
+        match c {
+            ' ' => uri.push_str("%20"),
+            '%' => uri.push_str("%25"),
+            '#' => uri.push_str("%23"),
+            '?' => uri.push_str("%3F"),
+            c => uri.push(c),
+        }
+    }
+    if !uri["file://".len()..].starts_with('/') {
+        uri.insert(("file://").len(), '/');
+    }
+    uri
+}
+
+/// Replace `path` with `diagnostics`. The file is written aside and renamed into place so a
+/// reader polling it never sees half of it.
+fn write_diagnostics(path: &Path, diagnostics: &[Diagnostic]) -> ValidateResult<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = DiagnosticsFile::new(diagnostics);
+
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_string_pretty(&file)? + "\n")
+        .map_err(|err| format!("Failed to write {}: {}", partial.display(), err))?;
+    fs::rename(&partial, path)
+        .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_diagnostics_file_lists_findings_and_watch_sees_edits() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("models");
+        fs::create_dir_all(models.join("orders")).unwrap();
+        fs::write(models.join("orders/orders.sql"), "SELECT 1 AS id").unwrap();
+        let output = dir.path().join(".featherflow/diagnostics.json");
+
+        let report = Report::check(&models, Checks::default()).unwrap();
+        write_diagnostics(&output, &report.diagnostics(&models)).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(written["errors"], serde_json::json!(1));
+        assert_eq!(
+            written["diagnostics"][0]["check"],
+            serde_json::json!("structure")
+        );
+        assert!(written["diagnostics"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Missing YAML file"));
+
+        let before = watch::fingerprint(&models, Some(&output));
+        fs::write(
+            models.join("orders/orders.yml"),
+            "models:\n  - name: orders\n",
+        )
+        .unwrap();
+        assert_ne!(watch::fingerprint(&models, Some(&output)), before);
+
+        let report = Report::check(&models, Checks::default()).unwrap();
+        assert!(!report.failed());
+        assert!(report.diagnostics(&models).is_empty());
+    }
+
+    #[test]
+    fn test_sarif_log_reports_codes_and_locations() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("my models");
+        fs::create_dir_all(models.join("orders")).unwrap();
+        fs::write(models.join("orders/orders.sql"), "SELECT 1 AS id").unwrap();
+
+        let diagnostics = Report::check(&models, Checks::default())
+            .unwrap()
+            .diagnostics(&models);
+        assert_eq!(diagnostics[0].code, codes::MISSING_YAML_FILE);
+
+        let log = sarif_log(&diagnostics);
+        assert_eq!(log["version"], json!("2.1.0"));
+        let run = &log["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"][0]["id"],
+            json!(codes::MISSING_YAML_FILE)
+        );
+        assert_eq!(run["results"][0]["ruleId"], json!("FF002"));
+        assert_eq!(run["results"][0]["level"], json!("error"));
+        let uri = run["results"][0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"]
+            .as_str()
+            .unwrap();
+        assert!(uri.starts_with("file:///"), "{}", uri);
+        assert!(uri.ends_with("/my%20models/orders"), "{}", uri);
+
+        assert!(OutputFormat::parse("junit").is_err());
+    }
+
+    #[test]
+    fn test_strict_yaml_fails_validation_with_lines() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("models");
+        fs::create_dir_all(models.join("orders")).unwrap();
+        fs::write(models.join("orders/orders.sql"), "SELECT 1 AS id").unwrap();
+        fs::write(
+            models.join("orders/orders.yml"),
+            "version: 2\nmodels:\n  - name: orders\n    materialized: table\n",
+        )
+        .unwrap();
+
+        assert!(!Report::check(&models, Checks::default()).unwrap().failed());
+        let report = Report::check(
+            &models,
+            Checks {
+                strict_yaml: true,
+                ..Checks::default()
+            },
+        )
+        .unwrap();
+        assert!(report.failed());
+        let diagnostics = report.diagnostics(&models);
+        assert_eq!(diagnostics[0].code, codes::UNKNOWN_YAML_KEY);
+        assert_eq!(diagnostics[0].line, Some(4));
+
+        let log = sarif_log(&diagnostics);
+        assert_eq!(
+            log["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"],
+            json!({"startLine": 4})
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! `--watch` for `ff parse` and `ff validate`
+//!
+//! Polls the model path and the project config, and reruns the command whenever a `.sql` or
+//! YAML file is added, edited or removed, printing which files changed first. Polling keeps
+//! this working on network mounts and in containers where file events are not delivered.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+use crate::config::PROJECT_CONFIG_FILE;
+use crate::display::status;
+
+/// How often the watched files are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Extensions of the files a change in reruns the command
+const WATCHED_EXTENSIONS: [&str; 3] = ["sql", "yml", "yaml"];
+
+/// Modification time and size of every watched file, keyed by path
+pub type Fingerprint = BTreeMap<PathBuf, (SystemTime, u64)>;
+
+/// Run `run` now and again after every change, until the process is interrupted. `skip` is a
+/// file the command writes itself, which must not trigger another run.
+pub fn watch(model_path: &Path, skip: Option<&Path>, mut run: impl FnMut()) -> ! {
+    let mut seen: Option<Fingerprint> = None;
+    loop {
+        let current = fingerprint(model_path, skip);
+        if let Some(previous) = &seen {
+            if *previous != current {
+                status!("\nChange detected:");
+                for change in changes(previous, &current) {
+                    status!("  {}", change);
+                }
+            }
+        }
+        if seen.as_ref() != Some(&current) {
+            run();
+            status!("Watching {} for changes", model_path.display());
+            seen = Some(current);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Watched files under `model_path`, plus the project config
+pub fn fingerprint(model_path: &Path, skip: Option<&Path>) -> Fingerprint {
+    let skip = skip.map(absolute);
+    WalkDir::new(model_path)
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| WATCHED_EXTENSIONS.contains(&extension))
+        })
+        .chain(config_path(model_path))
+        .filter(|path| match &skip {
+            Some(skip) => path.file_name() != skip.file_name() || absolute(path) != *skip,
+            None => true,
+        })
+        .filter_map(|path| {
+            let metadata = fs::metadata(&path).ok()?;
+            Some((path, (metadata.modified().ok()?, metadata.len())))
+        })
+        .collect()
+}
+
+/// What changed between two fingerprints, e.g. `modified: models/orders/orders.sql`
+fn changes(before: &Fingerprint, after: &Fingerprint) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (path, state) in after {
+        match before.get(path) {
+            None => changes.push(format!("added: {}", path.display())),
+            Some(previous) if previous != state => {
+                changes.push(format!("modified: {}", path.display()))
+            }
+            Some(_) => {}
+        }
+    }
+    for path in before.keys().filter(|path| !after.contains_key(*path)) {
+        changes.push(format!("removed: {}", path.display()));
+    }
+    changes
+}
+
+/// The project config that `FeatherFlowConfig::discover` would load for `model_path`
+pub fn config_path(model_path: &Path) -> Option<PathBuf> {
+    let start = model_path
+        .canonicalize()
+        .unwrap_or_else(|_| model_path.to_path_buf());
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_fingerprint_tracks_models_and_reports_changes() {
+        let dir = tempdir().unwrap();
+        let models = dir.path().join("models");
+        fs::create_dir_all(models.join("orders")).unwrap();
+        fs::write(models.join("orders/orders.sql"), "SELECT 1 AS id").unwrap();
+        fs::write(models.join("orders/notes.txt"), "scratch").unwrap();
+        let output = models.join("graph.yml");
+        fs::write(&output, "nodes: []").unwrap();
+
+        let before = fingerprint(&models, Some(&output));
+        assert_eq!(
+            before.keys().collect::<Vec<_>>(),
+            vec![&models.join("orders/orders.sql")]
+        );
+
+        fs::write(models.join("orders/notes.txt"), "more scratch").unwrap();
+        fs::write(&output, "nodes: [orders]").unwrap();
+        assert_eq!(fingerprint(&models, Some(&output)), before);
+
+        fs::write(
+            models.join("orders/orders.yml"),
+            "models:\n  - name: orders\n",
+        )
+        .unwrap();
+        fs::remove_file(models.join("orders/orders.sql")).unwrap();
+        let after = fingerprint(&models, Some(&output));
+        assert_eq!(
+            changes(&before, &after),
+            vec![
+                format!("added: {}", models.join("orders/orders.yml").display()),
+                format!("removed: {}", models.join("orders/orders.sql").display()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! `ff why`: explain how one node of the DAG depends on another
+//!
+//! Prints every path from a model to an upstream model or external source, with the location
+//! of each reference to the next node on the path.
+
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::project::{find_model, load_project};
+use crate::display::{self, status};
+use crate::sql_engine::sql_model::SqlModelCollection;
+
+type WhyResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// An upstream edge: the node a model reads from and the relation it names in its SQL
+#[derive(Debug, Clone, PartialEq)]
+struct Edge {
+    to: String,
+    relation: String,
+}
+
+pub fn why_command(
+    model_path: &Path,
+    target: Option<&str>,
+    vars: Option<&str>,
+    from: &str,
+    to: &str,
+) -> WhyResult<()> {
+    let project = load_project(model_path, target, vars)?;
+    let models = &project.models;
+
+    let from_id = find_model(models, from)?.unique_id.clone();
+    let to_id = match find_model(models, to) {
+        Ok(model) => model.unique_id.clone(),
+        Err(_) => to.to_lowercase(),
+    };
+
+    let edges = upstream_edges(models);
+    let paths = find_paths(&edges, &from_id, &to_id);
+
+    if paths.is_empty() {
+        status!("{} does not depend on {}", from, to);
+        return Ok(());
+    }
+
+    // One record per path: the unique ids from `from` to `to`
+    if display::porcelain() {
+        for path in &paths {
+            display::record(&path.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{} depends on {} through {} path(s):",
+        from.bold(),
+        to.bold(),
+        paths.len()
+    );
+
+    for (index, path) in paths.iter().enumerate() {
+        let names: Vec<&str> = path.iter().map(|id| display_name(models, id)).collect();
+        println!(
+            "\n{} {}",
+            format!("Path {}:", index + 1).bold(),
+            names.join(" -> ")
+        );
+
+        for step in path.windows(2) {
+            let (child_id, parent_id) = (&step[0], &step[1]);
+            let Some(child) = models.get_model(child_id) else {
+                continue;
+            };
+            let relation = edges[child_id]
+                .iter()
+                .find(|edge| &edge.to == parent_id)
+                .map(|edge| edge.relation.as_str())
+                .unwrap_or(parent_id);
+
+            println!("  {} reads {}", child.name, relation);
+            let sql = child.compiled_sql.as_deref().unwrap_or(&child.raw_sql);
+            for location in child
+                .reference_locations
+                .get(relation)
+                .into_iter()
+                .flatten()
+            {
+                let path = child.relative_file_path.display();
+                let reference = match location.line.and_then(|line| sql.lines().nth(line - 1)) {
+                    Some(text) => format!("{}:{}: {}", path, location, text.trim()),
+                    None => format!("{} ({})", path, location),
+                };
+                println!("    {}", reference.dimmed());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Upstream edges of every model, keyed by model unique id. External sources are keyed by
+/// their lowercased relation name.
+fn upstream_edges(models: &SqlModelCollection) -> BTreeMap<String, Vec<Edge>> {
+    let mut edges: BTreeMap<String, Vec<Edge>> = BTreeMap::new();
+
+    for dependency in models.get_implicit_dependencies() {
+        edges
+            .entry(dependency.model_id.clone())
+            .or_default()
+            .push(Edge {
+                to: dependency.parent_id.clone(),
+                relation: dependency.relation.clone(),
+            });
+    }
+
+    for model in models.get_execution_order().unwrap_or_default() {
+        let model_edges = edges.entry(model.unique_id.clone()).or_default();
+        for source in &model.external_sources {
+            model_edges.push(Edge {
+                to: source.to_lowercase(),
+                relation: source.clone(),
+            });
+        }
+        model_edges.sort_by(|a, b| a.to.cmp(&b.to));
+        model_edges.dedup_by(|a, b| a.to == b.to);
+    }
+
+    edges
+}
+
+/// Every path from `from` to `to` following upstream edges, shortest first
+fn find_paths(edges: &BTreeMap<String, Vec<Edge>>, from: &str, to: &str) -> Vec<Vec<String>> {
+    fn visit(
+        edges: &BTreeMap<String, Vec<Edge>>,
+        to: &str,
+        path: &mut Vec<String>,
+        paths: &mut Vec<Vec<String>>,
+    ) {
+        let current = path.last().cloned().unwrap_or_default();
+        if current == to {
+            paths.push(path.clone());
+            return;
+        }
+
+        for edge in edges.get(&current).into_iter().flatten() {
+            // Guard against cycles; the graph should be a DAG but may not be yet
+            if path.contains(&edge.to) {
+                continue;
+            }
+            path.push(edge.to.clone());
+            visit(edges, to, path, paths);
+            path.pop();
+        }
+    }
+
+    if from == to {
+        return Vec::new();
+    }
+
+    let mut paths = Vec::new();
+    visit(edges, to, &mut vec![from.to_string()], &mut paths);
+    paths.sort_by_key(Vec::len);
+    paths
+}
+
+fn display_name<'a>(models: &'a SqlModelCollection, id: &'a str) -> &'a str {
+    models
+        .get_model(id)
+        .map(|model| model.name.as_str())
+        .unwrap_or(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(to: &str) -> Edge {
+        Edge {
+            to: to.to_string(),
+            relation: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_paths_returns_every_path_shortest_first() {
+        let mut edges = BTreeMap::new();
+        edges.insert(
+            "model.summary".to_string(),
+            vec![edge("model.orders"), edge("raw_data.transactions")],
+        );
+        edges.insert(
+            "model.orders".to_string(),
+            vec![edge("raw_data.transactions")],
+        );
+
+        let paths = find_paths(&edges, "model.summary", "raw_data.transactions");
+        assert_eq!(
+            paths,
+            vec![
+                vec!["model.summary", "raw_data.transactions"],
+                vec!["model.summary", "model.orders", "raw_data.transactions"],
+            ]
+        );
+
+        assert!(find_paths(&edges, "model.orders", "model.summary").is_empty());
+    }
+}
@@ -0,0 +1,413 @@
+//! `ff workflow`: run a sequence of steps from a YAML file and track its runs
+//!
+//! A workflow file lists steps that run in order, each in the foreground of the invocation
+//! that started it:
+//!
+//! ```yaml
+//! name: nightly
+//! steps:
+//!   - name: build core
+//!     build: {select: "tag:core", exclude: "tag:slow"}
+//!   - test: {selector: nightly}
+//!   - shell: ./scripts/refresh_dashboards.sh
+//!   - export: {model: fct_orders, path: exports/fct_orders.csv}
+//! ```
+//!
+//! The first failing step fails the run and skips the rest. Every run is recorded in the
+//! state directory (see `state::workflow_runs`), which `list`, `status` and `stop` read.
+
+use anyhow::Context;
+use colored::{Color, Colorize};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use super::build::{build_command, BuildArgs};
+use super::project::{find_model, load_project, project_root};
+use super::test::{test_command, TestArgs};
+use crate::adapters::{self, AccessMode};
+use crate::config::FeatherFlowConfig;
+use crate::display::{self, status};
+use crate::state::state_dir;
+use crate::state::workflow_runs::{WorkflowRun, WorkflowRunStore, WorkflowStatus};
+use crate::table::{Cell, Table, TableFormat};
+
+type WorkflowResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// A workflow file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    pub name: String,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    /// Shown in `ff workflow status`; defaults to the action and its argument
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub action: StepAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepAction {
+    /// `ff build` of a selection
+    Build(SelectionStep),
+    /// `ff test --db` of a selection
+    Test(SelectionStep),
+    /// A command run by `sh -c` in the project root
+    Shell(String),
+    /// A model's relation written to a CSV file
+    Export(ExportStep),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SelectionStep {
+    pub select: Option<String>,
+    /// Name of a selector in `selectors.yml`, used instead of `select`
+    pub selector: Option<String>,
+    pub exclude: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportStep {
+    /// Name or unique id of the model
+    pub model: String,
+    /// CSV file to write, relative to the project root
+    pub path: PathBuf,
+}
+
+impl Workflow {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read workflow file: {}", path.display()))?;
+        let workflow: Workflow = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse workflow file: {}", path.display()))?;
+        if workflow.steps.is_empty() {
+            anyhow::bail!("Workflow '{}' has no steps", workflow.name);
+        }
+        Ok(workflow)
+    }
+}
+
+impl WorkflowStep {
+    pub fn display_name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match &self.action {
+            StepAction::Build(selection) => format!("build {}", selection.describe()),
+            StepAction::Test(selection) => format!("test {}", selection.describe()),
+            StepAction::Shell(command) => format!("shell {}", command),
+            StepAction::Export(export) => {
+                format!("export {} to {}", export.model, export.path.display())
+            }
+        }
+    }
+}
+
+impl SelectionStep {
+    fn describe(&self) -> String {
+        let mut description = match (&self.select, &self.selector) {
+            (_, Some(selector)) => format!("selector {}", selector),
+            (Some(select), None) => select.clone(),
+            (None, None) => "all".to_string(),
+        };
+        if let Some(exclude) = &self.exclude {
+            description.push_str(&format!(" excluding {}", exclude));
+        }
+        description
+    }
+}
+
+pub struct WorkflowStartArgs<'a> {
+    pub file: &'a Path,
+    pub model_path: &'a Path,
+    /// DuckDB database the build, test and export steps run against
+    pub database: &'a Path,
+    pub target: Option<&'a str>,
+    pub vars: Option<&'a str>,
+    pub mode: AccessMode,
+}
+
+/// Run every step of the workflow in `args.file`, recording the run as it goes
+pub fn workflow_start_command(args: &WorkflowStartArgs) -> WorkflowResult<()> {
+    let workflow = Workflow::load(args.file)?;
+    let (store, root) = open_store(args.model_path)?;
+    let steps = workflow
+        .steps
+        .iter()
+        .map(WorkflowStep::display_name)
+        .collect();
+    let mut run = store.update(
+        &WorkflowRun::start(&workflow.name, args.file, steps),
+        |_| {},
+    )?;
+    status!("Started workflow run {}", run.id.bold());
+
+    for (index, step) in workflow.steps.iter().enumerate() {
+        run = store.update(&run, |run| {
+            run.steps[index].status = WorkflowStatus::Running;
+            run.steps[index].started_at = Some(chrono::Utc::now());
+        })?;
+        if run.status == WorkflowStatus::Stopped {
+            status!("Workflow run {} was stopped", run.id);
+            return Err(format!("Workflow run {} was stopped", run.id).into());
+        }
+        status!("\n{} {}", "Step:".cyan(), run.steps[index].name);
+
+        let outcome = run_step(step, args, &root);
+        run = store.update(&run, |run| {
+            let record = &mut run.steps[index];
+            record.finished_at = Some(chrono::Utc::now());
+            match &outcome {
+                Ok(()) => record.status = WorkflowStatus::Success,
+                Err(err) => {
+                    record.status = WorkflowStatus::Failed;
+                    record.message = Some(err.to_string());
+                    run.status = WorkflowStatus::Failed;
+                    run.finished_at = record.finished_at;
+                    for later in run.steps.iter_mut().skip(index + 1) {
+                        later.status = WorkflowStatus::Skipped;
+                        later.message = Some("an earlier step failed".to_string());
+                    }
+                }
+            }
+        })?;
+        if let Err(err) = outcome {
+            return Err(format!(
+                "Workflow run {} failed at step '{}': {}",
+                run.id, run.steps[index].name, err
+            )
+            .into());
+        }
+    }
+
+    let run = store.update(&run, |run| {
+        run.status = WorkflowStatus::Success;
+        run.finished_at = Some(chrono::Utc::now());
+    })?;
+    status!(
+        "\n{} Workflow run {} finished as {}",
+        "OK:".green(),
+        run.id,
+        run.status
+    );
+    Ok(())
+}
+
+fn run_step(step: &WorkflowStep, args: &WorkflowStartArgs, root: &Path) -> WorkflowResult<()> {
+    match &step.action {
+        StepAction::Build(selection) => build_command(&BuildArgs {
+            model_path: args.model_path,
+            database: args.database,
+            target: args.target,
+            vars: args.vars,
+            select: selection.select.as_deref(),
+            selector: selection.selector.as_deref(),
+            exclude: selection.exclude.as_deref(),
+            resume: false,
+            mode: args.mode,
+            stats: false,
+            only: None,
+            fail_stale_sources: false,
+            skip_stale_subgraphs: false,
+        }),
+        StepAction::Test(selection) => test_command(&TestArgs {
+            model_path: args.model_path,
+            target: args.target,
+            vars: args.vars,
+            database: Some(args.database),
+            select: selection.select.as_deref(),
+            selector: selection.selector.as_deref(),
+            exclude: selection.exclude.as_deref(),
+            compiled_snapshots: false,
+            update_snapshots: false,
+            mode: args.mode,
+        }),
+        StepAction::Shell(command) => {
+            let exit = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .current_dir(root)
+                .status()
+                .map_err(|err| format!("Failed to run '{}': {}", command, err))?;
+            if !exit.success() {
+                return Err(format!("'{}' exited with {}", command, exit).into());
+            }
+            Ok(())
+        }
+        StepAction::Export(export) => {
+            if args.mode == AccessMode::ReadOnly {
+                return Err(
+                    "export writes a file through DuckDB and cannot run with --read-only".into(),
+                );
+            }
+            let project = load_project(args.model_path, args.target, args.vars)?;
+            let relation = find_model(&project.models, &export.model)?.qualified_relation();
+            let path = root.join(&export.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let adapter = adapters::connect(args.database, args.mode)?;
+            adapter.query(&format!(
+                "COPY (SELECT * FROM {}) TO {} (HEADER, DELIMITER ',')",
+                relation,
+                adapters::string_literal(&path.to_string_lossy())
+            ))?;
+            status!("Exported {} to {}", relation, path.display());
+            Ok(())
+        }
+    }
+}
+
+/// List every recorded run, newest first
+pub fn workflow_list_command(model_path: &Path, format: &str) -> WorkflowResult<()> {
+    let format = TableFormat::parse(format)?;
+    let (store, _) = open_store(model_path)?;
+    let runs = store.list()?;
+    if runs.is_empty() && format == TableFormat::Table {
+        status!("No workflow runs recorded");
+        return Ok(());
+    }
+
+    let mut table = Table::new(["id", "workflow", "status", "started_at", "finished_at"]);
+    for run in runs {
+        table.push([
+            Cell::new(run.id),
+            Cell::new(run.workflow),
+            Cell::colored(run.status.as_str(), status_color(run.status)),
+            Cell::new(run.started_at.to_rfc3339()),
+            Cell::new(
+                run.finished_at
+                    .map(|at| at.to_rfc3339())
+                    .unwrap_or_default(),
+            ),
+        ]);
+    }
+    table.print(format)?;
+    Ok(())
+}
+
+/// Show one run and the progress of each of its steps
+pub fn workflow_status_command(model_path: &Path, id: &str) -> WorkflowResult<()> {
+    let (store, _) = open_store(model_path)?;
+    let run = store.load(id)?;
+
+    status!(
+        "{} {} ({}), pid {}, started {}",
+        "Run:".bold(),
+        run.id,
+        run.file.display(),
+        run.pid,
+        run.started_at.to_rfc3339()
+    );
+    for step in &run.steps {
+        let seconds = step
+            .duration_secs()
+            .map(|secs| format!("{:.2}", secs))
+            .unwrap_or_default();
+        let message = step.message.as_deref().unwrap_or_default();
+        if display::porcelain() {
+            display::record(&[&step.name, step.status.as_str(), &seconds, message]);
+            continue;
+        }
+        let mut line = format!("{:<8} {}", colored_status(step.status), step.name);
+        if !seconds.is_empty() {
+            line.push_str(&format!(" ({}s)", seconds));
+        }
+        if !message.is_empty() {
+            line.push_str(&format!(": {}", message));
+        }
+        println!("{}", line);
+    }
+    status!("{} {}", "Status:".bold(), colored_status(run.status));
+    Ok(())
+}
+
+/// Mark a run stopped; its runner ends before starting another step
+pub fn workflow_stop_command(model_path: &Path, id: &str) -> WorkflowResult<()> {
+    let (store, _) = open_store(model_path)?;
+    let run = store.stop(id)?;
+    status!(
+        "Stopped workflow run {}; pid {} ends once its current step finishes",
+        run.id,
+        run.pid
+    );
+    Ok(())
+}
+
+fn open_store(model_path: &Path) -> WorkflowResult<(WorkflowRunStore, PathBuf)> {
+    let config = FeatherFlowConfig::discover(model_path)?.unwrap_or_default();
+    let root = project_root(&config, model_path);
+    let store = WorkflowRunStore::new(
+        &state_dir(&root),
+        Duration::from_secs(config.state.lock_timeout_secs),
+    );
+    Ok((store, root))
+}
+
+fn colored_status(status: WorkflowStatus) -> colored::ColoredString {
+    format!("{:<8}", status.as_str()).color(status_color(status))
+}
+
+fn status_color(status: WorkflowStatus) -> Color {
+    match status {
+        WorkflowStatus::Success => Color::Green,
+        WorkflowStatus::Failed => Color::Red,
+        WorkflowStatus::Running => Color::Cyan,
+        _ => Color::Yellow,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_workflow_steps() {
+        let workflow: Workflow = serde_yaml::from_str(
+            r#"
+name: nightly
+steps:
+  - name: build core
+    build: {select: "tag:core"}
+  - test: {}
+  - shell: echo done
+  - export: {model: fct_orders, path: exports/orders.csv}
+"#,
+        )
+        .unwrap();
+
+        let names: Vec<String> = workflow
+            .steps
+            .iter()
+            .map(WorkflowStep::display_name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "build core",
+                "test all",
+                "shell echo done",
+                "export fct_orders to exports/orders.csv",
+            ]
+        );
+        assert_eq!(
+            workflow.steps[0].action,
+            StepAction::Build(SelectionStep {
+                select: Some("tag:core".to_string()),
+                selector: None,
+                exclude: None,
+            })
+        );
+
+        assert!(serde_yaml::from_str::<Workflow>("name: x\nsteps:\n  - deploy: prod\n").is_err());
+    }
+}
@@ -0,0 +1,562 @@
+//! Writing generated files into a project without clobbering the user's edits
+//!
+//! Commands that generate project files (`ff init`, codegen and refactors) write through a
+//! `FileWriter`. New files are created, identical ones left alone, and a file nobody edited
+//! since FeatherFlow last generated it is replaced. When the user did edit it, YAML files
+//! are merged three ways: the base is the copy kept in `state::generated`, so the user's
+//! comments and extra keys survive wherever the generated output did not change the same
+//! lines. Whatever does not merge is a conflict: resolved hunk by hunk at a terminal, and
+//! otherwise left untouched and reported by `finish`. `--force` takes the generated version
+//! and `--dry-run` prints the changes without writing anything.
+//!
+//! Changes to a file the user owns, rather than regenerations of it, go through
+//! `update_yaml`, which edits the YAML in place with `YamlDocument`, or `ensure_line`, which
+//! appends a line such as a `.gitignore` entry.
+
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::display::{self, status};
+use crate::state::{self, generated::GeneratedFiles};
+use crate::yaml_edit::YamlDocument;
+
+type WriterResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Replace changed files with the generated version, conflicts or not
+    pub force: bool,
+    /// Report what would change and write nothing
+    pub dry_run: bool,
+    /// Ask how to resolve conflicts
+    pub interactive: bool,
+}
+
+impl WriteOptions {
+    /// Options for the `--force` and `--dry-run` flags; prompts only when a person is there
+    /// to answer them
+    pub fn from_flags(force: bool, dry_run: bool) -> Self {
+        Self {
+            force,
+            dry_run,
+            interactive: !display::porcelain()
+                && io::stdin().is_terminal()
+                && io::stderr().is_terminal(),
+        }
+    }
+}
+
+/// What happened to one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Created,
+    Unchanged,
+    /// Replaced by the generated version
+    Updated,
+    /// The user's edits merged with the generated version
+    Merged,
+    /// Conflicts left unresolved; the file is untouched
+    Conflict,
+}
+
+impl Outcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Created => "created",
+            Outcome::Unchanged => "unchanged",
+            Outcome::Updated => "updated",
+            Outcome::Merged => "merged",
+            Outcome::Conflict => "conflict",
+        }
+    }
+}
+
+pub struct FileWriter {
+    root: PathBuf,
+    options: WriteOptions,
+    generated: GeneratedFiles,
+    conflicts: Vec<PathBuf>,
+}
+
+impl FileWriter {
+    /// Writer for files under `root`, the project root
+    pub fn new(root: &Path, options: WriteOptions) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            options,
+            generated: GeneratedFiles::new(&state::state_dir(root)),
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// Write `content` to `relative_path` under the root, respecting local edits
+    pub fn write(&mut self, relative_path: &Path, content: &str) -> WriterResult<Outcome> {
+        let path = self.root.join(relative_path);
+        let current = read_existing(&path)?;
+
+        let (outcome, text) = match current.as_deref() {
+            None => (Outcome::Created, content.to_string()),
+            Some(current) if current == content => (Outcome::Unchanged, content.to_string()),
+            Some(_) if self.options.force => (Outcome::Updated, content.to_string()),
+            Some(current) => {
+                let base = self.generated.get(relative_path);
+                if base.as_deref() == Some(current) {
+                    (Outcome::Updated, content.to_string())
+                } else {
+                    let chunks = if is_yaml(relative_path) {
+                        merge(base.as_deref().unwrap_or(""), current, content)
+                    } else {
+                        vec![Chunk::Conflict {
+                            yours: lines(current),
+                            generated: lines(content),
+                        }]
+                    };
+                    self.resolve(relative_path, chunks)?
+                }
+            }
+        };
+
+        self.report(outcome, relative_path, current.as_deref(), &text);
+        if self.options.dry_run {
+            return Ok(outcome);
+        }
+        if outcome == Outcome::Conflict {
+            self.conflicts.push(relative_path.to_path_buf());
+            return Ok(outcome);
+        }
+        if outcome != Outcome::Unchanged {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, &text)
+                .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        }
+        self.generated.record(relative_path, content)?;
+        Ok(outcome)
+    }
+
+    /// Apply `edit` to the YAML file at `relative_path`, creating the file if missing.
+    /// Comments and formatting outside the edited entries are kept.
+    pub fn update_yaml(
+        &mut self,
+        relative_path: &Path,
+        edit: impl FnOnce(&mut YamlDocument) -> anyhow::Result<()>,
+    ) -> WriterResult<Outcome> {
+        let path = self.root.join(relative_path);
+        let current = read_existing(&path)?;
+        let mut document = YamlDocument::parse(current.as_deref().unwrap_or(""))
+            .map_err(|err| format!("{}: {:#}", path.display(), err))?;
+        edit(&mut document).map_err(|err| format!("{}: {:#}", path.display(), err))?;
+        let text = document.to_string();
+
+        let outcome = match current.as_deref() {
+            None => Outcome::Created,
+            Some(current) if current == text => Outcome::Unchanged,
+            Some(_) => Outcome::Updated,
+        };
+        self.report(outcome, relative_path, current.as_deref(), &text);
+        if !self.options.dry_run && outcome != Outcome::Unchanged {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, &text)
+                .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        }
+        Ok(outcome)
+    }
+
+    /// Add `line` to the file the user owns at `relative_path` unless it has the line already,
+    /// creating the file if missing
+    pub fn ensure_line(&mut self, relative_path: &Path, line: &str) -> WriterResult<Outcome> {
+        let path = self.root.join(relative_path);
+        let current = read_existing(&path)?;
+        let (outcome, text) = match current.as_deref() {
+            None => (Outcome::Created, format!("{}\n", line)),
+            Some(current) if current.lines().any(|existing| existing.trim() == line) => {
+                (Outcome::Unchanged, current.to_string())
+            }
+            Some(current) if current.is_empty() || current.ends_with('\n') => {
+                (Outcome::Updated, format!("{}{}\n", current, line))
+            }
+            Some(current) => (Outcome::Updated, format!("{}\n{}\n", current, line)),
+        };
+        self.report(outcome, relative_path, current.as_deref(), &text);
+        if !self.options.dry_run && outcome != Outcome::Unchanged {
+            fs::write(&path, &text)
+                .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        }
+        Ok(outcome)
+    }
+
+    /// Fails when any file was left with unresolved conflicts
+    pub fn finish(self) -> WriterResult<()> {
+        if self.conflicts.is_empty() {
+            return Ok(());
+        }
+        let paths: Vec<String> = self
+            .conflicts
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        Err(format!(
+            "Local changes conflict with the generated version of: {}. Rerun in a terminal to resolve them, or with --force to take the generated version.",
+            paths.join(", ")
+        )
+        .into())
+    }
+
+    /// Merged text, asking about each conflict when interactive
+    fn resolve(&self, relative_path: &Path, chunks: Vec<Chunk>) -> WriterResult<(Outcome, String)> {
+        let conflicts = chunks
+            .iter()
+            .filter(|chunk| matches!(chunk, Chunk::Conflict { .. }))
+            .count();
+        if conflicts > 0 && (self.options.dry_run || !self.options.interactive) {
+            return Ok((Outcome::Conflict, String::new()));
+        }
+
+        let mut text = String::new();
+        let mut seen = 0;
+        for chunk in chunks {
+            match chunk {
+                Chunk::Clean(lines) => text.extend(lines),
+                Chunk::Conflict { yours, generated } => {
+                    seen += 1;
+                    eprintln!(
+                        "\nConflict {}/{} in {}:",
+                        seen,
+                        conflicts,
+                        relative_path.display()
+                    );
+                    print_hunk("yours", &yours);
+                    print_hunk("generated", &generated);
+                    match ask("Keep [y]ours or take [g]enerated? ", &['y', 'g'])? {
+                        'y' => text.extend(yours),
+                        _ => text.extend(generated),
+                    }
+                }
+            }
+        }
+        Ok((Outcome::Merged, text))
+    }
+
+    fn report(&self, outcome: Outcome, relative_path: &Path, current: Option<&str>, text: &str) {
+        let path = relative_path.display().to_string();
+        if display::porcelain() {
+            display::record(&[outcome.as_str(), &path]);
+            return;
+        }
+        let label =
+            if self.options.dry_run && !matches!(outcome, Outcome::Unchanged | Outcome::Conflict) {
+                format!("would be {}", outcome.as_str())
+            } else {
+                outcome.as_str().to_string()
+            };
+        status!("{:<10} {}", label, path);
+        if self.options.dry_run && matches!(outcome, Outcome::Updated | Outcome::Merged) {
+            for line in diff(current.unwrap_or(""), text) {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+fn read_existing(path: &Path) -> WriterResult<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(current) => Ok(Some(current)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("Failed to read {}: {}", path.display(), err).into()),
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("yml" | "yaml")
+    )
+}
+
+fn print_hunk(label: &str, lines: &[&str]) {
+    eprintln!("  {}:", label);
+    for line in lines {
+        eprintln!("    | {}", line.trim_end_matches('\n'));
+    }
+}
+
+/// Read answers from stdin until one is among `choices`
+fn ask(question: &str, choices: &[char]) -> WriterResult<char> {
+    let stdin = io::stdin();
+    loop {
+        eprint!("{}", question);
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer)? == 0 {
+            return Err("No answer given; nothing was written for this file.".into());
+        }
+        if let Some(choice) = answer.trim().chars().next() {
+            let choice = choice.to_ascii_lowercase();
+            if choices.contains(&choice) {
+                return Ok(choice);
+            }
+        }
+    }
+}
+
+/// Lines of `text`, each keeping its newline
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// A run of merged lines, or a place where both sides changed the base differently
+#[derive(Debug, PartialEq)]
+enum Chunk<'a> {
+    Clean(Vec<&'a str>),
+    Conflict {
+        yours: Vec<&'a str>,
+        generated: Vec<&'a str>,
+    },
+}
+
+/// Three-way line merge of the user's version and the generated one against their base
+fn merge<'a>(base: &'a str, yours: &'a str, generated: &'a str) -> Vec<Chunk<'a>> {
+    let (base, yours, generated) = (lines(base), lines(yours), lines(generated));
+    let to_yours = matches(&base, &yours);
+    let to_generated = matches(&base, &generated);
+
+    let mut chunks = Vec::new();
+    let (mut b, mut y, mut g) = (0, 0, 0);
+    loop {
+        // The next base line both sides kept; everything before it changed on one side or both
+        let stable = (b..base.len()).find_map(|i| match (to_yours[i], to_generated[i]) {
+            (Some(yi), Some(gi)) => Some((i, yi, gi)),
+            _ => None,
+        });
+        let (bi, yi, gi) = stable.unwrap_or((base.len(), yours.len(), generated.len()));
+        let (base_part, yours_part, generated_part) =
+            (&base[b..bi], &yours[y..yi], &generated[g..gi]);
+        if yours_part == generated_part || generated_part == base_part {
+            push_clean(&mut chunks, yours_part);
+        } else if yours_part == base_part {
+            push_clean(&mut chunks, generated_part);
+        } else {
+            chunks.push(Chunk::Conflict {
+                yours: yours_part.to_vec(),
+                generated: generated_part.to_vec(),
+            });
+        }
+
+        match stable {
+            Some(_) => {
+                push_clean(&mut chunks, &base[bi..=bi]);
+                (b, y, g) = (bi + 1, yi + 1, gi + 1);
+            }
+            None => break,
+        }
+    }
+    chunks
+}
+
+fn push_clean<'a>(chunks: &mut Vec<Chunk<'a>>, lines: &[&'a str]) {
+    if lines.is_empty() {
+        return;
+    }
+    match chunks.last_mut() {
+        Some(Chunk::Clean(clean)) => clean.extend_from_slice(lines),
+        _ => chunks.push(Chunk::Clean(lines.to_vec())),
+    }
+}
+
+/// For each line of `base`, the line of `other` it corresponds to in their longest common
+/// subsequence
+fn matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let (n, m) = (base.len(), other.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if base[i] == other[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = vec![None; n];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if base[i] == other[j] {
+            result[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+/// Removed and added lines turning `old` into `new`, as `-`/`+` lines
+fn diff(old: &str, new: &str) -> Vec<String> {
+    let (old, new) = (lines(old), lines(new));
+    let mut out = Vec::new();
+    let mut j = 0;
+    for (i, matched) in matches(&old, &new).into_iter().enumerate() {
+        match matched {
+            Some(to) => {
+                out.extend(
+                    new[j..to]
+                        .iter()
+                        .map(|line| format!("+{}", line.trim_end())),
+                );
+                j = to + 1;
+            }
+            None => out.push(format!("-{}", old[i].trim_end())),
+        }
+    }
+    out.extend(new[j..].iter().map(|line| format!("+{}", line.trim_end())));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaml_edit::Step;
+    use tempfile::tempdir;
+
+    const BASE: &str = "version: 2\nmodels:\n  - name: orders\n    description: Orders\n    columns:\n      - name: id\n";
+
+    fn merged(chunks: &[Chunk]) -> String {
+        chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Clean(lines) => lines.concat(),
+                Chunk::Conflict { .. } => panic!("unexpected conflict"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_keeps_user_comments_and_keys() {
+        let yours = "# owned by finance\nversion: 2\nmodels:\n  - name: orders\n    description: Orders\n    meta:\n      owner: finance\n    columns:\n      - name: id\n";
+        let generated = "version: 2\nmodels:\n  - name: orders\n    description: Orders\n    columns:\n      - name: id\n      - name: amount\n";
+
+        assert_eq!(
+            merged(&merge(BASE, yours, generated)),
+            "# owned by finance\nversion: 2\nmodels:\n  - name: orders\n    description: Orders\n    meta:\n      owner: finance\n    columns:\n      - name: id\n      - name: amount\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_conflicts_on_the_same_lines() {
+        let yours = BASE.replace("description: Orders", "description: All orders");
+        let generated = BASE.replace("description: Orders", "description: Order events");
+
+        let chunks = merge(BASE, &yours, &generated);
+        assert_eq!(
+            chunks[1],
+            Chunk::Conflict {
+                yours: vec!["    description: All orders\n"],
+                generated: vec!["    description: Order events\n"],
+            }
+        );
+        assert_eq!(chunks.len(), 3);
+    }
+
+    #[test]
+    fn test_writer_merges_yaml_and_reports_conflicts() {
+        let temp_dir = tempdir().unwrap();
+        let path = Path::new("models/orders/orders.yml");
+        let mut writer = FileWriter::new(temp_dir.path(), WriteOptions::default());
+        assert_eq!(writer.write(path, BASE).unwrap(), Outcome::Created);
+        assert_eq!(writer.write(path, BASE).unwrap(), Outcome::Unchanged);
+
+        // A user edit merges with a new column
+        let edited = format!("# hand written\n{}", BASE);
+        fs::write(temp_dir.path().join(path), &edited).unwrap();
+        let generated = format!("{}      - name: amount\n", BASE);
+        assert_eq!(writer.write(path, &generated).unwrap(), Outcome::Merged);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join(path)).unwrap(),
+            format!("# hand written\n{}", generated)
+        );
+
+        // SQL is not merged: any local edit conflicts, and nothing is written without a prompt
+        let sql = Path::new("models/orders/orders.sql");
+        writer.write(sql, "SELECT 1 AS id\n").unwrap();
+        fs::write(temp_dir.path().join(sql), "SELECT 2 AS id\n").unwrap();
+        assert_eq!(
+            writer.write(sql, "SELECT 3 AS id\n").unwrap(),
+            Outcome::Conflict
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join(sql)).unwrap(),
+            "SELECT 2 AS id\n"
+        );
+        assert!(writer
+            .finish()
+            .unwrap_err()
+            .to_string()
+            .contains("orders.sql"));
+
+        let mut forced = FileWriter::new(
+            temp_dir.path(),
+            WriteOptions {
+                force: true,
+                ..WriteOptions::default()
+            },
+        );
+        assert_eq!(
+            forced.write(sql, "SELECT 3 AS id\n").unwrap(),
+            Outcome::Updated
+        );
+        forced.finish().unwrap();
+    }
+
+    #[test]
+    fn test_update_yaml_edits_in_place() {
+        let temp_dir = tempdir().unwrap();
+        let path = Path::new("models/orders/orders.yml");
+        let mut writer = FileWriter::new(temp_dir.path(), WriteOptions::default());
+        let edited = format!("# hand written\n{}", BASE);
+        fs::create_dir_all(temp_dir.path().join("models/orders")).unwrap();
+        fs::write(temp_dir.path().join(path), &edited).unwrap();
+
+        let outcome = writer
+            .update_yaml(path, |doc| {
+                doc.set(
+                    &[Step::Key("models"), Step::Named("orders")],
+                    "description",
+                    &"All orders",
+                )
+            })
+            .unwrap();
+        assert_eq!(outcome, Outcome::Updated);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join(path)).unwrap(),
+            edited.replace("description: Orders", "description: All orders")
+        );
+    }
+
+    #[test]
+    fn test_dry_run_writes_nothing() {
+        let temp_dir = tempdir().unwrap();
+        let mut writer = FileWriter::new(
+            temp_dir.path(),
+            WriteOptions {
+                dry_run: true,
+                ..WriteOptions::default()
+            },
+        );
+        assert_eq!(
+            writer
+                .write(Path::new("featherflow_project.yaml"), "name: x\n")
+                .unwrap(),
+            Outcome::Created
+        );
+        assert!(fs::read_dir(temp_dir.path()).unwrap().next().is_none());
+        assert_eq!(diff("a\nb\n", "a\nc\n"), vec!["-b", "+c"]);
+    }
+}
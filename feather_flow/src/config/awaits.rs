@@ -0,0 +1,123 @@
+//! Signals a source waits for before the nodes reading it build, configured per source or
+//! table
+//!
+//! ```yaml
+//! sources:
+//!   - name: raw_data
+//!     database: raw_data
+//!     await:
+//!       file: landing/_SUCCESS               # relative to the project root
+//!       timeout_secs: 7200                   # default 3600
+//!       poll_interval_secs: 30               # default 60
+//!     tables:
+//!       - name: orders
+//!       - name: customers
+//!         await:                             # instead of the source's
+//!           sql: SELECT count(*) > 0 FROM raw_data.loads WHERE day = current_date
+//!       - name: events
+//!         await:
+//!           http: https://scheduler.example.com/api/loads/events/done
+//! ```
+//!
+//! A signal holds when its file exists, its query returns a single true value (read like an
+//! `ff assert` assertion), or its URL answers with a success status. `ff build` checks it
+//! before the first node reading the table runs, and again every poll interval until it
+//! holds or the timeout passes, so a scheduled build can wait for upstream loads without an
+//! orchestrator.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// How long a build waits for a signal by default
+pub const DEFAULT_TIMEOUT_SECS: u64 = 3600;
+
+/// How often a signal is checked by default
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+
+/// `await:` as written in a source's YAML
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Await {
+    pub file: Option<String>,
+    pub sql: Option<String>,
+    pub http: Option<String>,
+    pub timeout_secs: Option<u64>,
+    pub poll_interval_secs: Option<u64>,
+}
+
+/// What an await checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal<'a> {
+    /// A path, relative to the project root, that must exist
+    File(&'a str),
+    /// A query that must return a single true value
+    Sql(&'a str),
+    /// A URL that must answer with a success status
+    Http(&'a str),
+}
+
+impl Await {
+    /// The configured signal, or why there is not exactly one
+    pub fn signal(&self) -> Result<Signal<'_>, String> {
+        match (&self.file, &self.sql, &self.http) {
+            (Some(file), None, None) => Ok(Signal::File(file)),
+            (None, Some(sql), None) => Ok(Signal::Sql(sql)),
+            (None, None, Some(url)) => Ok(Signal::Http(url)),
+            (None, None, None) => Err("await needs one of file, sql or http".to_string()),
+            _ => Err("await takes only one of file, sql or http".to_string()),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+
+    /// At least a second, so a zero interval does not check in a busy loop
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.poll_interval_secs
+                .unwrap_or(DEFAULT_POLL_INTERVAL_SECS)
+                .max(1),
+        )
+    }
+}
+
+impl fmt::Display for Signal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::File(path) => write!(f, "file {}", path),
+            Signal::Sql(sql) => write!(f, "query `{}`", sql),
+            Signal::Http(url) => write!(f, "URL {}", url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal() {
+        let file = Await {
+            file: Some("landing/_SUCCESS".to_string()),
+            ..Await::default()
+        };
+        assert_eq!(file.signal(), Ok(Signal::File("landing/_SUCCESS")));
+        assert_eq!(file.timeout(), Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+
+        let both = Await {
+            http: Some("http://localhost/ready".to_string()),
+            poll_interval_secs: Some(0),
+            ..file
+        };
+        assert_eq!(
+            both.signal(),
+            Err("await takes only one of file, sql or http".to_string())
+        );
+        assert_eq!(both.poll_interval(), Duration::from_secs(1));
+        assert_eq!(
+            Await::default().signal(),
+            Err("await needs one of file, sql or http".to_string())
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Criticality tiers and what `ff build` demands of each
+//!
+//! A model's tier comes from `meta.criticality` (or `config.criticality`): `p1` for models
+//! the business depends on, down to `p3`. Each tier's rules can be set in
+//! `featherflow_project.yaml`:
+//!
+//! ```yaml
+//! criticality:
+//!   p1:
+//!     require: [tests, owner]
+//!     enforce_contract: true
+//!     on_failure: abort
+//!   p3:
+//!     on_failure: warn
+//! ```
+//!
+//! A tier that is configured replaces its defaults entirely. By default p1 models must have
+//! tests, their built columns must match the declared ones, and their failure aborts the
+//! run; p2 and untiered models skip their downstream nodes on failure; p3 failures only warn.
+
+use serde::{Deserialize, Serialize};
+
+use super::policies::Requirement;
+
+/// How much a model matters to the business, from `meta.criticality`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Criticality {
+    P1,
+    P2,
+    P3,
+}
+
+impl Criticality {
+    /// Most critical first
+    pub const ALL: [Criticality; 3] = [Criticality::P1, Criticality::P2, Criticality::P3];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "p1" => Some(Criticality::P1),
+            "p2" => Some(Criticality::P2),
+            "p3" => Some(Criticality::P3),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Criticality::P1 => "p1",
+            Criticality::P2 => "p2",
+            Criticality::P3 => "p3",
+        }
+    }
+}
+
+/// What a failure of a model, or of one of its tests, does to the rest of the run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnFailure {
+    /// Stop the run; nothing after it is built
+    Abort,
+    /// Skip everything downstream of the model
+    #[default]
+    SkipDownstream,
+    /// Report the failure and carry on; it does not fail the run
+    Warn,
+}
+
+/// Rules `ff build` applies to the models of one tier
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TierRules {
+    /// Properties a model must have before the build starts
+    #[serde(default)]
+    pub require: Vec<Requirement>,
+    /// The built relation must have exactly the columns declared in YAML, with their types
+    #[serde(default)]
+    pub enforce_contract: bool,
+    #[serde(default)]
+    pub on_failure: OnFailure,
+}
+
+/// Rules of models without a tier
+static UNTIERED: TierRules = TierRules {
+    require: Vec::new(),
+    enforce_contract: false,
+    on_failure: OnFailure::SkipDownstream,
+};
+
+impl TierRules {
+    fn p1() -> Self {
+        Self {
+            require: vec![Requirement::Tests],
+            enforce_contract: true,
+            on_failure: OnFailure::Abort,
+        }
+    }
+
+    fn p3() -> Self {
+        Self {
+            on_failure: OnFailure::Warn,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CriticalityConfig {
+    #[serde(default = "TierRules::p1")]
+    pub p1: TierRules,
+    #[serde(default)]
+    pub p2: TierRules,
+    #[serde(default = "TierRules::p3")]
+    pub p3: TierRules,
+}
+
+impl Default for CriticalityConfig {
+    fn default() -> Self {
+        Self {
+            p1: TierRules::p1(),
+            p2: TierRules::default(),
+            p3: TierRules::p3(),
+        }
+    }
+}
+
+impl CriticalityConfig {
+    pub fn rules(&self, tier: Option<Criticality>) -> &TierRules {
+        match tier {
+            Some(Criticality::P1) => &self.p1,
+            Some(Criticality::P2) => &self.p2,
+            Some(Criticality::P3) => &self.p3,
+            None => &UNTIERED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_tiers_replace_their_defaults() {
+        let config: CriticalityConfig =
+            serde_yaml::from_str("p1:\n  require: [owner]\n  on_failure: skip_downstream\n")
+                .unwrap();
+
+        assert_eq!(config.p1.require, vec![Requirement::Owner]);
+        assert!(!config.p1.enforce_contract);
+        assert_eq!(
+            config.rules(Some(Criticality::P3)).on_failure,
+            OnFailure::Warn
+        );
+        assert_eq!(config.rules(None).on_failure, OnFailure::SkipDownstream);
+        assert_eq!(
+            CriticalityConfig::default().rules(Some(Criticality::P1)),
+            &TierRules::p1()
+        );
+    }
+}
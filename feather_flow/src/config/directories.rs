@@ -0,0 +1,223 @@
+//! Defaults for the models under a directory
+//!
+//! ```yaml
+//! models:
+//!   staging:
+//!     materialized: view
+//!     schema: staging
+//!     tags: [staging]
+//!   marts:
+//!     materialized: table
+//!     finance:
+//!       schema: marts_finance
+//!   legacy:
+//!     enabled: false
+//! ```
+//!
+//! Keys are directories relative to the model path, either nested as above or written as a
+//! path such as `marts/finance`. Any key that is not a setting names a subdirectory. A model
+//! takes the settings of every directory containing it, deeper directories winning, and its
+//! own YAML wins over all of them. Tags accumulate. Models under a directory with
+//! `enabled: false` are left out of the project.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Keys of a directory's mapping that are settings rather than subdirectories
+const SETTINGS: [&str; 4] = ["enabled", "materialized", "schema", "tags"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryConfig {
+    pub enabled: Option<bool>,
+    pub materialized: Option<String>,
+    pub schema: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A model's directory settings and the directory each came from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResolvedConfig {
+    pub config: DirectoryConfig,
+    /// Directory, as keyed in the flattened `models:`, by setting name. Tags list every
+    /// directory that added some.
+    pub sources: BTreeMap<&'static str, String>,
+}
+
+/// Deserialize `models:`, flattening nested directories into keys such as `marts/finance`
+pub fn deserialize_tree<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, DirectoryConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let tree = Option::<Mapping>::deserialize(deserializer)?.unwrap_or_default();
+    let mut configs = BTreeMap::new();
+    flatten(&tree, "", &mut configs).map_err(D::Error::custom)?;
+    Ok(configs)
+}
+
+fn flatten(
+    tree: &Mapping,
+    prefix: &str,
+    configs: &mut BTreeMap<String, DirectoryConfig>,
+) -> Result<(), String> {
+    for (key, value) in tree {
+        let Some(key) = key.as_str() else {
+            return Err(format!(
+                "models: directory keys must be strings, got {:?}",
+                key
+            ));
+        };
+        let dir = match prefix {
+            "" => key.trim_end_matches('/').to_string(),
+            _ => format!("{}/{}", prefix, key.trim_end_matches('/')),
+        };
+        let entries = match value {
+            Value::Mapping(entries) => entries.clone(),
+            Value::Null => Mapping::new(),
+            _ => return Err(format!("models.{}: expected a mapping of settings", dir)),
+        };
+
+        let (settings, subdirectories): (Mapping, Mapping) = entries
+            .into_iter()
+            .partition(|(key, _)| key.as_str().is_some_and(|key| SETTINGS.contains(&key)));
+        if let Some((key, _)) = subdirectories
+            .iter()
+            .find(|(_, value)| !matches!(value, Value::Mapping(_) | Value::Null))
+        {
+            return Err(format!(
+                "models.{}: {:?} is neither a setting ({}) nor a subdirectory",
+                dir,
+                key.as_str().unwrap_or_default(),
+                SETTINGS.join(", ")
+            ));
+        }
+        let config: DirectoryConfig = serde_yaml::from_value(Value::Mapping(settings))
+            .map_err(|err| format!("models.{}: {}", dir, err))?;
+        let merged = configs.entry(dir.clone()).or_default();
+        merged.enabled = config.enabled.or(merged.enabled);
+        merged.materialized = config.materialized.or(merged.materialized.take());
+        merged.schema = config.schema.or(merged.schema.take());
+        for tag in config.tags {
+            if !merged.tags.contains(&tag) {
+                merged.tags.push(tag);
+            }
+        }
+        flatten(&subdirectories, &dir, configs)?;
+    }
+    Ok(())
+}
+
+/// Settings for the model at `relative_file_path` (relative to the model path)
+pub fn resolve(
+    configs: &BTreeMap<String, DirectoryConfig>,
+    relative_file_path: &Path,
+) -> ResolvedConfig {
+    let mut matching: Vec<(&str, &DirectoryConfig)> = configs
+        .iter()
+        .map(|(dir, config)| (dir.trim_end_matches('/'), config))
+        .filter(|(dir, _)| relative_file_path.starts_with(dir))
+        .collect();
+    matching.sort_by_key(|(dir, _)| Path::new(dir).components().count());
+
+    let mut resolved = ResolvedConfig::default();
+    for (dir, config) in matching {
+        if config.enabled.is_some() {
+            resolved.config.enabled = config.enabled;
+            resolved.sources.insert("enabled", dir.to_string());
+        }
+        if config.materialized.is_some() {
+            resolved.config.materialized = config.materialized.clone();
+            resolved.sources.insert("materialized", dir.to_string());
+        }
+        if config.schema.is_some() {
+            resolved.config.schema = config.schema.clone();
+            resolved.sources.insert("schema", dir.to_string());
+        }
+        if !config.tags.is_empty() {
+            let source = resolved.sources.entry("tags").or_default();
+            if !source.is_empty() {
+                source.push_str(", ");
+            }
+            source.push_str(dir);
+        }
+        for tag in &config.tags {
+            if !resolved.config.tags.contains(tag) {
+                resolved.config.tags.push(tag.clone());
+            }
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(yaml: &str) -> Result<BTreeMap<String, DirectoryConfig>, serde_yaml::Error> {
+        deserialize_tree(serde_yaml::Deserializer::from_str(yaml))
+    }
+
+    #[test]
+    fn test_deeper_directories_win_and_tags_accumulate() {
+        let configs = tree(
+            "marts:\n  materialized: table\n  schema: marts\n  tags: [mart]\n\
+             marts/finance/:\n  schema: marts_finance\n  tags: [finance]\n\
+             marts/finance/archive:\n  enabled: false\n\
+             staging:\n  materialized: view\n",
+        )
+        .unwrap();
+
+        let resolved = resolve(&configs, Path::new("marts/finance/revenue/revenue.sql"));
+        assert_eq!(
+            resolved.config,
+            DirectoryConfig {
+                enabled: None,
+                materialized: Some("table".to_string()),
+                schema: Some("marts_finance".to_string()),
+                tags: vec!["mart".to_string(), "finance".to_string()],
+            }
+        );
+        assert_eq!(resolved.sources["materialized"], "marts");
+        assert_eq!(resolved.sources["schema"], "marts/finance");
+        assert_eq!(resolved.sources["tags"], "marts, marts/finance");
+        assert_eq!(
+            resolve(&configs, Path::new("marts/finance/archive/old/old.sql"))
+                .config
+                .enabled,
+            Some(false)
+        );
+        assert_eq!(
+            resolve(&configs, Path::new("marts_old/x/x.sql")),
+            ResolvedConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_nested_directories_flatten() {
+        let nested = tree(
+            "marts:\n  materialized: table\n  finance:\n    schema: finance\n    \
+             ledger:\n      materialized: incremental\n",
+        )
+        .unwrap();
+        assert_eq!(
+            nested.keys().collect::<Vec<_>>(),
+            vec!["marts", "marts/finance", "marts/finance/ledger"]
+        );
+
+        let resolved = resolve(&nested, Path::new("marts/finance/ledger/ledger.sql"));
+        assert_eq!(resolved.config.materialized.as_deref(), Some("incremental"));
+        assert_eq!(resolved.config.schema.as_deref(), Some("finance"));
+        assert_eq!(resolved.sources["schema"], "marts/finance");
+
+        let err = tree("marts:\n  finance:\n    schemaa: finance\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("models.marts/finance: \"schemaa\" is neither a setting"));
+        assert!(tree("marts:\n  tags: mart\n").is_err());
+    }
+}
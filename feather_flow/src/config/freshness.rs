@@ -0,0 +1,153 @@
+//! How recently sources must have been loaded, configured per source or table
+//!
+//! ```yaml
+//! sources:
+//!   - name: raw_data
+//!     database: raw_data
+//!     loaded_at_field: _loaded_at
+//!     freshness:
+//!       warn_after: {count: 12, period: hour}
+//!       error_after: {count: 1, period: day}
+//!     tables:
+//!       - name: orders
+//!       - name: customers
+//!         loaded_at_field: updated_at          # instead of the source's
+//!         freshness:                           # instead of the source's
+//!           error_after: {count: 7, period: day}
+//! ```
+//!
+//! Periods are `minute`, `hour` or `day`. Only tables with a `loaded_at_field` are checked;
+//! `ff source freshness` compares how long ago their latest row was loaded with the
+//! thresholds. Periods are kept as strings so a typo is reported by the check instead of
+//! failing the whole YAML file.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// `freshness:` as written in a source's YAML
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Freshness {
+    pub warn_after: Option<Threshold>,
+    pub error_after: Option<Threshold>,
+}
+
+/// An age such as `{count: 12, period: hour}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Threshold {
+    pub count: u64,
+    pub period: String,
+}
+
+/// Outcome of a source's freshness check, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreshnessStatus {
+    Pass,
+    Warn,
+    Error,
+    /// The check itself failed, e.g. the table is missing or a threshold is invalid
+    RuntimeError,
+}
+
+impl FreshnessStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FreshnessStatus::Pass => "pass",
+            FreshnessStatus::Warn => "warn",
+            FreshnessStatus::Error => "error",
+            FreshnessStatus::RuntimeError => "runtime_error",
+        }
+    }
+}
+
+impl fmt::Display for FreshnessStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Threshold {
+    pub fn seconds(&self) -> Result<i64, String> {
+        let unit = match self.period.as_str() {
+            "minute" => 60,
+            "hour" => 60 * 60,
+            "day" => 24 * 60 * 60,
+            other => {
+                return Err(format!(
+                    "unknown freshness period '{}' (expected minute, hour or day)",
+                    other
+                ))
+            }
+        };
+        i64::try_from(self.count)
+            .ok()
+            .and_then(|count| count.checked_mul(unit))
+            .ok_or_else(|| format!("freshness count {} is too large", self.count))
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.count == 1 { "" } else { "s" };
+        write!(f, "{} {}{}", self.count, self.period, plural)
+    }
+}
+
+impl Freshness {
+    /// Status of a table whose latest row was loaded `age_seconds` ago. A table without
+    /// loaded rows (`None`) is older than any threshold.
+    pub fn status(&self, age_seconds: Option<i64>) -> Result<FreshnessStatus, String> {
+        let exceeded = |threshold: &Option<Threshold>| -> Result<bool, String> {
+            let Some(threshold) = threshold else {
+                return Ok(false);
+            };
+            let limit = threshold.seconds()?;
+            Ok(age_seconds.is_none_or(|age| age > limit))
+        };
+        if exceeded(&self.error_after)? {
+            Ok(FreshnessStatus::Error)
+        } else if exceeded(&self.warn_after)? {
+            Ok(FreshnessStatus::Warn)
+        } else {
+            Ok(FreshnessStatus::Pass)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold(count: u64, period: &str) -> Option<Threshold> {
+        Some(Threshold {
+            count,
+            period: period.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_status_against_thresholds() {
+        let freshness = Freshness {
+            warn_after: threshold(12, "hour"),
+            error_after: threshold(1, "day"),
+        };
+        assert_eq!(freshness.status(Some(3600)), Ok(FreshnessStatus::Pass));
+        assert_eq!(freshness.status(Some(13 * 3600)), Ok(FreshnessStatus::Warn));
+        assert_eq!(
+            freshness.status(Some(2 * 86400)),
+            Ok(FreshnessStatus::Error)
+        );
+        assert_eq!(freshness.status(None), Ok(FreshnessStatus::Error));
+        assert_eq!(Freshness::default().status(None), Ok(FreshnessStatus::Pass));
+        assert_eq!(freshness.warn_after.unwrap().to_string(), "12 hours");
+
+        let typo = Freshness {
+            warn_after: threshold(1, "hours"),
+            error_after: None,
+        };
+        assert_eq!(
+            typo.status(Some(0)),
+            Err("unknown freshness period 'hours' (expected minute, hour or day)".to_string())
+        );
+    }
+}
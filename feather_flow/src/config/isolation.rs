@@ -0,0 +1,143 @@
+//! Per-developer schema isolation for shared development databases
+//!
+//! ```yaml
+//! schema_isolation:
+//!   targets: [dev]
+//!   suffix: "{user}_{branch}"
+//! ```
+//!
+//! For the listed targets every model is deployed to `<schema>_<suffix>` instead of its
+//! configured schema, and references between models are rewritten to match. `{user}` is the
+//! current user and `{branch}` the current git branch; `FF_SCHEMA_SUFFIX` overrides the whole
+//! suffix (useful in CI). `ff clean --schemas` drops a developer's isolated schemas.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Environment variable overriding the configured suffix
+pub const SCHEMA_SUFFIX_ENV: &str = "FF_SCHEMA_SUFFIX";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaIsolationConfig {
+    /// Targets whose schemas are isolated per developer
+    #[serde(default = "default_targets")]
+    pub targets: Vec<String>,
+    /// Suffix appended to every schema; may use `{user}` and `{branch}`
+    #[serde(default = "default_suffix")]
+    pub suffix: String,
+}
+
+fn default_targets() -> Vec<String> {
+    vec!["dev".to_string()]
+}
+
+fn default_suffix() -> String {
+    "{user}".to_string()
+}
+
+impl SchemaIsolationConfig {
+    /// The schema suffix for `target`, or `None` when the target is not isolated
+    pub fn suffix_for(&self, target: &str, project_root: &Path) -> Result<Option<String>> {
+        if !self.targets.iter().any(|isolated| isolated == target) {
+            return Ok(None);
+        }
+
+        let raw = match env::var(SCHEMA_SUFFIX_ENV) {
+            Ok(suffix) => suffix,
+            Err(_) => {
+                let mut suffix = self.suffix.clone();
+                if suffix.contains("{user}") {
+                    suffix = suffix.replace("{user}", &current_user()?);
+                }
+                if suffix.contains("{branch}") {
+                    suffix = suffix.replace("{branch}", &current_branch(project_root)?);
+                }
+                suffix
+            }
+        };
+
+        let suffix = sanitize(&raw);
+        if suffix.is_empty() {
+            bail!(
+                "Schema isolation suffix '{}' is empty once reduced to letters, digits and underscores",
+                raw
+            );
+        }
+        Ok(Some(suffix))
+    }
+}
+
+/// The isolated name of `schema`
+pub fn isolated_schema(schema: &str, suffix: &str) -> String {
+    format!("{}_{}", schema, suffix)
+}
+
+fn current_user() -> Result<String> {
+    match env::var("USER").or_else(|_| env::var("USERNAME")) {
+        Ok(user) if !user.is_empty() => Ok(user),
+        _ => bail!(
+            "Cannot determine the current user for schema isolation; set {}",
+            SCHEMA_SUFFIX_ENV
+        ),
+    }
+}
+
+fn current_branch(project_root: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(project_root)
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        _ => bail!(
+            "Cannot determine the git branch for schema isolation; set {}",
+            SCHEMA_SUFFIX_ENV
+        ),
+    }
+}
+
+/// Lowercase identifier-safe form of `value`: runs of other characters become one underscore
+fn sanitize(value: &str) -> String {
+    let mut sanitized = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c.to_ascii_lowercase());
+        } else if !sanitized.ends_with('_') {
+            sanitized.push('_');
+        }
+    }
+    sanitized.trim_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("Jane.Doe"), "jane_doe");
+        assert_eq!(sanitize("feature/ABC-123--fix"), "feature_abc_123_fix");
+        assert_eq!(sanitize("--"), "");
+    }
+
+    #[test]
+    fn test_suffix_only_for_isolated_targets() {
+        let config: SchemaIsolationConfig = serde_yaml::from_str("suffix: dev_box").unwrap();
+        assert_eq!(config.targets, vec!["dev"]);
+
+        assert_eq!(config.suffix_for("prod", Path::new(".")).unwrap(), None);
+        if env::var(SCHEMA_SUFFIX_ENV).is_err() {
+            assert_eq!(
+                config.suffix_for("dev", Path::new(".")).unwrap().as_deref(),
+                Some("dev_box")
+            );
+        }
+        assert_eq!(isolated_schema("staging", "dev_box"), "staging_dev_box");
+    }
+}
@@ -0,0 +1,185 @@
+//! Materialization strategies, built-in and project-defined
+//!
+//! Projects can register their own strategies (e.g. `insert_overwrite_partition` or
+//! `materialized_view`) as templated DDL recipes under `materializations:` in
+//! `featherflow_project.yaml`:
+//!
+//! ```yaml
+//! materializations:
+//!   materialized_view:
+//!     description: Materialized view refreshed by the warehouse
+//!     ddl: CREATE OR REPLACE MATERIALIZED VIEW {{ this }} AS {{ sql }}
+//! ```
+//!
+//! `{{ this }}` is replaced with the model's fully-qualified relation and `{{ sql }}` with its
+//! compiled SELECT statement.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::FeatherFlowConfig;
+
+/// Materializations that are available without any project configuration
+pub const BUILTIN_MATERIALIZATIONS: &[&str] =
+    &["view", "table", "incremental", "ephemeral", "snapshot"];
+
+/// A project-defined materialization recipe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterializationConfig {
+    /// Human readable description of the strategy
+    pub description: Option<String>,
+    /// Templated DDL executed to build a model with this materialization
+    pub ddl: String,
+}
+
+impl MaterializationConfig {
+    /// Check that the recipe references the placeholders it needs
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.ddl.trim().is_empty() {
+            errors.push(format!("Materialization '{}' has an empty ddl", name));
+            return errors;
+        }
+
+        for placeholder in ["this", "sql"] {
+            if !contains_placeholder(&self.ddl, placeholder) {
+                errors.push(format!(
+                    "Materialization '{}' ddl does not reference {{{{ {} }}}}",
+                    name, placeholder
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Render the recipe for a model's relation and compiled SQL
+    pub fn render(&self, this: &str, sql: &str) -> String {
+        let rendered = replace_placeholder(&self.ddl, "this", this);
+        replace_placeholder(&rendered, "sql", sql.trim().trim_end_matches(';'))
+    }
+}
+
+/// All materializations known to a project
+#[derive(Debug, Clone, Default)]
+pub struct MaterializationRegistry {
+    custom: HashMap<String, MaterializationConfig>,
+}
+
+impl MaterializationRegistry {
+    /// Build the registry from the project configuration
+    pub fn from_config(config: &FeatherFlowConfig) -> Self {
+        Self {
+            custom: config.materializations.clone(),
+        }
+    }
+
+    /// Whether a materialization name is built-in or defined by the project
+    pub fn is_known(&self, name: &str) -> bool {
+        BUILTIN_MATERIALIZATIONS.contains(&name) || self.custom.contains_key(name)
+    }
+
+    /// Get a project-defined recipe by name
+    pub fn get_custom(&self, name: &str) -> Option<&MaterializationConfig> {
+        self.custom.get(name)
+    }
+
+    /// Validate all project-defined recipes
+    pub fn validate_recipes(&self) -> Vec<String> {
+        let mut names: Vec<&String> = self.custom.keys().collect();
+        names.sort();
+
+        let mut errors = Vec::new();
+        for name in names {
+            if BUILTIN_MATERIALIZATIONS.contains(&name.as_str()) {
+                errors.push(format!(
+                    "Materialization '{}' shadows a built-in materialization",
+                    name
+                ));
+            }
+            errors.extend(self.custom[name].validate(name));
+        }
+
+        errors
+    }
+
+    /// All known materialization names, built-ins first
+    pub fn known_names(&self) -> Vec<String> {
+        let mut custom: Vec<String> = self.custom.keys().cloned().collect();
+        custom.sort();
+
+        BUILTIN_MATERIALIZATIONS
+            .iter()
+            .map(|name| name.to_string())
+            .chain(custom)
+            .collect()
+    }
+}
+
+fn contains_placeholder(template: &str, name: &str) -> bool {
+    template.contains(&format!("{{{{ {} }}}}", name))
+        || template.contains(&format!("{{{{{}}}}}", name))
+}
+
+fn replace_placeholder(template: &str, name: &str, value: &str) -> String {
+    template
+        .replace(&format!("{{{{ {} }}}}", name), value)
+        .replace(&format!("{{{{{}}}}}", name), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(ddl: &str) -> MaterializationConfig {
+        MaterializationConfig {
+            description: None,
+            ddl: ddl.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_recipe() {
+        let mv = recipe("CREATE OR REPLACE MATERIALIZED VIEW {{ this }} AS {{sql}}");
+        assert_eq!(
+            mv.render("analytics.marts.daily", "SELECT 1;\n"),
+            "CREATE OR REPLACE MATERIALIZED VIEW analytics.marts.daily AS SELECT 1"
+        );
+    }
+
+    #[test]
+    fn test_validate_recipe_placeholders() {
+        assert!(recipe("INSERT INTO {{ this }} {{ sql }}")
+            .validate("ok")
+            .is_empty());
+
+        let errors = recipe("INSERT INTO foo {{ sql }}").validate("broken");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("{{ this }}"));
+
+        assert_eq!(recipe("  ").validate("empty").len(), 1);
+    }
+
+    #[test]
+    fn test_registry_known_names() {
+        let mut config = FeatherFlowConfig::default();
+        config.materializations.insert(
+            "materialized_view".to_string(),
+            recipe("CREATE MATERIALIZED VIEW {{ this }} AS {{ sql }}"),
+        );
+        config.materializations.insert(
+            "table".to_string(),
+            recipe("CREATE TABLE {{ this }} AS {{ sql }}"),
+        );
+
+        let registry = MaterializationRegistry::from_config(&config);
+        assert!(registry.is_known("view"));
+        assert!(registry.is_known("materialized_view"));
+        assert!(!registry.is_known("insert_overwrite_partition"));
+
+        let errors = registry.validate_recipes();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("shadows a built-in"));
+    }
+}
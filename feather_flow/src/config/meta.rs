@@ -0,0 +1,107 @@
+//! Declared `meta` keys and the types their values must have
+//!
+//! ```yaml
+//! meta_schema:
+//!   owner: {type: string, required: true}
+//!   slack_channel: {type: string, pattern: "^#"}
+//!   domain: {type: string, values: [sales, finance, marketing]}
+//!   cost_center: {type: integer}
+//! ```
+//!
+//! `owner`, `team`, `domain` and `slack_channel` are strings and `criticality` one of `p1`,
+//! `p2` and `p3` whether declared or not; declaring them only adds requirements. Keys nobody
+//! declared stay free-form.
+
+use serde::{Deserialize, Serialize};
+
+use super::criticality::Criticality;
+
+/// Team or person accountable for the model
+pub const OWNER: &str = "owner";
+pub const TEAM: &str = "team";
+/// Business domain, e.g. finance
+pub const DOMAIN: &str = "domain";
+/// Tier from `p1` to `p3`, see `config::criticality`
+pub const CRITICALITY: &str = "criticality";
+/// Where to reach the owner about the model
+pub const SLACK_CHANNEL: &str = "slack_channel";
+
+/// Types a meta value can be declared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetaType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    List,
+}
+
+impl MetaType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MetaType::String => "string",
+            MetaType::Integer => "integer",
+            MetaType::Number => "number",
+            MetaType::Boolean => "boolean",
+            MetaType::List => "list",
+        }
+    }
+
+    /// Whether `value` has this type
+    pub fn accepts(self, value: &serde_json::Value) -> bool {
+        match self {
+            MetaType::String => value.is_string(),
+            MetaType::Integer => value.is_i64() || value.is_u64(),
+            MetaType::Number => value.is_number(),
+            MetaType::Boolean => value.is_boolean(),
+            MetaType::List => value.is_array(),
+        }
+    }
+}
+
+/// A declared meta key as written in `featherflow_project.yaml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetaKeyConfig {
+    #[serde(rename = "type")]
+    pub kind: MetaType,
+    /// Every model must set the key
+    #[serde(default)]
+    pub required: bool,
+    /// Values a string key may take
+    #[serde(default)]
+    pub values: Vec<String>,
+    /// Regex string values must match
+    pub pattern: Option<String>,
+}
+
+impl MetaKeyConfig {
+    fn string() -> Self {
+        Self {
+            kind: MetaType::String,
+            required: false,
+            values: Vec::new(),
+            pattern: None,
+        }
+    }
+}
+
+/// Keys with a meaning of their own and the declaration they have without `meta_schema:`
+pub fn built_in_keys() -> Vec<(&'static str, MetaKeyConfig)> {
+    vec![
+        (OWNER, MetaKeyConfig::string()),
+        (TEAM, MetaKeyConfig::string()),
+        (DOMAIN, MetaKeyConfig::string()),
+        (
+            CRITICALITY,
+            MetaKeyConfig {
+                values: Criticality::ALL
+                    .iter()
+                    .map(|tier| tier.as_str().to_string())
+                    .collect(),
+                ..MetaKeyConfig::string()
+            },
+        ),
+        (SLACK_CHANNEL, MetaKeyConfig::string()),
+    ]
+}
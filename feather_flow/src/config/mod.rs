@@ -0,0 +1,380 @@
+//! Project configuration loaded from `featherflow_project.yaml`
+
+pub mod awaits;
+pub mod criticality;
+pub mod directories;
+pub mod freshness;
+pub mod isolation;
+pub mod materializations;
+pub mod meta;
+pub mod naming;
+pub mod partitioning;
+pub mod policies;
+pub mod profiles;
+pub mod selectors;
+pub mod snapshots;
+pub mod vars;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::FeatherFlowError;
+use crate::sql_engine::checksum::{ChecksumMode, HashKind};
+use criticality::CriticalityConfig;
+use directories::DirectoryConfig;
+use isolation::SchemaIsolationConfig;
+use materializations::MaterializationConfig;
+use meta::MetaKeyConfig;
+use naming::NamingRule;
+use policies::PolicyConfig;
+use vars::VarDefinition;
+
+/// File name of the project configuration
+pub const PROJECT_CONFIG_FILE: &str = "featherflow_project.yaml";
+
+/// Model directory, relative to the project root, when `models_path` is not set
+pub const DEFAULT_MODELS_PATH: &str = "models";
+
+/// Environment variable naming the user-level configuration file, overriding the default of
+/// `$XDG_CONFIG_HOME/featherflow/config.yaml` (or `~/.config/featherflow/config.yaml`)
+pub const USER_CONFIG_ENV: &str = "FF_USER_CONFIG";
+
+/// Environment variable choosing the target when no `--target` is given
+pub const TARGET_ENV: &str = "FF_TARGET";
+
+/// Top-level project configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeatherFlowConfig {
+    /// Project name
+    pub name: Option<String>,
+    /// Profile in `profiles.yml` holding the project's connections; the project name when unset
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Model directory relative to the project root, used when a command is run without
+    /// `--model-path`; `models` when unset
+    #[serde(default)]
+    pub models_path: Option<PathBuf>,
+    /// SQL dialect the models are written in and compiled for
+    #[serde(default)]
+    pub dialect: SqlDialect,
+    /// Defaults for the models under each directory, keyed by path relative to the model path;
+    /// nested directories are flattened into keys such as `marts/finance`
+    #[serde(default, deserialize_with = "directories::deserialize_tree")]
+    pub models: BTreeMap<String, DirectoryConfig>,
+    /// Project-defined materialization strategies, keyed by the name used in `materialized:`
+    #[serde(default)]
+    pub materializations: HashMap<String, MaterializationConfig>,
+    /// Regex naming conventions enforced by validation
+    #[serde(default)]
+    pub naming_rules: Vec<NamingRule>,
+    /// Governance policies evaluated against the model graph
+    #[serde(default)]
+    pub policies: Vec<PolicyConfig>,
+    /// Expected `meta` keys and the types of their values
+    #[serde(default)]
+    pub meta_schema: BTreeMap<String, MetaKeyConfig>,
+    /// Least share of models with a test, keyed by directory relative to the model path
+    #[serde(default)]
+    pub require_tests: BTreeMap<String, f64>,
+    /// What `ff build` demands of the models of each criticality tier
+    #[serde(default)]
+    pub criticality: CriticalityConfig,
+    /// Typed variables models can reference with `{{ var('name') }}`
+    #[serde(default)]
+    pub vars: Vec<VarDefinition>,
+    /// Settings for the local state directory
+    #[serde(default)]
+    pub state: StateConfig,
+    /// Per-developer schemas for shared development databases
+    #[serde(default)]
+    pub schema_isolation: Option<SchemaIsolationConfig>,
+    /// Target used when neither `--target` nor `FF_TARGET` is given
+    #[serde(default)]
+    pub default_target: Option<String>,
+    /// Terminal output preferences, read from the user-level file only
+    #[serde(default)]
+    pub display: DisplayConfig,
+    /// Directory containing the configuration file
+    #[serde(skip)]
+    pub project_root: PathBuf,
+}
+
+/// SQL dialects models can be written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlDialect {
+    #[default]
+    DuckDb,
+    /// Compiled only: `ff compile` emits the DDL, nothing is executed
+    BigQuery,
+}
+
+impl SqlDialect {
+    /// Name exposed as `{{ target.type }}` and recorded on each model
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SqlDialect::DuckDb => "duckdb",
+            SqlDialect::BigQuery => "bigquery",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "duckdb" => Some(SqlDialect::DuckDb),
+            "bigquery" => Some(SqlDialect::BigQuery),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for the local state directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateConfig {
+    /// Seconds to wait for another invocation to release the state lock
+    #[serde(default = "default_lock_timeout_secs")]
+    pub lock_timeout_secs: u64,
+    /// Checksum comparing models between invocations, for resuming builds, skipping stale
+    /// subgraphs and caching previews; the parse cache always compares the raw text
+    #[serde(default)]
+    pub checksum: ChecksumMode,
+    /// Algorithm computing checksums and cache keys
+    #[serde(default)]
+    pub hash: HashKind,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            lock_timeout_secs: default_lock_timeout_secs(),
+            checksum: ChecksumMode::default(),
+            hash: HashKind::default(),
+        }
+    }
+}
+
+fn default_lock_timeout_secs() -> u64 {
+    crate::state::lock::DEFAULT_LOCK_TIMEOUT_SECS
+}
+
+/// Terminal output preferences. Output is configured before any project is found, so these
+/// come from the user-level file; `--no-color`, `--no-emoji` and `NO_COLOR` still apply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayConfig {
+    #[serde(default = "enabled")]
+    pub color: bool,
+    #[serde(default = "enabled")]
+    pub emoji: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            color: true,
+            emoji: true,
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl FeatherFlowConfig {
+    /// Load the configuration from a specific file, on top of the user-level configuration
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::load_layered(path, user_config_path().as_deref())
+    }
+
+    /// Load the project file at `path` with the settings it leaves out taken from
+    /// `user_file`, when that exists
+    pub fn load_layered(path: &Path, user_file: Option<&Path>) -> Result<Self> {
+        let mut layers = Value::Mapping(Mapping::new());
+        if let Some(user_file) = user_file.filter(|file| file.is_file()) {
+            merge(&mut layers, read_layer(user_file, "user config")?);
+        }
+        merge(&mut layers, read_layer(path, "project config")?);
+
+        let mut config: FeatherFlowConfig = serde_yaml::from_value(layers).map_err(|err| {
+            FeatherFlowError::InvalidProjectConfig {
+                path: path.to_path_buf(),
+                reason: err.to_string(),
+            }
+        })?;
+        config.project_root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        Ok(config)
+    }
+
+    /// Find the project configuration by walking up from `start` (usually the model path)
+    pub fn discover(start: &Path) -> Result<Option<Self>> {
+        let start = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+
+        for dir in start.ancestors() {
+            let candidate = dir.join(PROJECT_CONFIG_FILE);
+            if candidate.is_file() {
+                return Self::load(&candidate).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The project's model directory: `models_path` under the project root
+    pub fn models_dir(&self) -> PathBuf {
+        self.project_root.join(
+            self.models_path
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_MODELS_PATH)),
+        )
+    }
+
+    /// The target to use: `cli` (`--target`), else `FF_TARGET`, else `default_target`
+    pub fn target(&self, cli: Option<&str>) -> Option<String> {
+        cli.map(str::to_string)
+            .or_else(|| {
+                env::var(TARGET_ENV)
+                    .ok()
+                    .filter(|target| !target.is_empty())
+            })
+            .or_else(|| self.default_target.clone())
+    }
+
+    /// Validate the declared `vars` against the `--vars` argument (if any), returning each
+    /// variable's value rendered for SQL
+    pub fn resolve_vars(&self, cli_vars: Option<&str>) -> Result<BTreeMap<String, String>> {
+        let overrides = match cli_vars {
+            Some(input) => vars::parse_cli_vars(input)?,
+            None => HashMap::new(),
+        };
+        vars::resolve_vars(&self.vars, &overrides, self.dialect)
+    }
+}
+
+/// The user-level configuration file, which need not exist
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(USER_CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    let dir = config_home.join("featherflow");
+    let yml = dir.join("config.yml");
+    if yml.is_file() && !dir.join("config.yaml").is_file() {
+        return Some(yml);
+    }
+    Some(dir.join("config.yaml"))
+}
+
+/// Display preferences from the user-level file, the defaults when it is missing or invalid
+pub fn user_display_config() -> DisplayConfig {
+    let Some(path) = user_config_path().filter(|path| path.is_file()) else {
+        return DisplayConfig::default();
+    };
+    read_layer(&path, "user config")
+        .and_then(|layer| Ok(serde_yaml::from_value::<FeatherFlowConfig>(layer)?))
+        .map(|config| config.display)
+        .unwrap_or_else(|err| {
+            eprintln!("Warning: ignoring {}: {:#}", path.display(), err);
+            DisplayConfig::default()
+        })
+}
+
+/// One configuration file as YAML, an empty mapping when the file is empty
+pub fn read_layer(path: &Path, what: &str) -> Result<Value> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}: {}", what, path.display()))?;
+    let value: Value =
+        serde_yaml::from_str(&content).map_err(|err| FeatherFlowError::yaml_parse(path, err))?;
+    Ok(match value {
+        Value::Null => Value::Mapping(Mapping::new()),
+        value => value,
+    })
+}
+
+/// Merge `over` into `base`: mappings key by key, anything else replaced whole
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Mapping(base), Value::Mapping(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discover_walks_up_from_model_path() {
+        let temp_dir = tempdir().unwrap();
+        let models_dir = temp_dir.path().join("models").join("staging");
+        fs::create_dir_all(&models_dir).unwrap();
+        fs::write(
+            temp_dir.path().join(PROJECT_CONFIG_FILE),
+            "name: example\nprofile: dev\n",
+        )
+        .unwrap();
+
+        let config = FeatherFlowConfig::discover(&models_dir)
+            .unwrap()
+            .expect("config should be found");
+
+        assert_eq!(config.name.as_deref(), Some("example"));
+        assert_eq!(config.dialect, SqlDialect::DuckDb);
+        assert!(config.materializations.is_empty());
+        assert_eq!(config.project_root, temp_dir.path().canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_discover_without_config() {
+        let temp_dir = tempdir().unwrap();
+        assert!(FeatherFlowConfig::discover(temp_dir.path())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_project_file_wins_over_user_file() {
+        let temp_dir = tempdir().unwrap();
+        let user_file = temp_dir.path().join("user.yaml");
+        fs::write(
+            &user_file,
+            "dialect: bigquery\nstate:\n  lock_timeout_secs: 5\n",
+        )
+        .unwrap();
+        let project_file = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&project_file, "name: example\ndialect: duckdb\n").unwrap();
+
+        let config = FeatherFlowConfig::load_layered(&project_file, Some(&user_file)).unwrap();
+        assert_eq!(config.dialect, SqlDialect::DuckDb);
+        assert_eq!(config.state.lock_timeout_secs, 5);
+        assert_eq!(config.name.as_deref(), Some("example"));
+    }
+
+    #[test]
+    fn test_target_flag_wins_over_default_target() {
+        let config = FeatherFlowConfig {
+            default_target: Some("dev".to_string()),
+            ..FeatherFlowConfig::default()
+        };
+        assert_eq!(config.target(Some("prod")).as_deref(), Some("prod"));
+        if env::var(TARGET_ENV).is_err() {
+            assert_eq!(config.target(None).as_deref(), Some("dev"));
+        }
+    }
+}
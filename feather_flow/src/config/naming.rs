@@ -0,0 +1,29 @@
+//! Naming-convention rules configured per directory
+//!
+//! ```yaml
+//! naming_rules:
+//!   - path: staging
+//!     models: "^stg_"
+//!   - path: marts
+//!     models_must_not: "^stg_"
+//!   - columns: "^[a-z][a-z0-9_]*$"
+//! ```
+//!
+//! Patterns are regular expressions. A rule without `path` applies to every model.
+
+use serde::{Deserialize, Serialize};
+
+/// A single naming rule as written in `featherflow_project.yaml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NamingRule {
+    /// Directory, relative to the model path, whose models the rule applies to
+    pub path: Option<String>,
+    /// Pattern model names must match
+    pub models: Option<String>,
+    /// Pattern model names must not match
+    pub models_must_not: Option<String>,
+    /// Pattern column names must match
+    pub columns: Option<String>,
+    /// Pattern column names must not match
+    pub columns_must_not: Option<String>,
+}
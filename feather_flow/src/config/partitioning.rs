@@ -0,0 +1,232 @@
+//! BigQuery partitioning and clustering, configured per model
+//!
+//! ```yaml
+//! models:
+//!   - name: fct_events
+//!     config:
+//!       materialized: table
+//!       partition_by:
+//!         field: event_at
+//!         data_type: timestamp   # date (default), timestamp, datetime or int64
+//!         granularity: day       # hour, day (default), month or year
+//!       cluster_by: [customer_id, event_type]
+//! ```
+//!
+//! Integer-range partitions take a range instead of a granularity:
+//! `{field: customer_id, data_type: int64, range: {start: 0, end: 1000, interval: 10}}`.
+//! Both settings are rendered into the DDL `ff compile` emits for BigQuery; DuckDB has no
+//! equivalent and ignores them.
+
+use serde::{Deserialize, Serialize};
+
+/// Most columns BigQuery accepts in `CLUSTER BY`
+pub const MAX_CLUSTER_COLUMNS: usize = 4;
+
+/// `partition_by` as written in a model's YAML. Types and granularities are kept as
+/// strings so mistakes are reported by validation instead of failing the whole YAML file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionBy {
+    pub field: String,
+    pub data_type: Option<String>,
+    pub granularity: Option<String>,
+    pub range: Option<PartitionRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PartitionRange {
+    pub start: i64,
+    pub end: i64,
+    pub interval: i64,
+}
+
+/// `cluster_by`: one column or a list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ClusterBy {
+    Column(String),
+    Columns(Vec<String>),
+}
+
+impl ClusterBy {
+    pub fn into_columns(self) -> Vec<String> {
+        match self {
+            ClusterBy::Column(column) => vec![column],
+            ClusterBy::Columns(columns) => columns,
+        }
+    }
+}
+
+impl PartitionBy {
+    fn data_type(&self) -> String {
+        self.data_type.as_deref().unwrap_or("date").to_lowercase()
+    }
+
+    fn granularity(&self) -> String {
+        self.granularity.as_deref().unwrap_or("day").to_uppercase()
+    }
+
+    /// Problems with the configuration; `to_sql` is only meaningful when there are none
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.field.trim().is_empty() {
+            errors.push("partition_by needs a field".to_string());
+        }
+
+        let data_type = self.data_type();
+        let granularities: &[&str] = match data_type.as_str() {
+            "date" => &["DAY", "MONTH", "YEAR"],
+            "timestamp" | "datetime" => &["HOUR", "DAY", "MONTH", "YEAR"],
+            "int64" => &[],
+            other => {
+                errors.push(format!(
+                    "partition_by data_type '{}' is not one of date, timestamp, datetime, int64",
+                    other
+                ));
+                return errors;
+            }
+        };
+
+        match (&self.range, data_type.as_str()) {
+            (None, "int64") => errors.push("int64 partitioning needs a range".to_string()),
+            (Some(_), "int64") => {}
+            (Some(_), other) => errors.push(format!(
+                "partition_by range only applies to int64, not {}",
+                other
+            )),
+            (None, _) => {}
+        }
+        if let Some(range) = &self.range {
+            if range.interval <= 0 || range.start >= range.end {
+                errors.push(format!(
+                    "partition_by range needs start < end and a positive interval, got start {}, end {}, interval {}",
+                    range.start, range.end, range.interval
+                ));
+            }
+        }
+
+        if let Some(granularity) = &self.granularity {
+            if !granularities.contains(&self.granularity().as_str()) {
+                errors.push(format!(
+                    "partition_by granularity '{}' does not apply to {} (expected {})",
+                    granularity,
+                    data_type,
+                    if granularities.is_empty() {
+                        "none".to_string()
+                    } else {
+                        granularities.join(", ").to_lowercase()
+                    }
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// The BigQuery `PARTITION BY` expression
+    pub fn to_sql(&self) -> String {
+        let granularity = self.granularity();
+        match self.data_type().as_str() {
+            "int64" => {
+                let range = self.range.unwrap_or(PartitionRange {
+                    start: 0,
+                    end: 0,
+                    interval: 1,
+                });
+                format!(
+                    "RANGE_BUCKET({}, GENERATE_ARRAY({}, {}, {}))",
+                    self.field, range.start, range.end, range.interval
+                )
+            }
+            "date" if granularity == "DAY" => self.field.clone(),
+            data_type => format!(
+                "{}_TRUNC({}, {})",
+                data_type.to_uppercase(),
+                self.field,
+                granularity
+            ),
+        }
+    }
+}
+
+/// Problems with a `cluster_by` list
+pub fn validate_cluster_by(columns: &[String]) -> Vec<String> {
+    let mut errors = Vec::new();
+    if columns.iter().any(|column| column.trim().is_empty()) {
+        errors.push("cluster_by has an empty column name".to_string());
+    }
+    if columns.len() > MAX_CLUSTER_COLUMNS {
+        errors.push(format!(
+            "cluster_by lists {} columns; BigQuery allows at most {}",
+            columns.len(),
+            MAX_CLUSTER_COLUMNS
+        ));
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partition(yaml: &str) -> PartitionBy {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_partition_expressions() {
+        assert_eq!(partition("field: order_date").to_sql(), "order_date");
+        assert_eq!(
+            partition("{field: order_date, granularity: month}").to_sql(),
+            "DATE_TRUNC(order_date, MONTH)"
+        );
+        assert_eq!(
+            partition("{field: event_at, data_type: timestamp}").to_sql(),
+            "TIMESTAMP_TRUNC(event_at, DAY)"
+        );
+        assert_eq!(
+            partition("{field: id, data_type: int64, range: {start: 0, end: 100, interval: 10}}")
+                .to_sql(),
+            "RANGE_BUCKET(id, GENERATE_ARRAY(0, 100, 10))"
+        );
+    }
+
+    #[test]
+    fn test_partition_validation() {
+        assert!(
+            partition("{field: event_at, data_type: timestamp, granularity: hour}")
+                .validate()
+                .is_empty()
+        );
+        assert_eq!(
+            partition("{field: d, granularity: hour}").validate(),
+            vec!["partition_by granularity 'hour' does not apply to date (expected day, month, year)"]
+        );
+        assert_eq!(
+            partition("{field: id, data_type: int64}").validate(),
+            vec!["int64 partitioning needs a range"]
+        );
+        assert_eq!(
+            partition("{field: d, data_type: string}").validate(),
+            vec!["partition_by data_type 'string' is not one of date, timestamp, datetime, int64"]
+        );
+        assert_eq!(
+            partition("{field: id, data_type: int64, range: {start: 5, end: 5, interval: 1}}")
+                .validate()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_cluster_by_accepts_one_column_or_a_list() {
+        let one: ClusterBy = serde_yaml::from_str("customer_id").unwrap();
+        assert_eq!(one.into_columns(), vec!["customer_id"]);
+
+        let many: ClusterBy = serde_yaml::from_str("[a, b, c, d, e]").unwrap();
+        let columns = many.into_columns();
+        assert_eq!(
+            validate_cluster_by(&columns),
+            vec!["cluster_by lists 5 columns; BigQuery allows at most 4"]
+        );
+    }
+}
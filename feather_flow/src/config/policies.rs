@@ -0,0 +1,70 @@
+//! Governance policies evaluated against the model graph
+//!
+//! ```yaml
+//! policies:
+//!   - name: marts_read_staging
+//!     path: marts
+//!     allowed_upstream_paths: [staging, marts]
+//!   - name: pii_is_owned_and_tested
+//!     tags: [pii]
+//!     require: [owner, tests]
+//!   - name: few_raw_sources
+//!     level: warn
+//!     max_source_references: 2
+//! ```
+//!
+//! `path` and `tags` select the models a policy applies to; without them it applies to every
+//! model. A policy at level `fail` (the default) fails `ff parse`, one at level `warn` only
+//! reports its violations.
+
+use serde::{Deserialize, Serialize};
+
+/// How a violated policy is treated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyLevel {
+    Warn,
+    #[default]
+    Fail,
+}
+
+/// Properties a policy can require models to have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Requirement {
+    /// `meta.owner` in the model's YAML
+    Owner,
+    Description,
+    /// At least one column test
+    Tests,
+}
+
+impl Requirement {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Requirement::Owner => "owner",
+            Requirement::Description => "description",
+            Requirement::Tests => "tests",
+        }
+    }
+}
+
+/// A single policy as written in `featherflow_project.yaml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub level: PolicyLevel,
+    /// Directory, relative to the model path, whose models the policy applies to
+    pub path: Option<String>,
+    /// Only models with at least one of these tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Directories upstream models must live in
+    pub allowed_upstream_paths: Option<Vec<String>>,
+    #[serde(default)]
+    pub require: Vec<Requirement>,
+    /// Most raw relations (sources, seeds, unmanaged tables) a model may read directly
+    pub max_source_references: Option<usize>,
+}
@@ -0,0 +1,216 @@
+//! Warehouse connections by target, read from `profiles.yml`
+//!
+//! ```yaml
+//! shop:                     # the project's `profile:`, else its `name:`
+//!   target: dev             # used when no --target, FF_TARGET or default_target is given
+//!   outputs:
+//!     dev:
+//!       type: duckdb
+//!       path: "{{ env_var('SHOP_DEV_DB', 'target/dev.duckdb') }}"
+//!     prod:
+//!       type: duckdb
+//!       path: /data/{{ env_var('SHOP_ENV') }}/warehouse.duckdb
+//! ```
+//!
+//! The file is read from the project root, else from the directory of the user-level
+//! configuration file, so connection details can stay out of the repository. Values may use
+//! `{{ env_var('NAME'[, 'default']) }}`, rendered only when the connection is used, so a
+//! missing variable does not affect commands that never connect. A relative DuckDB path is
+//! relative to the project root. DuckDB is the only connection type for now; `type:` leaves
+//! room for other adapters.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{user_config_path, FeatherFlowConfig};
+use crate::sql_engine::template;
+
+/// File holding the connection profiles
+pub const PROFILES_FILE: &str = "profiles.yml";
+
+/// A profile: its targets' connections and the one used by default
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub target: Option<String>,
+    #[serde(default)]
+    pub outputs: BTreeMap<String, ConnectionConfig>,
+}
+
+/// A target's connection details, as written
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ConnectionConfig {
+    DuckDb { path: String },
+}
+
+/// A target's connection details, with environment variables rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Connection {
+    DuckDb { path: PathBuf },
+}
+
+impl Connection {
+    pub fn adapter_type(&self) -> &'static str {
+        match self {
+            Connection::DuckDb { .. } => "duckdb",
+        }
+    }
+
+    /// Database file to connect to
+    pub fn database(&self) -> &Path {
+        match self {
+            Connection::DuckDb { path } => path,
+        }
+    }
+}
+
+/// The connection of the target a command runs as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveConnection {
+    /// The `profiles.yml` it was read from
+    pub file: PathBuf,
+    pub profile: String,
+    pub target: String,
+    pub connection: Connection,
+}
+
+/// The `profiles.yml` of the project at `project_root`, else the user-level one
+pub fn find(project_root: &Path) -> Option<PathBuf> {
+    let user_file =
+        user_config_path().and_then(|config| config.parent().map(|dir| dir.join(PROFILES_FILE)));
+    std::iter::once(project_root.join(PROFILES_FILE))
+        .chain(user_file)
+        .find(|path| path.is_file())
+}
+
+/// The connection of the target to run as: `cli_target` (`--target`), else `FF_TARGET`,
+/// else the project's `default_target`, else the profile's `target`
+pub fn active(
+    config: &FeatherFlowConfig,
+    project_root: &Path,
+    cli_target: Option<&str>,
+) -> Result<ActiveConnection> {
+    let file = find(project_root).ok_or_else(|| {
+        anyhow!(
+            "No {} found in {} or the user config directory",
+            PROFILES_FILE,
+            project_root.display()
+        )
+    })?;
+    let content =
+        fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let mut profiles: BTreeMap<String, Profile> = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", file.display()))?;
+
+    let (profile_name, profile) = match config.profile.as_ref().or(config.name.as_ref()) {
+        Some(name) => {
+            let profile = profiles.remove(name).ok_or_else(|| {
+                anyhow!(
+                    "Profile '{}' is not defined in {} (profiles: {})",
+                    name,
+                    file.display(),
+                    names(profiles.keys())
+                )
+            })?;
+            (name.clone(), profile)
+        }
+        None if profiles.len() == 1 => profiles.pop_first().unwrap_or_default(),
+        None => bail!(
+            "Set profile: in {} to choose a profile of {} (profiles: {})",
+            super::PROJECT_CONFIG_FILE,
+            file.display(),
+            names(profiles.keys())
+        ),
+    };
+
+    let target = match config.target(cli_target).or(profile.target.clone()) {
+        Some(target) => target,
+        None if profile.outputs.len() == 1 => profile.outputs.keys().next().cloned().unwrap_or_default(),
+        None => bail!(
+            "No target given: pass --target, set FF_TARGET or set target: in profile '{}' (targets: {})",
+            profile_name,
+            names(profile.outputs.keys())
+        ),
+    };
+    let output = profile.outputs.get(&target).ok_or_else(|| {
+        anyhow!(
+            "Target '{}' is not defined in profile '{}' of {} (targets: {})",
+            target,
+            profile_name,
+            file.display(),
+            names(profile.outputs.keys())
+        )
+    })?;
+
+    let context = || format!("Target '{}' of profile '{}'", target, profile_name);
+    let connection = match output {
+        ConnectionConfig::DuckDb { path } => Connection::DuckDb {
+            path: project_root.join(template::render_env_vars(path).with_context(context)?),
+        },
+    };
+    Ok(ActiveConnection {
+        file,
+        profile: profile_name,
+        target,
+        connection,
+    })
+}
+
+fn names<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    let names: Vec<&str> = names.map(String::as_str).collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_active_connection() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROFILES_FILE),
+            "shop:\n  target: dev\n  outputs:\n    dev:\n      type: duckdb\n      \
+             path: \"{{ env_var('FF_PROFILES_TEST_DB', 'dev.duckdb') }}\"\n    \
+             prod:\n      type: duckdb\n      path: /data/{{ env_var('FF_PROFILES_TEST_ENV') }}.duckdb\n",
+        )
+        .unwrap();
+        env::remove_var("FF_PROFILES_TEST_DB");
+        env::remove_var("FF_PROFILES_TEST_ENV");
+        let config = FeatherFlowConfig {
+            name: Some("shop".to_string()),
+            ..FeatherFlowConfig::default()
+        };
+
+        let dev = active(&config, dir.path(), None).unwrap();
+        assert_eq!(dev.profile, "shop");
+        assert_eq!(dev.target, "dev");
+        assert_eq!(dev.connection.database(), dir.path().join("dev.duckdb"));
+        assert_eq!(dev.connection.adapter_type(), "duckdb");
+
+        let err = active(&config, dir.path(), Some("prod")).unwrap_err();
+        assert!(format!("{:#}", err).contains("'FF_PROFILES_TEST_ENV' is not set"));
+        env::set_var("FF_PROFILES_TEST_ENV", "prod");
+        let prod = active(&config, dir.path(), Some("prod")).unwrap();
+        assert_eq!(prod.connection.database(), Path::new("/data/prod.duckdb"));
+
+        let err = active(&config, dir.path(), Some("qa")).unwrap_err();
+        assert!(err.to_string().contains("(targets: dev, prod)"));
+        let other = FeatherFlowConfig {
+            profile: Some("other".to_string()),
+            ..config
+        };
+        let err = active(&other, dir.path(), None).unwrap_err();
+        assert!(err.to_string().contains("Profile 'other' is not defined"));
+    }
+}
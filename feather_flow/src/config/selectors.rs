@@ -0,0 +1,225 @@
+//! Named selections from `selectors.yml`, used with `--selector <name>`
+//!
+//! ```yaml
+//! selectors:
+//!   - name: core
+//!     definition: "tag:core"
+//!   - name: nightly
+//!     description: Core models and everything reading the orders mart
+//!     definition:
+//!       union:
+//!         - selector: core
+//!         - "fct_orders+"
+//!   - name: finance_ci
+//!     definition:
+//!       intersection:
+//!         - "path:marts/finance"
+//!         - exclude: "tag:deprecated"
+//! ```
+//!
+//! A definition is an expression in the `--select` grammar, or a mapping combining
+//! definitions: `union` and `intersection` take a list, `exclude` takes the definition to
+//! leave out and `selector` names another selector. Every definition is expanded into a
+//! single expression, so a selector behaves exactly like the `--select` it stands for.
+//! The file lives next to `featherflow_project.yaml`.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::error::FeatherFlowError;
+
+/// File holding the selectors, in the project root
+pub const SELECTORS_FILE: &str = "selectors.yml";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SelectorsFile {
+    #[serde(default)]
+    pub selectors: Vec<NamedSelector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedSelector {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub definition: Definition,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Definition {
+    Expression(String),
+    Combined(Combination),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Combination {
+    Union(Vec<Definition>),
+    Intersection(Vec<Definition>),
+    Exclude(Box<Definition>),
+    Selector(String),
+}
+
+impl SelectorsFile {
+    /// The project's selectors; a project without the file has none
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(SELECTORS_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let file: Self = serde_yaml::from_str(&content)
+            .map_err(|err| FeatherFlowError::yaml_parse(&path, err))?;
+
+        let mut names = BTreeSet::new();
+        for selector in &file.selectors {
+            if !names.insert(selector.name.as_str()) {
+                bail!(
+                    "Selector '{}' is defined more than once in {}",
+                    selector.name,
+                    path.display()
+                );
+            }
+        }
+        Ok(file)
+    }
+
+    /// The `--select` expression `name` stands for
+    pub fn expression(&self, name: &str) -> Result<String> {
+        self.expand_selector(name, &mut Vec::new())
+    }
+
+    fn expand_selector<'a>(&'a self, name: &'a str, stack: &mut Vec<&'a str>) -> Result<String> {
+        if stack.contains(&name) {
+            stack.push(name);
+            bail!(
+                "Selectors reference each other in a cycle: {}",
+                stack.join(" -> ")
+            );
+        }
+        let Some(selector) = self.selectors.iter().find(|selector| selector.name == name) else {
+            let known: Vec<&str> = self.selectors.iter().map(|s| s.name.as_str()).collect();
+            if known.is_empty() {
+                bail!("Unknown selector '{}': no {} found", name, SELECTORS_FILE);
+            }
+            bail!(
+                "Unknown selector '{}' (defined: {})",
+                name,
+                known.join(", ")
+            );
+        };
+
+        stack.push(name);
+        let expression = self
+            .expand(&selector.definition, stack)
+            .with_context(|| format!("Invalid definition of selector '{}'", name))?;
+        stack.pop();
+        Ok(expression)
+    }
+
+    fn expand<'a>(
+        &'a self,
+        definition: &'a Definition,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<String> {
+        let combination = match definition {
+            Definition::Expression(expression) if expression.trim().is_empty() => {
+                bail!("Empty selection expression")
+            }
+            Definition::Expression(expression) => return Ok(expression.trim().to_string()),
+            Definition::Combined(combination) => combination,
+        };
+
+        let (definitions, operator) = match combination {
+            Combination::Union(definitions) => (definitions, " or "),
+            Combination::Intersection(definitions) => (definitions, " and "),
+            Combination::Exclude(definition) => {
+                return Ok(format!("not ({})", self.expand(definition, stack)?))
+            }
+            Combination::Selector(name) => return self.expand_selector(name, stack),
+        };
+        if definitions.is_empty() {
+            bail!("A union or intersection needs at least one definition");
+        }
+        let parts = definitions
+            .iter()
+            .map(|definition| Ok(format!("({})", self.expand(definition, stack)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(parts.join(operator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn selectors(yaml: &str) -> SelectorsFile {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(SELECTORS_FILE), yaml).unwrap();
+        SelectorsFile::load(dir.path()).unwrap()
+    }
+
+    #[test]
+    fn test_definitions_expand_to_expressions() {
+        let file = selectors(
+            r#"
+selectors:
+  - name: core
+    definition: "tag:core"
+  - name: nightly
+    description: Core models and what reads the orders mart
+    definition:
+      union:
+        - selector: core
+        - "fct_orders+"
+  - name: finance_ci
+    definition:
+      intersection:
+        - "path:marts/finance"
+        - exclude: "tag:deprecated tag:wip"
+"#,
+        );
+
+        assert_eq!(file.expression("core").unwrap(), "tag:core");
+        assert_eq!(
+            file.expression("nightly").unwrap(),
+            "(tag:core) or (fct_orders+)"
+        );
+        assert_eq!(
+            file.expression("finance_ci").unwrap(),
+            "(path:marts/finance) and (not (tag:deprecated tag:wip))"
+        );
+        let error = file.expression("weekly").unwrap_err().to_string();
+        assert!(error.contains("defined: core, nightly, finance_ci"));
+    }
+
+    #[test]
+    fn test_cycles_and_missing_file_are_errors() {
+        let file = selectors(
+            r#"
+selectors:
+  - name: a
+    definition: {selector: b}
+  - name: b
+    definition: {union: ["tag:x", {selector: a}]}
+"#,
+        );
+        let error = format!("{:#}", file.expression("a").unwrap_err());
+        assert!(error.contains("a -> b -> a"), "{}", error);
+
+        let empty = SelectorsFile::load(tempdir().unwrap().path()).unwrap();
+        assert!(empty
+            .expression("nightly")
+            .unwrap_err()
+            .to_string()
+            .contains("no selectors.yml"));
+    }
+}
@@ -0,0 +1,285 @@
+//! History of slowly changing dimensions, kept by snapshots with a `unique_key`
+//!
+//! ```yaml
+//! models:
+//!   - name: customers_history
+//!     config:
+//!       materialized: snapshot
+//!       unique_key: customer_id       # one column or a list
+//!       strategy: timestamp           # or check
+//!       updated_at: updated_at        # timestamp: when the source row last changed
+//!       check_cols: [email, tier]     # check: columns whose changes count (default: all)
+//! ```
+//!
+//! The snapshot's table holds every version of each key, valid from `ff_valid_from` until
+//! `ff_valid_to`; the current version has no `ff_valid_to`. Each build compares the query's
+//! rows with the current versions: a key whose row changed has its current version closed
+//! and the new row inserted, and a new key is inserted. Keys the query no longer returns
+//! keep their current version.
+//!
+//! With the timestamp strategy a row changed when its `updated_at` is later than the current
+//! version's, and versions are valid from that `updated_at`. With the check strategy a row
+//! changed when any of `check_cols` differs, and versions are valid from when the build ran.
+//! Snapshots without a `unique_key` only append the rows that changed, see
+//! `sql_engine::nodes::SNAPSHOT_AT_COLUMN`.
+
+use serde::{Deserialize, Serialize};
+
+/// Column holding when a version of a key became current
+pub const VALID_FROM_COLUMN: &str = "ff_valid_from";
+
+/// Column holding when a version of a key was replaced; NULL for the current version
+pub const VALID_TO_COLUMN: &str = "ff_valid_to";
+
+/// Temporary table holding the query's rows while the history is updated
+const STAGED_TABLE: &str = "ff_snapshot_source";
+
+/// A snapshot's history settings as written in its YAML. The strategy is kept as a string
+/// so mistakes are reported by validation instead of failing the whole YAML file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotConfig {
+    pub unique_key: Vec<String>,
+    pub strategy: Option<String>,
+    pub updated_at: Option<String>,
+    /// Columns compared by the check strategy; None, or `all`, for every column
+    pub check_cols: Option<Vec<String>>,
+}
+
+enum Strategy<'a> {
+    Timestamp {
+        updated_at: &'a str,
+    },
+    /// None compares every column
+    Check {
+        columns: Option<&'a [String]>,
+    },
+}
+
+impl SnapshotConfig {
+    fn strategy(&self) -> Strategy<'_> {
+        match (self.strategy.as_deref(), &self.updated_at) {
+            (Some("timestamp"), Some(updated_at)) => Strategy::Timestamp { updated_at },
+            _ => Strategy::Check {
+                columns: self
+                    .check_cols
+                    .as_deref()
+                    .filter(|columns| !matches!(columns, [all] if all.eq_ignore_ascii_case("all"))),
+            },
+        }
+    }
+
+    /// Problems with the configuration; the SQL is only meaningful when there are none
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.unique_key.is_empty() {
+            errors.push("snapshot history needs a unique_key".to_string());
+        }
+        if self
+            .unique_key
+            .iter()
+            .any(|column| column.trim().is_empty())
+        {
+            errors.push("unique_key has an empty column name".to_string());
+        }
+        match self.strategy.as_deref() {
+            None => {
+                errors.push("snapshot history needs a strategy (timestamp or check)".to_string())
+            }
+            Some("timestamp") => {
+                if self.updated_at.is_none() {
+                    errors.push("the timestamp strategy needs updated_at".to_string());
+                }
+                if self.check_cols.is_some() {
+                    errors.push("check_cols only applies to the check strategy".to_string());
+                }
+            }
+            Some("check") => {
+                if self.updated_at.is_some() {
+                    errors.push("updated_at only applies to the timestamp strategy".to_string());
+                }
+                if self.check_cols.as_ref().is_some_and(Vec::is_empty) {
+                    errors.push("check_cols lists no columns".to_string());
+                }
+            }
+            Some(other) => errors.push(format!(
+                "snapshot strategy '{}' is not one of timestamp, check",
+                other
+            )),
+        }
+        errors
+    }
+
+    /// When a version taken from the `source` row became current
+    fn valid_from(&self) -> String {
+        match self.strategy() {
+            Strategy::Timestamp { updated_at } => format!("source.{}", updated_at),
+            // now() is the start of the transaction, so every version of a build shares it
+            Strategy::Check { .. } => "now()".to_string(),
+        }
+    }
+
+    /// Whether the `source` row differs from the `snapshot` row, the current version of its key
+    fn changed(&self, this: &str) -> String {
+        match self.strategy() {
+            Strategy::Timestamp { updated_at } => {
+                format!("source.{col} > snapshot.{col}", col = updated_at)
+            }
+            Strategy::Check {
+                columns: Some(columns),
+            } => columns
+                .iter()
+                .map(|column| format!("source.{col} IS DISTINCT FROM snapshot.{col}", col = column))
+                .collect::<Vec<_>>()
+                .join(" OR "),
+            Strategy::Check { columns: None } => format!(
+                "EXISTS (SELECT 1 FROM (SELECT * FROM {staged} EXCEPT SELECT * EXCLUDE ({from}, {to}) FROM {this} WHERE {to} IS NULL) AS changed WHERE {key})",
+                staged = STAGED_TABLE,
+                from = VALID_FROM_COLUMN,
+                to = VALID_TO_COLUMN,
+                this = this,
+                key = self.same_key("changed")
+            ),
+        }
+    }
+
+    /// Join condition of `alias` and `source` on the unique key
+    fn same_key(&self, alias: &str) -> String {
+        self.unique_key
+            .iter()
+            .map(|column| format!("{alias}.{col} = source.{col}", alias = alias, col = column))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+
+    /// Statement creating the history table `this` from the query `sql` on the first build
+    pub fn create_sql(&self, this: &str, sql: &str) -> String {
+        let valid_from = self.valid_from();
+        // NULLIF(x, x) is a NULL of the type of x, so both columns get the same type
+        format!(
+            "CREATE TABLE {this} AS SELECT source.*, {from_value} AS {from}, NULLIF({from_value}, {from_value}) AS {to} FROM ({sql}) AS source",
+            this = this,
+            sql = sql,
+            from_value = valid_from,
+            from = VALID_FROM_COLUMN,
+            to = VALID_TO_COLUMN
+        )
+    }
+
+    /// Statements closing the current versions of changed keys in `this` and inserting the
+    /// query's rows for keys without a current version, in one transaction
+    pub fn update_sql(&self, this: &str, sql: &str) -> String {
+        let valid_from = self.valid_from();
+        let current = format!(
+            "{key} AND snapshot.{to} IS NULL",
+            key = self.same_key("snapshot"),
+            to = VALID_TO_COLUMN
+        );
+        [
+            "BEGIN TRANSACTION".to_string(),
+            format!("CREATE OR REPLACE TEMP TABLE {} AS {}", STAGED_TABLE, sql),
+            format!(
+                "UPDATE {this} AS snapshot SET {to} = {from_value} FROM {staged} AS source WHERE {current} AND ({changed})",
+                this = this,
+                to = VALID_TO_COLUMN,
+                from_value = valid_from,
+                staged = STAGED_TABLE,
+                current = current,
+                changed = self.changed(this)
+            ),
+            format!(
+                "INSERT INTO {this} SELECT source.*, {from_value}, NULL FROM {staged} AS source WHERE NOT EXISTS (SELECT 1 FROM {this} AS snapshot WHERE {current})",
+                this = this,
+                from_value = valid_from,
+                staged = STAGED_TABLE,
+                current = current
+            ),
+            format!("DROP TABLE {}", STAGED_TABLE),
+            "COMMIT".to_string(),
+        ]
+        .join(";\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(
+        strategy: &str,
+        updated_at: Option<&str>,
+        check_cols: Option<&[&str]>,
+    ) -> SnapshotConfig {
+        SnapshotConfig {
+            unique_key: vec!["customer_id".to_string()],
+            strategy: Some(strategy.to_string()),
+            updated_at: updated_at.map(str::to_string),
+            check_cols: check_cols.map(|columns| columns.iter().map(|c| c.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config("timestamp", Some("updated_at"), None)
+            .validate()
+            .is_empty());
+        assert!(config("check", None, Some(&["all"])).validate().is_empty());
+        assert_eq!(
+            config("timestamp", None, Some(&["email"])).validate(),
+            vec![
+                "the timestamp strategy needs updated_at",
+                "check_cols only applies to the check strategy"
+            ]
+        );
+        assert_eq!(
+            config("hourly", None, None).validate(),
+            vec!["snapshot strategy 'hourly' is not one of timestamp, check"]
+        );
+        let keyless = SnapshotConfig {
+            strategy: None,
+            ..config("check", None, None)
+        };
+        assert_eq!(
+            SnapshotConfig {
+                unique_key: Vec::new(),
+                ..keyless
+            }
+            .validate(),
+            vec![
+                "snapshot history needs a unique_key",
+                "snapshot history needs a strategy (timestamp or check)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_history_sql() {
+        let timestamp = config("timestamp", Some("updated_at"), None);
+        assert_eq!(
+            timestamp.create_sql("snapshots.customers", "SELECT * FROM raw.customers"),
+            "CREATE TABLE snapshots.customers AS SELECT source.*, source.updated_at AS ff_valid_from, NULLIF(source.updated_at, source.updated_at) AS ff_valid_to FROM (SELECT * FROM raw.customers) AS source"
+        );
+        assert_eq!(
+            timestamp.update_sql("snapshots.customers", "SELECT * FROM raw.customers"),
+            "BEGIN TRANSACTION;
+CREATE OR REPLACE TEMP TABLE ff_snapshot_source AS SELECT * FROM raw.customers;
+UPDATE snapshots.customers AS snapshot SET ff_valid_to = source.updated_at FROM ff_snapshot_source AS source WHERE snapshot.customer_id = source.customer_id AND snapshot.ff_valid_to IS NULL AND (source.updated_at > snapshot.updated_at);
+INSERT INTO snapshots.customers SELECT source.*, source.updated_at, NULL FROM ff_snapshot_source AS source WHERE NOT EXISTS (SELECT 1 FROM snapshots.customers AS snapshot WHERE snapshot.customer_id = source.customer_id AND snapshot.ff_valid_to IS NULL);
+DROP TABLE ff_snapshot_source;
+COMMIT"
+        );
+
+        let columns = config("check", None, Some(&["email", "tier"]));
+        assert_eq!(
+            columns.changed("snapshots.customers"),
+            "source.email IS DISTINCT FROM snapshot.email OR source.tier IS DISTINCT FROM snapshot.tier"
+        );
+        let all = config("check", None, None);
+        assert_eq!(
+            all.changed("snapshots.customers"),
+            "EXISTS (SELECT 1 FROM (SELECT * FROM ff_snapshot_source EXCEPT SELECT * EXCLUDE (ff_valid_from, ff_valid_to) FROM snapshots.customers WHERE ff_valid_to IS NULL) AS changed WHERE changed.customer_id = source.customer_id)"
+        );
+        assert!(all
+            .create_sql("snapshots.customers", "SELECT 1")
+            .contains("now() AS ff_valid_from, NULLIF(now(), now()) AS ff_valid_to"));
+    }
+}
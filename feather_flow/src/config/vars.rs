@@ -0,0 +1,312 @@
+//! Typed project variables
+//!
+//! ```yaml
+//! vars:
+//!   - name: start_date
+//!     type: date
+//!     default: "2024-01-01"
+//!   - name: region
+//!     type: string
+//!     allowed_values: [us, eu]
+//!   - name: lookback_days
+//!     type: int
+//!     default: 7
+//!   - name: channels
+//!     type: list
+//!     default: [web, mobile]
+//! ```
+//!
+//! Values passed with `--vars` are checked against these declarations before any model is
+//! compiled, and models reference them as `{{ var('start_date') }}`. Lists render as
+//! comma-separated SQL literals, quoted for the project's dialect, so they can be used in
+//! `IN (...)`. Other values render as written: a free-form string belongs in
+//! `{{ literal(var('name')) }}` (see `ff validate`'s FF050).
+
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::SqlDialect;
+use crate::sql_engine::quoting;
+
+/// Type of a project variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VarType {
+    String,
+    Int,
+    /// `YYYY-MM-DD`
+    Date,
+    List,
+}
+
+impl VarType {
+    fn describe(self) -> &'static str {
+        match self {
+            VarType::String => "a string",
+            VarType::Int => "an integer",
+            VarType::Date => "a date (YYYY-MM-DD)",
+            VarType::List => "a list",
+        }
+    }
+}
+
+/// A variable declared under `vars:` in `featherflow_project.yaml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VarDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub var_type: VarType,
+    /// Value used when the variable is not passed with `--vars`; without one the variable
+    /// is required
+    pub default: Option<Value>,
+    /// Permitted values; for lists, every item must be one of them
+    pub allowed_values: Option<Vec<Value>>,
+    pub description: Option<String>,
+}
+
+impl VarDefinition {
+    /// Whether any text can be passed for the variable, so it must be quoted to be safe in SQL
+    pub fn is_free_form(&self) -> bool {
+        self.var_type == VarType::String && self.allowed_values.is_none()
+    }
+}
+
+/// Parse the `--vars` argument, a YAML (or JSON) mapping such as `{start_date: 2024-01-01}`
+pub fn parse_cli_vars(input: &str) -> Result<HashMap<String, Value>> {
+    serde_yaml::from_str(input).map_err(|err| {
+        anyhow!(
+            "Invalid --vars '{}': expected a mapping like '{{name: value}}' ({})",
+            input,
+            err
+        )
+    })
+}
+
+/// Check every declared variable against its type and allowed values, taking `--vars`
+/// values over defaults, and render each one as it appears in compiled SQL
+pub fn resolve_vars(
+    definitions: &[VarDefinition],
+    overrides: &HashMap<String, Value>,
+    dialect: SqlDialect,
+) -> Result<BTreeMap<String, String>> {
+    let mut declared = HashSet::new();
+    for definition in definitions {
+        if !declared.insert(definition.name.as_str()) {
+            bail!(
+                "Variable '{}' is declared more than once in vars",
+                definition.name
+            );
+        }
+    }
+
+    let mut unknown: Vec<&String> = overrides
+        .keys()
+        .filter(|name| !declared.contains(name.as_str()))
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort();
+        let mut names: Vec<&str> = declared.into_iter().collect();
+        names.sort();
+        bail!(
+            "Unknown variable(s) passed with --vars: {} (declared: {})",
+            unknown
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        );
+    }
+
+    let mut resolved = BTreeMap::new();
+    for definition in definitions {
+        let (value, origin) = match overrides.get(&definition.name) {
+            Some(value) => (value, "--vars"),
+            None => match &definition.default {
+                Some(value) => (value, "its default"),
+                None => bail!(
+                    "Variable '{}' has no default and must be passed with --vars",
+                    definition.name
+                ),
+            },
+        };
+
+        let rendered = check_value(definition, value, dialect).map_err(|problem| {
+            anyhow!(
+                "Variable '{}' from {}: {}",
+                definition.name,
+                origin,
+                problem
+            )
+        })?;
+        resolved.insert(definition.name.clone(), rendered);
+    }
+
+    Ok(resolved)
+}
+
+/// Validate `value` and render it for SQL; errors describe the problem
+fn check_value(
+    definition: &VarDefinition,
+    value: &Value,
+    dialect: SqlDialect,
+) -> Result<String, String> {
+    let allowed = definition
+        .allowed_values
+        .as_ref()
+        .map(|values| {
+            values
+                .iter()
+                .map(|value| scalar_text(value).unwrap_or_default())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let check_allowed = |text: &str| {
+        if allowed.is_empty() || allowed.iter().any(|a| a == text) {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{}' is not one of the allowed values: {}",
+                text,
+                allowed.join(", ")
+            ))
+        }
+    };
+
+    if definition.var_type == VarType::List {
+        let Value::Sequence(items) = value else {
+            return Err(format!(
+                "expected {}, got {}",
+                VarType::List.describe(),
+                show(value)
+            ));
+        };
+
+        let mut rendered = Vec::with_capacity(items.len());
+        for item in items {
+            let text = scalar_text(item)
+                .ok_or_else(|| format!("list items must be scalars, got {}", show(item)))?;
+            check_allowed(&text)?;
+            rendered.push(match item {
+                Value::Number(_) | Value::Bool(_) => text,
+                _ => quoting::string_literal(&text, dialect),
+            });
+        }
+        return Ok(rendered.join(", "));
+    }
+
+    let expected = || {
+        format!(
+            "expected {}, got {}",
+            definition.var_type.describe(),
+            show(value)
+        )
+    };
+    let text = scalar_text(value).ok_or_else(expected)?;
+
+    match definition.var_type {
+        VarType::Int if text.parse::<i64>().is_err() => return Err(expected()),
+        VarType::Date if NaiveDate::parse_from_str(&text, "%Y-%m-%d").is_err() => {
+            return Err(expected())
+        }
+        _ => {}
+    }
+
+    check_allowed(&text)?;
+    Ok(text)
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn show(value: &Value) -> String {
+    match scalar_text(value) {
+        Some(text) => format!("'{}'", text),
+        None => serde_yaml::to_string(value)
+            .map(|yaml| yaml.trim().to_string())
+            .unwrap_or_else(|_| "an unsupported value".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definitions() -> Vec<VarDefinition> {
+        serde_yaml::from_str(
+            r#"
+- name: start_date
+  type: date
+  default: "2024-01-01"
+- name: region
+  type: string
+  allowed_values: [us, eu]
+- name: lookback_days
+  type: int
+  default: 7
+- name: channels
+  type: list
+  default: [web, "partner's"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_defaults_and_overrides() {
+        let overrides = parse_cli_vars("{region: eu, lookback_days: 30}").unwrap();
+        let vars = resolve_vars(&definitions(), &overrides, SqlDialect::DuckDb).unwrap();
+
+        assert_eq!(vars["start_date"], "2024-01-01");
+        assert_eq!(vars["region"], "eu");
+        assert_eq!(vars["lookback_days"], "30");
+        assert_eq!(vars["channels"], "'web', 'partner''s'");
+
+        let vars = resolve_vars(&definitions(), &overrides, SqlDialect::BigQuery).unwrap();
+        assert_eq!(vars["channels"], "'web', 'partner\\'s'");
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let definitions = definitions();
+        let resolve = |input: &str| {
+            resolve_vars(
+                &definitions,
+                &parse_cli_vars(input).unwrap(),
+                SqlDialect::DuckDb,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        assert!(resolve("{}").contains("'region' has no default"));
+        assert!(
+            resolve("{region: apac}").contains("'apac' is not one of the allowed values: us, eu")
+        );
+        assert!(resolve("{region: us, start_date: 2024-13-01}")
+            .contains("'start_date' from --vars: expected a date (YYYY-MM-DD)"));
+        assert!(resolve("{region: us, lookback_days: week}").contains("expected an integer"));
+        assert!(resolve("{region: us, channels: web}").contains("expected a list"));
+        assert!(resolve("{region: us, regoin: eu}")
+            .contains("Unknown variable(s) passed with --vars: regoin"));
+    }
+
+    #[test]
+    fn test_invalid_cli_vars() {
+        assert!(parse_cli_vars("[a, b]").is_err());
+    }
+}
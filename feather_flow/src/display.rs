@@ -1,4 +1,102 @@
+//! Terminal output: the logo, status markers and the global output settings
+//!
+//! stdout carries a command's data only: the graph `ff parse` renders, query rows, build
+//! results. Banners, progress and summaries go to stderr through `status!`. With
+//! `--porcelain`, status lines are dropped and tabular results are printed as tab-separated
+//! `record`s whose columns only ever grow at the end.
+
 use colored::*;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--no-emoji`: print ASCII markers and the ASCII logo only
+static ASCII_ONLY: AtomicBool = AtomicBool::new(false);
+/// Set by `--porcelain`
+static PORCELAIN: AtomicBool = AtomicBool::new(false);
+
+/// Apply the global output flags. `NO_COLOR` and `CLICOLOR_FORCE` are honoured by `colored`
+/// itself; `--no-color` overrides both. `--porcelain` implies the other two.
+pub fn configure(no_emoji: bool, no_color: bool, porcelain: bool) {
+    ASCII_ONLY.store(no_emoji || porcelain, Ordering::Relaxed);
+    PORCELAIN.store(porcelain, Ordering::Relaxed);
+    if no_color || porcelain {
+        colored::control::set_override(false);
+    }
+}
+
+fn ascii_only() -> bool {
+    ASCII_ONLY.load(Ordering::Relaxed)
+}
+
+pub fn porcelain() -> bool {
+    PORCELAIN.load(Ordering::Relaxed)
+}
+
+/// `eprintln!` for banners, progress and summaries, which `--porcelain` silences.
+/// Warnings and errors use `eprintln!` directly so they are always shown.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::display::porcelain() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use status;
+
+/// Print one `--porcelain` record to stdout: fields separated by tabs, with backslashes,
+/// tabs and newlines inside fields escaped as `\\`, `\t` and `\n`
+pub fn record(fields: &[&str]) {
+    println!("{}", format_record(fields));
+}
+
+pub(crate) fn format_record(fields: &[&str]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// A status marker or list glyph, printed as emoji/Unicode or plain ASCII with `--no-emoji`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Ok,
+    Error,
+    Warning,
+    Bullet,
+    Arrow,
+}
+
+impl Marker {
+    fn symbol(self, ascii: bool) -> &'static str {
+        match (self, ascii) {
+            (Marker::Ok, false) => "✅",
+            (Marker::Ok, true) => "[OK]",
+            (Marker::Error, false) => "❌",
+            (Marker::Error, true) => "[ERROR]",
+            // The emoji renders two columns wide in most terminals
+            (Marker::Warning, false) => "⚠️ ",
+            (Marker::Warning, true) => "[WARN]",
+            (Marker::Bullet, false) => "•",
+            (Marker::Bullet, true) => "-",
+            (Marker::Arrow, false) => "→",
+            (Marker::Arrow, true) => "->",
+        }
+    }
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol(ascii_only()))
+    }
+}
 
 /// Returns the FeatherFlow ASCII art logo
 pub fn get_logo() -> String {
@@ -20,8 +118,11 @@ pub fn get_logo() -> String {
     logo.to_string()
 }
 
-/// Returns a colored version of the logo
+/// Returns a colored version of the logo, or of the compact ASCII logo with `--no-emoji`
 pub fn get_colored_logo() -> ColoredString {
+    if ascii_only() {
+        return get_compact_colored_logo();
+    }
     get_logo().bright_cyan()
 }
 
@@ -41,15 +142,48 @@ pub fn get_compact_colored_logo() -> ColoredString {
     get_compact_logo().bright_cyan()
 }
 
-/// Display version information with the ASCII art logo
+/// Display version information with the ASCII art logo; only the version is data
 pub fn display_version() {
-    println!("{}", get_colored_logo());
+    if porcelain() {
+        println!("{}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    eprintln!("{}", get_colored_logo());
     println!("FeatherFlow CLI version {}", env!("CARGO_PKG_VERSION"));
-    println!("A Rust-based SQL transformation tool");
-    println!("Repository: {}", env!("CARGO_PKG_REPOSITORY"));
+    eprintln!("A Rust-based SQL transformation tool");
+    eprintln!("Repository: {}", env!("CARGO_PKG_REPOSITORY"));
 }
 
 /// Display a welcome message for the parse command
 pub fn display_parse_welcome() {
-    println!("{}", get_compact_colored_logo());
+    status!("{}", get_compact_colored_logo());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_markers_are_ascii() {
+        for marker in [
+            Marker::Ok,
+            Marker::Error,
+            Marker::Warning,
+            Marker::Bullet,
+            Marker::Arrow,
+        ] {
+            assert!(marker.symbol(true).is_ascii(), "{:?}", marker);
+            assert!(!marker.symbol(false).is_ascii(), "{:?}", marker);
+        }
+        assert!(get_compact_logo().is_ascii());
+    }
+
+    #[test]
+    fn test_record_escapes_separators() {
+        assert_eq!(
+            format_record(&["OK", "a\tb", "two\nlines", "C:\\dir"]),
+            "OK\ta\\tb\ttwo\\nlines\tC:\\\\dir"
+        );
+        assert_eq!(format_record(&["", "x"]), "\tx");
+    }
 }
@@ -0,0 +1,296 @@
+//! Errors with stable codes
+//!
+//! Codes are grouped by area: `FF0xx` for model file structure and naming, `FF1xx` for the
+//! dependency graph and `FF2xx` for project configuration and YAML. A code keeps its meaning
+//! once released, so editors and CI can match on it instead of on the message. Findings that
+//! are only warnings have codes in `codes` but no `FeatherFlowError` variant.
+//!
+//! Only the failures listed in `codes` have one. Everything else parsing and compiling can
+//! fail on, such as SQL that does not parse, template errors and unknown references, is a
+//! plain `anyhow` message, as are warehouse errors; `code_of` finds no code in those.
+
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Every code, including those of warnings
+pub mod codes {
+    pub const MISSING_SQL_FILE: &str = "FF001";
+    pub const MISSING_YAML_FILE: &str = "FF002";
+    pub const UNEXPECTED_FILE: &str = "FF003";
+    pub const NOT_A_DIRECTORY: &str = "FF004";
+    pub const UNREADABLE_DIRECTORY: &str = "FF005";
+    pub const NAMING_VIOLATION: &str = "FF010";
+    pub const ORPHANED_YAML_ENTRY: &str = "FF020";
+    pub const UNBOUND_YAML_MODEL: &str = "FF021";
+    pub const EMPTY_DIRECTORY: &str = "FF022";
+    pub const UNSELECTED_COLUMN: &str = "FF030";
+    pub const UNDOCUMENTED_COLUMN: &str = "FF031";
+    pub const COLUMN_TYPE_MISMATCH: &str = "FF032";
+    pub const NULLABLE_NOT_NULL_COLUMN: &str = "FF033";
+    pub const TEST_COVERAGE: &str = "FF040";
+    pub const RAW_INTERPOLATION: &str = "FF050";
+    pub const DEPENDENCY_CYCLE: &str = "FF102";
+    pub const INVALID_PROJECT_CONFIG: &str = "FF200";
+    pub const YAML_PARSE_FAILURE: &str = "FF201";
+    pub const UNKNOWN_YAML_KEY: &str = "FF202";
+    pub const WRONG_YAML_TYPE: &str = "FF203";
+    pub const MISSING_YAML_KEY: &str = "FF204";
+    pub const DUPLICATE_YAML_ENTRY: &str = "FF205";
+
+    /// Short name and description of a code, for tools that list rules
+    pub fn describe(code: &str) -> (&'static str, &'static str) {
+        match code {
+            MISSING_SQL_FILE => (
+                "missing-sql-file",
+                "A model directory has no SQL file named after it",
+            ),
+            MISSING_YAML_FILE => (
+                "missing-yaml-file",
+                "A model directory has no YAML file named after it",
+            ),
+            UNEXPECTED_FILE => (
+                "unexpected-file",
+                "A model directory holds files other than its SQL and YAML",
+            ),
+            NOT_A_DIRECTORY => ("not-a-directory", "A model path is not a directory"),
+            UNREADABLE_DIRECTORY => (
+                "unreadable-directory",
+                "A model directory could not be read",
+            ),
+            NAMING_VIOLATION => (
+                "naming-violation",
+                "A name breaks one of the project's naming rules",
+            ),
+            ORPHANED_YAML_ENTRY => (
+                "orphaned-yaml-entry",
+                "A YAML entry names a model that has no SQL",
+            ),
+            UNBOUND_YAML_MODEL => (
+                "unbound-yaml-model",
+                "A YAML entry is outside the file its model's metadata is read from",
+            ),
+            EMPTY_DIRECTORY => (
+                "empty-directory",
+                "A model directory has no files left in it",
+            ),
+            UNSELECTED_COLUMN => (
+                "unselected-column",
+                "A column documented in a model's YAML is not selected by its SQL",
+            ),
+            UNDOCUMENTED_COLUMN => (
+                "undocumented-column",
+                "A column selected by a model's SQL is not documented in its YAML",
+            ),
+            COLUMN_TYPE_MISMATCH => (
+                "column-type-mismatch",
+                "A column's YAML data type differs from the warehouse schema synced by `ff sync-schemas`",
+            ),
+            NULLABLE_NOT_NULL_COLUMN => (
+                "nullable-not-null-column",
+                "A column tested not_null can be NULL by its model's SQL, e.g. through a LEFT JOIN",
+            ),
+            TEST_COVERAGE => (
+                "test-coverage",
+                "Fewer of a directory's models have tests than its `require_tests` threshold",
+            ),
+            RAW_INTERPOLATION => (
+                "raw-interpolation",
+                "A model writes an environment variable or free-form string variable into its SQL without literal() or identifier()",
+            ),
+            DEPENDENCY_CYCLE => ("dependency-cycle", "Models read each other in a loop"),
+            INVALID_PROJECT_CONFIG => (
+                "invalid-project-config",
+                "The project configuration could not be loaded",
+            ),
+            YAML_PARSE_FAILURE => ("yaml-parse-failure", "A YAML file could not be parsed"),
+            UNKNOWN_YAML_KEY => (
+                "unknown-yaml-key",
+                "A model or source YAML file has a key FeatherFlow does not read",
+            ),
+            WRONG_YAML_TYPE => (
+                "wrong-yaml-type",
+                "A value in a model or source YAML file has the wrong type",
+            ),
+            MISSING_YAML_KEY => (
+                "missing-yaml-key",
+                "A model or source YAML file lacks a required key, such as an entry's name",
+            ),
+            DUPLICATE_YAML_ENTRY => (
+                "duplicate-yaml-entry",
+                "Two entries of a list in a YAML file have the same name",
+            ),
+            _ => ("unknown", "Unknown finding"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeatherFlowError {
+    /// A model directory without `<name>.sql`
+    MissingSqlFile {
+        name: String,
+        expected: PathBuf,
+    },
+    /// A model directory without `<name>.yml`
+    MissingYamlFile {
+        name: String,
+        expected: PathBuf,
+    },
+    /// A file other than the model's SQL and YAML; `expected` lists the allowed names
+    UnexpectedFile {
+        file: String,
+        expected: Vec<String>,
+        imports: bool,
+    },
+    NotADirectory {
+        path: PathBuf,
+    },
+    UnreadableDirectory {
+        path: PathBuf,
+        reason: String,
+    },
+    /// Models reading each other in a loop, each cycle as the unique ids along it
+    DependencyCycle {
+        cycles: Vec<Vec<String>>,
+    },
+    /// Valid YAML that is not a valid project configuration, e.g. a setting of the wrong type
+    InvalidProjectConfig {
+        path: PathBuf,
+        reason: String,
+    },
+    YamlParse {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl FeatherFlowError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FeatherFlowError::MissingSqlFile { .. } => codes::MISSING_SQL_FILE,
+            FeatherFlowError::MissingYamlFile { .. } => codes::MISSING_YAML_FILE,
+            FeatherFlowError::UnexpectedFile { .. } => codes::UNEXPECTED_FILE,
+            FeatherFlowError::NotADirectory { .. } => codes::NOT_A_DIRECTORY,
+            FeatherFlowError::UnreadableDirectory { .. } => codes::UNREADABLE_DIRECTORY,
+            FeatherFlowError::DependencyCycle { .. } => codes::DEPENDENCY_CYCLE,
+            FeatherFlowError::InvalidProjectConfig { .. } => codes::INVALID_PROJECT_CONFIG,
+            FeatherFlowError::YamlParse { .. } => codes::YAML_PARSE_FAILURE,
+        }
+    }
+
+    pub fn yaml_parse(path: impl Into<PathBuf>, err: serde_yaml::Error) -> Self {
+        FeatherFlowError::YamlParse {
+            path: path.into(),
+            reason: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FeatherFlowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatherFlowError::MissingSqlFile { name, expected } => write!(
+                f,
+                "Missing SQL file: {} (expected at {})",
+                name,
+                expected.display()
+            ),
+            FeatherFlowError::MissingYamlFile { name, expected } => write!(
+                f,
+                "Missing YAML file: {} (expected at {})",
+                name,
+                expected.display()
+            ),
+            FeatherFlowError::UnexpectedFile {
+                file,
+                expected,
+                imports,
+            } => write!(
+                f,
+                "Unexpected file in {} directory: {} (only {} {} expected)",
+                if *imports { "imports" } else { "model" },
+                file,
+                expected.join(" and "),
+                if expected.len() == 1 { "is" } else { "are" }
+            ),
+            FeatherFlowError::NotADirectory { path } => {
+                write!(f, "Path is not a directory: {}", path.display())
+            }
+            FeatherFlowError::UnreadableDirectory { path, reason } => {
+                write!(f, "Failed to read directory {}: {}", path.display(), reason)
+            }
+            FeatherFlowError::DependencyCycle { cycles } => {
+                // Each cycle back to its first model, closing the loop
+                let loops: Vec<String> = cycles
+                    .iter()
+                    .map(|cycle| {
+                        let closed: Vec<&str> = cycle
+                            .iter()
+                            .chain(cycle.first())
+                            .map(String::as_str)
+                            .collect();
+                        closed.join(" -> ")
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "{} dependency cycle(s) detected, models cannot read each other in a loop: {}",
+                    cycles.len(),
+                    loops.join("; ")
+                )
+            }
+            FeatherFlowError::InvalidProjectConfig { path, reason } => {
+                write!(f, "Invalid project config {}: {}", path.display(), reason)
+            }
+            FeatherFlowError::YamlParse { path, reason } => {
+                write!(
+                    f,
+                    "Failed to parse YAML from {}: {}",
+                    path.display(),
+                    reason
+                )
+            }
+        }
+    }
+}
+
+impl Error for FeatherFlowError {}
+
+/// Code of the first `FeatherFlowError` in `err` or the errors it wraps
+pub fn code_of(err: &(dyn Error + 'static)) -> Option<&'static str> {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(err) = err.downcast_ref::<FeatherFlowError>() {
+            return Some(err.code());
+        }
+        current = err.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_codes_survive_wrapping() {
+        let cycle = FeatherFlowError::DependencyCycle {
+            cycles: vec![vec!["model.a".to_string(), "model.b".to_string()]],
+        };
+        assert!(cycle.to_string().ends_with("model.a -> model.b -> model.a"));
+
+        let wrapped = Err::<(), _>(FeatherFlowError::YamlParse {
+            path: PathBuf::from("models/orders/orders.yml"),
+            reason: "mapping values are not allowed".to_string(),
+        })
+        .context("Failed to load project")
+        .unwrap_err();
+        assert_eq!(code_of(wrapped.as_ref()), Some("FF201"));
+
+        let boxed: Box<dyn Error> = cycle.into();
+        assert_eq!(code_of(boxed.as_ref()), Some(codes::DEPENDENCY_CYCLE));
+        assert_eq!(code_of(&fmt::Error), None);
+    }
+}
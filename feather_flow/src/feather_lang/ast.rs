@@ -0,0 +1,138 @@
+//! Syntax tree produced by the parser. `Display` prints the tree with every operation
+//! parenthesised, which is how parser tests check precedence.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Let { name: String, value: Expression },
+    Return(Expression),
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockStatement {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Identifier(String),
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+    Array(Vec<Expression>),
+    Hash(Vec<(Expression, Expression)>),
+    Prefix {
+        operator: String,
+        right: Box<Expression>,
+    },
+    Infix {
+        left: Box<Expression>,
+        operator: String,
+        right: Box<Expression>,
+    },
+    /// `left[index]`; `left.name` is parsed as `left["name"]`
+    Index {
+        left: Box<Expression>,
+        index: Box<Expression>,
+    },
+    If {
+        condition: Box<Expression>,
+        consequence: BlockStatement,
+        alternative: Option<BlockStatement>,
+    },
+    Function {
+        parameters: Vec<String>,
+        body: BlockStatement,
+    },
+    Call {
+        function: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for statement in &self.statements {
+            write!(f, "{}", statement)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statement::Let { name, value } => write!(f, "let {} = {};", name, value),
+            Statement::Return(value) => write!(f, "return {};", value),
+            Statement::Expression(expression) => write!(f, "{}", expression),
+        }
+    }
+}
+
+impl fmt::Display for BlockStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for statement in &self.statements {
+            write!(f, "{}", statement)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Identifier(name) => write!(f, "{}", name),
+            Expression::Integer(value) => write!(f, "{}", value),
+            Expression::Boolean(value) => write!(f, "{}", value),
+            Expression::String(value) => write!(f, "{:?}", value),
+            Expression::Array(elements) => write!(f, "[{}]", join(elements)),
+            Expression::Hash(pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+            Expression::Prefix { operator, right } => write!(f, "({}{})", operator, right),
+            Expression::Infix {
+                left,
+                operator,
+                right,
+            } => write!(f, "({} {} {})", left, operator, right),
+            Expression::Index { left, index } => write!(f, "({}[{}])", left, index),
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                write!(f, "if {} {{ {} }}", condition, consequence)?;
+                if let Some(alternative) = alternative {
+                    write!(f, " else {{ {} }}", alternative)?;
+                }
+                Ok(())
+            }
+            Expression::Function { parameters, body } => {
+                write!(f, "fn({}) {{ {} }}", parameters.join(", "), body)
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => write!(f, "{}({})", function, join(arguments)),
+        }
+    }
+}
+
+fn join(expressions: &[Expression]) -> String {
+    expressions
+        .iter()
+        .map(Expression::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,372 @@
+//! Tree-walking evaluator. Runtime errors (type mismatches, unknown names, bad indexes) end
+//! evaluation and are returned as `Err`.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeMap;
+
+use super::ast::{BlockStatement, Expression, Program, Statement};
+use super::object::{Env, Environment, HashKey, Object};
+use super::stdlib;
+
+/// Evaluate every statement of `program` in `env`, returning the value of the last one or
+/// of the first `return`
+pub fn eval_program(program: &Program, env: &Env) -> Result<Object> {
+    let mut result = Object::Null;
+    for statement in &program.statements {
+        result = eval_statement(statement, env)?;
+        if let Object::Return(value) = result {
+            return Ok(*value);
+        }
+    }
+    Ok(result)
+}
+
+/// Like `eval_program`, but a `return` is passed up still wrapped so that it leaves every
+/// enclosing block
+fn eval_block(block: &BlockStatement, env: &Env) -> Result<Object> {
+    let mut result = Object::Null;
+    for statement in &block.statements {
+        result = eval_statement(statement, env)?;
+        if matches!(result, Object::Return(_)) {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+fn eval_statement(statement: &Statement, env: &Env) -> Result<Object> {
+    match statement {
+        Statement::Let { name, value } => {
+            let value = eval_expression(value, env)?;
+            env.borrow_mut().set(name, value);
+            Ok(Object::Null)
+        }
+        Statement::Return(value) => Ok(Object::Return(Box::new(eval_expression(value, env)?))),
+        Statement::Expression(expression) => eval_expression(expression, env),
+    }
+}
+
+fn eval_expression(expression: &Expression, env: &Env) -> Result<Object> {
+    match expression {
+        Expression::Identifier(name) => {
+            let bound = env.borrow().get(name);
+            bound
+                .or_else(|| builtin(name))
+                .ok_or_else(|| anyhow!("identifier not found: {}", name))
+        }
+        Expression::Integer(value) => Ok(Object::Integer(*value)),
+        Expression::Boolean(value) => Ok(Object::Boolean(*value)),
+        Expression::String(value) => Ok(Object::String(value.clone())),
+        Expression::Array(elements) => Ok(Object::Array(eval_expressions(elements, env)?)),
+        Expression::Hash(pairs) => {
+            let mut hash = BTreeMap::new();
+            for (key, value) in pairs {
+                let key = eval_expression(key, env)?;
+                let key = HashKey::from_object(&key)
+                    .ok_or_else(|| anyhow!("unusable as hash key: {}", key.type_name()))?;
+                hash.insert(key, eval_expression(value, env)?);
+            }
+            Ok(Object::Hash(hash))
+        }
+        Expression::Prefix { operator, right } => {
+            eval_prefix(operator, eval_expression(right, env)?)
+        }
+        Expression::Infix {
+            left,
+            operator,
+            right,
+        } => {
+            let left = eval_expression(left, env)?;
+            // `&&` and `||` only evaluate their right side when it decides the result
+            match operator.as_str() {
+                "&&" if !left.is_truthy() => return Ok(Object::Boolean(false)),
+                "||" if left.is_truthy() => return Ok(Object::Boolean(true)),
+                "&&" | "||" => {
+                    return Ok(Object::Boolean(eval_expression(right, env)?.is_truthy()))
+                }
+                _ => {}
+            }
+            eval_infix(operator, left, eval_expression(right, env)?)
+        }
+        Expression::Index { left, index } => {
+            eval_index(eval_expression(left, env)?, eval_expression(index, env)?)
+        }
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            if eval_expression(condition, env)?.is_truthy() {
+                eval_block(consequence, env)
+            } else if let Some(alternative) = alternative {
+                eval_block(alternative, env)
+            } else {
+                Ok(Object::Null)
+            }
+        }
+        Expression::Function { parameters, body } => Ok(Object::Function {
+            parameters: parameters.clone(),
+            body: body.clone(),
+            env: env.clone(),
+        }),
+        Expression::Call {
+            function,
+            arguments,
+        } => {
+            let function = eval_expression(function, env)?;
+            let arguments = eval_expressions(arguments, env)?;
+            apply_function(function, &arguments)
+        }
+    }
+}
+
+fn eval_expressions(expressions: &[Expression], env: &Env) -> Result<Vec<Object>> {
+    expressions
+        .iter()
+        .map(|expression| eval_expression(expression, env))
+        .collect()
+}
+
+fn eval_prefix(operator: &str, right: Object) -> Result<Object> {
+    match (operator, right) {
+        ("!", right) => Ok(Object::Boolean(!right.is_truthy())),
+        ("-", Object::Integer(value)) => value
+            .checked_neg()
+            .map(Object::Integer)
+            .ok_or_else(|| anyhow!("integer overflow: -{}", value)),
+        (operator, right) => bail!("unknown operator: {}{}", operator, right.type_name()),
+    }
+}
+
+fn eval_infix(operator: &str, left: Object, right: Object) -> Result<Object> {
+    match (&left, &right) {
+        (Object::Integer(l), Object::Integer(r)) => {
+            let (l, r) = (*l, *r);
+            let arithmetic = match operator {
+                "+" => l.checked_add(r),
+                "-" => l.checked_sub(r),
+                "*" => l.checked_mul(r),
+                "/" if r == 0 => bail!("division by zero: {} / 0", l),
+                "/" => l.checked_div(r),
+                _ => return compare(operator, &l, &r, &left, &right),
+            };
+            arithmetic
+                .map(Object::Integer)
+                .ok_or_else(|| anyhow!("integer overflow: {} {} {}", l, operator, r))
+        }
+        (Object::String(l), Object::String(r)) => match operator {
+            "+" => Ok(Object::String(format!("{}{}", l, r))),
+            _ => compare(operator, l, r, &left, &right),
+        },
+        (Object::Boolean(l), Object::Boolean(r)) if matches!(operator, "==" | "!=") => {
+            compare(operator, l, r, &left, &right)
+        }
+        (Object::Null, Object::Null) if matches!(operator, "==" | "!=") => {
+            Ok(Object::Boolean(operator == "=="))
+        }
+        _ if left.type_name() != right.type_name() => bail!(
+            "type mismatch: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        ),
+        _ => bail!(
+            "unknown operator: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        ),
+    }
+}
+
+/// Apply a comparison operator to two values of the same type
+fn compare<T: PartialOrd>(
+    operator: &str,
+    l: &T,
+    r: &T,
+    left: &Object,
+    right: &Object,
+) -> Result<Object> {
+    let result = match operator {
+        "==" => l == r,
+        "!=" => l != r,
+        "<" => l < r,
+        ">" => l > r,
+        "<=" => l <= r,
+        ">=" => l >= r,
+        _ => bail!(
+            "unknown operator: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        ),
+    };
+    Ok(Object::Boolean(result))
+}
+
+fn eval_index(left: Object, index: Object) -> Result<Object> {
+    match (&left, &index) {
+        (Object::Array(elements), Object::Integer(i)) => Ok(usize::try_from(*i)
+            .ok()
+            .and_then(|i| elements.get(i))
+            .cloned()
+            .unwrap_or(Object::Null)),
+        (Object::Hash(pairs), _) => {
+            let key = HashKey::from_object(&index)
+                .ok_or_else(|| anyhow!("unusable as hash key: {}", index.type_name()))?;
+            Ok(pairs.get(&key).cloned().unwrap_or(Object::Null))
+        }
+        _ => bail!(
+            "index operator not supported: {}[{}]",
+            left.type_name(),
+            index.type_name()
+        ),
+    }
+}
+
+fn apply_function(function: Object, arguments: &[Object]) -> Result<Object> {
+    match function {
+        Object::Function {
+            parameters,
+            body,
+            env,
+        } => {
+            if parameters.len() != arguments.len() {
+                bail!(
+                    "wrong number of arguments: want={}, got={}",
+                    parameters.len(),
+                    arguments.len()
+                );
+            }
+            let scope = Environment::enclosed(env);
+            for (parameter, argument) in parameters.iter().zip(arguments) {
+                scope.borrow_mut().set(parameter, argument.clone());
+            }
+            Ok(match eval_block(&body, &scope)? {
+                Object::Return(value) => *value,
+                value => value,
+            })
+        }
+        Object::Builtin { function, .. } => function(arguments),
+        other => bail!("not a function: {}", other.type_name()),
+    }
+}
+
+/// Functions available everywhere unless a binding of the same name hides them, the
+/// [`stdlib`] included
+fn builtin(name: &str) -> Option<Object> {
+    let function = match name {
+        "len" => Object::builtin(name, |arguments| match single(arguments)? {
+            Object::String(value) => Ok(Object::Integer(value.chars().count() as i64)),
+            Object::Array(elements) => Ok(Object::Integer(elements.len() as i64)),
+            other => bail!("argument to `len` not supported, got {}", other.type_name()),
+        }),
+        "first" => Object::builtin(name, |arguments| {
+            Ok(array(single(arguments)?, "first")?
+                .first()
+                .cloned()
+                .unwrap_or(Object::Null))
+        }),
+        "last" => Object::builtin(name, |arguments| {
+            Ok(array(single(arguments)?, "last")?
+                .last()
+                .cloned()
+                .unwrap_or(Object::Null))
+        }),
+        "rest" => Object::builtin(name, |arguments| {
+            let elements = array(single(arguments)?, "rest")?;
+            Ok(match elements.split_first() {
+                Some((_, rest)) => Object::Array(rest.to_vec()),
+                None => Object::Null,
+            })
+        }),
+        "push" => Object::builtin(name, |arguments| match arguments {
+            [list, value] => {
+                let mut elements = array(list, "push")?.to_vec();
+                elements.push(value.clone());
+                Ok(Object::Array(elements))
+            }
+            _ => bail!("wrong number of arguments: want=2, got={}", arguments.len()),
+        }),
+        _ => return stdlib::function(name),
+    };
+    Some(function)
+}
+
+fn single(arguments: &[Object]) -> Result<&Object> {
+    match arguments {
+        [argument] => Ok(argument),
+        _ => bail!("wrong number of arguments: want=1, got={}", arguments.len()),
+    }
+}
+
+fn array<'a>(object: &'a Object, name: &str) -> Result<&'a [Object]> {
+    match object {
+        Object::Array(elements) => Ok(elements),
+        other => bail!(
+            "argument to `{}` must be ARRAY, got {}",
+            name,
+            other.type_name()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feather_lang::eval;
+
+    fn eval_to_string(input: &str) -> String {
+        match eval(input, &Environment::new()) {
+            Ok(value) => format!("{:?}", value),
+            Err(err) => format!("ERROR: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let cases = [
+            ("5 + 5 * 2 - -10 / 2", "20"),
+            ("(1 < 2) == true && !(3 >= 4)", "true"),
+            ("\"feather\" + \"flow\" == 'featherflow'", "true"),
+            ("if (1 > 2) { 10 }", "null"),
+            (
+                "if (null_name) { 1 }",
+                "ERROR: identifier not found: null_name",
+            ),
+            ("if (10 > 1) { if (10 > 1) { return 10; } return 1; }", "10"),
+            (
+                "let add = fn(a) { fn(b) { a + b } }; let two = add(2); two(3)",
+                "5",
+            ),
+            (
+                "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(15)",
+                "610",
+            ),
+            (
+                "let a = [1, 2 * 2, 'x']; [len(a), a[1], a[3], last(push(a, 4))]",
+                "[3, 4, null, 4]",
+            ),
+            (
+                "let target = {'name': 'prod', 1: true}; [target.name, target[1], target.schema]",
+                "[\"prod\", true, null]",
+            ),
+            ("false || 0 && len('')", "true"),
+            ("5 + true", "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("'a' - 'b'", "ERROR: unknown operator: STRING - STRING"),
+            ("1 / 0", "ERROR: division by zero: 1 / 0"),
+            ("{[1]: 2}", "ERROR: unusable as hash key: ARRAY"),
+            (
+                "len(1)",
+                "ERROR: argument to `len` not supported, got INTEGER",
+            ),
+            (
+                "fn(x) { x }(1, 2)",
+                "ERROR: wrong number of arguments: want=1, got=2",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(eval_to_string(input), expected, "{}", input);
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use super::token::{lookup_ident, Token, TokenType};
+
+/// Lexer struct that iterates over an input string and produces tokens.
+///
+/// Input is read a `char` at a time, so any UTF-8 text is handled; identifiers may use any
+/// alphabetic characters. Every token records the line and column it starts at, both
+/// 1-based, with columns counted in characters. `//` starts a comment running to the end of
+/// the line, and `/* ... */` comments may span lines.
+pub struct Lexer<'a> {
+    input: &'a str,
+    position: usize,      // Byte index of current_char
+    read_position: usize, // Byte index of the char after it
+    current_char: Option<char>,
+    line: usize,   // Line of current_char
+    column: usize, // Column of current_char
+}
+
+impl<'a> Lexer<'a> {
+    /// Create a new Lexer from an input string.
+    pub fn new(input: &'a str) -> Self {
+        let mut lexer = Lexer {
+            input,
+            position: 0,
+            read_position: 0,
+            current_char: None,
+            line: 1,
+            column: 1,
+        };
+        lexer.read_char(); // Initialize the first character
+        lexer
+    }
+
+    /// Advance to the next character, keeping track of its line and column.
+    /// Sets current_char to None when end of input is reached.
+    fn read_char(&mut self) {
+        match self.current_char {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+            }
+            Some(_) => self.column += 1,
+            None => {}
+        }
+        self.position = self.read_position;
+        self.current_char = self.input[self.position..].chars().next();
+        if let Some(ch) = self.current_char {
+            self.read_position += ch.len_utf8();
+        }
+    }
+
+    /// Peek at the next character without moving the lexer forward.
+    /// Returns None if at end of input.
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.read_position..].chars().next()
+    }
+
+    /// Skip over whitespace and comments. Returns the start of a block comment left open
+    /// at the end of the input, which makes it illegal.
+    fn skip_whitespace_and_comments(&mut self) -> Option<Token> {
+        loop {
+            match (self.current_char, self.peek_char()) {
+                (Some(ch), _) if ch.is_whitespace() => self.read_char(),
+                (Some('/'), Some('/')) => {
+                    while !matches!(self.current_char, Some('\n') | None) {
+                        self.read_char();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let open = self.token(TokenType::Illegal, "unterminated comment");
+                    self.read_char();
+                    self.read_char();
+                    loop {
+                        match (self.current_char, self.peek_char()) {
+                            (Some('*'), Some('/')) => break,
+                            (Some(_), _) => self.read_char(),
+                            (None, _) => return Some(open),
+                        }
+                    }
+                    self.read_char();
+                    self.read_char();
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// A token starting at the current char
+    fn token(&self, token_type: TokenType, literal: &str) -> Token {
+        Token::new(token_type, literal, self.line, self.column)
+    }
+
+    /// Read a sequence of letters (and digits) to form an identifier or keyword.
+    /// Assumes current_char is at the start of an identifier.
+    fn read_identifier(&mut self) -> &'a str {
+        let start_pos = self.position;
+        while let Some(ch) = self.current_char {
+            if is_letter(ch) || ch.is_ascii_digit() {
+                self.read_char();
+            } else {
+                break;
+            }
+        }
+        &self.input[start_pos..self.position]
+    }
+
+    /// Read a sequence of digits to form a number literal.
+    /// Assumes current_char is at the start of a number.
+    fn read_number(&mut self) -> &'a str {
+        let start_pos = self.position;
+        while let Some(ch) = self.current_char {
+            if ch.is_ascii_digit() {
+                self.read_char();
+            } else {
+                break;
+            }
+        }
+        &self.input[start_pos..self.position]
+    }
+
+    /// Read a string literal up to the closing `quote`, leaving current_char on it, and
+    /// return its value with escapes (`\n`, `\t`, `\\` and an escaped quote) applied.
+    /// Fails with a description of the problem.
+    fn read_string(&mut self, quote: char) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            self.read_char();
+            match self.current_char {
+                Some(ch) if ch == quote => return Ok(value),
+                Some('\\') => {
+                    self.read_char();
+                    value.push(match self.current_char {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some(ch @ ('\\' | '"' | '\'')) => ch,
+                        Some(ch) => return Err(format!("unknown escape sequence '\\{}'", ch)),
+                        None => return Err("unterminated string".to_string()),
+                    });
+                }
+                Some(ch) => value.push(ch),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    /// A token for the current char, or for the two-char operator `second` completes
+    fn one_or_two(&mut self, second: char, one: TokenType, two: TokenType) -> Token {
+        let start = self.position;
+        let token = if self.peek_char() == Some(second) {
+            let token = self.token(two, "");
+            self.read_char();
+            token
+        } else {
+            self.token(one, "")
+        };
+        Token {
+            literal: self.input[start..self.read_position].to_string(),
+            ..token
+        }
+    }
+
+    /// Fetch the next token from the input.
+    pub fn next_token(&mut self) -> Token {
+        // Skip whitespace and comments, leaving current_char at the next token (or EOF)
+        if let Some(unclosed) = self.skip_whitespace_and_comments() {
+            return unclosed;
+        }
+
+        let single = |lexer: &Self, token_type| {
+            lexer.token(
+                token_type,
+                &lexer.input[lexer.position..lexer.read_position],
+            )
+        };
+        let token = match self.current_char {
+            // End of file/input
+            None => return self.token(TokenType::Eof, ""),
+
+            // Operators that may be followed by a second character
+            Some('=') => self.one_or_two('=', TokenType::Assign, TokenType::Eq),
+            Some('!') => self.one_or_two('=', TokenType::Bang, TokenType::NotEq),
+            Some('<') => self.one_or_two('=', TokenType::Lt, TokenType::LtEq),
+            Some('>') => self.one_or_two('=', TokenType::Gt, TokenType::GtEq),
+            Some('&') => self.one_or_two('&', TokenType::Illegal, TokenType::And),
+            Some('|') => self.one_or_two('|', TokenType::Illegal, TokenType::Or),
+
+            // Single-character tokens (operators & delimiters)
+            Some('+') => single(self, TokenType::Plus),
+            Some('-') => single(self, TokenType::Minus),
+            Some('*') => single(self, TokenType::Asterisk),
+            Some('/') => single(self, TokenType::Slash),
+            Some(',') => single(self, TokenType::Comma),
+            Some(';') => single(self, TokenType::Semicolon),
+            Some(':') => single(self, TokenType::Colon),
+            Some('.') => single(self, TokenType::Dot),
+            Some('(') => single(self, TokenType::LParen),
+            Some(')') => single(self, TokenType::RParen),
+            Some('{') => single(self, TokenType::LBrace),
+            Some('}') => single(self, TokenType::RBrace),
+            Some('[') => single(self, TokenType::LBracket),
+            Some(']') => single(self, TokenType::RBracket),
+
+            // String literals, in double or single quotes
+            Some(quote @ ('"' | '\'')) => {
+                let start = self.token(TokenType::String, "");
+                match self.read_string(quote) {
+                    Ok(value) => Token {
+                        literal: value,
+                        ..start
+                    },
+                    // The rest of the input can't be tokenized reliably after a bad string
+                    Err(problem) => {
+                        let rest = self.input.len();
+                        self.position = rest;
+                        self.read_position = rest;
+                        self.current_char = None;
+                        return Token {
+                            token_type: TokenType::Illegal,
+                            literal: problem,
+                            ..start
+                        };
+                    }
+                }
+            }
+
+            // Identifiers and keywords
+            Some(ch) if is_letter(ch) => {
+                let start = self.token(TokenType::Ident, "");
+                let literal = self.read_identifier();
+                // read_identifier() has already advanced current_char past the identifier
+                return Token {
+                    token_type: lookup_ident(literal),
+                    literal: literal.to_string(),
+                    ..start
+                };
+            }
+
+            // Numbers (integer literals)
+            Some(ch) if ch.is_ascii_digit() => {
+                let start = self.token(TokenType::Int, "");
+                let literal = self.read_number();
+                return Token {
+                    literal: literal.to_string(),
+                    ..start
+                };
+            }
+
+            // Any other character (not recognized)
+            Some(_) => single(self, TokenType::Illegal),
+        };
+        let token = match token.token_type {
+            TokenType::Illegal => Token {
+                literal: format!("unexpected character '{}'", token.literal),
+                ..token
+            },
+            _ => token,
+        };
+
+        // Advance to the next character for subsequent calls, since we consumed this token's char(s)
+        self.read_char();
+        token
+    }
+}
+
+/// Whether `ch` can start or continue an identifier: any alphabetic character or underscore.
+fn is_letter(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+// Iterate over the tokens up to, but not including, the final EOF.
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.next_token();
+        if tok.token_type == TokenType::Eof {
+            None // Stop iteration at EOF
+        } else {
+            Some(tok)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_token() {
+        let input = r#"let add = fn(x, y) { x + y; };
+            !-/ *5 < 10 >= 9 != 8 == 7 && true || false;
+            target.name == 'prod'; ["a", 1][0]; {"key": 2}"#;
+        let expected = [
+            (TokenType::Let, "let"),
+            (TokenType::Ident, "add"),
+            (TokenType::Assign, "="),
+            (TokenType::Function, "fn"),
+            (TokenType::LParen, "("),
+            (TokenType::Ident, "x"),
+            (TokenType::Comma, ","),
+            (TokenType::Ident, "y"),
+            (TokenType::RParen, ")"),
+            (TokenType::LBrace, "{"),
+            (TokenType::Ident, "x"),
+            (TokenType::Plus, "+"),
+            (TokenType::Ident, "y"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::RBrace, "}"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Bang, "!"),
+            (TokenType::Minus, "-"),
+            (TokenType::Slash, "/"),
+            (TokenType::Asterisk, "*"),
+            (TokenType::Int, "5"),
+            (TokenType::Lt, "<"),
+            (TokenType::Int, "10"),
+            (TokenType::GtEq, ">="),
+            (TokenType::Int, "9"),
+            (TokenType::NotEq, "!="),
+            (TokenType::Int, "8"),
+            (TokenType::Eq, "=="),
+            (TokenType::Int, "7"),
+            (TokenType::And, "&&"),
+            (TokenType::True, "true"),
+            (TokenType::Or, "||"),
+            (TokenType::False, "false"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "target"),
+            (TokenType::Dot, "."),
+            (TokenType::Ident, "name"),
+            (TokenType::Eq, "=="),
+            (TokenType::String, "prod"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::LBracket, "["),
+            (TokenType::String, "a"),
+            (TokenType::Comma, ","),
+            (TokenType::Int, "1"),
+            (TokenType::RBracket, "]"),
+            (TokenType::LBracket, "["),
+            (TokenType::Int, "0"),
+            (TokenType::RBracket, "]"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::LBrace, "{"),
+            (TokenType::String, "key"),
+            (TokenType::Colon, ":"),
+            (TokenType::Int, "2"),
+            (TokenType::RBrace, "}"),
+        ];
+
+        let tokens: Vec<Token> = Lexer::new(input).collect();
+        let tokens: Vec<(TokenType, &str)> = tokens
+            .iter()
+            .map(|token| (token.token_type, token.literal.as_str()))
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_unicode_positions_comments_and_strings() {
+        let input = "// région du client\nlet größe = \"naïve \\\"café\\\"\\n\"; /* note:\n ünïcode */ größe\n'open";
+        let tokens: Vec<Token> = Lexer::new(input).collect();
+        let tokens: Vec<(TokenType, &str, usize, usize)> = tokens
+            .iter()
+            .map(|token| {
+                let literal = token.literal.as_str();
+                (token.token_type, literal, token.line, token.column)
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                (TokenType::Let, "let", 2, 1),
+                (TokenType::Ident, "größe", 2, 5),
+                (TokenType::Assign, "=", 2, 11),
+                (TokenType::String, "naïve \"café\"\n", 2, 13),
+                (TokenType::Semicolon, ";", 2, 31),
+                (TokenType::Ident, "größe", 3, 13),
+                (TokenType::Illegal, "unterminated string", 4, 1),
+            ]
+        );
+        assert_eq!(
+            Lexer::new("1 /* open").nth(1).unwrap(),
+            Token::new(TokenType::Illegal, "unterminated comment", 1, 3)
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! feather_lang: a small expression language in the style of Monkey
+//!
+//! Integers, booleans, strings, arrays, hashes, first-class functions with closures, `let`,
+//! `if`/`else`, `return`, and a [`stdlib`] of string and date functions. Source goes through
+//! the [`lexer`], a Pratt [`parser`] building the [`ast`], and a tree-walking [`evaluator`].
+//! It backs `ff expr`, its REPL, and the function calls and `{% if %}` conditions in model
+//! SQL (see [`crate::sql_engine::template`]).
+
+pub mod ast;
+pub mod evaluator;
+pub mod lexer;
+pub mod object;
+pub mod parser;
+pub mod repl;
+pub mod stdlib;
+pub mod token;
+
+use anyhow::{bail, Result};
+
+use lexer::Lexer;
+use object::{Env, Object};
+use parser::Parser;
+
+/// Parse `source`, failing with every parse error found and where it is
+pub fn parse(source: &str) -> Result<ast::Program> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        let errors: Vec<String> = parser.errors().iter().map(ToString::to_string).collect();
+        bail!("{}", errors.join("; "));
+    }
+    Ok(program)
+}
+
+/// Parse and evaluate `source` in `env`, returning the value of its last statement
+pub fn eval(source: &str, env: &Env) -> Result<Object> {
+    evaluator::eval_program(&parse(source)?, env)
+}
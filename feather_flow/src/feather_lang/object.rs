@@ -0,0 +1,168 @@
+//! Runtime values and the environments that bind names to them
+
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use super::ast::BlockStatement;
+
+/// A shared, mutable scope; functions keep the one they were defined in
+pub type Env = Rc<RefCell<Environment>>;
+
+/// Signature of functions implemented in Rust
+pub type BuiltinFunction = dyn Fn(&[Object]) -> Result<Object>;
+
+#[derive(Clone)]
+pub enum Object {
+    Null,
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
+    /// Carries a `return` out of nested blocks; never seen outside the evaluator
+    Return(Box<Object>),
+    Function {
+        parameters: Vec<String>,
+        body: BlockStatement,
+        env: Env,
+    },
+    Builtin {
+        name: String,
+        function: Rc<BuiltinFunction>,
+    },
+}
+
+impl Object {
+    /// Name of the value's type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Null => "NULL",
+            Object::Integer(_) => "INTEGER",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::String(_) => "STRING",
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
+            Object::Return(_) => "RETURN_VALUE",
+            Object::Function { .. } => "FUNCTION",
+            Object::Builtin { .. } => "BUILTIN",
+        }
+    }
+
+    /// `null` and `false` are false, every other value is true
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Null | Object::Boolean(false))
+    }
+
+    pub fn builtin(name: &str, function: impl Fn(&[Object]) -> Result<Object> + 'static) -> Self {
+        Object::Builtin {
+            name: name.to_string(),
+            function: Rc::new(function),
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Null => write!(f, "null"),
+            Object::Integer(value) => write!(f, "{}", value),
+            Object::Boolean(value) => write!(f, "{}", value),
+            Object::String(value) => write!(f, "{}", value),
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(inspect).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Object::Hash(pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, inspect(value)))
+                    .collect();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+            Object::Return(value) => write!(f, "{}", value),
+            Object::Function {
+                parameters, body, ..
+            } => {
+                write!(f, "fn({}) {{ {} }}", parameters.join(", "), body)
+            }
+            Object::Builtin { name, .. } => write!(f, "builtin {}", name),
+        }
+    }
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", inspect(self))
+    }
+}
+
+/// A value as it would be written in source: strings quoted, everything else displayed
+fn inspect(object: &Object) -> String {
+    match object {
+        Object::String(value) => format!("{:?}", value),
+        other => other.to_string(),
+    }
+}
+
+/// The values that can key a hash
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HashKey {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+impl HashKey {
+    pub fn from_object(object: &Object) -> Option<Self> {
+        match object {
+            Object::Integer(value) => Some(HashKey::Integer(*value)),
+            Object::Boolean(value) => Some(HashKey::Boolean(*value)),
+            Object::String(value) => Some(HashKey::String(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashKey::Integer(value) => write!(f, "{}", value),
+            HashKey::Boolean(value) => write!(f, "{}", value),
+            HashKey::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Environment {
+    store: HashMap<String, Object>,
+    outer: Option<Env>,
+}
+
+impl Environment {
+    pub fn new() -> Env {
+        Rc::new(RefCell::new(Self::default()))
+    }
+
+    /// A scope for a function call, falling back to `outer` for names it does not bind
+    pub fn enclosed(outer: Env) -> Env {
+        Rc::new(RefCell::new(Self {
+            store: HashMap::new(),
+            outer: Some(outer),
+        }))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        match self.store.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.outer.as_ref()?.borrow().get(name),
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: Object) {
+        self.store.insert(name.to_string(), value);
+    }
+}
@@ -0,0 +1,428 @@
+//! Pratt parser: each token type has a prefix and/or infix parse function, and binary
+//! operators bind by [`Precedence`]
+
+use std::fmt;
+
+use super::ast::{BlockStatement, Expression, Program, Statement};
+use super::lexer::Lexer;
+use super::token::{Token, TokenType};
+
+/// A problem found while parsing, at the start of the token it is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Lowest,
+    Or,
+    And,
+    Equals,
+    LessGreater,
+    Sum,
+    Product,
+    Prefix,
+    Call,
+    Index,
+}
+
+fn precedence(token_type: TokenType) -> Precedence {
+    match token_type {
+        TokenType::Or => Precedence::Or,
+        TokenType::And => Precedence::And,
+        TokenType::Eq | TokenType::NotEq => Precedence::Equals,
+        TokenType::Lt | TokenType::Gt | TokenType::LtEq | TokenType::GtEq => {
+            Precedence::LessGreater
+        }
+        TokenType::Plus | TokenType::Minus => Precedence::Sum,
+        TokenType::Asterisk | TokenType::Slash => Precedence::Product,
+        TokenType::LParen => Precedence::Call,
+        TokenType::LBracket | TokenType::Dot => Precedence::Index,
+        _ => Precedence::Lowest,
+    }
+}
+
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    current: Token,
+    peek: Token,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(lexer: Lexer<'a>) -> Self {
+        let mut lexer = lexer;
+        let current = lexer.next_token();
+        let peek = lexer.next_token();
+        Self {
+            lexer,
+            current,
+            peek,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Problems found by `parse_program`, in the order they were found
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    fn error(&mut self, at: &Token, message: String) {
+        self.errors.push(ParseError {
+            line: at.line,
+            column: at.column,
+            message,
+        });
+    }
+
+    pub fn parse_program(&mut self) -> Program {
+        let mut statements = Vec::new();
+        while self.current.token_type != TokenType::Eof {
+            match self.parse_statement() {
+                Some(statement) => statements.push(statement),
+                // Skip the rest of a broken statement rather than reporting each of its tokens
+                None => {
+                    while !matches!(
+                        self.current.token_type,
+                        TokenType::Semicolon | TokenType::Eof
+                    ) {
+                        self.next_token();
+                    }
+                }
+            }
+            self.next_token();
+        }
+        Program { statements }
+    }
+
+    fn next_token(&mut self) {
+        self.current = std::mem::replace(&mut self.peek, self.lexer.next_token());
+    }
+
+    fn peek_is(&self, token_type: TokenType) -> bool {
+        self.peek.token_type == token_type
+    }
+
+    /// Advance onto the peek token if it is `token_type`, recording an error otherwise
+    fn expect_peek(&mut self, token_type: TokenType) -> Option<()> {
+        if self.peek_is(token_type) {
+            self.next_token();
+            return Some(());
+        }
+        let peek = self.peek.clone();
+        let message = match peek.token_type {
+            TokenType::Eof => format!("expected {:?}, got end of input", token_type),
+            TokenType::Illegal => peek.literal.clone(),
+            _ => format!(
+                "expected {:?}, got {:?} '{}'",
+                token_type, peek.token_type, peek.literal
+            ),
+        };
+        self.error(&peek, message);
+        None
+    }
+
+    fn parse_statement(&mut self) -> Option<Statement> {
+        match self.current.token_type {
+            TokenType::Let => self.parse_let_statement(),
+            TokenType::Return => self.parse_return_statement(),
+            _ => self.parse_expression_statement(),
+        }
+    }
+
+    fn parse_let_statement(&mut self) -> Option<Statement> {
+        self.expect_peek(TokenType::Ident)?;
+        let name = self.current.literal.clone();
+        self.expect_peek(TokenType::Assign)?;
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Some(Statement::Let { name, value })
+    }
+
+    fn parse_return_statement(&mut self) -> Option<Statement> {
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Some(Statement::Return(value))
+    }
+
+    fn parse_expression_statement(&mut self) -> Option<Statement> {
+        let expression = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Some(Statement::Expression(expression))
+    }
+
+    fn parse_expression(&mut self, precedence_floor: Precedence) -> Option<Expression> {
+        let mut left = self.parse_prefix()?;
+        while !self.peek_is(TokenType::Semicolon)
+            && precedence_floor < precedence(self.peek.token_type)
+        {
+            self.next_token();
+            left = self.parse_infix(left)?;
+        }
+        Some(left)
+    }
+
+    fn parse_prefix(&mut self) -> Option<Expression> {
+        let token = self.current.clone();
+        match token.token_type {
+            TokenType::Ident => Some(Expression::Identifier(token.literal)),
+            TokenType::Int => match token.literal.parse() {
+                Ok(value) => Some(Expression::Integer(value)),
+                Err(_) => {
+                    let message = format!("could not parse {} as an integer", token.literal);
+                    self.error(&token, message);
+                    None
+                }
+            },
+            TokenType::String => Some(Expression::String(token.literal)),
+            TokenType::True => Some(Expression::Boolean(true)),
+            TokenType::False => Some(Expression::Boolean(false)),
+            TokenType::Bang | TokenType::Minus => {
+                self.next_token();
+                let right = self.parse_expression(Precedence::Prefix)?;
+                Some(Expression::Prefix {
+                    operator: token.literal,
+                    right: Box::new(right),
+                })
+            }
+            TokenType::LParen => {
+                self.next_token();
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                self.expect_peek(TokenType::RParen)?;
+                Some(expression)
+            }
+            TokenType::LBracket => Some(Expression::Array(
+                self.parse_expression_list(TokenType::RBracket)?,
+            )),
+            TokenType::LBrace => self.parse_hash_literal(),
+            TokenType::If => self.parse_if_expression(),
+            TokenType::Function => self.parse_function_literal(),
+            TokenType::Eof => {
+                self.error(&token, "unexpected end of input".to_string());
+                None
+            }
+            TokenType::Illegal => {
+                self.error(&token, token.literal.clone());
+                None
+            }
+            _ => {
+                let message = format!(
+                    "no expression can start with {:?} '{}'",
+                    token.token_type, token.literal
+                );
+                self.error(&token, message);
+                None
+            }
+        }
+    }
+
+    fn parse_infix(&mut self, left: Expression) -> Option<Expression> {
+        let token = self.current.clone();
+        match token.token_type {
+            TokenType::LParen => Some(Expression::Call {
+                function: Box::new(left),
+                arguments: self.parse_expression_list(TokenType::RParen)?,
+            }),
+            TokenType::LBracket => {
+                self.next_token();
+                let index = self.parse_expression(Precedence::Lowest)?;
+                self.expect_peek(TokenType::RBracket)?;
+                Some(Expression::Index {
+                    left: Box::new(left),
+                    index: Box::new(index),
+                })
+            }
+            TokenType::Dot => {
+                self.expect_peek(TokenType::Ident)?;
+                Some(Expression::Index {
+                    left: Box::new(left),
+                    index: Box::new(Expression::String(self.current.literal.clone())),
+                })
+            }
+            _ => {
+                let precedence = precedence(token.token_type);
+                self.next_token();
+                let right = self.parse_expression(precedence)?;
+                Some(Expression::Infix {
+                    left: Box::new(left),
+                    operator: token.literal,
+                    right: Box::new(right),
+                })
+            }
+        }
+    }
+
+    /// Comma-separated expressions up to `end`; the current token is the opening delimiter
+    fn parse_expression_list(&mut self, end: TokenType) -> Option<Vec<Expression>> {
+        let mut list = Vec::new();
+        if self.peek_is(end) {
+            self.next_token();
+            return Some(list);
+        }
+        self.next_token();
+        list.push(self.parse_expression(Precedence::Lowest)?);
+        while self.peek_is(TokenType::Comma) {
+            self.next_token();
+            self.next_token();
+            list.push(self.parse_expression(Precedence::Lowest)?);
+        }
+        self.expect_peek(end)?;
+        Some(list)
+    }
+
+    fn parse_hash_literal(&mut self) -> Option<Expression> {
+        let mut pairs = Vec::new();
+        while !self.peek_is(TokenType::RBrace) {
+            self.next_token();
+            let key = self.parse_expression(Precedence::Lowest)?;
+            self.expect_peek(TokenType::Colon)?;
+            self.next_token();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+            if !self.peek_is(TokenType::RBrace) {
+                self.expect_peek(TokenType::Comma)?;
+            }
+        }
+        self.expect_peek(TokenType::RBrace)?;
+        Some(Expression::Hash(pairs))
+    }
+
+    fn parse_if_expression(&mut self) -> Option<Expression> {
+        self.expect_peek(TokenType::LParen)?;
+        self.next_token();
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(TokenType::RParen)?;
+        self.expect_peek(TokenType::LBrace)?;
+        let consequence = self.parse_block_statement();
+
+        let alternative = if self.peek_is(TokenType::Else) {
+            self.next_token();
+            self.expect_peek(TokenType::LBrace)?;
+            Some(self.parse_block_statement())
+        } else {
+            None
+        };
+        Some(Expression::If {
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+        })
+    }
+
+    /// Statements up to the closing brace; the current token is the opening one
+    fn parse_block_statement(&mut self) -> BlockStatement {
+        let mut statements = Vec::new();
+        self.next_token();
+        while !matches!(self.current.token_type, TokenType::RBrace | TokenType::Eof) {
+            if let Some(statement) = self.parse_statement() {
+                statements.push(statement);
+            }
+            self.next_token();
+        }
+        if self.current.token_type == TokenType::Eof {
+            let end = self.current.clone();
+            self.error(&end, "expected RBrace, got end of input".to_string());
+        }
+        BlockStatement { statements }
+    }
+
+    fn parse_function_literal(&mut self) -> Option<Expression> {
+        self.expect_peek(TokenType::LParen)?;
+        let mut parameters = Vec::new();
+        if self.peek_is(TokenType::RParen) {
+            self.next_token();
+        } else {
+            self.expect_peek(TokenType::Ident)?;
+            parameters.push(self.current.literal.clone());
+            while self.peek_is(TokenType::Comma) {
+                self.next_token();
+                self.expect_peek(TokenType::Ident)?;
+                parameters.push(self.current.literal.clone());
+            }
+            self.expect_peek(TokenType::RParen)?;
+        }
+        self.expect_peek(TokenType::LBrace)?;
+        Some(Expression::Function {
+            parameters,
+            body: self.parse_block_statement(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Program {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+        program
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let cases = [
+            ("-a * b", "((-a) * b)"),
+            ("!-a", "(!(-a))"),
+            ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f)"),
+            ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
+            ("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4)"),
+            ("a || b && c == d", "(a || (b && (c == d)))"),
+            ("a + add(b * c) + d", "((a + add((b * c))) + d)"),
+            (
+                "a * [1, 2, 3, 4][b * c] * d",
+                "((a * ([1, 2, 3, 4][(b * c)])) * d)",
+            ),
+            (
+                "target.name == \"prod\"",
+                "((target[\"name\"]) == \"prod\")",
+            ),
+            (
+                "let x = fn(a, b) { a + b };",
+                "let x = fn(a, b) { (a + b) };",
+            ),
+            ("if (x < y) { x } else { y }", "if (x < y) { x } else { y }"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input).to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut parser = Parser::new(Lexer::new("let = 5; (1 + ;\n{\"a\" 1}; 'café' == \"open"));
+        parser.parse_program();
+        let errors: Vec<String> = parser.errors().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "line 1, column 5: expected Ident, got Assign '='",
+                "line 1, column 15: no expression can start with Semicolon ';'",
+                "line 2, column 6: expected Colon, got Int '1'",
+                "line 2, column 20: unterminated string",
+            ]
+        );
+    }
+}
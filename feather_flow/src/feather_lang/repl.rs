@@ -0,0 +1,228 @@
+//! Read-Eval-Print Loop
+//!
+//! Input continues over several lines while parentheses, braces or brackets are left open.
+//! Lines starting with `:` are commands to the REPL itself (see [`HELP`]). Bindings made
+//! with `let` last for the session, so callers can seed the environment with their own.
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use super::ast::Statement;
+use super::evaluator;
+use super::lexer::Lexer;
+use super::object::Env;
+use super::token::TokenType;
+
+pub const PROMPT: &str = ">> ";
+
+/// Prompt for the lines of an expression that is still open
+pub const CONTINUATION_PROMPT: &str = ".. ";
+
+pub const HELP: &str = "\
+Enter an expression or statement to evaluate it, e.g. `let double = fn(x) { x * 2 }; double(21)`.
+Input continues on the next line while a (, { or [ is open.
+
+Commands:
+  :help   Show this help
+  :quit   Leave the REPL (also Ctrl-D)
+
+Ctrl-C discards the input being entered.";
+
+/// What the REPL does after a line of input
+#[derive(Debug, PartialEq, Eq)]
+enum Next {
+    Prompt(&'static str),
+    Quit,
+}
+
+/// Input and bindings of one REPL session
+struct Session {
+    env: Env,
+    /// Lines of an expression that is still open
+    pending: String,
+}
+
+impl Session {
+    fn new(env: Env) -> Self {
+        Self {
+            env,
+            pending: String::new(),
+        }
+    }
+
+    /// Drop the lines of an unfinished expression
+    fn cancel(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Take one line of input, evaluating it once it completes an expression
+    fn feed<W: Write>(&mut self, line: &str, output: &mut W) -> io::Result<Next> {
+        if self.pending.is_empty() {
+            match line.trim() {
+                "" => return Ok(Next::Prompt(PROMPT)),
+                ":quit" | ":q" | ":exit" => return Ok(Next::Quit),
+                ":help" | ":h" => {
+                    writeln!(output, "{}", HELP)?;
+                    return Ok(Next::Prompt(PROMPT));
+                }
+                command if command.starts_with(':') => {
+                    writeln!(output, "unknown command {}; :help lists them", command)?;
+                    return Ok(Next::Prompt(PROMPT));
+                }
+                _ => {}
+            }
+        }
+
+        self.pending.push_str(line.trim_end_matches(['\r', '\n']));
+        self.pending.push('\n');
+        if is_open(&self.pending) {
+            return Ok(Next::Prompt(CONTINUATION_PROMPT));
+        }
+        let source = std::mem::take(&mut self.pending);
+
+        let program = match super::parse(&source) {
+            Ok(program) => program,
+            Err(err) => {
+                writeln!(output, "parse error: {}", err)?;
+                return Ok(Next::Prompt(PROMPT));
+            }
+        };
+        match evaluator::eval_program(&program, &self.env) {
+            // A `let` has no value worth echoing
+            Ok(_) if matches!(program.statements.last(), Some(Statement::Let { .. })) => {}
+            Ok(value) => writeln!(output, "{:?}", value)?,
+            Err(err) => writeln!(output, "error: {}", err)?,
+        }
+        Ok(Next::Prompt(PROMPT))
+    }
+}
+
+/// Whether `source` opens more parentheses, braces or brackets than it closes
+fn is_open(source: &str) -> bool {
+    let depth = Lexer::new(source).fold(0i64, |depth, token| match token.token_type {
+        TokenType::LParen | TokenType::LBrace | TokenType::LBracket => depth + 1,
+        TokenType::RParen | TokenType::RBrace | TokenType::RBracket => depth - 1,
+        _ => depth,
+    });
+    depth > 0
+}
+
+/// Starts the REPL over any reader, e.g. piped standard input, without line editing.
+///
+/// # Parameters
+/// - `input`: An object implementing `BufRead` (e.g. standard input)
+/// - `output`: A mutable reference to an object implementing `Write` (e.g. standard output)
+/// - `env`: The environment expressions are evaluated in
+pub fn start<R: BufRead, W: Write>(mut input: R, output: &mut W, env: Env) -> io::Result<()> {
+    let mut session = Session::new(env);
+    let mut prompt = PROMPT;
+    loop {
+        // Print the prompt and flush to ensure it appears immediately.
+        write!(output, "{}", prompt)?;
+        output.flush()?;
+
+        // Read a line of input from the user; zero bytes means EOF.
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(());
+        }
+        match session.feed(&line, output)? {
+            Next::Prompt(next) => prompt = next,
+            Next::Quit => return Ok(()),
+        }
+    }
+}
+
+/// Starts the REPL on the terminal with line editing. Entries are read from and saved to
+/// `history`, when given; a history file that can't be read or written is not an error.
+pub fn start_interactive(env: Env, history: Option<&Path>) -> Result<(), ReadlineError> {
+    let mut editor = DefaultEditor::new()?;
+    if let Some(history) = history {
+        // A missing file just means there is no history yet
+        let _ = editor.load_history(history);
+    }
+
+    let mut session = Session::new(env);
+    let mut stdout = io::stdout();
+    let mut prompt = PROMPT;
+    loop {
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                session.cancel();
+                prompt = PROMPT;
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err),
+        };
+        if !line.trim().is_empty() {
+            editor.add_history_entry(line.as_str())?;
+        }
+        match session.feed(&line, &mut stdout)? {
+            Next::Prompt(next) => prompt = next,
+            Next::Quit => break,
+        }
+    }
+
+    if let Some(history) = history {
+        if let Some(dir) = history.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(err) = editor.save_history(history) {
+            eprintln!(
+                "Warning: failed to save REPL history to {}: {}",
+                history.display(),
+                err
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feather_lang::object::Environment;
+
+    #[test]
+    fn test_bindings_last_for_the_session() {
+        let input = "let double = fn(x) { x * 2 };\ndouble(21)\nlet = 1\nmissing\n";
+        let mut output = Vec::new();
+        start(input.as_bytes(), &mut output, Environment::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            ">> >> 42\n>> parse error: line 1, column 5: expected Ident, got Assign '='\n\
+             >> error: identifier not found: missing\n>> \n"
+        );
+    }
+
+    #[test]
+    fn test_multi_line_input_and_commands() {
+        let input = "let add = fn(a, b) {\n  a + b\n}\n:nope\nadd(1,\n 2)\n:quit\n3\n";
+        let mut output = Vec::new();
+        start(input.as_bytes(), &mut output, Environment::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            ">> .. .. >> unknown command :nope; :help lists them\n>> .. 3\n>> "
+        );
+
+        let mut session = Session::new(Environment::new());
+        let mut output = Vec::new();
+        assert_eq!(
+            session.feed("[1, (2", &mut output).unwrap(),
+            Next::Prompt(CONTINUATION_PROMPT)
+        );
+        session.cancel();
+        assert_eq!(
+            session.feed(":help", &mut output).unwrap(),
+            Next::Prompt(PROMPT)
+        );
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .starts_with("Enter an expression"));
+    }
+}
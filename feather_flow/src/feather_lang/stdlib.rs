@@ -0,0 +1,375 @@
+//! Standard library: pure string and date functions for templates
+//!
+//! Available wherever feather_lang is, so in `{{ ... }}` expressions, `{% if %}` conditions
+//! and `ff expr`. Dates are `YYYY-MM-DD` strings, as date variables are. Every function is
+//! listed in [`FUNCTIONS`], which `ff help templating` prints.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Days, Months, NaiveDate};
+
+use super::object::Object;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A function of the standard library
+pub struct Function {
+    pub name: &'static str,
+    /// How it is called, e.g. `upper(s)`
+    pub signature: &'static str,
+    pub description: &'static str,
+    call: fn(&[Object]) -> Result<Object>,
+}
+
+pub const FUNCTIONS: &[Function] = &[
+    Function {
+        name: "upper",
+        signature: "upper(s)",
+        description: "s in upper case",
+        call: upper,
+    },
+    Function {
+        name: "lower",
+        signature: "lower(s)",
+        description: "s in lower case",
+        call: lower,
+    },
+    Function {
+        name: "trim",
+        signature: "trim(s)",
+        description: "s without leading and trailing whitespace",
+        call: trim,
+    },
+    Function {
+        name: "replace",
+        signature: "replace(s, from, to)",
+        description: "s with every occurrence of from replaced by to",
+        call: replace,
+    },
+    Function {
+        name: "concat",
+        signature: "concat(a, b, ...)",
+        description: "the arguments written one after another, e.g. concat('v', 2) is 'v2'",
+        call: concat,
+    },
+    Function {
+        name: "format",
+        signature: "format(template, a, ...)",
+        description: "template with each {} replaced by the next argument",
+        call: format,
+    },
+    Function {
+        name: "coalesce",
+        signature: "coalesce(a, b, ...)",
+        description: "the first argument that is neither null nor an empty string",
+        call: coalesce,
+    },
+    Function {
+        name: "date_add",
+        signature: "date_add(date, n[, unit])",
+        description: "date moved by n days, or by n of unit: day, week, month or year",
+        call: date_add,
+    },
+    Function {
+        name: "date_diff",
+        signature: "date_diff(end, start)",
+        description: "the number of days from start to end",
+        call: date_diff,
+    },
+];
+
+/// The standard library function `name` as a builtin
+pub fn function(name: &str) -> Option<Object> {
+    FUNCTIONS
+        .iter()
+        .find(|function| function.name == name)
+        .map(|function| Object::builtin(name, function.call))
+}
+
+fn upper(arguments: &[Object]) -> Result<Object> {
+    let [value] = arguments else {
+        return wrong_count(1, arguments);
+    };
+    Ok(Object::String(string(value, "upper")?.to_uppercase()))
+}
+
+fn lower(arguments: &[Object]) -> Result<Object> {
+    let [value] = arguments else {
+        return wrong_count(1, arguments);
+    };
+    Ok(Object::String(string(value, "lower")?.to_lowercase()))
+}
+
+fn trim(arguments: &[Object]) -> Result<Object> {
+    let [value] = arguments else {
+        return wrong_count(1, arguments);
+    };
+    Ok(Object::String(string(value, "trim")?.trim().to_string()))
+}
+
+fn replace(arguments: &[Object]) -> Result<Object> {
+    let [value, from, to] = arguments else {
+        return wrong_count(3, arguments);
+    };
+    let from = string(from, "replace")?;
+    if from.is_empty() {
+        bail!("argument to `replace` must not replace an empty string");
+    }
+    Ok(Object::String(
+        string(value, "replace")?.replace(from, string(to, "replace")?),
+    ))
+}
+
+fn concat(arguments: &[Object]) -> Result<Object> {
+    let mut joined = String::new();
+    for argument in arguments {
+        joined.push_str(&scalar(argument, "concat")?);
+    }
+    Ok(Object::String(joined))
+}
+
+fn format(arguments: &[Object]) -> Result<Object> {
+    let Some((template, values)) = arguments.split_first() else {
+        bail!("wrong number of arguments: want at least 1, got=0");
+    };
+    let pieces: Vec<&str> = string(template, "format")?.split("{}").collect();
+    if pieces.len() - 1 != values.len() {
+        bail!(
+            "format template has {} placeholder(s) but {} value(s) were given",
+            pieces.len() - 1,
+            values.len()
+        );
+    }
+
+    let mut formatted = pieces[0].to_string();
+    for (value, piece) in values.iter().zip(&pieces[1..]) {
+        formatted.push_str(&scalar(value, "format")?);
+        formatted.push_str(piece);
+    }
+    Ok(Object::String(formatted))
+}
+
+fn coalesce(arguments: &[Object]) -> Result<Object> {
+    Ok(arguments
+        .iter()
+        .find(|argument| !matches!(argument, Object::Null) && !is_empty_string(argument))
+        .cloned()
+        .unwrap_or(Object::Null))
+}
+
+fn is_empty_string(object: &Object) -> bool {
+    matches!(object, Object::String(value) if value.is_empty())
+}
+
+fn date_add(arguments: &[Object]) -> Result<Object> {
+    let (date, amount, unit) = match arguments {
+        [date, amount] => (date, amount, "day"),
+        [date, amount, unit] => (date, amount, string(unit, "date_add")?),
+        _ => bail!(
+            "wrong number of arguments: want=2 or 3, got={}",
+            arguments.len()
+        ),
+    };
+    let date = parse_date(date, "date_add")?;
+    let Object::Integer(amount) = amount else {
+        bail!(
+            "argument to `date_add` must be INTEGER, got {}",
+            amount.type_name()
+        );
+    };
+
+    let magnitude = amount.unsigned_abs();
+    let moved = match unit {
+        "day" | "days" => {
+            let days = Days::new(magnitude);
+            if *amount < 0 {
+                date.checked_sub_days(days)
+            } else {
+                date.checked_add_days(days)
+            }
+        }
+        "week" | "weeks" => {
+            let days = Days::new(magnitude.saturating_mul(7));
+            if *amount < 0 {
+                date.checked_sub_days(days)
+            } else {
+                date.checked_add_days(days)
+            }
+        }
+        "month" | "months" | "year" | "years" => {
+            let factor = if unit.starts_with("year") { 12 } else { 1 };
+            let months = u32::try_from(magnitude.saturating_mul(factor))
+                .map(Months::new)
+                .map_err(|_| anyhow!("date_add() moved {} out of range", date))?;
+            if *amount < 0 {
+                date.checked_sub_months(months)
+            } else {
+                date.checked_add_months(months)
+            }
+        }
+        other => bail!(
+            "unknown date_add() unit '{}' (use day, week, month or year)",
+            other
+        ),
+    }
+    .ok_or_else(|| anyhow!("date_add() moved {} out of range", date))?;
+
+    Ok(Object::String(moved.format(DATE_FORMAT).to_string()))
+}
+
+fn date_diff(arguments: &[Object]) -> Result<Object> {
+    let [end, start] = arguments else {
+        return wrong_count(2, arguments);
+    };
+    let days = parse_date(end, "date_diff")? - parse_date(start, "date_diff")?;
+    Ok(Object::Integer(days.num_days()))
+}
+
+fn parse_date(object: &Object, function: &str) -> Result<NaiveDate> {
+    let text = string(object, function)?;
+    NaiveDate::parse_from_str(text, DATE_FORMAT).map_err(|_| {
+        anyhow!(
+            "argument to `{}` must be a YYYY-MM-DD date, got '{}'",
+            function,
+            text
+        )
+    })
+}
+
+fn string<'a>(object: &'a Object, function: &str) -> Result<&'a str> {
+    match object {
+        Object::String(value) => Ok(value),
+        other => bail!(
+            "argument to `{}` must be STRING, got {}",
+            function,
+            other.type_name()
+        ),
+    }
+}
+
+/// A string, integer or boolean as text; other values are an error
+fn scalar(object: &Object, function: &str) -> Result<String> {
+    match object {
+        Object::String(_) | Object::Integer(_) | Object::Boolean(_) => Ok(object.to_string()),
+        other => bail!(
+            "argument to `{}` not supported, got {}",
+            function,
+            other.type_name()
+        ),
+    }
+}
+
+fn wrong_count(want: usize, arguments: &[Object]) -> Result<Object> {
+    bail!(
+        "wrong number of arguments: want={}, got={}",
+        want,
+        arguments.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feather_lang::{eval, object::Environment};
+
+    fn eval_to_string(input: &str) -> String {
+        match eval(input, &Environment::new()) {
+            Ok(value) => format!("{:?}", value),
+            Err(err) => format!("ERROR: {}", err),
+        }
+    }
+
+    fn check(cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            assert_eq!(eval_to_string(input), *expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_upper_lower_trim() {
+        check(&[
+            ("upper('Straße')", "\"STRASSE\""),
+            ("lower('PROD')", "\"prod\""),
+            ("trim('  eu \n')", "\"eu\""),
+            (
+                "upper(1)",
+                "ERROR: argument to `upper` must be STRING, got INTEGER",
+            ),
+            ("lower()", "ERROR: wrong number of arguments: want=1, got=0"),
+        ]);
+    }
+
+    #[test]
+    fn test_replace() {
+        check(&[
+            ("replace('a-b-c', '-', '_')", "\"a_b_c\""),
+            ("replace('abc', 'x', 'y')", "\"abc\""),
+            (
+                "replace('abc', '', 'y')",
+                "ERROR: argument to `replace` must not replace an empty string",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_concat() {
+        check(&[
+            ("concat('v', 2, '_', true)", "\"v2_true\""),
+            ("concat()", "\"\""),
+            (
+                "concat('a', [1])",
+                "ERROR: argument to `concat` not supported, got ARRAY",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_format() {
+        check(&[
+            ("format('{}_{}', 'events', 2024)", "\"events_2024\""),
+            ("format('plain')", "\"plain\""),
+            (
+                "format('{}_{}', 'events')",
+                "ERROR: format template has 2 placeholder(s) but 1 value(s) were given",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        check(&[
+            ("coalesce('', if (false) { 1 }, 'eu', 'us')", "\"eu\""),
+            ("coalesce(0, 1)", "0"),
+            ("coalesce()", "null"),
+        ]);
+    }
+
+    #[test]
+    fn test_date_add() {
+        check(&[
+            ("date_add('2024-02-27', 3)", "\"2024-03-01\""),
+            ("date_add('2024-01-01', -1)", "\"2023-12-31\""),
+            ("date_add('2024-01-31', 1, 'month')", "\"2024-02-29\""),
+            ("date_add('2024-01-01', 2, 'weeks')", "\"2024-01-15\""),
+            ("date_add('2024-02-29', -1, 'year')", "\"2023-02-28\""),
+            (
+                "date_add('2024-13-01', 1)",
+                "ERROR: argument to `date_add` must be a YYYY-MM-DD date, got '2024-13-01'",
+            ),
+            (
+                "date_add('2024-01-01', 1, 'hour')",
+                "ERROR: unknown date_add() unit 'hour' (use day, week, month or year)",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_date_diff() {
+        check(&[
+            ("date_diff('2024-03-01', '2024-02-01')", "29"),
+            ("date_diff('2024-01-01', '2024-01-08')", "-7"),
+            (
+                "date_diff('2024-01-01', 7)",
+                "ERROR: argument to `date_diff` must be STRING, got INTEGER",
+            ),
+        ]);
+    }
+}
@@ -0,0 +1,77 @@
+/// Kinds of token the lexer produces
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TokenType {
+    Illegal,
+    Eof,
+    Ident,
+    Int,
+    String,
+    Assign,
+    Plus,
+    Minus,
+    Bang,
+    Asterisk,
+    Slash,
+    Lt,
+    Gt,
+    LtEq,
+    GtEq,
+    Eq,
+    NotEq,
+    And,
+    Or,
+    Comma,
+    Semicolon,
+    Colon,
+    Dot,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Function,
+    Let,
+    True,
+    False,
+    If,
+    Else,
+    Return,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Token {
+    pub token_type: TokenType,
+    /// Source text of the token; a string literal's value with escapes applied; for an
+    /// `Illegal` token, what is wrong
+    pub literal: String,
+    /// 1-based line the token starts on
+    pub line: usize,
+    /// 1-based column, in characters, the token starts at
+    pub column: usize,
+}
+
+impl Token {
+    pub fn new(token_type: TokenType, literal: &str, line: usize, column: usize) -> Self {
+        Self {
+            token_type,
+            literal: literal.to_string(),
+            line,
+            column,
+        }
+    }
+}
+
+/// The keyword `ident` spells, or `Ident` for any other name
+pub fn lookup_ident(ident: &str) -> TokenType {
+    match ident {
+        "fn" => TokenType::Function,
+        "let" => TokenType::Let,
+        "true" => TokenType::True,
+        "false" => TokenType::False,
+        "if" => TokenType::If,
+        "else" => TokenType::Else,
+        "return" => TokenType::Return,
+        _ => TokenType::Ident,
+    }
+}
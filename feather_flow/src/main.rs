@@ -1,27 +1,79 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use std::process;
 
+use commands::parse::ImplicitRefPolicy;
+
+mod adapters;
 mod commands;
+mod config;
 mod display;
+mod error;
+mod feather_lang;
+mod profiling;
+mod run;
 mod sql_engine;
+mod state;
+mod stats;
+mod table;
 mod validators;
+mod yaml_edit;
 
 /// FeatherFlow (ff) CLI - SQL transformation tool
 #[derive(Parser)]
-#[clap(name = "ff", about = "FeatherFlow - SQL transformation tool", version)]
+#[clap(
+    name = "ff",
+    about = "FeatherFlow - SQL transformation tool",
+    version,
+    disable_help_subcommand = true
+)]
 struct Cli {
+    /// Refuse any SQL that could write to the warehouse; only SELECTs and metadata queries run
+    #[clap(long, global = true)]
+    read_only: bool,
+
+    /// Write a Chrome trace of parsing, YAML loading, graph building and queries to this file
+    /// (needs ff built with the `profiling` feature)
+    #[clap(long, global = true)]
+    profile_output: Option<PathBuf>,
+
+    /// Print plain ASCII status markers instead of emoji and Unicode symbols
+    #[clap(long, global = true)]
+    no_emoji: bool,
+
+    /// Disable colored output (also disabled when NO_COLOR is set)
+    #[clap(long, global = true)]
+    no_color: bool,
+
+    /// Stable, script-friendly output: data only on stdout, tab-separated records for
+    /// tabular results, no banners, colors or emoji
+    #[clap(long, global = true)]
+    porcelain: bool,
+
     #[clap(subcommand)]
     command: Command,
 }
 
+/// `--target` and `--vars`, taken by every command that loads the project
+#[derive(Args)]
+struct ProjectArgs {
+    /// Target whose schema/database overrides should be applied (e.g. dev, prod)
+    #[clap(short, long)]
+    target: Option<String>,
+
+    /// Values for project vars as a YAML mapping, e.g. '{start_date: 2024-01-01}'
+    #[clap(long)]
+    vars: Option<String>,
+}
+
 #[derive(Subcommand)]
 enum Command {
     /// Parse SQL files and build a dependency graph
     Parse {
-        /// Path to the SQL model files
+        /// Path to the SQL model files (default: `models_path` of the featherflow_project.yaml
+        /// found from the current directory)
         #[clap(short, long)]
-        model_path: PathBuf,
+        model_path: Option<PathBuf>,
 
         /// Output format for the graph (dot, text, json, yaml)
         #[clap(short, long, default_value = "text")]
@@ -30,76 +82,1515 @@ enum Command {
         /// File to write output to (if not provided, output to stdout)
         #[clap(short, long)]
         output_file: Option<String>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Warn about dependencies matched by schema.name instead of an explicit ref
+        #[clap(long)]
+        warn_implicit_refs: bool,
+
+        /// Fail when any dependency is matched by schema.name instead of an explicit ref
+        #[clap(long, conflicts_with = "warn_implicit_refs")]
+        require_explicit_refs: bool,
+
+        /// Keep running and parse again whenever a model's SQL or YAML or the project config
+        /// changes
+        #[clap(long)]
+        watch: bool,
+
+        /// Print how long each phase took, to see which one dominates
+        #[clap(long)]
+        stats: bool,
+
+        /// Fail on model and source YAML with unknown keys, wrong types, missing names or
+        /// duplicate entries
+        #[clap(long)]
+        strict_yaml: bool,
     },
 
     /// Validate model file structure
     Validate {
-        /// Path to the SQL model files
+        /// Path to the SQL model files (default: `models_path` of the featherflow_project.yaml
+        /// found from the current directory)
         #[clap(short, long)]
-        model_path: PathBuf,
+        model_path: Option<PathBuf>,
 
         /// Quiet mode - only output errors
         #[clap(short, long)]
         quiet: bool,
+
+        /// Keep running and validate again whenever a model's SQL or YAML or the project
+        /// config changes
+        #[clap(long)]
+        watch: bool,
+
+        /// Also write every finding as JSON to this file, e.g. .featherflow/diagnostics.json
+        #[clap(long)]
+        diagnostics_file: Option<PathBuf>,
+
+        /// Output format: text, json or sarif (SARIF 2.1.0 for code scanning)
+        #[clap(short, long, default_value = "text")]
+        format: String,
+
+        /// Also check model and source YAML for unknown keys, wrong types, missing names and
+        /// duplicate entries
+        #[clap(long)]
+        strict_yaml: bool,
+
+        /// Also compare the columns each model's YAML documents with the columns its SQL selects
+        #[clap(long)]
+        check_columns: bool,
+    },
+
+    /// List the project's nodes, or those a selection matches
+    Ls {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Nodes to list, in the same syntax as ff build --select (default: all); repeat to
+        /// select the union
+        #[clap(short, long)]
+        select: Vec<String>,
+
+        /// Name of a selector defined in selectors.yml, used instead of --select
+        #[clap(long, conflicts_with = "select")]
+        selector: Option<String>,
+
+        /// Nodes to leave out, in the same syntax as --select
+        #[clap(long)]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Run the data tests declared in model YAML, and regression checks against the project
+    Test {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// DuckDB database file to run the data tests against
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        /// Models whose data tests run, in the ff build selection syntax (default: all);
+        /// repeat to select the union
+        #[clap(short, long, requires = "db")]
+        select: Vec<String>,
+
+        /// Name of a selector defined in selectors.yml, used instead of --select
+        #[clap(long, requires = "db", conflicts_with = "select")]
+        selector: Option<String>,
+
+        /// Data tests or models to leave out, in the same syntax as --select
+        #[clap(long, requires = "db")]
+        exclude: Vec<String>,
+
+        /// Compare each model's compiled SQL with its snapshot under tests/snapshots/
+        #[clap(long)]
+        compiled_snapshots: bool,
+
+        /// Accept the current compiled SQL as the new snapshots
+        #[clap(long, requires = "compiled_snapshots")]
+        update_snapshots: bool,
+    },
+
+    /// Run a model's compiled SQL and show the first rows
+    Preview {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Name or unique id of the model to preview
+        #[clap(long)]
+        model: String,
+
+        /// DuckDB database file to query
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Maximum number of rows to show
+        #[clap(short, long, default_value_t = 10)]
+        limit: usize,
+
+        /// Always run the query instead of reusing a cached result
+        #[clap(long)]
+        no_cache: bool,
+
+        /// Seconds a cached result stays valid
+        #[clap(long, default_value_t = state::preview_cache::DEFAULT_TTL_SECS)]
+        cache_ttl: u64,
+
+        /// Seconds to wait for another invocation holding the state lock
+        #[clap(long)]
+        lock_timeout: Option<u64>,
+
+        /// Manifest (ff parse -f json output) of the environment to read missing upstream
+        /// models from
+        #[clap(long)]
+        state: Option<PathBuf>,
+
+        /// Read every upstream model from the --state manifest, even if it exists in --db
+        #[clap(long, requires = "state")]
+        favor_state: bool,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Remove local state and, with --schemas, this developer's isolated schemas
+    #[command(mut_arg("target", |arg| {
+        arg.help("Target whose isolated schemas are dropped (e.g. dev)")
+    }))]
+    Clean {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Drop the per-developer schemas configured under schema_isolation
+        #[clap(long, requires = "db")]
+        schemas: bool,
+
+        /// DuckDB database file holding the isolated schemas
+        #[clap(long)]
+        db: Option<PathBuf>,
+    },
+
+    /// Show the active profile and target and check that its connection works
+    Debug {
+        /// Path to the SQL model files (default: `models_path` of the featherflow_project.yaml
+        /// found from the current directory)
+        #[clap(short, long)]
+        model_path: Option<PathBuf>,
+
+        /// Target whose connection to check (default: FF_TARGET, then default_target, then
+        /// the profile's target)
+        #[clap(short, long)]
+        target: Option<String>,
+    },
+
+    /// Remove a stale lock left on the state directory by an interrupted invocation
+    Unlock {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+    },
+
+    /// Load seeds, build models and snapshots, and run their tests in dependency order
+    Build {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// DuckDB database file to build into
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        /// Nodes to build, e.g. '+fct_orders tag:finance,tag:daily @dim_customers' (default:
+        /// all); repeat to select the union
+        #[clap(short, long)]
+        select: Vec<String>,
+
+        /// Name of a selector defined in selectors.yml, used instead of --select
+        #[clap(long, conflicts_with = "select")]
+        selector: Option<String>,
+
+        /// Nodes to leave out, in the same syntax as --select
+        #[clap(long)]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Reuse the nodes the previous build completed before it was interrupted or failed
+        #[clap(long)]
+        resume: bool,
+
+        /// Print how long each phase took, to see which one dominates
+        #[clap(long)]
+        stats: bool,
+
+        /// Check the freshness of the sources upstream of the selection first, and fail
+        /// before running anything if any is past its error_after
+        #[clap(long)]
+        fail_stale_sources: bool,
+
+        /// Check the freshness of the sources upstream of the selection first, and skip the
+        /// nodes whose sources were not loaded since they last built successfully
+        #[clap(long)]
+        skip_stale_subgraphs: bool,
+    },
+
+    /// Capture the selected snapshots, updating the history of those with a unique_key
+    Snapshot {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// DuckDB database file holding the snapshots
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        /// Snapshots to capture, in the same syntax as build --select (default: all)
+        #[clap(short, long)]
+        select: Vec<String>,
+
+        /// Snapshots to leave out, in the same syntax as --select
+        #[clap(long)]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+
+    /// Print the DDL creating every model in the project's dialect, without running it
+    Compile {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Compile the project as of a git revision (commit, branch or tag) instead of the
+        /// working tree, and write that revision's manifest
+        #[clap(long)]
+        at: Option<String>,
+
+        /// Also keep each compiled SELECT in this content-addressed store, which compiles of
+        /// other branches can share
+        #[clap(long, value_name = "DIR")]
+        artifact_store: Option<PathBuf>,
+
+        /// Write each model's SQL to target/compiled/ formatted, keeping its header comments
+        #[clap(long)]
+        format: bool,
+
+        /// Print how long each phase took, to see which one dominates
+        #[clap(long)]
+        stats: bool,
+    },
+
+    /// Compile the project several times and fail if any artifact differs between runs
+    VerifyDeterminism {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Number of compilations to compare, run concurrently
+        #[clap(long, default_value_t = 2)]
+        runs: usize,
+    },
+
+    /// Propose ALTER statements migrating a table model's relation to its new columns
+    PlanMigration {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// DuckDB database file holding the deployed relation
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        /// Name or unique id of the model to migrate
+        #[clap(short, long)]
+        select: String,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+
+    /// Explain how one model depends on another model or an external source
+    Why {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Name or unique id of the downstream model
+        from: String,
+
+        /// Upstream model (name or unique id) or external source (schema.table)
+        to: String,
+    },
+
+    /// Trace each column of a model back to the source columns it is computed from
+    Lineage {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Output format (text, dot, json)
+        #[clap(short, long, default_value = "text")]
+        format: String,
+
+        /// Name or unique id of the model
+        model: String,
+    },
+
+    /// Show dependencies, column lineage and lint findings for a single SQL text
+    Analyze {
+        /// SQL file to analyze, or '-' to read from stdin
+        input: String,
+
+        /// Output format (text, json)
+        #[clap(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Run one-off SQL assertions, each a query returning a single boolean
+    Assert {
+        /// DuckDB database file to query
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        /// Assertion queries, e.g.
+        /// "SELECT count(*) = 0 FROM staging.stg_customers WHERE email IS NULL"
+        queries: Vec<String>,
+
+        /// YAML file with named assertions
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+    },
+
+    /// Check the project's sources against the warehouse
+    Source {
+        #[clap(subcommand)]
+        command: SourceCommand,
+    },
+
+    /// Record the warehouse columns of every source, model and snapshot for offline checks
+    SyncSchemas {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// DuckDB database file to introspect
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+
+    /// Suggest what the project is missing, such as tests on key columns
+    Suggest {
+        #[clap(subcommand)]
+        command: SuggestCommand,
+    },
+
+    /// Generate documentation from the project
+    Docs {
+        #[clap(subcommand)]
+        command: DocsCommand,
+    },
+
+    /// Run the steps of a YAML workflow file and track its runs
+    Workflow {
+        #[clap(subcommand)]
+        command: WorkflowCommand,
+    },
+
+    /// Reports on the project as a whole
+    Report {
+        #[clap(subcommand)]
+        command: ReportCommand,
+    },
+
+    /// Show or change settings, with the file, variable or flag each value comes from
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Show help for a command or a topic (templating)
+    Help {
+        /// Command or topic, e.g. "templating" for the functions available in model SQL
+        topic: Option<String>,
+    },
+
+    /// Evaluate a feather_lang expression, or start a REPL without one
+    Expr {
+        /// Expression to evaluate, e.g. "let x = 2; x * 21"
+        expression: Option<String>,
+    },
+
+    /// Scaffold a new project
+    Init {
+        /// Directory to create the project in, e.g. the project name
+        #[clap(default_value = ".")]
+        dir: PathBuf,
+
+        /// Project name; asked at a terminal, else defaults to the directory name
+        #[clap(long)]
+        name: Option<String>,
+
+        /// SQL dialect: duckdb or bigquery; asked at a terminal, else duckdb
+        #[clap(long)]
+        dialect: Option<String>,
+
+        /// Project layout: basic or medallion (bronze/silver/gold layers)
+        #[clap(long, default_value = "basic")]
+        template: String,
+
+        /// Overwrite files that differ from the template, discarding local edits
+        #[clap(long)]
+        force: bool,
+
+        /// Show what would be written without writing anything
+        #[clap(long)]
+        dry_run: bool,
     },
 
     /// Show version information
     Version,
 }
 
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// List every effective setting with where its value comes from
+    List {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Target the settings are shown for (e.g. dev, prod)
+        #[clap(short, long)]
+        target: Option<String>,
+    },
+
+    /// Print one setting, or every setting of a section, e.g. state or schema_isolation
+    Get {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Target the setting is shown for (e.g. dev, prod)
+        #[clap(short, long)]
+        target: Option<String>,
+
+        /// Dotted setting name, e.g. state.lock_timeout_secs
+        key: String,
+    },
+
+    /// Write a setting to featherflow_project.yaml, or to the user-level file with --user
+    Set {
+        /// Path to the SQL model files, used to find the project file
+        #[clap(short, long, required_unless_present = "user")]
+        model_path: Option<PathBuf>,
+
+        /// Write to the user-level file (FF_USER_CONFIG, else ~/.config/featherflow/config.yaml)
+        #[clap(long)]
+        user: bool,
+
+        /// Dotted setting name, e.g. state.lock_timeout_secs
+        key: String,
+
+        /// Value, read as YAML: 30, true, [dev, ci]
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReportCommand {
+    /// List pairs of models with near-duplicate SQL
+    Duplicates {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Minimum similarity, from 0 to 1, for a pair to be reported
+        #[clap(long, default_value_t = commands::report::DEFAULT_DUPLICATE_THRESHOLD)]
+        threshold: f64,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// List each column of a model, source or seed with the downstream models using it
+    ColumnUsage {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Model name or unique id, source (schema.table) or seed name
+        node: String,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// List models nothing reads: no downstream models and no exposures
+    DeadModels {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Text export of queries run against the warehouse; models queried in it are kept
+        #[clap(long)]
+        query_history: Option<PathBuf>,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Evaluate the project's governance policies and report each outcome
+    Policies {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Report each require_tests directory's share of models with tests
+    TestCoverage {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// List each model's owner, team, domain, criticality and Slack channel from its meta
+    Owners {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Flag models whose joins are likely to explode in size, with estimated sizes
+    CostEstimate {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Run results holding row counts (defaults to target/run_results.json)
+        #[clap(long)]
+        run_results: Option<PathBuf>,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SuggestCommand {
+    /// Propose unique/not_null tests for probable key columns and relationships tests for
+    /// columns referencing another model's key, as YAML to paste
+    Tests {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Models to suggest tests for, in the same syntax as ff build --select (default:
+        /// all); repeat to select the union
+        #[clap(short, long)]
+        select: Vec<String>,
+
+        /// Models to leave out, in the same syntax as --select
+        #[clap(long)]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Add the tests to the models' YAML files instead of printing them
+        #[clap(long)]
+        apply: bool,
+
+        /// With --apply, show the changes without writing anything
+        #[clap(long, requires = "apply")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SourceCommand {
+    /// Report how long ago each source table with a loaded_at_field was last loaded, against
+    /// its freshness thresholds
+    Freshness {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// DuckDB database file to query
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        /// Sources to check, in the same syntax as ff build --select, e.g. '+fct_orders'
+        /// for the sources it reads (default: all); repeat to select the union
+        #[clap(short, long)]
+        select: Vec<String>,
+
+        /// Sources to leave out, in the same syntax as --select
+        #[clap(long)]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DocsCommand {
+    /// Write a Mermaid ER diagram of the models in a directory
+    Erd {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Directory whose models are drawn (e.g. models/marts/finance)
+        #[clap(short, long)]
+        dir: PathBuf,
+
+        /// File to write the diagram to (defaults to README.md in the directory)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write a static HTML documentation site with a searchable model index and DAG view
+    Generate {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+
+        /// Directory to write the site to (defaults to target/docs)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkflowCommand {
+    /// Run a workflow file's steps in order, recording the run
+    Start {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Workflow file, e.g. workflows/nightly.yml
+        file: PathBuf,
+
+        /// DuckDB database file the build, test and export steps run against
+        /// (default: the database of the active target in profiles.yml)
+        #[clap(long)]
+        db: Option<PathBuf>,
+
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+
+    /// List recorded workflow runs, newest first
+    List {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Output format (table, csv, json)
+        #[clap(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Show a workflow run and each of its steps
+    Status {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Run id, as printed by start and list
+        id: String,
+    },
+
+    /// Stop a running workflow before its next step
+    Stop {
+        /// Path to the SQL model files
+        #[clap(short, long)]
+        model_path: PathBuf,
+
+        /// Run id, as printed by start and list
+        id: String,
+    },
+}
+
+/// Repeated `--select` or `--exclude` values as one expression selecting their union
+fn union(expressions: &[String]) -> Option<String> {
+    (!expressions.is_empty()).then(|| expressions.join(" "))
+}
+
+/// `--model-path`, else the model directory of the project containing the current directory
+fn default_model_path(model_path: Option<PathBuf>) -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    match commands::project::model_path_or_default(model_path, &cwd) {
+        Ok(model_path) => model_path,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            exit(1);
+        }
+    }
+}
+
+/// `--db` and `--target`, else the database of the active target in profiles.yml and that
+/// target
+fn connection(
+    db: Option<PathBuf>,
+    model_path: &Path,
+    target: Option<String>,
+) -> (PathBuf, Option<String>) {
+    match commands::project::resolve_connection(db, model_path, target) {
+        Ok(connection) => connection,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            exit(1);
+        }
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
+    let preferences = config::user_display_config();
+    display::configure(
+        cli.no_emoji || !preferences.emoji,
+        cli.no_color || !preferences.color,
+        cli.porcelain,
+    );
+    if let Some(output) = &cli.profile_output {
+        if let Err(err) = profiling::enable(output) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+    }
+    let access_mode = if cli.read_only {
+        adapters::AccessMode::ReadOnly
+    } else {
+        adapters::AccessMode::ReadWrite
+    };
 
     match cli.command {
         Command::Parse {
             model_path,
             format,
             output_file,
+            project: ProjectArgs { target, vars },
+            warn_implicit_refs,
+            require_explicit_refs,
+            watch,
+            stats,
+            strict_yaml,
         } => {
+            let model_path = default_model_path(model_path);
+            let implicit_refs = if require_explicit_refs {
+                ImplicitRefPolicy::Error
+            } else if warn_implicit_refs {
+                ImplicitRefPolicy::Warn
+            } else {
+                ImplicitRefPolicy::Allow
+            };
+
             // Run the parse command with validation always enabled
-            if let Err(err) =
-                commands::parse::parse_command(&model_path, &format, true, output_file.as_deref())
-            {
+            if let Err(err) = commands::parse::parse_command(&commands::parse::ParseArgs {
+                model_path: &model_path,
+                format: &format,
+                validate: true,
+                output_file: output_file.as_deref(),
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+                implicit_refs,
+                watch,
+                stats,
+                strict_yaml,
+            }) {
                 eprintln!("Error: {}", err);
-                process::exit(1);
+                exit(1);
             }
         }
-        Command::Validate { model_path, quiet } => {
+        Command::Validate {
+            model_path,
+            quiet,
+            watch,
+            diagnostics_file,
+            format,
+            strict_yaml,
+            check_columns,
+        } => {
+            let model_path = default_model_path(model_path);
             // Show compact ASCII art for validate command
             if !quiet {
                 display::display_parse_welcome();
             }
 
-            // Run the validate command
-            let results = validators::validate_models_directory(&model_path);
-
-            let mut error_count = 0;
-            let mut success_count = 0;
-
-            for result in &results {
-                if result.is_valid {
-                    success_count += 1;
-                    if !quiet {
-                        println!("✅ Valid model structure: {}", result.path.display());
-                    }
-                } else {
-                    error_count += 1;
-                    eprintln!("❌ Invalid model structure: {}", result.path.display());
-                    for error in &result.errors {
-                        eprintln!("   - {}", error);
-                    }
+            if let Err(err) = commands::validate::validate_command(
+                &model_path,
+                quiet,
+                watch,
+                diagnostics_file.as_deref(),
+                &format,
+                commands::validate::Checks {
+                    strict_yaml,
+                    columns: check_columns,
+                },
+            ) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Ls {
+            model_path,
+            select,
+            selector,
+            exclude,
+            project: ProjectArgs { target, vars },
+            format,
+        } => {
+            let (select, exclude) = (union(&select), union(&exclude));
+            let args = commands::ls::LsArgs {
+                model_path: &model_path,
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+                select: select.as_deref(),
+                selector: selector.as_deref(),
+                exclude: exclude.as_deref(),
+                format: &format,
+            };
+            if let Err(err) = commands::ls::ls_command(&args) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Test {
+            model_path,
+            project: ProjectArgs { target, vars },
+            db,
+            select,
+            selector,
+            exclude,
+            compiled_snapshots,
+            update_snapshots,
+        } => {
+            let (select, exclude) = (union(&select), union(&exclude));
+            let args = commands::test::TestArgs {
+                model_path: &model_path,
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+                database: db.as_deref(),
+                select: select.as_deref(),
+                selector: selector.as_deref(),
+                exclude: exclude.as_deref(),
+                compiled_snapshots,
+                update_snapshots,
+                mode: access_mode,
+            };
+            if let Err(err) = commands::test::test_command(&args) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Preview {
+            model_path,
+            model,
+            db,
+            project: ProjectArgs { target, vars },
+            limit,
+            no_cache,
+            cache_ttl,
+            lock_timeout,
+            state,
+            favor_state,
+            format,
+        } => {
+            let (db, target) = connection(db, &model_path, target);
+            let args = commands::preview::PreviewArgs {
+                model_path: &model_path,
+                model: &model,
+                database: &db,
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+                limit,
+                no_cache,
+                cache_ttl_secs: cache_ttl,
+                lock_timeout_secs: lock_timeout,
+                mode: access_mode,
+                state: state.as_deref(),
+                favor_state,
+                format: &format,
+            };
+            if let Err(err) = commands::preview::preview_command(&args) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Clean {
+            model_path,
+            project: ProjectArgs { target, vars },
+            schemas,
+            db,
+        } => {
+            let args = commands::clean::CleanArgs {
+                model_path: &model_path,
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+                schemas,
+                database: db.as_deref(),
+                mode: access_mode,
+            };
+            if let Err(err) = commands::clean::clean_command(&args) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Debug { model_path, target } => {
+            let model_path = default_model_path(model_path);
+            if let Err(err) = commands::debug::debug_command(&model_path, target.as_deref()) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Unlock { model_path } => {
+            if let Err(err) = commands::unlock::unlock_command(&model_path) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Build {
+            model_path,
+            db,
+            select,
+            selector,
+            exclude,
+            project: ProjectArgs { target, vars },
+            resume,
+            stats,
+            fail_stale_sources,
+            skip_stale_subgraphs,
+        } => {
+            let (db, target) = connection(db, &model_path, target);
+            let (select, exclude) = (union(&select), union(&exclude));
+            let args = commands::build::BuildArgs {
+                model_path: &model_path,
+                database: &db,
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+                select: select.as_deref(),
+                selector: selector.as_deref(),
+                exclude: exclude.as_deref(),
+                resume,
+                mode: access_mode,
+                stats,
+                only: None,
+                fail_stale_sources,
+                skip_stale_subgraphs,
+            };
+            if let Err(err) = commands::build::build_command(&args) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Snapshot {
+            model_path,
+            db,
+            select,
+            exclude,
+            project: ProjectArgs { target, vars },
+        } => {
+            let (db, target) = connection(db, &model_path, target);
+            let (select, exclude) = (union(&select), union(&exclude));
+            let args = commands::build::BuildArgs {
+                model_path: &model_path,
+                database: &db,
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+                select: select.as_deref(),
+                selector: None,
+                exclude: exclude.as_deref(),
+                resume: false,
+                mode: access_mode,
+                stats: false,
+                only: Some(sql_engine::nodes::ResourceType::Snapshot),
+                fail_stale_sources: false,
+                skip_stale_subgraphs: false,
+            };
+            if let Err(err) = commands::build::build_command(&args) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Compile {
+            model_path,
+            project: ProjectArgs { target, vars },
+            at,
+            artifact_store,
+            format,
+            stats,
+        } => {
+            if let Err(err) = commands::compile::compile_command(
+                &model_path,
+                target.as_deref(),
+                vars.as_deref(),
+                at.as_deref(),
+                artifact_store.as_deref(),
+                format,
+                stats,
+            ) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::VerifyDeterminism {
+            model_path,
+            project: ProjectArgs { target, vars },
+            runs,
+        } => {
+            if let Err(err) = commands::determinism::verify_determinism_command(
+                &model_path,
+                target.as_deref(),
+                vars.as_deref(),
+                runs,
+            ) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::PlanMigration {
+            model_path,
+            db,
+            select,
+            project: ProjectArgs { target, vars },
+        } => {
+            let (db, target) = connection(db, &model_path, target);
+            if let Err(err) = commands::migration::plan_migration_command(
+                &model_path,
+                &db,
+                &select,
+                target.as_deref(),
+                vars.as_deref(),
+                access_mode,
+            ) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Why {
+            model_path,
+            project: ProjectArgs { target, vars },
+            from,
+            to,
+        } => {
+            if let Err(err) = commands::why::why_command(
+                &model_path,
+                target.as_deref(),
+                vars.as_deref(),
+                &from,
+                &to,
+            ) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Lineage {
+            model_path,
+            project: ProjectArgs { target, vars },
+            format,
+            model,
+        } => {
+            if let Err(err) = commands::lineage::lineage_command(
+                &model_path,
+                target.as_deref(),
+                vars.as_deref(),
+                &model,
+                &format,
+            ) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Analyze { input, format } => {
+            if let Err(err) = commands::analyze::analyze_command(&input, &format) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Assert { db, queries, file } => {
+            let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            let (db, _) = connection(db, &cwd, None);
+            if let Err(err) =
+                commands::assertions::assert_command(&db, &queries, file.as_deref(), access_mode)
+            {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Workflow { command } => {
+            let result = match command {
+                WorkflowCommand::Start {
+                    model_path,
+                    file,
+                    db,
+                    project: ProjectArgs { target, vars },
+                } => {
+                    let (db, target) = connection(db, &model_path, target);
+                    commands::workflow::workflow_start_command(
+                        &commands::workflow::WorkflowStartArgs {
+                            file: &file,
+                            model_path: &model_path,
+                            database: &db,
+                            target: target.as_deref(),
+                            vars: vars.as_deref(),
+                            mode: access_mode,
+                        },
+                    )
+                }
+                WorkflowCommand::List { model_path, format } => {
+                    commands::workflow::workflow_list_command(&model_path, &format)
+                }
+                WorkflowCommand::Status { model_path, id } => {
+                    commands::workflow::workflow_status_command(&model_path, &id)
+                }
+                WorkflowCommand::Stop { model_path, id } => {
+                    commands::workflow::workflow_stop_command(&model_path, &id)
                 }
+            };
+            if let Err(err) = result {
+                eprintln!("Error: {}", err);
+                exit(1);
             }
-
-            if !quiet || error_count > 0 {
-                println!("\nValidation summary:");
-                println!("  Valid models: {}", success_count);
-                println!("  Invalid models: {}", error_count);
-                println!("  Total models checked: {}", results.len());
+        }
+        Command::Source { command } => match command {
+            SourceCommand::Freshness {
+                model_path,
+                db,
+                select,
+                exclude,
+                project: ProjectArgs { target, vars },
+                format,
+            } => {
+                let (db, target) = connection(db, &model_path, target);
+                let (select, exclude) = (union(&select), union(&exclude));
+                let args = commands::source::FreshnessArgs {
+                    model_path: &model_path,
+                    database: &db,
+                    target: target.as_deref(),
+                    vars: vars.as_deref(),
+                    select: select.as_deref(),
+                    exclude: exclude.as_deref(),
+                    format: &format,
+                    mode: access_mode,
+                };
+                if let Err(err) = commands::source::source_freshness_command(&args) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
             }
-
-            if error_count > 0 {
-                process::exit(1);
+        },
+        Command::SyncSchemas {
+            model_path,
+            db,
+            project: ProjectArgs { target, vars },
+        } => {
+            let (db, target) = connection(db, &model_path, target);
+            let args = commands::sync_schemas::SyncSchemasArgs {
+                model_path: &model_path,
+                database: &db,
+                target: target.as_deref(),
+                vars: vars.as_deref(),
+            };
+            if let Err(err) = commands::sync_schemas::sync_schemas_command(&args) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Suggest { command } => match command {
+            SuggestCommand::Tests {
+                model_path,
+                select,
+                exclude,
+                project: ProjectArgs { target, vars },
+                apply,
+                dry_run,
+            } => {
+                let (select, exclude) = (union(&select), union(&exclude));
+                let args = commands::suggest::SuggestTestsArgs {
+                    model_path: &model_path,
+                    target: target.as_deref(),
+                    vars: vars.as_deref(),
+                    select: select.as_deref(),
+                    exclude: exclude.as_deref(),
+                    apply,
+                    options: commands::writer::WriteOptions::from_flags(false, dry_run),
+                };
+                if let Err(err) = commands::suggest::suggest_tests_command(&args) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+        },
+        Command::Docs { command } => match command {
+            DocsCommand::Erd {
+                model_path,
+                dir,
+                output,
+            } => {
+                if let Err(err) =
+                    commands::docs::docs_erd_command(&model_path, &dir, output.as_deref())
+                {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+            DocsCommand::Generate {
+                model_path,
+                project: ProjectArgs { target, vars },
+                output,
+            } => {
+                if let Err(err) = commands::docs::docs_generate_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    output.as_deref(),
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+        },
+        Command::Config { command } => {
+            let result = match command {
+                ConfigCommand::List { model_path, target } => {
+                    commands::config::config_list_command(&model_path, target.as_deref())
+                }
+                ConfigCommand::Get {
+                    model_path,
+                    target,
+                    key,
+                } => commands::config::config_get_command(&model_path, target.as_deref(), &key),
+                ConfigCommand::Set {
+                    model_path,
+                    user,
+                    key,
+                    value,
+                } => {
+                    commands::config::config_set_command(model_path.as_deref(), &key, &value, user)
+                }
+            };
+            if let Err(err) = result {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Report { command } => match command {
+            ReportCommand::Duplicates {
+                model_path,
+                project: ProjectArgs { target, vars },
+                threshold,
+                format,
+            } => {
+                if let Err(err) = commands::report::report_duplicates_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    threshold,
+                    &format,
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+            ReportCommand::ColumnUsage {
+                model_path,
+                project: ProjectArgs { target, vars },
+                node,
+                format,
+            } => {
+                if let Err(err) = commands::report::report_column_usage_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    &node,
+                    &format,
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+            ReportCommand::DeadModels {
+                model_path,
+                project: ProjectArgs { target, vars },
+                query_history,
+                format,
+            } => {
+                if let Err(err) = commands::report::report_dead_models_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    query_history.as_deref(),
+                    &format,
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+            ReportCommand::Policies {
+                model_path,
+                project: ProjectArgs { target, vars },
+                format,
+            } => {
+                if let Err(err) = commands::report::report_policies_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    &format,
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+            ReportCommand::TestCoverage {
+                model_path,
+                project: ProjectArgs { target, vars },
+                format,
+            } => {
+                if let Err(err) = commands::report::report_test_coverage_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    &format,
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+            ReportCommand::Owners {
+                model_path,
+                project: ProjectArgs { target, vars },
+                format,
+            } => {
+                if let Err(err) = commands::report::report_owners_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    &format,
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+            ReportCommand::CostEstimate {
+                model_path,
+                project: ProjectArgs { target, vars },
+                run_results,
+                format,
+            } => {
+                if let Err(err) = commands::report::report_cost_estimate_command(
+                    &model_path,
+                    target.as_deref(),
+                    vars.as_deref(),
+                    run_results.as_deref(),
+                    &format,
+                ) {
+                    eprintln!("Error: {}", err);
+                    exit(1);
+                }
+            }
+        },
+        Command::Help { topic } => {
+            if let Err(err) = commands::help::help_command(topic.as_deref(), Cli::command()) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Expr { expression } => {
+            if let Err(err) = commands::expr::expr_command(expression.as_deref()) {
+                eprintln!("Error: {}", err);
+                exit(1);
+            }
+        }
+        Command::Init {
+            dir,
+            name,
+            dialect,
+            template,
+            force,
+            dry_run,
+        } => {
+            if let Err(err) = commands::init::init_command(
+                &dir,
+                name.as_deref(),
+                dialect.as_deref(),
+                &template,
+                commands::writer::WriteOptions::from_flags(force, dry_run),
+            ) {
+                eprintln!("Error: {}", err);
+                exit(1);
             }
         }
         Command::Version => {
@@ -107,4 +1598,11 @@ fn main() {
             display::display_version();
         }
     }
+    profiling::finish();
+}
+
+/// Exit after writing any recorded profiling spans
+fn exit(code: i32) -> ! {
+    profiling::finish();
+    process::exit(code)
 }
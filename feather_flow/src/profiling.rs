@@ -0,0 +1,185 @@
+//! Timing spans for hot paths, written as a Chrome trace with `--profile-output`
+//!
+//! Spans cover parsing each model file, loading YAML, the phases of building the dependency
+//! graph and every adapter query. They are only recorded when `ff` is built with the
+//! `profiling` feature (`cargo build --release --features profiling`); otherwise `span!`
+//! compiles to nothing. The output opens in `chrome://tracing`, Perfetto or speedscope.
+
+/// Start a span that ends when the returned guard is dropped:
+/// `let _span = profiling::span!("parse", "{}", path.display());`
+#[cfg(feature = "profiling")]
+macro_rules! span {
+    ($category:expr, $($name:tt)+) => {
+        $crate::profiling::Span::enter($category, || format!($($name)+))
+    };
+}
+
+#[cfg(not(feature = "profiling"))]
+macro_rules! span {
+    ($category:expr, $($name:tt)+) => {
+        $crate::profiling::Span
+    };
+}
+
+pub(crate) use span;
+
+#[cfg(not(feature = "profiling"))]
+pub use disabled::*;
+#[cfg(feature = "profiling")]
+pub use enabled::*;
+
+#[cfg(not(feature = "profiling"))]
+mod disabled {
+    use std::path::Path;
+
+    /// Guard returned by `span!`; records nothing in builds without the `profiling` feature
+    pub struct Span;
+
+    pub fn enable(_output: &Path) -> Result<(), String> {
+        Err("--profile-output needs ff built with the 'profiling' feature (cargo build --features profiling)".to_string())
+    }
+
+    pub fn finish() {}
+}
+
+#[cfg(feature = "profiling")]
+mod enabled {
+    use serde::Serialize;
+    use std::cell::Cell;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Mutex, OnceLock};
+    use std::time::Instant;
+
+    /// Output file and clock origin, set once profiling is enabled
+    static OUTPUT: OnceLock<(PathBuf, Instant)> = OnceLock::new();
+    static EVENTS: Mutex<Vec<TraceEvent>> = Mutex::new(Vec::new());
+    static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+    thread_local! {
+        static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// A complete ("X") event in the Chrome trace event format
+    #[derive(Debug, Serialize)]
+    struct TraceEvent {
+        name: String,
+        cat: &'static str,
+        ph: &'static str,
+        /// Microseconds since profiling was enabled
+        ts: u128,
+        dur: u128,
+        pid: u32,
+        tid: u64,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Trace<'a> {
+        trace_events: &'a [TraceEvent],
+        display_time_unit: &'static str,
+    }
+
+    /// Guard returned by `span!`; records the span when dropped
+    pub struct Span {
+        active: Option<(&'static str, String, Instant)>,
+    }
+
+    impl Span {
+        pub fn enter(category: &'static str, name: impl FnOnce() -> String) -> Self {
+            let active = OUTPUT.get().map(|_| (category, name(), Instant::now()));
+            Self { active }
+        }
+    }
+
+    impl Drop for Span {
+        fn drop(&mut self) {
+            let (Some((category, name, start)), Some((_, origin))) =
+                (self.active.take(), OUTPUT.get())
+            else {
+                return;
+            };
+
+            let event = TraceEvent {
+                name,
+                cat: category,
+                ph: "X",
+                ts: start.duration_since(*origin).as_micros(),
+                dur: start.elapsed().as_micros(),
+                pid: std::process::id(),
+                tid: thread_id(),
+            };
+            if let Ok(mut events) = EVENTS.lock() {
+                events.push(event);
+            }
+        }
+    }
+
+    fn thread_id() -> u64 {
+        THREAD_ID.with(|id| {
+            if id.get() == 0 {
+                id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+            }
+            id.get()
+        })
+    }
+
+    /// Record spans from now on and write them to `output` on `finish`
+    pub fn enable(output: &Path) -> Result<(), String> {
+        OUTPUT
+            .set((output.to_path_buf(), Instant::now()))
+            .map_err(|_| "Profiling is already enabled".to_string())
+    }
+
+    /// Write the recorded spans, if profiling was enabled
+    pub fn finish() {
+        let Some((output, _)) = OUTPUT.get() else {
+            return;
+        };
+        let Ok(events) = EVENTS.lock() else {
+            return;
+        };
+
+        let trace = Trace {
+            trace_events: &events,
+            display_time_unit: "ms",
+        };
+        let written = serde_json::to_string(&trace)
+            .map_err(|err| err.to_string())
+            .and_then(|json| fs::write(output, json).map_err(|err| err.to_string()));
+        match written {
+            Ok(()) => eprintln!(
+                "Wrote {} profiling span(s) to {}",
+                events.len(),
+                output.display()
+            ),
+            Err(err) => eprintln!("Warning: failed to write {}: {}", output.display(), err),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "profiling"))]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_finish_writes_chrome_trace() {
+        let temp_dir = tempdir().unwrap();
+        let output = temp_dir.path().join("flame.json");
+        enable(&output).unwrap();
+
+        {
+            let _span = span!("parse", "{}", "orders.sql");
+        }
+        finish();
+
+        let trace: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        let event = &trace["traceEvents"][0];
+        assert_eq!(event["name"], "orders.sql");
+        assert_eq!(event["cat"], "parse");
+        assert_eq!(event["ph"], "X");
+    }
+}
@@ -0,0 +1,115 @@
+//! Flagging nodes that ran much slower than expected
+//!
+//! A model can declare how long it should take:
+//!
+//! ```yaml
+//! models:
+//!   - name: fct_orders
+//!     config:
+//!       expected_runtime: 2m       # seconds (s), minutes (m) or hours (h)
+//! ```
+//!
+//! `ff build` compares each node that succeeds with that expectation or, for nodes without
+//! one, with the average of their last successful builds (see `state::run_history`). A node
+//! taking more than `SLOWDOWN_FACTOR` times as long is an anomaly: it is listed after the
+//! build's summary and recorded in `run_results.json`, so a performance regression shows up
+//! in the build of the SQL change that introduced it. The node's status is unchanged.
+
+use crate::state::run_history::RunHistory;
+
+/// How many times slower than expected a node must run to be flagged
+pub const SLOWDOWN_FACTOR: f64 = 2.0;
+
+/// Least number of seconds a node must run over what was expected to be flagged, so quick
+/// nodes are not flagged for noise
+const MIN_SLOWDOWN_SECS: f64 = 1.0;
+
+/// Seconds in an `expected_runtime` such as `90s`, `2m` or `1.5h`
+pub fn parse_runtime(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    let (number, unit) = text.split_at(text.len() - text.chars().last().map_or(0, char::len_utf8));
+    let scale = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => {
+            return Err(format!(
+                "expected_runtime '{}' needs a unit: s, m or h (e.g. 2m)",
+                text
+            ))
+        }
+    };
+    match number.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok(value * scale),
+        _ => Err(format!(
+            "expected_runtime '{}' is not a positive duration (e.g. 2m)",
+            text
+        )),
+    }
+}
+
+/// Why `secs` is anomalous for the node `unique_id`, if it is. `expected` is the node's
+/// declared runtime; without one, its average in `history` is the baseline.
+pub fn check(
+    unique_id: &str,
+    expected: Option<f64>,
+    history: &RunHistory,
+    secs: f64,
+) -> Option<String> {
+    let (baseline, basis) = match expected {
+        Some(expected) => (expected, "expected_runtime".to_string()),
+        None => {
+            let (average, runs) = history.average(unique_id)?;
+            (average, format!("average of the last {} builds", runs))
+        }
+    };
+    if secs > baseline * SLOWDOWN_FACTOR && secs - baseline >= MIN_SLOWDOWN_SECS {
+        Some(format!(
+            "took {:.1}s, {:.1}x the {:.1}s {}",
+            secs,
+            secs / baseline,
+            baseline,
+            basis
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_runtime() {
+        assert_eq!(parse_runtime("90s"), Ok(90.0));
+        assert_eq!(parse_runtime("2m"), Ok(120.0));
+        assert_eq!(parse_runtime(" 1.5h "), Ok(5400.0));
+        assert!(parse_runtime("120").unwrap_err().contains("needs a unit"));
+        assert!(parse_runtime("0m").unwrap_err().contains("not a positive"));
+        assert!(parse_runtime("").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let mut history = RunHistory::default();
+        assert_eq!(check("model.a", Some(60.0), &history, 100.0), None);
+        assert_eq!(
+            check("model.a", Some(60.0), &history, 150.0),
+            Some("took 150.0s, 2.5x the 60.0s expected_runtime".to_string())
+        );
+        assert_eq!(check("model.a", None, &history, 150.0), None);
+
+        for secs in [10.0, 12.0] {
+            history.record("model.a", secs);
+        }
+        assert_eq!(check("model.a", None, &history, 50.0), None);
+        history.record("model.a", 8.0);
+        assert_eq!(
+            check("model.a", None, &history, 50.0),
+            Some("took 50.0s, 5.0x the 10.0s average of the last 3 builds".to_string())
+        );
+        // Twice as slow, but by less than a second
+        assert_eq!(check("model.b", Some(0.2), &history, 0.9), None);
+    }
+}
@@ -0,0 +1,135 @@
+//! Waiting for the `await:` signals of sources before building what reads them
+//!
+//! See `config::awaits` for the configuration. URLs are fetched with the `curl` command-line
+//! client, as the warehouse is queried through the `duckdb` one, so `ff` does not link an
+//! HTTP library.
+
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Instant;
+
+use super::interrupt;
+use crate::adapters::Adapter;
+use crate::config::awaits::Signal;
+use crate::display::status;
+use crate::sql_engine::nodes::Source;
+
+/// Longest a single HTTP check may take, in seconds
+const HTTP_MAX_TIME_SECS: &str = "30";
+
+/// Wait until the signal `source` awaits holds, checking it every poll interval. Fails with
+/// the last reason it did not hold once the timeout passes or the build is interrupted.
+/// Sources without an `await:` are ready at once.
+pub fn wait_for(adapter: &dyn Adapter, root: &Path, source: &Source) -> Result<(), String> {
+    let Some(config) = &source.awaited else {
+        return Ok(());
+    };
+    let signal = config.signal()?;
+    let timeout = config.timeout();
+    let started = Instant::now();
+    loop {
+        let reason = match check(adapter, root, signal) {
+            Ok(()) => return Ok(()),
+            Err(reason) => reason,
+        };
+        let waited = started.elapsed();
+        if waited >= timeout || interrupt::requested() {
+            return Err(format!(
+                "gave up waiting for {} after {}s: {}",
+                signal,
+                waited.as_secs(),
+                reason
+            ));
+        }
+        let pause = config.poll_interval().min(timeout - waited);
+        status!(
+            "Waiting for {} of {}: {}; checking again in {}s",
+            signal,
+            source.unique_id,
+            reason,
+            pause.as_secs().max(1)
+        );
+        thread::sleep(pause);
+    }
+}
+
+/// Whether `signal` holds now, else why not
+fn check(adapter: &dyn Adapter, root: &Path, signal: Signal) -> Result<(), String> {
+    match signal {
+        Signal::File(path) => {
+            if root.join(path).exists() {
+                Ok(())
+            } else {
+                Err(format!("{} does not exist", path))
+            }
+        }
+        Signal::Sql(sql) => adapter
+            .query(sql)
+            .map_err(|err| format!("{:#}", err))
+            .and_then(|result| result.truth()),
+        Signal::Http(url) => {
+            let output = Command::new("curl")
+                .args(["--silent", "--show-error", "--fail", "--location"])
+                .args(["--max-time", HTTP_MAX_TIME_SECS, url])
+                .output()
+                .map_err(|err| format!("cannot run curl: {}", err))?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::QueryResult;
+    use crate::config::awaits::Await;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Answers every query with one value
+    struct Value(&'static str);
+
+    impl Adapter for Value {
+        fn query(&self, _sql: &str) -> anyhow::Result<QueryResult> {
+            Ok(QueryResult {
+                columns: vec!["ready".to_string()],
+                rows: vec![vec![self.0.to_string()]],
+            })
+        }
+    }
+
+    #[test]
+    fn test_wait_for() {
+        let dir = tempdir().unwrap();
+        let mut source = Source::new("raw.orders");
+        assert_eq!(wait_for(&Value("false"), dir.path(), &source), Ok(()));
+
+        source.awaited = Some(Await {
+            file: Some("landing/_SUCCESS".to_string()),
+            timeout_secs: Some(0),
+            ..Await::default()
+        });
+        assert_eq!(
+            wait_for(&Value("true"), dir.path(), &source),
+            Err("gave up waiting for file landing/_SUCCESS after 0s: landing/_SUCCESS does not exist".to_string())
+        );
+        fs::create_dir(dir.path().join("landing")).unwrap();
+        fs::write(dir.path().join("landing/_SUCCESS"), "").unwrap();
+        assert_eq!(wait_for(&Value("true"), dir.path(), &source), Ok(()));
+
+        source.awaited = Some(Await {
+            sql: Some("SELECT loaded FROM raw.loads".to_string()),
+            timeout_secs: Some(0),
+            ..Await::default()
+        });
+        assert_eq!(wait_for(&Value("t"), dir.path(), &source), Ok(()));
+        assert!(wait_for(&Value("false"), dir.path(), &source)
+            .unwrap_err()
+            .ends_with("returned false"));
+    }
+}
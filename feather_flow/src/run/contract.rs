@@ -0,0 +1,124 @@
+//! Model contracts: the columns declared in a model's YAML against those it built
+//!
+//! `ff build` checks the contract of models whose criticality tier sets `enforce_contract`.
+//! Column names compare case-insensitively; types only where declared, after normalizing
+//! spellings such as `int` and `INTEGER` or `decimal(18, 2)` and `DECIMAL(18,2)`.
+
+use std::collections::HashMap;
+
+use crate::sql_engine::sql_model::ColumnInfo;
+
+/// Differences between the declared columns and the built `(name, type)` pairs
+pub fn violations(
+    declared: &HashMap<String, ColumnInfo>,
+    built: &[(String, String)],
+) -> Vec<String> {
+    let built: HashMap<String, &str> = built
+        .iter()
+        .map(|(name, data_type)| (name.to_lowercase(), data_type.as_str()))
+        .collect();
+
+    let mut problems = Vec::new();
+    let mut names: Vec<&ColumnInfo> = declared.values().collect();
+    names.sort_by(|a, b| a.name.cmp(&b.name));
+    for column in names {
+        match built.get(&column.name.to_lowercase()) {
+            None => problems.push(format!("declared column '{}' was not built", column.name)),
+            Some(actual) => {
+                if let Some(expected) = &column.data_type {
+                    if normalize_type(expected) != normalize_type(actual) {
+                        problems.push(format!(
+                            "column '{}' is {}, declared as {}",
+                            column.name, actual, expected
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    let declared: Vec<String> = declared
+        .values()
+        .map(|column| column.name.to_lowercase())
+        .collect();
+    let mut extra: Vec<&String> = built
+        .keys()
+        .filter(|name| !declared.contains(name))
+        .collect();
+    extra.sort();
+    problems.extend(
+        extra
+            .into_iter()
+            .map(|name| format!("column '{}' is not declared", name)),
+    );
+    problems
+}
+
+/// Upper-cased type without spaces, with common synonyms mapped to one spelling
+pub fn normalize_type(data_type: &str) -> String {
+    let compact: String = data_type
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase();
+    let normalized = match compact.as_str() {
+        "TEXT" | "STRING" => "VARCHAR",
+        "INT" | "INT4" | "INT32" => "INTEGER",
+        "INT8" | "INT64" | "LONG" => "BIGINT",
+        "BOOL" => "BOOLEAN",
+        "DOUBLE PRECISION" | "FLOAT8" => "DOUBLE",
+        "FLOAT4" | "REAL" => "FLOAT",
+        other => other,
+    };
+    normalized.replace(' ', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn column(name: &str, data_type: Option<&str>) -> (String, ColumnInfo) {
+        (
+            name.to_string(),
+            ColumnInfo {
+                name: name.to_string(),
+                description: None,
+                data_type: data_type.map(str::to_string),
+                tests: Vec::new(),
+                test_args: BTreeMap::new(),
+                meta: HashMap::new(),
+                source_columns: Vec::new(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_violations() {
+        let declared: HashMap<String, ColumnInfo> = [
+            column("id", Some("int")),
+            column("amount", Some("decimal(18, 2)")),
+            column("Name", None),
+            column("status", Some("text")),
+            column("created_at", None),
+        ]
+        .into_iter()
+        .collect();
+        let built = [
+            ("ID".to_string(), "INTEGER".to_string()),
+            ("amount".to_string(), "DECIMAL(18,2)".to_string()),
+            ("name".to_string(), "VARCHAR".to_string()),
+            ("status".to_string(), "INTEGER".to_string()),
+            ("loaded_at".to_string(), "TIMESTAMP".to_string()),
+        ];
+
+        assert_eq!(
+            violations(&declared, &built),
+            vec![
+                "declared column 'created_at' was not built",
+                "column 'status' is INTEGER, declared as text",
+                "column 'loaded_at' is not declared",
+            ]
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Column tests run against the warehouse, shared by `ff build` and `ff test`
+//!
+//! Each test is a query counting the rows of its model that fail it; any failing row fails
+//! the test.
+
+use anyhow::{anyhow, Result};
+
+use crate::adapters::Adapter;
+use crate::sql_engine::nodes::ColumnTest;
+use crate::sql_engine::sql_model::SqlModelCollection;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    Passed,
+    /// This many rows fail the test
+    Failed(u64),
+    /// The test has no query, e.g. an unknown test or one missing its arguments
+    Unsupported(String),
+}
+
+/// Run `test` against the relations its model and `relationships` target are built into
+pub fn run_column_test(
+    adapter: &dyn Adapter,
+    models: &SqlModelCollection,
+    test: &ColumnTest,
+) -> Result<TestOutcome> {
+    let relation = models
+        .relation_of(&test.model_id)
+        .ok_or_else(|| anyhow!("Model {} is not loaded", test.model_id))?;
+    let to_relation = test.to_id.as_deref().and_then(|id| models.relation_of(id));
+    let Some(sql) = test.sql(&relation, to_relation.as_deref()) else {
+        return Ok(TestOutcome::Unsupported(unsupported_reason(test)));
+    };
+
+    let result = adapter.query(&sql)?;
+    let failures = result
+        .rows
+        .first()
+        .and_then(|row| row.first())
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(0);
+    Ok(match failures {
+        0 => TestOutcome::Passed,
+        failures => TestOutcome::Failed(failures),
+    })
+}
+
+fn unsupported_reason(test: &ColumnTest) -> String {
+    match test.test.as_str() {
+        "accepted_values" => "accepted_values needs a non-empty `values:` list".to_string(),
+        "relationships" if test.to_id.is_none() => format!(
+            "relationships needs `to:` naming a model or seed, got {}",
+            test.to_name().unwrap_or("nothing")
+        ),
+        "relationships" => "relationships needs a `field:`".to_string(),
+        other => format!("test '{}' cannot run against the warehouse", other),
+    }
+}
@@ -0,0 +1,219 @@
+//! Debug bundles of models that failed in `ff build`
+//!
+//! Each failed model gets `target/failures/<model>/` with what is needed to reproduce the
+//! failure without rerunning the build: `compiled.sql`, the adapter's `error.txt`, the
+//! columns of every relation it reads in `upstream.json`, and the run's last lines up to
+//! the failure in `log.txt`. A model's bundle is removed when it next builds successfully.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::adapters::{self, Adapter};
+use crate::sql_engine::nodes::Node;
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+use crate::sql_engine::template::DEFAULT_SCHEMA;
+
+/// Directory, relative to the project root, holding one bundle per failed model
+pub const FAILURES_DIR: &str = "target/failures";
+
+/// Lines of the run's log kept in a bundle
+pub const LOG_LINES: usize = 50;
+
+/// Columns of one relation the failed model reads, or why they could not be read
+#[derive(Debug, Default, Serialize)]
+struct UpstreamSchema {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    columns: Vec<UpstreamColumn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct UpstreamColumn {
+    name: String,
+    data_type: String,
+}
+
+/// Bundle directory of `model` under the project root
+pub fn bundle_dir(root: &Path, model: &SqlModel) -> PathBuf {
+    root.join(FAILURES_DIR).join(&model.name)
+}
+
+/// Write the bundle of `model`, replacing an earlier one, and return its directory
+pub fn write_bundle(
+    root: &Path,
+    adapter: &dyn Adapter,
+    models: &SqlModelCollection,
+    model: &SqlModel,
+    error: &str,
+    log: &[String],
+) -> Result<PathBuf> {
+    let dir = bundle_dir(root, model);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    fs::write(dir.join("compiled.sql"), compiled)?;
+    fs::write(dir.join("error.txt"), format!("{}\n", error))?;
+    let upstream = upstream_schemas(adapter, models, model);
+    fs::write(
+        dir.join("upstream.json"),
+        serde_json::to_string_pretty(&upstream)?,
+    )?;
+    fs::write(dir.join("log.txt"), log.join("\n") + "\n")?;
+    Ok(dir)
+}
+
+/// Remove the bundle of `model` after it built successfully
+pub fn clear_bundle(root: &Path, model: &SqlModel) -> Result<()> {
+    let dir = bundle_dir(root, model);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(())
+}
+
+/// Columns of the upstream models, seeds and external relations `model` reads, keyed by
+/// relation
+fn upstream_schemas(
+    adapter: &dyn Adapter,
+    models: &SqlModelCollection,
+    model: &SqlModel,
+) -> BTreeMap<String, UpstreamSchema> {
+    let mut relations: Vec<(Option<String>, String, String)> = Vec::new();
+    for id in &model.upstream_models {
+        match models.get_node(id) {
+            Some(Node::Model(upstream) | Node::Snapshot(upstream)) => relations.push((
+                upstream.database.clone(),
+                upstream
+                    .schema
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_SCHEMA.to_string()),
+                upstream.relation_name().to_string(),
+            )),
+            Some(Node::Seed(seed)) => {
+                relations.push((None, seed.schema.clone(), seed.name.clone()))
+            }
+            _ => {}
+        }
+    }
+    for source in model.get_external_sources() {
+        let parts: Vec<&str> = source.split('.').collect();
+        match parts.as_slice() {
+            [database, schema, name] => relations.push((
+                Some(database.to_string()),
+                schema.to_string(),
+                name.to_string(),
+            )),
+            [schema, name] => relations.push((None, schema.to_string(), name.to_string())),
+            _ => {}
+        }
+    }
+
+    relations
+        .into_iter()
+        .map(|(database, schema, name)| {
+            let relation = match &database {
+                Some(database) => format!("{}.{}.{}", database, schema, name),
+                None => format!("{}.{}", schema, name),
+            };
+            let schema =
+                match adapters::relation_columns(adapter, database.as_deref(), &schema, &name) {
+                    Ok(columns) if columns.is_empty() => UpstreamSchema {
+                        error: Some("relation does not exist".to_string()),
+                        ..UpstreamSchema::default()
+                    },
+                    Ok(columns) => UpstreamSchema {
+                        columns: columns
+                            .into_iter()
+                            .map(|(name, data_type)| UpstreamColumn { name, data_type })
+                            .collect(),
+                        error: None,
+                    },
+                    Err(err) => UpstreamSchema {
+                        error: Some(format!("{:#}", err)),
+                        ..UpstreamSchema::default()
+                    },
+                };
+            (relation, schema)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::QueryResult;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use sqlparser::dialect::DuckDbDialect;
+    use tempfile::tempdir;
+
+    /// Every relation has one `id INTEGER` column
+    struct OneColumn;
+
+    impl Adapter for OneColumn {
+        fn query(&self, _sql: &str) -> Result<QueryResult> {
+            Ok(QueryResult {
+                columns: vec!["column_name".to_string(), "data_type".to_string()],
+                rows: vec![vec!["id".to_string(), "INTEGER".to_string()]],
+            })
+        }
+    }
+
+    #[test]
+    fn test_bundle_is_written_and_cleared() {
+        let dir = tempdir().unwrap();
+        let model_dir = dir.path().join("models/orders");
+        fs::create_dir_all(&model_dir).unwrap();
+        let file = model_dir.join("orders.sql");
+        fs::write(&file, "SELECT id FROM raw.orders").unwrap();
+        let mut model = SqlModel::from_path(
+            &file,
+            &dir.path().join("models"),
+            "duckdb",
+            &DuckDbDialect {},
+            &Sha256Hash,
+        )
+        .unwrap();
+        model.external_sources.insert("raw.orders".to_string());
+
+        let log = vec!["OK model model.customers.customers".to_string()];
+        let bundle = write_bundle(
+            dir.path(),
+            &OneColumn,
+            &SqlModelCollection::new(),
+            &model,
+            "Binder Error: column x not found",
+            &log,
+        )
+        .unwrap();
+
+        assert_eq!(bundle, dir.path().join("target/failures/orders"));
+        assert_eq!(
+            fs::read_to_string(bundle.join("compiled.sql")).unwrap(),
+            "SELECT id FROM raw.orders"
+        );
+        assert_eq!(
+            fs::read_to_string(bundle.join("error.txt")).unwrap(),
+            "Binder Error: column x not found\n"
+        );
+        let upstream: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(bundle.join("upstream.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            upstream["raw.orders"]["columns"][0]["data_type"],
+            serde_json::json!("INTEGER")
+        );
+        assert!(fs::read_to_string(bundle.join("log.txt"))
+            .unwrap()
+            .contains("model.customers.customers"));
+
+        clear_bundle(dir.path(), &model).unwrap();
+        assert!(!bundle.exists());
+    }
+}
@@ -0,0 +1,218 @@
+//! Source freshness checks against the warehouse, persisted as `sources.json`
+//!
+//! Every source table with a `loaded_at_field` is queried for its latest value and how long
+//! ago that was, which its `freshness:` thresholds turn into a status (see
+//! `config::freshness`). Tables without thresholds pass whatever their age, as do models that
+//! declare a `loaded_at_field`, which are checked the same way to show when they were last
+//! updated.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::adapters::Adapter;
+use crate::config::freshness::Freshness;
+use crate::config::freshness::{FreshnessStatus, Threshold};
+use crate::sql_engine::nodes::Source;
+use crate::sql_engine::sql_model::SqlModel;
+
+/// Default location of the freshness results, relative to the project root
+pub const SOURCES_FILE: &str = "target/sources.json";
+
+/// Outcome of one source table's or model's check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceFreshness {
+    pub unique_id: String,
+    pub relation: String,
+    pub status: FreshnessStatus,
+    /// Latest `loaded_at_field` value as the warehouse prints it; None for an empty table
+    pub max_loaded_at: Option<String>,
+    /// Seconds from `max_loaded_at` to when the check ran
+    pub age_seconds: Option<i64>,
+    pub warn_after: Option<Threshold>,
+    pub error_after: Option<Threshold>,
+    pub checked_at: DateTime<Utc>,
+    /// Why the check failed or the table is stale
+    pub message: Option<String>,
+}
+
+/// All results of one invocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreshnessResults {
+    pub generated_at: DateTime<Utc>,
+    pub results: Vec<SourceFreshness>,
+}
+
+impl FreshnessResults {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write source freshness: {}", path.display()))
+    }
+}
+
+/// Query returning the latest `loaded_at_field` value of `relation` as text and its age in
+/// seconds
+pub fn query(relation: &str, loaded_at_field: &str) -> String {
+    format!(
+        "SELECT CAST(max({field}) AS VARCHAR) AS max_loaded_at, \
+         CAST(epoch(current_timestamp) - epoch(max({field})) AS BIGINT) AS age_seconds \
+         FROM {}",
+        relation,
+        field = loaded_at_field
+    )
+}
+
+/// Check `source` against the warehouse; None when it declares no `loaded_at_field`
+pub fn check_source(adapter: &dyn Adapter, source: &Source) -> Option<SourceFreshness> {
+    let field = source.loaded_at_field.as_deref()?;
+    Some(check(
+        adapter,
+        &source.unique_id,
+        &source.relation,
+        field,
+        source.freshness.clone().unwrap_or_default(),
+    ))
+}
+
+/// Check when `model` was last loaded; None when it declares no `loaded_at_field`. Models
+/// have no thresholds, so the check only fails when the query does.
+pub fn check_model(adapter: &dyn Adapter, model: &SqlModel) -> Option<SourceFreshness> {
+    let field = model.loaded_at_field.as_deref()?;
+    Some(check(
+        adapter,
+        &model.unique_id,
+        &model.qualified_relation(),
+        field,
+        Freshness::default(),
+    ))
+}
+
+fn check(
+    adapter: &dyn Adapter,
+    unique_id: &str,
+    relation: &str,
+    loaded_at_field: &str,
+    freshness: Freshness,
+) -> SourceFreshness {
+    let sql = query(relation, loaded_at_field);
+    let mut result = SourceFreshness {
+        unique_id: unique_id.to_string(),
+        relation: relation.to_string(),
+        status: FreshnessStatus::Pass,
+        max_loaded_at: None,
+        age_seconds: None,
+        warn_after: freshness.warn_after.clone(),
+        error_after: freshness.error_after.clone(),
+        checked_at: Utc::now(),
+        message: None,
+    };
+
+    let row = match adapter.query(&sql) {
+        Ok(query) => query.rows.into_iter().next().unwrap_or_default(),
+        Err(err) => {
+            result.status = FreshnessStatus::RuntimeError;
+            result.message = Some(format!("{:#}", err));
+            return result;
+        }
+    };
+    let mut values = row
+        .into_iter()
+        .map(|value| Some(value).filter(|v| !v.is_empty()));
+    result.max_loaded_at = values.next().flatten();
+    result.age_seconds = values.next().flatten().and_then(|age| age.parse().ok());
+
+    match freshness.status(result.age_seconds) {
+        Ok(status) => {
+            result.status = status;
+            result.message = match (status, &result.max_loaded_at) {
+                (FreshnessStatus::Pass, _) => None,
+                (_, None) => Some("no rows have a loaded_at_field value".to_string()),
+                (FreshnessStatus::Warn, Some(_)) => freshness
+                    .warn_after
+                    .as_ref()
+                    .map(|threshold| format!("last loaded more than {} ago", threshold)),
+                (_, Some(_)) => freshness
+                    .error_after
+                    .as_ref()
+                    .map(|threshold| format!("last loaded more than {} ago", threshold)),
+            };
+        }
+        Err(err) => {
+            result.status = FreshnessStatus::RuntimeError;
+            result.message = Some(err);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::QueryResult;
+
+    /// Answers every query with one row
+    struct Row(Vec<&'static str>);
+
+    impl Adapter for Row {
+        fn query(&self, _sql: &str) -> Result<QueryResult> {
+            Ok(QueryResult {
+                columns: vec!["max_loaded_at".to_string(), "age_seconds".to_string()],
+                rows: vec![self.0.iter().map(|value| value.to_string()).collect()],
+            })
+        }
+    }
+
+    #[test]
+    fn test_check_source() {
+        let mut source = Source::new("raw.orders");
+        assert_eq!(check_source(&Row(vec![]), &source), None);
+
+        source.loaded_at_field = Some("_loaded_at".to_string());
+        source.freshness = Some(Freshness {
+            warn_after: Some(Threshold {
+                count: 1,
+                period: "hour".to_string(),
+            }),
+            error_after: Some(Threshold {
+                count: 1,
+                period: "day".to_string(),
+            }),
+        });
+
+        let fresh = check_source(&Row(vec!["2024-01-01 10:00:00", "60"]), &source).unwrap();
+        assert_eq!(fresh.status, FreshnessStatus::Pass);
+        assert_eq!(fresh.max_loaded_at.as_deref(), Some("2024-01-01 10:00:00"));
+        assert_eq!(fresh.age_seconds, Some(60));
+
+        let stale = check_source(&Row(vec!["2024-01-01 10:00:00", "7200"]), &source).unwrap();
+        assert_eq!(stale.status, FreshnessStatus::Warn);
+        assert_eq!(
+            stale.message.as_deref(),
+            Some("last loaded more than 1 hour ago")
+        );
+
+        let empty = check_source(&Row(vec!["", ""]), &source).unwrap();
+        assert_eq!(empty.status, FreshnessStatus::Error);
+        assert_eq!(
+            empty.message.as_deref(),
+            Some("no rows have a loaded_at_field value")
+        );
+    }
+
+    #[test]
+    fn test_query() {
+        assert_eq!(
+            query("raw.orders", "_loaded_at"),
+            "SELECT CAST(max(_loaded_at) AS VARCHAR) AS max_loaded_at, \
+             CAST(epoch(current_timestamp) - epoch(max(_loaded_at)) AS BIGINT) AS age_seconds \
+             FROM raw.orders"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Graceful shutdown on Ctrl-C or SIGTERM
+//!
+//! The first signal only sets a flag: `ff build` finishes the node it is running, skips the
+//! rest and records its progress for `ff build --resume`. A second signal exits at once.
+
+use colored::Colorize;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static INSTALL: Once = Once::new();
+
+/// Exit status of a process ended by SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Install the signal handler; later calls do nothing
+pub fn install() {
+    INSTALL.call_once(|| {
+        let installed = ctrlc::set_handler(|| {
+            if REQUESTED.swap(true, Ordering::SeqCst) {
+                process::exit(INTERRUPTED_EXIT_CODE);
+            }
+            eprintln!("\nInterrupted: finishing the current node; interrupt again to stop now");
+        });
+        if let Err(err) = installed {
+            eprintln!(
+                "{} Cannot handle interrupts gracefully: {}",
+                "Warning:".yellow(),
+                err
+            );
+        }
+    });
+}
+
+/// Whether an interrupt has been received
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
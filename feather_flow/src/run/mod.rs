@@ -0,0 +1,74 @@
+//! Model execution state shared by run-time reporting
+//!
+//! `ff build` plans and executes the project; these types describe a run's progress so
+//! reporting (live DAG snapshots, `run_results.json`, timelines) can be shared with it.
+
+pub mod anomalies;
+pub mod awaits;
+pub mod contract;
+pub mod data_tests;
+pub mod failures;
+pub mod freshness;
+pub mod interrupt;
+pub mod plan;
+pub mod results;
+pub mod selection;
+pub mod snapshot;
+pub mod timeline;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Execution status of a single model within a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelRunStatus {
+    Pending,
+    Running,
+    Success,
+    Failed,
+    /// Failed, but its criticality tier only warns; nodes downstream still run
+    Warned,
+    Skipped,
+}
+
+impl ModelRunStatus {
+    /// All statuses in the order they are reported
+    pub const ALL: [ModelRunStatus; 6] = [
+        ModelRunStatus::Pending,
+        ModelRunStatus::Running,
+        ModelRunStatus::Success,
+        ModelRunStatus::Failed,
+        ModelRunStatus::Warned,
+        ModelRunStatus::Skipped,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelRunStatus::Pending => "pending",
+            ModelRunStatus::Running => "running",
+            ModelRunStatus::Success => "success",
+            ModelRunStatus::Failed => "failed",
+            ModelRunStatus::Warned => "warned",
+            ModelRunStatus::Skipped => "skipped",
+        }
+    }
+
+    /// Fill color used when rendering the status in DOT/HTML output
+    pub fn color(&self) -> &'static str {
+        match self {
+            ModelRunStatus::Pending => "#e0e0e0",
+            ModelRunStatus::Running => "#fff176",
+            ModelRunStatus::Success => "#81c784",
+            ModelRunStatus::Failed => "#e57373",
+            ModelRunStatus::Warned => "#ffb74d",
+            ModelRunStatus::Skipped => "#b0bec5",
+        }
+    }
+}
+
+impl fmt::Display for ModelRunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
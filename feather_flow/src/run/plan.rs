@@ -0,0 +1,245 @@
+//! The graph `ff build` executes, over every node of the collection
+//!
+//! Sources and exposures take part in selection (`+dashboard` selects what an exposure
+//! reads) but have nothing to run. Each test is ordered directly after the node it checks,
+//! or after the last of them for tests such as `relationships` that read two.
+
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::sql_engine::nodes::{Node, ResourceType};
+use crate::sql_engine::sql_model::SqlModelCollection;
+
+#[derive(Debug)]
+pub struct PlanNode<'a> {
+    pub node: &'a Node,
+    pub depends_on: BTreeSet<String>,
+}
+
+impl PlanNode<'_> {
+    pub fn unique_id(&self) -> &str {
+        self.node.unique_id()
+    }
+
+    pub fn resource_type(&self) -> ResourceType {
+        self.node.resource_type()
+    }
+
+    /// Whether `ff build` has anything to run for the node
+    pub fn is_executable(&self) -> bool {
+        !matches!(
+            self.resource_type(),
+            ResourceType::Source | ResourceType::Exposure
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BuildPlan<'a> {
+    nodes: BTreeMap<String, PlanNode<'a>>,
+}
+
+impl<'a> BuildPlan<'a> {
+    pub fn new(collection: &'a SqlModelCollection) -> Self {
+        let nodes = collection
+            .nodes()
+            .map(|node| {
+                let plan_node = PlanNode {
+                    node,
+                    depends_on: collection.node_dependencies(node.unique_id()),
+                };
+                (node.unique_id().to_string(), plan_node)
+            })
+            .collect();
+
+        Self { nodes }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &PlanNode<'a>> {
+        self.nodes.values()
+    }
+
+    pub fn get(&self, unique_id: &str) -> Option<&PlanNode<'a>> {
+        self.nodes.get(unique_id)
+    }
+
+    /// Every node `unique_id` depends on, directly or indirectly
+    pub fn ancestors(&self, unique_id: &str) -> BTreeSet<String> {
+        self.walk(unique_id, |node| node.depends_on.iter().cloned().collect())
+    }
+
+    /// Every node depending on `unique_id`, directly or indirectly
+    pub fn descendants(&self, unique_id: &str) -> BTreeSet<String> {
+        self.walk(unique_id, |node| self.children(node.unique_id()))
+    }
+
+    fn children(&self, unique_id: &str) -> Vec<String> {
+        self.nodes
+            .values()
+            .filter(|node| node.depends_on.contains(unique_id))
+            .map(|node| node.unique_id().to_string())
+            .collect()
+    }
+
+    fn walk(&self, start: &str, next: impl Fn(&PlanNode) -> Vec<String>) -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![start.to_string()];
+
+        while let Some(id) = pending.pop() {
+            let Some(node) = self.nodes.get(&id) else {
+                continue;
+            };
+            for related in next(node) {
+                if seen.insert(related.clone()) {
+                    pending.push(related);
+                }
+            }
+        }
+        seen
+    }
+
+    /// The nodes in `selected`, dependencies first and each test directly after the last node
+    /// it reads. Ties are broken by unique id so the order is stable.
+    pub fn order(&self, selected: &BTreeSet<String>) -> Result<Vec<&PlanNode<'a>>> {
+        let is_test = |node: &PlanNode| node.resource_type() == ResourceType::Test;
+
+        let known_dependencies = |node: &PlanNode| {
+            node.depends_on
+                .iter()
+                .filter(|dep| self.nodes.contains_key(*dep))
+                .count()
+        };
+        let mut remaining: BTreeMap<&str, usize> = self
+            .nodes
+            .values()
+            .filter(|node| !is_test(node))
+            .map(|node| (node.unique_id(), known_dependencies(node)))
+            .collect();
+        // A test with several dependencies, e.g. `relationships`, waits for the last of them
+        let mut waiting_tests: BTreeMap<&str, usize> = self
+            .nodes
+            .values()
+            .filter(|node| is_test(node))
+            .map(|node| (node.unique_id(), known_dependencies(node)))
+            .collect();
+        let mut ready: BTreeSet<&str> = remaining
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut ordered = Vec::with_capacity(selected.len());
+        while let Some(id) = ready.pop_first() {
+            remaining.remove(id);
+
+            if selected.contains(id) {
+                ordered.push(&self.nodes[id]);
+            }
+            for dependent in self.nodes.values() {
+                if !dependent.depends_on.contains(id) {
+                    continue;
+                }
+                if is_test(dependent) {
+                    let waiting = waiting_tests.entry(dependent.unique_id()).or_default();
+                    *waiting = waiting.saturating_sub(1);
+                    if *waiting == 0 && selected.contains(dependent.unique_id()) {
+                        ordered.push(dependent);
+                    }
+                    continue;
+                }
+                if let Some(count) = remaining.get_mut(dependent.unique_id()) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.insert(dependent.unique_id());
+                    }
+                }
+            }
+        }
+
+        if !remaining.is_empty() {
+            let cycle: Vec<&str> = remaining.keys().copied().collect();
+            bail!(
+                "Cannot order the build: dependency cycle among {}",
+                cycle.join(", ")
+            );
+        }
+        Ok(ordered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::fixtures::write_model;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_order_runs_tests_right_after_their_model() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let seeds_dir = root.join("seeds");
+        fs::create_dir(&seeds_dir).unwrap();
+        fs::write(seeds_dir.join("countries.csv"), "code\nNL\n").unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(write_model(
+            root,
+            "a_customers",
+            "SELECT id, country FROM public.countries",
+            Some("version: 2\nmodels:\n  - name: a_customers\n    columns:\n      - name: id\n        tests: [not_null]\n"),
+        ));
+        collection.add_model(write_model(root, "b_orders", "SELECT 1 AS id", None));
+        collection.add_model(write_model(
+            root,
+            "z_history",
+            "SELECT * FROM public.a_customers JOIN public.b_orders USING (id)",
+            Some("version: 2\nmodels:\n  - name: z_history\n    config:\n      materialized: snapshot\n"),
+        ));
+        collection.load_seeds(&seeds_dir).unwrap();
+        collection.build_dependency_graph();
+
+        let plan = BuildPlan::new(&collection);
+        let all: BTreeSet<String> = plan.nodes().map(|n| n.unique_id().to_string()).collect();
+        let order: Vec<&str> = plan
+            .order(&all)
+            .unwrap()
+            .into_iter()
+            .map(|n| n.unique_id())
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                "model.b_orders.b_orders",
+                "seed.countries",
+                "model.a_customers.a_customers",
+                "test.a_customers.a_customers.not_null_id",
+                "model.z_history.z_history",
+            ]
+        );
+        assert_eq!(
+            plan.get("model.z_history.z_history")
+                .unwrap()
+                .resource_type(),
+            ResourceType::Snapshot
+        );
+        assert!(plan
+            .descendants("seed.countries")
+            .contains("test.a_customers.a_customers.not_null_id"));
+    }
+
+    #[test]
+    fn test_seeds_are_planned_without_models() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("countries.csv"), "code\nNL\n").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "not a seed").unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        collection.load_seeds(temp_dir.path()).unwrap();
+        let plan = BuildPlan::new(&collection);
+
+        let ids: Vec<&str> = plan.nodes().map(|n| n.unique_id()).collect();
+        assert_eq!(ids, vec!["seed.countries"]);
+        assert!(plan.get("seed.countries").unwrap().is_executable());
+    }
+}
@@ -0,0 +1,68 @@
+//! Per-model outcomes of a run, persisted as `run_results.json`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::ModelRunStatus;
+use crate::stats::PhaseTiming;
+
+/// Default location of the run results, relative to the project root
+pub const RUN_RESULTS_FILE: &str = "target/run_results.json";
+
+/// Outcome of a single model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunResult {
+    pub unique_id: String,
+    pub status: ModelRunStatus,
+    /// Worker thread that executed the model
+    pub thread_id: usize,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    /// Adapter error or other detail, if any
+    pub message: Option<String>,
+    /// Rows in the relation the node left behind; recorded for seeds and tables, not views
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows: Option<u64>,
+    /// How the node ran much slower than expected, see `run::anomalies`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anomaly: Option<String>,
+}
+
+impl RunResult {
+    pub fn duration_secs(&self) -> f64 {
+        (self.completed_at - self.started_at).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+/// All results of one invocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunResults {
+    pub generated_at: DateTime<Utc>,
+    pub results: Vec<RunResult>,
+    /// How long each phase of the invocation took, up to writing these results
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<PhaseTiming>,
+}
+
+impl RunResults {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read run results: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse run results: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write run results: {}", path.display()))
+    }
+}
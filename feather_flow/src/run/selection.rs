@@ -0,0 +1,548 @@
+//! `--select` / `--exclude` expressions, applied alike to every resource type
+//!
+//! An expression is a space-separated list of terms; a node is selected when any term
+//! matches it. A term is a node name or unique id, `tag:<tag>`, `resource_type:<type>` or
+//! `path:<glob>`, optionally prefixed with `+` to add everything it depends on and suffixed
+//! with `+` to add everything depending on it. Prefixed with `@` instead, it adds everything
+//! depending on it and everything those depend on, i.e. what a rebuild of its descendants
+//! needs. Terms joined by commas, without spaces, select only the nodes matching all of them:
+//!
+//! ```text
+//! ff build --select "+fct_orders resource_type:seed tag:finance,tag:daily" \
+//!     --exclude "tag:nightly"
+//! ```
+//!
+//! Terms also combine with the words `and`, `or` and `not` and with parentheses, e.g.
+//! `tag:finance and not (tag:deprecated or tag:wip)`. `not` binds tightest, then `and`, then
+//! `or`; terms next to each other without an operator are joined by `or`.
+//!
+//! A path selects the models and seeds whose file it matches, or whose file is under a
+//! directory it matches. `*` and `?` match within one path component and `**` across any
+//! number of them. Paths are matched relative to the model path, and as reached from the
+//! working directory through `--model-path`, so `path:staging` and `path:models/staging`
+//! agree when run with `-m models`. A term without a method that contains `/`, a glob
+//! character or ends in `.sql` or `.csv` is taken as a path.
+//!
+//! Tests of selected models and snapshots are selected with them.
+
+use anyhow::{bail, Result};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use super::plan::{BuildPlan, PlanNode};
+use crate::sql_engine::nodes::{Node, ResourceType, SEEDS_DIR};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Method {
+    Name(String),
+    Tag(String),
+    ResourceType(ResourceType),
+    /// Glob, normalized to `/` separators without a leading `./` or trailing `/`
+    Path(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    method: Method,
+    parents: bool,
+    children: bool,
+    /// `@`: descendants and all of their ancestors
+    children_parents: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Term(Term),
+    /// Nodes matching any of the expressions: spaces and `or`
+    Any(Vec<Expr>),
+    /// Nodes matching all of the expressions: commas and `and`
+    All(Vec<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    expr: Expr,
+}
+
+impl Selector {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let tokens = tokenize(expression);
+        if tokens.is_empty() {
+            bail!("Empty selection expression");
+        }
+
+        let mut parser = ExprParser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expr = parser.any()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected '{}' in selection '{}'", token, expression);
+        }
+        Ok(Self { expr })
+    }
+
+    /// Unique ids of every node in `plan` the expression selects
+    fn matches(&self, plan: &BuildPlan) -> BTreeSet<String> {
+        self.expr.matches(plan)
+    }
+}
+
+impl Expr {
+    fn matches(&self, plan: &BuildPlan) -> BTreeSet<String> {
+        match self {
+            Expr::Term(term) => term.matches(plan),
+            Expr::Any(exprs) => exprs.iter().flat_map(|expr| expr.matches(plan)).collect(),
+            Expr::All(exprs) => {
+                let mut matched = exprs.iter().map(|expr| expr.matches(plan));
+                let first = matched.next().unwrap_or_default();
+                matched.fold(first, |all, next| &all & &next)
+            }
+            Expr::Not(expr) => {
+                let excluded = expr.matches(plan);
+                plan.nodes()
+                    .map(|node| node.unique_id().to_string())
+                    .filter(|id| !excluded.contains(id))
+                    .collect()
+            }
+        }
+    }
+
+    /// `exprs` joined by `wrap`, or the only one of them
+    fn join(mut exprs: Vec<Expr>, wrap: fn(Vec<Expr>) -> Expr) -> Expr {
+        if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            wrap(exprs)
+        }
+    }
+}
+
+/// Words and parentheses; anything else between spaces is a term or comma-joined terms
+fn tokenize(expression: &str) -> Vec<String> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Recursive descent over the tokens, one method per precedence level
+struct ExprParser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl ExprParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.position).map(String::as_str);
+        self.position += 1;
+        token
+    }
+
+    /// `and` expressions joined by `or` or by nothing at all
+    fn any(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.all()?];
+        loop {
+            match self.peek() {
+                None | Some(")") => break,
+                Some("or") => {
+                    self.next();
+                    exprs.push(self.all()?);
+                }
+                Some(_) => exprs.push(self.all()?),
+            }
+        }
+        Ok(Expr::join(exprs, Expr::Any))
+    }
+
+    fn all(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.unary()?];
+        while self.peek() == Some("and") {
+            self.next();
+            exprs.push(self.unary()?);
+        }
+        Ok(Expr::join(exprs, Expr::All))
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some("not") => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some("(") => {
+                let expr = self.any()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => bail!("Missing ')' in selection"),
+                }
+            }
+            Some(token @ (")" | "and" | "or")) => {
+                bail!("Expected a selection term before '{}'", token)
+            }
+            Some(token) => {
+                let terms = token
+                    .split(',')
+                    .map(|term| parse_term(term).map(Expr::Term))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Expr::join(terms, Expr::All))
+            }
+            None => bail!("Selection ends where a term is expected"),
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, plan: &BuildPlan) -> BTreeSet<String> {
+        let mut selected = BTreeSet::new();
+
+        for node in plan.nodes().filter(|node| self.method.matches(node)) {
+            selected.insert(node.unique_id().to_string());
+            if self.parents {
+                selected.extend(plan.ancestors(node.unique_id()));
+            }
+            if self.children || self.children_parents {
+                let descendants = plan.descendants(node.unique_id());
+                if self.children_parents {
+                    for descendant in &descendants {
+                        selected.extend(plan.ancestors(descendant));
+                    }
+                    selected.extend(plan.ancestors(node.unique_id()));
+                }
+                selected.extend(descendants);
+            }
+        }
+        selected
+    }
+}
+
+impl Method {
+    fn matches(&self, node: &PlanNode) -> bool {
+        match self {
+            Method::Name(name) => node.node.name() == name || node.unique_id() == name,
+            Method::Tag(tag) => node.node.tags().iter().any(|node_tag| node_tag == tag),
+            Method::ResourceType(resource_type) => node.resource_type() == *resource_type,
+            Method::Path(glob) => {
+                let pattern = glob_regex(glob);
+                node_paths(node.node).iter().any(|path| {
+                    path.ancestors()
+                        .any(|dir| pattern.is_match(&normalize_path(&dir.to_string_lossy())))
+                })
+            }
+        }
+    }
+}
+
+/// Files a node is defined in, as they can be written in a `path:` selector
+fn node_paths(node: &Node) -> Vec<PathBuf> {
+    match node {
+        Node::Model(model) | Node::Snapshot(model) => vec![
+            model.relative_file_path.clone(),
+            model.fully_qualified_file_path.clone(),
+        ],
+        Node::Seed(seed) => {
+            let mut paths = vec![seed.path.clone()];
+            if let Some(file_name) = seed.path.file_name() {
+                paths.push(Path::new(SEEDS_DIR).join(file_name));
+            }
+            paths
+        }
+        Node::Source(_) | Node::Test(_) | Node::Exposure(_) => Vec::new(),
+    }
+}
+
+/// `/` separators, without a leading `./` or trailing `/`
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    path.to_string()
+}
+
+/// Regex matching a whole path against `glob`
+fn glob_regex(glob: &str) -> Regex {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).expect("escaped glob is a valid regex")
+}
+
+/// Whether a term without a method names a file or directory rather than a node
+fn looks_like_path(body: &str) -> bool {
+    body.contains(['/', '\\', '*', '?']) || body.ends_with(".sql") || body.ends_with(".csv")
+}
+
+fn parse_term(term: &str) -> Result<Term> {
+    let children_parents = term.starts_with('@');
+    let term_without_at = term.strip_prefix('@').unwrap_or(term);
+    let parents = term_without_at.starts_with('+');
+    let children = term_without_at.len() > 1 && term_without_at.ends_with('+');
+    if children_parents && (parents || children) {
+        bail!("'{}' cannot combine @ with +", term);
+    }
+    let body = term_without_at
+        .trim_start_matches('+')
+        .trim_end_matches('+');
+
+    let method = match body.split_once(':') {
+        Some(("tag", tag)) if !tag.is_empty() => Method::Tag(tag.to_string()),
+        Some(("resource_type", name)) => match ResourceType::from_name(name) {
+            Some(resource_type) => Method::ResourceType(resource_type),
+            None => bail!(
+                "Unknown resource type '{}' in '{}'; expected one of: {}",
+                name,
+                term,
+                ResourceType::ALL.map(|t| t.as_str()).join(", ")
+            ),
+        },
+        Some(("path", path)) if !normalize_path(path).is_empty() => {
+            Method::Path(normalize_path(path))
+        }
+        Some((method, _)) => bail!("Unknown selection method '{}' in '{}'", method, term),
+        None if looks_like_path(body) => Method::Path(normalize_path(body)),
+        None if !body.is_empty() => Method::Name(body.to_string()),
+        None => bail!("Invalid selection term '{}'", term),
+    };
+
+    Ok(Term {
+        method,
+        parents,
+        children,
+        children_parents,
+    })
+}
+
+/// The nodes of `plan` to build: everything `select` matches (all nodes without it), plus
+/// the tests of selected models and snapshots, minus everything `exclude` matches
+pub fn resolve(
+    plan: &BuildPlan,
+    select: Option<&Selector>,
+    exclude: Option<&Selector>,
+) -> BTreeSet<String> {
+    let mut selected = match select {
+        Some(selector) => selector.matches(plan),
+        None => plan
+            .nodes()
+            .map(|node| node.unique_id().to_string())
+            .collect(),
+    };
+
+    let tests: Vec<String> = plan
+        .nodes()
+        .filter(|node| node.resource_type() == ResourceType::Test)
+        .filter(|node| node.depends_on.iter().all(|dep| selected.contains(dep)))
+        .map(|node| node.unique_id().to_string())
+        .collect();
+    selected.extend(tests);
+
+    if let Some(exclude) = exclude {
+        let excluded = exclude.matches(plan);
+        selected.retain(|id| !excluded.contains(id));
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::fixtures;
+    use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn write_model(root: &Path, name: &str, sql: &str, tags: &[&str]) -> SqlModel {
+        let mut model = fixtures::write_model(root, name, sql, None);
+        model.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        model
+    }
+
+    #[test]
+    fn test_parse_terms() {
+        let selector = Selector::parse("+orders tag:finance  resource_type:seed+").unwrap();
+        assert_eq!(
+            selector.expr,
+            Expr::Any(vec![
+                Expr::Term(Term {
+                    method: Method::Name("orders".to_string()),
+                    parents: true,
+                    children: false,
+                    children_parents: false,
+                }),
+                Expr::Term(Term {
+                    method: Method::Tag("finance".to_string()),
+                    parents: false,
+                    children: false,
+                    children_parents: false,
+                }),
+                Expr::Term(Term {
+                    method: Method::ResourceType(ResourceType::Seed),
+                    parents: false,
+                    children: true,
+                    children_parents: false,
+                }),
+            ])
+        );
+
+        let selector = Selector::parse("tag:finance,@orders").unwrap();
+        let Expr::All(terms) = &selector.expr else {
+            panic!("expected an intersection, got {:?}", selector.expr);
+        };
+        assert!(matches!(&terms[1], Expr::Term(term) if term.children_parents));
+
+        assert!(Selector::parse("resource_type:metric").is_err());
+        assert!(Selector::parse("path:").is_err());
+        assert!(Selector::parse("state:modified").is_err());
+        let method = |expression: &str| match Selector::parse(expression).unwrap().expr {
+            Expr::Term(term) => term.method,
+            other => panic!("expected a single term, got {:?}", other),
+        };
+        assert_eq!(
+            method("./models/staging/"),
+            Method::Path("models/staging".to_string())
+        );
+        assert_eq!(
+            method("stg_orders.sql"),
+            Method::Path("stg_orders.sql".to_string())
+        );
+
+        assert!(Selector::parse("tag:a and").is_err());
+        assert!(Selector::parse("(tag:a or tag:b").is_err());
+        assert!(Selector::parse("tag:a)").is_err());
+        assert!(Selector::parse("or tag:a").is_err());
+        assert!(Selector::parse("  ").is_err());
+        assert!(Selector::parse("+").is_err());
+        assert!(Selector::parse("@+orders").is_err());
+        assert!(Selector::parse("tag:finance,").is_err());
+    }
+
+    #[test]
+    fn test_resolve_selects_seeds_and_excludes() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("countries.csv"), "code\nNL\n").unwrap();
+        fs::write(temp_dir.path().join("currencies.csv"), "code\nEUR\n").unwrap();
+        let mut collection = SqlModelCollection::new();
+        collection.load_seeds(temp_dir.path()).unwrap();
+        let plan = BuildPlan::new(&collection);
+
+        let all = resolve(&plan, None, None);
+        assert_eq!(all.len(), 2);
+
+        let seeds = Selector::parse("resource_type:seed").unwrap();
+        let currencies = Selector::parse("currencies").unwrap();
+        assert_eq!(
+            resolve(&plan, Some(&seeds), Some(&currencies)),
+            BTreeSet::from(["seed.countries".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_resolve_intersections_and_at_operator() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(write_model(root, "orders", "SELECT 1 AS id", &["finance"]));
+        collection.add_model(write_model(
+            root,
+            "rates",
+            "SELECT 1 AS id",
+            &["finance", "daily"],
+        ));
+        collection.add_model(write_model(
+            root,
+            "revenue",
+            "SELECT * FROM public.orders",
+            &["daily"],
+        ));
+        collection.add_model(write_model(
+            root,
+            "report",
+            "SELECT * FROM public.revenue JOIN public.rates USING (id)",
+            &[],
+        ));
+        collection.build_dependency_graph();
+        let plan = BuildPlan::new(&collection);
+        let names = |expression: &str| -> Vec<String> {
+            let selector = Selector::parse(expression).unwrap();
+            resolve(&plan, Some(&selector), None)
+                .into_iter()
+                .map(|id| id.split('.').next_back().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(names("tag:finance,tag:daily"), vec!["rates"]);
+        assert_eq!(
+            names("tag:finance,tag:daily orders"),
+            vec!["orders", "rates"]
+        );
+        assert_eq!(names("revenue+,tag:daily"), vec!["revenue"]);
+        assert_eq!(names("+revenue+"), vec!["orders", "report", "revenue"]);
+        assert_eq!(
+            names("@revenue"),
+            vec!["orders", "rates", "report", "revenue"]
+        );
+        assert_eq!(names("tag:finance and not tag:daily"), vec!["orders"]);
+        assert_eq!(names("not (tag:finance or tag:daily)"), vec!["report"]);
+        assert_eq!(
+            names("report or tag:daily and not tag:finance"),
+            vec!["report", "revenue"]
+        );
+        assert_eq!(names("(tag:finance)and(tag:daily)"), vec!["rates"]);
+    }
+
+    #[test]
+    fn test_resolve_paths_and_globs() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let mut collection = SqlModelCollection::new();
+        for dir in ["staging", "marts", "marts/finance"] {
+            let name = format!("{}_model", dir.replace('/', "_"));
+            collection.add_model(fixtures::write_model_in(
+                root,
+                dir,
+                &name,
+                "SELECT 1 AS id",
+                None,
+            ));
+        }
+        let plan = BuildPlan::new(&collection);
+        let names = |expression: &str| -> Vec<String> {
+            let selector = Selector::parse(expression).unwrap();
+            resolve(&plan, Some(&selector), None)
+                .into_iter()
+                .map(|id| id.split('.').next_back().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(names("path:staging"), vec!["staging_model"]);
+        assert_eq!(names("marts/*"), vec!["marts_finance_model", "marts_model"]);
+        assert_eq!(names("path:marts/*/*.sql"), vec!["marts_model"]);
+        assert_eq!(names("path:**/finance_*"), Vec::<String>::new());
+        assert_eq!(
+            names("**/marts_finance_model.sql"),
+            vec!["marts_finance_model"]
+        );
+        let absolute = format!("path:{}/staging", root.display());
+        assert_eq!(names(&absolute), vec!["staging_model"]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Live DAG snapshots of an in-progress run
+//!
+//! `ff build` periodically writes `run_graph.dot` and a self-refreshing `run_graph.html`
+//! into `target/` so operators of long runs can see which nodes are pending, running,
+//! done, failed or skipped. Files are written to a temporary path and renamed, so readers
+//! never observe a half-written snapshot.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::ModelRunStatus;
+use crate::sql_engine::sql_model::SqlModelCollection;
+
+pub const SNAPSHOT_DOT_FILE: &str = "run_graph.dot";
+pub const SNAPSHOT_HTML_FILE: &str = "run_graph.html";
+
+/// Directory `ff build` writes its snapshots to, relative to the project root
+pub const SNAPSHOT_DIR: &str = "target";
+
+/// Least time between two snapshots of a build
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Throttled writer for run-status DAG snapshots
+#[derive(Debug)]
+pub struct RunGraphSnapshot {
+    output_dir: PathBuf,
+    interval: Duration,
+    last_written: Option<Instant>,
+}
+
+impl RunGraphSnapshot {
+    pub fn new(output_dir: &Path, interval: Duration) -> Self {
+        Self {
+            output_dir: output_dir.to_path_buf(),
+            interval,
+            last_written: None,
+        }
+    }
+
+    /// Write a snapshot if at least `interval` has passed since the previous one.
+    /// Returns whether a snapshot was written.
+    pub fn maybe_write(
+        &mut self,
+        collection: &SqlModelCollection,
+        statuses: &HashMap<String, ModelRunStatus>,
+    ) -> Result<bool> {
+        if let Some(last) = self.last_written {
+            if last.elapsed() < self.interval {
+                return Ok(false);
+            }
+        }
+
+        self.write(collection, statuses)?;
+        Ok(true)
+    }
+
+    /// Write a snapshot unconditionally (e.g. at the end of the run)
+    pub fn write(
+        &mut self,
+        collection: &SqlModelCollection,
+        statuses: &HashMap<String, ModelRunStatus>,
+    ) -> Result<()> {
+        fs::create_dir_all(&self.output_dir).with_context(|| {
+            format!(
+                "Failed to create snapshot directory: {}",
+                self.output_dir.display()
+            )
+        })?;
+
+        let dot = collection.to_status_dot_graph(statuses);
+        let html = render_html(&dot, statuses, self.interval);
+
+        write_atomic(&self.output_dir.join(SNAPSHOT_DOT_FILE), &dot)?;
+        write_atomic(&self.output_dir.join(SNAPSHOT_HTML_FILE), &html)?;
+
+        self.last_written = Some(Instant::now());
+        Ok(())
+    }
+}
+
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)
+        .with_context(|| format!("Failed to write snapshot: {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to replace snapshot: {}", path.display()))
+}
+
+fn render_html(
+    dot: &str,
+    statuses: &HashMap<String, ModelRunStatus>,
+    refresh_interval: Duration,
+) -> String {
+    let mut models: Vec<(&String, &ModelRunStatus)> = statuses.iter().collect();
+    models.sort();
+
+    let mut summary = String::new();
+    for status in ModelRunStatus::ALL {
+        let count = models.iter().filter(|(_, s)| **s == status).count();
+        summary.push_str(&format!(
+            "<span style=\"background:{}\">{}: {}</span>\n",
+            status.color(),
+            status,
+            count
+        ));
+    }
+
+    let mut rows = String::new();
+    for (model_id, status) in models {
+        rows.push_str(&format!(
+            "<tr style=\"background:{}\"><td>{}</td><td>{}</td></tr>\n",
+            status.color(),
+            escape_html(model_id),
+            status
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="{refresh}">
+<title>FeatherFlow run</title>
+<style>
+body {{ font-family: sans-serif; }}
+span {{ padding: 2px 8px; margin-right: 4px; }}
+td {{ padding: 2px 8px; }}
+</style>
+</head>
+<body>
+<h1>FeatherFlow run</h1>
+<p>{summary}</p>
+<table>
+{rows}</table>
+<h2>Graph</h2>
+<pre>{dot}</pre>
+</body>
+</html>
+"#,
+        refresh = refresh_interval.as_secs().max(1),
+        summary = summary,
+        rows = rows,
+        dot = escape_html(dot),
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_snapshot_is_throttled() {
+        let temp_dir = tempdir().unwrap();
+        let collection = SqlModelCollection::new();
+        let mut statuses = HashMap::new();
+        statuses.insert("model.orders.orders".to_string(), ModelRunStatus::Running);
+        statuses.insert(
+            "model.customers.customers".to_string(),
+            ModelRunStatus::Failed,
+        );
+
+        let mut snapshot = RunGraphSnapshot::new(temp_dir.path(), Duration::from_secs(3600));
+        assert!(snapshot.maybe_write(&collection, &statuses).unwrap());
+        assert!(!snapshot.maybe_write(&collection, &statuses).unwrap());
+
+        let html = fs::read_to_string(temp_dir.path().join(SNAPSHOT_HTML_FILE)).unwrap();
+        assert!(html.contains("running: 1"));
+        assert!(html.contains("failed: 1"));
+        assert!(html.contains("model.orders.orders"));
+        assert!(temp_dir.path().join(SNAPSHOT_DOT_FILE).exists());
+        assert!(!temp_dir.path().join("run_graph.tmp").exists());
+    }
+}
@@ -0,0 +1,198 @@
+//! Gantt-style timeline of a finished run
+//!
+//! Renders `run_results.json` as an HTML page with an inline SVG: one lane per worker
+//! thread, one bar per model, so idle gaps in parallelism and long-running stragglers
+//! stand out.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::results::{RunResult, RunResults};
+
+/// Where `ff build` writes the timeline of its run
+pub const TIMELINE_FILE: &str = "target/timeline.html";
+
+const LABEL_WIDTH: f64 = 120.0;
+const CHART_WIDTH: f64 = 900.0;
+const LANE_HEIGHT: f64 = 28.0;
+const AXIS_HEIGHT: f64 = 24.0;
+const AXIS_TICKS: usize = 5;
+const SLOWEST_MODELS: usize = 5;
+
+/// Write the timeline HTML for `results` to `path`
+pub fn write_timeline(results: &RunResults, path: &Path) -> Result<()> {
+    fs::write(path, render_timeline(results))
+        .with_context(|| format!("Failed to write timeline: {}", path.display()))
+}
+
+/// Render the timeline as a standalone HTML document
+pub fn render_timeline(results: &RunResults) -> String {
+    let run_start = results.results.iter().map(|r| r.started_at).min();
+    let run_end = results.results.iter().map(|r| r.completed_at).max();
+
+    let (run_start, total_secs) = match (run_start, run_end) {
+        (Some(start), Some(end)) => (
+            start,
+            ((end - start).num_milliseconds() as f64 / 1000.0).max(0.001),
+        ),
+        _ => return wrap_html("<p>No models were run.</p>"),
+    };
+
+    let mut lanes: BTreeMap<usize, Vec<&RunResult>> = BTreeMap::new();
+    for result in &results.results {
+        lanes.entry(result.thread_id).or_default().push(result);
+    }
+
+    let scale = CHART_WIDTH / total_secs;
+    let height = AXIS_HEIGHT + lanes.len() as f64 * LANE_HEIGHT;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-size=\"11\">\n",
+        LABEL_WIDTH + CHART_WIDTH + 10.0,
+        height
+    );
+
+    for tick in 0..=AXIS_TICKS {
+        let secs = total_secs * tick as f64 / AXIS_TICKS as f64;
+        let x = LABEL_WIDTH + secs * scale;
+        svg.push_str(&format!(
+            "<line x1=\"{x:.1}\" y1=\"{top}\" x2=\"{x:.1}\" y2=\"{height}\" stroke=\"#ddd\"/>\n\
+             <text x=\"{x:.1}\" y=\"12\" text-anchor=\"middle\">{secs:.1}s</text>\n",
+            top = AXIS_HEIGHT - 6.0,
+        ));
+    }
+
+    for (lane, (thread_id, lane_results)) in lanes.iter().enumerate() {
+        let y = AXIS_HEIGHT + lane as f64 * LANE_HEIGHT;
+        svg.push_str(&format!(
+            "<text x=\"4\" y=\"{:.1}\">thread {}</text>\n",
+            y + LANE_HEIGHT / 2.0 + 4.0,
+            thread_id
+        ));
+
+        for result in lane_results {
+            let offset = (result.started_at - run_start).num_milliseconds() as f64 / 1000.0;
+            let x = LABEL_WIDTH + offset * scale;
+            let width = (result.duration_secs() * scale).max(1.0);
+            let name = escape_xml(model_name(&result.unique_id));
+
+            svg.push_str(&format!(
+                "<rect x=\"{x:.1}\" y=\"{:.1}\" width=\"{width:.1}\" height=\"{:.1}\" fill=\"{}\" stroke=\"#555\">\
+                 <title>{} ({}, {:.2}s)</title></rect>\n",
+                y + 3.0,
+                LANE_HEIGHT - 6.0,
+                result.status.color(),
+                escape_xml(&result.unique_id),
+                result.status,
+                result.duration_secs(),
+            ));
+
+            // Only label bars wide enough to hold the text
+            if width > name.len() as f64 * 6.5 {
+                svg.push_str(&format!(
+                    "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+                    x + 3.0,
+                    y + LANE_HEIGHT / 2.0 + 4.0,
+                    name
+                ));
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+
+    let mut slowest: Vec<&RunResult> = results.results.iter().collect();
+    slowest.sort_by(|a, b| b.duration_secs().total_cmp(&a.duration_secs()));
+
+    let mut rows = String::new();
+    for result in slowest.into_iter().take(SLOWEST_MODELS) {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{:.2}s</td></tr>\n",
+            escape_xml(&result.unique_id),
+            result.thread_id,
+            result.duration_secs()
+        ));
+    }
+
+    wrap_html(&format!(
+        "<p>{} models on {} threads in {:.2}s</p>\n{}<h2>Slowest models</h2>\n<table>\n{}</table>\n",
+        results.results.len(),
+        lanes.len(),
+        total_secs,
+        svg,
+        rows
+    ))
+}
+
+fn wrap_html(body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>FeatherFlow run timeline</title>\n\
+         <style>body {{ font-family: sans-serif; }} td {{ padding: 2px 8px; }}</style>\n\
+         </head>\n<body>\n<h1>FeatherFlow run timeline</h1>\n{}</body>\n</html>\n",
+        body
+    )
+}
+
+fn model_name(unique_id: &str) -> &str {
+    unique_id.rsplit('.').next().unwrap_or(unique_id)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::ModelRunStatus;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn result(unique_id: &str, thread_id: usize, start: i64, secs: i64) -> RunResult {
+        let started_at = Utc.timestamp_opt(1_700_000_000 + start, 0).unwrap();
+        RunResult {
+            unique_id: unique_id.to_string(),
+            status: ModelRunStatus::Success,
+            thread_id,
+            started_at,
+            completed_at: started_at + Duration::seconds(secs),
+            message: None,
+            rows: None,
+            anomaly: None,
+        }
+    }
+
+    #[test]
+    fn test_render_timeline_lanes_and_stragglers() {
+        let results = RunResults {
+            generated_at: Utc::now(),
+            results: vec![
+                result("model.stg_orders.stg_orders", 0, 0, 2),
+                result("model.stg_customers.stg_customers", 1, 0, 1),
+                result("model.fct_orders.fct_orders", 0, 2, 30),
+            ],
+            phases: Vec::new(),
+        };
+
+        let html = render_timeline(&results);
+        assert!(html.contains("3 models on 2 threads in 32.00s"));
+        assert!(html.contains("thread 0"));
+        assert!(html.contains("thread 1"));
+        assert_eq!(html.matches("<rect").count(), 3);
+
+        let slowest = html.split("Slowest models").nth(1).unwrap();
+        assert!(slowest.find("fct_orders").unwrap() < slowest.find("stg_orders").unwrap());
+    }
+
+    #[test]
+    fn test_render_empty_timeline() {
+        let results = RunResults {
+            generated_at: Utc::now(),
+            results: Vec::new(),
+            phases: Vec::new(),
+        };
+        assert!(render_timeline(&results).contains("No models were run."));
+    }
+}
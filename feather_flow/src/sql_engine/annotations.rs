@@ -0,0 +1,135 @@
+//! Inline test annotations in model SQL comments
+//!
+//! Quick column checks can live next to the logic instead of in YAML:
+//!
+//! ```sql
+//! -- ff:test not_null(customer_id), unique(customer_id)
+//! SELECT customer_id, email FROM raw.customers
+//! ```
+
+/// Comment marker that introduces test annotations
+pub const TEST_ANNOTATION_MARKER: &str = "ff:test";
+
+/// A single `test(column)` annotation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestAnnotation {
+    /// 1-based line of the comment in the model file
+    pub line: usize,
+    pub test: String,
+    pub column: String,
+}
+
+/// Extract all test annotations from `sql`; malformed annotations are returned as errors
+pub fn parse_test_annotations(sql: &str) -> (Vec<TestAnnotation>, Vec<String>) {
+    let mut annotations = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, line) in sql.lines().enumerate() {
+        let Some(comment) = line.trim_start().strip_prefix("--") else {
+            continue;
+        };
+        let Some(body) = comment.trim_start().strip_prefix(TEST_ANNOTATION_MARKER) else {
+            continue;
+        };
+
+        let line_number = index + 1;
+        let body = body.trim();
+        if body.is_empty() {
+            errors.push(format!(
+                "line {}: '{}' annotation without any tests",
+                line_number, TEST_ANNOTATION_MARKER
+            ));
+            continue;
+        }
+
+        for item in split_items(body) {
+            match parse_item(item) {
+                Some((test, column)) => annotations.push(TestAnnotation {
+                    line: line_number,
+                    test,
+                    column,
+                }),
+                None => errors.push(format!(
+                    "line {}: invalid test annotation '{}' (expected test_name(column))",
+                    line_number, item
+                )),
+            }
+        }
+    }
+
+    (annotations, errors)
+}
+
+/// Split `a(x), b(y)` on commas outside parentheses
+fn split_items(body: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (i, c) in body.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(body[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(body[start..].trim());
+
+    items.into_iter().filter(|item| !item.is_empty()).collect()
+}
+
+fn parse_item(item: &str) -> Option<(String, String)> {
+    let (test, rest) = item.split_once('(')?;
+    let column = rest.strip_suffix(')')?.trim();
+    let test = test.trim();
+
+    let is_identifier =
+        |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier(test) || !is_identifier(column) {
+        return None;
+    }
+
+    Some((test.to_string(), column.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_annotations() {
+        let sql = "-- ff:test not_null(customer_id), unique(customer_id)\n\
+                   SELECT customer_id, email\n\
+                   --ff:test not_null(email)\n\
+                   FROM raw.customers -- a regular comment";
+
+        let (annotations, errors) = parse_test_annotations(sql);
+        assert!(errors.is_empty());
+        assert_eq!(annotations.len(), 3);
+        assert_eq!(
+            annotations[1],
+            TestAnnotation {
+                line: 1,
+                test: "unique".to_string(),
+                column: "customer_id".to_string(),
+            }
+        );
+        assert_eq!(annotations[2].line, 3);
+        assert_eq!(annotations[2].column, "email");
+    }
+
+    #[test]
+    fn test_malformed_annotations() {
+        let (annotations, errors) =
+            parse_test_annotations("-- ff:test not_null\n-- ff:test\n-- ff:test unique(id)");
+
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("line 1: invalid test annotation 'not_null'"));
+        assert!(errors[1].starts_with("line 2:"));
+    }
+}
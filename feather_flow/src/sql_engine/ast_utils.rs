@@ -4,9 +4,16 @@
 //! tasks like schema replacement and SQL transformation. Currently, these functions
 //! are only used in tests, but are intended to be integrated into the main application
 //! for features like SQL query rewriting, database migrations, or SQL optimization.
-use sqlparser::ast::{Ident, Query, Statement, TableFactor};
+//!
+//! Modified statements are turned back into SQL by sqlparser's `Display` implementations,
+//! which cover every statement type the parser produces.
+use sqlparser::ast::{
+    visit_relations_mut, Ident, ObjectName, Query, Statement, TableFactor, Visit, Visitor,
+};
 use sqlparser::dialect::DuckDbDialect;
 use sqlparser::parser::Parser;
+use std::collections::HashSet;
+use std::ops::ControlFlow;
 
 #[allow(dead_code)]
 pub fn swap_sql_tables(sql: &str) -> String {
@@ -14,20 +21,11 @@ pub fn swap_sql_tables(sql: &str) -> String {
 
     let mut ast = Parser::parse_sql(&dialect, sql).unwrap();
 
-    println!("Original AST: {:#?}", ast);
-
-    let table_names = get_table_names(&ast);
-    println!("Original Tables: {:?}", table_names);
-
     // Modify the AST to change schema references
     modify_table_schemas(&mut ast, "private");
 
-    println!("Modified AST: {:#?}", ast);
-
     // Convert the modified AST back to SQL
-    let modified_sql = ast_to_sql(&ast);
-    println!("Modified SQL: {}", modified_sql);
-    modified_sql
+    ast_to_sql(&ast)
 }
 
 #[allow(dead_code)]
@@ -57,275 +55,67 @@ fn collect_table_names(table_factor: &TableFactor, table_names: &mut Vec<String>
     }
 }
 
-#[allow(dead_code)]
-fn modify_table_schemas(statements: &mut [Statement], target_schema: &str) {
-    for statement in statements {
-        if let Statement::Query(query) = statement {
-            modify_query_table_schemas(&mut *query, target_schema);
-        }
-    }
-}
+/// Names of the CTEs defined anywhere in the statements
+struct CteNames(HashSet<String>);
 
-#[allow(dead_code)]
-fn modify_query_table_schemas(query: &mut Query, target_schema: &str) {
-    if let sqlparser::ast::SetExpr::Select(select) = &mut *query.body {
-        for table_with_joins in &mut select.from {
-            modify_table_schema(&mut table_with_joins.relation, target_schema);
-            for join in &mut table_with_joins.joins {
-                modify_table_schema(&mut join.relation, target_schema);
-            }
-        }
-    }
-}
+impl Visitor for CteNames {
+    type Break = ();
 
-#[allow(dead_code)]
-fn modify_table_schema(table_factor: &mut TableFactor, target_schema: &str) {
-    if let TableFactor::Table { name, .. } = table_factor {
-        // If it's a simple table name without schema, add the target schema
-        match name.0.len() {
-            1 => {
-                let table_name = name.0[0].value.clone();
-                name.0.clear();
-                name.0.push(Ident::new(target_schema));
-                name.0.push(Ident::new(&table_name));
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.0.insert(cte.alias.name.value.to_lowercase());
             }
-            len if len > 1 => {
-                name.0[0] = Ident::new(target_schema);
-            }
-            _ => {}
         }
+        ControlFlow::Continue(())
     }
 }
 
+/// Move every relation the statements read or write into `target_schema`, wherever it
+/// appears: CTE bodies, subqueries, set operations, joins and DML targets. References to
+/// CTEs are left alone.
 #[allow(dead_code)]
-fn ast_to_sql(statements: &[Statement]) -> String {
-    let mut result = String::new();
-
-    for (i, statement) in statements.iter().enumerate() {
-        if i > 0 {
-            result.push(' ');
-        }
-
-        match statement {
-            Statement::Query(query) => {
-                result.push_str(&query_to_sql(query));
-            }
-            // Add other statement types as needed
-            _ => result.push_str("/* Unsupported statement type */"),
-        }
-
-        result.push(';');
+fn modify_table_schemas(statements: &mut [Statement], target_schema: &str) {
+    let mut ctes = CteNames(HashSet::new());
+    for statement in statements.iter() {
+        let _ = statement.visit(&mut ctes);
     }
 
-    result
-}
-
-#[allow(dead_code)]
-fn query_to_sql(query: &Query) -> String {
-    match &*query.body {
-        sqlparser::ast::SetExpr::Select(select) => {
-            // Build SELECT clause
-            let mut sql = String::from("SELECT ");
-
-            // Project expressions (columns)
-            for (i, projection) in select.projection.iter().enumerate() {
-                if i > 0 {
-                    sql.push_str(", ");
-                }
-
-                match projection {
-                    sqlparser::ast::SelectItem::Wildcard(_) => {
-                        sql.push('*');
-                    }
-                    sqlparser::ast::SelectItem::UnnamedExpr(expr) => {
-                        match expr {
-                            sqlparser::ast::Expr::Identifier(ident) => {
-                                sql.push_str(&ident.value);
-                            }
-                            sqlparser::ast::Expr::CompoundIdentifier(idents) => {
-                                sql.push_str(
-                                    &idents
-                                        .iter()
-                                        .map(|ident| ident.value.clone())
-                                        .collect::<Vec<_>>()
-                                        .join("."),
-                                );
-                            }
-                            // Handle other expression types as needed
-                            _ => sql.push_str(&expr_to_sql(expr)),
-                        }
-                    }
-                    sqlparser::ast::SelectItem::ExprWithAlias { expr, alias } => {
-                        // Handle expressions with aliases (AS)
-                        match expr {
-                            sqlparser::ast::Expr::Identifier(ident) => {
-                                sql.push_str(&format!("{} AS {}", ident.value, alias.value));
-                            }
-                            sqlparser::ast::Expr::CompoundIdentifier(idents) => {
-                                let column_name = idents
-                                    .iter()
-                                    .map(|ident| ident.value.clone())
-                                    .collect::<Vec<_>>()
-                                    .join(".");
-                                sql.push_str(&format!("{} AS {}", column_name, alias.value));
-                            }
-                            _ => {
-                                sql.push_str(&format!("{} AS {}", expr_to_sql(expr), alias.value));
-                            }
-                        }
-                    }
-                    // Handle other projection types as needed
-                    _ => sql.push_str("/* complex projection */"),
-                }
-            }
-
-            // FROM clause
-            if !select.from.is_empty() {
-                sql.push_str(" FROM ");
-
-                for (i, table_with_joins) in select.from.iter().enumerate() {
-                    if i > 0 {
-                        sql.push_str(", ");
-                    }
-
-                    // Main table
-                    sql.push_str(&table_factor_to_sql(&table_with_joins.relation));
-
-                    // JOINs
-                    for join in &table_with_joins.joins {
-                        match join.join_operator {
-                            sqlparser::ast::JoinOperator::Inner(_) => {
-                                sql.push_str(" INNER JOIN ");
-                            }
-                            sqlparser::ast::JoinOperator::LeftOuter(_) => {
-                                sql.push_str(" LEFT JOIN ");
-                            }
-                            sqlparser::ast::JoinOperator::RightOuter(_) => {
-                                sql.push_str(" RIGHT JOIN ");
-                            }
-                            sqlparser::ast::JoinOperator::FullOuter(_) => {
-                                sql.push_str(" FULL JOIN ");
-                            }
-                            // Add other join types as needed
-                            _ => {
-                                println!("Unsupported join operator: {:?}", join.join_operator);
-                                sql.push_str(" JOIN ");
-                            }
-                        }
-
-                        sql.push_str(&table_factor_to_sql(&join.relation));
-
-                        // JOIN condition
-                        match &join.join_operator {
-                            sqlparser::ast::JoinOperator::Inner(
-                                sqlparser::ast::JoinConstraint::On(expr),
-                            )
-                            | sqlparser::ast::JoinOperator::LeftOuter(
-                                sqlparser::ast::JoinConstraint::On(expr),
-                            )
-                            | sqlparser::ast::JoinOperator::RightOuter(
-                                sqlparser::ast::JoinConstraint::On(expr),
-                            )
-                            | sqlparser::ast::JoinOperator::FullOuter(
-                                sqlparser::ast::JoinConstraint::On(expr),
-                            ) => {
-                                sql.push_str(" ON ");
-                                sql.push_str(&expr_to_sql(expr));
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
-
-            // WHERE clause
-            if let Some(selection) = &select.selection {
-                sql.push_str(" WHERE ");
-                sql.push_str(&expr_to_sql(selection));
-            }
-
-            sql
-        }
-        // Handle other query types as needed
-        _ => String::from("/* Unsupported query type */"),
+    for statement in statements {
+        let _ = visit_relations_mut(statement, |name| {
+            modify_table_schema(name, target_schema, &ctes.0);
+            ControlFlow::<()>::Continue(())
+        });
     }
 }
 
 #[allow(dead_code)]
-fn table_factor_to_sql(table_factor: &TableFactor) -> String {
-    match table_factor {
-        TableFactor::Table { name, alias, .. } => {
-            let table_name = name
-                .0
-                .iter()
-                .map(|ident| ident.value.clone())
-                .collect::<Vec<_>>()
-                .join(".");
-
-            // Add table alias if present
-            if let Some(table_alias) = alias {
-                format!("{} {}", table_name, table_alias.name.value)
-            } else {
-                table_name
-            }
+fn modify_table_schema(name: &mut ObjectName, target_schema: &str, ctes: &HashSet<String>) {
+    match name.0.len() {
+        // A simple table name without schema gets the target schema
+        1 if !ctes.contains(&name.0[0].value.to_lowercase()) => {
+            name.0.insert(0, Ident::new(target_schema));
         }
-        // Handle other table factor types as needed
-        _ => String::from("/* Unsupported table factor */"),
+        len if len > 1 => {
+            name.0[len - 2] = Ident::new(target_schema);
+        }
+        _ => {}
     }
 }
 
+/// Render statements as SQL, each terminated by a semicolon
 #[allow(dead_code)]
-fn expr_to_sql(expr: &sqlparser::ast::Expr) -> String {
-    match expr {
-        sqlparser::ast::Expr::BinaryOp { left, op, right } => {
-            format!(
-                "{} {} {}",
-                expr_to_sql(left),
-                match op {
-                    sqlparser::ast::BinaryOperator::Eq => "=",
-                    sqlparser::ast::BinaryOperator::Gt => ">",
-                    sqlparser::ast::BinaryOperator::Lt => "<",
-                    sqlparser::ast::BinaryOperator::GtEq => ">=",
-                    sqlparser::ast::BinaryOperator::LtEq => "<=",
-                    sqlparser::ast::BinaryOperator::NotEq => "<>",
-                    sqlparser::ast::BinaryOperator::And => "AND",
-                    sqlparser::ast::BinaryOperator::Or => "OR",
-                    // Handle other operators as needed
-                    _ => {
-                        println!("Unsupported binary operator: {:?}", op);
-                        "??"
-                    }
-                },
-                expr_to_sql(right)
-            )
-        }
-        sqlparser::ast::Expr::Identifier(ident) => ident.value.clone(),
-        sqlparser::ast::Expr::CompoundIdentifier(idents) => idents
-            .iter()
-            .map(|ident| ident.value.clone())
-            .collect::<Vec<_>>()
-            .join("."),
-        sqlparser::ast::Expr::Value(value) => {
-            match value {
-                sqlparser::ast::Value::Number(num, _) => num.clone(),
-                sqlparser::ast::Value::SingleQuotedString(s) => format!("'{}'", s),
-                sqlparser::ast::Value::Boolean(b) => b.to_string(),
-                sqlparser::ast::Value::Null => String::from("NULL"),
-                // Handle other value types as needed
-                _ => String::from("/* unknown value */"),
-            }
-        }
-        // Handle other expression types as needed
-        _ => {
-            println!("Unsupported expression type: {:?}", expr);
-            String::from("/* complex expression */")
-        }
-    }
+fn ast_to_sql(statements: &[Statement]) -> String {
+    statements
+        .iter()
+        .map(|statement| format!("{};", statement))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*; // This is correct, but unused because we're calling functions directly
+    use super::*;
 
     #[test]
     fn test_simple_select() {
@@ -356,9 +146,9 @@ mod tests {
 
     #[test]
     fn test_with_join() {
-        let input = "SELECT * FROM users INNER JOIN orders ON users.id = orders.user_id";
+        let input = "SELECT * FROM users JOIN orders ON users.id = orders.user_id";
         let expected =
-            "SELECT * FROM private.users INNER JOIN private.orders ON users.id = orders.user_id;";
+            "SELECT * FROM private.users JOIN private.orders ON users.id = orders.user_id;";
 
         let result = swap_sql_tables(input);
         assert_eq!(result, expected);
@@ -384,8 +174,7 @@ mod tests {
     #[test]
     fn test_with_table_alias() {
         let input = "SELECT u.id, u.name FROM users u WHERE u.active = 1";
-        // With the updated implementation we now correctly preserve table aliases
-        let expected = "SELECT u.id, u.name FROM private.users u WHERE u.active = 1;";
+        let expected = "SELECT u.id, u.name FROM private.users AS u WHERE u.active = 1;";
 
         let result = swap_sql_tables(input);
         assert_eq!(result, expected);
@@ -424,7 +213,34 @@ mod tests {
     #[test]
     fn test_left_join() {
         let input = "SELECT c.id, c.name, o.order_date FROM customers c LEFT JOIN orders o ON c.id = o.customer_id";
-        let expected = "SELECT c.id, c.name, o.order_date FROM private.customers c LEFT JOIN private.orders o ON c.id = o.customer_id;";
+        let expected = "SELECT c.id, c.name, o.order_date FROM private.customers AS c LEFT JOIN private.orders AS o ON c.id = o.customer_id;";
+
+        let result = swap_sql_tables(input);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_ctes_and_subqueries() {
+        let input = "WITH recent AS (SELECT * FROM orders WHERE id IN (SELECT order_id FROM refunds)) SELECT customer_id, count(*) FROM recent GROUP BY customer_id ORDER BY 2 DESC";
+        let expected = "WITH recent AS (SELECT * FROM private.orders WHERE id IN (SELECT order_id FROM private.refunds)) SELECT customer_id, count(*) FROM recent GROUP BY customer_id ORDER BY 2 DESC;";
+
+        let result = swap_sql_tables(input);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_window_functions_and_set_operations() {
+        let input = "SELECT id, row_number() OVER (PARTITION BY customer_id ORDER BY created_at) AS rn FROM orders UNION ALL SELECT id, 0 FROM archive.orders";
+        let expected = "SELECT id, row_number() OVER (PARTITION BY customer_id ORDER BY created_at) AS rn FROM private.orders UNION ALL SELECT id, 0 FROM private.orders;";
+
+        let result = swap_sql_tables(input);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_statements_other_than_queries() {
+        let input = "INSERT INTO totals SELECT sum(amount) FROM payments; CREATE TABLE copy AS SELECT * FROM totals";
+        let expected = "INSERT INTO private.totals SELECT sum(amount) FROM private.payments; CREATE TABLE private.copy AS SELECT * FROM private.totals;";
 
         let result = swap_sql_tables(input);
         assert_eq!(result, expected);
@@ -0,0 +1,211 @@
+//! Checksums of model SQL, raw over the exact text or normalized over its tokens
+//!
+//! Every checksum and cache key is a digest by the project's hash algorithm, `state.hash`,
+//! which callers resolve from the project configuration and pass in: SHA-256 by default, or
+//! xxHash (XXH3, 128 bits) when `ff` is built with the `xxhash` feature, which is much faster
+//! on projects with thousands of models. Digests by different algorithms never compare equal,
+//! so switching only costs one full parse and build.
+//!
+//! The normalized checksum hashes the SQL's tokens without whitespace or comments, with
+//! unquoted keywords lowercased, so reindenting a model or editing its comments leaves it
+//! unchanged while any change to what the SQL says does not. Text the tokenizer rejects,
+//! such as an unterminated string, falls back to collapsing runs of whitespace.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlparser::dialect::{BigQueryDialect, Dialect, DuckDbDialect};
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::config::SqlDialect;
+
+/// A hash function producing hex digests
+pub trait HashAlgorithm: Sync {
+    /// Name the algorithm is configured by and recorded under
+    fn name(&self) -> &'static str;
+
+    /// Hex digest of `content`
+    fn hash(&self, content: &[u8]) -> String;
+
+    /// Hex digest of `parts`, each followed by a zero byte so `["ab", "c"]` and `["a", "bc"]`
+    /// differ
+    fn hash_parts(&self, parts: &[&str]) -> String {
+        let mut content = Vec::with_capacity(parts.iter().map(|part| part.len() + 1).sum());
+        for part in parts {
+            content.extend_from_slice(part.as_bytes());
+            content.push(0);
+        }
+        self.hash(&content)
+    }
+}
+
+pub struct Sha256Hash;
+
+impl HashAlgorithm for Sha256Hash {
+    fn name(&self) -> &'static str {
+        "sha256"
+    }
+
+    fn hash(&self, content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+}
+
+#[cfg(feature = "xxhash")]
+pub struct Xxh3Hash;
+
+#[cfg(feature = "xxhash")]
+impl HashAlgorithm for Xxh3Hash {
+    fn name(&self) -> &'static str {
+        "xxh3"
+    }
+
+    fn hash(&self, content: &[u8]) -> String {
+        format!("{:032x}", xxhash_rust::xxh3::xxh3_128(content))
+    }
+}
+
+/// Hash algorithms `state.hash` can name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashKind {
+    #[default]
+    Sha256,
+    /// Only available when built with the `xxhash` feature
+    Xxh3,
+}
+
+impl HashKind {
+    pub fn algorithm(self) -> Result<&'static dyn HashAlgorithm, String> {
+        match self {
+            HashKind::Sha256 => Ok(&Sha256Hash),
+            #[cfg(feature = "xxhash")]
+            HashKind::Xxh3 => Ok(&Xxh3Hash),
+            #[cfg(not(feature = "xxhash"))]
+            HashKind::Xxh3 => {
+                Err("state.hash is xxh3, but ff was built without the xxhash feature".to_string())
+            }
+        }
+    }
+}
+
+/// Which checksum tells whether a model changed, set by `state.checksum` in the project
+/// configuration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumMode {
+    /// Any edit to the file counts, formatting included
+    #[default]
+    Raw,
+    /// Only edits to the SQL's tokens count
+    Normalized,
+}
+
+impl ChecksumMode {
+    /// Checksum of `sql`, written in `dialect_name`, as this mode compares it
+    pub fn checksum(self, sql: &str, dialect_name: &str, hash: &dyn HashAlgorithm) -> String {
+        match self {
+            ChecksumMode::Raw => raw(sql, hash),
+            ChecksumMode::Normalized => normalized(sql, dialect_name, hash),
+        }
+    }
+}
+
+/// Digest of the exact text
+pub fn raw(content: &str, hash: &dyn HashAlgorithm) -> String {
+    hash.hash(content.as_bytes())
+}
+
+/// Digest of the text's tokens, without whitespace or comments
+pub fn normalized(content: &str, dialect_name: &str, hash: &dyn HashAlgorithm) -> String {
+    raw(&normalized_text(content, dialect_name), hash)
+}
+
+fn normalized_text(content: &str, dialect_name: &str) -> String {
+    let dialect: &dyn Dialect = match SqlDialect::from_name(dialect_name) {
+        Some(SqlDialect::BigQuery) => &BigQueryDialect {},
+        _ => &DuckDbDialect {},
+    };
+    match Tokenizer::new(dialect, content).tokenize() {
+        Ok(tokens) => tokens
+            .into_iter()
+            .filter_map(|token| match token {
+                // Comments are whitespace tokens
+                Token::Whitespace(_) | Token::EOF => None,
+                Token::Word(word)
+                    if word.quote_style.is_none() && word.keyword != Keyword::NoKeyword =>
+                {
+                    Some(word.value.to_lowercase())
+                }
+                other => Some(other.to_string()),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Err(_) => content.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_algorithms() {
+        let sha256 = HashKind::Sha256.algorithm().unwrap();
+        assert_eq!(
+            sha256.hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(sha256.hash_parts(&["ab", "c"]), sha256.hash(b"ab\0c\0"));
+        assert_ne!(
+            sha256.hash_parts(&["ab", "c"]),
+            sha256.hash_parts(&["a", "bc"])
+        );
+
+        #[cfg(feature = "xxhash")]
+        {
+            let xxh3 = HashKind::Xxh3.algorithm().unwrap();
+            assert_eq!(xxh3.hash(b"").len(), 32);
+            assert_ne!(xxh3.hash(b"a"), xxh3.hash(b"b"));
+        }
+        #[cfg(not(feature = "xxhash"))]
+        assert!(HashKind::Xxh3.algorithm().is_err());
+    }
+
+    #[test]
+    fn test_normalized_ignores_formatting() {
+        let original = "SELECT id, name FROM {{ ref('customers') }} WHERE active";
+        let reformatted = "-- Active customers\nselect\n    id,\n    name /* display name */\nfrom {{ ref('customers') }}\nwhere active\n";
+        let hash = &Sha256Hash;
+        assert_ne!(raw(original, hash), raw(reformatted, hash));
+        assert_eq!(
+            normalized(original, "duckdb", hash),
+            normalized(reformatted, "duckdb", hash)
+        );
+
+        assert_ne!(
+            normalized(original, "duckdb", hash),
+            normalized(
+                "SELECT id, name FROM {{ ref('customers') }} WHERE NOT active",
+                "duckdb",
+                hash
+            )
+        );
+        assert_ne!(
+            normalized("SELECT 'a  b'", "duckdb", hash),
+            normalized("SELECT 'a b'", "duckdb", hash)
+        );
+        assert_ne!(
+            normalized("SELECT \"Name\" FROM t", "duckdb", hash),
+            normalized("SELECT \"name\" FROM t", "duckdb", hash)
+        );
+        assert_eq!(
+            normalized("SELECT 'unterminated\n  FROM t", "duckdb", hash),
+            normalized("SELECT 'unterminated FROM t", "duckdb", hash)
+        );
+        assert_eq!(
+            ChecksumMode::Raw.checksum(original, "duckdb", hash),
+            raw(original, hash)
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Which downstream models use each column of a model, source or seed
+//!
+//! A model uses a column when its lineage traces an output column back to it, when it
+//! selects `*` from the node, or when it names the column anywhere else in its SQL (filters,
+//! joins, grouping), which lineage does not follow. Only models reading the node directly
+//! count: use further downstream goes through their own columns.
+
+use anyhow::{bail, Context, Result};
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::lineage::{self, ColumnLineage};
+use super::nodes::Node;
+use super::sql_model::{SqlModel, SqlModelCollection};
+
+/// Synthetic names lineage gives to unnamed expressions
+const SYNTHETIC_COLUMN_PREFIX: &str = "_col";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnUsage {
+    pub column: String,
+    /// Unique ids of the models using the column
+    pub used_by: BTreeSet<String>,
+    /// Whether the column is declared for the node or produced by it, rather than only seen
+    /// in a downstream model
+    pub declared: bool,
+}
+
+/// Usage of every column of node `node_id` by the models that read it directly, ordered by
+/// column name
+pub fn column_usage(collection: &SqlModelCollection, node_id: &str) -> Result<Vec<ColumnUsage>> {
+    let Some(node) = collection.get_node(node_id) else {
+        bail!("Node '{}' not found", node_id);
+    };
+    let relation = node_relation_name(node)?;
+
+    let mut usage: BTreeMap<String, ColumnUsage> = BTreeMap::new();
+    for column in node_columns(node)? {
+        usage.insert(
+            column.to_lowercase(),
+            ColumnUsage {
+                column,
+                used_by: BTreeSet::new(),
+                declared: true,
+            },
+        );
+    }
+
+    let readers = collection.models().filter(|model| {
+        collection
+            .node_dependencies(&model.unique_id)
+            .contains(node_id)
+    });
+    for reader in readers {
+        let sql = reader.compiled_sql.as_deref().unwrap_or(&reader.raw_sql);
+        let lineage = lineage::extract_column_lineage(sql).unwrap_or_default();
+        let (columns, wildcard) = columns_read_from(&lineage, relation);
+
+        if wildcard {
+            for entry in usage.values_mut() {
+                entry.used_by.insert(reader.unique_id.clone());
+            }
+        }
+        for column in columns {
+            usage
+                .entry(column.to_lowercase())
+                .or_insert_with(|| ColumnUsage {
+                    column,
+                    used_by: BTreeSet::new(),
+                    declared: false,
+                })
+                .used_by
+                .insert(reader.unique_id.clone());
+        }
+
+        let words = sql_words(sql)?;
+        for (name, entry) in usage.iter_mut() {
+            if words.contains(name) {
+                entry.used_by.insert(reader.unique_id.clone());
+            }
+        }
+    }
+
+    Ok(usage.into_values().collect())
+}
+
+/// Unqualified name model SQL uses for the node's relation
+fn node_relation_name(node: &Node) -> Result<&str> {
+    match node {
+        Node::Model(model) | Node::Snapshot(model) => Ok(model.relation_name()),
+        Node::Source(source) => Ok(&source.name),
+        Node::Seed(seed) => Ok(&seed.name),
+        Node::Test(_) | Node::Exposure(_) => bail!(
+            "{} is a {}; only models, snapshots, sources and seeds have columns",
+            node.unique_id(),
+            node.resource_type()
+        ),
+    }
+}
+
+/// Columns known for the node: YAML columns and, for models, the columns lineage finds in
+/// their output; for seeds, the CSV header
+fn node_columns(node: &Node) -> Result<Vec<String>> {
+    let columns = match node {
+        Node::Model(model) | Node::Snapshot(model) => model_columns(model),
+        Node::Source(source) => source.columns.clone(),
+        Node::Seed(seed) => seed.columns()?,
+        Node::Test(_) | Node::Exposure(_) => Vec::new(),
+    };
+    Ok(columns)
+}
+
+fn model_columns(model: &SqlModel) -> Vec<String> {
+    let mut columns: Vec<String> = model.columns.keys().cloned().collect();
+    let sql = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    for entry in lineage::extract_column_lineage(sql).unwrap_or_default() {
+        let column = entry.target.column;
+        if column != "*" && !column.starts_with(SYNTHETIC_COLUMN_PREFIX) {
+            columns.push(column);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    columns.retain(|column| seen.insert(column.to_lowercase()));
+    columns
+}
+
+/// Columns lineage attributes to `relation`, and whether any output selects `relation.*`
+fn columns_read_from(lineage: &[ColumnLineage], relation: &str) -> (Vec<String>, bool) {
+    let mut columns = Vec::new();
+    let mut wildcard = false;
+
+    let sources = lineage.iter().flat_map(|entry| &entry.sources);
+    for source in sources {
+        // Lineage keeps the schema the SQL names the table with
+        let table = source.table.as_deref().unwrap_or_default();
+        let unqualified = table.rsplit('.').next().unwrap_or(table);
+        if !unqualified.eq_ignore_ascii_case(relation) {
+            continue;
+        }
+        if source.column == "*" {
+            wildcard = true;
+        } else {
+            columns.push(source.column.clone());
+        }
+    }
+    (columns, wildcard)
+}
+
+/// Every word (identifier or keyword) in `sql`, lowercased; strings and comments excluded
+fn sql_words(sql: &str) -> Result<HashSet<String>> {
+    let dialect = DuckDbDialect {};
+    let tokens = Tokenizer::new(&dialect, sql)
+        .tokenize()
+        .context("Failed to tokenize model SQL")?;
+
+    Ok(tokens
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Word(word) => Some(word.value.to_lowercase()),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use crate::sql_engine::lineage::ColumnRef;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn source(table: &str, column: &str) -> ColumnRef {
+        ColumnRef::new(Some(table.to_string()), column.to_string())
+    }
+
+    #[test]
+    fn test_columns_read_from_relation() {
+        let lineage = vec![
+            ColumnLineage {
+                target: ColumnRef::new(None, "total".to_string()),
+                sources: vec![
+                    source("Transactions", "amount"),
+                    source("raw.transactions", "fee"),
+                    source("fx", "rate"),
+                ],
+                transformation: "expression".to_string(),
+            },
+            ColumnLineage {
+                target: ColumnRef::new(None, "*".to_string()),
+                sources: vec![source("fx", "*")],
+                transformation: "direct".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            columns_read_from(&lineage, "transactions"),
+            (vec!["amount".to_string(), "fee".to_string()], false)
+        );
+        assert_eq!(
+            columns_read_from(&lineage, "fx"),
+            (vec!["rate".to_string()], true)
+        );
+    }
+
+    #[test]
+    fn test_seed_columns_come_from_the_csv_header() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("countries.csv");
+        fs::write(&path, "code,name,legacy_id\nNL,Netherlands,7\n").unwrap();
+
+        let seed = Node::Seed(crate::sql_engine::nodes::Seed::new(path, "countries"));
+        assert_eq!(
+            node_columns(&seed).unwrap(),
+            vec!["code", "name", "legacy_id"]
+        );
+    }
+
+    #[test]
+    fn test_column_usage_of_a_seed() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let seeds_dir = root.join("seeds");
+        fs::create_dir(&seeds_dir).unwrap();
+        fs::write(seeds_dir.join("countries.csv"), "code,name,legacy_id\n").unwrap();
+
+        let model_dir = root.join("dim_country");
+        fs::create_dir(&model_dir).unwrap();
+        let file = model_dir.join("dim_country.sql");
+        fs::write(
+            &file,
+            "SELECT upper(name) AS country FROM public.countries WHERE code <> 'XX'",
+        )
+        .unwrap();
+        let mut model =
+            SqlModel::from_path(&file, root, "duckdb", &DuckDbDialect {}, &Sha256Hash).unwrap();
+        model.extract_dependencies().unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model);
+        collection.load_seeds(&seeds_dir).unwrap();
+        collection.build_dependency_graph();
+
+        let usage = column_usage(&collection, "seed.countries").unwrap();
+        let counts: Vec<(&str, usize)> = usage
+            .iter()
+            .map(|entry| (entry.column.as_str(), entry.used_by.len()))
+            .collect();
+        assert_eq!(counts, vec![("code", 1), ("legacy_id", 0), ("name", 1)]);
+    }
+}
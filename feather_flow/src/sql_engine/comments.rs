@@ -0,0 +1,153 @@
+//! Comment capture for SQL that is rebuilt from the AST
+//!
+//! sqlparser discards comments, so any SQL rendered from parsed statements loses the
+//! documentation written inline. Comments are captured from the source text with their byte
+//! offsets before parsing and re-attached to the rendered output. Compiled SQL is rendered
+//! textually and keeps its comments as written.
+
+/// Comment syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `-- ...` up to the end of the line
+    Line,
+    /// `/* ... */`
+    Block,
+}
+
+/// A comment and its position in the source SQL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlComment {
+    pub kind: CommentKind,
+    /// Byte offset of the first character of the comment marker
+    pub start: usize,
+    /// Byte offset just past the comment (the newline ending a line comment is excluded)
+    pub end: usize,
+    /// The comment exactly as written, including its markers
+    pub text: String,
+}
+
+/// Find every comment in `sql`, skipping comment markers inside string literals and quoted
+/// identifiers
+pub fn capture_comments(sql: &str) -> Vec<SqlComment> {
+    let bytes = sql.as_bytes();
+    let mut comments = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"') => {
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == quote && bytes.get(i + 1) == Some(&quote) {
+                        i += 2;
+                    } else if bytes[i] == quote {
+                        i += 1;
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                let end = sql[i..].find('\n').map_or(bytes.len(), |offset| i + offset);
+                let end = if sql[..end].ends_with('\r') {
+                    end - 1
+                } else {
+                    end
+                };
+                comments.push(comment(sql, CommentKind::Line, i, end));
+                i = end;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = sql[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |offset| i + 2 + offset + 2);
+                comments.push(comment(sql, CommentKind::Block, i, end));
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    comments
+}
+
+fn comment(sql: &str, kind: CommentKind, start: usize, end: usize) -> SqlComment {
+    SqlComment {
+        kind,
+        start,
+        end,
+        text: sql[start..end].to_string(),
+    }
+}
+
+/// Comments before the first SQL token, i.e. the file header
+pub fn header_comments(sql: &str, comments: &[SqlComment]) -> Vec<SqlComment> {
+    let mut header = Vec::new();
+    let mut position = 0;
+
+    for comment in comments {
+        if !sql[position..comment.start].trim().is_empty() {
+            break;
+        }
+        header.push(comment.clone());
+        position = comment.end;
+    }
+
+    header
+}
+
+/// Prefix `rendered` SQL with the header comments captured from `source`, one per line
+pub fn attach_header_comments(source: &str, rendered: &str) -> String {
+    let header = header_comments(source, &capture_comments(source));
+    if header.is_empty() {
+        return rendered.to_string();
+    }
+
+    let mut output = String::with_capacity(rendered.len() + source.len() / 4);
+    for comment in &header {
+        output.push_str(&comment.text);
+        output.push('\n');
+    }
+    output.push_str(rendered);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_comments_with_offsets() {
+        let sql = "-- header\n/* block\n comment */\nSELECT '-- not a comment', \"a/*b\" -- trailing\nFROM t";
+        let comments = capture_comments(sql);
+
+        assert_eq!(comments.len(), 3);
+        assert_eq!(comments[0].kind, CommentKind::Line);
+        assert_eq!(comments[0].text, "-- header");
+        assert_eq!(&sql[comments[0].start..comments[0].end], "-- header");
+        assert_eq!(comments[1].kind, CommentKind::Block);
+        assert_eq!(comments[1].text, "/* block\n comment */");
+        assert_eq!(comments[2].text, "-- trailing");
+    }
+
+    #[test]
+    fn test_header_comments_stop_at_first_token() {
+        let sql =
+            "\n-- Orders staged from raw\n-- Grain: one row per order\n\nSELECT 1 -- inline\n";
+        let header = header_comments(sql, &capture_comments(sql));
+
+        assert_eq!(header.len(), 2);
+        assert_eq!(header[1].text, "-- Grain: one row per order");
+    }
+
+    #[test]
+    fn test_attach_header_comments() {
+        let source = "/* Customer dimension */\n-- owner: analytics\nselect id   from   raw.customers -- inline";
+        assert_eq!(
+            attach_header_comments(source, "SELECT id FROM raw.customers"),
+            "/* Customer dimension */\n-- owner: analytics\nSELECT id FROM raw.customers"
+        );
+        assert_eq!(attach_header_comments("SELECT 1", "SELECT 1"), "SELECT 1");
+    }
+}
@@ -0,0 +1,793 @@
+//! Heuristic size estimates of models, for `ff report cost-estimate`
+//!
+//! Each SELECT's join structure is read from the AST. A join is risky when it is a cross
+//! join (explicit, comma-separated without a WHERE, or without an equality condition), or
+//! when it matches keys that neither side is known to be unique on and the SELECT does not
+//! aggregate. A key is unique when the model declares a `unique` test on the column, or when
+//! a CTE or subquery groups by it.
+//!
+//! Row estimates start from each input's row count in the last `ff build`, multiply across
+//! risky joins and take the larger side of safe ones. Row width comes from the model's
+//! declared column types.
+
+use sqlparser::ast::{
+    BinaryOperator, Expr, GroupByExpr, Join, JoinConstraint, JoinOperator, Query, Select,
+    SelectItem, SetExpr, Statement, TableFactor, TableWithJoins,
+};
+use std::collections::{HashMap, HashSet};
+
+use super::nodes::Node;
+use super::sql_model::{SqlModel, SqlModelCollection};
+use super::template::DEFAULT_SCHEMA;
+
+/// Functions that collapse rows; a SELECT using one without a window aggregates
+const AGGREGATE_FUNCTIONS: [&str; 14] = [
+    "count",
+    "sum",
+    "avg",
+    "min",
+    "max",
+    "any_value",
+    "array_agg",
+    "list",
+    "string_agg",
+    "listagg",
+    "median",
+    "arg_max",
+    "arg_min",
+    "approx_count_distinct",
+];
+
+/// Assumed bytes per value of variable-width types (strings, JSON, blobs)
+const VARIABLE_WIDTH_BYTES: u64 = 32;
+
+/// Assumed bytes per value of columns without a declared type
+const UNTYPED_BYTES: u64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinRisk {
+    CrossJoin,
+    ManyToMany,
+}
+
+/// A join likely to multiply rows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskyJoin {
+    pub risk: JoinRisk,
+    pub left: String,
+    pub right: String,
+    /// Columns matched on, for many-to-many joins
+    pub keys: Vec<String>,
+}
+
+impl RiskyJoin {
+    pub fn describe(&self) -> String {
+        match self.risk {
+            JoinRisk::CrossJoin => format!(
+                "cross join of {} with {}, without an equality condition",
+                self.left, self.right
+            ),
+            JoinRisk::ManyToMany => format!(
+                "many-to-many join of {} with {} on {}, without aggregation; neither side is known to be unique on it",
+                self.left,
+                self.right,
+                self.keys.join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostEstimate {
+    pub model_id: String,
+    /// Upper bound on the model's rows, when the row counts of its inputs are known
+    pub rows: Option<u64>,
+    /// Bytes per row from declared column types; `None` when no column declares one
+    pub row_bytes: Option<u64>,
+    pub risky_joins: Vec<RiskyJoin>,
+}
+
+impl CostEstimate {
+    pub fn bytes(&self) -> Option<u64> {
+        Some(self.rows?.saturating_mul(self.row_bytes?))
+    }
+}
+
+/// Estimate every model and snapshot of `collection`, ordered by unique id. `row_counts`
+/// maps unique ids of models, seeds and sources to their current row counts.
+pub fn estimate_costs(
+    collection: &SqlModelCollection,
+    row_counts: &HashMap<String, u64>,
+) -> Vec<CostEstimate> {
+    let relations = relation_index(collection, row_counts);
+    let mut estimates: Vec<CostEstimate> = collection
+        .models()
+        .map(|model| estimate_model(model, &relations))
+        .collect();
+    estimates.sort_by(|a, b| a.model_id.cmp(&b.model_id));
+    estimates
+}
+
+fn estimate_model(model: &SqlModel, relations: &HashMap<String, RelationInfo>) -> CostEstimate {
+    let mut estimator = Estimator {
+        relations,
+        risky_joins: Vec::new(),
+    };
+    let rows = model
+        .ast
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Query(query) => estimator.query(query, &HashMap::new()).rows,
+            _ => None,
+        })
+        .max();
+
+    CostEstimate {
+        model_id: model.unique_id.clone(),
+        rows,
+        row_bytes: row_bytes(model),
+        risky_joins: estimator.risky_joins,
+    }
+}
+
+/// Bytes per row from the declared column types, if any column declares one
+fn row_bytes(model: &SqlModel) -> Option<u64> {
+    if model
+        .columns
+        .values()
+        .all(|column| column.data_type.is_none())
+    {
+        return None;
+    }
+    Some(
+        model
+            .columns
+            .values()
+            .map(|column| {
+                column
+                    .data_type
+                    .as_deref()
+                    .map_or(UNTYPED_BYTES, type_bytes)
+            })
+            .sum(),
+    )
+}
+
+fn type_bytes(data_type: &str) -> u64 {
+    let lowered = data_type.to_lowercase();
+    let base = lowered.split('(').next().unwrap_or_default().trim();
+    match base {
+        "boolean" | "bool" | "tinyint" | "utinyint" => 1,
+        "smallint" | "usmallint" | "int2" => 2,
+        "integer" | "int" | "int4" | "uinteger" | "date" | "real" | "float" | "float4" => 4,
+        "hugeint" | "uhugeint" | "uuid" | "interval" => 16,
+        "varchar" | "text" | "string" | "char" | "bpchar" | "json" | "blob" | "bytes" => {
+            VARIABLE_WIDTH_BYTES
+        }
+        // bigint, double, timestamps, decimals, int64, float64
+        _ => 8,
+    }
+}
+
+/// What is known about a relation a SELECT reads
+#[derive(Debug, Clone, Default)]
+struct RelationInfo {
+    rows: Option<u64>,
+    /// Lowercased columns no two rows share a value of
+    unique: HashSet<String>,
+}
+
+/// Models, snapshots, seeds and sources by every lowercased name model SQL may use for them
+fn relation_index(
+    collection: &SqlModelCollection,
+    row_counts: &HashMap<String, u64>,
+) -> HashMap<String, RelationInfo> {
+    let mut index = HashMap::new();
+    for node in collection.nodes() {
+        let (names, unique) = match node {
+            Node::Model(model) | Node::Snapshot(model) => {
+                let schema = model.schema.as_deref().unwrap_or(DEFAULT_SCHEMA);
+                let unique = model
+                    .columns
+                    .values()
+                    .filter(|column| column.tests.iter().any(|test| test == "unique"))
+                    .map(|column| column.name.to_lowercase())
+                    .collect();
+                (
+                    vec![
+                        format!("{}.{}", schema, model.relation_name()),
+                        model.qualified_relation(),
+                    ],
+                    unique,
+                )
+            }
+            Node::Seed(seed) => (
+                vec![
+                    seed.name.clone(),
+                    format!("{}.{}", DEFAULT_SCHEMA, seed.name),
+                    seed.relation(),
+                ],
+                HashSet::new(),
+            ),
+            Node::Source(source) => (vec![source.relation.clone()], HashSet::new()),
+            Node::Test(_) | Node::Exposure(_) => continue,
+        };
+
+        let info = RelationInfo {
+            rows: row_counts.get(node.unique_id()).copied(),
+            unique,
+        };
+        for name in names {
+            index.insert(name.to_lowercase(), info.clone());
+        }
+    }
+    index
+}
+
+/// One or more joined relations of a FROM clause
+struct Side {
+    /// Relations as written, for messages
+    label: String,
+    /// Names columns of these relations can be qualified with
+    qualifiers: HashSet<String>,
+    rows: Option<u64>,
+    /// (qualifier, column) pairs the joined rows are still unique on
+    unique: HashSet<(String, String)>,
+}
+
+impl Side {
+    fn is_unique_on(&self, column: &ColumnRef) -> bool {
+        self.unique.iter().any(|(qualifier, name)| {
+            *name == column.name
+                && column
+                    .qualifier
+                    .as_ref()
+                    .is_none_or(|wanted| wanted == qualifier)
+        })
+    }
+
+    fn owns(&self, column: &ColumnRef) -> bool {
+        column
+            .qualifier
+            .as_ref()
+            .is_some_and(|qualifier| self.qualifiers.contains(qualifier))
+    }
+}
+
+/// A column in a join condition, lowercased
+#[derive(Debug, Clone, PartialEq)]
+struct ColumnRef {
+    qualifier: Option<String>,
+    name: String,
+}
+
+impl ColumnRef {
+    fn from_expr(expr: &Expr) -> Option<Self> {
+        match expr {
+            Expr::Identifier(ident) => Some(Self {
+                qualifier: None,
+                name: ident.value.to_lowercase(),
+            }),
+            Expr::CompoundIdentifier(idents) => {
+                let (name, qualifier) = idents.split_last()?;
+                Some(Self {
+                    qualifier: (!qualifier.is_empty()).then(|| {
+                        qualifier
+                            .iter()
+                            .map(|ident| ident.value.to_lowercase())
+                            .collect::<Vec<_>>()
+                            .join(".")
+                    }),
+                    name: name.value.to_lowercase(),
+                })
+            }
+            Expr::Nested(inner) => Self::from_expr(inner),
+            _ => None,
+        }
+    }
+}
+
+enum Condition<'a> {
+    Cross,
+    On(&'a Expr),
+    Using(Vec<String>),
+    /// Semi and anti joins, which never add rows to the left side
+    Filter,
+    /// NATURAL joins, whose keys are not spelled out
+    Unknown,
+}
+
+/// How many rows a join produces
+enum Fanout {
+    /// No more than the larger side; `left_unique`/`right_unique` say whether each side is
+    /// unique on the join keys
+    Bounded {
+        left_unique: bool,
+        right_unique: bool,
+    },
+    /// Up to the product of both sides
+    Product,
+}
+
+struct Estimator<'a> {
+    relations: &'a HashMap<String, RelationInfo>,
+    risky_joins: Vec<RiskyJoin>,
+}
+
+impl Estimator<'_> {
+    /// `ctes` holds what is known of the CTEs in scope
+    fn query(&mut self, query: &Query, ctes: &HashMap<String, RelationInfo>) -> RelationInfo {
+        let mut scope = ctes.clone();
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                let info = self.query(&cte.query, &scope);
+                scope.insert(cte.alias.name.value.to_lowercase(), info);
+            }
+        }
+        self.set_expr(&query.body, &scope)
+    }
+
+    fn set_expr(&mut self, body: &SetExpr, scope: &HashMap<String, RelationInfo>) -> RelationInfo {
+        match body {
+            SetExpr::Select(select) => self.select(select, scope),
+            SetExpr::Query(query) => self.query(query, scope),
+            SetExpr::SetOperation { left, right, .. } => {
+                let left = self.set_expr(left, scope);
+                let right = self.set_expr(right, scope);
+                RelationInfo {
+                    rows: left.rows.zip(right.rows).map(|(l, r)| l.saturating_add(r)),
+                    unique: HashSet::new(),
+                }
+            }
+            _ => RelationInfo::default(),
+        }
+    }
+
+    fn select(&mut self, select: &Select, scope: &HashMap<String, RelationInfo>) -> RelationInfo {
+        let aggregated = is_aggregated(select);
+        let mut combined: Option<Side> = None;
+        for item in &select.from {
+            let side = self.table_with_joins(item, aggregated, scope);
+            combined = Some(match combined {
+                None => side,
+                Some(left) => {
+                    let condition = select
+                        .selection
+                        .as_ref()
+                        .map_or(Condition::Cross, Condition::On);
+                    self.combine(left, side, condition, aggregated)
+                }
+            });
+        }
+
+        let Some(side) = combined else {
+            // SELECT without FROM
+            return RelationInfo {
+                rows: Some(1),
+                unique: HashSet::new(),
+            };
+        };
+        match group_by_columns(select) {
+            Some(columns) => RelationInfo {
+                rows: side.rows,
+                unique: if columns.len() == 1 {
+                    columns.into_iter().collect()
+                } else {
+                    HashSet::new()
+                },
+            },
+            None if has_aggregate(select) => RelationInfo {
+                rows: Some(1),
+                unique: HashSet::new(),
+            },
+            None => RelationInfo {
+                rows: side.rows,
+                unique: side.unique.into_iter().map(|(_, column)| column).collect(),
+            },
+        }
+    }
+
+    fn table_with_joins(
+        &mut self,
+        item: &TableWithJoins,
+        aggregated: bool,
+        scope: &HashMap<String, RelationInfo>,
+    ) -> Side {
+        let mut side = self.factor(&item.relation, aggregated, scope);
+        for join in &item.joins {
+            let right = self.factor(&join.relation, aggregated, scope);
+            side = self.combine(side, right, join_condition(join), aggregated);
+        }
+        side
+    }
+
+    fn factor(
+        &mut self,
+        factor: &TableFactor,
+        aggregated: bool,
+        scope: &HashMap<String, RelationInfo>,
+    ) -> Side {
+        let (label, qualifiers, info) = match factor {
+            TableFactor::Table { name, alias, .. } => {
+                let parts: Vec<String> = name.0.iter().map(|ident| ident.value.clone()).collect();
+                let full = parts.join(".").to_lowercase();
+                let info = match scope.get(&full) {
+                    Some(cte) if parts.len() == 1 => cte.clone(),
+                    _ => self.relations.get(&full).cloned().unwrap_or_default(),
+                };
+                let mut qualifiers = HashSet::from([full]);
+                if let Some(last) = parts.last() {
+                    qualifiers.insert(last.to_lowercase());
+                }
+                if let Some(alias) = alias {
+                    qualifiers = HashSet::from([alias.name.value.to_lowercase()]);
+                }
+                (parts.join("."), qualifiers, info)
+            }
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => {
+                let info = self.query(subquery, scope);
+                let alias = alias.as_ref().map(|alias| alias.name.value.clone());
+                let label = alias.as_ref().map_or("a subquery".to_string(), |alias| {
+                    format!("subquery {}", alias)
+                });
+                (
+                    label,
+                    alias.map(|a| a.to_lowercase()).into_iter().collect(),
+                    info,
+                )
+            }
+            TableFactor::NestedJoin {
+                table_with_joins,
+                alias,
+            } => {
+                let mut side = self.table_with_joins(table_with_joins, aggregated, scope);
+                if let Some(alias) = alias {
+                    side.qualifiers.insert(alias.name.value.to_lowercase());
+                }
+                return side;
+            }
+            _ => (
+                "a table function".to_string(),
+                factor_alias(factor).into_iter().collect(),
+                RelationInfo::default(),
+            ),
+        };
+
+        let unique = qualifiers
+            .iter()
+            .flat_map(|qualifier| {
+                info.unique
+                    .iter()
+                    .map(move |column| (qualifier.clone(), column.clone()))
+            })
+            .collect();
+        Side {
+            label,
+            qualifiers,
+            rows: info.rows,
+            unique,
+        }
+    }
+
+    /// Join `right` to `left`, recording the join when it is likely to multiply rows
+    fn combine(&mut self, left: Side, right: Side, condition: Condition, aggregated: bool) -> Side {
+        let keys = match &condition {
+            Condition::Cross => Vec::new(),
+            Condition::On(expr) => join_keys(expr, &left, &right),
+            Condition::Using(columns) => columns
+                .iter()
+                .map(|column| {
+                    let column = ColumnRef {
+                        qualifier: None,
+                        name: column.to_lowercase(),
+                    };
+                    (column.clone(), column)
+                })
+                .collect(),
+            Condition::Filter => return left,
+            Condition::Unknown => {
+                return merge(
+                    left,
+                    right,
+                    Fanout::Bounded {
+                        left_unique: false,
+                        right_unique: false,
+                    },
+                )
+            }
+        };
+
+        if keys.is_empty() {
+            self.risky_joins.push(RiskyJoin {
+                risk: JoinRisk::CrossJoin,
+                left: left.label.clone(),
+                right: right.label.clone(),
+                keys: Vec::new(),
+            });
+            return merge(left, right, Fanout::Product);
+        }
+
+        let left_unique = keys.iter().any(|(l, _)| left.is_unique_on(l));
+        let right_unique = keys.iter().any(|(_, r)| right.is_unique_on(r));
+        if left_unique || right_unique {
+            return merge(
+                left,
+                right,
+                Fanout::Bounded {
+                    left_unique,
+                    right_unique,
+                },
+            );
+        }
+
+        if !aggregated {
+            let mut names: Vec<String> = keys.iter().map(|(l, _)| l.name.clone()).collect();
+            names.dedup();
+            self.risky_joins.push(RiskyJoin {
+                risk: JoinRisk::ManyToMany,
+                left: left.label.clone(),
+                right: right.label.clone(),
+                keys: names,
+            });
+        }
+        merge(left, right, Fanout::Product)
+    }
+}
+
+fn merge(left: Side, right: Side, fanout: Fanout) -> Side {
+    let (rows, unique) = match fanout {
+        Fanout::Bounded {
+            left_unique,
+            right_unique,
+        } => {
+            // Each row of one side matches at most one row of a side unique on the keys, so
+            // the other side's unique columns stay unique
+            let unique = match (left_unique, right_unique) {
+                (true, true) => left.unique.union(&right.unique).cloned().collect(),
+                (false, true) => left.unique,
+                (true, false) => right.unique,
+                (false, false) => HashSet::new(),
+            };
+            (left.rows.zip(right.rows).map(|(l, r)| l.max(r)), unique)
+        }
+        Fanout::Product => (
+            left.rows.zip(right.rows).map(|(l, r)| l.saturating_mul(r)),
+            HashSet::new(),
+        ),
+    };
+
+    Side {
+        label: format!("{}, {}", left.label, right.label),
+        qualifiers: left.qualifiers.union(&right.qualifiers).cloned().collect(),
+        rows,
+        unique,
+    }
+}
+
+fn join_condition(join: &Join) -> Condition<'_> {
+    let constraint = match &join.join_operator {
+        JoinOperator::CrossJoin => return Condition::Cross,
+        JoinOperator::Inner(constraint)
+        | JoinOperator::LeftOuter(constraint)
+        | JoinOperator::RightOuter(constraint)
+        | JoinOperator::FullOuter(constraint)
+        | JoinOperator::AsOf { constraint, .. } => constraint,
+        JoinOperator::LeftSemi(_)
+        | JoinOperator::RightSemi(_)
+        | JoinOperator::LeftAnti(_)
+        | JoinOperator::RightAnti(_) => return Condition::Filter,
+        _ => return Condition::Unknown,
+    };
+    match constraint {
+        JoinConstraint::On(expr) => Condition::On(expr),
+        // USING takes object names; a join column is the last part of each
+        JoinConstraint::Using(columns) => Condition::Using(
+            columns
+                .iter()
+                .filter_map(|name| name.0.last())
+                .map(|ident| ident.value.clone())
+                .collect(),
+        ),
+        JoinConstraint::None => Condition::Cross,
+        JoinConstraint::Natural => Condition::Unknown,
+    }
+}
+
+/// Column pairs an ON or WHERE expression equates across the two sides, left column first.
+/// Unqualified columns are assumed to belong to whichever side the other column does not.
+fn join_keys(expr: &Expr, left: &Side, right: &Side) -> Vec<(ColumnRef, ColumnRef)> {
+    let mut keys = Vec::new();
+    let mut pending = vec![expr];
+    while let Some(expr) = pending.pop() {
+        match expr {
+            Expr::BinaryOp {
+                left: a,
+                op: BinaryOperator::And,
+                right: b,
+            } => {
+                pending.push(a);
+                pending.push(b);
+            }
+            Expr::Nested(inner) => pending.push(inner),
+            Expr::BinaryOp {
+                left: a,
+                op: BinaryOperator::Eq,
+                right: b,
+            } => {
+                let (Some(a), Some(b)) = (ColumnRef::from_expr(a), ColumnRef::from_expr(b)) else {
+                    continue;
+                };
+                // Both columns of the same side: a filter, not a join key
+                if (left.owns(&a) && left.owns(&b)) || (right.owns(&a) && right.owns(&b)) {
+                    continue;
+                }
+                if left.owns(&a) || right.owns(&b) {
+                    keys.push((a, b));
+                } else if left.owns(&b) || right.owns(&a) {
+                    keys.push((b, a));
+                } else if a.qualifier.is_none() && b.qualifier.is_none() {
+                    keys.push((a, b));
+                }
+            }
+            _ => {}
+        }
+    }
+    keys
+}
+
+fn factor_alias(factor: &TableFactor) -> Option<String> {
+    let alias = match factor {
+        TableFactor::TableFunction { alias, .. }
+        | TableFactor::Function { alias, .. }
+        | TableFactor::UNNEST { alias, .. } => alias.as_ref(),
+        _ => None,
+    };
+    alias.map(|alias| alias.name.value.to_lowercase())
+}
+
+fn is_aggregated(select: &Select) -> bool {
+    select.distinct.is_some() || group_by_columns(select).is_some() || has_aggregate(select)
+}
+
+/// Whether the output calls an aggregate function outside a window
+fn has_aggregate(select: &Select) -> bool {
+    select.projection.iter().any(|item| match item {
+        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+            is_aggregate_call(expr)
+        }
+        _ => false,
+    })
+}
+
+fn is_aggregate_call(expr: &Expr) -> bool {
+    match expr {
+        Expr::Function(function) => {
+            function.over.is_none()
+                && function.name.0.last().is_some_and(|name| {
+                    AGGREGATE_FUNCTIONS.contains(&name.value.to_lowercase().as_str())
+                })
+        }
+        Expr::Nested(inner) | Expr::Cast { expr: inner, .. } => is_aggregate_call(inner),
+        Expr::BinaryOp { left, right, .. } => is_aggregate_call(left) || is_aggregate_call(right),
+        _ => false,
+    }
+}
+
+/// Lowercased columns of a GROUP BY (empty for `GROUP BY ALL` or expressions), or `None`
+/// without one
+pub(super) fn group_by_columns(select: &Select) -> Option<Vec<String>> {
+    match &select.group_by {
+        GroupByExpr::Expressions(exprs, _) if !exprs.is_empty() => {
+            let columns: Vec<String> = exprs
+                .iter()
+                .filter_map(ColumnRef::from_expr)
+                .map(|column| column.name)
+                .collect();
+            // An expression key leaves the grouped columns unknown
+            Some(if columns.len() == exprs.len() {
+                columns
+            } else {
+                Vec::new()
+            })
+        }
+        GroupByExpr::All(_) => Some(Vec::new()),
+        GroupByExpr::Expressions(..) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::fixtures::write_model;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_type_bytes() {
+        assert_eq!(type_bytes("BIGINT"), 8);
+        assert_eq!(type_bytes("integer"), 4);
+        assert_eq!(type_bytes("VARCHAR(255)"), VARIABLE_WIDTH_BYTES);
+        assert_eq!(type_bytes("decimal(18, 2)"), 8);
+        assert_eq!(type_bytes("boolean"), 1);
+    }
+
+    #[test]
+    fn test_flags_cross_and_many_to_many_joins() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(write_model(
+            root,
+            "customers",
+            "SELECT id, name FROM raw.customers",
+            Some("version: 2\nmodels:\n  - name: customers\n    columns:\n      - name: id\n        data_type: bigint\n        tests: [unique]\n"),
+        ));
+        collection.add_model(write_model(
+            root,
+            "orders",
+            "SELECT id, customer_id FROM raw.orders",
+            None,
+        ));
+        collection.add_model(write_model(
+            root,
+            "payments",
+            "SELECT id, customer_id FROM raw.payments",
+            None,
+        ));
+        collection.add_model(write_model(
+            root,
+            "order_customers",
+            "SELECT o.id, c.name FROM public.orders o JOIN public.customers c ON o.customer_id = c.id",
+            None,
+        ));
+        collection.add_model(write_model(
+            root,
+            "order_payments",
+            "SELECT o.id, p.id AS payment_id FROM public.orders o JOIN public.payments p ON o.customer_id = p.customer_id",
+            Some("version: 2\nmodels:\n  - name: order_payments\n    columns:\n      - name: id\n        data_type: bigint\n      - name: payment_id\n        data_type: bigint\n"),
+        ));
+        collection.add_model(write_model(
+            root,
+            "payments_per_customer",
+            "WITH totals AS (SELECT customer_id, count(*) AS n FROM public.payments GROUP BY customer_id) \
+             SELECT o.id, t.n FROM public.orders o JOIN totals t ON o.customer_id = t.customer_id \
+             CROSS JOIN raw.calendar",
+            None,
+        ));
+        collection.build_dependency_graph();
+
+        let row_counts = HashMap::from([
+            ("model.orders.orders".to_string(), 1_000),
+            ("model.payments.payments".to_string(), 2_000),
+            ("model.customers.customers".to_string(), 100),
+        ]);
+        let estimates: HashMap<String, CostEstimate> = estimate_costs(&collection, &row_counts)
+            .into_iter()
+            .map(|estimate| (estimate.model_id.clone(), estimate))
+            .collect();
+
+        let joined = &estimates["model.order_customers.order_customers"];
+        assert!(joined.risky_joins.is_empty());
+        assert_eq!(joined.rows, Some(1_000));
+
+        let exploded = &estimates["model.order_payments.order_payments"];
+        assert_eq!(
+            exploded.risky_joins,
+            vec![RiskyJoin {
+                risk: JoinRisk::ManyToMany,
+                left: "public.orders".to_string(),
+                right: "public.payments".to_string(),
+                keys: vec!["customer_id".to_string()],
+            }]
+        );
+        assert_eq!(exploded.rows, Some(2_000_000));
+        assert_eq!(exploded.bytes(), Some(32_000_000));
+
+        // Grouping makes the CTE unique on customer_id; the calendar has no row count
+        let grouped = &estimates["model.payments_per_customer.payments_per_customer"];
+        assert_eq!(grouped.risky_joins.len(), 1);
+        assert_eq!(grouped.risky_joins[0].risk, JoinRisk::CrossJoin);
+        assert_eq!(grouped.rows, None);
+    }
+}
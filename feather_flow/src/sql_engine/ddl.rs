@@ -0,0 +1,121 @@
+//! DDL creating a model's relation in a given SQL dialect, for `ff compile`
+//!
+//! Statements create the relation from scratch: incremental models get the `CREATE TABLE`
+//! of their first run. For BigQuery, relations are quoted with backticks and tables carry
+//! the model's `partition_by` and `cluster_by` (see [`crate::config::partitioning`]).
+
+use crate::config::materializations::MaterializationRegistry;
+use crate::config::SqlDialect;
+
+use super::nodes::SNAPSHOT_MATERIALIZATION;
+use super::sql_model::SqlModel;
+
+/// What `ff compile` emits for a model
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ddl {
+    Statement(String),
+    /// Nothing to emit, and why
+    Skipped(String),
+}
+
+pub fn create_statement(
+    model: &SqlModel,
+    dialect: SqlDialect,
+    registry: &MaterializationRegistry,
+) -> Ddl {
+    let qualified = model.qualified_relation();
+    let this = match dialect {
+        SqlDialect::DuckDb => qualified,
+        SqlDialect::BigQuery => format!("`{}`", qualified),
+    };
+    let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+    let sql = compiled.trim().trim_end_matches(';').trim_end();
+
+    let statement = match model.materialized.as_deref().unwrap_or("view") {
+        "ephemeral" => return Ddl::Skipped("ephemeral; nothing to create".to_string()),
+        SNAPSHOT_MATERIALIZATION => {
+            return Ddl::Skipped("snapshots are captured by ff build".to_string())
+        }
+        "view" => format!("CREATE OR REPLACE VIEW {} AS\n{}", this, sql),
+        "table" => format!(
+            "CREATE OR REPLACE TABLE {}{}\nAS\n{}",
+            this,
+            table_options(model, dialect),
+            sql
+        ),
+        "incremental" => format!(
+            "CREATE TABLE IF NOT EXISTS {}{}\nAS\n{}",
+            this,
+            table_options(model, dialect),
+            sql
+        ),
+        custom => match registry.get_custom(custom) {
+            Some(recipe) => recipe.render(&this, sql),
+            None => return Ddl::Skipped(format!("unknown materialization '{}'", custom)),
+        },
+    };
+    Ddl::Statement(statement)
+}
+
+/// `PARTITION BY` and `CLUSTER BY` clauses, each on its own line
+fn table_options(model: &SqlModel, dialect: SqlDialect) -> String {
+    if dialect != SqlDialect::BigQuery {
+        return String::new();
+    }
+
+    let mut options = String::new();
+    if let Some(partition_by) = &model.partition_by {
+        options.push_str(&format!("\nPARTITION BY {}", partition_by.to_sql()));
+    }
+    if !model.cluster_by.is_empty() {
+        options.push_str(&format!("\nCLUSTER BY {}", model.cluster_by.join(", ")));
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use sqlparser::dialect::BigQueryDialect;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bigquery_table_with_partitioning_and_clustering() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("fct_events");
+        fs::create_dir(&model_dir).unwrap();
+        let file = model_dir.join("fct_events.sql");
+        fs::write(&file, "SELECT event_at, customer_id FROM raw.events;\n").unwrap();
+        fs::write(
+            model_dir.join("fct_events.yml"),
+            "version: 2\nmodels:\n  - name: fct_events\n    schema_name: analytics\n    config:\n      materialized: table\n      partition_by: {field: event_at, data_type: timestamp}\n      cluster_by: customer_id\n",
+        )
+        .unwrap();
+        let model = SqlModel::from_path(
+            &file,
+            temp_dir.path(),
+            "bigquery",
+            &BigQueryDialect {},
+            &Sha256Hash,
+        )
+        .unwrap();
+        let registry = MaterializationRegistry::default();
+
+        assert_eq!(
+            create_statement(&model, SqlDialect::BigQuery, &registry),
+            Ddl::Statement(
+                "CREATE OR REPLACE TABLE `analytics.fct_events`\nPARTITION BY TIMESTAMP_TRUNC(event_at, DAY)\nCLUSTER BY customer_id\nAS\nSELECT event_at, customer_id FROM raw.events"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            create_statement(&model, SqlDialect::DuckDb, &registry),
+            Ddl::Statement(
+                "CREATE OR REPLACE TABLE analytics.fct_events\nAS\nSELECT event_at, customer_id FROM raw.events"
+                    .to_string()
+            )
+        );
+    }
+}
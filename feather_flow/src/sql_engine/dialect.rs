@@ -0,0 +1,236 @@
+//! DuckDB syntax that sqlparser cannot parse, rewritten into equivalent parseable SQL
+//!
+//! The rewrite only feeds the parser; compiled SQL keeps the original text.
+//!
+//! - `ASOF [LEFT] JOIN t ON ...` becomes `[LEFT] JOIN t ON ...` (sqlparser only understands
+//!   Snowflake's `MATCH_CONDITION` form). Relations and join conditions are unchanged, which
+//!   is all dependency extraction and lineage need.
+//! - List comprehensions `[expr FOR x IN list IF cond]` become
+//!   `list_transform(list_filter(list, x -> cond), x -> expr)`, DuckDB's own equivalent.
+//!
+//! `QUALIFY`, struct access (`col.field`, `col['field']`) and list literals are parsed
+//! natively by the DuckDB dialect.
+
+/// Rewrite DuckDB-only constructs so the whole statement can be parsed
+pub fn normalize_for_parser(sql: &str) -> String {
+    let sql = remove_asof_keyword(sql);
+    rewrite_list_comprehensions(&sql)
+}
+
+/// Byte-level mask: true where the byte is SQL code rather than a string, quoted identifier
+/// or comment
+pub(crate) fn code_mask(sql: &str) -> Vec<bool> {
+    let bytes = sql.as_bytes();
+    let mut mask = vec![true; bytes.len()];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let end = match bytes[i] {
+            quote @ (b'\'' | b'"') => {
+                let mut j = i + 1;
+                loop {
+                    match bytes.get(j) {
+                        None => break bytes.len(),
+                        // Doubled quotes are escapes
+                        Some(&b) if b == quote && bytes.get(j + 1) == Some(&quote) => j += 2,
+                        Some(&b) if b == quote => break j + 1,
+                        Some(_) => j += 1,
+                    }
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                sql[i..].find('\n').map_or(bytes.len(), |offset| i + offset)
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => sql[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |offset| i + 2 + offset + 2),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        mask[i..end].iter_mut().for_each(|m| *m = false);
+        i = end;
+    }
+
+    mask
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Whether `keyword` appears as a whole word at byte offset `at`
+fn keyword_at(sql: &str, mask: &[bool], at: usize, keyword: &str) -> bool {
+    let bytes = sql.as_bytes();
+    let end = at + keyword.len();
+
+    end <= bytes.len()
+        && mask[at]
+        && sql.is_char_boundary(end)
+        && sql[at..end].eq_ignore_ascii_case(keyword)
+        && (at == 0 || !is_word_byte(bytes[at - 1]))
+        && (end == bytes.len() || !is_word_byte(bytes[end]))
+}
+
+/// First whole-word `keyword` at or after `from` that is outside strings, comments and any
+/// parentheses/brackets/braces opened after `from`
+fn find_top_level_keyword(sql: &str, mask: &[bool], from: usize, keyword: &str) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    let mut depth = 0usize;
+
+    for i in from..bytes.len() {
+        if !mask[i] {
+            continue;
+        }
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && keyword_at(sql, mask, i, keyword) => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn remove_asof_keyword(sql: &str) -> String {
+    let mask = code_mask(sql);
+    let mut result = String::with_capacity(sql.len());
+    let mut i = 0;
+
+    while i < sql.len() {
+        if keyword_at(sql, &mask, i, "asof") {
+            let after = i + "asof".len();
+            let next_word = after + (sql[after..].len() - sql[after..].trim_start().len());
+            if keyword_at(sql, &mask, next_word, "join")
+                || keyword_at(sql, &mask, next_word, "left")
+            {
+                i = next_word;
+                continue;
+            }
+        }
+
+        let c = sql[i..].chars().next().unwrap_or_default();
+        result.push(c);
+        i += c.len_utf8();
+    }
+
+    result
+}
+
+fn matching_bracket(sql: &str, mask: &[bool], open: usize) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    let mut depth = 0usize;
+
+    for i in open..bytes.len() {
+        if !mask[i] {
+            continue;
+        }
+        match bytes[i] {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn rewrite_list_comprehensions(sql: &str) -> String {
+    let mask = code_mask(sql);
+    let mut result = String::with_capacity(sql.len());
+    let mut i = 0;
+
+    while i < sql.len() {
+        if mask[i] && sql.as_bytes()[i] == b'[' {
+            if let Some(close) = matching_bracket(sql, &mask, i) {
+                if let Some(rewritten) = rewrite_comprehension(&sql[i + 1..close]) {
+                    result.push_str(&rewritten);
+                    i = close + 1;
+                    continue;
+                }
+            }
+        }
+
+        let c = sql[i..].chars().next().unwrap_or_default();
+        result.push(c);
+        i += c.len_utf8();
+    }
+
+    result
+}
+
+/// Rewrite the inside of `[...]` if it is a comprehension
+fn rewrite_comprehension(inner: &str) -> Option<String> {
+    let inner = rewrite_list_comprehensions(inner);
+    let mask = code_mask(&inner);
+
+    let for_at = find_top_level_keyword(&inner, &mask, 0, "for")?;
+    let in_at = find_top_level_keyword(&inner, &mask, for_at, "in")?;
+    let if_at = find_top_level_keyword(&inner, &mask, in_at, "if");
+
+    let expr = inner[..for_at].trim();
+    let var = inner[for_at + "for".len()..in_at].trim();
+    let list_end = if_at.unwrap_or(inner.len());
+    let list = inner[in_at + "in".len()..list_end].trim();
+
+    if expr.is_empty() || list.is_empty() || !var.bytes().all(is_word_byte) || var.is_empty() {
+        return None;
+    }
+
+    let source = match if_at {
+        Some(if_at) => format!(
+            "list_filter({}, {} -> {})",
+            list,
+            var,
+            inner[if_at + "if".len()..].trim()
+        ),
+        None => list.to_string(),
+    };
+
+    Some(format!("list_transform({}, {} -> {})", source, var, expr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asof_join_keyword_removed() {
+        assert_eq!(
+            normalize_for_parser(
+                "SELECT * FROM trades t ASOF JOIN quotes q ON t.ts >= q.ts ASOF  LEFT JOIN x.y ON 1=1"
+            ),
+            "SELECT * FROM trades t JOIN quotes q ON t.ts >= q.ts LEFT JOIN x.y ON 1=1"
+        );
+
+        // Only the join keyword is affected
+        let untouched = "SELECT asof, 'ASOF JOIN' FROM t -- ASOF JOIN";
+        assert_eq!(normalize_for_parser(untouched), untouched);
+    }
+
+    #[test]
+    fn test_list_comprehension_rewritten() {
+        assert_eq!(
+            normalize_for_parser("SELECT [x * 2 FOR x IN amounts IF x > 0] AS doubled FROM t"),
+            "SELECT list_transform(list_filter(amounts, x -> x > 0), x -> x * 2) AS doubled FROM t"
+        );
+        assert_eq!(
+            normalize_for_parser("SELECT [upper(s) for s in [n FOR n IN names]] FROM t"),
+            "SELECT list_transform(list_transform(names, n -> n), s -> upper(s)) FROM t"
+        );
+    }
+
+    #[test]
+    fn test_list_literals_and_strings_untouched() {
+        let sql = "SELECT [1, 2, 3], s['for x in y'], '[a FOR a IN b]' FROM t";
+        assert_eq!(normalize_for_parser(sql), sql);
+    }
+}
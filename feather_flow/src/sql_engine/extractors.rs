@@ -1,6 +1,9 @@
 //! Table and dependency extraction utilities for SQL
 
-use sqlparser::ast::{Expr, Query, SetExpr, Statement, TableFactor};
+use sqlparser::ast::{
+    Expr, Function, FunctionArg, FunctionArgExpr, FunctionArguments, GroupByExpr, JoinConstraint,
+    JoinOperator, Query, Select, SelectItem, SetExpr, Statement, TableFactor,
+};
 use std::collections::HashSet;
 
 /// Extract table names from a SQL statement, including tables from CTEs (WITH clauses)
@@ -29,6 +32,33 @@ pub fn get_external_table_deps(statements: &[Statement]) -> Vec<String> {
         .collect()
 }
 
+/// Tables referenced without a schema, other than CTEs, in order of first use
+pub fn get_unqualified_table_names(statements: &[Statement]) -> Vec<String> {
+    let mut cte_names = HashSet::new();
+    for statement in statements {
+        if let Statement::Query(query) = statement {
+            collect_cte_names(query, &mut cte_names);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    get_table_names(statements)
+        .into_iter()
+        .filter(|table| {
+            !table.contains('.') && !cte_names.contains(table) && seen.insert(table.clone())
+        })
+        .collect()
+}
+
+fn collect_cte_names(query: &Query, names: &mut HashSet<String>) {
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            names.insert(cte.alias.name.value.clone());
+            collect_cte_names(&cte.query, names);
+        }
+    }
+}
+
 /// Extract tables from a SQL query
 pub fn extract_tables_from_query(query: &Query, table_names: &mut Vec<String>) {
     // Extract tables from CTEs (WITH clause) first
@@ -43,40 +73,14 @@ pub fn extract_tables_from_query(query: &Query, table_names: &mut Vec<String>) {
     }
 
     // Extract tables from the query body
-    match &*query.body {
-        SetExpr::Select(select) => {
-            // Extract tables from FROM clause
-            for table_with_joins in &select.from {
-                extract_table_from_relation(&table_with_joins.relation, table_names);
-
-                // Extract tables from JOINs
-                for join in &table_with_joins.joins {
-                    extract_table_from_relation(&join.relation, table_names);
-                }
-            }
-
-            // Extract tables from WHERE clause (for subqueries)
-            if let Some(where_expr) = &select.selection {
-                extract_tables_from_expr(where_expr, table_names);
-            }
-
-            // Extract tables from SELECT expressions (for subqueries)
-            for item in &select.projection {
-                match item {
-                    sqlparser::ast::SelectItem::ExprWithAlias { expr, .. } => {
-                        extract_tables_from_expr(expr, table_names);
-                    }
-                    sqlparser::ast::SelectItem::UnnamedExpr(expr) => {
-                        extract_tables_from_expr(expr, table_names);
-                    }
-                    _ => {}
-                }
-            }
+    extract_tables_from_set_expr(&query.body, table_names);
+}
 
-            // Extract tables from GROUP BY, HAVING, etc.
-            if let Some(having) = &select.having {
-                extract_tables_from_expr(having, table_names);
-            }
+/// Helper function to extract tables from a SetExpr
+pub fn extract_tables_from_set_expr(expr: &SetExpr, table_names: &mut Vec<String>) {
+    match expr {
+        SetExpr::Select(select) => {
+            extract_tables_from_select(select, table_names);
         }
         SetExpr::Query(subquery) => {
             extract_tables_from_query(subquery, table_names);
@@ -90,31 +94,96 @@ pub fn extract_tables_from_query(query: &Query, table_names: &mut Vec<String>) {
     }
 }
 
-/// Helper function to extract tables from a SetExpr
-pub fn extract_tables_from_set_expr(expr: &SetExpr, table_names: &mut Vec<String>) {
-    match expr {
-        SetExpr::Select(select) => {
-            // Extract tables from FROM clause
-            for table_with_joins in &select.from {
-                extract_table_from_relation(&table_with_joins.relation, table_names);
-                for join in &table_with_joins.joins {
-                    extract_table_from_relation(&join.relation, table_names);
-                }
-            }
+/// Extract tables from every clause of a SELECT
+fn extract_tables_from_select(select: &Select, table_names: &mut Vec<String>) {
+    // Extract tables from FROM clause
+    for table_with_joins in &select.from {
+        extract_table_from_relation(&table_with_joins.relation, table_names);
 
-            // Process subqueries in WHERE
-            if let Some(where_expr) = &select.selection {
-                extract_tables_from_expr(where_expr, table_names);
+        // Extract tables from JOINs and their conditions
+        for join in &table_with_joins.joins {
+            extract_table_from_relation(&join.relation, table_names);
+            extract_tables_from_join_operator(&join.join_operator, table_names);
+        }
+    }
+
+    // Extract tables from WHERE clause (for subqueries)
+    if let Some(where_expr) = &select.selection {
+        extract_tables_from_expr(where_expr, table_names);
+    }
+
+    // Extract tables from SELECT expressions (for subqueries)
+    for item in &select.projection {
+        match item {
+            SelectItem::ExprWithAlias { expr, .. } => {
+                extract_tables_from_expr(expr, table_names);
+            }
+            SelectItem::UnnamedExpr(expr) => {
+                extract_tables_from_expr(expr, table_names);
             }
+            _ => {}
         }
-        SetExpr::Query(subquery) => {
-            extract_tables_from_query(subquery, table_names);
+    }
+
+    // Extract tables from GROUP BY, HAVING and QUALIFY
+    if let GroupByExpr::Expressions(exprs, _) = &select.group_by {
+        for expr in exprs {
+            extract_tables_from_expr(expr, table_names);
         }
-        SetExpr::SetOperation { left, right, .. } => {
-            extract_tables_from_set_expr(left, table_names);
-            extract_tables_from_set_expr(right, table_names);
+    }
+    if let Some(having) = &select.having {
+        extract_tables_from_expr(having, table_names);
+    }
+    if let Some(qualify) = &select.qualify {
+        extract_tables_from_expr(qualify, table_names);
+    }
+}
+
+/// Extract tables from join conditions (subqueries in ON, ASOF match conditions)
+fn extract_tables_from_join_operator(operator: &JoinOperator, table_names: &mut Vec<String>) {
+    let constraint = match operator {
+        JoinOperator::Inner(constraint)
+        | JoinOperator::LeftOuter(constraint)
+        | JoinOperator::RightOuter(constraint)
+        | JoinOperator::FullOuter(constraint)
+        | JoinOperator::LeftSemi(constraint)
+        | JoinOperator::RightSemi(constraint)
+        | JoinOperator::LeftAnti(constraint)
+        | JoinOperator::RightAnti(constraint) => constraint,
+        JoinOperator::AsOf {
+            match_condition,
+            constraint,
+        } => {
+            extract_tables_from_expr(match_condition, table_names);
+            constraint
         }
-        _ => {}
+        _ => return,
+    };
+
+    if let JoinConstraint::On(expr) = constraint {
+        extract_tables_from_expr(expr, table_names);
+    }
+}
+
+/// Extract tables from function arguments and window clauses
+fn extract_tables_from_function(func: &Function, table_names: &mut Vec<String>) {
+    match &func.args {
+        FunctionArguments::Subquery(subquery) => extract_tables_from_query(subquery, table_names),
+        FunctionArguments::List(list) => {
+            for arg in &list.args {
+                let (FunctionArg::Named { arg, .. }
+                | FunctionArg::ExprNamed { arg, .. }
+                | FunctionArg::Unnamed(arg)) = arg;
+                if let FunctionArgExpr::Expr(expr) = arg {
+                    extract_tables_from_expr(expr, table_names);
+                }
+            }
+        }
+        FunctionArguments::None => {}
+    }
+
+    if let Some(filter) = &func.filter {
+        extract_tables_from_expr(filter, table_names);
     }
 }
 
@@ -128,13 +197,43 @@ pub fn extract_tables_from_expr(expr: &Expr, table_names: &mut Vec<String>) {
             extract_tables_from_expr(left, table_names);
             extract_tables_from_expr(right, table_names);
         }
-        Expr::UnaryOp { expr, .. } => {
+        Expr::UnaryOp { expr, .. }
+        | Expr::Nested(expr)
+        | Expr::IsNull(expr)
+        | Expr::IsNotNull(expr) => {
             extract_tables_from_expr(expr, table_names);
         }
+        Expr::Exists { subquery, .. } => {
+            extract_tables_from_query(subquery, table_names);
+        }
+        Expr::Between {
+            expr, low, high, ..
+        } => {
+            extract_tables_from_expr(expr, table_names);
+            extract_tables_from_expr(low, table_names);
+            extract_tables_from_expr(high, table_names);
+        }
+        Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } => {
+            extract_tables_from_expr(expr, table_names);
+            extract_tables_from_expr(pattern, table_names);
+        }
+        // DuckDB struct/list access: `s.field`, `s['field']`, `l[1]`
+        Expr::CompoundFieldAccess { root, .. } => {
+            extract_tables_from_expr(root, table_names);
+        }
+        Expr::Array(array) => {
+            for elem in &array.elem {
+                extract_tables_from_expr(elem, table_names);
+            }
+        }
+        Expr::Lambda(lambda) => {
+            extract_tables_from_expr(&lambda.body, table_names);
+        }
         Expr::Cast { expr, .. } => {
             extract_tables_from_expr(expr, table_names);
         }
-        Expr::InSubquery { subquery, .. } => {
+        Expr::InSubquery { expr, subquery, .. } => {
+            extract_tables_from_expr(expr, table_names);
             extract_tables_from_query(subquery, table_names);
         }
         Expr::InList { list, .. } => {
@@ -185,6 +284,8 @@ pub fn extract_tables_from_expr(expr: &Expr, table_names: &mut Vec<String>) {
             if !common_sql_functions.contains(&func_name.as_str()) {
                 table_names.push(func.name.to_string());
             }
+
+            extract_tables_from_function(func, table_names);
         }
         Expr::Case {
             operand,
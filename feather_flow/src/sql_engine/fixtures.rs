@@ -0,0 +1,45 @@
+//! Model fixtures shared by unit tests across the crate
+
+use sqlparser::dialect::DuckDbDialect;
+use std::fs;
+use std::path::Path;
+
+use super::checksum::Sha256Hash;
+use super::sql_model::SqlModel;
+
+/// Write `<name>/<name>.sql`, and `<name>.yml` beside it when given, under `root` and parse
+/// it as a DuckDB model with its dependencies extracted
+pub fn write_model(root: &Path, name: &str, sql: &str, yaml: Option<&str>) -> SqlModel {
+    write_model_files(root, &root.join(name), name, sql, yaml)
+}
+
+/// `write_model` for a model under the `dir` subdirectory of `root`
+pub fn write_model_in(
+    root: &Path,
+    dir: &str,
+    name: &str,
+    sql: &str,
+    yaml: Option<&str>,
+) -> SqlModel {
+    write_model_files(root, &root.join(dir).join(name), name, sql, yaml)
+}
+
+fn write_model_files(
+    root: &Path,
+    model_dir: &Path,
+    name: &str,
+    sql: &str,
+    yaml: Option<&str>,
+) -> SqlModel {
+    fs::create_dir_all(model_dir).unwrap();
+    let file = model_dir.join(format!("{}.sql", name));
+    fs::write(&file, sql).unwrap();
+    if let Some(yaml) = yaml {
+        fs::write(model_dir.join(format!("{}.yml", name)), yaml).unwrap();
+    }
+
+    let mut model =
+        SqlModel::from_path(&file, root, "duckdb", &DuckDbDialect {}, &Sha256Hash).unwrap();
+    model.extract_dependencies().unwrap();
+    model
+}
@@ -0,0 +1,512 @@
+//! Probable keys of models and the column tests that would check them, for `ff suggest tests`
+//!
+//! A model's key is the column most likely to identify its rows, from the strongest evidence
+//! there is:
+//! - a column its YAML already tests `unique`;
+//! - the only column its final SELECT groups by;
+//! - a column its final SELECT passes through unchanged from the key of the one upstream
+//!   model it reads, without joins;
+//! - a column named after the model: `customer_id` for `stg_customers`, else `id`.
+//!
+//! Keys get `unique` and `not_null`. Any other `*_id` column is a foreign key when another
+//! model that is not downstream of this one is keyed on a column of that name, or on `id`
+//! and named after the entity, and gets a `relationships` test against it. Tests a column
+//! already has are not suggested again. Snapshots repeat their keys and are left out.
+
+use sqlparser::ast::{Expr, Query, Select, SelectItem, SetExpr, Statement, TableFactor};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use super::cost;
+use super::nodes::Node;
+use super::projection::Projector;
+use super::sql_model::{SqlModel, SqlModelCollection};
+
+/// Prefixes naming a model's layer rather than the entity it holds
+const LAYER_PREFIXES: [&str; 6] = ["stg_", "int_", "dim_", "fct_", "base_", "mart_"];
+
+/// Why a column is taken for its model's key
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyEvidence {
+    Declared,
+    GroupedBy,
+    PassedThrough { model: String, column: String },
+    Named,
+}
+
+impl fmt::Display for KeyEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyEvidence::Declared => write!(f, "already tested unique"),
+            KeyEvidence::GroupedBy => write!(f, "the only GROUP BY column"),
+            KeyEvidence::PassedThrough { model, column } => {
+                write!(
+                    f,
+                    "passed through from {}.{}, the key of {}",
+                    model, column, model
+                )
+            }
+            KeyEvidence::Named => write!(f, "named after the model"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelKey {
+    pub column: String,
+    pub evidence: KeyEvidence,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SuggestedTest {
+    Unique,
+    NotNull,
+    /// Checks the column against `field` of the model named `to`
+    Relationships {
+        to: String,
+        field: String,
+    },
+}
+
+impl SuggestedTest {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SuggestedTest::Unique => "unique",
+            SuggestedTest::NotNull => "not_null",
+            SuggestedTest::Relationships { .. } => "relationships",
+        }
+    }
+
+    /// The entry of a column's `tests:` declaring this test
+    pub fn to_yaml(&self) -> serde_yaml::Value {
+        match self {
+            SuggestedTest::Relationships { to, field } => {
+                let mut args = serde_yaml::Mapping::new();
+                args.insert("to".into(), format!("ref('{}')", to).into());
+                args.insert("field".into(), field.as_str().into());
+                let mut entry = serde_yaml::Mapping::new();
+                entry.insert(self.name().into(), args.into());
+                entry.into()
+            }
+            _ => self.name().into(),
+        }
+    }
+}
+
+/// A test a model's column probably should have
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSuggestion {
+    pub model_id: String,
+    pub column: String,
+    pub test: SuggestedTest,
+    pub reason: String,
+}
+
+/// Key of every model that has one, by unique id
+pub fn infer_keys(collection: &SqlModelCollection) -> BTreeMap<String, ModelKey> {
+    let mut projector = Projector::new(collection);
+    let mut keys = BTreeMap::new();
+    // Upstream models first, so keys passed through from them are recognized
+    for model in collection.models_by_depth() {
+        if !is_model(collection, &model.unique_id) {
+            continue;
+        }
+        let columns = output_columns(model, &mut projector);
+        if let Some(key) = model_key(collection, model, &columns, &keys) {
+            keys.insert(model.unique_id.clone(), key);
+        }
+    }
+    keys
+}
+
+/// Tests the models' key and foreign key columns are missing, by model then column
+pub fn suggest_tests(collection: &SqlModelCollection) -> Vec<TestSuggestion> {
+    let keys = infer_keys(collection);
+    let mut projector = Projector::new(collection);
+    let mut suggestions = Vec::new();
+    for model in collection.models_by_id() {
+        if !is_model(collection, &model.unique_id) {
+            continue;
+        }
+        let key = keys.get(&model.unique_id);
+        let mut suggest = |column: &str, test: SuggestedTest, reason: String| {
+            if !has_test(model, column, test.name()) {
+                suggestions.push(TestSuggestion {
+                    model_id: model.unique_id.clone(),
+                    column: column.to_string(),
+                    test,
+                    reason,
+                });
+            }
+        };
+
+        if let Some(key) = key {
+            for test in [SuggestedTest::Unique, SuggestedTest::NotNull] {
+                suggest(&key.column, test, key.evidence.to_string());
+            }
+        }
+        for column in output_columns(model, &mut projector) {
+            let is_key = key.is_some_and(|key| key.column.eq_ignore_ascii_case(&column));
+            if is_key || !column.to_lowercase().ends_with("_id") {
+                continue;
+            }
+            if let Some((parent, field)) = referenced_model(collection, &keys, model, &column) {
+                let reason = format!("matches {}.{}, the key of {}", parent, field, parent);
+                suggest(
+                    &column,
+                    SuggestedTest::Relationships {
+                        to: parent.to_string(),
+                        field,
+                    },
+                    reason,
+                );
+            }
+        }
+    }
+    suggestions
+}
+
+fn is_model(collection: &SqlModelCollection, id: &str) -> bool {
+    matches!(collection.get_node(id), Some(Node::Model(_)))
+}
+
+/// Whether the model's YAML declares `test` on `column`
+fn has_test(model: &SqlModel, column: &str, test: &str) -> bool {
+    model
+        .columns
+        .values()
+        .filter(|info| info.name.eq_ignore_ascii_case(column))
+        .any(|info| info.tests.iter().any(|declared| declared == test))
+}
+
+/// Columns of the model's SQL output, then any others its YAML documents
+fn output_columns(model: &SqlModel, projector: &mut Projector) -> Vec<String> {
+    let mut columns = projector
+        .model(&model.unique_id)
+        .map(|projection| projection.columns)
+        .unwrap_or_default();
+    let mut documented: Vec<&String> = model.columns.values().map(|info| &info.name).collect();
+    documented.sort();
+    columns.extend(documented.into_iter().cloned());
+
+    let mut seen = HashSet::new();
+    columns.retain(|column| seen.insert(column.to_lowercase()));
+    columns
+}
+
+fn model_key(
+    collection: &SqlModelCollection,
+    model: &SqlModel,
+    columns: &[String],
+    keys: &BTreeMap<String, ModelKey>,
+) -> Option<ModelKey> {
+    let key = |column: &str, evidence: KeyEvidence| ModelKey {
+        column: column.to_string(),
+        evidence,
+    };
+    let mut declared: Vec<&str> = model
+        .columns
+        .values()
+        .filter(|info| info.tests.iter().any(|test| test == "unique"))
+        .map(|info| info.name.as_str())
+        .collect();
+    declared.sort();
+    if let Some(column) = declared.first() {
+        return Some(key(column, KeyEvidence::Declared));
+    }
+
+    if let Some((query, select)) = final_select(model) {
+        let outputs = passed_through(select);
+        if let Some([grouped]) = cost::group_by_columns(select).as_deref() {
+            if let Some(output) = outputs.get(grouped) {
+                return Some(key(output, KeyEvidence::GroupedBy));
+            }
+        }
+        let upstream = single_upstream(collection, model, query, select)
+            .and_then(|upstream| Some((upstream, keys.get(&upstream.unique_id)?)));
+        if let Some((upstream, upstream_key)) = upstream {
+            let output = outputs
+                .get(&upstream_key.column.to_lowercase())
+                .cloned()
+                .or_else(|| selects_wildcard(select).then(|| upstream_key.column.clone()));
+            if let Some(output) = output {
+                return Some(key(
+                    &output,
+                    KeyEvidence::PassedThrough {
+                        model: upstream.name.clone(),
+                        column: upstream_key.column.clone(),
+                    },
+                ));
+            }
+        }
+    }
+
+    let named = format!("{}_id", entity(&model.name));
+    columns
+        .iter()
+        .find(|column| column.eq_ignore_ascii_case(&named))
+        .or_else(|| {
+            columns
+                .iter()
+                .find(|column| column.eq_ignore_ascii_case("id"))
+        })
+        .map(|column| key(column, KeyEvidence::Named))
+}
+
+/// What one row of a model holds: its name without the layer prefix, in the singular
+fn entity(model_name: &str) -> String {
+    let name = model_name.to_lowercase();
+    let name = LAYER_PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(&name);
+    if let Some(stem) = name.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if name.ends_with('s') && !name.ends_with("ss") {
+        name[..name.len() - 1].to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// The last query of the model and its outer SELECT, when that is not a set operation
+fn final_select(model: &SqlModel) -> Option<(&Query, &Select)> {
+    let query = model
+        .ast
+        .iter()
+        .rev()
+        .find_map(|statement| match statement {
+            Statement::Query(query) => Some(query.as_ref()),
+            _ => None,
+        })?;
+    match query.body.as_ref() {
+        SetExpr::Select(select) => Some((query, select.as_ref())),
+        _ => None,
+    }
+}
+
+/// Output name of each input column the SELECT outputs unchanged, by lowercased input name
+fn passed_through(select: &Select) -> HashMap<String, String> {
+    let column = |expr: &Expr| match expr {
+        Expr::Identifier(ident) => Some(ident.value.clone()),
+        Expr::CompoundIdentifier(idents) => idents.last().map(|ident| ident.value.clone()),
+        _ => None,
+    };
+    select
+        .projection
+        .iter()
+        .filter_map(|item| match item {
+            SelectItem::UnnamedExpr(expr) => column(expr).map(|name| (name.clone(), name)),
+            SelectItem::ExprWithAlias { expr, alias } => {
+                column(expr).map(|name| (name, alias.value.clone()))
+            }
+            _ => None,
+        })
+        .map(|(input, output)| (input.to_lowercase(), output))
+        .collect()
+}
+
+fn selects_wildcard(select: &Select) -> bool {
+    select.projection.iter().any(|item| {
+        matches!(
+            item,
+            SelectItem::Wildcard(options) | SelectItem::QualifiedWildcard(_, options)
+                if options.opt_exclude.is_none()
+        )
+    })
+}
+
+/// The upstream model a SELECT reads as its only relation, without joins
+fn single_upstream<'a>(
+    collection: &'a SqlModelCollection,
+    model: &SqlModel,
+    query: &Query,
+    select: &Select,
+) -> Option<&'a SqlModel> {
+    let [from] = select.from.as_slice() else {
+        return None;
+    };
+    if !from.joins.is_empty() {
+        return None;
+    }
+    let TableFactor::Table { name, .. } = &from.relation else {
+        return None;
+    };
+    let relation = name.0.last()?.value.to_lowercase();
+    let is_cte = query.with.as_ref().is_some_and(|with| {
+        with.cte_tables
+            .iter()
+            .any(|cte| cte.alias.name.value.eq_ignore_ascii_case(&relation))
+    });
+    if is_cte {
+        return None;
+    }
+    collection
+        .node_dependencies(&model.unique_id)
+        .iter()
+        .filter_map(|id| collection.get_model(id))
+        .find(|upstream| upstream.relation_name().eq_ignore_ascii_case(&relation))
+}
+
+/// The model `column` of `model` most likely refers to, with the key column it matches.
+/// Upstream models are preferred, then the shallowest; downstream models are never chosen.
+fn referenced_model<'a>(
+    collection: &'a SqlModelCollection,
+    keys: &BTreeMap<String, ModelKey>,
+    model: &SqlModel,
+    column: &str,
+) -> Option<(&'a str, String)> {
+    let ancestors = reachable(collection, &model.unique_id, |m| &m.upstream_models);
+    let descendants = reachable(collection, &model.unique_id, |m| &m.downstream_models);
+    keys.iter()
+        .filter(|(id, _)| **id != model.unique_id && !descendants.contains(*id))
+        .filter_map(|(id, key)| {
+            let parent = collection.get_model(id)?;
+            let matches = key.column.eq_ignore_ascii_case(column)
+                || (key.column.eq_ignore_ascii_case("id")
+                    && column.eq_ignore_ascii_case(&format!("{}_id", entity(&parent.name))));
+            matches.then_some(parent)
+        })
+        .min_by_key(|parent| {
+            (
+                !ancestors.contains(&parent.unique_id),
+                parent.depth.unwrap_or(usize::MAX),
+                &parent.unique_id,
+            )
+        })
+        .map(|parent| (parent.name.as_str(), keys[&parent.unique_id].column.clone()))
+}
+
+/// Models reachable from `id` by repeatedly following `edges`
+fn reachable(
+    collection: &SqlModelCollection,
+    id: &str,
+    edges: impl Fn(&SqlModel) -> &HashSet<String>,
+) -> HashSet<String> {
+    let mut seen = HashSet::new();
+    let mut pending = vec![id.to_string()];
+    while let Some(current) = pending.pop() {
+        let Some(model) = collection.get_model(&current) else {
+            continue;
+        };
+        for next in edges(model) {
+            if seen.insert(next.clone()) {
+                pending.push(next.clone());
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::fixtures::write_model;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_entity_strips_layer_and_plural() {
+        assert_eq!(entity("stg_customers"), "customer");
+        assert_eq!(entity("dim_categories"), "category");
+        assert_eq!(entity("fct_address"), "address");
+        assert_eq!(entity("daily_trends"), "daily_trend");
+    }
+
+    #[test]
+    fn test_suggests_keys_and_relationships() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let mut collection = SqlModelCollection::new();
+        for model in [
+            write_model(
+                root,
+                "stg_customers",
+                "SELECT id AS customer_id, name FROM raw.customers",
+                None,
+            ),
+            write_model(
+                root,
+                "stg_orders",
+                "SELECT order_id, customer_id, amount FROM raw.orders",
+                Some(
+                    "version: 2\nmodels:\n  - name: stg_orders\n    columns:\n      - name: order_id\n        tests: [unique]\n",
+                ),
+            ),
+            write_model(
+                root,
+                "dim_customers",
+                "SELECT customer_id AS id, name FROM public.stg_customers",
+                None,
+            ),
+            write_model(
+                root,
+                "customer_revenue",
+                "SELECT customer_id, sum(amount) AS revenue FROM public.stg_orders GROUP BY customer_id",
+                None,
+            ),
+        ] {
+            collection.add_model(model);
+        }
+        collection.build_dependency_graph();
+
+        let keys = infer_keys(&collection);
+        let key = |id: &str| {
+            let key = &keys[id];
+            (key.column.as_str(), key.evidence.to_string())
+        };
+        assert_eq!(
+            key("model.stg_customers.stg_customers"),
+            ("customer_id", "named after the model".to_string())
+        );
+        assert_eq!(
+            key("model.stg_orders.stg_orders"),
+            ("order_id", "already tested unique".to_string())
+        );
+        assert_eq!(
+            key("model.dim_customers.dim_customers"),
+            (
+                "id",
+                "passed through from stg_customers.customer_id, the key of stg_customers"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            key("model.customer_revenue.customer_revenue"),
+            ("customer_id", "the only GROUP BY column".to_string())
+        );
+
+        let suggestions: Vec<String> = suggest_tests(&collection)
+            .iter()
+            .map(|s| format!("{} {} {}", s.model_id, s.column, s.test.name()))
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                "model.customer_revenue.customer_revenue customer_id unique",
+                "model.customer_revenue.customer_revenue customer_id not_null",
+                "model.dim_customers.dim_customers id unique",
+                "model.dim_customers.dim_customers id not_null",
+                "model.stg_customers.stg_customers customer_id unique",
+                "model.stg_customers.stg_customers customer_id not_null",
+                "model.stg_orders.stg_orders order_id not_null",
+                "model.stg_orders.stg_orders customer_id relationships",
+            ]
+        );
+
+        let relationship = suggest_tests(&collection)
+            .into_iter()
+            .find(|s| s.test.name() == "relationships")
+            .unwrap();
+        assert_eq!(
+            relationship.test,
+            SuggestedTest::Relationships {
+                to: "stg_customers".to_string(),
+                field: "customer_id".to_string()
+            }
+        );
+        assert_eq!(
+            serde_yaml::to_string(&relationship.test.to_yaml()).unwrap(),
+            "relationships:\n  to: ref('stg_customers')\n  field: customer_id\n"
+        );
+    }
+}
@@ -1,16 +1,19 @@
 //! Column-level lineage tracking for SQL
 //!
-//! TODO: This module provides infrastructure for column-level lineage tracking,
-//! which allows tracing how data flows from source columns to target columns.
-//! Currently, this feature is only used in tests, but is planned to be fully
-//! integrated into the main application for data lineage visualization and analysis.
-use std::collections::{HashMap, HashSet};
+//! Traces how data flows from source columns to target columns. Used by `ff lineage` for a
+//! project's models and `ff analyze` for a single SQL text.
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
 
-use sqlparser::ast::{Expr, Query, SelectItem, SetExpr, Statement, TableFactor};
+use sqlparser::ast::{
+    AccessExpr, Expr, FunctionArg, FunctionArgExpr, FunctionArguments, ObjectName, Query,
+    SelectItem, SetExpr, Statement, TableFactor,
+};
 use sqlparser::dialect::DuckDbDialect;
 use sqlparser::parser::Parser;
 
+use super::dialect::normalize_for_parser;
+
 /// Represents a column reference in a table
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ColumnRef {
@@ -40,7 +43,6 @@ impl fmt::Display for ColumnRef {
 
 /// Represents column-level lineage information
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct ColumnLineage {
     /// Target column (in the result set)
     pub target: ColumnRef,
@@ -51,11 +53,10 @@ pub struct ColumnLineage {
 }
 
 /// Extract column-level lineage from SQL
-#[allow(dead_code)]
 pub fn extract_column_lineage(sql: &str) -> Result<Vec<ColumnLineage>, String> {
     let dialect = DuckDbDialect {};
-    let statements =
-        Parser::parse_sql(&dialect, sql).map_err(|e| format!("Error parsing SQL: {}", e))?;
+    let statements = Parser::parse_sql(&dialect, &normalize_for_parser(sql))
+        .map_err(|e| format!("Error parsing SQL: {}", e))?;
 
     let mut lineage_results = Vec::new();
 
@@ -94,8 +95,10 @@ fn extract_query_lineage(query: &Query) -> Result<Vec<ColumnLineage>, String> {
                     // For expressions without explicit alias, create synthetic name
                     let target_name = match expr {
                         Expr::Identifier(ident) => ident.value.clone(),
-                        Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
-                            idents[1].value.clone()
+                        // table.col, and DuckDB struct access (s.field, t.s.field) which
+                        // DuckDB names after the last field
+                        Expr::CompoundIdentifier(idents) if idents.len() >= 2 => {
+                            idents[idents.len() - 1].value.clone()
                         }
                         _ => format!("_col{}", idx + 1), // Synthetic name for complex expressions
                     };
@@ -127,7 +130,8 @@ fn extract_query_lineage(query: &Query) -> Result<Vec<ColumnLineage>, String> {
                     // For * we need to expand all columns from all tables
                     // This is simplistic - in a real implementation we'd need
                     // metadata about available columns in each table
-                    for table in alias_map.keys() {
+                    let tables: BTreeSet<&String> = alias_map.values().collect();
+                    for table in tables {
                         lineage_results.push(ColumnLineage {
                             target: ColumnRef::new(Some(table.clone()), "*".to_string()),
                             sources: vec![ColumnRef::new(Some(table.clone()), "*".to_string())],
@@ -138,7 +142,8 @@ fn extract_query_lineage(query: &Query) -> Result<Vec<ColumnLineage>, String> {
                 SelectItem::QualifiedWildcard(obj_name, _) => {
                     // For table.* we expand all columns from that table
                     if !obj_name.0.is_empty() {
-                        let table_name = obj_name.0[0].value.clone();
+                        let qualifier = &obj_name.0[0].value;
+                        let table_name = alias_map.get(qualifier).unwrap_or(qualifier).clone();
                         lineage_results.push(ColumnLineage {
                             target: ColumnRef::new(Some(table_name.clone()), "*".to_string()),
                             sources: vec![ColumnRef::new(Some(table_name), "*".to_string())],
@@ -174,7 +179,7 @@ fn extract_expr_columns(
             if !from_tables.is_empty() {
                 if let TableFactor::Table { name, .. } = &from_tables[0].relation {
                     if !name.0.is_empty() {
-                        let table_name = name.0.last().unwrap().value.clone();
+                        let table_name = relation_name(name);
                         columns.insert(ColumnRef::new(Some(table_name), ident.value.clone()));
                         return columns.into_iter().collect();
                     }
@@ -191,6 +196,39 @@ fn extract_expr_columns(
             let real_table = alias_map.get(&table_ref).cloned().unwrap_or(table_ref);
             columns.insert(ColumnRef::new(Some(real_table), column_name));
         }
+        // DuckDB struct field access: table.struct_col.field or struct_col.field.nested
+        Expr::CompoundIdentifier(idents) if idents.len() > 2 => {
+            let first = &idents[0].value;
+            match alias_map.get(first) {
+                Some(real_table) => {
+                    columns.insert(ColumnRef::new(
+                        Some(real_table.clone()),
+                        idents[1].value.clone(),
+                    ));
+                }
+                None => {
+                    let root = Expr::Identifier(idents[0].clone());
+                    columns.extend(extract_expr_columns(&root, alias_map, from_tables));
+                }
+            }
+        }
+        // DuckDB bracket access (s['field'], t.s['field'], l[1]) reads the root column
+        Expr::CompoundFieldAccess { root, access_chain } => {
+            match (root.as_ref(), access_chain.first()) {
+                (Expr::Identifier(table), Some(AccessExpr::Dot(Expr::Identifier(column))))
+                    if alias_map.contains_key(&table.value) =>
+                {
+                    columns.insert(ColumnRef::new(
+                        alias_map.get(&table.value).cloned(),
+                        column.value.clone(),
+                    ));
+                }
+                _ => columns.extend(extract_expr_columns(root, alias_map, from_tables)),
+            }
+        }
+        Expr::Nested(inner) | Expr::Cast { expr: inner, .. } => {
+            columns.extend(extract_expr_columns(inner, alias_map, from_tables));
+        }
         // Binary operations (e.g., a + b, a > b)
         Expr::BinaryOp { left, right, .. } => {
             let left_columns = extract_expr_columns(left, alias_map, from_tables);
@@ -199,17 +237,16 @@ fn extract_expr_columns(
             columns.extend(left_columns);
             columns.extend(right_columns);
         }
-        // Function calls (e.g., SUM(a), COUNT(*))
-        Expr::Function(func) => {
-            // Simply check the function name
-            if !func.name.0.is_empty() {
-                let func_name = func.name.0[0].value.to_lowercase();
-                if func_name == "count" {
-                    // COUNT is usually special, but for simplicity we'll just skip it
-                    // In a real implementation, we'd need to extract columns from the args
-                } else {
-                    // For now, we don't extract columns from function arguments
-                    // This is a limitation of the current implementation
+        // Function calls (e.g., SUM(a), COUNT(*)) read the columns of their arguments
+        Expr::Function(function) => {
+            if let FunctionArguments::List(list) = &function.args {
+                for arg in &list.args {
+                    let (FunctionArg::Named { arg, .. }
+                    | FunctionArg::ExprNamed { arg, .. }
+                    | FunctionArg::Unnamed(arg)) = arg;
+                    if let FunctionArgExpr::Expr(expr) = arg {
+                        columns.extend(extract_expr_columns(expr, alias_map, from_tables));
+                    }
                 }
             }
         }
@@ -223,26 +260,32 @@ fn extract_expr_columns(
 /// Collect table aliases from a TableFactor
 #[allow(dead_code)]
 fn collect_table_aliases(table_factor: &TableFactor, alias_map: &mut HashMap<String, String>) {
-    match table_factor {
-        TableFactor::Table { name, alias, .. } => {
-            // Get the table name from the ObjectName's last element in the vector
-            if !name.0.is_empty() {
-                let real_table = name.0.last().unwrap().value.clone();
-
-                // If there's an alias, map it to the real table name
-                if let Some(table_alias) = alias {
-                    alias_map.insert(table_alias.name.value.clone(), real_table.clone());
-                }
-
-                // Also map the real name to itself
-                alias_map.insert(real_table.clone(), real_table);
+    // Other table factor types are not handled yet
+    if let TableFactor::Table { name, alias, .. } = table_factor {
+        // Columns are qualified by the alias, or else by the table's unqualified name
+        if let Some(last) = name.0.last() {
+            let real_table = relation_name(name);
+
+            // If there's an alias, map it to the real table name
+            if let Some(table_alias) = alias {
+                alias_map.insert(table_alias.name.value.clone(), real_table.clone());
             }
+
+            // Also map the table's own name to it
+            alias_map.insert(last.value.clone(), real_table);
         }
-        // Handle other table factor types as needed
-        _ => {}
     }
 }
 
+/// The relation `name` as written, schema and database included, without quotes
+fn relation_name(name: &ObjectName) -> String {
+    name.0
+        .iter()
+        .map(|ident| ident.value.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
 /// Determine the transformation type
 #[allow(dead_code)]
 fn determine_transformation_type(expr: &Expr) -> String {
@@ -279,7 +322,6 @@ fn determine_transformation_type(expr: &Expr) -> String {
 }
 
 /// Generate a graph representation of the lineage (dot format for Graphviz)
-#[allow(dead_code)]
 pub fn generate_lineage_graph(lineage: &[ColumnLineage]) -> String {
     let mut result = String::from("digraph lineage {\n");
     result.push_str("  rankdir=LR;\n");
@@ -419,5 +461,13 @@ mod tests {
         // Check total_amount lineage
         assert_eq!(lineage[2].target.column, "total_amount");
         assert_eq!(lineage[2].transformation, "aggregation");
+        assert_eq!(
+            lineage[2].sources,
+            vec![ColumnRef::new(
+                Some("orders".to_string()),
+                "amount".to_string()
+            )]
+        );
+        assert!(lineage[1].sources.is_empty());
     }
 }
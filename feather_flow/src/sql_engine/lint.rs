@@ -0,0 +1,78 @@
+//! Lightweight lint checks on parsed model SQL
+
+use serde::Serialize;
+use sqlparser::ast::{Query, SelectItem, SetExpr, Statement};
+
+use super::extractors;
+
+/// A single lint finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl LintFinding {
+    fn new(rule: &'static str, message: String) -> Self {
+        Self { rule, message }
+    }
+}
+
+/// Run every lint rule over `statements`
+pub fn lint_statements(statements: &[Statement]) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+
+    for statement in statements {
+        if let Statement::Query(query) = statement {
+            check_select_star(query, &mut findings);
+        }
+    }
+    check_unqualified_tables(statements, &mut findings);
+
+    findings
+}
+
+/// `SELECT *` in the output makes the model's columns depend on upstream column order
+fn check_select_star(query: &Query, findings: &mut Vec<LintFinding>) {
+    fn visit(body: &SetExpr, findings: &mut Vec<LintFinding>) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &select.projection {
+                    let star = match item {
+                        SelectItem::Wildcard(_) => "*".to_string(),
+                        SelectItem::QualifiedWildcard(name, _) => format!("{}.*", name),
+                        _ => continue,
+                    };
+                    findings.push(LintFinding::new(
+                        "select_star",
+                        format!(
+                            "Output selects '{}'; list columns explicitly so upstream changes cannot silently change this model",
+                            star
+                        ),
+                    ));
+                }
+            }
+            SetExpr::Query(query) => visit(&query.body, findings),
+            SetExpr::SetOperation { left, right, .. } => {
+                visit(left, findings);
+                visit(right, findings);
+            }
+            _ => {}
+        }
+    }
+
+    visit(&query.body, findings);
+}
+
+/// Tables without a schema are never matched to models or imports
+fn check_unqualified_tables(statements: &[Statement], findings: &mut Vec<LintFinding>) {
+    for table in extractors::get_unqualified_table_names(statements) {
+        findings.push(LintFinding::new(
+            "unqualified_table",
+            format!(
+                "Table '{}' has no schema and will not be resolved as a dependency",
+                table
+            ),
+        ));
+    }
+}
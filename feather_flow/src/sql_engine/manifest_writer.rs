@@ -0,0 +1,197 @@
+//! Streaming writer for the `ff parse` manifest
+//!
+//! Each model is serialized straight to the output as it is written, so memory stays flat
+//! however many models a project has. The documents match what serializing the whole
+//! manifest at once produces: `{"models": {...}}` for JSON and `version: 1` plus `models:`
+//! for YAML.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    Json,
+    Yaml,
+}
+
+/// Version written at the top of YAML manifests
+const YAML_MANIFEST_VERSION: i32 = 1;
+
+pub struct ManifestWriter<W: Write> {
+    out: W,
+    format: ManifestFormat,
+    models: usize,
+}
+
+impl<W: Write> ManifestWriter<W> {
+    /// Write the document header; models follow through `write_model`
+    pub fn new(mut out: W, format: ManifestFormat) -> Result<Self> {
+        match format {
+            ManifestFormat::Json => write!(out, "{{\n  \"models\": {{"),
+            ManifestFormat::Yaml => write!(out, "version: {}\nmodels:", YAML_MANIFEST_VERSION),
+        }
+        .context("Failed to write manifest header")?;
+
+        Ok(Self {
+            out,
+            format,
+            models: 0,
+        })
+    }
+
+    /// Append one model under `unique_id`. Callers write models in the order they should
+    /// appear; ids are not checked for duplicates.
+    pub fn write_model(&mut self, unique_id: &str, model: &impl Serialize) -> Result<()> {
+        let context = || format!("Failed to write {} to the manifest", unique_id);
+        match self.format {
+            ManifestFormat::Json => {
+                let separator = if self.models == 0 { "" } else { "," };
+                let key = serde_json::to_string(unique_id).with_context(context)?;
+                write!(self.out, "{}\n    {}: ", separator, key).with_context(context)?;
+                serde_json::to_writer_pretty(Indented::new(&mut self.out, "    ", false), model)
+                    .with_context(context)?;
+            }
+            ManifestFormat::Yaml => {
+                writeln!(self.out).with_context(context)?;
+                // A single-entry map lets serde_yaml quote the id as a key where needed
+                let entry = BTreeMap::from([(unique_id, model)]);
+                serde_yaml::to_writer(Indented::new(&mut self.out, "  ", true), &entry)
+                    .with_context(context)?;
+            }
+        }
+
+        self.models += 1;
+        Ok(())
+    }
+
+    /// Close the document and flush the output
+    pub fn finish(mut self) -> Result<W> {
+        match (self.format, self.models) {
+            (ManifestFormat::Json, 0) => writeln!(self.out, "}}\n}}"),
+            (ManifestFormat::Json, _) => writeln!(self.out, "\n  }}\n}}"),
+            (ManifestFormat::Yaml, 0) => writeln!(self.out, " {{}}"),
+            (ManifestFormat::Yaml, _) => writeln!(self.out),
+        }
+        .context("Failed to finish the manifest")?;
+
+        self.out.flush().context("Failed to flush the manifest")?;
+        Ok(self.out)
+    }
+}
+
+/// Prefixes every non-empty line with `indent`, nesting a serialized value in the document.
+/// A trailing newline is dropped so the caller decides what follows the value.
+struct Indented<'a, W: Write> {
+    out: &'a mut W,
+    indent: &'static str,
+    at_line_start: bool,
+    pending_newline: bool,
+}
+
+impl<'a, W: Write> Indented<'a, W> {
+    fn new(out: &'a mut W, indent: &'static str, at_line_start: bool) -> Self {
+        Self {
+            out,
+            indent,
+            at_line_start,
+            pending_newline: false,
+        }
+    }
+}
+
+impl<W: Write> Write for Indented<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for (i, line) in buf.split(|byte| *byte == b'\n').enumerate() {
+            if i > 0 {
+                if self.pending_newline {
+                    self.out.write_all(b"\n")?;
+                }
+                self.pending_newline = true;
+                self.at_line_start = true;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if self.pending_newline {
+                self.out.write_all(b"\n")?;
+                self.pending_newline = false;
+            }
+            if self.at_line_start {
+                self.out.write_all(self.indent.as_bytes())?;
+                self.at_line_start = false;
+            }
+            self.out.write_all(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_manifest(format: ManifestFormat, models: &[(&str, serde_json::Value)]) -> String {
+        let mut writer = ManifestWriter::new(Vec::new(), format).unwrap();
+        for (id, model) in models {
+            writer.write_model(id, model).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_streamed_json_matches_whole_document() {
+        let models = [
+            (
+                "model.a.a",
+                json!({"name": "a", "tags": ["x"], "columns": []}),
+            ),
+            ("model.b.b", json!({"name": "b\nline", "depth": null})),
+        ];
+        let streamed = write_manifest(ManifestFormat::Json, &models);
+
+        let whole = json!({"models": {"model.a.a": models[0].1, "model.b.b": models[1].1}});
+        assert_eq!(
+            streamed,
+            format!("{}\n", serde_json::to_string_pretty(&whole).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_streamed_yaml_parses_back() {
+        let models = [
+            (
+                "model.a.a",
+                json!({"name": "a", "description": "two\nlines\n", "tags": ["x"]}),
+            ),
+            (
+                "model.b: odd",
+                json!({"name": "b", "columns": [{"name": "id"}]}),
+            ),
+        ];
+        let streamed = write_manifest(ManifestFormat::Yaml, &models);
+
+        let parsed: serde_json::Value = serde_yaml::from_str(&streamed).unwrap();
+        assert_eq!(
+            parsed,
+            json!({"version": 1, "models": {"model.a.a": models[0].1, "model.b: odd": models[1].1}})
+        );
+    }
+
+    #[test]
+    fn test_empty_manifests() {
+        let json: serde_json::Value =
+            serde_json::from_str(&write_manifest(ManifestFormat::Json, &[])).unwrap();
+        assert_eq!(json, json!({"models": {}}));
+
+        let yaml: serde_json::Value =
+            serde_yaml::from_str(&write_manifest(ManifestFormat::Yaml, &[])).unwrap();
+        assert_eq!(yaml, json!({"version": 1, "models": {}}));
+    }
+}
@@ -1,10 +1,30 @@
 //! SQL Engine module for parsing and executing SQL queries
 
+pub mod annotations;
 pub mod ast_utils;
+pub mod checksum;
+pub mod column_usage;
+pub mod comments;
+pub mod cost;
+pub mod ddl;
+pub mod dialect;
 pub mod extractors;
+#[cfg(test)]
+pub mod fixtures;
+pub mod keys;
 pub mod lineage;
+pub mod lint;
+pub mod manifest_writer;
+pub mod nodes;
+pub mod nullability;
+pub mod projection;
+pub mod quoting;
+pub mod references;
+pub mod similarity;
 pub mod sql_model;
+pub mod statements;
 pub mod tables;
+pub mod template;
 
 #[cfg(test)]
 mod tests;
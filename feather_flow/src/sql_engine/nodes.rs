@@ -0,0 +1,402 @@
+//! Every resource type a project is made of, stored together in `SqlModelCollection`
+//!
+//! Models and snapshots are SQL files; sources are declared in `models/imports/*.yml`;
+//! seeds are CSV files in `seeds/`; column tests come from YAML `tests:` and `-- ff:test`
+//! annotations, and run as queries counting failing rows; exposures (dashboards,
+//! applications) are declared next to sources under `exposures:`.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use super::sql_model::SqlModel;
+use super::template::DEFAULT_SCHEMA;
+use crate::adapters::string_literal;
+use crate::config::awaits::Await;
+use crate::config::freshness::Freshness;
+
+/// Directory, relative to the project root, holding seed CSV files
+pub const SEEDS_DIR: &str = "seeds";
+
+/// Materialization marking a model as a snapshot
+pub const SNAPSHOT_MATERIALIZATION: &str = "snapshot";
+
+/// Column recording when a snapshot row was captured, for snapshots without a `unique_key`
+pub const SNAPSHOT_AT_COLUMN: &str = "ff_snapshot_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourceType {
+    Model,
+    Source,
+    Seed,
+    Snapshot,
+    Test,
+    Exposure,
+}
+
+impl ResourceType {
+    pub const ALL: [ResourceType; 6] = [
+        ResourceType::Model,
+        ResourceType::Source,
+        ResourceType::Seed,
+        ResourceType::Snapshot,
+        ResourceType::Test,
+        ResourceType::Exposure,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceType::Model => "model",
+            ResourceType::Source => "source",
+            ResourceType::Seed => "seed",
+            ResourceType::Snapshot => "snapshot",
+            ResourceType::Test => "test",
+            ResourceType::Exposure => "exposure",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|resource_type| resource_type.as_str() == name)
+    }
+}
+
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An external table declared in `models/imports/`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub unique_id: String,
+    pub name: String,
+    /// `schema.table` as referenced from model SQL
+    pub relation: String,
+    /// Columns declared for the table in YAML
+    pub columns: Vec<String>,
+    /// Timestamp column recording when each row was loaded; the table's, else its source's
+    pub loaded_at_field: Option<String>,
+    /// Freshness thresholds; the table's, else its source's
+    pub freshness: Option<Freshness>,
+    /// Signal waited for before the nodes reading the table build; the table's, else its
+    /// source's
+    pub awaited: Option<Await>,
+}
+
+impl Source {
+    pub fn new(relation: &str) -> Self {
+        Self {
+            unique_id: format!("source.{}", relation),
+            name: relation.rsplit('.').next().unwrap_or(relation).to_string(),
+            relation: relation.to_string(),
+            columns: Vec::new(),
+            loaded_at_field: None,
+            freshness: None,
+            awaited: None,
+        }
+    }
+}
+
+/// A CSV file loaded into `<schema>.<file stem>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seed {
+    pub unique_id: String,
+    pub name: String,
+    pub path: PathBuf,
+    /// Schema the seed is loaded into; differs from the default under schema isolation
+    pub schema: String,
+}
+
+impl Seed {
+    pub fn new(path: PathBuf, name: &str) -> Self {
+        Self {
+            unique_id: format!("seed.{}", name),
+            name: name.to_string(),
+            path,
+            schema: DEFAULT_SCHEMA.to_string(),
+        }
+    }
+
+    pub fn relation(&self) -> String {
+        format!("{}.{}", self.schema, self.name)
+    }
+
+    /// Columns of the seed, from the CSV header
+    pub fn columns(&self) -> anyhow::Result<Vec<String>> {
+        Ok(csv::Reader::from_path(&self.path)
+            .and_then(|mut reader| reader.headers().cloned())
+            .with_context(|| format!("Failed to read the header of {}", self.path.display()))?
+            .iter()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Whether model SQL referencing `table` reads this seed: as `public.<name>` or `<name>`
+    pub fn is_referenced_as(&self, table: &str) -> bool {
+        table.eq_ignore_ascii_case(&self.name)
+            || table.eq_ignore_ascii_case(&format!("{}.{}", DEFAULT_SCHEMA, self.name))
+    }
+}
+
+/// A column test such as `not_null(customer_id)` on a model or snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnTest {
+    pub unique_id: String,
+    pub name: String,
+    pub model_id: String,
+    pub test: String,
+    pub column: String,
+    pub tags: Vec<String>,
+    /// Arguments given in YAML, e.g. the `values` of `accepted_values`
+    pub args: BTreeMap<String, serde_json::Value>,
+    /// Model or seed a `relationships` test checks the column against, resolved from its `to:`
+    pub to_id: Option<String>,
+}
+
+impl ColumnTest {
+    pub fn new(model: &SqlModel, test: &str, column: &str) -> Self {
+        let model_path = model
+            .unique_id
+            .strip_prefix("model.")
+            .unwrap_or(&model.unique_id);
+
+        Self {
+            unique_id: format!("test.{}.{}_{}", model_path, test, column),
+            name: format!("{}_{}_{}", test, model.name, column),
+            model_id: model.unique_id.clone(),
+            test: test.to_string(),
+            column: column.to_string(),
+            tags: model.tags.clone(),
+            args: BTreeMap::new(),
+            to_id: None,
+        }
+    }
+
+    /// The model or seed name of a `relationships` test's `to:`, which may be written as
+    /// `ref('customers')` or `customers`
+    pub fn to_name(&self) -> Option<&str> {
+        let to = self.args.get("to")?.as_str()?.trim();
+        let name = to
+            .strip_prefix("ref(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .map(|inner| inner.trim().trim_matches(|c| c == '\'' || c == '"'))
+            .unwrap_or(to);
+        Some(name).filter(|name| !name.is_empty())
+    }
+
+    /// Query counting the rows of the model's `relation` that fail the test, or `None` for
+    /// tests that cannot run against the warehouse. `to_relation` is the relation of `to_id`.
+    pub fn sql(&self, relation: &str, to_relation: Option<&str>) -> Option<String> {
+        match self.test.as_str() {
+            "not_null" => Some(format!(
+                "SELECT count(*) AS failures FROM {} WHERE {} IS NULL",
+                relation, self.column
+            )),
+            "unique" => Some(format!(
+                "SELECT count(*) AS failures FROM (SELECT {column} FROM {} WHERE {column} IS NOT NULL GROUP BY {column} HAVING count(*) > 1) AS duplicates",
+                relation,
+                column = self.column
+            )),
+            "accepted_values" => {
+                let values = self
+                    .args
+                    .get("values")?
+                    .as_array()?
+                    .iter()
+                    .map(value_literal)
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|values| !values.is_empty())?;
+                Some(format!(
+                    "SELECT count(*) AS failures FROM {} WHERE {column} IS NOT NULL AND {column} NOT IN ({})",
+                    relation,
+                    values.join(", "),
+                    column = self.column
+                ))
+            }
+            "relationships" => {
+                let field = self.args.get("field")?.as_str()?;
+                Some(format!(
+                    "SELECT count(*) AS failures FROM {} AS child WHERE child.{column} IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {} AS parent WHERE parent.{} = child.{column})",
+                    relation,
+                    to_relation?,
+                    field,
+                    column = self.column
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An accepted value as a SQL literal; only strings, numbers and booleans are accepted
+fn value_literal(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(string_literal(text)),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// A downstream use of the project, such as a dashboard, declared under `exposures:`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exposure {
+    #[serde(skip)]
+    pub unique_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner: Option<String>,
+    /// Names or unique ids of the models the exposure reads
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Model(SqlModel),
+    Source(Source),
+    Seed(Seed),
+    Snapshot(SqlModel),
+    Test(ColumnTest),
+    Exposure(Exposure),
+}
+
+impl Node {
+    /// Wrap a model, as a snapshot when it is materialized as one
+    pub fn from_model(model: SqlModel) -> Self {
+        if model.materialized.as_deref() == Some(SNAPSHOT_MATERIALIZATION) {
+            Node::Snapshot(model)
+        } else {
+            Node::Model(model)
+        }
+    }
+
+    pub fn unique_id(&self) -> &str {
+        match self {
+            Node::Model(model) | Node::Snapshot(model) => &model.unique_id,
+            Node::Source(source) => &source.unique_id,
+            Node::Seed(seed) => &seed.unique_id,
+            Node::Test(test) => &test.unique_id,
+            Node::Exposure(exposure) => &exposure.unique_id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Node::Model(model) | Node::Snapshot(model) => &model.name,
+            Node::Source(source) => &source.name,
+            Node::Seed(seed) => &seed.name,
+            Node::Test(test) => &test.name,
+            Node::Exposure(exposure) => &exposure.name,
+        }
+    }
+
+    pub fn resource_type(&self) -> ResourceType {
+        match self {
+            Node::Model(_) => ResourceType::Model,
+            Node::Source(_) => ResourceType::Source,
+            Node::Seed(_) => ResourceType::Seed,
+            Node::Snapshot(_) => ResourceType::Snapshot,
+            Node::Test(_) => ResourceType::Test,
+            Node::Exposure(_) => ResourceType::Exposure,
+        }
+    }
+
+    pub fn tags(&self) -> &[String] {
+        match self {
+            Node::Model(model) | Node::Snapshot(model) => &model.tags,
+            Node::Test(test) => &test.tags,
+            Node::Exposure(exposure) => &exposure.tags,
+            Node::Source(_) | Node::Seed(_) => &[],
+        }
+    }
+
+    /// The model or snapshot, if the node is one
+    pub fn as_model(&self) -> Option<&SqlModel> {
+        match self {
+            Node::Model(model) | Node::Snapshot(model) => Some(model),
+            _ => None,
+        }
+    }
+
+    pub fn as_model_mut(&mut self) -> Option<&mut SqlModel> {
+        match self {
+            Node::Model(model) | Node::Snapshot(model) => Some(model),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+
+    #[test]
+    fn test_resource_type_names_round_trip() {
+        for resource_type in ResourceType::ALL {
+            assert_eq!(
+                ResourceType::from_name(resource_type.as_str()),
+                Some(resource_type)
+            );
+        }
+        assert_eq!(ResourceType::from_name("metric"), None);
+    }
+
+    #[test]
+    fn test_seed_references() {
+        let mut seed = Seed::new(PathBuf::from("seeds/countries.csv"), "countries");
+        assert!(seed.is_referenced_as("public.Countries"));
+        assert!(seed.is_referenced_as("countries"));
+        assert!(!seed.is_referenced_as("raw.countries"));
+
+        seed.schema = "public_alice".to_string();
+        assert_eq!(seed.relation(), "public_alice.countries");
+        assert!(seed.is_referenced_as("public.countries"));
+    }
+
+    #[test]
+    fn test_source_from_relation() {
+        let source = Source::new("raw.orders");
+        assert_eq!(source.unique_id, "source.raw.orders");
+        assert_eq!(source.name, "orders");
+    }
+
+    #[test]
+    fn test_column_tests_with_arguments() {
+        let model = SqlModel::from_content(
+            std::path::Path::new("orders/orders.sql"),
+            std::path::Path::new(""),
+            "SELECT 1 AS status".to_string(),
+            "duckdb",
+            &sqlparser::dialect::DuckDbDialect {},
+            &Sha256Hash,
+        )
+        .unwrap();
+
+        let mut accepted = ColumnTest::new(&model, "accepted_values", "status");
+        assert_eq!(accepted.sql("public.orders", None), None);
+        accepted.args = serde_json::from_str(r#"{"values": ["open", "it's", 3]}"#).unwrap();
+        assert_eq!(
+            accepted.sql("public.orders", None).unwrap(),
+            "SELECT count(*) AS failures FROM public.orders WHERE status IS NOT NULL AND status NOT IN ('open', 'it''s', 3)"
+        );
+
+        let mut relationship = ColumnTest::new(&model, "relationships", "customer_id");
+        relationship.args =
+            serde_json::from_str(r#"{"to": "ref('customers')", "field": "id"}"#).unwrap();
+        assert_eq!(relationship.to_name(), Some("customers"));
+        assert_eq!(relationship.sql("public.orders", None), None);
+        assert!(relationship
+            .sql("public.orders", Some("public.customers"))
+            .unwrap()
+            .contains("NOT EXISTS (SELECT 1 FROM public.customers AS parent WHERE parent.id = child.customer_id)"));
+    }
+}
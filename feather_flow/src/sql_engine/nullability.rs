@@ -0,0 +1,249 @@
+//! Which columns of a query can be NULL, and why
+//!
+//! Worked out alongside each projection (see `sql_engine::projection`). A column can be NULL
+//! when it reads the side of an outer join that unmatched rows fill with NULLs, unless the
+//! WHERE clause requires a column of that side; when it is a NULL literal, a CASE without ELSE
+//! or a NULLIF; or when it reads a column that can be NULL in a CTE, subquery or upstream
+//! model, unless the upstream model tests that column `not_null`. Operators and functions over
+//! such a column can return NULL too, except COALESCE with an argument that cannot, COUNT,
+//! AND, OR and the IS [NOT] NULL family. Anything else is taken as not NULL, so a column is
+//! only reported when the SQL provably lets NULLs into it.
+
+use sqlparser::ast::{
+    BinaryOperator, Expr, FunctionArg, FunctionArgExpr, FunctionArguments, Value,
+};
+use std::fmt;
+
+use super::sql_model::SqlModel;
+
+/// Name of the column test declaring a column never NULL
+pub const NOT_NULL_TEST: &str = "not_null";
+
+/// Why a column can be NULL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NullReason {
+    /// Reads a relation an outer join fills with NULLs for rows without a match
+    OuterJoin {
+        relation: String,
+    },
+    NullLiteral,
+    CaseWithoutElse,
+    NullIf,
+    /// Reads a column of an upstream model that can be NULL
+    Upstream {
+        model: String,
+        column: String,
+    },
+}
+
+impl fmt::Display for NullReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NullReason::OuterJoin { relation } => write!(
+                f,
+                "reads {}, which an outer join fills with NULLs for rows without a match",
+                relation
+            ),
+            NullReason::NullLiteral => write!(f, "is a NULL literal"),
+            NullReason::CaseWithoutElse => write!(f, "is a CASE without ELSE"),
+            NullReason::NullIf => write!(f, "is a NULLIF"),
+            NullReason::Upstream { model, column } => {
+                write!(f, "reads {}.{}, which can be NULL", model, column)
+            }
+        }
+    }
+}
+
+/// Whether the model's YAML tests `column` `not_null`
+pub fn declared_not_null(model: &SqlModel, column: &str) -> bool {
+    model
+        .columns
+        .values()
+        .filter(|info| info.name.eq_ignore_ascii_case(column))
+        .any(|info| info.tests.iter().any(|test| test == NOT_NULL_TEST))
+}
+
+/// Why `expr` can be NULL, given why each column it reads can be. `column` takes the
+/// qualifier, if any, and the name of a column reference.
+pub fn expr(
+    expr: &Expr,
+    column: &dyn Fn(Option<&str>, &str) -> Option<NullReason>,
+) -> Option<NullReason> {
+    let operand = |operand: &Expr| self::expr(operand, column);
+    match expr {
+        Expr::Identifier(ident) => column(None, &ident.value),
+        Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [.., table, name] => column(Some(&table.value), &name.value),
+            [name] => column(None, &name.value),
+            [] => None,
+        },
+        Expr::Value(Value::Null) => Some(NullReason::NullLiteral),
+        Expr::Nested(inner)
+        | Expr::UnaryOp { expr: inner, .. }
+        | Expr::Cast { expr: inner, .. } => operand(inner),
+        // NULL AND FALSE is FALSE and NULL OR TRUE is TRUE
+        Expr::BinaryOp {
+            op: BinaryOperator::And | BinaryOperator::Or,
+            ..
+        } => None,
+        Expr::BinaryOp { left, right, .. } => operand(left).or_else(|| operand(right)),
+        Expr::Case {
+            results,
+            else_result,
+            ..
+        } => match else_result {
+            None => Some(NullReason::CaseWithoutElse),
+            Some(else_result) => results
+                .iter()
+                .chain(std::iter::once(else_result.as_ref()))
+                .find_map(operand),
+        },
+        Expr::Function(function) => {
+            let name = function
+                .name
+                .0
+                .last()
+                .map(|ident| ident.value.to_lowercase())
+                .unwrap_or_default();
+            let args = function_args(&function.args);
+            match name.as_str() {
+                "count" => None,
+                "nullif" => Some(NullReason::NullIf),
+                // NULL only when every argument is
+                "coalesce" | "ifnull" => args
+                    .iter()
+                    .map(|arg| operand(arg))
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|reasons| reasons.into_iter().last()),
+                _ => args.into_iter().find_map(operand),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Columns the WHERE condition `selection` only keeps rows for when they are not NULL, as
+/// qualifier and name: those compared by a top-level condition or tested IS NOT NULL
+pub fn required_columns(selection: &Expr) -> Vec<(Option<String>, String)> {
+    let reference = |expr: &Expr| match expr {
+        Expr::Identifier(ident) => Some((None, ident.value.clone())),
+        Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [.., table, name] => Some((Some(table.value.clone()), name.value.clone())),
+            _ => None,
+        },
+        _ => None,
+    };
+    match selection {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut columns = required_columns(left);
+            columns.extend(required_columns(right));
+            columns
+        }
+        Expr::Nested(inner) => required_columns(inner),
+        Expr::IsNotNull(operand) => reference(operand).into_iter().collect(),
+        Expr::BinaryOp {
+            left,
+            op:
+                BinaryOperator::Eq
+                | BinaryOperator::NotEq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq,
+            right,
+        } => reference(left)
+            .into_iter()
+            .chain(reference(right))
+            .collect(),
+        Expr::InList { expr, .. } | Expr::Between { expr, .. } | Expr::Like { expr, .. } => {
+            reference(expr).into_iter().collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn function_args(args: &FunctionArguments) -> Vec<&Expr> {
+    let FunctionArguments::List(list) = args else {
+        return Vec::new();
+    };
+    list.args
+        .iter()
+        .filter_map(|arg| {
+            let (FunctionArg::Named { arg, .. }
+            | FunctionArg::ExprNamed { arg, .. }
+            | FunctionArg::Unnamed(arg)) = arg;
+            match arg {
+                FunctionArgExpr::Expr(expr) => Some(expr),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::dialect::DuckDbDialect;
+    use sqlparser::parser::Parser;
+
+    fn reason(sql: &str) -> Option<NullReason> {
+        let parsed = Parser::new(&DuckDbDialect {})
+            .try_with_sql(sql)
+            .unwrap()
+            .parse_expr()
+            .unwrap();
+        // Only `c.*` columns can be NULL, because c is outer joined
+        expr(&parsed, &|table, _| {
+            (table == Some("c")).then(|| NullReason::OuterJoin {
+                relation: "c".to_string(),
+            })
+        })
+    }
+
+    #[test]
+    fn test_expression_nullability() {
+        let outer = Some(NullReason::OuterJoin {
+            relation: "c".to_string(),
+        });
+        assert_eq!(reason("c.name"), outer);
+        assert_eq!(reason("upper(c.name) || o.id"), outer);
+        assert_eq!(reason("o.amount * 2"), None);
+        assert_eq!(reason("coalesce(c.name, 'unknown')"), None);
+        assert_eq!(reason("coalesce(c.name, c.email)"), outer);
+        assert_eq!(reason("count(c.id)"), None);
+        assert_eq!(reason("c.id IS NULL"), None);
+        assert_eq!(
+            reason("CAST(NULL AS INTEGER)"),
+            Some(NullReason::NullLiteral)
+        );
+        assert_eq!(reason("nullif(o.amount, 0)"), Some(NullReason::NullIf));
+        assert_eq!(
+            reason("CASE WHEN o.amount > 0 THEN 'paid' END"),
+            Some(NullReason::CaseWithoutElse)
+        );
+        assert_eq!(
+            reason("CASE WHEN o.amount > 0 THEN c.name ELSE 'x' END"),
+            outer
+        );
+    }
+
+    #[test]
+    fn test_required_columns() {
+        let selection = Parser::new(&DuckDbDialect {})
+            .try_with_sql("c.region = 'EU' AND (status IS NOT NULL) AND (c.id IS NULL OR x > 1)")
+            .unwrap()
+            .parse_expr()
+            .unwrap();
+        assert_eq!(
+            required_columns(&selection),
+            vec![
+                (Some("c".to_string()), "region".to_string()),
+                (None, "status".to_string()),
+            ]
+        );
+    }
+}
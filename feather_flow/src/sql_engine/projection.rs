@@ -0,0 +1,618 @@
+//! Columns a model's SQL produces
+//!
+//! Read from the model's last query: aliased expressions and plain column references name
+//! themselves, and `*` or `t.*` expand to the columns of the relations they read. Those come
+//! from the query's CTEs and subqueries, or from the upstream model, source or seed the
+//! relation names: an upstream model's own projection, a source's declared columns or a
+//! seed's CSV header. Models and sources whose columns are not known that way fall back to
+//! the warehouse schema recorded by `ff sync-schemas`, and models then to their YAML
+//! columns. A projection is incomplete when part
+//! of it cannot be named, e.g. an expression without an alias or a `*` over a relation whose
+//! columns are unknown. Each projection also records which of its columns can be NULL (see
+//! `sql_engine::nullability`).
+
+use sqlparser::ast::{
+    ExcludeSelectItem, Expr, JoinOperator, ObjectName, Query, Select, SelectItem, SetExpr,
+    SetOperator, Statement, TableAliasColumnDef, TableFactor, WildcardAdditionalOptions,
+};
+use sqlparser::dialect::{BigQueryDialect, DuckDbDialect};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::nodes::Node;
+use super::nullability::{self, NullReason};
+use super::sql_model::{SqlModel, SqlModelCollection};
+use super::statements;
+use crate::config::SqlDialect;
+
+/// Output columns of a query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Projection {
+    /// Every column that could be named, in output order
+    pub columns: Vec<String>,
+    /// Whether `columns` is all of the output
+    pub complete: bool,
+    /// Why each column that can be NULL can be, by lowercase name
+    pub nullable: BTreeMap<String, NullReason>,
+}
+
+impl Projection {
+    fn unknown() -> Self {
+        Self::default()
+    }
+}
+
+/// Works out the projections of a collection's models, each at most once
+pub struct Projector<'a> {
+    collection: &'a SqlModelCollection,
+    projections: HashMap<String, Option<Projection>>,
+    /// Models being worked out, so a dependency cycle ends instead of recursing forever
+    visiting: HashSet<String>,
+}
+
+/// A relation a SELECT reads
+#[derive(Debug, Clone, Default)]
+struct Relation {
+    /// Name its columns are qualified with
+    name: String,
+    /// Its columns, when they are known
+    columns: Option<Vec<String>>,
+    /// Why each of its columns that can be NULL can be, by lowercase name
+    nullable: BTreeMap<String, NullReason>,
+    /// Whether an outer join fills its columns with NULLs for rows without a match
+    outer: bool,
+}
+
+impl Relation {
+    fn has_column(&self, column: &str) -> bool {
+        self.columns.as_ref().is_some_and(|columns| {
+            columns
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(column))
+        })
+    }
+
+    /// Why its column `column` can be NULL
+    fn null_reason(&self, column: &str) -> Option<NullReason> {
+        if self.outer {
+            return Some(NullReason::OuterJoin {
+                relation: self.name.clone(),
+            });
+        }
+        self.nullable.get(&column.to_lowercase()).cloned()
+    }
+}
+
+impl<'a> Projector<'a> {
+    pub fn new(collection: &'a SqlModelCollection) -> Self {
+        Self {
+            collection,
+            projections: HashMap::new(),
+            visiting: HashSet::new(),
+        }
+    }
+
+    /// Projection of the model or snapshot `id`; None when its SQL has no query
+    pub fn model(&mut self, id: &str) -> Option<Projection> {
+        if let Some(projection) = self.projections.get(id) {
+            return projection.clone();
+        }
+        if !self.visiting.insert(id.to_string()) {
+            return Some(Projection::unknown());
+        }
+        let projection = self
+            .collection
+            .get_model(id)
+            .and_then(|model| self.work_out(model));
+        self.visiting.remove(id);
+        self.projections.insert(id.to_string(), projection.clone());
+        projection
+    }
+
+    fn work_out(&mut self, model: &SqlModel) -> Option<Projection> {
+        let sql = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+        let (parsed, _) = match SqlDialect::from_name(&model.dialect) {
+            Some(SqlDialect::BigQuery) => statements::parse_statements(&BigQueryDialect {}, sql),
+            _ => statements::parse_statements(&DuckDbDialect {}, sql),
+        };
+        let query = parsed.iter().rev().find_map(|statement| match statement {
+            Statement::Query(query) => Some(query),
+            _ => None,
+        })?;
+        let upstream = self.collection.node_dependencies(&model.unique_id);
+        let mut scope = Scope {
+            upstream: upstream.iter().map(String::as_str).collect(),
+            ctes: HashMap::new(),
+        };
+        Some(self.query(query, &mut scope))
+    }
+
+    fn query(&mut self, query: &Query, scope: &mut Scope) -> Projection {
+        let outer = scope.ctes.clone();
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                let mut projection = self.query(&cte.query, scope);
+                if !cte.alias.columns.is_empty() {
+                    rename(&mut projection, &cte.alias.columns);
+                }
+                scope
+                    .ctes
+                    .insert(cte.alias.name.value.to_lowercase(), projection);
+            }
+        }
+        let projection = self.set_expr(&query.body, scope);
+        scope.ctes = outer;
+        projection
+    }
+
+    fn set_expr(&mut self, body: &SetExpr, scope: &mut Scope) -> Projection {
+        match body {
+            SetExpr::Select(select) => {
+                let relations = self.relations(select, scope);
+                select_items(&select.projection, &relations)
+            }
+            SetExpr::Query(query) => self.query(query, scope),
+            // The first branch names the columns; in a UNION, a column can be NULL if it can in
+            // either branch
+            SetExpr::SetOperation {
+                left, right, op, ..
+            } => {
+                let mut projection = self.set_expr(left, scope);
+                if projection.complete && *op == SetOperator::Union {
+                    let reasons = self.branch_nullability(right, scope);
+                    for (column, reason) in projection.columns.iter().zip(reasons) {
+                        if let Some(reason) = reason {
+                            projection
+                                .nullable
+                                .entry(column.to_lowercase())
+                                .or_insert(reason);
+                        }
+                    }
+                }
+                projection
+            }
+            _ => Projection::unknown(),
+        }
+    }
+
+    /// Why each output column of a set operation's branch can be NULL, by position; empty
+    /// when the positions are not known
+    fn branch_nullability(&mut self, body: &SetExpr, scope: &mut Scope) -> Vec<Option<NullReason>> {
+        match body {
+            // Columns of a branch after the first need no names, e.g. `UNION ALL SELECT 1, NULL`
+            SetExpr::Select(select) if !selects_wildcard(&select.projection) => {
+                let relations = self.relations(select, scope);
+                select
+                    .projection
+                    .iter()
+                    .map(|item| match item {
+                        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                            null_reason(expr, &relations)
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            }
+            _ => {
+                let projection = self.set_expr(body, scope);
+                if !projection.complete {
+                    return Vec::new();
+                }
+                projection
+                    .columns
+                    .iter()
+                    .map(|column| projection.nullable.get(&column.to_lowercase()).cloned())
+                    .collect()
+            }
+        }
+    }
+
+    /// Relations a SELECT reads, in FROM order, with those outer joins fill with NULLs marked
+    fn relations(&mut self, select: &Select, scope: &mut Scope) -> Vec<Relation> {
+        let mut relations = Vec::new();
+        for from in &select.from {
+            let first = relations.len();
+            relations.push(self.relation(&from.relation, scope));
+            for join in &from.joins {
+                let mut relation = self.relation(&join.relation, scope);
+                match &join.join_operator {
+                    JoinOperator::LeftOuter(_) => relation.outer = true,
+                    JoinOperator::RightOuter(_) => {
+                        relations[first..].iter_mut().for_each(|r| r.outer = true)
+                    }
+                    JoinOperator::FullOuter(_) => {
+                        relations[first..].iter_mut().for_each(|r| r.outer = true);
+                        relation.outer = true;
+                    }
+                    _ => {}
+                }
+                relations.push(relation);
+            }
+        }
+        // A WHERE condition on a column of the outer side drops its unmatched rows
+        if let Some(selection) = &select.selection {
+            for (table, column) in nullability::required_columns(selection) {
+                if let Some(index) = find(&relations, table.as_deref(), &column) {
+                    relations[index].outer = false;
+                }
+            }
+        }
+        relations
+    }
+
+    fn relation(&mut self, relation: &TableFactor, scope: &mut Scope) -> Relation {
+        match relation {
+            TableFactor::Table { name, alias, .. } => {
+                let table = last_part(name);
+                let cte = (name.0.len() == 1)
+                    .then(|| scope.ctes.get(&table.to_lowercase()))
+                    .flatten()
+                    .cloned();
+                let (columns, nullable) = match cte {
+                    Some(projection) => (
+                        projection.complete.then_some(projection.columns),
+                        projection.nullable,
+                    ),
+                    None => self.upstream(&table, &scope.upstream),
+                };
+                Relation {
+                    name: alias
+                        .as_ref()
+                        .map_or(table, |alias| alias.name.value.clone()),
+                    columns,
+                    nullable,
+                    outer: false,
+                }
+            }
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => {
+                let mut projection = self.query(subquery, scope);
+                let name = match alias {
+                    Some(alias) => {
+                        if !alias.columns.is_empty() {
+                            rename(&mut projection, &alias.columns);
+                        }
+                        alias.name.value.clone()
+                    }
+                    None => String::new(),
+                };
+                Relation {
+                    name,
+                    columns: projection.complete.then_some(projection.columns),
+                    nullable: projection.nullable,
+                    outer: false,
+                }
+            }
+            TableFactor::Function { alias, .. }
+            | TableFactor::TableFunction { alias, .. }
+            | TableFactor::UNNEST { alias, .. } => Relation {
+                name: alias
+                    .as_ref()
+                    .map(|alias| alias.name.value.clone())
+                    .unwrap_or_default(),
+                ..Relation::default()
+            },
+            _ => Relation::default(),
+        }
+    }
+
+    /// Columns of the upstream node read as `table`, when they are known, and why those that
+    /// can be NULL can be
+    fn upstream(
+        &mut self,
+        table: &str,
+        upstream: &[&str],
+    ) -> (Option<Vec<String>>, BTreeMap<String, NullReason>) {
+        let collection = self.collection;
+        let Some(node) = upstream
+            .iter()
+            .filter_map(|id| collection.get_node(id))
+            .find(|node| match node {
+                Node::Model(model) | Node::Snapshot(model) => {
+                    model.relation_name().eq_ignore_ascii_case(table)
+                }
+                Node::Source(source) => source.name.eq_ignore_ascii_case(table),
+                Node::Seed(seed) => seed.name.eq_ignore_ascii_case(table),
+                Node::Test(_) | Node::Exposure(_) => false,
+            })
+        else {
+            return (None, BTreeMap::new());
+        };
+        let tables = collection.table_schemas();
+        match node {
+            Node::Model(model) | Node::Snapshot(model) => {
+                let projection = self.model(&model.unique_id).unwrap_or_default();
+                // A column the model tests not_null is reported there, not downstream
+                let nullable = projection
+                    .columns
+                    .iter()
+                    .filter(|column| !nullability::declared_not_null(model, column))
+                    .filter(|column| projection.nullable.contains_key(&column.to_lowercase()))
+                    .map(|column| {
+                        let reason = NullReason::Upstream {
+                            model: model.name.clone(),
+                            column: column.clone(),
+                        };
+                        (column.to_lowercase(), reason)
+                    })
+                    .collect();
+                let columns = if projection.complete {
+                    Some(projection.columns)
+                } else {
+                    tables
+                        .get_column_names(&model.qualified_relation())
+                        .or_else(|| {
+                            let mut columns: Vec<String> = model.columns.keys().cloned().collect();
+                            columns.sort();
+                            (!columns.is_empty()).then_some(columns)
+                        })
+                };
+                (columns, nullable)
+            }
+            Node::Source(source) if !source.columns.is_empty() => {
+                (Some(source.columns.clone()), BTreeMap::new())
+            }
+            Node::Source(source) => (tables.get_column_names(&source.relation), BTreeMap::new()),
+            Node::Seed(seed) => (seed.columns().ok(), BTreeMap::new()),
+            _ => (None, BTreeMap::new()),
+        }
+    }
+}
+
+struct Scope<'u> {
+    /// Unique ids of the nodes the model reads
+    upstream: Vec<&'u str>,
+    /// Projections of the CTEs in scope, by lowercase name
+    ctes: HashMap<String, Projection>,
+}
+
+/// Index of the relation a column reference reads: the one named `table`, else the one
+/// known to have the column, else the only one
+fn find(relations: &[Relation], table: Option<&str>, column: &str) -> Option<usize> {
+    match table {
+        Some(table) => relations
+            .iter()
+            .position(|relation| relation.name.eq_ignore_ascii_case(table)),
+        None => relations
+            .iter()
+            .position(|relation| relation.has_column(column))
+            .or((relations.len() == 1).then_some(0)),
+    }
+}
+
+/// Why `expr`, over `relations`, can be NULL
+fn null_reason(expr: &Expr, relations: &[Relation]) -> Option<NullReason> {
+    nullability::expr(expr, &|table, column| {
+        find(relations, table, column).and_then(|index| relations[index].null_reason(column))
+    })
+}
+
+fn selects_wildcard(items: &[SelectItem]) -> bool {
+    items.iter().any(|item| {
+        matches!(
+            item,
+            SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)
+        )
+    })
+}
+
+fn select_items(items: &[SelectItem], relations: &[Relation]) -> Projection {
+    let mut projection = Projection {
+        complete: true,
+        ..Projection::default()
+    };
+    let name = |projection: &mut Projection, name: &str, expr: &Expr| {
+        projection.columns.push(name.to_string());
+        if let Some(reason) = null_reason(expr, relations) {
+            projection.nullable.insert(name.to_lowercase(), reason);
+        }
+    };
+    for item in items {
+        match item {
+            SelectItem::ExprWithAlias { expr, alias } => name(&mut projection, &alias.value, expr),
+            SelectItem::UnnamedExpr(expr @ Expr::Identifier(ident)) => {
+                name(&mut projection, &ident.value, expr)
+            }
+            // `t.column` and struct fields are named after their last part
+            SelectItem::UnnamedExpr(expr @ Expr::CompoundIdentifier(idents)) => {
+                if let Some(ident) = idents.last() {
+                    name(&mut projection, &ident.value, expr);
+                }
+            }
+            SelectItem::UnnamedExpr(_) => projection.complete = false,
+            SelectItem::Wildcard(options) => {
+                for relation in relations {
+                    expand(&mut projection, Some(relation), options);
+                }
+            }
+            SelectItem::QualifiedWildcard(name, options) => {
+                let table = last_part(name);
+                let relation = relations
+                    .iter()
+                    .find(|relation| relation.name.eq_ignore_ascii_case(&table));
+                expand(&mut projection, relation, options);
+            }
+        }
+    }
+    projection
+}
+
+/// Add the columns a `*` over `relation` expands to, less those it excludes
+fn expand(
+    projection: &mut Projection,
+    relation: Option<&Relation>,
+    options: &WildcardAdditionalOptions,
+) {
+    let Some((relation, columns)) =
+        relation.and_then(|relation| Some((relation, relation.columns.as_ref()?)))
+    else {
+        projection.complete = false;
+        return;
+    };
+    let excluded: Vec<String> = match &options.opt_exclude {
+        Some(ExcludeSelectItem::Single(ident)) => vec![ident.value.to_lowercase()],
+        Some(ExcludeSelectItem::Multiple(idents)) => idents
+            .iter()
+            .map(|ident| ident.value.to_lowercase())
+            .collect(),
+        None => Vec::new(),
+    };
+    for column in columns {
+        if excluded.contains(&column.to_lowercase()) {
+            continue;
+        }
+        projection.columns.push(column.clone());
+        if let Some(reason) = relation.null_reason(column) {
+            projection.nullable.insert(column.to_lowercase(), reason);
+        }
+    }
+}
+
+/// Apply a column list such as `cte (a, b)` to the first columns of `projection`
+fn rename(projection: &mut Projection, names: &[TableAliasColumnDef]) {
+    let mut nullable = BTreeMap::new();
+    for (column, new) in projection.columns.iter_mut().zip(names) {
+        if let Some(reason) = projection.nullable.remove(&column.to_lowercase()) {
+            nullable.insert(new.name.value.to_lowercase(), reason);
+        }
+        *column = new.name.value.clone();
+    }
+    projection.nullable.extend(nullable);
+    if names.len() > projection.columns.len() {
+        let extra = &names[projection.columns.len()..];
+        projection
+            .columns
+            .extend(extra.iter().map(|new| new.name.value.clone()));
+    }
+}
+
+fn last_part(name: &ObjectName) -> String {
+    name.0
+        .last()
+        .map(|ident| ident.value.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::fixtures::write_model;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_projection_expands_wildcards_through_ctes_and_upstream_models() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(write_model(
+            root,
+            "stg_orders",
+            "SELECT id, customer_id, amount, created_at FROM raw.orders",
+            None,
+        ));
+        collection.add_model(write_model(
+            root,
+            "fct_orders",
+            "WITH orders AS (SELECT * EXCLUDE (created_at) FROM public.stg_orders)\n\
+             SELECT o.*, amount * 2 AS doubled, upper(o.id) FROM orders AS o",
+            None,
+        ));
+        collection.add_model(write_model(
+            root,
+            "customers",
+            "SELECT c.* FROM (SELECT customer_id FROM public.stg_orders) AS c (id)\n\
+             UNION ALL SELECT 1",
+            None,
+        ));
+        collection.build_dependency_graph();
+
+        let mut projector = Projector::new(&collection);
+        assert_eq!(
+            projector.model("model.fct_orders.fct_orders"),
+            Some(Projection {
+                columns: vec![
+                    "id".to_string(),
+                    "customer_id".to_string(),
+                    "amount".to_string(),
+                    "doubled".to_string()
+                ],
+                // upper(o.id) has no name
+                complete: false,
+                nullable: BTreeMap::new(),
+            })
+        );
+        assert_eq!(
+            projector.model("model.customers.customers"),
+            Some(Projection {
+                columns: vec!["id".to_string()],
+                complete: true,
+                nullable: BTreeMap::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_nullable_columns_through_outer_joins_ctes_and_upstream_models() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(write_model(
+            root,
+            "stg_orders",
+            "SELECT o.id, c.name AS customer_name, c.region, o.amount\n\
+             FROM raw.orders AS o LEFT JOIN raw.customers AS c ON o.customer_id = c.id",
+            Some(
+                "version: 2\nmodels:\n  - name: stg_orders\n    columns:\n      \
+                 - name: region\n        tests: [not_null]\n",
+            ),
+        ));
+        collection.add_model(write_model(
+            root,
+            "fct_orders",
+            "WITH paid AS (SELECT *, CASE WHEN amount > 0 THEN 'paid' END AS state FROM public.stg_orders)\n\
+             SELECT id, customer_name, region, state, coalesce(customer_name, 'n/a') AS label FROM paid\n\
+             UNION ALL SELECT 1, 'x', 'y', 'z', NULL",
+            None,
+        ));
+        collection.add_model(write_model(
+            root,
+            "eu_orders",
+            "SELECT o.id, c.name FROM raw.orders AS o LEFT JOIN raw.customers AS c ON o.customer_id = c.id\n\
+             WHERE c.region = 'EU'",
+            None,
+        ));
+        collection.build_dependency_graph();
+
+        let mut projector = Projector::new(&collection);
+        let nullable = |projector: &mut Projector, id: &str| -> Vec<(String, String)> {
+            projector
+                .model(id)
+                .unwrap()
+                .nullable
+                .into_iter()
+                .map(|(column, reason)| (column, reason.to_string()))
+                .collect()
+        };
+        let outer = "reads c, which an outer join fills with NULLs for rows without a match";
+        assert_eq!(
+            nullable(&mut projector, "model.stg_orders.stg_orders"),
+            vec![
+                ("customer_name".to_string(), outer.to_string()),
+                ("region".to_string(), outer.to_string()),
+            ]
+        );
+        // region is tested not_null upstream; label is NULL in the second branch
+        assert_eq!(
+            nullable(&mut projector, "model.fct_orders.fct_orders"),
+            vec![
+                (
+                    "customer_name".to_string(),
+                    "reads stg_orders.customer_name, which can be NULL".to_string()
+                ),
+                ("label".to_string(), "is a NULL literal".to_string()),
+                ("state".to_string(), "is a CASE without ELSE".to_string()),
+            ]
+        );
+        assert!(nullable(&mut projector, "model.eu_orders.eu_orders").is_empty());
+    }
+}
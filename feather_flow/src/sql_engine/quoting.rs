@@ -0,0 +1,67 @@
+//! Values written into SQL as literals and quoted identifiers, escaped for each dialect
+//!
+//! Everything FeatherFlow interpolates into SQL text goes through here: list variables,
+//! `literal()` and `identifier()` in templates, and the values adapters inline into their
+//! metadata queries. The DuckDB adapter runs SQL text through the CLI, so values are inlined
+//! as escaped literals rather than bound as parameters.
+
+use crate::config::SqlDialect;
+
+/// `value` as a single-quoted string literal: quotes are doubled for DuckDB, and quotes,
+/// backslashes and line breaks are backslash-escaped for BigQuery
+pub fn string_literal(value: &str, dialect: SqlDialect) -> String {
+    match dialect {
+        SqlDialect::DuckDb => format!("'{}'", value.replace('\'', "''")),
+        SqlDialect::BigQuery => {
+            let mut escaped = String::with_capacity(value.len() + 2);
+            escaped.push('\'');
+            for c in value.chars() {
+                match c {
+                    '\\' => escaped.push_str("\\\\"),
+                    '\'' => escaped.push_str("\\'"),
+                    '\n' => escaped.push_str("\\n"),
+                    '\r' => escaped.push_str("\\r"),
+                    _ => escaped.push(c),
+                }
+            }
+            escaped.push('\'');
+            escaped
+        }
+    }
+}
+
+/// `name` as a quoted identifier: in double quotes for DuckDB, in backticks for BigQuery
+pub fn identifier(name: &str, dialect: SqlDialect) -> String {
+    match dialect {
+        SqlDialect::DuckDb => format!("\"{}\"", name.replace('"', "\"\"")),
+        SqlDialect::BigQuery => {
+            format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escapes_per_dialect() {
+        let value = "it's\\ a\nvalue";
+        assert_eq!(
+            string_literal(value, SqlDialect::DuckDb),
+            "'it''s\\ a\nvalue'"
+        );
+        assert_eq!(
+            string_literal(value, SqlDialect::BigQuery),
+            "'it\\'s\\\\ a\\nvalue'"
+        );
+        assert_eq!(
+            identifier("my \"table\"", SqlDialect::DuckDb),
+            "\"my \"\"table\"\"\""
+        );
+        assert_eq!(
+            identifier("my `table`", SqlDialect::BigQuery),
+            "`my \\`table\\``"
+        );
+    }
+}
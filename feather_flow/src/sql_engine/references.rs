@@ -0,0 +1,178 @@
+//! Where in a model's SQL each referenced relation appears
+//!
+//! Locations are found in the statement text rather than taken from parser spans, so they
+//! point at the SQL as written (after template rendering) even when the parser sees a
+//! normalized statement.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+
+use super::dialect::code_mask;
+use super::extractors;
+use super::statements::ParsedSpan;
+
+/// A reference to a relation: the statement it occurs in and, when it can be found in the
+/// text, its 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub statement: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}", line, column),
+            _ => write!(f, "statement {}", self.statement),
+        }
+    }
+}
+
+/// Every location of each external relation referenced by `parsed`, keyed like
+/// `extractors::get_external_table_deps_set`
+pub fn locate_references(
+    sql: &str,
+    parsed: &[ParsedSpan],
+) -> BTreeMap<String, Vec<SourceLocation>> {
+    let mut references: BTreeMap<String, Vec<SourceLocation>> = BTreeMap::new();
+
+    for parsed_span in parsed {
+        let text = &parsed_span.span.text;
+        let mask = code_mask(text);
+
+        for relation in extractors::get_external_table_deps_set(&parsed_span.statements) {
+            let ranges = find_relation(text, &mask, &relation);
+            let locations = references.entry(relation).or_default();
+
+            if ranges.is_empty() {
+                locations.push(SourceLocation {
+                    statement: parsed_span.index,
+                    line: None,
+                    column: None,
+                });
+            }
+            for range in ranges {
+                let (line, column) = line_and_column(sql, parsed_span.span.start + range.start);
+                locations.push(SourceLocation {
+                    statement: parsed_span.index,
+                    line: Some(line),
+                    column: Some(column),
+                });
+            }
+        }
+    }
+
+    references
+}
+
+/// Replace every reference to a relation in `replacements` with its replacement, leaving
+/// comments, strings and the rest of the formatting untouched
+pub fn rewrite_relations(sql: &str, replacements: &HashMap<String, String>) -> String {
+    let mask = code_mask(sql);
+    let mut edits: Vec<(Range<usize>, &str)> = replacements
+        .iter()
+        .flat_map(|(relation, replacement)| {
+            find_relation(sql, &mask, relation)
+                .into_iter()
+                .map(move |range| (range, replacement.as_str()))
+        })
+        .collect();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+    let mut rewritten = sql.to_string();
+    for (range, replacement) in edits {
+        rewritten.replace_range(range, replacement);
+    }
+    rewritten
+}
+
+/// Byte ranges in `text` where `relation` occurs as code, ignoring case, identifier quotes
+/// and whitespace around the dots
+fn find_relation(text: &str, mask: &[bool], relation: &str) -> Vec<Range<usize>> {
+    let pattern = relation
+        .split('.')
+        .map(|part| format!("\"?{}\"?", regex::escape(part.trim_matches('"'))))
+        .collect::<Vec<_>>()
+        .join(r"\s*\.\s*");
+    let Ok(regex) = Regex::new(&format!("(?i){}", pattern)) else {
+        return Vec::new();
+    };
+
+    let bytes = text.as_bytes();
+    let is_identifier = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'.';
+
+    regex
+        .find_iter(text)
+        .filter(|found| {
+            let start = found.start();
+            let in_code = mask[start] || bytes[start] == b'"';
+            let bounded_before = start == 0 || !is_identifier(bytes[start - 1]);
+            let bounded_after = bytes.get(found.end()).is_none_or(|&b| !is_identifier(b));
+            in_code && bounded_before && bounded_after
+        })
+        .map(|found| found.range())
+        .collect()
+}
+
+fn line_and_column(sql: &str, offset: usize) -> (usize, usize) {
+    let before = &sql[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_relation_skips_comments_strings_and_longer_names() {
+        let text = "-- raw.orders\nSELECT 'raw.orders' FROM RAW.Orders o\nJOIN \"raw\".\"orders\" p ON 1 = 1\nJOIN raw.orders_archive a ON 1 = 1";
+        let ranges = find_relation(text, &code_mask(text), "raw.orders");
+
+        let found: Vec<(usize, usize)> = ranges
+            .into_iter()
+            .map(|range| line_and_column(text, range.start))
+            .collect();
+        assert_eq!(found, vec![(2, 26), (3, 6)]);
+    }
+
+    #[test]
+    fn test_rewrite_relations() {
+        let sql = "-- reads staging.orders\nSELECT * FROM staging.orders o JOIN staging.orders_archive a ON o.id = a.id";
+        let replacements = HashMap::from([(
+            "staging.orders".to_string(),
+            "staging_alice.orders".to_string(),
+        )]);
+
+        assert_eq!(
+            rewrite_relations(sql, &replacements),
+            "-- reads staging.orders\nSELECT * FROM staging_alice.orders o JOIN staging.orders_archive a ON o.id = a.id"
+        );
+    }
+
+    #[test]
+    fn test_location_display() {
+        let located = SourceLocation {
+            statement: 2,
+            line: Some(7),
+            column: Some(10),
+        };
+        assert_eq!(located.to_string(), "7:10");
+
+        let statement_only = SourceLocation {
+            statement: 2,
+            line: None,
+            column: None,
+        };
+        assert_eq!(statement_only.to_string(), "statement 2");
+    }
+}
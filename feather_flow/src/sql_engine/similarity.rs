@@ -0,0 +1,173 @@
+//! Near-duplicate detection for models, such as copy-pasted staging models
+//!
+//! A model is fingerprinted from its parsed statements: they are rendered back to SQL, which
+//! drops comments and formatting, then tokenized with identifiers lowercased and literals
+//! replaced by `?`. The fingerprint is the set of hashed runs of `SHINGLE_SIZE` tokens, and
+//! two models are as similar as the Jaccard index of their fingerprints.
+
+use anyhow::{Context, Result};
+use sqlparser::ast::Statement;
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// Tokens per shingle; shorter runs make unrelated models look alike
+const SHINGLE_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    shingles: HashSet<u64>,
+}
+
+impl Fingerprint {
+    /// Fingerprint of a model's statements, or `None` when it has none
+    pub fn of_statements(statements: &[Statement]) -> Result<Option<Self>> {
+        let mut tokens = Vec::new();
+        for statement in statements {
+            tokens.extend(normalized_tokens(&statement.to_string())?);
+        }
+        Ok(Self::from_tokens(&tokens))
+    }
+
+    fn from_tokens(tokens: &[String]) -> Option<Self> {
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let size = SHINGLE_SIZE.min(tokens.len());
+        let shingles = tokens
+            .windows(size)
+            .map(|shingle| {
+                let mut hasher = DefaultHasher::new();
+                shingle.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+        Some(Self { shingles })
+    }
+
+    /// Jaccard index of the two fingerprints, from 0.0 (nothing shared) to 1.0 (identical)
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let shared = self.shingles.intersection(&other.shingles).count();
+        let total = self.shingles.len() + other.shingles.len() - shared;
+        if total == 0 {
+            return 0.0;
+        }
+        shared as f64 / total as f64
+    }
+}
+
+/// Tokens of `sql` without whitespace, with words lowercased and literals as `?`
+fn normalized_tokens(sql: &str) -> Result<Vec<String>> {
+    let dialect = DuckDbDialect {};
+    let tokens = Tokenizer::new(&dialect, sql)
+        .tokenize()
+        .with_context(|| format!("Failed to tokenize: {}", sql))?;
+
+    Ok(tokens
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Whitespace(_) | Token::EOF => None,
+            Token::Word(word) => Some(word.value.to_lowercase()),
+            Token::Number(..)
+            | Token::SingleQuotedString(_)
+            | Token::NationalStringLiteral(_)
+            | Token::EscapedStringLiteral(_)
+            | Token::HexStringLiteral(_) => Some("?".to_string()),
+            other => Some(other.to_string()),
+        })
+        .collect())
+}
+
+/// Two models whose fingerprints reached the similarity threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarPair {
+    pub first: String,
+    pub second: String,
+    pub similarity: f64,
+}
+
+/// Every pair of `fingerprints` at least `threshold` similar, most similar first
+pub fn similar_pairs(fingerprints: &[(String, Fingerprint)], threshold: f64) -> Vec<SimilarPair> {
+    let mut pairs = Vec::new();
+    for (i, (first, first_print)) in fingerprints.iter().enumerate() {
+        for (second, second_print) in &fingerprints[i + 1..] {
+            let similarity = first_print.similarity(second_print);
+            if similarity >= threshold {
+                pairs.push(SimilarPair {
+                    first: first.clone(),
+                    second: second.clone(),
+                    similarity,
+                });
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.first.cmp(&b.first))
+            .then_with(|| a.second.cmp(&b.second))
+    });
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::parser::Parser;
+
+    fn tokens(sql: &str) -> Vec<String> {
+        sql.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn fingerprint(sql: &str) -> Fingerprint {
+        let statements = Parser::parse_sql(&DuckDbDialect {}, sql).unwrap();
+        Fingerprint::of_statements(&statements).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_similarity_is_jaccard_of_shingles() {
+        let a = Fingerprint::from_tokens(&tokens("select a b c from t")).unwrap();
+        let b = Fingerprint::from_tokens(&tokens("select a b c from u")).unwrap();
+
+        assert_eq!(a.similarity(&a), 1.0);
+        // Shingles: 3 each, 2 shared
+        assert_eq!(a.similarity(&b), 0.5);
+        assert!(Fingerprint::from_tokens(&[]).is_none());
+    }
+
+    #[test]
+    fn test_formatting_comments_and_literals_are_ignored() {
+        let original = fingerprint(
+            "SELECT id, lower(email) AS email FROM raw.customers WHERE status = 'active'",
+        );
+        let copy = fingerprint(
+            "-- copied from stg_customers\nselect id,\n    LOWER(email) as email\nfrom raw.customers\nwhere status = 'enabled'",
+        );
+        let other = fingerprint("SELECT order_id, sum(amount) FROM raw.orders GROUP BY 1");
+
+        assert_eq!(original.similarity(&copy), 1.0);
+        assert!(original.similarity(&other) < 0.5);
+    }
+
+    #[test]
+    fn test_similar_pairs_are_ordered_by_similarity() {
+        let print = |sql: &str| Fingerprint::from_tokens(&tokens(sql)).unwrap();
+        let fingerprints = vec![
+            ("a".to_string(), print("select a b c from t")),
+            ("b".to_string(), print("select a b c from u")),
+            ("c".to_string(), print("select a b c from t")),
+            ("d".to_string(), print("with x as y select z")),
+        ];
+
+        let pairs = similar_pairs(&fingerprints, 0.5);
+        let ids: Vec<(&str, &str)> = pairs
+            .iter()
+            .map(|pair| (pair.first.as_str(), pair.second.as_str()))
+            .collect();
+        assert_eq!(ids, vec![("a", "c"), ("a", "b"), ("b", "c")]);
+    }
+}
@@ -2,29 +2,52 @@ use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_yaml;
-use sha2::{Digest, Sha256};
 use sqlparser::ast::Statement;
 use sqlparser::dialect::Dialect;
-use sqlparser::parser::Parser as SqlParser;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use crate::config::awaits::Await;
+use crate::config::criticality::Criticality;
+use crate::config::directories::{self, DirectoryConfig, ResolvedConfig};
+use crate::config::freshness::Freshness;
+use crate::config::isolation;
+use crate::config::materializations::MaterializationRegistry;
+use crate::config::meta;
+use crate::config::partitioning::{self, ClusterBy, PartitionBy};
+use crate::config::snapshots::SnapshotConfig;
+use crate::config::SqlDialect;
+use crate::error::FeatherFlowError;
+use crate::profiling;
+use crate::run::anomalies;
+use crate::run::ModelRunStatus;
+use crate::state::manifest::StateManifest;
+use crate::state::parse_cache::{ParseCache, ParsedStatements};
+use crate::validators::meta::MetaSchema;
 use crate::validators::validate_model_structure;
 
+use super::annotations;
+use super::checksum::{self, ChecksumMode, HashAlgorithm};
+use super::comments;
 use super::extractors;
+use super::manifest_writer::{ManifestFormat, ManifestWriter};
+use super::nodes::{ColumnTest, Exposure, Node, Seed, Source, SNAPSHOT_MATERIALIZATION};
+use super::references::{self, SourceLocation};
+use super::statements::{self, ParsedSpan, StatementError};
+use super::tables::TableManager;
+use super::template::{self, TargetContext, TemplateContext, DEFAULT_TARGET};
+
+/// Materializations `partition_by` and `cluster_by` apply to
+const PARTITIONED_MATERIALIZATIONS: &[&str] = &["table", "incremental"];
 
 #[derive(Debug, Serialize, Deserialize)]
 struct YamlConfig {
     version: i32,
     models: Option<Vec<YamlModel>>,
     sources: Option<Vec<YamlSource>>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct YamlOutput {
-    pub version: i32,
-    pub models: std::collections::BTreeMap<String, YamlOutputModel>,
+    exposures: Option<Vec<Exposure>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,7 +59,13 @@ pub struct YamlOutputModel {
     pub database: Option<String>,
     pub schema: Option<String>,
     pub object_name: Option<String>,
+    pub alias: Option<String>,
+    pub loaded_at_field: Option<String>,
+    pub checksum: String,
+    pub normalized_checksum: String,
     pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub config_sources: BTreeMap<String, String>,
     pub columns: Vec<YamlOutputColumn>,
     pub depends_on: Vec<String>,
     pub referenced_by: Vec<String>,
@@ -60,6 +89,9 @@ struct YamlModel {
     database_name: Option<String>,
     schema_name: Option<String>,
     object_name: Option<String>,
+    alias: Option<String>,
+    /// Timestamp column recording when each row was loaded, used for freshness
+    loaded_at_field: Option<String>,
     columns: Option<Vec<YamlColumn>>,
 }
 
@@ -68,6 +100,13 @@ struct YamlSource {
     name: String,
     description: Option<String>,
     database: String,
+    /// Default for tables that do not set their own
+    loaded_at_field: Option<String>,
+    /// Default for tables that do not set their own
+    freshness: Option<Freshness>,
+    /// Default for tables that do not set their own
+    #[serde(rename = "await")]
+    awaited: Option<Await>,
     tables: Vec<YamlSourceTable>,
 }
 
@@ -75,25 +114,91 @@ struct YamlSource {
 struct YamlSourceTable {
     name: String,
     description: Option<String>,
+    loaded_at_field: Option<String>,
+    freshness: Option<Freshness>,
+    #[serde(rename = "await")]
+    awaited: Option<Await>,
     columns: Option<Vec<YamlColumn>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct YamlModelConfig {
     materialized: Option<String>,
+    targets: Option<HashMap<String, TargetOverride>>,
+    partition_by: Option<PartitionBy>,
+    cluster_by: Option<ClusterBy>,
+    /// Snapshot history settings, see `config::snapshots`
+    unique_key: Option<ClusterBy>,
+    strategy: Option<String>,
+    updated_at: Option<String>,
+    check_cols: Option<ClusterBy>,
+    /// Same as `meta.criticality`, which wins when both are set
+    criticality: Option<String>,
+    session: Option<BTreeMap<String, serde_json::Value>>,
+    /// How long a build should take, e.g. `2m`; see `run::anomalies`
+    expected_runtime: Option<String>,
     #[serde(flatten)]
     extra: HashMap<String, serde_json::Value>,
 }
 
+impl YamlModelConfig {
+    /// History settings, when any of them is set
+    fn snapshot(&self) -> Option<SnapshotConfig> {
+        if self.unique_key.is_none()
+            && self.strategy.is_none()
+            && self.updated_at.is_none()
+            && self.check_cols.is_none()
+        {
+            return None;
+        }
+        Some(SnapshotConfig {
+            unique_key: self
+                .unique_key
+                .clone()
+                .map(ClusterBy::into_columns)
+                .unwrap_or_default(),
+            strategy: self.strategy.clone(),
+            updated_at: self.updated_at.clone(),
+            check_cols: self.check_cols.clone().map(ClusterBy::into_columns),
+        })
+    }
+}
+
+/// Schema/database overrides applied when building for a specific target
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetOverride {
+    pub schema: Option<String>,
+    pub database: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct YamlColumn {
     name: String,
     description: Option<String>,
     data_type: Option<String>,
-    tests: Option<Vec<String>>,
+    tests: Option<Vec<YamlColumnTest>>,
     meta: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// An entry of a column's `tests:`, either a test name or a test name mapped to its
+/// arguments:
+///
+/// ```yaml
+/// tests:
+///   - not_null
+///   - accepted_values:
+///       values: [placed, shipped]
+///   - relationships:
+///       to: ref('customers')
+///       field: id
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum YamlColumnTest {
+    Name(String),
+    WithArgs(BTreeMap<String, BTreeMap<String, serde_json::Value>>),
+}
+
 #[derive(Debug, Clone)]
 // Many fields are used indirectly through serialization or test code
 #[allow(dead_code)]
@@ -103,13 +208,22 @@ pub struct SqlModel {
     pub fully_qualified_file_path: PathBuf,
     pub relative_file_path: PathBuf,
     pub file_name: String,
+    /// Checksum of the SQL file
     pub checksum: String,
+    /// Checksum of the SQL's tokens, unchanged by edits to its formatting or comments
+    pub normalized_checksum: String,
+    /// Checksum of the model's YAML file, when it has one
+    pub yaml_checksum: Option<String>,
     pub parent_dir: PathBuf,
     pub raw_sql: String,
     pub compiled_sql: Option<String>,
     pub ast: Vec<Statement>,
     pub depends_on: HashSet<String>,
     pub referenced_tables: HashSet<String>,
+    /// Tables the compiled SQL names without a schema, which resolve in the model's schema
+    pub unqualified_tables: Vec<String>,
+    /// Relations the SQL names through `{{ ref(...) }}` or `{{ source(...) }}`
+    pub explicit_references: HashSet<String>,
     pub referenced_sources: HashSet<String>,
     pub upstream_models: HashSet<String>,
     pub downstream_models: HashSet<String>,
@@ -120,15 +234,41 @@ pub struct SqlModel {
     pub tags: Vec<String>,
     pub meta: HashMap<String, serde_json::Value>,
     pub materialized: Option<String>,
+    /// BigQuery partitioning of the model's table
+    pub partition_by: Option<PartitionBy>,
+    /// BigQuery clustering columns of the model's table
+    pub cluster_by: Vec<String>,
+    /// History kept by a snapshot with a `unique_key`; None for append-only snapshots
+    pub snapshot: Option<SnapshotConfig>,
+    /// How long a build should take, as written, e.g. `2m`
+    pub expected_runtime: Option<String>,
     pub database: Option<String>,
     pub schema: Option<String>,
     pub object_name: Option<String>,
     pub alias: Option<String>,
+    /// Timestamp column recording when each row was loaded
+    pub loaded_at_field: Option<String>,
+    pub target_overrides: HashMap<String, TargetOverride>,
+    /// Warehouse session parameters set while the model runs, e.g. DuckDB's `memory_limit`
+    pub session: BTreeMap<String, serde_json::Value>,
+    /// Relations the model's YAML places it at, when a target override moved it elsewhere
+    pub relocated_from: Vec<String>,
+    /// Where each of `materialized`, `schema`, `database` and `tags` was set: `model YAML`,
+    /// `models: <directory>` in the project config, `targets.<target>` or `schema_isolation`
+    pub config_sources: BTreeMap<String, String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub columns: HashMap<String, ColumnInfo>,
     pub is_valid_structure: bool,
     pub structure_errors: Vec<String>,
+    /// Malformed `-- ff:test` annotations and conflicts with YAML-defined tests
+    pub test_annotation_issues: Vec<String>,
+    /// Statements that failed to parse while others in the same file succeeded
+    pub statement_errors: Vec<StatementError>,
+    /// Where each referenced relation appears in the compiled SQL
+    pub reference_locations: BTreeMap<String, Vec<SourceLocation>>,
+    /// Parse results were restored from the parse cache, so `ast` is empty
+    pub from_parse_cache: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +277,9 @@ pub struct ColumnInfo {
     pub description: Option<String>,
     pub data_type: Option<String>,
     pub tests: Vec<String>,
+    /// Arguments of the tests in `tests` that take any, by test name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub test_args: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
     pub meta: HashMap<String, serde_json::Value>,
     pub source_columns: Vec<ColumnLineageInfo>,
 }
@@ -149,16 +292,41 @@ pub struct ColumnLineageInfo {
 }
 
 impl SqlModel {
+    /// The model in the SQL file at `path`, its checksums digested by `hash`
     pub fn from_path(
         path: &Path,
         project_root: &Path,
         dialect_name: &str,
         dialect: &dyn Dialect,
+        hash: &dyn HashAlgorithm,
+    ) -> Result<Self> {
+        let _span = profiling::span!("parse", "{}", path.display());
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read SQL file: {}", path.display()))?;
+
+        Self::from_content(path, project_root, content, dialect_name, dialect, hash)
+    }
+
+    /// Like `from_path`, reusing the parse results in `cache` when the SQL is unchanged and
+    /// digesting by the cache's hash algorithm
+    pub fn from_path_with_cache(
+        path: &Path,
+        project_root: &Path,
+        dialect_name: &str,
+        cache: &mut ParseCache,
     ) -> Result<Self> {
+        let _span = profiling::span!("parse", "{}", path.display());
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read SQL file: {}", path.display()))?;
 
-        Self::from_content(path, project_root, content, dialect_name, dialect)
+        Self::load(
+            path,
+            project_root,
+            content,
+            dialect_name,
+            cache.hash(),
+            Some(cache),
+        )
     }
 
     pub fn get_external_sources(&self) -> &HashSet<String> {
@@ -171,29 +339,47 @@ impl SqlModel {
         content: String,
         dialect_name: &str,
         _dialect: &dyn Dialect,
+        hash: &dyn HashAlgorithm,
+    ) -> Result<Self> {
+        Self::load(path, project_root, content, dialect_name, hash, None)
+    }
+
+    fn load(
+        path: &Path,
+        project_root: &Path,
+        content: String,
+        dialect_name: &str,
+        hash: &dyn HashAlgorithm,
+        cache: Option<&mut ParseCache>,
     ) -> Result<Self> {
-        let ast = parse_sql_content(&content, path)?;
-        let metadata = extract_file_metadata(path, project_root)?;
+        let metadata = extract_file_metadata(path, project_root, hash)?;
         let (is_valid_structure, structure_errors) =
             validate_directory_structure(&metadata.parent_dir);
 
-        let model = Self::create_model(
+        let mut model = Self::create_model(
             metadata,
             content,
-            ast,
             dialect_name.to_string(),
+            hash,
             is_valid_structure,
             structure_errors,
         );
 
+        // YAML metadata is loaded first so `{{ this }}` resolves to the configured relation.
+        // Models using `{{ var(...) }}`, `{{ ref(...) }}` or `{{ source(...) }}` wait for
+        // `SqlModelCollection::compile` with the resolved variables and relations.
+        if !template::uses_project_context(&model.raw_sql) {
+            model.compile(&TargetContext::new(DEFAULT_TARGET, dialect_name), cache)?;
+        }
+
         Ok(model)
     }
 
     fn create_model(
         metadata: ModelMetadata,
         content: String,
-        ast: Vec<Statement>,
         dialect: String,
+        hash: &dyn HashAlgorithm,
         is_valid_structure: bool,
         structure_errors: Vec<String>,
     ) -> Self {
@@ -206,12 +392,16 @@ impl SqlModel {
             relative_file_path: metadata.relative_path,
             file_name: metadata.file_name,
             checksum: metadata.checksum,
+            normalized_checksum: checksum::normalized(&content, &dialect, hash),
+            yaml_checksum: None,
             parent_dir: metadata.parent_dir,
             raw_sql: content,
             compiled_sql: None,
-            ast,
+            ast: Vec::new(),
             depends_on: HashSet::new(),
             referenced_tables: HashSet::new(),
+            unqualified_tables: Vec::new(),
+            explicit_references: HashSet::new(),
             referenced_sources: HashSet::new(),
             upstream_models: HashSet::new(),
             downstream_models: HashSet::new(),
@@ -222,20 +412,34 @@ impl SqlModel {
             tags: Vec::new(),
             meta: HashMap::new(),
             materialized: None,
+            partition_by: None,
+            cluster_by: Vec::new(),
+            snapshot: None,
+            expected_runtime: None,
             schema: None,
             database: None,
             alias: None,
+            loaded_at_field: None,
             object_name: None,
+            target_overrides: HashMap::new(),
+            session: BTreeMap::new(),
+            relocated_from: Vec::new(),
+            config_sources: BTreeMap::new(),
             created_at: now,
             updated_at: now,
             columns: HashMap::new(),
             is_valid_structure,
             structure_errors,
+            test_annotation_issues: Vec::new(),
+            statement_errors: Vec::new(),
+            reference_locations: BTreeMap::new(),
+            from_parse_cache: false,
         };
 
         if model.is_valid_structure {
-            let _ = model.load_yaml_metadata();
+            let _ = model.load_yaml_metadata(hash);
         }
+        model.apply_test_annotations();
 
         model
     }
@@ -261,12 +465,13 @@ impl SqlModel {
                 "external_sources": self.external_sources,
                 "dialect": self.dialect,
                 "alias": self.alias,
+                "loaded_at_field": self.loaded_at_field,
                 "created_at": self.created_at.to_rfc3339(),
                 "updated_at": self.updated_at.to_rfc3339(),
             },
             "metadata": {
                 "depth": self.depth,
-                "description": self.description,
+                "description": self.rendered_description(),
                 "tags": self.tags,
                 "meta": self.meta,
                 "materialized": self.materialized,
@@ -277,6 +482,13 @@ impl SqlModel {
             "structure": {
                 "is_valid": self.is_valid_structure,
                 "errors": self.structure_errors,
+                "test_annotation_issues": self.test_annotation_issues,
+                "statement_errors": self
+                    .statement_errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                "reference_locations": self.reference_locations,
                 "columns": self.columns.keys().collect::<Vec<_>>(),
             },
             "graph": {
@@ -288,12 +500,101 @@ impl SqlModel {
         json
     }
 
+    /// Render template expressions for `target` and parse the compiled SQL, reusing the parse
+    /// results in `cache` when the compiled SQL is unchanged. A cache hit restores
+    /// `referenced_tables` too; after a fresh parse they come from `extract_dependencies`
+    pub fn compile(
+        &mut self,
+        target: &TargetContext,
+        mut cache: Option<&mut ParseCache>,
+    ) -> Result<()> {
+        let context = TemplateContext {
+            this: self.qualified_relation(),
+            target,
+        };
+        let compiled = template::render(&self.raw_sql, &context).with_context(|| {
+            format!(
+                "Failed to render template in {}",
+                self.fully_qualified_file_path.display()
+            )
+        })?;
+        self.explicit_references = template::explicit_relations(&self.raw_sql, target)
+            .into_iter()
+            .collect();
+
+        if let Some(cached) = cache
+            .as_deref_mut()
+            .and_then(|cache| cache.get(self, &compiled))
+        {
+            self.ast = Vec::new();
+            self.referenced_tables = cached.referenced_tables;
+            self.unqualified_tables = cached.unqualified_tables;
+            self.reference_locations = cached.reference_locations;
+            self.statement_errors = cached.statement_errors;
+            self.from_parse_cache = true;
+            self.compiled_sql = Some(compiled);
+            return Ok(());
+        }
+
+        let (parsed, statement_errors) =
+            parse_sql_content(&compiled, &self.fully_qualified_file_path, &self.dialect)?;
+        self.reference_locations = references::locate_references(&compiled, &parsed);
+        self.ast = parsed
+            .into_iter()
+            .flat_map(|parsed_span| parsed_span.statements)
+            .collect();
+        self.statement_errors = statement_errors;
+        self.unqualified_tables = extractors::get_unqualified_table_names(&self.ast);
+        self.from_parse_cache = false;
+
+        if let Some(cache) = cache {
+            let parsed = ParsedStatements {
+                referenced_tables: extractors::get_external_table_deps_set(&self.ast),
+                reference_locations: self.reference_locations.clone(),
+                statement_errors: self.statement_errors.clone(),
+                unqualified_tables: self.unqualified_tables.clone(),
+            };
+            cache.put(self, &compiled, parsed);
+        }
+        self.compiled_sql = Some(compiled);
+
+        Ok(())
+    }
+
+    /// SQL rendered from the parsed statements with the compiled SQL's header comments
+    /// re-attached
+    pub fn formatted_sql(&self) -> String {
+        let rendered = self
+            .ast
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>()
+            .join(";\n");
+
+        comments::attach_header_comments(
+            self.compiled_sql.as_deref().unwrap_or(&self.raw_sql),
+            &rendered,
+        )
+    }
+
+    /// First place the compiled SQL references `relation`
+    pub fn first_reference(&self, relation: &str) -> Option<&SourceLocation> {
+        self.reference_locations
+            .get(relation)
+            .and_then(|locations| locations.first())
+    }
+
     pub fn extract_dependencies(&mut self) -> Result<()> {
+        // Restored from the parse cache along with the rest of the parse results
+        if self.from_parse_cache {
+            return Ok(());
+        }
         self.referenced_tables = extractors::get_external_table_deps_set(&self.ast);
         Ok(())
     }
 
-    pub fn load_yaml_metadata(&mut self) -> Result<()> {
+    pub fn load_yaml_metadata(&mut self, hash: &dyn HashAlgorithm) -> Result<()> {
+        let _span = profiling::span!("yaml", "{}.yml", self.name);
         let yaml_path = self.parent_dir.join(format!("{}.yml", self.name));
 
         if !yaml_path.exists() {
@@ -301,6 +602,7 @@ impl SqlModel {
         }
 
         let yaml_content = load_yaml_file(&yaml_path)?;
+        self.yaml_checksum = Some(checksum::raw(&yaml_content, hash));
         let yaml_config: YamlConfig = parse_yaml_content(&yaml_content, &yaml_path)?;
 
         self.apply_yaml_config(&yaml_config);
@@ -308,6 +610,46 @@ impl SqlModel {
         Ok(())
     }
 
+    /// Merge `-- ff:test` annotations from the SQL into the column tests loaded from YAML
+    fn apply_test_annotations(&mut self) {
+        let (annotations, mut issues) = annotations::parse_test_annotations(&self.raw_sql);
+        let yaml_declares_columns = !self.columns.is_empty();
+
+        for annotation in annotations {
+            match self.columns.get_mut(&annotation.column) {
+                Some(column) if column.tests.contains(&annotation.test) => {
+                    issues.push(format!(
+                        "line {}: test '{}' on column '{}' is also defined in YAML",
+                        annotation.line, annotation.test, annotation.column
+                    ));
+                }
+                Some(column) => column.tests.push(annotation.test),
+                None => {
+                    if yaml_declares_columns {
+                        issues.push(format!(
+                            "line {}: column '{}' is not declared in YAML",
+                            annotation.line, annotation.column
+                        ));
+                    }
+                    self.columns.insert(
+                        annotation.column.clone(),
+                        ColumnInfo {
+                            name: annotation.column,
+                            description: None,
+                            data_type: None,
+                            tests: vec![annotation.test],
+                            test_args: BTreeMap::new(),
+                            meta: HashMap::new(),
+                            source_columns: Vec::new(),
+                        },
+                    );
+                }
+            }
+        }
+
+        self.test_annotation_issues = issues;
+    }
+
     fn apply_yaml_config(&mut self, yaml_config: &YamlConfig) {
         if let Some(models) = &yaml_config.models {
             for model_config in models {
@@ -325,15 +667,144 @@ impl SqlModel {
 
         if let Some(config) = &model_config.config {
             self.materialized = config.materialized.clone();
+            self.target_overrides = config.targets.clone().unwrap_or_default();
+            self.partition_by = config.partition_by.clone();
+            self.cluster_by = config
+                .cluster_by
+                .clone()
+                .map(ClusterBy::into_columns)
+                .unwrap_or_default();
+            self.session = config.session.clone().unwrap_or_default();
+            self.snapshot = config.snapshot();
+            self.expected_runtime = config.expected_runtime.clone();
+            if let Some(criticality) = &config.criticality {
+                self.meta
+                    .entry(meta::CRITICALITY.to_string())
+                    .or_insert_with(|| serde_json::Value::String(criticality.clone()));
+            }
         }
 
         self.database = model_config.database_name.clone();
         self.schema = model_config.schema_name.clone();
         self.object_name = model_config.object_name.clone();
+        self.alias = model_config.alias.clone();
+        self.loaded_at_field = model_config.loaded_at_field.clone();
+
+        for (setting, set) in [
+            ("materialized", self.materialized.is_some()),
+            ("schema", self.schema.is_some()),
+            ("database", self.database.is_some()),
+            ("tags", !self.tags.is_empty()),
+        ] {
+            if set {
+                self.config_sources
+                    .insert(setting.to_string(), "model YAML".to_string());
+            }
+        }
 
         self.load_column_information(model_config);
     }
 
+    /// Name of the relation this model materializes into: alias, then object_name, then
+    /// the file name
+    pub fn relation_name(&self) -> &str {
+        self.alias
+            .as_deref()
+            .or(self.object_name.as_deref())
+            .unwrap_or(&self.name)
+    }
+
+    /// Checksum of the SQL file that `mode` compares
+    pub fn checksum_for(&self, mode: ChecksumMode) -> &str {
+        match mode {
+            ChecksumMode::Raw => &self.checksum,
+            ChecksumMode::Normalized => &self.normalized_checksum,
+        }
+    }
+
+    /// Fully-qualified relation (`[database.]schema.relation`) used for matching and DDL
+    pub fn qualified_relation(&self) -> String {
+        let schema = self.schema.as_deref().unwrap_or(template::DEFAULT_SCHEMA);
+        match &self.database {
+            Some(database) => format!("{}.{}.{}", database, schema, self.relation_name()),
+            None => format!("{}.{}", schema, self.relation_name()),
+        }
+    }
+
+    /// `meta.owner`, when set
+    pub fn owner(&self) -> Option<&str> {
+        self.meta_str(meta::OWNER)
+    }
+
+    pub fn team(&self) -> Option<&str> {
+        self.meta_str(meta::TEAM)
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.meta_str(meta::DOMAIN)
+    }
+
+    /// `meta.criticality`, when set to one of the tiers
+    pub fn criticality(&self) -> Option<Criticality> {
+        self.meta_str(meta::CRITICALITY)
+            .and_then(Criticality::from_name)
+    }
+
+    pub fn slack_channel(&self) -> Option<&str> {
+        self.meta_str(meta::SLACK_CHANNEL)
+    }
+
+    /// The description with its templates, e.g. `{{ meta.domain }}`, rendered. A description
+    /// that does not render is kept as written; `ff parse` reports it.
+    pub fn rendered_description(&self) -> Option<String> {
+        let description = self.description.as_deref()?;
+        Some(
+            template::render_description(description, &self.name, &self.meta)
+                .unwrap_or_else(|_| description.to_string()),
+        )
+    }
+
+    /// A string meta value, ignoring blank ones
+    fn meta_str(&self, key: &str) -> Option<&str> {
+        self.meta
+            .get(key)?
+            .as_str()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Apply the schema/database overrides configured for `target`, if any
+    pub fn apply_target(&mut self, target: &str) {
+        let Some(overrides) = self.target_overrides.get(target) else {
+            return;
+        };
+        let configured = self.relation_keys();
+        let source = format!("targets.{}", target);
+        if let Some(schema) = &overrides.schema {
+            self.schema = Some(schema.clone());
+            self.config_sources
+                .insert("schema".to_string(), source.clone());
+        }
+        if let Some(database) = &overrides.database {
+            self.database = Some(database.clone());
+            self.config_sources.insert("database".to_string(), source);
+        }
+        if self.relation_keys() != configured {
+            self.relocated_from = configured;
+        }
+    }
+
+    /// Relations references can name the model by: `schema.relation`, and the
+    /// database-qualified relation when the model has a database
+    fn relation_keys(&self) -> Vec<String> {
+        let schema = self.schema.as_deref().unwrap_or(template::DEFAULT_SCHEMA);
+        let mut keys = vec![format!("{}.{}", schema, self.relation_name())];
+        if self.database.is_some() {
+            keys.push(self.qualified_relation());
+        }
+        keys
+    }
+
     fn apply_model_meta(&mut self, model_config: &YamlModel) {
         if let Some(meta) = &model_config.meta {
             self.meta = meta.clone();
@@ -369,13 +840,34 @@ struct ModelMetadata {
     parent_dir: PathBuf,
 }
 
-fn parse_sql_content(content: &str, path: &Path) -> Result<Vec<Statement>> {
-    let dialect = sqlparser::dialect::DuckDbDialect {};
-    SqlParser::parse_sql(&dialect, content)
-        .with_context(|| format!("Failed to parse SQL from {}", path.display()))
+/// Parse every statement of a model; only a file where no statement parses is an error
+fn parse_sql_content(
+    content: &str,
+    path: &Path,
+    dialect_name: &str,
+) -> Result<(Vec<ParsedSpan>, Vec<StatementError>)> {
+    let (parsed, errors) = match SqlDialect::from_name(dialect_name) {
+        Some(SqlDialect::BigQuery) => {
+            statements::parse_statement_spans(&sqlparser::dialect::BigQueryDialect {}, content)
+        }
+        _ => statements::parse_statement_spans(&sqlparser::dialect::DuckDbDialect {}, content),
+    };
+
+    if parsed.is_empty() {
+        if let Some(error) = errors.first() {
+            return Err(anyhow!("{}", error))
+                .with_context(|| format!("Failed to parse SQL from {}", path.display()));
+        }
+    }
+
+    Ok((parsed, errors))
 }
 
-fn extract_file_metadata(path: &Path, project_root: &Path) -> Result<ModelMetadata> {
+fn extract_file_metadata(
+    path: &Path,
+    project_root: &Path,
+    hash: &dyn HashAlgorithm,
+) -> Result<ModelMetadata> {
     let file_name = path
         .file_name()
         .with_context(|| "File has no name")?
@@ -403,7 +895,7 @@ fn extract_file_metadata(path: &Path, project_root: &Path) -> Result<ModelMetada
             .replace(".sql", "")
     );
 
-    let checksum = calculate_checksum(path)?;
+    let checksum = calculate_checksum(path, hash)?;
 
     Ok(ModelMetadata {
         unique_id,
@@ -416,7 +908,7 @@ fn extract_file_metadata(path: &Path, project_root: &Path) -> Result<ModelMetada
     })
 }
 
-fn calculate_checksum(path: &Path) -> Result<String> {
+fn calculate_checksum(path: &Path, hash: &dyn HashAlgorithm) -> Result<String> {
     let content = match fs::read_to_string(path) {
         Ok(content) => content,
         Err(e) => {
@@ -430,15 +922,18 @@ fn calculate_checksum(path: &Path) -> Result<String> {
         }
     };
 
-    let mut hasher = Sha256::new();
-    hasher.update(&content);
-    Ok(format!("{:x}", hasher.finalize()))
+    Ok(checksum::raw(&content, hash))
 }
 
 fn validate_directory_structure(parent_dir: &Path) -> (bool, Vec<String>) {
     if parent_dir.exists() {
         let validation_result = validate_model_structure(parent_dir);
-        (validation_result.is_valid, validation_result.errors)
+        let errors = validation_result
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        (validation_result.is_valid, errors)
     } else {
         (false, vec!["Parent directory does not exist".to_string()])
     }
@@ -451,15 +946,30 @@ fn load_yaml_file(yaml_path: &Path) -> Result<String> {
 
 fn parse_yaml_content(yaml_content: &str, yaml_path: &Path) -> Result<YamlConfig> {
     serde_yaml::from_str(yaml_content)
-        .with_context(|| format!("Failed to parse YAML from {}", yaml_path.display()))
+        .map_err(|err| FeatherFlowError::yaml_parse(yaml_path, err).into())
 }
 
 fn create_column_info(yaml_col: &YamlColumn) -> ColumnInfo {
+    let mut tests = Vec::new();
+    let mut test_args = BTreeMap::new();
+    for test in yaml_col.tests.iter().flatten() {
+        match test {
+            YamlColumnTest::Name(name) => tests.push(name.clone()),
+            YamlColumnTest::WithArgs(with_args) => {
+                for (name, args) in with_args {
+                    tests.push(name.clone());
+                    test_args.insert(name.clone(), args.clone());
+                }
+            }
+        }
+    }
+
     ColumnInfo {
         name: yaml_col.name.clone(),
         description: yaml_col.description.clone(),
         data_type: yaml_col.data_type.clone(),
-        tests: yaml_col.tests.clone().unwrap_or_default(),
+        tests,
+        test_args,
         meta: yaml_col.meta.clone().unwrap_or_default(),
         source_columns: Vec::new(),
     }
@@ -467,61 +977,356 @@ fn create_column_info(yaml_col: &YamlColumn) -> ColumnInfo {
 
 #[derive(Debug, Clone, Default)]
 pub struct SqlModelCollection {
-    models: HashMap<String, SqlModel>,
+    nodes: HashMap<String, Node>,
     child_map: HashMap<String, HashSet<String>>,
     parent_map: HashMap<String, HashSet<String>>,
     defined_imports: HashSet<String>,
+    /// Relation of each declared source table by source and table name
+    source_tables: BTreeMap<(String, String), String>,
     missing_imports: HashMap<String, HashSet<String>>,
+    self_references: HashMap<String, HashSet<String>>,
+    implicit_dependencies: Vec<ImplicitDependency>,
+    /// Warehouse schemas recorded by `ff sync-schemas`
+    table_schemas: TableManager,
+}
+
+/// A dependency link created only because a referenced `schema.name` matched a model's
+/// relation, rather than through an explicit ref
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ImplicitDependency {
+    pub model_id: String,
+    pub relation: String,
+    pub parent_id: String,
 }
 
 impl SqlModelCollection {
     pub fn new() -> Self {
         Self {
-            models: HashMap::new(),
+            nodes: HashMap::new(),
             child_map: HashMap::new(),
             parent_map: HashMap::new(),
             defined_imports: HashSet::new(),
+            source_tables: BTreeMap::new(),
             missing_imports: HashMap::new(),
+            self_references: HashMap::new(),
+            implicit_dependencies: Vec::new(),
+            table_schemas: TableManager::new(),
         }
     }
 
+    /// Use the warehouse schemas recorded by `ff sync-schemas` for relations whose columns
+    /// the project does not declare
+    pub fn set_table_schemas(&mut self, table_schemas: TableManager) {
+        self.table_schemas = table_schemas;
+    }
+
+    pub fn table_schemas(&self) -> &TableManager {
+        &self.table_schemas
+    }
+
     pub fn models_count(&self) -> usize {
-        self.models.len()
+        self.models().count()
     }
 
-    // Used in tests
-    #[cfg(test)]
     pub fn get_model(&self, id: &str) -> Option<&SqlModel> {
-        self.models.get(id)
+        self.nodes.get(id).and_then(Node::as_model)
     }
 
-    pub fn to_yaml(&self) -> Result<YamlOutput> {
-        match self.get_execution_order() {
-            Ok(models) => {
-                // Preallocate with capacity for better performance
-                let mut yaml_models = HashMap::with_capacity(models.len());
-                for model in models {
-                    yaml_models.insert(model.unique_id.clone(), model_to_yaml_output(model));
-                }
+    pub fn get_node(&self, id: &str) -> Option<&Node> {
+        self.nodes.get(id)
+    }
 
-                let ordered_models: std::collections::BTreeMap<String, YamlOutputModel> =
-                    yaml_models.into_iter().collect();
+    /// Every model and snapshot, in no particular order
+    pub fn models(&self) -> impl Iterator<Item = &SqlModel> {
+        self.nodes.values().filter_map(Node::as_model)
+    }
 
-                Ok(YamlOutput {
-                    version: 1,
-                    models: ordered_models,
-                })
-            }
-            Err(err) => Err(anyhow!("Error determining execution order: {}", err)),
+    /// Every node of every resource type, in no particular order
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.values()
+    }
+
+    /// Stream the YAML manifest to `out` one model at a time, in `models_by_depth` order
+    pub fn write_yaml<W: Write>(&self, out: W) -> Result<W> {
+        let mut writer = ManifestWriter::new(out, ManifestFormat::Yaml)?;
+        for model in self.models_by_depth() {
+            writer.write_model(&model.unique_id, &model_to_yaml_output(model))?;
         }
+        writer.finish()
     }
 
     pub fn add_model(&mut self, model: SqlModel) {
         let id = model.unique_id.clone();
-        self.models.insert(id, model);
+        self.nodes.insert(id, Node::from_model(model));
+    }
+
+    /// Resolve per-target schema/database overrides for every model.
+    /// Must run before `build_dependency_graph` so matching uses the target locations.
+    pub fn apply_target(&mut self, target: &str) {
+        for model in self.nodes.values_mut().filter_map(Node::as_model_mut) {
+            model.apply_target(target);
+        }
+    }
+
+    /// Fill in the settings models leave unset from the `models:` defaults of their
+    /// directories, and drop the models of disabled directories. Run before `apply_target`,
+    /// whose overrides win, and before the dependency graph is built.
+    pub fn apply_directory_configs(&mut self, configs: &BTreeMap<String, DirectoryConfig>) {
+        if configs.is_empty() {
+            return;
+        }
+        let mut disabled = Vec::new();
+        for model in self.nodes.values_mut().filter_map(Node::as_model_mut) {
+            let ResolvedConfig {
+                config: defaults,
+                sources,
+            } = directories::resolve(configs, &model.relative_file_path);
+            if defaults.enabled == Some(false) {
+                disabled.push(model.unique_id.clone());
+                continue;
+            }
+            let directory = |setting: &str| format!("models: {}", sources[setting]);
+            if model.materialized.is_none() && defaults.materialized.is_some() {
+                model.materialized = defaults.materialized;
+                model
+                    .config_sources
+                    .insert("materialized".to_string(), directory("materialized"));
+            }
+            if model.schema.is_none() && defaults.schema.is_some() {
+                model.schema = defaults.schema;
+                model
+                    .config_sources
+                    .insert("schema".to_string(), directory("schema"));
+            }
+            if !defaults.tags.is_empty() {
+                let source = model.config_sources.entry("tags".to_string()).or_default();
+                if !source.is_empty() {
+                    source.push_str(", ");
+                }
+                source.push_str(&directory("tags"));
+            }
+            for tag in defaults.tags {
+                if !model.tags.contains(&tag) {
+                    model.tags.push(tag);
+                }
+            }
+        }
+        for id in disabled {
+            self.nodes.remove(&id);
+        }
+    }
+
+    /// Re-render templated models for `target` and refresh their dependencies.
+    /// Run after `apply_target` so `{{ this }}` and `{{ ref(...) }}` reflect the target's
+    /// locations, and after `load_seeds` and `load_source_definitions`, which `ref` and
+    /// `source` resolve against.
+    pub fn compile(&mut self, target: &TargetContext) -> Result<()> {
+        self.compile_with(target, None)
+    }
+
+    /// Like `compile`, reusing the parse results in `cache` when the compiled SQL is unchanged
+    pub fn compile_with_cache(
+        &mut self,
+        target: &TargetContext,
+        cache: &mut ParseCache,
+    ) -> Result<()> {
+        self.compile_with(target, Some(cache))
+    }
+
+    fn compile_with(
+        &mut self,
+        target: &TargetContext,
+        mut cache: Option<&mut ParseCache>,
+    ) -> Result<()> {
+        let refs = self
+            .seeds()
+            .map(|seed| (seed.name.clone(), format!("{}.{}", seed.schema, seed.name)))
+            .chain(
+                self.models()
+                    .map(|model| (model.name.clone(), model.qualified_relation())),
+            )
+            .collect();
+        let target = &target
+            .clone()
+            .with_relations(refs, self.source_tables.clone());
+
+        for model in self.nodes.values_mut().filter_map(Node::as_model_mut) {
+            if !template::has_template_expressions(&model.raw_sql) {
+                continue;
+            }
+            model.compile(target, cache.as_deref_mut())?;
+            model.extract_dependencies()?;
+        }
+        Ok(())
+    }
+
+    /// Point references to the configured location of models a target override moved at
+    /// their target location. Run after `build_dependency_graph`, which links those references.
+    pub fn rewrite_relocated_references(&mut self) {
+        let occupied: HashSet<String> = self.models().flat_map(SqlModel::relation_keys).collect();
+        let replacements: HashMap<String, String> = self
+            .models()
+            .flat_map(|model| {
+                let relation = model.qualified_relation();
+                model
+                    .relocated_from
+                    .iter()
+                    .map(move |configured| (configured.clone(), relation.clone()))
+            })
+            .filter(|(configured, _)| !occupied.contains(configured))
+            .collect();
+        if replacements.is_empty() {
+            return;
+        }
+
+        for model in self.nodes.values_mut().filter_map(Node::as_model_mut) {
+            let referenced: HashMap<String, String> = replacements
+                .iter()
+                .filter(|(relation, _)| model.referenced_tables.contains(*relation))
+                .map(|(relation, moved)| (relation.clone(), moved.clone()))
+                .collect();
+            if !referenced.is_empty() {
+                let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+                model.compiled_sql = Some(references::rewrite_relations(compiled, &referenced));
+            }
+        }
+    }
+
+    /// Move every model and seed to its per-developer schema and point references to them at
+    /// the isolated relations. Run after `build_dependency_graph`, which matches references
+    /// against the configured schemas.
+    pub fn isolate_schemas(&mut self, suffix: &str) {
+        let mut replacements = HashMap::new();
+        for seed in self.seeds() {
+            replacements.insert(
+                format!("{}.{}", template::DEFAULT_SCHEMA, seed.name),
+                format!(
+                    "{}.{}",
+                    isolation::isolated_schema(&seed.schema, suffix),
+                    seed.name
+                ),
+            );
+        }
+        for model in self.models() {
+            let schema = model.schema.as_deref().unwrap_or(template::DEFAULT_SCHEMA);
+            let isolated = isolation::isolated_schema(schema, suffix);
+            let relation = model.relation_name();
+
+            replacements.insert(
+                format!("{}.{}", schema, relation),
+                format!("{}.{}", isolated, relation),
+            );
+            if let Some(database) = &model.database {
+                replacements.insert(
+                    model.qualified_relation(),
+                    format!("{}.{}.{}", database, isolated, relation),
+                );
+            }
+        }
+
+        for seed in self.nodes.values_mut().filter_map(|node| match node {
+            Node::Seed(seed) => Some(seed),
+            _ => None,
+        }) {
+            seed.schema = isolation::isolated_schema(&seed.schema, suffix);
+        }
+        for model in self.nodes.values_mut().filter_map(Node::as_model_mut) {
+            let schema = model.schema.as_deref().unwrap_or(template::DEFAULT_SCHEMA);
+            model.schema = Some(isolation::isolated_schema(schema, suffix));
+            let source = model
+                .config_sources
+                .entry("schema".to_string())
+                .or_default();
+            if !source.is_empty() {
+                source.push_str(", ");
+            }
+            source.push_str("schema_isolation");
+
+            let referenced: HashMap<String, String> = replacements
+                .iter()
+                .filter(|(relation, _)| model.referenced_tables.contains(*relation))
+                .map(|(relation, isolated)| (relation.clone(), isolated.clone()))
+                .collect();
+            if !referenced.is_empty() {
+                let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+                model.compiled_sql = Some(references::rewrite_relations(compiled, &referenced));
+            }
+        }
+    }
+
+    /// Point references from `selected` models to unselected upstream models at the
+    /// relations recorded in `manifest`, for every upstream model `should_defer` accepts.
+    /// Returns the unique ids of the models deferred to.
+    pub fn defer_to_state(
+        &mut self,
+        selected: &HashSet<String>,
+        manifest: &StateManifest,
+        should_defer: impl Fn(&SqlModel) -> bool,
+    ) -> Vec<String> {
+        let mut deferred = BTreeMap::new();
+        for id in selected {
+            let Some(model) = self.get_model(id) else {
+                continue;
+            };
+            for upstream_id in &model.upstream_models {
+                if selected.contains(upstream_id) || deferred.contains_key(upstream_id) {
+                    continue;
+                }
+                let Some(upstream) = self.get_model(upstream_id) else {
+                    continue;
+                };
+                let Some(production) = manifest.relation(upstream_id) else {
+                    continue;
+                };
+                if should_defer(upstream) {
+                    deferred.insert(upstream_id.clone(), (upstream, production.to_string()));
+                }
+            }
+        }
+
+        // Keyed on the upstream's relation as it now appears in compiled SQL, which schema
+        // isolation may already have rewritten away from `referenced_tables`
+        let mut replacements: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for (id, (upstream, production)) in &deferred {
+            let schema = upstream
+                .schema
+                .as_deref()
+                .unwrap_or(template::DEFAULT_SCHEMA);
+            replacements.insert(
+                id.clone(),
+                vec![
+                    (
+                        format!("{}.{}", schema, upstream.relation_name()),
+                        production.clone(),
+                    ),
+                    (upstream.qualified_relation(), production.clone()),
+                ],
+            );
+        }
+        let deferred: Vec<String> = deferred.into_keys().collect();
+
+        for id in selected {
+            let Some(model) = self.nodes.get_mut(id).and_then(Node::as_model_mut) else {
+                continue;
+            };
+            let referenced: HashMap<String, String> = model
+                .upstream_models
+                .iter()
+                .filter_map(|upstream_id| replacements.get(upstream_id))
+                .flatten()
+                .cloned()
+                .collect();
+            if !referenced.is_empty() {
+                let compiled = model.compiled_sql.as_deref().unwrap_or(&model.raw_sql);
+                model.compiled_sql = Some(references::rewrite_relations(compiled, &referenced));
+            }
+        }
+
+        deferred
     }
 
     pub fn load_source_definitions(&mut self, project_root: &Path) -> std::io::Result<()> {
+        let _span = profiling::span!("yaml", "source definitions");
         let imports_dir = get_imports_directory_path(project_root);
 
         if !imports_dir.exists() {
@@ -533,35 +1338,190 @@ impl SqlModelCollection {
         }
 
         self.defined_imports.clear();
+        self.source_tables.clear();
+        self.nodes
+            .retain(|_, node| !matches!(node, Node::Source(_) | Node::Exposure(_)));
         let yaml_files = find_yaml_files(&imports_dir);
 
+        let mut exposures = Vec::new();
+        let mut declared = HashMap::new();
         for yaml_path in yaml_files {
-            process_import_yaml_file(&yaml_path, &mut self.defined_imports)?;
+            process_import_yaml_file(
+                &yaml_path,
+                &mut self.defined_imports,
+                &mut self.source_tables,
+                &mut declared,
+                &mut exposures,
+            )?;
         }
 
         debug_log_imports(&self.defined_imports);
 
+        for relation in &self.defined_imports {
+            let source = declared
+                .remove(relation)
+                .unwrap_or_else(|| Source::new(relation));
+            self.nodes
+                .insert(source.unique_id.clone(), Node::Source(source));
+        }
+        for mut exposure in exposures {
+            exposure.unique_id = format!("exposure.{}", exposure.name);
+            self.nodes
+                .insert(exposure.unique_id.clone(), Node::Exposure(exposure));
+        }
+
+        Ok(())
+    }
+
+    /// Add every CSV file in `seeds_dir` as a seed. Run before `build_dependency_graph` so
+    /// references to seeds are not reported as missing sources.
+    pub fn load_seeds(&mut self, seeds_dir: &Path) -> Result<()> {
+        self.nodes.retain(|_, node| !matches!(node, Node::Seed(_)));
+        if !seeds_dir.is_dir() {
+            return Ok(());
+        }
+
+        let entries = fs::read_dir(seeds_dir)
+            .with_context(|| format!("Failed to read seeds directory {}", seeds_dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("csv") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            let seed = Seed::new(path.clone(), name);
+            self.nodes.insert(seed.unique_id.clone(), Node::Seed(seed));
+        }
         Ok(())
     }
 
+    /// Relation a model, snapshot or seed is built into, as its current schema places it
+    pub fn relation_of(&self, id: &str) -> Option<String> {
+        match self.nodes.get(id)? {
+            Node::Model(model) | Node::Snapshot(model) => Some(model.qualified_relation()),
+            Node::Seed(seed) => Some(seed.relation()),
+            _ => None,
+        }
+    }
+
+    fn seeds(&self) -> impl Iterator<Item = &Seed> {
+        self.nodes.values().filter_map(|node| match node {
+            Node::Seed(seed) => Some(seed),
+            _ => None,
+        })
+    }
+
+    /// Unique ids of the nodes `id` depends on: upstream models plus the seeds and sources a
+    /// model reads, the model a test checks, or the models an exposure uses
+    pub fn node_dependencies(&self, id: &str) -> BTreeSet<String> {
+        match self.nodes.get(id) {
+            Some(Node::Model(model) | Node::Snapshot(model)) => {
+                let mut dependencies: BTreeSet<String> =
+                    model.upstream_models.iter().cloned().collect();
+                for table in &model.external_sources {
+                    dependencies.extend(
+                        self.seeds()
+                            .filter(|seed| seed.is_referenced_as(table))
+                            .map(|seed| seed.unique_id.clone()),
+                    );
+                    let source_id = format!("source.{}", table);
+                    if self.nodes.contains_key(&source_id) {
+                        dependencies.insert(source_id);
+                    }
+                }
+                dependencies
+            }
+            Some(Node::Test(test)) => {
+                let mut dependencies = BTreeSet::from([test.model_id.clone()]);
+                dependencies.extend(test.to_id.clone());
+                dependencies
+            }
+            Some(Node::Exposure(exposure)) => exposure
+                .depends_on
+                .iter()
+                .filter_map(|name| {
+                    self.get_model(name)
+                        .or_else(|| self.models().find(|model| model.name == *name))
+                })
+                .map(|model| model.unique_id.clone())
+                .collect(),
+            Some(Node::Seed(_) | Node::Source(_)) | None => BTreeSet::new(),
+        }
+    }
+
+    /// Replace the test nodes with one per column test of every model and snapshot, resolving
+    /// the model or seed each `relationships` test points to
+    fn refresh_test_nodes(&mut self) {
+        self.nodes.retain(|_, node| !matches!(node, Node::Test(_)));
+
+        let tests: Vec<ColumnTest> = self
+            .models()
+            .flat_map(|model| {
+                model.columns.values().flat_map(move |column| {
+                    column.tests.iter().map(move |test| {
+                        let mut column_test = ColumnTest::new(model, test, &column.name);
+                        column_test.args = column.test_args.get(test).cloned().unwrap_or_default();
+                        column_test
+                    })
+                })
+            })
+            .map(|mut test| {
+                if let Some(to) = test.to_name() {
+                    test.to_id = self
+                        .models()
+                        .find(|model| model.name == to)
+                        .map(|model| model.unique_id.clone())
+                        .or_else(|| {
+                            self.seeds()
+                                .find(|seed| seed.name == to)
+                                .map(|seed| seed.unique_id.clone())
+                        });
+                }
+                test
+            })
+            .collect();
+        for test in tests {
+            self.nodes.insert(test.unique_id.clone(), Node::Test(test));
+        }
+    }
+
     pub fn build_dependency_graph(&mut self) {
+        let _span = profiling::span!("graph", "build dependency graph");
         self.clear_dependency_maps();
 
         // Collect keys once instead of repeatedly
-        let model_ids: Vec<String> = self.models.keys().cloned().collect();
-        let table_to_model = self.build_table_to_model_map(&model_ids);
+        let model_ids: Vec<String> = self.models().map(|model| model.unique_id.clone()).collect();
+        let table_to_model = {
+            let _span = profiling::span!("graph", "index relations");
+            self.build_table_to_model_map(&model_ids)
+        };
 
-        let relationships = self.collect_model_relationships(&model_ids, &table_to_model);
-        self.update_model_dependency_relationships(&relationships);
+        {
+            let _span = profiling::span!("graph", "link models");
+            let relationships = self.collect_model_relationships(&model_ids, &table_to_model);
+            self.update_model_dependency_relationships(&relationships);
+        }
 
-        self.calculate_external_sources(&model_ids, &table_to_model);
-        self.calculate_model_depths();
+        {
+            let _span = profiling::span!("graph", "external sources");
+            self.calculate_external_sources(&model_ids, &table_to_model);
+        }
+        {
+            let _span = profiling::span!("graph", "model depths");
+            self.calculate_model_depths();
+        }
+        self.refresh_test_nodes();
     }
 
     fn clear_dependency_maps(&mut self) {
         self.child_map.clear();
         self.parent_map.clear();
         self.missing_imports.clear();
+        self.self_references.clear();
+        self.implicit_dependencies.clear();
     }
 
     fn build_table_to_model_map(&self, model_ids: &[String]) -> HashMap<String, String> {
@@ -569,13 +1529,27 @@ impl SqlModelCollection {
         let mut table_to_model = HashMap::with_capacity(model_ids.len());
 
         for id in model_ids {
-            if let Some(model) = self.models.get(id) {
-                let table_name = format!(
-                    "{}.{}",
-                    model.schema.as_deref().unwrap_or("public"),
-                    model.name
-                );
+            if let Some(model) = self.get_model(id) {
+                let schema = model.schema.as_deref().unwrap_or("public");
+                let table_name = format!("{}.{}", schema, model.relation_name());
                 table_to_model.insert(table_name, id.clone());
+
+                // Database-qualified references resolve to the same model
+                if model.database.is_some() {
+                    table_to_model.insert(model.qualified_relation(), id.clone());
+                }
+            }
+        }
+
+        // References to where the YAML places a model still find it after a target moved
+        // it, unless another model lives there now
+        for id in model_ids {
+            if let Some(model) = self.get_model(id) {
+                for relation in &model.relocated_from {
+                    table_to_model
+                        .entry(relation.clone())
+                        .or_insert_with(|| id.clone());
+                }
             }
         }
 
@@ -591,11 +1565,28 @@ impl SqlModelCollection {
         let mut relationships = Vec::with_capacity(model_ids.len() * 2); // Estimate
 
         for id in model_ids {
-            if let Some(model) = self.models.get(id) {
+            if let Some(model) = self.nodes.get(id).and_then(Node::as_model) {
                 for ref_table in &model.referenced_tables {
                     if let Some(parent_id) = table_to_model.get(ref_table) {
+                        // A model reading its own relation is not a graph edge
+                        if parent_id == id {
+                            self.self_references
+                                .entry(id.clone())
+                                .or_default()
+                                .insert(ref_table.clone());
+                            continue;
+                        }
+
                         relationships.push((id.clone(), parent_id.clone()));
 
+                        if !model.explicit_references.contains(ref_table) {
+                            self.implicit_dependencies.push(ImplicitDependency {
+                                model_id: id.clone(),
+                                relation: ref_table.clone(),
+                                parent_id: parent_id.clone(),
+                            });
+                        }
+
                         self.child_map
                             .entry(parent_id.clone())
                             .or_default()
@@ -610,16 +1601,17 @@ impl SqlModelCollection {
             }
         }
 
+        self.implicit_dependencies.sort();
         relationships
     }
 
     fn update_model_dependency_relationships(&mut self, relationships: &[(String, String)]) {
         for (child_id, parent_id) in relationships {
-            if let Some(child_model) = self.models.get_mut(child_id) {
+            if let Some(child_model) = self.nodes.get_mut(child_id).and_then(Node::as_model_mut) {
                 child_model.upstream_models.insert(parent_id.clone());
             }
 
-            if let Some(parent_model) = self.models.get_mut(parent_id) {
+            if let Some(parent_model) = self.nodes.get_mut(parent_id).and_then(Node::as_model_mut) {
                 parent_model.downstream_models.insert(child_id.clone());
             }
         }
@@ -632,7 +1624,7 @@ impl SqlModelCollection {
     ) {
         for id in model_ids {
             let model_sources = {
-                if let Some(model) = self.models.get(id) {
+                if let Some(model) = self.get_model(id) {
                     self.identify_external_sources(model, table_to_model)
                 } else {
                     continue;
@@ -641,7 +1633,7 @@ impl SqlModelCollection {
 
             let (external_sources, missing_sources) = model_sources;
 
-            if let Some(model) = self.models.get_mut(id) {
+            if let Some(model) = self.nodes.get_mut(id).and_then(Node::as_model_mut) {
                 if !missing_sources.is_empty() {
                     self.missing_imports.insert(id.clone(), missing_sources);
                 }
@@ -663,7 +1655,8 @@ impl SqlModelCollection {
             if !table_to_model.contains_key(ref_table) {
                 external_sources.insert(ref_table.clone());
 
-                if !self.defined_imports.contains(ref_table) {
+                let is_seed = self.seeds().any(|seed| seed.is_referenced_as(ref_table));
+                if !self.defined_imports.contains(ref_table) && !is_seed {
                     missing_sources.insert(ref_table.clone());
                 }
             }
@@ -672,8 +1665,60 @@ impl SqlModelCollection {
         (external_sources, missing_sources)
     }
 
+    /// Dependency cycles found by a depth-first walk of the graph, each as the unique ids
+    /// along it, where every model feeds the next and the last feeds the first. Each cycle
+    /// starts at its smallest id; self-references are reported separately.
     pub fn detect_cycles(&self) -> Vec<Vec<String>> {
-        Vec::new()
+        let mut finished: HashMap<&str, bool> = HashMap::new();
+        let mut path = Vec::new();
+        let mut cycles = BTreeSet::new();
+        for model in self.models_by_id() {
+            if !finished.contains_key(model.unique_id.as_str()) {
+                self.walk_for_cycles(&model.unique_id, &mut finished, &mut path, &mut cycles);
+            }
+        }
+        cycles.into_iter().collect()
+    }
+
+    /// Visit `id` and its descendants; `finished` is false for models still on `path`
+    fn walk_for_cycles<'a>(
+        &'a self,
+        id: &'a str,
+        finished: &mut HashMap<&'a str, bool>,
+        path: &mut Vec<&'a str>,
+        cycles: &mut BTreeSet<Vec<String>>,
+    ) {
+        finished.insert(id, false);
+        path.push(id);
+
+        let mut children: Vec<&String> = self
+            .child_map
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter(|child| child.as_str() != id)
+            .collect();
+        children.sort();
+        for child in children {
+            match finished.get(child.as_str()) {
+                None => self.walk_for_cycles(child, finished, path, cycles),
+                Some(false) => {
+                    let start = path.iter().position(|on_path| on_path == child);
+                    let mut cycle: Vec<String> = path[start.unwrap_or(0)..]
+                        .iter()
+                        .map(|id| id.to_string())
+                        .collect();
+                    if let Some(smallest) = (0..cycle.len()).min_by_key(|&i| &cycle[i]) {
+                        cycle.rotate_left(smallest);
+                    }
+                    cycles.insert(cycle);
+                }
+                Some(true) => {}
+            }
+        }
+
+        path.pop();
+        finished.insert(id, true);
     }
 
     pub fn has_missing_sources(&self) -> bool {
@@ -690,8 +1735,8 @@ impl SqlModelCollection {
         let mut report = Vec::with_capacity(self.missing_imports.len());
 
         for (model_id, missing_sources) in &self.missing_imports {
-            if let Some(model) = self.models.get(model_id) {
-                let missing_list = format_missing_sources(missing_sources);
+            if let Some(model) = self.get_model(model_id) {
+                let missing_list = format_missing_sources(model, missing_sources);
                 report.push(format!(
                     "Model '{}' references undefined external import(s): {}",
                     model.name, missing_list
@@ -702,26 +1747,311 @@ impl SqlModelCollection {
         report
     }
 
-    pub fn get_execution_order(&self) -> Result<Vec<&SqlModel>> {
-        let mut models: Vec<&SqlModel> = self.models.values().collect();
-        models.sort_by(|a, b| a.unique_id.cmp(&b.unique_id));
-        Ok(models)
+    /// Dependency links created by `schema.name` string matching, sorted by model
+    pub fn get_implicit_dependencies(&self) -> &[ImplicitDependency] {
+        &self.implicit_dependencies
     }
 
-    pub fn calculate_model_depths(&mut self) {
-        // Reset all depths
-        for model in self.models.values_mut() {
-            model.depth = None;
-        }
-
-        let model_ids: Vec<String> = self.models.keys().cloned().collect();
-        self.mark_source_nodes(&model_ids);
-        self.calculate_model_depths_iteratively(&model_ids);
+    pub fn get_implicit_dependencies_report(&self) -> Vec<String> {
+        self.implicit_dependencies
+            .iter()
+            .filter_map(|dependency| {
+                let model = self.get_model(&dependency.model_id)?;
+                let parent = self.get_model(&dependency.parent_id)?;
+                Some(format!(
+                    "Model '{}' depends on '{}' through an implicit match on '{}'",
+                    model.name, parent.name, dependency.relation
+                ))
+            })
+            .collect()
+    }
+
+    pub fn get_test_annotation_report(&self) -> Vec<String> {
+        let mut report: Vec<String> = self
+            .models()
+            .flat_map(|model| {
+                model
+                    .test_annotation_issues
+                    .iter()
+                    .map(move |issue| format!("Model '{}', {}", model.name, issue))
+            })
+            .collect();
+
+        report.sort();
+        report
+    }
+
+    /// Syntax errors in individual statements of otherwise parseable models
+    pub fn get_statement_error_report(&self) -> Vec<String> {
+        let mut report: Vec<String> =
+            self.models()
+                .flat_map(|model| {
+                    model.statement_errors.iter().map(move |error| {
+                        format!("{}: {}", model.relative_file_path.display(), error)
+                    })
+                })
+                .collect();
+
+        report.sort();
+        report
+    }
+
+    /// Report models that select from their own relation without being incremental.
+    /// Only incremental models may read `{{ this }}` to find already-loaded rows.
+    pub fn get_invalid_self_references_report(&self) -> Vec<String> {
+        let mut report: Vec<String> = self
+            .self_references
+            .iter()
+            .filter_map(|(model_id, tables)| {
+                let model = self.get_model(model_id)?;
+                if model.materialized.as_deref() == Some("incremental") {
+                    return None;
+                }
+
+                let mut tables: Vec<&String> = tables.iter().collect();
+                tables.sort();
+                let tables: Vec<&str> = tables.iter().map(|t| t.as_str()).collect();
+                Some(format!(
+                    "Model '{}' selects from its own relation ({}). Self-references are only allowed for incremental models via {{{{ this }}}}.",
+                    model.name,
+                    tables.join(", ")
+                ))
+            })
+            .collect();
+
+        report.sort();
+        report
+    }
+
+    /// Report models whose `materialized:` value is neither built-in nor defined by the project
+    pub fn get_unknown_materializations_report(
+        &self,
+        registry: &MaterializationRegistry,
+    ) -> Vec<String> {
+        let mut report: Vec<String> = self
+            .models()
+            .filter_map(|model| {
+                let materialized = model.materialized.as_deref()?;
+                if registry.is_known(materialized) {
+                    return None;
+                }
+                Some(format!(
+                    "Model '{}' uses unknown materialization '{}' (known: {})",
+                    model.name,
+                    materialized,
+                    registry.known_names().join(", ")
+                ))
+            })
+            .collect();
+
+        report.sort();
+        report
+    }
+
+    /// Invalid `partition_by`/`cluster_by` configs, and ones on models without a table
+    pub fn get_partitioning_report(&self) -> Vec<String> {
+        let mut report = Vec::new();
+        for model in self.models() {
+            if model.partition_by.is_none() && model.cluster_by.is_empty() {
+                continue;
+            }
+
+            let mut errors = Vec::new();
+            let materialized = model.materialized.as_deref().unwrap_or("view");
+            if !PARTITIONED_MATERIALIZATIONS.contains(&materialized) {
+                errors.push(format!(
+                    "partition_by and cluster_by need a table or incremental materialization, not {}",
+                    materialized
+                ));
+            }
+            if let Some(partition_by) = &model.partition_by {
+                errors.extend(partition_by.validate());
+            }
+            errors.extend(partitioning::validate_cluster_by(&model.cluster_by));
+
+            // Columns can only be checked against the YAML when it declares them
+            let declared = |column: &String| {
+                model.columns.is_empty()
+                    || model
+                        .columns
+                        .keys()
+                        .any(|name| name.eq_ignore_ascii_case(column))
+            };
+            let fields = model
+                .partition_by
+                .iter()
+                .map(|partition_by| &partition_by.field)
+                .chain(&model.cluster_by);
+            for field in fields.filter(|field| !declared(field)) {
+                errors.push(format!("column '{}' is not declared in YAML", field));
+            }
+
+            report.extend(
+                errors
+                    .into_iter()
+                    .map(|error| format!("Model '{}': {}", model.name, error)),
+            );
+        }
+
+        report.sort();
+        report
+    }
+
+    /// `expected_runtime` values that are not durations
+    pub fn get_expected_runtime_report(&self) -> Vec<String> {
+        let mut report: Vec<String> = self
+            .models()
+            .filter_map(|model| {
+                let error = anomalies::parse_runtime(model.expected_runtime.as_deref()?).err()?;
+                Some(format!("Model '{}': {}", model.name, error))
+            })
+            .collect();
+        report.sort();
+        report
+    }
+
+    /// Invalid snapshot history configs, and ones on models that are not snapshots
+    pub fn get_snapshot_report(&self) -> Vec<String> {
+        let mut report = Vec::new();
+        for model in self.models() {
+            let Some(snapshot) = &model.snapshot else {
+                continue;
+            };
+            let mut errors = Vec::new();
+            let materialized = model.materialized.as_deref().unwrap_or("view");
+            if materialized != SNAPSHOT_MATERIALIZATION {
+                errors.push(format!(
+                    "unique_key and strategy need a snapshot materialization, not {}",
+                    materialized
+                ));
+            }
+            errors.extend(snapshot.validate());
+            report.extend(
+                errors
+                    .into_iter()
+                    .map(|error| format!("Model '{}': {}", model.name, error)),
+            );
+        }
+
+        report.sort();
+        report
+    }
+
+    /// Meta values that break the built-in or declared meta schema
+    pub fn get_meta_report(&self, schema: &MetaSchema) -> Vec<String> {
+        let mut report: Vec<String> = self
+            .models()
+            .flat_map(|model| {
+                schema
+                    .check(&model.meta)
+                    .into_iter()
+                    .map(|problem| format!("Model '{}': {}", model.name, problem))
+            })
+            .collect();
+        report.sort();
+        report
+    }
+
+    /// Descriptions whose templates do not render
+    pub fn get_description_report(&self) -> Vec<String> {
+        let mut report: Vec<String> = self
+            .models()
+            .filter_map(|model| {
+                let description = model.description.as_deref()?;
+                template::render_description(description, &model.name, &model.meta)
+                    .err()
+                    .map(|err| format!("Model '{}': description: {}", model.name, err))
+            })
+            .collect();
+        report.sort();
+        report
+    }
+
+    /// Every model and snapshot ordered by unique id
+    pub fn models_by_id(&self) -> Vec<&SqlModel> {
+        let mut models: Vec<&SqlModel> = self.models().collect();
+        models.sort_by(|a, b| a.unique_id.cmp(&b.unique_id));
+        models
+    }
+
+    /// Models by their depth in the graph, then name and unique id; models in a cycle, which
+    /// have no depth, come last. Only needs `build_dependency_graph` to have run.
+    pub fn models_by_depth(&self) -> Vec<&SqlModel> {
+        let mut models: Vec<&SqlModel> = self.models().collect();
+        models.sort_by(|a, b| {
+            (a.depth.is_none(), a.depth, &a.name, &a.unique_id).cmp(&(
+                b.depth.is_none(),
+                b.depth,
+                &b.name,
+                &b.unique_id,
+            ))
+        });
+        models
+    }
+
+    /// Every model after the models it depends on, ties broken by unique id so the order is
+    /// stable. Fails when dependencies form a cycle.
+    pub fn get_execution_order(&self) -> Result<Vec<&SqlModel>> {
+        let models = self.models_by_id();
+        let parent_count = |id: &str| {
+            self.parent_map
+                .get(id)
+                .into_iter()
+                .flatten()
+                .filter(|parent| parent.as_str() != id && self.get_model(parent).is_some())
+                .count()
+        };
+
+        // Kahn's algorithm: a model is ready once all of its parents are ordered
+        let mut waiting_on: BTreeMap<&str, usize> = models
+            .iter()
+            .map(|model| (model.unique_id.as_str(), parent_count(&model.unique_id)))
+            .collect();
+        let mut ready: BTreeSet<&str> = waiting_on
+            .iter()
+            .filter(|(_, parents)| **parents == 0)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut order = Vec::with_capacity(models.len());
+        while let Some(id) = ready.pop_first() {
+            waiting_on.remove(id);
+            if let Some(model) = self.get_model(id) {
+                order.push(model);
+            }
+            for child in self.child_map.get(id).into_iter().flatten() {
+                if let Some(parents) = waiting_on.get_mut(child.as_str()) {
+                    *parents -= 1;
+                    if *parents == 0 {
+                        ready.insert(child.as_str());
+                    }
+                }
+            }
+        }
+
+        if !waiting_on.is_empty() {
+            return Err(FeatherFlowError::DependencyCycle {
+                cycles: self.detect_cycles(),
+            }
+            .into());
+        }
+        Ok(order)
+    }
+
+    pub fn calculate_model_depths(&mut self) {
+        // Reset all depths
+        for model in self.nodes.values_mut().filter_map(Node::as_model_mut) {
+            model.depth = None;
+        }
+
+        let model_ids: Vec<String> = self.models().map(|model| model.unique_id.clone()).collect();
+        self.mark_source_nodes(&model_ids);
+        self.calculate_model_depths_iteratively(&model_ids);
     }
 
     fn mark_source_nodes(&mut self, model_ids: &[String]) {
         for id in model_ids {
-            if let Some(model) = self.models.get_mut(id) {
+            if let Some(model) = self.nodes.get_mut(id).and_then(Node::as_model_mut) {
                 if model.upstream_models.is_empty() {
                     model.depth = Some(0);
                 }
@@ -735,13 +2065,13 @@ impl SqlModelCollection {
             made_changes = false;
 
             for id in model_ids {
-                if self.models.get(id).is_some_and(|m| m.depth.is_some()) {
+                if self.get_model(id).is_some_and(|m| m.depth.is_some()) {
                     continue;
                 }
 
                 if let Some((needs_update, max_depth)) = self.check_model_dependencies(id) {
                     if needs_update {
-                        if let Some(model) = self.models.get_mut(id) {
+                        if let Some(model) = self.nodes.get_mut(id).and_then(Node::as_model_mut) {
                             model.depth = max_depth.map(|d| d + 1);
                             made_changes = true;
                         }
@@ -752,7 +2082,7 @@ impl SqlModelCollection {
     }
 
     fn check_model_dependencies(&self, model_id: &str) -> Option<(bool, Option<usize>)> {
-        let model = self.models.get(model_id)?;
+        let model = self.get_model(model_id)?;
 
         if model.upstream_models.is_empty() || model.depth.is_some() {
             return Some((false, None));
@@ -762,7 +2092,7 @@ impl SqlModelCollection {
         let mut all_upstreams_have_depths = true;
 
         for upstream_id in &model.upstream_models {
-            if let Some(upstream) = self.models.get(upstream_id) {
+            if let Some(upstream) = self.get_model(upstream_id) {
                 if let Some(depth) = upstream.depth {
                     max_upstream_depth = Some(max_upstream_depth.unwrap_or(0).max(depth));
                 } else {
@@ -776,14 +2106,18 @@ impl SqlModelCollection {
     }
 
     pub fn to_dot_graph(&self) -> String {
-        generate_dot_graph(self)
+        generate_dot_graph(self, None)
+    }
+
+    /// DOT graph with every node filled by its run status; models without a status are pending
+    pub fn to_status_dot_graph(&self, statuses: &HashMap<String, ModelRunStatus>) -> String {
+        generate_dot_graph(self, Some(statuses))
     }
 
     /// Export all models in a serializable format for debugging and analysis
     #[cfg(test)]
     pub fn export_all_models(&self) -> Vec<serde_json::Value> {
-        self.models
-            .values()
+        self.models()
             .map(|model| model.to_serializable_format())
             .collect()
     }
@@ -825,12 +2159,17 @@ fn model_to_yaml_output(model: &SqlModel) -> YamlOutputModel {
     YamlOutputModel {
         name: model.name.clone(),
         path: model.relative_file_path.to_string_lossy().to_string(),
-        description: model.description.clone(),
+        description: model.rendered_description(),
         materialized: model.materialized.clone(),
         database: model.database.clone(),
         schema: model.schema.clone(),
         object_name: model.object_name.clone(),
+        alias: model.alias.clone(),
+        loaded_at_field: model.loaded_at_field.clone(),
+        checksum: model.checksum.clone(),
+        normalized_checksum: model.normalized_checksum.clone(),
         tags,
+        config_sources: model.config_sources.clone(),
         columns,
         depends_on,
         referenced_by,
@@ -881,12 +2220,16 @@ fn log_yaml_files_count(count: usize) {
 fn process_import_yaml_file(
     yaml_path: &Path,
     defined_imports: &mut HashSet<String>,
+    source_tables: &mut BTreeMap<(String, String), String>,
+    declared: &mut HashMap<String, Source>,
+    exposures: &mut Vec<Exposure>,
 ) -> std::io::Result<()> {
     let yaml_content = read_yaml_file_content(yaml_path)?;
     let yaml_config = parse_yaml_config(&yaml_content, yaml_path);
 
-    if let Ok(config) = yaml_config {
-        process_yaml_sources(config, yaml_path, defined_imports);
+    if let Ok(mut config) = yaml_config {
+        exposures.extend(config.exposures.take().unwrap_or_default());
+        process_yaml_sources(config, yaml_path, defined_imports, source_tables, declared);
     }
 
     Ok(())
@@ -913,25 +2256,47 @@ fn process_yaml_sources(
     yaml_config: YamlConfig,
     yaml_path: &Path,
     defined_imports: &mut HashSet<String>,
+    source_tables: &mut BTreeMap<(String, String), String>,
+    declared: &mut HashMap<String, Source>,
 ) {
     if let Some(sources) = yaml_config.sources {
         eprintln!("Found {} sources in {}", sources.len(), yaml_path.display());
 
         for source in sources {
-            extract_import_sources(&source, defined_imports);
+            extract_import_sources(&source, defined_imports, source_tables, declared);
         }
     } else {
         eprintln!("No imports found in {}", yaml_path.display());
     }
 }
 
-fn extract_import_sources(source: &YamlSource, defined_imports: &mut HashSet<String>) {
+fn extract_import_sources(
+    source: &YamlSource,
+    defined_imports: &mut HashSet<String>,
+    source_tables: &mut BTreeMap<(String, String), String>,
+    declared: &mut HashMap<String, Source>,
+) {
     let source_prefix = source.database.to_string();
     log_import_processing(&source.name, &source_prefix);
 
     for table in &source.tables {
         let import_name = format!("{}.{}", source_prefix, table.name);
         log_import_added(&import_name);
+        let mut declaration = Source::new(&import_name);
+        if let Some(columns) = &table.columns {
+            declaration.columns = columns.iter().map(|column| column.name.clone()).collect();
+        }
+        declaration.loaded_at_field = table
+            .loaded_at_field
+            .clone()
+            .or_else(|| source.loaded_at_field.clone());
+        declaration.freshness = table.freshness.clone().or_else(|| source.freshness.clone());
+        declaration.awaited = table.awaited.clone().or_else(|| source.awaited.clone());
+        declared.insert(import_name.clone(), declaration);
+        source_tables.insert(
+            (source.name.clone(), table.name.clone()),
+            import_name.clone(),
+        );
         defined_imports.insert(import_name);
     }
 }
@@ -966,17 +2331,38 @@ fn log_imports_details(defined_imports: &HashSet<String>) {
     }
 }
 
-fn format_missing_sources(missing_sources: &HashSet<String>) -> String {
-    missing_sources
-        .iter()
-        .map(|s| format!("'{}'", s))
+/// `'schema.table' (path:line:column)` for each missing source, pointing at its first reference
+fn format_missing_sources(model: &SqlModel, missing_sources: &HashSet<String>) -> String {
+    let mut sources: Vec<&String> = missing_sources.iter().collect();
+    sources.sort();
+
+    sources
+        .into_iter()
+        .map(|source| match model.first_reference(source) {
+            Some(location) if location.line.is_some() => format!(
+                "'{}' ({}:{})",
+                source,
+                model.relative_file_path.display(),
+                location
+            ),
+            Some(location) => format!(
+                "'{}' ({}, {})",
+                source,
+                model.relative_file_path.display(),
+                location
+            ),
+            None => format!("'{}'", source),
+        })
         .collect::<Vec<_>>()
         .join(", ")
 }
 
-fn generate_dot_graph(collection: &SqlModelCollection) -> String {
+fn generate_dot_graph(
+    collection: &SqlModelCollection,
+    statuses: Option<&HashMap<String, ModelRunStatus>>,
+) -> String {
     // Estimate capacity based on typical graph size
-    let models_count = collection.models.len();
+    let models_count = collection.models().count();
     let edges_count = collection
         .child_map
         .values()
@@ -992,12 +2378,29 @@ fn generate_dot_graph(collection: &SqlModelCollection) -> String {
     result.push_str("  node [shape=box];\n");
 
     // Add nodes
-    for model in collection.models.values() {
-        let depth_label = model.depth.map_or("?".to_string(), |d| d.to_string());
-        result.push_str(&format!(
-            "  \"{}\" [label=\"{} (depth: {})\"];\n",
-            model.unique_id, model.name, depth_label
-        ));
+    for model in collection.models() {
+        match statuses {
+            Some(statuses) => {
+                let status = statuses
+                    .get(&model.unique_id)
+                    .copied()
+                    .unwrap_or(ModelRunStatus::Pending);
+                result.push_str(&format!(
+                    "  \"{}\" [label=\"{} ({})\", style=filled, fillcolor=\"{}\"];\n",
+                    model.unique_id,
+                    model.name,
+                    status,
+                    status.color()
+                ));
+            }
+            None => {
+                let depth_label = model.depth.map_or("?".to_string(), |d| d.to_string());
+                result.push_str(&format!(
+                    "  \"{}\" [label=\"{} (depth: {})\"];\n",
+                    model.unique_id, model.name, depth_label
+                ));
+            }
+        }
     }
 
     // Add edges
@@ -1009,15 +2412,14 @@ fn generate_dot_graph(collection: &SqlModelCollection) -> String {
 
     // Compute max depth once
     let max_depth = collection
-        .models
-        .values()
+        .models()
         .filter_map(|m| m.depth)
         .max()
         .unwrap_or(0);
 
     // Group models by depth for more efficient iteration
     let mut models_by_depth: Vec<Vec<&str>> = vec![Vec::new(); max_depth + 1];
-    for model in collection.models.values() {
+    for model in collection.models() {
         if let Some(depth) = model.depth {
             if depth <= max_depth {
                 models_by_depth[depth].push(&model.unique_id);
@@ -1044,6 +2446,7 @@ fn generate_dot_graph(collection: &SqlModelCollection) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
     use sqlparser::dialect::DuckDbDialect;
     use std::fs;
     use std::path::PathBuf;
@@ -1083,15 +2486,18 @@ mod tests {
         // Create model collection and parse models
         let mut model_collection = SqlModelCollection::new();
 
-        let mut model_a = SqlModel::from_path(&file_a, project_root, "duckdb", &dialect).unwrap();
+        let mut model_a =
+            SqlModel::from_path(&file_a, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
         model_a.extract_dependencies().unwrap();
         model_collection.add_model(model_a);
 
-        let mut model_b = SqlModel::from_path(&file_b, project_root, "duckdb", &dialect).unwrap();
+        let mut model_b =
+            SqlModel::from_path(&file_b, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
         model_b.extract_dependencies().unwrap();
         model_collection.add_model(model_b);
 
-        let mut model_c = SqlModel::from_path(&file_c, project_root, "duckdb", &dialect).unwrap();
+        let mut model_c =
+            SqlModel::from_path(&file_c, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
         model_c.extract_dependencies().unwrap();
         model_collection.add_model(model_c);
 
@@ -1099,9 +2505,9 @@ mod tests {
         model_collection.build_dependency_graph();
 
         // Get models by ID
-        let model_a_id = format!("model.model_a.model_a");
-        let model_b_id = format!("model.model_b.model_b");
-        let model_c_id = format!("model.model_c.model_c");
+        let model_a_id = "model.model_a.model_a".to_string();
+        let model_b_id = "model.model_b.model_b".to_string();
+        let model_c_id = "model.model_c.model_c".to_string();
 
         // Verify depths
         if let Some(model_a) = model_collection.get_model(&model_a_id) {
@@ -1138,9 +2544,15 @@ mod tests {
         let project_root = PathBuf::from("/tmp");
         let dialect = DuckDbDialect {};
 
-        let model =
-            SqlModel::from_content(&path, &project_root, sql.to_string(), "duckdb", &dialect)
-                .unwrap();
+        let model = SqlModel::from_content(
+            &path,
+            &project_root,
+            sql.to_string(),
+            "duckdb",
+            &dialect,
+            &Sha256Hash,
+        )
+        .unwrap();
 
         assert_eq!(model.name, "test_model");
         assert_eq!(model.file_name, "test_model.sql");
@@ -1156,9 +2568,15 @@ mod tests {
         let project_root = PathBuf::from("/tmp");
         let dialect = DuckDbDialect {};
 
-        let mut model =
-            SqlModel::from_content(&path, &project_root, sql.to_string(), "duckdb", &dialect)
-                .unwrap();
+        let mut model = SqlModel::from_content(
+            &path,
+            &project_root,
+            sql.to_string(),
+            "duckdb",
+            &dialect,
+            &Sha256Hash,
+        )
+        .unwrap();
 
         model.extract_dependencies().unwrap();
 
@@ -1206,7 +2624,9 @@ models:
 
         // Create and parse the model
         let dialect = DuckDbDialect {};
-        let model = SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect).unwrap();
+        let model =
+            SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                .unwrap();
 
         // Test that validation passes
         assert!(model.is_valid_structure);
@@ -1237,4 +2657,888 @@ models:
         assert_eq!(name_column.description, Some("The user's name".to_string()));
         assert_eq!(name_column.data_type, Some("string".to_string()));
     }
+
+    #[test]
+    fn test_description_templates_render_from_meta() {
+        let temp_dir = tempdir().unwrap();
+        let mut collection = SqlModelCollection::new();
+        for (name, description) in [
+            ("stg_trends", "Daily trends for {{ meta.domain }} team"),
+            ("stg_events", "Events for {{ meta.team }} team"),
+        ] {
+            let model_dir = temp_dir.path().join(name);
+            fs::create_dir(&model_dir).unwrap();
+            let sql_file = model_dir.join(format!("{}.sql", name));
+            fs::write(&sql_file, "SELECT id FROM raw.events").unwrap();
+            fs::write(
+                model_dir.join(format!("{}.yml", name)),
+                format!(
+                    "version: 2\nmodels:\n  - name: {}\n    description: \"{}\"\n    meta:\n      domain: marketing\n",
+                    name, description
+                ),
+            )
+            .unwrap();
+            let model = SqlModel::from_path(
+                &sql_file,
+                temp_dir.path(),
+                "duckdb",
+                &DuckDbDialect {},
+                &Sha256Hash,
+            )
+            .unwrap();
+            collection.add_model(model);
+        }
+
+        let trends = collection.get_model("model.stg_trends.stg_trends").unwrap();
+        assert_eq!(
+            trends.rendered_description().as_deref(),
+            Some("Daily trends for marketing team")
+        );
+        let events = collection.get_model("model.stg_events.stg_events").unwrap();
+        assert_eq!(
+            events.rendered_description().as_deref(),
+            Some("Events for {{ meta.team }} team")
+        );
+        assert_eq!(
+            collection.get_description_report(),
+            vec!["Model 'stg_events': description: {{ meta.team }} has no value"]
+        );
+    }
+
+    #[test]
+    fn test_apply_target_overrides() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        // Model A lives in a different schema depending on the target
+        let model_a_dir = project_root.join("model_a");
+        fs::create_dir(&model_a_dir).unwrap();
+        let file_a = model_a_dir.join("model_a.sql");
+        fs::write(&file_a, "SELECT id FROM raw.events").unwrap();
+        fs::write(
+            model_a_dir.join("model_a.yml"),
+            r#"
+version: 2
+models:
+  - name: model_a
+    config:
+      materialized: table
+      targets:
+        prod:
+          schema: analytics
+          database: warehouse
+        dev:
+          schema: dev_analytics
+    schema_name: analytics
+"#,
+        )
+        .unwrap();
+
+        // Model B references the dev location of model A
+        let model_b_dir = project_root.join("model_b");
+        fs::create_dir(&model_b_dir).unwrap();
+        let file_b = model_b_dir.join("model_b.sql");
+        fs::write(&file_b, "SELECT id FROM dev_analytics.model_a").unwrap();
+        fs::write(model_b_dir.join("model_b.yml"), "version: 2\n").unwrap();
+
+        let mut model_a =
+            SqlModel::from_path(&file_a, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+        assert_eq!(model_a.target_overrides.len(), 2);
+
+        model_a.apply_target("prod");
+        assert_eq!(model_a.schema.as_deref(), Some("analytics"));
+        assert_eq!(model_a.database.as_deref(), Some("warehouse"));
+
+        // Unknown targets leave the configured location untouched
+        model_a.apply_target("staging");
+        assert_eq!(model_a.schema.as_deref(), Some("analytics"));
+
+        let mut model_b =
+            SqlModel::from_path(&file_b, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+        model_a.extract_dependencies().unwrap();
+        model_b.extract_dependencies().unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model_a);
+        collection.add_model(model_b);
+        collection.apply_target("dev");
+        collection.build_dependency_graph();
+
+        let model_b = collection.get_model("model.model_b.model_b").unwrap();
+        assert!(model_b.upstream_models.contains("model.model_a.model_a"));
+        assert!(model_b.external_sources.is_empty());
+    }
+
+    #[test]
+    fn test_execution_order_is_topological() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let mut collection = SqlModelCollection::new();
+        for (name, sql) in [
+            (
+                "a_top",
+                "SELECT id FROM public.m_mid JOIN public.z_base USING (id)",
+            ),
+            ("m_mid", "SELECT id FROM public.z_base"),
+            ("b_other", "SELECT 1 AS id"),
+            ("z_base", "SELECT 1 AS id"),
+        ] {
+            let dir = project_root.join(name);
+            fs::create_dir(&dir).unwrap();
+            let file = dir.join(format!("{}.sql", name));
+            fs::write(&file, sql).unwrap();
+            let mut model =
+                SqlModel::from_path(&file, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+        collection.build_dependency_graph();
+
+        let order: Vec<&str> = collection
+            .get_execution_order()
+            .unwrap()
+            .into_iter()
+            .map(|model| model.name.as_str())
+            .collect();
+        assert_eq!(order, vec!["b_other", "z_base", "m_mid", "a_top"]);
+
+        // z_base reading a_top closes a cycle
+        let file = project_root.join("z_base/z_base.sql");
+        fs::write(&file, "SELECT id FROM public.a_top").unwrap();
+        let mut z_base =
+            SqlModel::from_path(&file, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+        z_base.extract_dependencies().unwrap();
+        collection.add_model(z_base);
+        collection.build_dependency_graph();
+
+        let err = collection.get_execution_order().unwrap_err();
+        assert_eq!(
+            crate::error::code_of(err.as_ref()),
+            Some(crate::error::codes::DEPENDENCY_CYCLE)
+        );
+        assert!(err
+            .to_string()
+            .contains("model.a_top.a_top -> model.z_base.z_base -> model.a_top.a_top"));
+        assert_eq!(
+            collection.detect_cycles(),
+            vec![
+                vec!["model.a_top.a_top", "model.z_base.z_base"],
+                vec![
+                    "model.a_top.a_top",
+                    "model.z_base.z_base",
+                    "model.m_mid.m_mid"
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rewrite_relocated_references() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let model_a_dir = project_root.join("stg_events");
+        fs::create_dir(&model_a_dir).unwrap();
+        let file_a = model_a_dir.join("stg_events.sql");
+        fs::write(&file_a, "SELECT id FROM raw.events").unwrap();
+        fs::write(
+            model_a_dir.join("stg_events.yml"),
+            "version: 2\nmodels:\n  - name: stg_events\n    schema_name: staging\n    config:\n      targets:\n        prod: {schema: analytics, database: warehouse}\n",
+        )
+        .unwrap();
+
+        let model_b_dir = project_root.join("events");
+        fs::create_dir(&model_b_dir).unwrap();
+        let file_b = model_b_dir.join("events.sql");
+        fs::write(&file_b, "SELECT id FROM staging.stg_events").unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        for file in [&file_a, &file_b] {
+            let mut model =
+                SqlModel::from_path(file, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+        collection.apply_target("prod");
+        collection.build_dependency_graph();
+        collection.rewrite_relocated_references();
+
+        let model_b = collection.get_model("model.events.events").unwrap();
+        assert!(model_b
+            .upstream_models
+            .contains("model.stg_events.stg_events"));
+        assert_eq!(
+            model_b.compiled_sql.as_deref(),
+            Some("SELECT id FROM warehouse.analytics.stg_events")
+        );
+    }
+
+    #[test]
+    fn test_ref_resolves_to_target_relation_as_explicit_dependency() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let model_a_dir = project_root.join("stg_events");
+        fs::create_dir(&model_a_dir).unwrap();
+        let file_a = model_a_dir.join("stg_events.sql");
+        fs::write(&file_a, "SELECT id FROM raw.events").unwrap();
+        fs::write(
+            model_a_dir.join("stg_events.yml"),
+            "version: 2\nmodels:\n  - name: stg_events\n    schema_name: staging\n    config:\n      targets:\n        prod: {schema: analytics}\n",
+        )
+        .unwrap();
+
+        let model_b_dir = project_root.join("events");
+        fs::create_dir(&model_b_dir).unwrap();
+        let file_b = model_b_dir.join("events.sql");
+        fs::write(&file_b, "SELECT id FROM {{ ref('stg_events') }}").unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        for file in [&file_a, &file_b] {
+            let mut model =
+                SqlModel::from_path(file, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+        collection.apply_target("prod");
+        collection
+            .compile(&TargetContext::new("prod", "duckdb"))
+            .unwrap();
+        collection.build_dependency_graph();
+
+        let model_b = collection.get_model("model.events.events").unwrap();
+        assert_eq!(
+            model_b.compiled_sql.as_deref(),
+            Some("SELECT id FROM analytics.stg_events")
+        );
+        assert!(model_b
+            .upstream_models
+            .contains("model.stg_events.stg_events"));
+        assert!(collection.get_implicit_dependencies().is_empty());
+    }
+
+    #[test]
+    fn test_isolate_schemas_rewrites_model_references() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let model_a_dir = project_root.join("stg_events");
+        fs::create_dir(&model_a_dir).unwrap();
+        let file_a = model_a_dir.join("stg_events.sql");
+        fs::write(&file_a, "SELECT id FROM raw.events").unwrap();
+        fs::write(
+            model_a_dir.join("stg_events.yml"),
+            "version: 2\nmodels:\n  - name: stg_events\n    schema_name: staging\n",
+        )
+        .unwrap();
+
+        let model_b_dir = project_root.join("events");
+        fs::create_dir(&model_b_dir).unwrap();
+        let file_b = model_b_dir.join("events.sql");
+        fs::write(
+            &file_b,
+            "SELECT id FROM staging.stg_events -- not staging.other",
+        )
+        .unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        for file in [&file_a, &file_b] {
+            let mut model =
+                SqlModel::from_path(file, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+        collection.build_dependency_graph();
+        collection.isolate_schemas("alice");
+
+        let model_a = collection.get_model("model.stg_events.stg_events").unwrap();
+        assert_eq!(model_a.qualified_relation(), "staging_alice.stg_events");
+        assert_eq!(
+            model_a.compiled_sql.as_deref(),
+            Some("SELECT id FROM raw.events")
+        );
+
+        let model_b = collection.get_model("model.events.events").unwrap();
+        assert_eq!(
+            model_b.compiled_sql.as_deref(),
+            Some("SELECT id FROM staging_alice.stg_events -- not staging.other")
+        );
+    }
+
+    #[test]
+    fn test_defer_to_state_rewrites_unselected_upstream_references() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let model_a_dir = project_root.join("stg_events");
+        fs::create_dir(&model_a_dir).unwrap();
+        let file_a = model_a_dir.join("stg_events.sql");
+        fs::write(&file_a, "SELECT id FROM raw.events").unwrap();
+        fs::write(
+            model_a_dir.join("stg_events.yml"),
+            "version: 2\nmodels:\n  - name: stg_events\n    schema_name: staging\n",
+        )
+        .unwrap();
+
+        let model_b_dir = project_root.join("events");
+        fs::create_dir(&model_b_dir).unwrap();
+        let file_b = model_b_dir.join("events.sql");
+        fs::write(&file_b, "SELECT id FROM staging.stg_events").unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        for file in [&file_a, &file_b] {
+            let mut model =
+                SqlModel::from_path(file, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+        collection.build_dependency_graph();
+        collection.isolate_schemas("alice");
+
+        let manifest_path = project_root.join("prod.json");
+        fs::write(
+            &manifest_path,
+            r#"{"models": {"model.stg_events.stg_events": {"name": "stg_events", "schema": "staging", "database": "prod"}}}"#,
+        )
+        .unwrap();
+        let manifest = StateManifest::load(&manifest_path).unwrap();
+
+        let selected = HashSet::from(["model.events.events".to_string()]);
+        let deferred = collection.defer_to_state(&selected, &manifest, |_| true);
+        assert_eq!(deferred, vec!["model.stg_events.stg_events"]);
+
+        let model_b = collection.get_model("model.events.events").unwrap();
+        assert_eq!(
+            model_b.compiled_sql.as_deref(),
+            Some("SELECT id FROM prod.staging.stg_events")
+        );
+
+        let none = collection.defer_to_state(&selected, &manifest, |_| false);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_alias_resolution_and_matching() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let model_a_dir = project_root.join("stg_events");
+        fs::create_dir(&model_a_dir).unwrap();
+        let file_a = model_a_dir.join("stg_events.sql");
+        fs::write(&file_a, "SELECT id FROM raw.events").unwrap();
+        fs::write(
+            model_a_dir.join("stg_events.yml"),
+            r#"
+version: 2
+models:
+  - name: stg_events
+    database_name: warehouse
+    schema_name: staging
+    object_name: stg_events
+    alias: events_v2
+"#,
+        )
+        .unwrap();
+
+        let model_b_dir = project_root.join("daily_events");
+        fs::create_dir(&model_b_dir).unwrap();
+        let file_b = model_b_dir.join("daily_events.sql");
+        fs::write(
+            &file_b,
+            "SELECT a.id FROM staging.events_v2 a JOIN warehouse.staging.events_v2 b ON a.id = b.id",
+        )
+        .unwrap();
+        fs::write(model_b_dir.join("daily_events.yml"), "version: 2\n").unwrap();
+
+        let mut model_a =
+            SqlModel::from_path(&file_a, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+        assert_eq!(model_a.alias.as_deref(), Some("events_v2"));
+        assert_eq!(model_a.relation_name(), "events_v2");
+        assert_eq!(model_a.qualified_relation(), "warehouse.staging.events_v2");
+
+        let mut model_b =
+            SqlModel::from_path(&file_b, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+        assert_eq!(model_b.relation_name(), "daily_events");
+
+        model_a.extract_dependencies().unwrap();
+        model_b.extract_dependencies().unwrap();
+
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model_a);
+        collection.add_model(model_b);
+        collection.build_dependency_graph();
+
+        let model_b = collection
+            .get_model("model.daily_events.daily_events")
+            .unwrap();
+        assert!(model_b
+            .upstream_models
+            .contains("model.stg_events.stg_events"));
+        assert!(model_b.external_sources.is_empty());
+
+        let yaml = collection.write_yaml(Vec::new()).unwrap();
+        let manifest: serde_yaml::Value = serde_yaml::from_slice(&yaml).unwrap();
+        let output = &manifest["models"]["model.stg_events.stg_events"];
+        assert_eq!(output["alias"].as_str(), Some("events_v2"));
+        // Upstream first, although its unique id sorts after the downstream one's
+        let ids: Vec<&str> = manifest["models"]
+            .as_mapping()
+            .unwrap()
+            .keys()
+            .filter_map(|key| key.as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                "model.stg_events.stg_events",
+                "model.daily_events.daily_events"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_self_references_only_allowed_for_incremental() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let mut collection = SqlModelCollection::new();
+        for (name, materialized) in [("events", "incremental"), ("sessions", "table")] {
+            let model_dir = project_root.join(name);
+            fs::create_dir(&model_dir).unwrap();
+            let sql_file = model_dir.join(format!("{}.sql", name));
+            fs::write(
+                &sql_file,
+                format!(
+                    "SELECT id FROM raw.{0} WHERE id > (SELECT max(id) FROM analytics.{0})",
+                    name
+                ),
+            )
+            .unwrap();
+            fs::write(
+                model_dir.join(format!("{}.yml", name)),
+                format!(
+                    "version: 2\nmodels:\n  - name: {}\n    schema_name: analytics\n    config:\n      materialized: {}\n",
+                    name, materialized
+                ),
+            )
+            .unwrap();
+
+            let mut model =
+                SqlModel::from_path(&sql_file, project_root, "duckdb", &dialect, &Sha256Hash)
+                    .unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+
+        collection.build_dependency_graph();
+
+        // Self-references never become graph edges
+        let events = collection.get_model("model.events.events").unwrap();
+        assert!(events.upstream_models.is_empty());
+        assert!(events.downstream_models.is_empty());
+        assert!(events.external_sources.contains("raw.events"));
+        assert!(!events.external_sources.contains("analytics.events"));
+
+        let report = collection.get_invalid_self_references_report();
+        assert_eq!(report.len(), 1);
+        assert!(report[0].contains("'sessions'"));
+        assert!(report[0].contains("analytics.sessions"));
+    }
+
+    #[test]
+    fn test_snapshot_history_config() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let mut collection = SqlModelCollection::new();
+        for (name, config) in [
+            (
+                "customers_history",
+                "materialized: snapshot\n      unique_key: [customer_id, region]\n      strategy: check\n      check_cols: all",
+            ),
+            (
+                "orders_history",
+                "materialized: table\n      unique_key: order_id\n      strategy: timestamp",
+            ),
+        ] {
+            let model_dir = project_root.join(name);
+            fs::create_dir(&model_dir).unwrap();
+            let sql_file = model_dir.join(format!("{}.sql", name));
+            fs::write(&sql_file, "SELECT 1 AS id").unwrap();
+            fs::write(
+                model_dir.join(format!("{}.yml", name)),
+                format!(
+                    "version: 2\nmodels:\n  - name: {}\n    config:\n      {}\n",
+                    name, config
+                ),
+            )
+            .unwrap();
+            collection.add_model(
+                SqlModel::from_path(&sql_file, project_root, "duckdb", &dialect, &Sha256Hash).unwrap(),
+            );
+        }
+
+        let customers = collection
+            .get_model("model.customers_history.customers_history")
+            .unwrap();
+        let snapshot = customers.snapshot.as_ref().unwrap();
+        assert_eq!(snapshot.unique_key, vec!["customer_id", "region"]);
+        assert_eq!(snapshot.check_cols, Some(vec!["all".to_string()]));
+
+        assert_eq!(
+            collection.get_snapshot_report(),
+            vec![
+                "Model 'orders_history': the timestamp strategy needs updated_at",
+                "Model 'orders_history': unique_key and strategy need a snapshot materialization, not table",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_this_for_target() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("events");
+        fs::create_dir(&model_dir).unwrap();
+
+        let sql_file = model_dir.join("events.sql");
+        fs::write(
+            &sql_file,
+            "SELECT * FROM raw.events WHERE event_at > (SELECT max(event_at) FROM {{ this }})",
+        )
+        .unwrap();
+        fs::write(
+            model_dir.join("events.yml"),
+            r#"
+version: 2
+models:
+  - name: events
+    schema_name: analytics
+    config:
+      materialized: incremental
+      targets:
+        dev:
+          schema: dev_analytics
+"#,
+        )
+        .unwrap();
+
+        let dialect = DuckDbDialect {};
+        let mut model =
+            SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                .unwrap();
+        model.extract_dependencies().unwrap();
+        assert!(model
+            .compiled_sql
+            .as_deref()
+            .unwrap()
+            .contains("FROM analytics.events)"));
+        assert!(model.referenced_tables.contains("analytics.events"));
+
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model);
+        collection.apply_target("dev");
+        collection
+            .compile(&TargetContext::new("dev", "duckdb"))
+            .unwrap();
+        collection.build_dependency_graph();
+
+        let model = collection.get_model("model.events.events").unwrap();
+        assert!(model.referenced_tables.contains("dev_analytics.events"));
+        assert!(!model.referenced_tables.contains("analytics.events"));
+        assert!(collection.get_invalid_self_references_report().is_empty());
+    }
+
+    #[test]
+    fn test_loaded_at_field_recorded() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("events");
+        fs::create_dir(&model_dir).unwrap();
+
+        let sql_file = model_dir.join("events.sql");
+        fs::write(&sql_file, "SELECT id, _loaded_at FROM raw.events").unwrap();
+        fs::write(
+            model_dir.join("events.yml"),
+            "version: 2\nmodels:\n  - name: events\n    schema_name: analytics\n    loaded_at_field: _loaded_at\n",
+        )
+        .unwrap();
+
+        let dialect = DuckDbDialect {};
+        let model =
+            SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                .unwrap();
+
+        assert_eq!(model.loaded_at_field.as_deref(), Some("_loaded_at"));
+        assert_eq!(model.qualified_relation(), "analytics.events");
+        assert_eq!(
+            model_to_yaml_output(&model).loaded_at_field.as_deref(),
+            Some("_loaded_at")
+        );
+    }
+
+    #[test]
+    fn test_comments_survive_compilation() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("orders");
+        fs::create_dir(&model_dir).unwrap();
+
+        let sql_file = model_dir.join("orders.sql");
+        fs::write(
+            &sql_file,
+            "-- Orders with one row per order\n/* owner: analytics */\nSELECT id -- primary key\nFROM {{ target.schema }}.raw_orders",
+        )
+        .unwrap();
+
+        let dialect = DuckDbDialect {};
+        let model =
+            SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                .unwrap();
+
+        let compiled = model.compiled_sql.as_deref().unwrap();
+        assert!(compiled.starts_with("-- Orders with one row per order\n/* owner: analytics */"));
+        assert!(compiled.contains("-- primary key"));
+
+        assert_eq!(
+            model.formatted_sql(),
+            "-- Orders with one row per order\n/* owner: analytics */\nSELECT id FROM public.raw_orders"
+        );
+    }
+
+    #[test]
+    fn test_statement_errors_are_localized() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("setup");
+        fs::create_dir(&model_dir).unwrap();
+        let dialect = DuckDbDialect {};
+
+        let sql_file = model_dir.join("setup.sql");
+        fs::write(
+            &sql_file,
+            "SELECT 1 FROM raw.a;\nSELECT FROM WHERE;\nSELECT 2\nFROM raw.b;\n",
+        )
+        .unwrap();
+
+        let mut model =
+            SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                .unwrap();
+        model.extract_dependencies().unwrap();
+        assert_eq!(model.ast.len(), 2);
+        assert!(model.referenced_tables.contains("raw.b"));
+        assert_eq!(model.statement_errors.len(), 1);
+        assert_eq!(model.statement_errors[0].statement, 2);
+        assert_eq!(model.statement_errors[0].start_line, 2);
+
+        fs::write(&sql_file, "SELECT FROM WHERE;").unwrap();
+        let err = SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("statement 1 (line 1)"));
+    }
+
+    #[test]
+    fn test_reference_locations_point_at_table_factors() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("orders");
+        fs::create_dir(&model_dir).unwrap();
+        let dialect = DuckDbDialect {};
+
+        let sql_file = model_dir.join("orders.sql");
+        fs::write(
+            &sql_file,
+            "SELECT 1;\nSELECT o.id\nFROM raw.orders o\n  JOIN raw.customers c ON o.customer_id = c.id\n",
+        )
+        .unwrap();
+
+        let mut model =
+            SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                .unwrap();
+        model.extract_dependencies().unwrap();
+
+        let orders = model.first_reference("raw.orders").unwrap();
+        assert_eq!(
+            (orders.statement, orders.line, orders.column),
+            (2, Some(3), Some(6))
+        );
+        let customers = model.first_reference("raw.customers").unwrap();
+        assert_eq!((customers.line, customers.column), (Some(4), Some(8)));
+
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model);
+        collection.build_dependency_graph();
+        let report = collection.get_missing_sources_report();
+        assert!(report[0].contains("'raw.customers' (orders/orders.sql:4:8)"));
+    }
+
+    #[test]
+    fn test_implicit_dependencies_are_recorded() {
+        let temp_dir = tempdir().unwrap();
+        let project_root = temp_dir.path();
+        let dialect = DuckDbDialect {};
+
+        let mut collection = SqlModelCollection::new();
+        for (name, sql) in [
+            ("stg_orders", "SELECT id FROM raw.orders"),
+            ("fct_orders", "SELECT id FROM staging.stg_orders"),
+        ] {
+            let model_dir = project_root.join(name);
+            fs::create_dir(&model_dir).unwrap();
+            let sql_file = model_dir.join(format!("{}.sql", name));
+            fs::write(&sql_file, sql).unwrap();
+            fs::write(
+                model_dir.join(format!("{}.yml", name)),
+                format!(
+                    "version: 2\nmodels:\n  - name: {}\n    schema_name: staging\n",
+                    name
+                ),
+            )
+            .unwrap();
+
+            let mut model =
+                SqlModel::from_path(&sql_file, project_root, "duckdb", &dialect, &Sha256Hash)
+                    .unwrap();
+            model.extract_dependencies().unwrap();
+            collection.add_model(model);
+        }
+
+        collection.build_dependency_graph();
+
+        assert_eq!(
+            collection.get_implicit_dependencies(),
+            &[ImplicitDependency {
+                model_id: "model.fct_orders.fct_orders".to_string(),
+                relation: "staging.stg_orders".to_string(),
+                parent_id: "model.stg_orders.stg_orders".to_string(),
+            }]
+        );
+        assert_eq!(
+            collection.get_implicit_dependencies_report(),
+            vec!["Model 'fct_orders' depends on 'stg_orders' through an implicit match on 'staging.stg_orders'"]
+        );
+    }
+
+    #[test]
+    fn test_inline_test_annotations_merge_with_yaml() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("stg_customers");
+        fs::create_dir(&model_dir).unwrap();
+
+        let sql_file = model_dir.join("stg_customers.sql");
+        fs::write(
+            &sql_file,
+            "-- ff:test unique(customer_id), not_null(customer_id)\n\
+             -- ff:test not_null(email)\n\
+             SELECT customer_id, email FROM raw.customers",
+        )
+        .unwrap();
+        fs::write(
+            model_dir.join("stg_customers.yml"),
+            r#"
+version: 2
+models:
+  - name: stg_customers
+    columns:
+      - name: customer_id
+        tests: [not_null]
+"#,
+        )
+        .unwrap();
+
+        let dialect = DuckDbDialect {};
+        let model =
+            SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                .unwrap();
+
+        assert_eq!(
+            model.columns["customer_id"].tests,
+            vec!["not_null", "unique"]
+        );
+        assert_eq!(model.columns["email"].tests, vec!["not_null"]);
+        assert_eq!(
+            model.test_annotation_issues,
+            vec![
+                "line 1: test 'not_null' on column 'customer_id' is also defined in YAML",
+                "line 2: column 'email' is not declared in YAML",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_yaml_tests_with_arguments_become_test_nodes() {
+        let temp_dir = tempdir().unwrap();
+        let dialect = DuckDbDialect {};
+        let mut collection = SqlModelCollection::new();
+        for (name, sql, yaml) in [
+            ("customers", "SELECT 1 AS id", None),
+            (
+                "orders",
+                "SELECT 1 AS customer_id, 'placed' AS status",
+                Some(
+                    r#"
+version: 2
+models:
+  - name: orders
+    columns:
+      - name: status
+        tests:
+          - not_null
+          - accepted_values:
+              values: [placed, shipped]
+      - name: customer_id
+        tests:
+          - relationships:
+              to: ref('customers')
+              field: id
+"#,
+                ),
+            ),
+        ] {
+            let model_dir = temp_dir.path().join(name);
+            fs::create_dir(&model_dir).unwrap();
+            let sql_file = model_dir.join(format!("{}.sql", name));
+            fs::write(&sql_file, sql).unwrap();
+            if let Some(yaml) = yaml {
+                fs::write(model_dir.join(format!("{}.yml", name)), yaml).unwrap();
+            }
+            let model =
+                SqlModel::from_path(&sql_file, temp_dir.path(), "duckdb", &dialect, &Sha256Hash)
+                    .unwrap();
+            collection.add_model(model);
+        }
+        collection.build_dependency_graph();
+
+        let orders = collection.get_model("model.orders.orders").unwrap();
+        assert_eq!(
+            orders.columns["status"].tests,
+            vec!["not_null", "accepted_values"]
+        );
+        assert_eq!(
+            orders.columns["status"].test_args["accepted_values"]["values"],
+            serde_json::json!(["placed", "shipped"])
+        );
+
+        let Some(Node::Test(relationship)) =
+            collection.get_node("test.orders.orders.relationships_customer_id")
+        else {
+            panic!("relationships test node missing");
+        };
+        assert_eq!(
+            relationship.to_id.as_deref(),
+            Some("model.customers.customers")
+        );
+        assert_eq!(
+            collection.node_dependencies(&relationship.unique_id),
+            BTreeSet::from([
+                "model.customers.customers".to_string(),
+                "model.orders.orders".to_string(),
+            ])
+        );
+    }
 }
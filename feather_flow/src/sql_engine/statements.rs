@@ -0,0 +1,185 @@
+//! Statement-by-statement parsing of multi-statement SQL files
+//!
+//! A file is split on top-level semicolons (outside strings, quoted identifiers and
+//! comments) and every statement is parsed on its own, so a syntax error in one statement
+//! is reported against that statement's lines while the others still parse.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use sqlparser::ast::Statement;
+use sqlparser::dialect::Dialect;
+use sqlparser::parser::Parser;
+
+use super::dialect::{code_mask, normalize_for_parser};
+
+/// One statement's text and where it sits in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementSpan {
+    /// Byte offset of the statement's first byte
+    pub start: usize,
+    /// Byte offset just past the statement, excluding the terminating semicolon
+    pub end: usize,
+    /// 1-based line of the first non-whitespace character
+    pub start_line: usize,
+    /// 1-based line of the last non-whitespace character
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// A statement that failed to parse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatementError {
+    /// 1-based position of the statement in the file
+    pub statement: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for StatementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start_line == self.end_line {
+            write!(
+                f,
+                "statement {} (line {}): {}",
+                self.statement, self.start_line, self.message
+            )
+        } else {
+            write!(
+                f,
+                "statement {} (lines {}-{}): {}",
+                self.statement, self.start_line, self.end_line, self.message
+            )
+        }
+    }
+}
+
+/// Split `sql` on top-level semicolons. Pieces holding only whitespace and comments are
+/// dropped.
+pub fn split_statements(sql: &str) -> Vec<StatementSpan> {
+    let mask = code_mask(sql);
+    let bytes = sql.as_bytes();
+    let mut spans = Vec::new();
+    let mut start = 0;
+
+    for i in 0..=bytes.len() {
+        if i < bytes.len() && !(mask[i] && bytes[i] == b';') {
+            continue;
+        }
+
+        let has_code = (start..i).any(|j| mask[j] && !bytes[j].is_ascii_whitespace());
+        if has_code {
+            let text = &sql[start..i];
+            let leading = text.len() - text.trim_start().len();
+            let trailing = text.len() - text.trim_end().len();
+            spans.push(StatementSpan {
+                start,
+                end: i,
+                start_line: line_at(sql, start + leading),
+                end_line: line_at(sql, i - trailing),
+                text: text.to_string(),
+            });
+        }
+        start = i + 1;
+    }
+
+    spans
+}
+
+fn line_at(sql: &str, offset: usize) -> usize {
+    sql[..offset].matches('\n').count() + 1
+}
+
+/// Parse each statement of `sql` independently, returning the statements that parsed and an
+/// error for each one that did not
+pub fn parse_statements(dialect: &dyn Dialect, sql: &str) -> (Vec<Statement>, Vec<StatementError>) {
+    let (parsed, errors) = parse_statement_spans(dialect, sql);
+    let statements = parsed
+        .into_iter()
+        .flat_map(|statement| statement.statements)
+        .collect();
+
+    (statements, errors)
+}
+
+/// A piece of the file that parsed, with the statements it produced
+#[derive(Debug, Clone)]
+pub struct ParsedSpan {
+    /// 1-based position of the statement in the file
+    pub index: usize,
+    pub span: StatementSpan,
+    pub statements: Vec<Statement>,
+}
+
+/// Like [`parse_statements`], but keeps each parsed statement with its place in the file
+pub fn parse_statement_spans(
+    dialect: &dyn Dialect,
+    sql: &str,
+) -> (Vec<ParsedSpan>, Vec<StatementError>) {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, span) in split_statements(sql).into_iter().enumerate() {
+        match Parser::parse_sql(dialect, &normalize_for_parser(&span.text)) {
+            Ok(statements) => parsed.push(ParsedSpan {
+                index: index + 1,
+                span,
+                statements,
+            }),
+            Err(err) => errors.push(StatementError {
+                statement: index + 1,
+                start_line: span.start_line,
+                end_line: span.end_line,
+                message: err.to_string(),
+            }),
+        }
+    }
+
+    (parsed, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_respects_strings_and_comments() {
+        let sql = "SELECT ';' AS a;\n-- comment; not a split\nSELECT \"x;y\" FROM t /* ; */;\n\n-- trailing comment\n";
+        let spans = split_statements(sql);
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].text, "SELECT ';' AS a");
+        assert_eq!(spans[0].start_line, 1);
+        assert_eq!(
+            spans[1].text.trim(),
+            "-- comment; not a split\nSELECT \"x;y\" FROM t /* ; */"
+        );
+        assert_eq!(spans[1].start_line, 2);
+        assert_eq!(spans[1].end_line, 3);
+        assert_eq!(&sql[spans[1].start..spans[1].end], spans[1].text);
+    }
+
+    #[test]
+    fn test_statement_without_trailing_semicolon() {
+        let spans = split_statements("SELECT 1;\n\nSELECT 2\n");
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].text.trim(), "SELECT 2");
+        assert_eq!(spans[1].start_line, 3);
+    }
+
+    #[test]
+    fn test_error_display() {
+        let error = StatementError {
+            statement: 3,
+            start_line: 7,
+            end_line: 9,
+            message: "Expected: end of statement".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "statement 3 (lines 7-9): Expected: end of statement"
+        );
+    }
+}
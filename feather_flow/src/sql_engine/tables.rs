@@ -1,78 +1,129 @@
-use std::collections::HashMap;
+//! Column schemas of warehouse relations, synced by `ff sync-schemas`
+//!
+//! `ff sync-schemas --db <file>` reads `information_schema.columns` for the relation of every
+//! source table, model and snapshot and records it in `target/state/table_schemas.json`.
+//! Commands that load the project read the file back: `*` expands over relations whose
+//! columns no YAML declares (see `sql_engine::projection`), and `ff validate --check-columns`
+//! compares the data types declared in model YAML with the warehouse's. Entries are as fresh
+//! as the last sync.
 
-// TODO: This module contains infrastructure for a future table schema management feature.
-// Currently, this code is not used in the main application, but is kept for planned
-// future implementation. Consider removing if this functionality is no longer needed.
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-/// Represents a SQL table schema
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
+/// File inside the state directory holding the synced schemas
+pub const TABLE_SCHEMAS_FILE: &str = "table_schemas.json";
+
+/// Columns of one relation, in the warehouse's order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TableSchema {
+    /// `[database.]schema.name`, as models reference the relation
     pub name: String,
     pub columns: Vec<ColumnDef>,
-    pub primary_key: Option<Vec<String>>,
 }
 
-/// Represents a column definition
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
+/// A column as the warehouse reports it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ColumnDef {
     pub name: String,
-    pub data_type: SqlType,
+    /// Type as the warehouse spells it, e.g. `DECIMAL(18,2)`
+    pub data_type: String,
     pub nullable: bool,
 }
 
-/// Represents SQL data types
-#[derive(Debug, Clone, PartialEq)]
-#[allow(dead_code)]
-pub enum SqlType {
-    Integer,
-    Float,
-    Text,
-    Boolean,
-    Date,
-    Timestamp,
-    // Other types can be added as needed
-}
-
-/// Table manager for handling table operations
-#[derive(Default)]
-#[allow(dead_code)]
+/// Synced schemas by relation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TableManager {
-    schemas: HashMap<String, TableSchema>,
+    /// When the last `ff sync-schemas` ran
+    pub synced_at: Option<DateTime<Utc>>,
+    /// By lowercase relation
+    schemas: BTreeMap<String, TableSchema>,
 }
 
 impl TableManager {
     /// Create a new empty table manager
-    #[allow(dead_code)]
     pub fn new() -> Self {
-        Self {
-            schemas: HashMap::new(),
+        Self::default()
+    }
+
+    pub fn path(state_dir: &Path) -> PathBuf {
+        state_dir.join(TABLE_SCHEMAS_FILE)
+    }
+
+    /// The synced schemas; none before the first sync
+    pub fn load(state_dir: &Path) -> Result<Self> {
+        let path = Self::path(state_dir);
+        if !path.is_file() {
+            return Ok(Self::default());
         }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+        let path = Self::path(state_dir);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to write {}", path.display()))
     }
 
     /// Add or update a table schema
-    #[allow(dead_code)]
     pub fn register_schema(&mut self, schema: TableSchema) {
-        self.schemas.insert(schema.name.clone(), schema);
+        self.schemas.insert(schema.name.to_lowercase(), schema);
     }
 
-    /// Get all available table names
-    #[allow(dead_code)]
-    pub fn get_table_names(&self) -> Vec<String> {
-        self.schemas.keys().cloned().collect()
+    /// Forget the schema of a relation that no longer exists
+    pub fn remove_schema(&mut self, table_name: &str) -> Option<TableSchema> {
+        self.schemas.remove(&table_name.to_lowercase())
     }
 
-    /// Get a specific table schema by name
-    #[allow(dead_code)]
+    /// Get a specific table schema by relation, case-insensitively
     pub fn get_schema(&self, table_name: &str) -> Option<&TableSchema> {
-        self.schemas.get(table_name)
+        self.schemas.get(&table_name.to_lowercase())
     }
 
     /// Get column names for a specific table
-    #[allow(dead_code)]
     pub fn get_column_names(&self, table_name: &str) -> Option<Vec<String>> {
         self.get_schema(table_name)
             .map(|schema| schema.columns.iter().map(|col| col.name.clone()).collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_schemas_round_trip_case_insensitively() {
+        let dir = tempdir().unwrap();
+        let mut tables = TableManager::load(dir.path()).unwrap();
+        assert_eq!(tables, TableManager::default());
+        tables.register_schema(TableSchema {
+            name: "raw.Orders".to_string(),
+            columns: vec![ColumnDef {
+                name: "id".to_string(),
+                data_type: "INTEGER".to_string(),
+                nullable: false,
+            }],
+        });
+        tables.save(dir.path()).unwrap();
+
+        let mut loaded = TableManager::load(dir.path()).unwrap();
+        assert_eq!(loaded, tables);
+        assert_eq!(
+            loaded.get_column_names("RAW.orders"),
+            Some(vec!["id".to_string()])
+        );
+        assert!(loaded.remove_schema("raw.orders").is_some());
+        assert!(loaded.get_schema("raw.orders").is_none());
+    }
+}
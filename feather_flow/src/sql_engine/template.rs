@@ -0,0 +1,1036 @@
+//! Compile-time template context for model SQL
+//!
+//! Models can reference a small set of context objects before they are parsed:
+//!
+//! - `{{ this }}`: the model's own resolved relation (`[database.]schema.relation`)
+//! - `{{ target.name }}`, `{{ target.schema }}`, `{{ target.type }}`: the active target
+//! - `{{ var('name') }}`: a project variable declared under `vars:` (see
+//!   [`crate::config::vars`])
+//! - `{{ ref('name') }}`: the resolved relation of the project's model or seed `name`
+//! - `{{ source('source', 'table') }}`: a table declared under `sources:` in `imports/`
+//! - `{{ env_var('NAME') }}`, `{{ env_var('NAME', 'default') }}`: an environment variable,
+//!   an error when it is unset and there is no default
+//! - any other call is a [`crate::feather_lang`] expression over the same names, so the
+//!   [`crate::feather_lang::stdlib`] functions apply, e.g.
+//!   `{{ format('events_{}', upper(var('region'))) }}`
+//! - `{{ literal(value) }}`, `{{ identifier(value) }}`: a value as a SQL literal or quoted
+//!   identifier, escaped for the target's dialect (see [`crate::sql_engine::quoting`])
+//!
+//! `var` and `env_var` write their values into the SQL as is, so a value that can hold any
+//! text belongs in `literal()` or `identifier()`; [`raw_interpolations`] finds those that
+//! are not.
+//!
+//! This makes incremental predicates possible, e.g.
+//! `WHERE event_at > (SELECT max(event_at) FROM {{ this }})`. `ref` and `source` name what a
+//! model reads independently of where it lives, and the dependencies they create are
+//! explicit rather than matched by `schema.name`.
+//!
+//! Parts of a model can be kept or dropped with
+//! `{% if <condition> %} ... {% elif <condition> %} ... {% else %} ... {% endif %}`. Conditions
+//! are [`crate::feather_lang`] expressions over `this`, `target` (`target.name`,
+//! `target.schema`, `target.type`), `var('name')` and `env_var('NAME'[, 'default'])`, e.g.
+//! `{% if target.name == "prod" && var('lookback_days') > 7 %}`. Variables that are integers
+//! compare as integers, the rest as strings.
+//!
+//! Templates are rendered before the SQL is parsed; render errors name the line they are on.
+//!
+//! Model descriptions are templates too, over the model's `meta` and `name` (see
+//! [`render_description`]), so similar models can share one description pattern.
+
+use anyhow::{anyhow, bail, Result};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::ops::Range;
+
+use super::quoting;
+use crate::config::SqlDialect;
+use crate::feather_lang::{
+    self,
+    object::{Env, Environment, HashKey, Object},
+};
+
+/// Target used when no `--target` is given
+pub const DEFAULT_TARGET: &str = "default";
+
+/// Schema used when neither the model nor the target provides one
+pub const DEFAULT_SCHEMA: &str = "public";
+
+/// Template functions that quote their argument for SQL
+const QUOTING_FUNCTIONS: [&str; 2] = ["literal", "identifier"];
+
+/// The `target` object exposed to templates, along with the project variables resolved for
+/// this invocation
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetContext {
+    pub name: String,
+    pub schema: String,
+    pub adapter_type: String,
+    /// Validated variable values, already rendered for SQL
+    pub vars: BTreeMap<String, String>,
+    /// Relation of each model and seed by name, for `ref`
+    pub refs: BTreeMap<String, String>,
+    /// Relation of each declared source table by source and table name, for `source`
+    pub sources: BTreeMap<(String, String), String>,
+}
+
+impl TargetContext {
+    pub fn new(name: &str, adapter_type: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
+            adapter_type: adapter_type.to_string(),
+            vars: BTreeMap::new(),
+            refs: BTreeMap::new(),
+            sources: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_vars(mut self, vars: BTreeMap<String, String>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    pub fn with_relations(
+        mut self,
+        refs: BTreeMap<String, String>,
+        sources: BTreeMap<(String, String), String>,
+    ) -> Self {
+        self.refs = refs;
+        self.sources = sources;
+        self
+    }
+}
+
+/// Everything a single model's template can reference
+#[derive(Debug, Clone)]
+pub struct TemplateContext<'a> {
+    pub this: String,
+    pub target: &'a TargetContext,
+}
+
+impl TemplateContext<'_> {
+    fn lookup(&self, expression: &str, env: &Env) -> Result<Cow<'_, str>> {
+        if let Some(relation) = self.target.relation(expression) {
+            return relation.map(Cow::Borrowed);
+        }
+        if let Some(arguments) = call_arguments(expression, "env_var") {
+            return env_var(&arguments).map(Cow::Owned);
+        }
+        let value = match expression {
+            "this" => Some(self.this.as_str()),
+            "target.name" => Some(self.target.name.as_str()),
+            "target.schema" => Some(self.target.schema.as_str()),
+            "target.type" => Some(self.target.adapter_type.as_str()),
+            _ => var_name(expression)
+                .and_then(|name| self.target.vars.get(name))
+                .map(String::as_str),
+        };
+        if let Some(value) = value {
+            return Ok(Cow::Borrowed(value));
+        }
+
+        // Any other call is a feather_lang expression, e.g. `upper(var('region'))`
+        if expression.contains('(') && var_name(expression).is_none() {
+            return feather_lang::eval(expression, env)
+                .map(|value| Cow::Owned(value.to_string()))
+                .map_err(|err| anyhow!("Failed to evaluate {{{{ {} }}}}: {}", expression, err));
+        }
+        Err(anyhow!(
+            "Unknown template variable '{}' (available: this, target.name, target.schema, target.type, var('<declared var>'), env_var('<name>'[, '<default>']), ref('<model>'), source('<source>', '<table>'), and functions such as upper(...); see ff help templating)",
+            expression
+        ))
+    }
+}
+
+/// The value of `env_var('NAME')`, or of `env_var('NAME', 'default')` when `NAME` is unset
+fn env_var<S: AsRef<str>>(arguments: &[S]) -> Result<String> {
+    match arguments {
+        [name] => env::var(name.as_ref()).map_err(|_| {
+            anyhow!(
+                "Environment variable '{}' is not set and env_var() has no default",
+                name.as_ref()
+            )
+        }),
+        [name, default] => {
+            Ok(env::var(name.as_ref()).unwrap_or_else(|_| default.as_ref().to_string()))
+        }
+        _ => bail!("env_var() takes a variable name and an optional default"),
+    }
+}
+
+impl TargetContext {
+    /// The relation a `ref(...)` or `source(...)` expression resolves to, `None` for other
+    /// expressions
+    fn relation(&self, expression: &str) -> Option<Result<&str>> {
+        if let Some(arguments) = call_arguments(expression, "ref") {
+            let resolved = match arguments.as_slice() {
+                [name] => self
+                    .refs
+                    .get(*name)
+                    .map(String::as_str)
+                    .ok_or_else(|| anyhow!("ref('{}') names no model or seed", name)),
+                _ => Err(anyhow!("ref() takes one model name: '{}'", expression)),
+            };
+            return Some(resolved);
+        }
+        if let Some(arguments) = call_arguments(expression, "source") {
+            let resolved = match arguments.as_slice() {
+                [source, table] => self
+                    .sources
+                    .get(&(source.to_string(), table.to_string()))
+                    .map(String::as_str)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "source('{}', '{}') is not declared under sources: in imports/",
+                            source,
+                            table
+                        )
+                    }),
+                _ => Err(anyhow!(
+                    "source() takes a source name and a table name: '{}'",
+                    expression
+                )),
+            };
+            return Some(resolved);
+        }
+        None
+    }
+}
+
+/// The quoted arguments of `function('a', "b")`, or `None` when `expression` is not a call
+/// of `function` with only quoted arguments
+fn call_arguments<'a>(expression: &'a str, function: &str) -> Option<Vec<&'a str>> {
+    let arguments = expression
+        .strip_prefix(function)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+    arguments
+        .split(',')
+        .map(|argument| {
+            let argument = argument.trim();
+            ['\'', '"'].into_iter().find_map(|quote| {
+                argument
+                    .strip_prefix(quote)?
+                    .strip_suffix(quote)
+                    .map(str::trim)
+            })
+        })
+        .collect()
+}
+
+/// `var('name')` or `var("name")` to `name`
+fn var_name(expression: &str) -> Option<&str> {
+    match call_arguments(expression, "var")?.as_slice() {
+        [name] => Some(name),
+        _ => None,
+    }
+}
+
+/// The trimmed expressions of every closed `{{ ... }}` in the SQL
+fn expressions(sql: &str) -> Vec<&str> {
+    delimited(sql, "{{", "}}")
+}
+
+/// The trimmed contents of every closed `open ... close` in the SQL
+fn delimited<'a>(sql: &'a str, open: &str, close: &str) -> Vec<&'a str> {
+    delimited_at(sql, open, close)
+        .into_iter()
+        .map(|(_, content)| content)
+        .collect()
+}
+
+/// The offset of every closed `open ... close` in the SQL with its trimmed contents
+fn delimited_at<'a>(sql: &'a str, open: &str, close: &str) -> Vec<(usize, &'a str)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = sql[offset..].find(open) {
+        let opened = offset + start;
+        let content = opened + open.len();
+        let Some(end) = sql[content..].find(close) else {
+            break;
+        };
+        found.push((opened, sql[content..content + end].trim()));
+        offset = content + end + close.len();
+    }
+    found
+}
+
+/// Offsets in `text` of each call of `function`: the name starting a word, then `(`
+fn call_offsets<'a>(text: &'a str, function: &'a str) -> impl Iterator<Item = usize> + 'a {
+    text.match_indices(function).filter_map(move |(at, _)| {
+        let starts_word = !text[..at].ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_');
+        (starts_word && text[at + function.len()..].trim_start().starts_with('(')).then_some(at)
+    })
+}
+
+/// Each call of `function` in `expression` through its closing parenthesis, with its span
+fn call_spans<'a>(expression: &'a str, function: &'a str) -> Vec<(Range<usize>, &'a str)> {
+    call_offsets(expression, function)
+        .filter_map(|at| {
+            let open = at + expression[at..].find('(')?;
+            let mut depth = 0;
+            let mut quote = None;
+            for (offset, c) in expression[open..].char_indices() {
+                match (quote, c) {
+                    (Some(q), c) if c == q => quote = None,
+                    (Some(_), _) => {}
+                    (None, '\'' | '"') => quote = Some(c),
+                    (None, '(') => depth += 1,
+                    (None, ')') => {
+                        depth -= 1;
+                        if depth == 0 {
+                            let end = open + offset + 1;
+                            return Some((at..end, &expression[at..end]));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            None
+        })
+        .collect()
+}
+
+/// Whether the SQL contains any `{{ ... }}` expressions or `{% ... %}` tags
+pub fn has_template_expressions(sql: &str) -> bool {
+    sql.contains("{{") || sql.contains("{%")
+}
+
+/// Whether the SQL uses `var(...)`, `{{ ref(...) }}` or `{{ source(...) }}`; such models can
+/// only be compiled once the project variables and the relations of every model, seed and
+/// source are known
+pub fn uses_project_context(sql: &str) -> bool {
+    let calls = |text: &str, function: &str| call_offsets(text, function).next().is_some();
+    let in_expressions = expressions(sql).into_iter().any(|expression| {
+        ["var", "ref", "source"]
+            .into_iter()
+            .any(|function| calls(expression, function))
+    });
+    in_expressions
+        || delimited(sql, "{%", "%}")
+            .into_iter()
+            .any(|tag| calls(tag, "var"))
+}
+
+/// A `{{ ... }}` that writes an untrusted value into the SQL as is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawInterpolation {
+    /// Line of the `{{`, counting from 1
+    pub line: usize,
+    pub expression: String,
+    /// The untrusted call, e.g. `env_var('SUFFIX')`
+    pub value: String,
+}
+
+/// `{{ ... }}` expressions that write an environment variable, or a project variable
+/// `untrusted_var` holds for, into the SQL outside `literal()` and `identifier()`
+pub fn raw_interpolations(
+    sql: &str,
+    untrusted_var: impl Fn(&str) -> bool,
+) -> Vec<RawInterpolation> {
+    let mut found = Vec::new();
+    for (offset, expression) in delimited_at(sql, "{{", "}}") {
+        let quoted: Vec<Range<usize>> = QUOTING_FUNCTIONS
+            .into_iter()
+            .flat_map(|function| call_spans(expression, function))
+            .map(|(span, _)| span)
+            .collect();
+        let vars = call_spans(expression, "var")
+            .into_iter()
+            .filter(|(_, call)| var_name(call).is_some_and(&untrusted_var));
+        for (span, call) in call_spans(expression, "env_var").into_iter().chain(vars) {
+            if !quoted.iter().any(|range| range.contains(&span.start)) {
+                found.push(RawInterpolation {
+                    line: sql[..offset].matches('\n').count() + 1,
+                    expression: expression.to_string(),
+                    value: call.to_string(),
+                });
+            }
+        }
+    }
+    found
+}
+
+/// Relations the SQL names through `ref` and `source` that resolve in `target`
+pub fn explicit_relations(sql: &str, target: &TargetContext) -> Vec<String> {
+    expressions(sql)
+        .into_iter()
+        .filter_map(|expression| target.relation(expression)?.ok())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Keep the branches of `{% if %}` blocks whose conditions hold and replace every
+/// `{{ expression }}` in them with its value from the context. Errors start with the line of
+/// `sql` they are about.
+pub fn render(sql: &str, context: &TemplateContext) -> Result<String> {
+    let mut source = Source { sql, offset: 0 };
+    let (nodes, closing) = source.parse_nodes()?;
+    if let Some((tag, line)) = closing {
+        return Err(at_line(
+            line,
+            anyhow!("{{% {} %}} without a matching {{% if %}}", tag.keyword()),
+        ));
+    }
+
+    let mut rendered = String::with_capacity(sql.len());
+    render_nodes(&nodes, context, &condition_env(context), &mut rendered)?;
+    Ok(rendered)
+}
+
+/// Replace every `{{ expression }}` in a model's description with its value. Expressions are
+/// [`crate::feather_lang`] over `meta`, the model's meta values, and `name`, the model's name,
+/// e.g. `Daily trends for {{ meta.domain }} team` or `{{ upper(meta.team) }}`. An expression
+/// without a value, such as a meta key the model does not set, is an error.
+pub fn render_description(
+    description: &str,
+    name: &str,
+    meta: &HashMap<String, serde_json::Value>,
+) -> Result<String> {
+    if !description.contains("{{") {
+        return Ok(description.to_string());
+    }
+    let env = Environment::new();
+    let meta = meta
+        .iter()
+        .map(|(key, value)| (HashKey::String(key.clone()), json_object(value)))
+        .collect();
+    env.borrow_mut().set("meta", Object::Hash(meta));
+    env.borrow_mut()
+        .set("name", Object::String(name.to_string()));
+
+    let mut rendered = String::with_capacity(description.len());
+    let mut rest = description;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            bail!("{{{{ without a matching }}}}");
+        };
+        rendered.push_str(&rest[..start]);
+        let expression = rest[start + 2..start + end].trim();
+        match feather_lang::eval(expression, &env) {
+            Ok(Object::Null) => bail!("{{{{ {} }}}} has no value", expression),
+            Ok(value) => rendered.push_str(&value.to_string()),
+            Err(err) => bail!("Failed to evaluate {{{{ {} }}}}: {}", expression, err),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Replace every `{{ env_var('NAME'[, 'default']) }}` in `text` with its value, for settings
+/// such as connection details in `profiles.yml`; any other expression is an error
+pub fn render_env_vars(text: &str) -> Result<String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            bail!("{{{{ without a matching }}}}");
+        };
+        rendered.push_str(&rest[..start]);
+        let expression = rest[start + 2..start + end].trim();
+        match call_arguments(expression, "env_var") {
+            Some(arguments) => rendered.push_str(&env_var(&arguments)?),
+            None => bail!(
+                "{{{{ {} }}}} is not allowed here; only env_var('<name>'[, '<default>']) is",
+                expression
+            ),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// A meta value as a feather_lang value; numbers that are not integers become strings
+fn json_object(value: &serde_json::Value) -> Object {
+    match value {
+        serde_json::Value::Null => Object::Null,
+        serde_json::Value::Bool(value) => Object::Boolean(*value),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(number) => Object::Integer(number),
+            None => Object::String(number.to_string()),
+        },
+        serde_json::Value::String(value) => Object::String(value.clone()),
+        serde_json::Value::Array(values) => Object::Array(values.iter().map(json_object).collect()),
+        serde_json::Value::Object(pairs) => Object::Hash(
+            pairs
+                .iter()
+                .map(|(key, value)| (HashKey::String(key.clone()), json_object(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn at_line(line: usize, err: anyhow::Error) -> anyhow::Error {
+    anyhow!("line {}: {:#}", line, err)
+}
+
+/// A piece of template source
+enum Node<'a> {
+    Text(&'a str),
+    Expression {
+        expression: &'a str,
+        line: usize,
+    },
+    /// `{% if %}` with its `{% elif %}` branches, each a condition, the line of its tag and
+    /// the nodes it keeps, and the nodes `{% else %}` keeps
+    If {
+        branches: Vec<(&'a str, usize, Vec<Node<'a>>)>,
+        otherwise: Vec<Node<'a>>,
+    },
+}
+
+/// A `{% ... %}` tag
+enum Tag<'a> {
+    If(&'a str),
+    Elif(&'a str),
+    Else,
+    Endif,
+}
+
+impl<'a> Tag<'a> {
+    fn parse(content: &'a str) -> Result<Self> {
+        let (keyword, argument) = content
+            .split_once(char::is_whitespace)
+            .map(|(keyword, argument)| (keyword, argument.trim()))
+            .unwrap_or((content, ""));
+        match (keyword, argument) {
+            ("if" | "elif", "") => bail!("{{% {} %}} needs a condition", keyword),
+            ("if", condition) => Ok(Tag::If(condition)),
+            ("elif", condition) => Ok(Tag::Elif(condition)),
+            ("else", "") => Ok(Tag::Else),
+            ("endif", "") => Ok(Tag::Endif),
+            _ => bail!(
+                "Unknown template tag '{{% {} %}}' (available: if, elif, else, endif)",
+                content
+            ),
+        }
+    }
+
+    fn keyword(&self) -> &'static str {
+        match self {
+            Tag::If(_) => "if",
+            Tag::Elif(_) => "elif",
+            Tag::Else => "else",
+            Tag::Endif => "endif",
+        }
+    }
+}
+
+/// A tag and the line it is on
+type LineTag<'a> = (Tag<'a>, usize);
+
+/// Template source split into nodes from `offset` on
+struct Source<'a> {
+    sql: &'a str,
+    offset: usize,
+}
+
+impl<'a> Source<'a> {
+    /// 1-based line of the byte at `offset`
+    fn line_at(&self, offset: usize) -> usize {
+        self.sql[..offset].matches('\n').count() + 1
+    }
+
+    /// Nodes up to the first tag that ends a block (`elif`, `else` or `endif`), which is
+    /// returned with its line; parsing resumes just after it
+    fn parse_nodes(&mut self) -> Result<(Vec<Node<'a>>, Option<LineTag<'a>>)> {
+        let mut nodes = Vec::new();
+        loop {
+            let rest = &self.sql[self.offset..];
+            let Some(start) = [rest.find("{{"), rest.find("{%")]
+                .into_iter()
+                .flatten()
+                .min()
+            else {
+                if !rest.is_empty() {
+                    nodes.push(Node::Text(rest));
+                }
+                self.offset = self.sql.len();
+                return Ok((nodes, None));
+            };
+            if start > 0 {
+                nodes.push(Node::Text(&rest[..start]));
+            }
+
+            let line = self.line_at(self.offset + start);
+            let is_tag = rest[start..].starts_with("{%");
+            let after_open = &rest[start + 2..];
+            let end = after_open
+                .find(if is_tag { "%}" } else { "}}" })
+                .ok_or_else(|| {
+                    let unclosed = rest[start..].lines().next().unwrap_or_default();
+                    at_line(
+                        line,
+                        anyhow!(
+                            "Unclosed template {}: '{}'",
+                            if is_tag { "tag" } else { "expression" },
+                            unclosed
+                        ),
+                    )
+                })?;
+            let content = after_open[..end].trim();
+            self.offset += start + 2 + end + 2;
+
+            if !is_tag {
+                nodes.push(Node::Expression {
+                    expression: content,
+                    line,
+                });
+                continue;
+            }
+            match Tag::parse(content).map_err(|err| at_line(line, err))? {
+                Tag::If(condition) => nodes.push(self.parse_if(condition, line)?),
+                closing => return Ok((nodes, Some((closing, line)))),
+            }
+        }
+    }
+
+    /// The rest of an `{% if condition %}` block at `line`, through its `{% endif %}`
+    fn parse_if(&mut self, condition: &'a str, line: usize) -> Result<Node<'a>> {
+        let missing_endif = || {
+            at_line(
+                line,
+                anyhow!("Missing {{% endif %}} for {{% if {} %}}", condition),
+            )
+        };
+        let mut branches = Vec::new();
+        let mut current = (condition, line);
+        loop {
+            let (nodes, closing) = self.parse_nodes()?;
+            branches.push((current.0, current.1, nodes));
+            match closing {
+                Some((Tag::Elif(next), next_line)) => current = (next, next_line),
+                Some((Tag::Else, _)) => {
+                    let (otherwise, closing) = self.parse_nodes()?;
+                    return match closing {
+                        Some((Tag::Endif, _)) => Ok(Node::If {
+                            branches,
+                            otherwise,
+                        }),
+                        Some((tag, tag_line)) => Err(at_line(
+                            tag_line,
+                            anyhow!(
+                                "{{% {} %}} after {{% else %}} in {{% if {} %}}",
+                                tag.keyword(),
+                                condition
+                            ),
+                        )),
+                        None => Err(missing_endif()),
+                    };
+                }
+                Some((Tag::Endif, _)) => {
+                    return Ok(Node::If {
+                        branches,
+                        otherwise: Vec::new(),
+                    })
+                }
+                Some((Tag::If(_), _)) => unreachable!("parse_nodes handles nested ifs"),
+                None => return Err(missing_endif()),
+            }
+        }
+    }
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    context: &TemplateContext,
+    env: &Env,
+    rendered: &mut String,
+) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => rendered.push_str(text),
+            Node::Expression { expression, line } => rendered.push_str(
+                &context
+                    .lookup(expression, env)
+                    .map_err(|err| at_line(*line, err))?,
+            ),
+            Node::If {
+                branches,
+                otherwise,
+            } => {
+                let mut taken = otherwise;
+                for (condition, line, nodes) in branches {
+                    let value = feather_lang::eval(condition, env).map_err(|err| {
+                        at_line(
+                            *line,
+                            anyhow!("Failed to evaluate {{% if {} %}}: {}", condition, err),
+                        )
+                    })?;
+                    if value.is_truthy() {
+                        taken = nodes;
+                        break;
+                    }
+                }
+                render_nodes(taken, context, env, rendered)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The names `{% if %}` conditions can use
+fn condition_env(context: &TemplateContext) -> Env {
+    let env = Environment::new();
+    let target = &context.target;
+    let string = |value: &str| Object::String(value.to_string());
+    let target_hash = [
+        ("name", &target.name),
+        ("schema", &target.schema),
+        ("type", &target.adapter_type),
+    ]
+    .into_iter()
+    .map(|(key, value)| (HashKey::String(key.to_string()), string(value)))
+    .collect();
+
+    let vars = target.vars.clone();
+    let var = Object::builtin("var", move |arguments| match arguments {
+        [Object::String(name)] => {
+            let value = vars
+                .get(name)
+                .ok_or_else(|| anyhow!("var('{}') is not a declared variable", name))?;
+            Ok(match value.parse() {
+                Ok(number) => Object::Integer(number),
+                Err(_) => Object::String(value.clone()),
+            })
+        }
+        _ => bail!("var() takes one variable name"),
+    });
+
+    let env_var = Object::builtin("env_var", |arguments| {
+        let arguments = arguments
+            .iter()
+            .map(|argument| match argument {
+                Object::String(value) => Ok(value.as_str()),
+                other => bail!("env_var() takes strings, got {}", other.type_name()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        env_var(&arguments).map(Object::String)
+    });
+
+    let dialect = SqlDialect::from_name(&target.adapter_type).unwrap_or_default();
+    let literal = Object::builtin("literal", move |arguments| match arguments {
+        [Object::String(value)] => Ok(Object::String(quoting::string_literal(value, dialect))),
+        [Object::Integer(value)] => Ok(Object::String(value.to_string())),
+        [Object::Boolean(value)] => Ok(Object::String(value.to_string().to_uppercase())),
+        [Object::Null] => Ok(Object::String("NULL".to_string())),
+        [other] => bail!(
+            "literal() takes a string, integer, boolean or null, got {}",
+            other.type_name()
+        ),
+        _ => bail!("literal() takes one value"),
+    });
+    let identifier = Object::builtin("identifier", move |arguments| match arguments {
+        [Object::String(name)] => Ok(Object::String(quoting::identifier(name, dialect))),
+        [other] => bail!("identifier() takes a string, got {}", other.type_name()),
+        _ => bail!("identifier() takes one name"),
+    });
+
+    let mut scope = env.borrow_mut();
+    scope.set("this", string(&context.this));
+    scope.set("target", Object::Hash(target_hash));
+    scope.set("var", var);
+    scope.set("env_var", env_var);
+    scope.set("literal", literal);
+    scope.set("identifier", identifier);
+    drop(scope);
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_this_and_target() {
+        let target = TargetContext::new("prod", "duckdb");
+        let context = TemplateContext {
+            this: "analytics.events".to_string(),
+            target: &target,
+        };
+
+        let sql = "SELECT * FROM raw.events WHERE event_at > (SELECT max(event_at) FROM {{ this }}) -- {{target.name}} on {{ target.type }}";
+        assert_eq!(
+            render(sql, &context).unwrap(),
+            "SELECT * FROM raw.events WHERE event_at > (SELECT max(event_at) FROM analytics.events) -- prod on duckdb"
+        );
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let target = TargetContext::new(DEFAULT_TARGET, "duckdb");
+        let context = TemplateContext {
+            this: "public.events".to_string(),
+            target: &target,
+        };
+
+        let err = render("SELECT {{ var('x') }}", &context).unwrap_err();
+        assert!(err.to_string().contains("Unknown template variable"));
+
+        let err = render("SELECT * FROM {{ this", &context).unwrap_err();
+        assert!(err.to_string().contains("Unclosed"));
+
+        let err = render("SELECT 1\nFROM t\nWHERE {{ target.nme }}", &context).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("line 3: Unknown template variable"));
+    }
+
+    #[test]
+    fn test_render_env_var() {
+        let target = TargetContext::new(DEFAULT_TARGET, "duckdb");
+        let context = TemplateContext {
+            this: "public.events".to_string(),
+            target: &target,
+        };
+        env::set_var("FF_TEMPLATE_TEST_DATABASE", "warehouse");
+        env::remove_var("FF_TEMPLATE_TEST_UNSET");
+
+        let sql = "SELECT * FROM {{ env_var('FF_TEMPLATE_TEST_DATABASE') }}.events\
+            {% if env_var('FF_TEMPLATE_TEST_UNSET', 'full') == 'full' %} -- {{ env_var(\"FF_TEMPLATE_TEST_UNSET\", 'all rows') }}{% endif %}";
+        assert_eq!(
+            render(sql, &context).unwrap(),
+            "SELECT * FROM warehouse.events -- all rows"
+        );
+        assert!(!uses_project_context(sql));
+
+        let err = render("SELECT '{{ env_var('FF_TEMPLATE_TEST_UNSET') }}'", &context).unwrap_err();
+        assert!(err.to_string().contains("is not set"));
+
+        assert_eq!(
+            render_env_vars("/data/{{ env_var('FF_TEMPLATE_TEST_DATABASE') }}.duckdb").unwrap(),
+            "/data/warehouse.duckdb"
+        );
+        assert_eq!(
+            render_env_vars("{{env_var('FF_TEMPLATE_TEST_UNSET', 'dev.duckdb')}}").unwrap(),
+            "dev.duckdb"
+        );
+        assert!(render_env_vars("{{ var('x') }}")
+            .unwrap_err()
+            .to_string()
+            .contains("not allowed here"));
+    }
+
+    #[test]
+    fn test_render_functions() {
+        let target = TargetContext::new("prod", "duckdb").with_vars(BTreeMap::from([
+            ("start_date".to_string(), "2024-01-31".to_string()),
+            ("region".to_string(), "eu".to_string()),
+        ]));
+        let context = TemplateContext {
+            this: "analytics.events".to_string(),
+            target: &target,
+        };
+
+        let sql = "SELECT * FROM {{ format('events_{}', upper(var('region'))) }}\n\
+            WHERE day < '{{ date_add(var('start_date'), 1, 'month') }}'";
+        assert_eq!(
+            render(sql, &context).unwrap(),
+            "SELECT * FROM events_EU\nWHERE day < '2024-02-29'"
+        );
+        assert!(uses_project_context(sql));
+
+        let err = render("SELECT 1\n{{ upper(1) }}", &context).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: Failed to evaluate {{ upper(1) }}: argument to `upper` must be STRING, got INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_render_if_blocks() {
+        let target = TargetContext::new("prod", "duckdb").with_vars(BTreeMap::from([
+            ("lookback_days".to_string(), "7".to_string()),
+            ("region".to_string(), "eu".to_string()),
+        ]));
+        let context = TemplateContext {
+            this: "analytics.events".to_string(),
+            target: &target,
+        };
+
+        let sql = "SELECT * FROM raw.events\
+            {% if target.name == \"dev\" %} LIMIT 100\
+            {% elif var('lookback_days') > 3 && var('region') == 'eu' %} WHERE day > {{ var('lookback_days') }}\
+            {% if this == 'analytics.events' %} AND kept{% endif %}\
+            {% else %} -- everything{% endif %}";
+        assert!(uses_project_context(sql));
+        assert_eq!(
+            render(sql, &context).unwrap(),
+            "SELECT * FROM raw.events WHERE day > 7 AND kept"
+        );
+
+        let cases = [
+            ("{% if true %}", "Missing {% endif %}"),
+            ("{% endif %}", "without a matching {% if %}"),
+            (
+                "{% if true %}{% else %}{% elif false %}",
+                "after {% else %}",
+            ),
+            ("{% for x in y %}", "Unknown template tag"),
+            (
+                "{% if var('missing') %}{% endif %}",
+                "not a declared variable",
+            ),
+            ("{% if target.name == 1 %}{% endif %}", "type mismatch"),
+            (
+                "SELECT 1\n{% if true %}\n{% else %}\n{% endif %}\n{% endif %}",
+                "line 5: {% endif %} without a matching {% if %}",
+            ),
+            ("{% if true", "Unclosed template tag"),
+        ];
+        for (sql, expected) in cases {
+            let err = render(sql, &context).unwrap_err().to_string();
+            assert!(err.contains(expected), "{}: {}", sql, err);
+        }
+    }
+
+    #[test]
+    fn test_render_vars() {
+        let target = TargetContext::new(DEFAULT_TARGET, "duckdb").with_vars(BTreeMap::from([
+            ("start_date".to_string(), "2024-01-01".to_string()),
+            ("regions".to_string(), "'us', 'eu'".to_string()),
+        ]));
+        let context = TemplateContext {
+            this: "public.orders".to_string(),
+            target: &target,
+        };
+
+        let sql = "SELECT * FROM raw.orders WHERE ordered_at >= '{{ var('start_date') }}' AND region IN ({{ var(\"regions\") }})";
+        assert!(uses_project_context(sql));
+        assert_eq!(
+            render(sql, &context).unwrap(),
+            "SELECT * FROM raw.orders WHERE ordered_at >= '2024-01-01' AND region IN ('us', 'eu')"
+        );
+        assert!(!uses_project_context("SELECT * FROM {{ this }}"));
+    }
+
+    #[test]
+    fn test_render_literal_and_identifier() {
+        let vars = BTreeMap::from([
+            (
+                "customer".to_string(),
+                "O'Brien'; DROP TABLE x; --".to_string(),
+            ),
+            ("lookback_days".to_string(), "7".to_string()),
+        ]);
+        let sql = "SELECT * FROM {{ identifier(var('customer')) }} \
+            WHERE name = {{ literal(var('customer')) }} AND days > {{ literal(var('lookback_days')) }}";
+
+        let duckdb = TargetContext::new(DEFAULT_TARGET, "duckdb").with_vars(vars.clone());
+        let context = TemplateContext {
+            this: "public.orders".to_string(),
+            target: &duckdb,
+        };
+        assert_eq!(
+            render(sql, &context).unwrap(),
+            "SELECT * FROM \"O'Brien'; DROP TABLE x; --\" \
+            WHERE name = 'O''Brien''; DROP TABLE x; --' AND days > 7"
+        );
+
+        let bigquery = TargetContext::new(DEFAULT_TARGET, "bigquery").with_vars(vars);
+        let context = TemplateContext {
+            this: "public.orders".to_string(),
+            target: &bigquery,
+        };
+        assert_eq!(
+            render("{{ literal(var('customer')) }}", &context).unwrap(),
+            "'O\\'Brien\\'; DROP TABLE x; --'"
+        );
+        assert!(render("{{ identifier(1) }}", &context)
+            .unwrap_err()
+            .to_string()
+            .contains("identifier() takes a string, got INTEGER"));
+    }
+
+    #[test]
+    fn test_raw_interpolations() {
+        let sql = "SELECT * FROM {{ env_var('FF_SCHEMA') }}.orders\n\
+            WHERE region = '{{ var('region') }}' AND name = {{ literal(var('customer')) }}\n\
+            AND note = '{{ var('customer') }}' AND tag = {{ format('{}_x', literal(env_var('TAG'))) }}\n\
+            {% if env_var('FF_FULL', '') == '' %}AND day = '{{ var(\"day\") }}'{% endif %}";
+        let raw = raw_interpolations(sql, |name| name == "customer" || name == "day");
+        assert_eq!(
+            raw,
+            vec![
+                RawInterpolation {
+                    line: 1,
+                    expression: "env_var('FF_SCHEMA')".to_string(),
+                    value: "env_var('FF_SCHEMA')".to_string(),
+                },
+                RawInterpolation {
+                    line: 3,
+                    expression: "var('customer')".to_string(),
+                    value: "var('customer')".to_string(),
+                },
+                RawInterpolation {
+                    line: 4,
+                    expression: "var(\"day\")".to_string(),
+                    value: "var(\"day\")".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_ref_and_source() {
+        let target = TargetContext::new(DEFAULT_TARGET, "duckdb").with_relations(
+            BTreeMap::from([("orders".to_string(), "analytics.orders".to_string())]),
+            BTreeMap::from([(
+                ("raw_data".to_string(), "customers".to_string()),
+                "raw_data.customers".to_string(),
+            )]),
+        );
+        let context = TemplateContext {
+            this: "public.order_facts".to_string(),
+            target: &target,
+        };
+
+        let sql = "SELECT * FROM {{ ref('orders') }} JOIN {{ source(\"raw_data\", 'customers') }} USING (customer_id)";
+        assert!(uses_project_context(sql));
+        assert_eq!(
+            render(sql, &context).unwrap(),
+            "SELECT * FROM analytics.orders JOIN raw_data.customers USING (customer_id)"
+        );
+        assert_eq!(
+            explicit_relations(sql, &target),
+            vec!["analytics.orders", "raw_data.customers"]
+        );
+
+        let err = render("SELECT * FROM {{ ref('missing') }}", &context).unwrap_err();
+        assert!(err.to_string().contains("names no model or seed"));
+        let err = render("SELECT * FROM {{ source('raw_data') }}", &context).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("takes a source name and a table name"));
+    }
+
+    #[test]
+    fn test_render_description() {
+        let meta = HashMap::from([
+            ("domain".to_string(), serde_json::json!("marketing")),
+            ("sla_hours".to_string(), serde_json::json!(6)),
+        ]);
+        assert_eq!(
+            render_description(
+                "Daily trends for {{ meta.domain }} team ({{name}}, {{ upper(meta.domain) }}), fresh within {{ meta.sla_hours }}h",
+                "stg_trends",
+                &meta
+            )
+            .unwrap(),
+            "Daily trends for marketing team (stg_trends, MARKETING), fresh within 6h"
+        );
+        assert_eq!(
+            render_description("No templates", "stg_trends", &meta).unwrap(),
+            "No templates"
+        );
+
+        let err = render_description("For {{ meta.team }}", "stg_trends", &meta).unwrap_err();
+        assert_eq!(err.to_string(), "{{ meta.team }} has no value");
+        let err = render_description("For {{ meta.domain", "stg_trends", &meta).unwrap_err();
+        assert_eq!(err.to_string(), "{{ without a matching }}");
+    }
+}
@@ -0,0 +1,90 @@
+use super::super::dialect::normalize_for_parser;
+use super::super::extractors::get_external_table_deps_set;
+use super::super::lineage::extract_column_lineage;
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+use std::collections::HashSet;
+
+/// External dependencies of `sql`, parsed the same way models are
+fn deps(sql: &str) -> HashSet<String> {
+    let statements = Parser::parse_sql(&DuckDbDialect {}, &normalize_for_parser(sql))
+        .unwrap_or_else(|e| panic!("Failed to parse {}: {}", sql, e));
+    get_external_table_deps_set(&statements)
+}
+
+fn set(tables: &[&str]) -> HashSet<String> {
+    tables.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn test_qualify_with_subquery() {
+    let sql = "SELECT customer_id, order_date
+               FROM raw.orders
+               QUALIFY row_number() OVER (PARTITION BY customer_id ORDER BY order_date DESC) = 1
+                   AND customer_id IN (SELECT id FROM raw.active_customers)";
+
+    assert_eq!(deps(sql), set(&["raw.orders", "raw.active_customers"]));
+}
+
+#[test]
+fn test_asof_joins() {
+    let sql = "SELECT t.symbol, t.price, q.bid
+               FROM raw.trades t
+               ASOF JOIN raw.quotes q ON t.symbol = q.symbol AND t.ts >= q.ts
+               ASOF LEFT JOIN raw.fx f ON t.currency = f.currency AND t.ts >= f.ts";
+
+    assert_eq!(deps(sql), set(&["raw.trades", "raw.quotes", "raw.fx"]));
+}
+
+#[test]
+fn test_subqueries_in_join_conditions_and_filters() {
+    let sql = "SELECT o.id
+               FROM raw.orders o
+               JOIN raw.customers c
+                 ON o.customer_id = c.id
+                AND EXISTS (SELECT 1 FROM raw.blocklist b WHERE b.id = c.id)
+               WHERE o.amount BETWEEN 0 AND (SELECT max(limit_amount) FROM raw.limits)
+               GROUP BY o.id
+               HAVING count(*) > (SELECT count(*) FROM raw.thresholds)";
+
+    assert_eq!(
+        deps(sql),
+        set(&[
+            "raw.orders",
+            "raw.customers",
+            "raw.blocklist",
+            "raw.limits",
+            "raw.thresholds"
+        ])
+    );
+}
+
+#[test]
+fn test_struct_and_list_syntax() {
+    let sql = "SELECT e.payload.user.id AS user_id,
+                      e.attributes['country'] AS country,
+                      [x * 2 FOR x IN e.amounts IF x > 0] AS doubled,
+                      [1, 2, 3] AS literal_list,
+                      {'a': 1} AS literal_struct
+               FROM raw.events e";
+
+    assert_eq!(deps(sql), set(&["raw.events"]));
+}
+
+#[test]
+fn test_struct_access_lineage() {
+    let sql = "SELECT e.payload.user_id, e.attributes['country'] AS country
+               FROM raw.events e";
+
+    let lineage = extract_column_lineage(sql).unwrap();
+    assert_eq!(lineage.len(), 2);
+
+    assert_eq!(lineage[0].target.column, "user_id");
+    assert_eq!(lineage[0].sources.len(), 1);
+    assert_eq!(lineage[0].sources[0].table, Some("raw.events".to_string()));
+    assert_eq!(lineage[0].sources[0].column, "payload");
+
+    assert_eq!(lineage[1].target.column, "country");
+    assert_eq!(lineage[1].sources.len(), 1);
+    assert_eq!(lineage[1].sources[0].column, "attributes");
+}
@@ -1,3 +1,4 @@
+use super::super::checksum::Sha256Hash;
 use super::super::sql_model::{SqlModel, SqlModelCollection};
 use sqlparser::dialect::DuckDbDialect;
 use std::collections::HashSet;
@@ -41,7 +42,8 @@ fn test_external_sources_basic() {
     fs::write(&model_path, sql_content).unwrap();
 
     // Create and parse the model
-    let mut model = SqlModel::from_path(&model_path, project_root, "duckdb", &dialect).unwrap();
+    let mut model =
+        SqlModel::from_path(&model_path, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
     model.extract_dependencies().unwrap();
 
     // Check that the external source is correctly identified
@@ -73,7 +75,7 @@ fn test_external_sources_basic() {
 
     // Verify that get_external_sources() returns the correct set
     let external_sources = updated_model.get_external_sources();
-    assert_contains(&external_sources, &["external_schema.external_table"]);
+    assert_contains(external_sources, &["external_schema.external_table"]);
     assert_eq!(external_sources.len(), 1);
 }
 
@@ -101,8 +103,10 @@ fn test_model_references_vs_external_sources() {
     fs::write(&file_b, sql_b).unwrap();
 
     // Create and parse the models
-    let mut model_a = SqlModel::from_path(&file_a, project_root, "duckdb", &dialect).unwrap();
-    let mut model_b = SqlModel::from_path(&file_b, project_root, "duckdb", &dialect).unwrap();
+    let mut model_a =
+        SqlModel::from_path(&file_a, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+    let mut model_b =
+        SqlModel::from_path(&file_b, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
 
     model_a.extract_dependencies().unwrap();
     model_b.extract_dependencies().unwrap();
@@ -175,9 +179,12 @@ fn test_complex_dependency_graph_with_external_sources() {
     fs::write(&file_c, sql_c).unwrap();
 
     // Create and parse models
-    let mut model_a = SqlModel::from_path(&file_a, project_root, "duckdb", &dialect).unwrap();
-    let mut model_b = SqlModel::from_path(&file_b, project_root, "duckdb", &dialect).unwrap();
-    let mut model_c = SqlModel::from_path(&file_c, project_root, "duckdb", &dialect).unwrap();
+    let mut model_a =
+        SqlModel::from_path(&file_a, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+    let mut model_b =
+        SqlModel::from_path(&file_b, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+    let mut model_c =
+        SqlModel::from_path(&file_c, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
 
     // Extract dependencies
     model_a.extract_dependencies().unwrap();
@@ -280,10 +287,12 @@ fn test_external_sources_with_real_world_structure() {
     fs::write(&file_report, sql_report).unwrap();
 
     // Create and parse models
-    let mut stg_model = SqlModel::from_path(&file_stg, project_root, "duckdb", &dialect).unwrap();
-    let mut core_model = SqlModel::from_path(&file_core, project_root, "duckdb", &dialect).unwrap();
+    let mut stg_model =
+        SqlModel::from_path(&file_stg, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
+    let mut core_model =
+        SqlModel::from_path(&file_core, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
     let mut report_model =
-        SqlModel::from_path(&file_report, project_root, "duckdb", &dialect).unwrap();
+        SqlModel::from_path(&file_report, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
 
     // Extract dependencies
     stg_model.extract_dependencies().unwrap();
@@ -381,7 +390,8 @@ fn test_get_external_sources_method() {
     fs::write(&model_path, sql_content).unwrap();
 
     // Create and parse the model
-    let mut model = SqlModel::from_path(&model_path, project_root, "duckdb", &dialect).unwrap();
+    let mut model =
+        SqlModel::from_path(&model_path, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
     model.extract_dependencies().unwrap();
 
     // Add to collection and build dependency graph
@@ -400,7 +410,7 @@ fn test_get_external_sources_method() {
 
     // Verify that all external sources are correctly identified
     assert_contains(
-        &external_sources,
+        external_sources,
         &["source1.table1", "source2.table2", "source3.table3"],
     );
     assert_eq!(external_sources.len(), 3);
@@ -482,7 +492,8 @@ models:
     fs::write(&model_yaml_path, model_yaml).unwrap();
 
     // Create and parse the model
-    let mut model = SqlModel::from_path(&model_path, project_root, "duckdb", &dialect).unwrap();
+    let mut model =
+        SqlModel::from_path(&model_path, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
     model.extract_dependencies().unwrap();
 
     // Add to collection and check for missing sources
@@ -621,7 +632,8 @@ models:
     fs::write(&model_yaml_path, model_yaml).unwrap();
 
     // Create and parse the model
-    let mut model = SqlModel::from_path(&model_path, project_root, "duckdb", &dialect).unwrap();
+    let mut model =
+        SqlModel::from_path(&model_path, project_root, "duckdb", &dialect, &Sha256Hash).unwrap();
     model.extract_dependencies().unwrap();
 
     // Add to collection and check for missing sources
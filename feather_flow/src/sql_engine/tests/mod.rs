@@ -3,3 +3,6 @@ mod sql_model_tests;
 
 /// Tests for external sources functionality
 mod external_sources_tests;
+
+/// Tests for DuckDB-specific syntax in extraction and lineage
+mod duckdb_dialect_tests;
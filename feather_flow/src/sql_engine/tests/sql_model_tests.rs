@@ -1,3 +1,4 @@
+use super::super::checksum::Sha256Hash;
 use super::super::sql_model::{SqlModel, SqlModelCollection};
 use sqlparser::dialect::DuckDbDialect;
 use std::collections::HashSet;
@@ -19,7 +20,7 @@ fn fixtures_dir() -> PathBuf {
         }
 
         // Try parent directory
-        dir.parent().map(|parent| find_repo_root(parent)).flatten()
+        dir.parent().and_then(find_repo_root)
     }
 
     let current_dir = std::env::current_dir().expect("Failed to get current directory");
@@ -40,7 +41,10 @@ fn load_fixture(relative_path: &str) -> (PathBuf, PathBuf) {
     let fixtures_root = fixtures_dir();
 
     // Get the directory part and the file name
-    let base_name = relative_path.split('/').last().unwrap_or(relative_path);
+    let base_name = relative_path
+        .split('/')
+        .next_back()
+        .unwrap_or(relative_path);
     let directory = relative_path
         .rsplit_once('/')
         .map(|(dir, _)| dir)
@@ -73,8 +77,8 @@ fn create_model_from_fixture(relative_path: &str) -> SqlModel {
     let dialect = DuckDbDialect {};
 
     // For calculating unique_id, we need to adjust the path to match the new directory structure
-    let model = SqlModel::from_path(&file_path, &fixtures_root, "duckdb", &dialect)
-        .expect(&format!("Failed to create model from {}", relative_path));
+    let model = SqlModel::from_path(&file_path, &fixtures_root, "duckdb", &dialect, &Sha256Hash)
+        .unwrap_or_else(|_| panic!("Failed to create model from {}", relative_path));
 
     // The model path includes the extra directory now, which affects the unique_id
     // We need to ensure the unique_id is the same as before the restructuring
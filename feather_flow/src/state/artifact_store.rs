@@ -0,0 +1,111 @@
+//! Content-addressed store of compiled artifacts, for `ff compile --artifact-store <dir>`
+//!
+//! Each artifact is written once, to `objects/<algorithm>/<first two digits>/<digest>` under
+//! the store, named by the digest of its content. Compiles of different branches sharing one
+//! store, such as a CI cache directory, keep a single copy of every model that compiles
+//! identically. Objects are never modified, so concurrent compiles can share a store: an
+//! object is written to a temporary file first and renamed into place.
+//!
+//! Each compile also writes `artifacts.json` next to its compiled SQL, listing the digest of
+//! every model's artifact, so the artifacts of a compile can be found in the store.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::sql_engine::checksum::HashAlgorithm;
+
+/// File listing the artifacts of a compile
+pub const ARTIFACT_INDEX_FILE: &str = "artifacts.json";
+
+pub struct ArtifactStore {
+    root: PathBuf,
+    algorithm: &'static dyn HashAlgorithm,
+}
+
+/// Digest of each artifact of a compile
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactIndex {
+    pub algorithm: String,
+    /// By model path, relative to the model directory
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl ArtifactStore {
+    /// The store at `root`, addressing objects by their digest by `algorithm`
+    pub fn new(root: &Path, algorithm: &'static dyn HashAlgorithm) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            algorithm,
+        }
+    }
+
+    pub fn object_path(&self, digest: &str) -> PathBuf {
+        let (prefix, _) = digest.split_at(2.min(digest.len()));
+        self.root
+            .join("objects")
+            .join(self.algorithm.name())
+            .join(prefix)
+            .join(digest)
+    }
+
+    /// Store `content` unless an identical object exists, returning its digest and whether it
+    /// was new
+    pub fn put(&self, content: &[u8]) -> Result<(String, bool)> {
+        let digest = self.algorithm.hash(content);
+        let path = self.object_path(&digest);
+        if path.is_file() {
+            return Ok((digest, false));
+        }
+        let dir = path.parent().unwrap_or(&self.root);
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let tmp_path = path.with_extension(format!("{}.tmp", process::id()));
+        fs::write(&tmp_path, content)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok((digest, true))
+    }
+
+    /// An empty index of artifacts stored by this store's algorithm
+    pub fn index(&self) -> ArtifactIndex {
+        ArtifactIndex {
+            algorithm: self.algorithm.name().to_string(),
+            artifacts: BTreeMap::new(),
+        }
+    }
+}
+
+impl ArtifactIndex {
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(ARTIFACT_INDEX_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_put_deduplicates() {
+        let dir = tempdir().unwrap();
+        let store = ArtifactStore::new(dir.path(), &Sha256Hash);
+        let (digest, new) = store.put(b"SELECT 1").unwrap();
+        assert!(new);
+        assert_eq!(store.put(b"SELECT 1").unwrap(), (digest.clone(), false));
+        let path = store.object_path(&digest);
+        assert_eq!(fs::read(&path).unwrap(), b"SELECT 1");
+        assert!(path.starts_with(dir.path().join("objects").join("sha256").join(&digest[..2])));
+
+        let (other, new) = store.put(b"SELECT 2").unwrap();
+        assert!(new);
+        assert_ne!(other, digest);
+    }
+}
@@ -0,0 +1,159 @@
+//! Progress of the latest `ff build`, for `ff build --resume`
+//!
+//! The build rewrites `target/state/build_progress.json` after every node that succeeds,
+//! so a build that is killed or fails leaves a record of what it completed. Each completed
+//! node is stored with a fingerprint of what it ran; a resumed build only reuses nodes whose
+//! fingerprint is unchanged. A build that finishes without failures removes the file.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::sql_engine::checksum::HashAlgorithm;
+
+/// File inside the state directory holding the progress
+pub const BUILD_PROGRESS_FILE: &str = "build_progress.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildProgress {
+    pub started_at: DateTime<Utc>,
+    /// Arguments of the build, compared when resuming
+    pub database: PathBuf,
+    pub target: Option<String>,
+    pub select: Option<String>,
+    pub exclude: Option<String>,
+    /// Fingerprint of every node the build completed, by unique id
+    pub completed: BTreeMap<String, String>,
+}
+
+impl BuildProgress {
+    pub fn new(
+        database: &Path,
+        target: Option<&str>,
+        select: Option<&str>,
+        exclude: Option<&str>,
+    ) -> Self {
+        Self {
+            started_at: Utc::now(),
+            database: database.to_path_buf(),
+            target: target.map(str::to_string),
+            select: select.map(str::to_string),
+            exclude: exclude.map(str::to_string),
+            completed: BTreeMap::new(),
+        }
+    }
+
+    /// Hash of everything that determines what a node runs
+    pub fn fingerprint(parts: &[&str], hash: &dyn HashAlgorithm) -> String {
+        hash.hash_parts(parts)
+    }
+
+    pub fn path(state_dir: &Path) -> PathBuf {
+        state_dir.join(BUILD_PROGRESS_FILE)
+    }
+
+    /// The recorded progress, if a build left any
+    pub fn load(state_dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(state_dir);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+        let path = Self::path(state_dir);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn remove(state_dir: &Path) -> Result<()> {
+        let path = Self::path(state_dir);
+        if path.is_file() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Differences between the arguments of the recorded build and `other`'s, for warnings
+    pub fn argument_changes(&self, other: &BuildProgress) -> Vec<String> {
+        let describe = |value: &Option<String>| value.clone().unwrap_or_else(|| "none".to_string());
+        let mut changes = Vec::new();
+        if self.database != other.database {
+            changes.push(format!(
+                "--db was {}, now {}",
+                self.database.display(),
+                other.database.display()
+            ));
+        }
+        for (flag, before, now) in [
+            ("--target", &self.target, &other.target),
+            ("--select", &self.select, &other.select),
+            ("--exclude", &self.exclude, &other.exclude),
+        ] {
+            if before != now {
+                changes.push(format!(
+                    "{} was {}, now {}",
+                    flag,
+                    describe(before),
+                    describe(now)
+                ));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_progress_round_trip_and_argument_changes() {
+        let temp_dir = tempdir().unwrap();
+        assert_eq!(BuildProgress::load(temp_dir.path()).unwrap(), None);
+
+        let mut progress =
+            BuildProgress::new(Path::new("dev.duckdb"), Some("dev"), Some("tag:core"), None);
+        progress.completed.insert(
+            "model.orders".to_string(),
+            BuildProgress::fingerprint(&["select 1", "table"], &Sha256Hash),
+        );
+        progress.save(temp_dir.path()).unwrap();
+        assert_eq!(
+            BuildProgress::load(temp_dir.path()).unwrap(),
+            Some(progress.clone())
+        );
+
+        let resumed = BuildProgress::new(Path::new("dev.duckdb"), Some("dev"), None, None);
+        assert_eq!(
+            progress.argument_changes(&resumed),
+            vec!["--select was tag:core, now none"]
+        );
+
+        BuildProgress::remove(temp_dir.path()).unwrap();
+        assert_eq!(BuildProgress::load(temp_dir.path()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_fingerprint_separates_parts() {
+        assert_ne!(
+            BuildProgress::fingerprint(&["ab", "c"], &Sha256Hash),
+            BuildProgress::fingerprint(&["a", "bc"], &Sha256Hash)
+        );
+    }
+}
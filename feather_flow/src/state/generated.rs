@@ -0,0 +1,36 @@
+//! What FeatherFlow last generated for each file it writes into the project
+//!
+//! A copy of every generated file is kept under `target/state/generated`, at the same
+//! relative path. When a file is generated again, that copy is the base of the three-way
+//! merge between the user's edits and the new output.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct GeneratedFiles {
+    dir: PathBuf,
+}
+
+impl GeneratedFiles {
+    pub fn new(state_dir: &Path) -> Self {
+        Self {
+            dir: state_dir.join("generated"),
+        }
+    }
+
+    /// Content last generated for `relative_path`, if it was generated before
+    pub fn get(&self, relative_path: &Path) -> Option<String> {
+        fs::read_to_string(self.dir.join(relative_path)).ok()
+    }
+
+    pub fn record(&self, relative_path: &Path, content: &str) -> Result<()> {
+        let path = self.dir.join(relative_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write generated copy: {}", path.display()))
+    }
+}
@@ -0,0 +1,177 @@
+//! Advisory lock serializing writes to the state directory
+//!
+//! The lock is a file created exclusively inside the state directory and removed when the
+//! holder is dropped. It records who took it, so a lock left behind by a killed process can
+//! be identified and cleared with `ff unlock`.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Lock file name inside the state directory
+pub const LOCK_FILE: &str = "state.lock";
+
+/// Seconds to wait for another invocation to release the lock
+pub const DEFAULT_LOCK_TIMEOUT_SECS: u64 = 30;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Who holds the lock, as written into the lock file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub user: String,
+    pub host: String,
+    pub command: String,
+    pub acquired_at: DateTime<Utc>,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+            host: std::env::var("HOSTNAME")
+                .ok()
+                .or_else(|| fs::read_to_string("/etc/hostname").ok())
+                .map(|host| host.trim().to_string())
+                .filter(|host| !host.is_empty())
+                .unwrap_or_else(|| "unknown".to_string()),
+            command: std::env::args().collect::<Vec<_>>().join(" "),
+            acquired_at: Utc::now(),
+        }
+    }
+}
+
+impl fmt::Display for LockOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pid {} ({}@{}) since {}: {}",
+            self.pid,
+            self.user,
+            self.host,
+            self.acquired_at.to_rfc3339(),
+            self.command
+        )
+    }
+}
+
+/// A held lock on a state directory, released on drop
+#[derive(Debug)]
+pub struct StateLock {
+    path: PathBuf,
+}
+
+impl StateLock {
+    /// Take the lock on `state_dir`, waiting up to `timeout` for another holder to release it
+    pub fn acquire(state_dir: &Path, timeout: Duration) -> Result<Self> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create state directory {}", state_dir.display()))?;
+
+        let path = state_dir.join(LOCK_FILE);
+        let owner = serde_json::to_string_pretty(&LockOwner::current())?;
+        let started = Instant::now();
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(owner.as_bytes())
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    return Ok(Self { path });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if started.elapsed() >= timeout {
+                        let holder = read_owner(state_dir)
+                            .ok()
+                            .flatten()
+                            .map_or_else(|| "an unknown process".to_string(), |o| o.to_string());
+                        return Err(anyhow!(
+                            "State directory {} is locked by {}. Wait for it to finish, or run `ff unlock` if that process is gone.",
+                            state_dir.display(),
+                            holder
+                        ));
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Failed to create {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The current holder of the lock on `state_dir`, if it is locked
+pub fn read_owner(state_dir: &Path) -> Result<Option<LockOwner>> {
+    let path = state_dir.join(LOCK_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    serde_json::from_str(&content)
+        .map(Some)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Remove the lock on `state_dir` regardless of who holds it. Returns whether a lock file
+/// existed.
+pub fn force_unlock(state_dir: &Path) -> Result<bool> {
+    let path = state_dir.join(LOCK_FILE);
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_excludes_and_releases() {
+        let temp_dir = tempdir().unwrap();
+        let state_dir = temp_dir.path().join("state");
+
+        let lock = StateLock::acquire(&state_dir, Duration::ZERO).unwrap();
+        let owner = read_owner(&state_dir).unwrap().unwrap();
+        assert_eq!(owner.pid, std::process::id());
+
+        let err = StateLock::acquire(&state_dir, Duration::from_millis(150)).unwrap_err();
+        assert!(err.to_string().contains(&format!("pid {}", owner.pid)));
+
+        drop(lock);
+        assert!(read_owner(&state_dir).unwrap().is_none());
+        assert!(StateLock::acquire(&state_dir, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn test_force_unlock_clears_stale_lock() {
+        let temp_dir = tempdir().unwrap();
+        let lock = StateLock::acquire(temp_dir.path(), Duration::ZERO).unwrap();
+        // Simulate a holder that died without cleaning up
+        std::mem::forget(lock);
+
+        assert!(force_unlock(temp_dir.path()).unwrap());
+        assert!(!force_unlock(temp_dir.path()).unwrap());
+        assert!(StateLock::acquire(temp_dir.path(), Duration::ZERO).is_ok());
+    }
+}
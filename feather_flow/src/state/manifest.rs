@@ -0,0 +1,98 @@
+//! Deployed relations recorded by another environment, used for deferral
+//!
+//! A state manifest is the output of `ff parse --format json` run against the environment
+//! to defer to, e.g. `ff parse -m models -f json -t prod > prod.json`. Only each model's
+//! location is read from it.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::sql_engine::template::DEFAULT_SCHEMA;
+
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    models: HashMap<String, ManifestModel>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ManifestModel {
+    name: String,
+    database: Option<String>,
+    schema: Option<String>,
+    object_name: Option<String>,
+    alias: Option<String>,
+}
+
+/// Relation of every model in the manifest, keyed by unique id
+#[derive(Debug, Default)]
+pub struct StateManifest {
+    relations: HashMap<String, String>,
+}
+
+impl StateManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read state manifest {}", path.display()))?;
+        Self::from_json(&content)
+            .with_context(|| format!("Failed to parse state manifest {}", path.display()))
+    }
+
+    fn from_json(content: &str) -> Result<Self> {
+        let manifest: ManifestFile = serde_json::from_str(content)?;
+        let relations = manifest
+            .models
+            .into_iter()
+            .map(|(unique_id, model)| (unique_id, relation(&model)))
+            .collect();
+
+        Ok(Self { relations })
+    }
+
+    /// The deployed `[database.]schema.relation` of the model with `unique_id`
+    pub fn relation(&self, unique_id: &str) -> Option<&str> {
+        self.relations.get(unique_id).map(String::as_str)
+    }
+}
+
+fn relation(model: &ManifestModel) -> String {
+    let name = model
+        .alias
+        .as_deref()
+        .or(model.object_name.as_deref())
+        .unwrap_or(&model.name);
+    let schema = model.schema.as_deref().unwrap_or(DEFAULT_SCHEMA);
+
+    match &model.database {
+        Some(database) => format!("{}.{}.{}", database, schema, name),
+        None => format!("{}.{}", schema, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relations_from_parse_output() {
+        let manifest = StateManifest::from_json(
+            r#"{"models": {
+                "model.staging.stg_orders": {"name": "stg_orders", "path": "staging/stg_orders/stg_orders.sql", "schema": "staging", "database": null, "object_name": null, "alias": null},
+                "model.marts.orders": {"name": "orders", "schema": "analytics", "database": "warehouse", "alias": "fct_orders"}
+            }}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.relation("model.staging.stg_orders"),
+            Some("staging.stg_orders")
+        );
+        assert_eq!(
+            manifest.relation("model.marts.orders"),
+            Some("warehouse.analytics.fct_orders")
+        );
+        assert_eq!(manifest.relation("model.unknown"), None);
+    }
+}
@@ -0,0 +1,23 @@
+//! Local state kept between invocations under the project's `target/` directory
+
+pub mod artifact_store;
+pub mod build_progress;
+pub mod generated;
+pub mod lock;
+pub mod manifest;
+pub mod parse_cache;
+pub mod preview_cache;
+pub mod revision;
+pub mod run_history;
+pub mod source_watermarks;
+pub mod workflow_runs;
+
+use std::path::{Path, PathBuf};
+
+/// Directory, relative to the project root, holding local state
+pub const STATE_DIR: &str = "target/state";
+
+/// State directory of the project rooted at `project_root`
+pub fn state_dir(project_root: &Path) -> PathBuf {
+    project_root.join(STATE_DIR)
+}
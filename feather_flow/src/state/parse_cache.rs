@@ -0,0 +1,243 @@
+//! Cached parse results for `ff parse`
+//!
+//! Each model's entry records the checksums of its SQL file and of its YAML file separately,
+//! along with what parsing its compiled SQL produced, keyed by a hash of that compiled SQL.
+//! YAML metadata is always re-read, so editing only a description or tests reuses the parse
+//! results. Any change to the SQL file is reparsed, as is a YAML change that alters the
+//! compiled SQL, such as a new alias rendered into `{{ this }}`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::sql_engine::checksum::HashAlgorithm;
+use crate::sql_engine::references::SourceLocation;
+use crate::sql_engine::sql_model::SqlModel;
+use crate::sql_engine::statements::StatementError;
+
+/// File in the state directory holding the cache
+pub const PARSE_CACHE_FILE: &str = "parse_cache.json";
+
+/// What parsing a model's compiled SQL produced, apart from the AST
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedStatements {
+    pub referenced_tables: HashSet<String>,
+    pub reference_locations: BTreeMap<String, Vec<SourceLocation>>,
+    pub statement_errors: Vec<StatementError>,
+    pub unqualified_tables: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    sql_checksum: String,
+    yaml_checksum: Option<String>,
+    /// Parse results keyed by a hash of the dialect and compiled SQL; templated models are
+    /// compiled once per target
+    parsed: BTreeMap<String, ParsedStatements>,
+}
+
+/// How a model's parse results were obtained in this run, least work first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CacheOutcome {
+    Unchanged,
+    /// Only the YAML changed: metadata was refreshed and the parse results reused
+    MetadataRefreshed,
+    Reparsed,
+}
+
+/// Number of models by `CacheOutcome`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSummary {
+    pub unchanged: usize,
+    pub metadata_refreshed: usize,
+    pub reparsed: usize,
+}
+
+pub struct ParseCache {
+    path: PathBuf,
+    previous: HashMap<String, CacheEntry>,
+    current: HashMap<String, CacheEntry>,
+    outcomes: HashMap<String, CacheOutcome>,
+    hash: &'static dyn HashAlgorithm,
+}
+
+impl ParseCache {
+    /// The cache in `state_dir`, empty when missing or unreadable, keyed by digests by `hash`
+    pub fn load(state_dir: &Path, hash: &'static dyn HashAlgorithm) -> Self {
+        let path = state_dir.join(PARSE_CACHE_FILE);
+        let previous = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            previous,
+            current: HashMap::new(),
+            outcomes: HashMap::new(),
+            hash,
+        }
+    }
+
+    /// The hash algorithm models parsed through the cache are digested by
+    pub fn hash(&self) -> &'static dyn HashAlgorithm {
+        self.hash
+    }
+
+    /// Cached parse results of `compiled_sql` for `model`, if its SQL file is unchanged
+    pub fn get(&mut self, model: &SqlModel, compiled_sql: &str) -> Option<ParsedStatements> {
+        let previous = self.previous.get(&model.unique_id)?;
+        if previous.sql_checksum != model.checksum {
+            return None;
+        }
+        let parsed = previous
+            .parsed
+            .get(&self.compiled_key(model, compiled_sql))?
+            .clone();
+
+        let outcome = if previous.yaml_checksum == model.yaml_checksum {
+            CacheOutcome::Unchanged
+        } else {
+            CacheOutcome::MetadataRefreshed
+        };
+        self.record(model, compiled_sql, parsed.clone(), outcome);
+        Some(parsed)
+    }
+
+    /// Store the results of parsing `compiled_sql` for `model`
+    pub fn put(&mut self, model: &SqlModel, compiled_sql: &str, parsed: ParsedStatements) {
+        self.record(model, compiled_sql, parsed, CacheOutcome::Reparsed);
+    }
+
+    fn record(
+        &mut self,
+        model: &SqlModel,
+        compiled_sql: &str,
+        parsed: ParsedStatements,
+        outcome: CacheOutcome,
+    ) {
+        let key = self.compiled_key(model, compiled_sql);
+        let entry = self
+            .current
+            .entry(model.unique_id.clone())
+            .or_insert_with(|| CacheEntry {
+                sql_checksum: model.checksum.clone(),
+                yaml_checksum: model.yaml_checksum.clone(),
+                parsed: BTreeMap::new(),
+            });
+        entry.parsed.insert(key, parsed);
+
+        let recorded = self
+            .outcomes
+            .entry(model.unique_id.clone())
+            .or_insert(outcome);
+        *recorded = (*recorded).max(outcome);
+    }
+
+    pub fn summary(&self) -> CacheSummary {
+        let mut summary = CacheSummary::default();
+        for outcome in self.outcomes.values() {
+            match outcome {
+                CacheOutcome::Unchanged => summary.unchanged += 1,
+                CacheOutcome::MetadataRefreshed => summary.metadata_refreshed += 1,
+                CacheOutcome::Reparsed => summary.reparsed += 1,
+            }
+        }
+        summary
+    }
+
+    fn compiled_key(&self, model: &SqlModel, compiled_sql: &str) -> String {
+        self.hash.hash_parts(&[&model.dialect, compiled_sql])
+    }
+
+    /// Write the entries used in this run, dropping models that no longer exist
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create state directory {}", dir.display()))?;
+        }
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string(&self.current)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_yaml_only_changes_reuse_parse_results() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let state_dir = root.join("state");
+        let model_dir = root.join("orders");
+        fs::create_dir(&model_dir).unwrap();
+        let sql_path = model_dir.join("orders.sql");
+        let yaml_path = model_dir.join("orders.yml");
+        fs::write(&sql_path, "SELECT id FROM raw.orders").unwrap();
+        fs::write(
+            &yaml_path,
+            "version: 2\nmodels:\n  - name: orders\n    description: Orders\n",
+        )
+        .unwrap();
+
+        let parse = |expected: CacheSummary| {
+            let mut cache = ParseCache::load(&state_dir, &Sha256Hash);
+            let mut model =
+                SqlModel::from_path_with_cache(&sql_path, root, "duckdb", &mut cache).unwrap();
+            model.extract_dependencies().unwrap();
+            assert_eq!(cache.summary(), expected);
+            cache.save().unwrap();
+            model
+        };
+        let reparsed = CacheSummary {
+            reparsed: 1,
+            ..CacheSummary::default()
+        };
+
+        let first = parse(reparsed);
+        assert!(first.referenced_tables.contains("raw.orders"));
+
+        let second = parse(CacheSummary {
+            unchanged: 1,
+            ..CacheSummary::default()
+        });
+        assert_eq!(second.referenced_tables, first.referenced_tables);
+
+        fs::write(
+            &yaml_path,
+            "version: 2\nmodels:\n  - name: orders\n    description: One row per order\n",
+        )
+        .unwrap();
+        let refreshed = parse(CacheSummary {
+            metadata_refreshed: 1,
+            ..CacheSummary::default()
+        });
+        assert_eq!(refreshed.description.as_deref(), Some("One row per order"));
+        assert_eq!(refreshed.referenced_tables, first.referenced_tables);
+
+        fs::write(&sql_path, "SELECT id FROM raw.orders_v2").unwrap();
+        let edited = parse(reparsed);
+        assert!(edited.referenced_tables.contains("raw.orders_v2"));
+    }
+
+    #[test]
+    fn test_unreadable_cache_is_empty() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(PARSE_CACHE_FILE), "not json").unwrap();
+
+        let cache = ParseCache::load(temp_dir.path(), &Sha256Hash);
+        assert!(cache.previous.is_empty());
+        assert_eq!(cache.summary(), CacheSummary::default());
+    }
+}
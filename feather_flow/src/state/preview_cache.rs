@@ -0,0 +1,127 @@
+//! Cached preview results
+//!
+//! Entries are keyed by a hash of the compiled SQL and the checksums of every upstream
+//! model, so editing the model or anything it reads from produces a new key. Upstream data
+//! can still change without any SQL changing, which the TTL bounds.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::adapters::QueryResult;
+use crate::sql_engine::checksum::HashAlgorithm;
+
+/// Default lifetime of a cached preview
+pub const DEFAULT_TTL_SECS: u64 = 3600;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    cached_at: DateTime<Utc>,
+    result: QueryResult,
+}
+
+/// A cache hit and how old it is
+#[derive(Debug, PartialEq)]
+pub struct CachedPreview {
+    pub cached_at: DateTime<Utc>,
+    pub result: QueryResult,
+}
+
+pub struct PreviewCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl PreviewCache {
+    pub fn new(state_dir: &Path, ttl_secs: u64) -> Self {
+        Self {
+            dir: state_dir.join("preview_cache"),
+            ttl: Duration::seconds(i64::try_from(ttl_secs).unwrap_or(i64::MAX)),
+        }
+    }
+
+    /// Hash of everything that determines a preview's result
+    pub fn key(parts: &[&str], hash: &dyn HashAlgorithm) -> String {
+        hash.hash_parts(parts)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The cached result for `key`, unless missing, unreadable or older than the TTL
+    pub fn get(&self, key: &str) -> Option<CachedPreview> {
+        let content = fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+
+        if Utc::now() - entry.cached_at > self.ttl {
+            return None;
+        }
+
+        Some(CachedPreview {
+            cached_at: entry.cached_at,
+            result: entry.result,
+        })
+    }
+
+    pub fn put(&self, key: &str, result: &QueryResult) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create preview cache at {}", self.dir.display()))?;
+
+        let entry = CacheEntry {
+            cached_at: Utc::now(),
+            result: result.clone(),
+        };
+        let path = self.entry_path(key);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string(&entry)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use tempfile::tempdir;
+
+    fn result() -> QueryResult {
+        QueryResult {
+            columns: vec!["id".to_string()],
+            rows: vec![vec!["1".to_string()]],
+        }
+    }
+
+    #[test]
+    fn test_cache_roundtrip_and_ttl() {
+        let temp_dir = tempdir().unwrap();
+        let key = PreviewCache::key(&["SELECT 1", "model.a:abc"], &Sha256Hash);
+
+        let cache = PreviewCache::new(temp_dir.path(), DEFAULT_TTL_SECS);
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, &result()).unwrap();
+        assert_eq!(cache.get(&key).unwrap().result, result());
+
+        let expired = PreviewCache::new(temp_dir.path(), 0);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(expired.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_key_separates_parts() {
+        assert_ne!(
+            PreviewCache::key(&["ab", "c"], &Sha256Hash),
+            PreviewCache::key(&["a", "bc"], &Sha256Hash)
+        );
+        assert_eq!(
+            PreviewCache::key(&["a"], &Sha256Hash),
+            PreviewCache::key(&["a"], &Sha256Hash)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Project files as of a git revision, read from the repository's object database
+//!
+//! `ff compile --at <rev>` loads the project from here instead of the working tree. The
+//! project directory's tree at the commit is written blob by blob under
+//! `target/state/revisions/<commit>/tree`, without touching the index, `HEAD` or the working
+//! tree. Commits never change, so an export is reused by later invocations.
+
+use anyhow::{anyhow, bail, Context, Result};
+use git2::{ObjectType, Repository, Tree};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory under the state directory holding one export per commit
+const REVISIONS_DIR: &str = "revisions";
+
+/// Git file mode of symbolic links, which are not exported
+const SYMLINK_MODE: i32 = 0o120000;
+
+/// A project directory exported at one commit
+pub struct Revision {
+    /// Full id of the commit the revision resolved to
+    pub commit: String,
+    /// Directory holding the project's files at the commit
+    pub root: PathBuf,
+}
+
+impl Revision {
+    /// Short commit id for messages
+    pub fn short_id(&self) -> &str {
+        &self.commit[..self.commit.len().min(8)]
+    }
+
+    /// Directory for files derived from the revision, e.g. its manifest
+    pub fn state_dir(&self) -> &Path {
+        self.root.parent().unwrap_or(&self.root)
+    }
+}
+
+/// Export the directory `project_root` as it is at `rev` (a commit, branch, tag or any other
+/// revision git understands) into `state_dir`
+pub fn export(project_root: &Path, rev: &str, state_dir: &Path) -> Result<Revision> {
+    let repo = Repository::discover(project_root)
+        .with_context(|| format!("{} is not inside a git repository", project_root.display()))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Cannot read revisions from a bare repository"))?
+        .canonicalize()?;
+    let project_root = project_root.canonicalize()?;
+    let prefix = project_root.strip_prefix(&workdir).with_context(|| {
+        format!(
+            "{} is outside the repository at {}",
+            project_root.display(),
+            workdir.display()
+        )
+    })?;
+
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .with_context(|| format!("Unknown git revision '{}'", rev))?;
+    let commit_id = commit.id().to_string();
+    let tree = commit.tree()?;
+    let tree = if prefix.as_os_str().is_empty() {
+        tree
+    } else {
+        let entry = tree
+            .get_path(prefix)
+            .with_context(|| format!("{} does not exist at {}", prefix.display(), rev))?;
+        repo.find_tree(entry.id())
+            .with_context(|| format!("{} is not a directory at {}", prefix.display(), rev))?
+    };
+
+    let dir = state_dir.join(REVISIONS_DIR).join(&commit_id);
+    let root = dir.join("tree");
+    if !root.is_dir() {
+        // Written aside and renamed so an interrupted export is never reused
+        let partial = dir.join("tree.partial");
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+        write_tree(&repo, &tree, &partial)?;
+        fs::rename(&partial, &root)?;
+    }
+
+    Ok(Revision {
+        commit: commit_id,
+        root,
+    })
+}
+
+/// Write every file of `tree` under `dir`, skipping symlinks and submodules
+fn write_tree(repo: &Repository, tree: &Tree, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    for entry in tree.iter() {
+        let Some(name) = entry.name() else {
+            bail!("Non UTF-8 file name in {}", dir.display());
+        };
+        let path = dir.join(name);
+        match entry.kind() {
+            Some(ObjectType::Tree) => write_tree(repo, &repo.find_tree(entry.id())?, &path)?,
+            Some(ObjectType::Blob) if entry.filemode() != SYMLINK_MODE => {
+                fs::write(&path, repo.find_blob(entry.id())?.content())
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{IndexAddOption, Signature};
+    use tempfile::tempdir;
+
+    fn commit_all(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("ff", "ff@example.com").unwrap();
+        let parent = repo
+            .revparse_single("HEAD")
+            .and_then(|object| object.peel_to_commit())
+            .ok();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_export_reads_the_project_at_a_commit() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("models/orders")).unwrap();
+        fs::write(project.join("models/orders/orders.sql"), "SELECT 1 AS id").unwrap();
+        fs::write(dir.path().join("README.md"), "outside the project").unwrap();
+        commit_all(&repo, "first");
+        fs::write(project.join("models/orders/orders.sql"), "SELECT 2 AS id").unwrap();
+        commit_all(&repo, "second");
+        fs::write(project.join("models/orders/orders.sql"), "SELECT 3 AS id").unwrap();
+
+        let state = project.join("target/state");
+        let revision = export(&project, "HEAD~1", &state).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(revision.root.join("models/orders/orders.sql")).unwrap(),
+            "SELECT 1 AS id"
+        );
+        assert!(!revision.root.join("README.md").exists());
+        assert!(revision.root.starts_with(state.join(REVISIONS_DIR)));
+        assert!(export(&project, "no-such-branch", &state).is_err());
+    }
+}
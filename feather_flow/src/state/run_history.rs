@@ -0,0 +1,93 @@
+//! How long each node's recent successful builds took, for `run::anomalies`
+//!
+//! `ff build` records the duration of every node it runs successfully in
+//! `target/state/run_history.json`, keeping the last `KEPT_RUNS` of each.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File inside the state directory holding the history
+pub const RUN_HISTORY_FILE: &str = "run_history.json";
+
+/// Builds kept per node
+pub const KEPT_RUNS: usize = 10;
+
+/// Builds a node needs before their average is a baseline
+pub const MIN_RUNS: usize = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunHistory {
+    /// Seconds each recent successful build took, oldest first, by unique id
+    pub nodes: BTreeMap<String, VecDeque<f64>>,
+}
+
+impl RunHistory {
+    pub fn path(state_dir: &Path) -> PathBuf {
+        state_dir.join(RUN_HISTORY_FILE)
+    }
+
+    /// The recorded history; empty before the first build
+    pub fn load(state_dir: &Path) -> Result<Self> {
+        let path = Self::path(state_dir);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+        let path = Self::path(state_dir);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn record(&mut self, unique_id: &str, secs: f64) {
+        let runs = self.nodes.entry(unique_id.to_string()).or_default();
+        runs.push_back(secs);
+        while runs.len() > KEPT_RUNS {
+            runs.pop_front();
+        }
+    }
+
+    /// Average seconds of the node's recorded builds and how many there are, once there are
+    /// at least `MIN_RUNS`
+    pub fn average(&self, unique_id: &str) -> Option<(f64, usize)> {
+        let runs = self.nodes.get(unique_id)?;
+        if runs.len() < MIN_RUNS {
+            return None;
+        }
+        Some((runs.iter().sum::<f64>() / runs.len() as f64, runs.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_keeps_recent_runs() {
+        let dir = tempdir().unwrap();
+        let state_dir = dir.path().join("state");
+        let mut history = RunHistory::load(&state_dir).unwrap();
+        for secs in 1..=KEPT_RUNS + 2 {
+            history.record("model.orders", secs as f64);
+        }
+        history.save(&state_dir).unwrap();
+
+        let loaded = RunHistory::load(&state_dir).unwrap();
+        assert_eq!(loaded.nodes["model.orders"].len(), KEPT_RUNS);
+        assert_eq!(loaded.average("model.orders"), Some((7.5, KEPT_RUNS)));
+        assert_eq!(loaded.average("model.customers"), None);
+    }
+}
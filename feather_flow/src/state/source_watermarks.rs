@@ -0,0 +1,131 @@
+//! Source loads each node was last built from, for `ff build --skip-stale-subgraphs`
+//!
+//! When a build checks the freshness of its sources, every node that succeeds is recorded in
+//! `target/state/source_watermarks.json` with the latest `loaded_at_field` value of each
+//! source upstream of it. A later build with `--skip-stale-subgraphs` does not run a node
+//! again while those values, what it runs and where it builds to are all unchanged.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File inside the state directory holding the watermarks
+pub const SOURCE_WATERMARKS_FILE: &str = "source_watermarks.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceWatermarks {
+    /// Latest successful build of each node whose upstream source loads were known, by
+    /// unique id
+    pub nodes: BTreeMap<String, NodeWatermark>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeWatermark {
+    pub database: PathBuf,
+    pub target: Option<String>,
+    /// Fingerprint of what the node ran, as recorded in the build progress
+    pub fingerprint: String,
+    /// Latest `loaded_at_field` value of every upstream source, by unique id
+    pub sources: BTreeMap<String, String>,
+    pub built_at: DateTime<Utc>,
+}
+
+impl NodeWatermark {
+    /// Whether a node built as `other` would build from the same inputs into the same place
+    pub fn same_inputs(&self, other: &NodeWatermark) -> bool {
+        self.database == other.database
+            && self.target == other.target
+            && self.fingerprint == other.fingerprint
+            && self.sources == other.sources
+    }
+}
+
+impl SourceWatermarks {
+    pub fn path(state_dir: &Path) -> PathBuf {
+        state_dir.join(SOURCE_WATERMARKS_FILE)
+    }
+
+    /// The recorded watermarks; none before the first build that checked its sources
+    pub fn load(state_dir: &Path) -> Result<Self> {
+        let path = Self::path(state_dir);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+        let path = Self::path(state_dir);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Whether `unique_id` last built successfully from the same inputs as `current`
+    pub fn unchanged(&self, unique_id: &str, current: &NodeWatermark) -> bool {
+        self.nodes
+            .get(unique_id)
+            .is_some_and(|recorded| recorded.same_inputs(current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_unchanged_after_round_trip() {
+        let dir = tempdir().unwrap();
+        let state_dir = dir.path().join("state");
+        assert_eq!(
+            SourceWatermarks::load(&state_dir).unwrap(),
+            SourceWatermarks::default()
+        );
+
+        let built = NodeWatermark {
+            database: PathBuf::from("dev.duckdb"),
+            target: None,
+            fingerprint: "abc".to_string(),
+            sources: BTreeMap::from([(
+                "source.raw.orders".to_string(),
+                "2024-01-01 10:00:00".to_string(),
+            )]),
+            built_at: Utc::now(),
+        };
+        let mut watermarks = SourceWatermarks::default();
+        watermarks
+            .nodes
+            .insert("model.orders.orders".to_string(), built.clone());
+        watermarks.save(&state_dir).unwrap();
+        let loaded = SourceWatermarks::load(&state_dir).unwrap();
+
+        let later = NodeWatermark {
+            built_at: Utc::now(),
+            ..built.clone()
+        };
+        assert!(loaded.unchanged("model.orders.orders", &later));
+        assert!(!loaded.unchanged("model.customers.customers", &later));
+
+        let mut loaded_again = later.clone();
+        loaded_again.sources.insert(
+            "source.raw.orders".to_string(),
+            "2024-01-02 10:00:00".to_string(),
+        );
+        assert!(!loaded.unchanged("model.orders.orders", &loaded_again));
+        let other_target = NodeWatermark {
+            target: Some("prod".to_string()),
+            ..later
+        };
+        assert!(!loaded.unchanged("model.orders.orders", &other_target));
+    }
+}
@@ -0,0 +1,266 @@
+//! Records of `ff workflow` runs, one JSON file per run under `target/state/workflows/`
+//!
+//! The invocation running a workflow rewrites its record as each step starts and ends.
+//! `ff workflow stop` marks a record stopped; the runner reads the record back before every
+//! step and ends once it sees that. Updates take the state lock so the two never interleave.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::lock::StateLock;
+
+/// Directory inside the state directory holding the run records
+pub const WORKFLOW_RUNS_DIR: &str = "workflows";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowStatus {
+    Pending,
+    Running,
+    Success,
+    Failed,
+    Skipped,
+    Stopped,
+}
+
+impl WorkflowStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkflowStatus::Pending => "pending",
+            WorkflowStatus::Running => "running",
+            WorkflowStatus::Success => "success",
+            WorkflowStatus::Failed => "failed",
+            WorkflowStatus::Skipped => "skipped",
+            WorkflowStatus::Stopped => "stopped",
+        }
+    }
+
+    /// Whether nothing more will happen to the run or step
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, WorkflowStatus::Pending | WorkflowStatus::Running)
+    }
+}
+
+impl fmt::Display for WorkflowStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Progress of one step of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub name: String,
+    pub status: WorkflowStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Error of a failed step, or why it was skipped
+    pub message: Option<String>,
+}
+
+impl StepRecord {
+    pub fn duration_secs(&self) -> Option<f64> {
+        let started_at = self.started_at?;
+        let finished_at = self.finished_at.unwrap_or_else(Utc::now);
+        Some((finished_at - started_at).num_milliseconds() as f64 / 1000.0)
+    }
+}
+
+/// One invocation of a workflow file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: String,
+    pub workflow: String,
+    pub file: PathBuf,
+    /// Process running the workflow
+    pub pid: u32,
+    pub status: WorkflowStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub steps: Vec<StepRecord>,
+}
+
+impl WorkflowRun {
+    /// A new running record with every step pending
+    pub fn start(workflow: &str, file: &Path, steps: Vec<String>) -> Self {
+        let started_at = Utc::now();
+        Self {
+            id: format!(
+                "{}-{}-{}",
+                workflow,
+                started_at.format("%Y%m%dT%H%M%S"),
+                std::process::id()
+            ),
+            workflow: workflow.to_string(),
+            file: file.to_path_buf(),
+            pid: std::process::id(),
+            status: WorkflowStatus::Running,
+            started_at,
+            finished_at: None,
+            steps: steps
+                .into_iter()
+                .map(|name| StepRecord {
+                    name,
+                    status: WorkflowStatus::Pending,
+                    started_at: None,
+                    finished_at: None,
+                    message: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The run records of one project
+pub struct WorkflowRunStore {
+    state_dir: PathBuf,
+    lock_timeout: Duration,
+}
+
+impl WorkflowRunStore {
+    pub fn new(state_dir: &Path, lock_timeout: Duration) -> Self {
+        Self {
+            state_dir: state_dir.to_path_buf(),
+            lock_timeout,
+        }
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.state_dir.join(WORKFLOW_RUNS_DIR)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir().join(format!("{}.json", id))
+    }
+
+    pub fn load(&self, id: &str) -> Result<WorkflowRun> {
+        let path = self.path(id);
+        if !path.is_file() {
+            bail!("No workflow run with id '{}'", id);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Every recorded run, newest first
+    pub fn list(&self) -> Result<Vec<WorkflowRun>> {
+        let dir = self.dir();
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut runs = Vec::new();
+        for entry in
+            fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let Some(id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".json"))
+            else {
+                continue;
+            };
+            runs.push(self.load(id)?);
+        }
+        runs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then(a.id.cmp(&b.id)));
+        Ok(runs)
+    }
+
+    /// Apply `change` to the stored record of `run` under the state lock and return the
+    /// stored result. A record already finished, e.g. stopped by `ff workflow stop`, is left
+    /// as it is.
+    pub fn update(
+        &self,
+        run: &WorkflowRun,
+        change: impl FnOnce(&mut WorkflowRun),
+    ) -> Result<WorkflowRun> {
+        let _lock = StateLock::acquire(&self.state_dir, self.lock_timeout)?;
+        let mut stored = if self.path(&run.id).is_file() {
+            self.load(&run.id)?
+        } else {
+            run.clone()
+        };
+        if stored.status.is_finished() {
+            return Ok(stored);
+        }
+        change(&mut stored);
+        self.write(&stored)?;
+        Ok(stored)
+    }
+
+    /// Mark the run `id` stopped, along with its unfinished steps
+    pub fn stop(&self, id: &str) -> Result<WorkflowRun> {
+        let _lock = StateLock::acquire(&self.state_dir, self.lock_timeout)?;
+        let mut run = self.load(id)?;
+        if run.status.is_finished() {
+            bail!("Workflow run '{}' already finished as {}", id, run.status);
+        }
+
+        let now = Utc::now();
+        run.status = WorkflowStatus::Stopped;
+        run.finished_at = Some(now);
+        for step in run
+            .steps
+            .iter_mut()
+            .filter(|step| !step.status.is_finished())
+        {
+            step.status = WorkflowStatus::Stopped;
+            step.finished_at = step.started_at.map(|_| now);
+        }
+        self.write(&run)?;
+        Ok(run)
+    }
+
+    fn write(&self, run: &WorkflowRun) -> Result<()> {
+        let dir = self.dir();
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let path = self.path(&run.id);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(run)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_stopped_runs_are_not_updated() {
+        let temp_dir = tempdir().unwrap();
+        let store = WorkflowRunStore::new(temp_dir.path(), Duration::from_secs(1));
+        let run = WorkflowRun::start(
+            "nightly",
+            Path::new("nightly.yml"),
+            vec!["build".to_string(), "export".to_string()],
+        );
+
+        let stored = store
+            .update(&run, |run| run.steps[0].status = WorkflowStatus::Running)
+            .unwrap();
+        assert_eq!(stored.steps[0].status, WorkflowStatus::Running);
+        assert_eq!(store.list().unwrap(), vec![stored]);
+
+        let stopped = store.stop(&run.id).unwrap();
+        assert_eq!(stopped.status, WorkflowStatus::Stopped);
+        assert_eq!(stopped.steps[1].status, WorkflowStatus::Stopped);
+        assert!(store.stop(&run.id).is_err());
+
+        let after = store
+            .update(&run, |run| run.status = WorkflowStatus::Success)
+            .unwrap();
+        assert_eq!(after.status, WorkflowStatus::Stopped);
+        assert!(store.load("missing").is_err());
+    }
+}
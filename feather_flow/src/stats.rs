@@ -0,0 +1,137 @@
+//! Phase timings for `--stats`
+//!
+//! `ff parse`, `ff compile` and `ff build` time their phases with a `PhaseTimer` and, given
+//! `--stats`, print a footer with each phase's share of the total so it is clear which one
+//! dominates. `ff build` also records the timings in `run_results.json`.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Wall-clock time of one phase of a command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub seconds: f64,
+}
+
+/// Times consecutive phases: starting one ends the one before
+#[derive(Debug)]
+pub struct PhaseTimer {
+    finished: Vec<(&'static str, Duration)>,
+    current: Option<(&'static str, Instant)>,
+}
+
+impl PhaseTimer {
+    pub fn new() -> Self {
+        Self {
+            finished: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// End the running phase, if any, and start `phase`
+    pub fn phase(&mut self, phase: &'static str) {
+        self.end();
+        self.current = Some((phase, Instant::now()));
+    }
+
+    /// End the running phase
+    pub fn end(&mut self) {
+        if let Some((phase, started)) = self.current.take() {
+            self.record(phase, started.elapsed());
+        }
+    }
+
+    /// Time of every phase, in the order each first ran; a phase run twice is summed
+    pub fn timings(&self) -> Vec<PhaseTiming> {
+        let mut timings: Vec<PhaseTiming> = Vec::new();
+        let running = self
+            .current
+            .map(|(phase, started)| (phase, started.elapsed()));
+        for (phase, duration) in self.finished.iter().copied().chain(running) {
+            match timings.iter_mut().find(|timing| timing.phase == phase) {
+                Some(timing) => timing.seconds += duration.as_secs_f64(),
+                None => timings.push(PhaseTiming {
+                    phase: phase.to_string(),
+                    seconds: duration.as_secs_f64(),
+                }),
+            }
+        }
+        timings
+    }
+
+    /// The footer printed with `--stats`. It goes to stderr, even with `--porcelain`, since
+    /// it was asked for.
+    pub fn print(&self) {
+        eprint!("{}", footer(&self.timings()));
+    }
+
+    fn record(&mut self, phase: &'static str, duration: Duration) {
+        self.finished.push((phase, duration));
+    }
+}
+
+impl Default for PhaseTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn footer(timings: &[PhaseTiming]) -> String {
+    let total: f64 = timings.iter().map(|timing| timing.seconds).sum();
+    let width = timings
+        .iter()
+        .map(|timing| timing.phase.len())
+        .max()
+        .unwrap_or(0)
+        .max("total".len());
+    let mut footer = String::from("\nPhase timings:\n");
+    for timing in timings {
+        let share = if total > 0.0 {
+            timing.seconds / total * 100.0
+        } else {
+            0.0
+        };
+        footer.push_str(&format!(
+            "  {:<width$}  {:>8.3}s  {:>3.0}%\n",
+            timing.phase,
+            timing.seconds,
+            share,
+            width = width
+        ));
+    }
+    footer.push_str(&format!(
+        "  {:<width$}  {:>8.3}s\n",
+        "total",
+        total,
+        width = width
+    ));
+    footer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_phases_are_summed_in_first_run_order() {
+        let mut timer = PhaseTimer::new();
+        timer.record("parse", Duration::from_millis(300));
+        timer.record("graph", Duration::from_millis(100));
+        timer.record("parse", Duration::from_millis(100));
+        let timings = timer.timings();
+        assert_eq!(
+            timings
+                .iter()
+                .map(|timing| timing.phase.as_str())
+                .collect::<Vec<_>>(),
+            vec!["parse", "graph"]
+        );
+        assert!((timings[0].seconds - 0.4).abs() < 1e-9);
+
+        assert_eq!(
+            footer(&timings),
+            "\nPhase timings:\n  parse     0.400s   80%\n  graph     0.100s   20%\n  total     0.500s\n"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Tabular command output in one of three formats
+//!
+//! Commands that list things build a `Table` and print it in the format chosen with
+//! `--format`: `table` aligns the columns for reading, `csv` quotes fields as RFC 4180 and
+//! `json` writes an array with one object per row, keyed by the headers. Only `table` output
+//! is styled, and under `--porcelain` it becomes tab-separated records without the header.
+//! The headers name the columns in CSV and JSON and are part of the output's contract.
+
+use anyhow::{bail, Result};
+use colored::{Color, Colorize};
+use serde_json::{Map, Value};
+
+use crate::display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
+impl TableFormat {
+    pub fn parse(format: &str) -> Result<Self> {
+        match format {
+            "table" => Ok(TableFormat::Table),
+            "csv" => Ok(TableFormat::Csv),
+            "json" => Ok(TableFormat::Json),
+            other => bail!("Unknown output format '{}' (use table, csv or json)", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Plain,
+    Color(Color),
+}
+
+/// A value and how it is styled in `table` output
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    text: String,
+    style: Style,
+}
+
+impl Cell {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            style: Style::Plain,
+        }
+    }
+
+    pub fn colored(text: impl Into<String>, color: Color) -> Self {
+        Self {
+            text: text.into(),
+            style: Style::Color(color),
+        }
+    }
+
+    fn padded(&self, width: usize) -> String {
+        let padding = " ".repeat(width.saturating_sub(self.text.chars().count()));
+        let text = match self.style {
+            Style::Plain => self.text.clone(),
+            Style::Color(color) => self.text.color(color).to_string(),
+        };
+        format!("{}{}", text, padding)
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::new(text)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::new(text)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+    /// Print the header as the first `--porcelain` record, for columns known only at runtime
+    porcelain_headers: bool,
+}
+
+impl Table {
+    pub fn new<S: Into<String>>(headers: impl IntoIterator<Item = S>) -> Self {
+        let headers: Vec<String> = headers.into_iter().map(Into::into).collect();
+        Self {
+            headers,
+            rows: Vec::new(),
+            porcelain_headers: false,
+        }
+    }
+
+    pub fn with_porcelain_headers(mut self) -> Self {
+        self.porcelain_headers = true;
+        self
+    }
+
+    /// Add a row; missing trailing cells are empty and extra ones are dropped
+    pub fn push<C: Into<Cell>>(&mut self, row: impl IntoIterator<Item = C>) {
+        let mut row: Vec<Cell> = row.into_iter().map(Into::into).collect();
+        row.resize(self.headers.len(), Cell::new(""));
+        self.rows.push(row);
+    }
+
+    pub fn print(&self, format: TableFormat) -> Result<()> {
+        print!("{}", self.render(format)?);
+        Ok(())
+    }
+
+    pub fn render(&self, format: TableFormat) -> Result<String> {
+        match format {
+            TableFormat::Table if display::porcelain() => Ok(self.render_records()),
+            TableFormat::Table => Ok(self.render_table()),
+            TableFormat::Csv => self.render_csv(),
+            TableFormat::Json => Ok(serde_json::to_string_pretty(&self.json())? + "\n"),
+        }
+    }
+
+    fn render_table(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.text.chars().count());
+            }
+        }
+
+        // Padding the last column would only add trailing spaces
+        if let Some(width) = widths.last_mut() {
+            *width = 0;
+        }
+
+        let line = |cells: Vec<String>| cells.join("  ") + "\n";
+        let mut output = line(
+            self.headers
+                .iter()
+                .zip(&widths)
+                .map(|(header, width)| Cell::new(header.as_str()).padded(*width))
+                .map(|header| header.bold().to_string())
+                .collect(),
+        );
+        for row in &self.rows {
+            output.push_str(&line(
+                row.iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| cell.padded(*width))
+                    .collect(),
+            ));
+        }
+        output
+    }
+
+    fn render_records(&self) -> String {
+        let mut records = Vec::new();
+        if self.porcelain_headers {
+            records.push(display::format_record(
+                &self.headers.iter().map(String::as_str).collect::<Vec<_>>(),
+            ));
+        }
+        for row in &self.rows {
+            records.push(display::format_record(
+                &row.iter()
+                    .map(|cell| cell.text.as_str())
+                    .collect::<Vec<_>>(),
+            ));
+        }
+        records
+            .iter()
+            .map(|record| format!("{}\n", record))
+            .collect()
+    }
+
+    fn render_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.headers)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(|cell| cell.text.as_str()))?;
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    fn json(&self) -> Value {
+        Value::Array(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Value> = self
+                        .headers
+                        .iter()
+                        .zip(row)
+                        .map(|(header, cell)| (header.clone(), Value::String(cell.text.clone())))
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Table {
+        let mut table = Table::new(["model", "rows"]);
+        table.push([Cell::new("orders"), Cell::colored("12", Color::Green)]);
+        table.push(["customers, \"vip\"", "3"]);
+        table
+    }
+
+    fn plain(text: String) -> String {
+        regex::Regex::new("\x1b\\[[0-9;]*m")
+            .unwrap()
+            .replace_all(&text, "")
+            .to_string()
+    }
+
+    #[test]
+    fn test_table_aligns_columns() {
+        assert_eq!(
+            plain(sample().render(TableFormat::Table).unwrap()),
+            "model             rows\norders            12\ncustomers, \"vip\"  3\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_and_json_keep_values_parseable() {
+        assert_eq!(
+            sample().render(TableFormat::Csv).unwrap(),
+            "model,rows\norders,12\n\"customers, \"\"vip\"\"\",3\n"
+        );
+        let json: Value =
+            serde_json::from_str(&sample().render(TableFormat::Json).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"model": "orders", "rows": "12"},
+                {"model": "customers, \"vip\"", "rows": "3"},
+            ])
+        );
+        assert!(TableFormat::parse("xml").is_err());
+    }
+}
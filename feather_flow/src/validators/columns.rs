@@ -0,0 +1,372 @@
+//! Columns a model's YAML declares against the columns its SQL selects
+//!
+//! `ff validate --check-columns` reports every column documented in a model's YAML that its
+//! SQL does not select, and every selected column the YAML does not document. Names compare
+//! case-insensitively. Where part of a model's output cannot be named (see
+//! `sql_engine::projection`), documented columns are given the benefit of the doubt and not
+//! reported as unselected. Once `ff sync-schemas` has recorded a model's relation, columns
+//! whose YAML `data_type` differs from the warehouse's are reported too. So are columns the
+//! YAML tests `not_null` that the SQL provably lets NULLs into, e.g. through a LEFT JOIN (see
+//! `sql_engine::nullability`).
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::snapshots::{VALID_FROM_COLUMN, VALID_TO_COLUMN};
+use crate::error::codes;
+use crate::run::contract;
+use crate::sql_engine::nodes::{SNAPSHOT_AT_COLUMN, SNAPSHOT_MATERIALIZATION};
+use crate::sql_engine::nullability::{NullReason, NOT_NULL_TEST};
+use crate::sql_engine::projection::Projector;
+use crate::sql_engine::sql_model::{ColumnInfo, SqlModel, SqlModelCollection};
+use crate::yaml_edit::{Step, YamlDocument};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnMismatchKind {
+    /// Documented in YAML, not selected by the SQL
+    NotSelected,
+    /// Selected by the SQL, not documented in YAML
+    Undocumented,
+    /// Documented with a data type other than the synced warehouse schema's
+    TypeMismatch,
+    /// Tested `not_null`, but the SQL can produce NULLs in it
+    Nullable,
+}
+
+impl ColumnMismatchKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            ColumnMismatchKind::NotSelected => codes::UNSELECTED_COLUMN,
+            ColumnMismatchKind::Undocumented => codes::UNDOCUMENTED_COLUMN,
+            ColumnMismatchKind::TypeMismatch => codes::COLUMN_TYPE_MISMATCH,
+            ColumnMismatchKind::Nullable => codes::NULLABLE_NOT_NULL_COLUMN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnMismatch {
+    pub kind: ColumnMismatchKind,
+    /// Unique id of the model
+    pub model: String,
+    pub column: String,
+    /// The model's YAML file, or its SQL file when it has no YAML
+    pub path: PathBuf,
+    /// Line of the column's YAML entry, counting from 1
+    pub line: Option<usize>,
+    /// Declared and warehouse data types, for a `TypeMismatch`
+    pub types: Option<(String, String)>,
+    /// Why the column can be NULL, for a `Nullable`
+    pub null_reason: Option<NullReason>,
+}
+
+impl ColumnMismatch {
+    pub fn message(&self) -> String {
+        match self.kind {
+            ColumnMismatchKind::NotSelected => format!(
+                "Column '{}' is documented for {} but not selected by its SQL",
+                self.column, self.model
+            ),
+            ColumnMismatchKind::Undocumented => format!(
+                "Column '{}' is selected by {} but not documented in its YAML",
+                self.column, self.model
+            ),
+            ColumnMismatchKind::TypeMismatch => {
+                let (declared, actual) = self.types.clone().unwrap_or_default();
+                format!(
+                    "Column '{}' of {} is declared as {} but is {} in the warehouse",
+                    self.column, self.model, declared, actual
+                )
+            }
+            ColumnMismatchKind::Nullable => match &self.null_reason {
+                Some(reason) => format!(
+                    "Column '{}' of {} is tested not_null but can be NULL: it {}",
+                    self.column, self.model, reason
+                ),
+                None => format!(
+                    "Column '{}' of {} is tested not_null but can be NULL",
+                    self.column, self.model
+                ),
+            },
+        }
+    }
+}
+
+/// Every mismatch of every model and snapshot, by model unique id
+pub fn check_columns(collection: &SqlModelCollection) -> Vec<ColumnMismatch> {
+    let mut projector = Projector::new(collection);
+    let mut mismatches = Vec::new();
+    for model in collection.models_by_id() {
+        let Some(projection) = projector.model(&model.unique_id) else {
+            continue;
+        };
+        let yaml_path = model.parent_dir.join(format!("{}.yml", model.name));
+        let document = fs::read_to_string(&yaml_path)
+            .ok()
+            .and_then(|text| YamlDocument::parse(&text).ok());
+        let path = if document.is_some() {
+            yaml_path
+        } else {
+            model.fully_qualified_file_path.clone()
+        };
+
+        let mut selected: HashSet<String> = projection
+            .columns
+            .iter()
+            .map(|column| column.to_lowercase())
+            .collect();
+        if model.materialized.as_deref() == Some(SNAPSHOT_MATERIALIZATION) {
+            match model.snapshot {
+                Some(_) => {
+                    selected.extend([VALID_FROM_COLUMN.to_string(), VALID_TO_COLUMN.to_string()])
+                }
+                None => {
+                    selected.insert(SNAPSHOT_AT_COLUMN.to_string());
+                }
+            }
+        }
+        let documented: HashSet<String> = model
+            .columns
+            .keys()
+            .map(|column| column.to_lowercase())
+            .collect();
+
+        if projection.complete {
+            let mut unselected: Vec<&String> = model
+                .columns
+                .keys()
+                .filter(|column| !selected.contains(&column.to_lowercase()))
+                .collect();
+            unselected.sort();
+            for column in unselected {
+                mismatches.push(ColumnMismatch {
+                    kind: ColumnMismatchKind::NotSelected,
+                    model: model.unique_id.clone(),
+                    column: column.clone(),
+                    path: path.clone(),
+                    line: document
+                        .as_ref()
+                        .and_then(|document| document.line(&column_path(model, column))),
+                    types: None,
+                    null_reason: None,
+                });
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for column in &projection.columns {
+            let key = column.to_lowercase();
+            if documented.contains(&key) || !seen.insert(key) {
+                continue;
+            }
+            mismatches.push(ColumnMismatch {
+                kind: ColumnMismatchKind::Undocumented,
+                model: model.unique_id.clone(),
+                column: column.clone(),
+                path: path.clone(),
+                line: None,
+                types: None,
+                null_reason: None,
+            });
+        }
+
+        let mut not_null: Vec<&ColumnInfo> = model
+            .columns
+            .values()
+            .filter(|column| column.tests.iter().any(|test| test == NOT_NULL_TEST))
+            .collect();
+        not_null.sort_by(|a, b| a.name.cmp(&b.name));
+        for column in not_null {
+            let Some(reason) = projection.nullable.get(&column.name.to_lowercase()) else {
+                continue;
+            };
+            mismatches.push(ColumnMismatch {
+                kind: ColumnMismatchKind::Nullable,
+                model: model.unique_id.clone(),
+                column: column.name.clone(),
+                path: path.clone(),
+                line: document
+                    .as_ref()
+                    .and_then(|document| document.line(&column_path(model, &column.name))),
+                types: None,
+                null_reason: Some(reason.clone()),
+            });
+        }
+
+        let Some(synced) = collection
+            .table_schemas()
+            .get_schema(&model.qualified_relation())
+        else {
+            continue;
+        };
+        let mut typed: Vec<&ColumnInfo> = model
+            .columns
+            .values()
+            .filter(|column| column.data_type.is_some())
+            .collect();
+        typed.sort_by(|a, b| a.name.cmp(&b.name));
+        for column in typed {
+            let declared = column.data_type.clone().unwrap_or_default();
+            let Some(actual) = synced
+                .columns
+                .iter()
+                .find(|synced| synced.name.eq_ignore_ascii_case(&column.name))
+            else {
+                continue;
+            };
+            if contract::normalize_type(&declared) == contract::normalize_type(&actual.data_type) {
+                continue;
+            }
+            mismatches.push(ColumnMismatch {
+                kind: ColumnMismatchKind::TypeMismatch,
+                model: model.unique_id.clone(),
+                column: column.name.clone(),
+                path: path.clone(),
+                line: document
+                    .as_ref()
+                    .and_then(|document| document.line(&column_path(model, &column.name))),
+                types: Some((declared, actual.data_type.clone())),
+                null_reason: None,
+            });
+        }
+    }
+    mismatches
+}
+
+fn column_path<'a>(model: &'a SqlModel, column: &'a str) -> [Step<'a>; 4] {
+    [
+        Step::Key("models"),
+        Step::Named(&model.name),
+        Step::Key("columns"),
+        Step::Named(column),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use crate::sql_engine::tables::{ColumnDef, TableManager, TableSchema};
+    use sqlparser::dialect::DuckDbDialect;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reports_unselected_and_undocumented_columns() {
+        let dir = tempdir().unwrap();
+        let model_dir = dir.path().join("orders");
+        fs::create_dir_all(&model_dir).unwrap();
+        let file = model_dir.join("orders.sql");
+        fs::write(&file, "SELECT id, Amount, status AS state FROM raw.orders").unwrap();
+        fs::write(
+            model_dir.join("orders.yml"),
+            "version: 2\nmodels:\n  - name: orders\n    columns:\n      - name: id\n      \
+             - name: amount\n      - name: status\n",
+        )
+        .unwrap();
+        let model =
+            SqlModel::from_path(&file, dir.path(), "duckdb", &DuckDbDialect {}, &Sha256Hash)
+                .unwrap();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model);
+        collection.build_dependency_graph();
+
+        let mismatches = check_columns(&collection);
+        let found: Vec<(ColumnMismatchKind, &str, Option<usize>)> = mismatches
+            .iter()
+            .map(|mismatch| (mismatch.kind, mismatch.column.as_str(), mismatch.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (ColumnMismatchKind::NotSelected, "status", Some(7)),
+                (ColumnMismatchKind::Undocumented, "state", None),
+            ]
+        );
+        assert_eq!(
+            mismatches[0].message(),
+            "Column 'status' is documented for model.orders.orders but not selected by its SQL"
+        );
+        assert_eq!(mismatches[1].kind.code(), "FF031");
+    }
+
+    #[test]
+    fn test_reports_types_differing_from_synced_schema() {
+        let dir = tempdir().unwrap();
+        let model_dir = dir.path().join("orders");
+        fs::create_dir_all(&model_dir).unwrap();
+        let file = model_dir.join("orders.sql");
+        fs::write(&file, "SELECT * FROM raw.orders").unwrap();
+        fs::write(
+            model_dir.join("orders.yml"),
+            "version: 2\nmodels:\n  - name: orders\n    columns:\n      - name: id\n        \
+             data_type: int\n      - name: amount\n        data_type: integer\n",
+        )
+        .unwrap();
+        let model =
+            SqlModel::from_path(&file, dir.path(), "duckdb", &DuckDbDialect {}, &Sha256Hash)
+                .unwrap();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model);
+        let mut tables = TableManager::new();
+        tables.register_schema(TableSchema {
+            name: "public.orders".to_string(),
+            columns: [("ID", "INTEGER"), ("amount", "DECIMAL(18,2)")]
+                .into_iter()
+                .map(|(name, data_type)| ColumnDef {
+                    name: name.to_string(),
+                    data_type: data_type.to_string(),
+                    nullable: true,
+                })
+                .collect(),
+        });
+        collection.set_table_schemas(tables);
+        collection.build_dependency_graph();
+
+        let mismatches = check_columns(&collection);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].kind, ColumnMismatchKind::TypeMismatch);
+        assert_eq!(mismatches[0].line, Some(7));
+        assert_eq!(
+            mismatches[0].message(),
+            "Column 'amount' of model.orders.orders is declared as integer but is DECIMAL(18,2) in the warehouse"
+        );
+    }
+
+    #[test]
+    fn test_reports_not_null_columns_the_sql_can_leave_null() {
+        let dir = tempdir().unwrap();
+        let model_dir = dir.path().join("orders");
+        fs::create_dir_all(&model_dir).unwrap();
+        let file = model_dir.join("orders.sql");
+        fs::write(
+            &file,
+            "SELECT o.id, c.name AS customer, coalesce(c.region, 'none') AS region\n\
+             FROM raw.orders AS o LEFT JOIN raw.customers AS c ON o.customer_id = c.id",
+        )
+        .unwrap();
+        fs::write(
+            model_dir.join("orders.yml"),
+            "version: 2\nmodels:\n  - name: orders\n    columns:\n      - name: id\n        \
+             tests: [not_null]\n      - name: customer\n        tests: [not_null]\n      \
+             - name: region\n        tests: [not_null]\n",
+        )
+        .unwrap();
+        let model =
+            SqlModel::from_path(&file, dir.path(), "duckdb", &DuckDbDialect {}, &Sha256Hash)
+                .unwrap();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(model);
+        collection.build_dependency_graph();
+
+        let mismatches = check_columns(&collection);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].kind.code(), "FF033");
+        assert_eq!(mismatches[0].line, Some(7));
+        assert_eq!(
+            mismatches[0].message(),
+            "Column 'customer' of model.orders.orders is tested not_null but can be NULL: it reads c, \
+             which an outer join fills with NULLs for rows without a match"
+        );
+    }
+}
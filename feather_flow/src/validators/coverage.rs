@@ -0,0 +1,198 @@
+//! Share of models with at least one test, against the project's `require_tests` thresholds
+//!
+//! ```yaml
+//! require_tests:
+//!   marts: 0.8       # 80% of the models under marts/ need a test
+//!   staging: 0.5
+//!   .: 0.25          # every model in the project
+//! ```
+//!
+//! Directories are relative to the model path. A model is tested when any of its columns has
+//! a test, as for a policy's `require: [tests]`. Snapshots count as models.
+
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::policies;
+use crate::config::policies::Requirement;
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+
+/// A model without any test
+#[derive(Debug, Clone, PartialEq)]
+pub struct UntestedModel {
+    pub model_id: String,
+    /// Model file, relative to the model path
+    pub path: PathBuf,
+}
+
+/// Test coverage of the models under one `require_tests` directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCoverage {
+    pub path: String,
+    /// Least share of tested models, from 0 to 1
+    pub required: f64,
+    /// Number of models under `path`
+    pub models: usize,
+    /// Models under `path` without a test, by unique id
+    pub untested: Vec<UntestedModel>,
+}
+
+impl TestCoverage {
+    pub fn tested(&self) -> usize {
+        self.models - self.untested.len()
+    }
+
+    /// Share of tested models; a directory without models is fully covered
+    pub fn coverage(&self) -> f64 {
+        if self.models == 0 {
+            return 1.0;
+        }
+        self.tested() as f64 / self.models as f64
+    }
+
+    /// How many untested models need a test to reach the threshold
+    pub fn missing(&self) -> usize {
+        // Rounded so that e.g. 0.7 of 10 models asks for 7, not 8, despite float error
+        let needed = (self.required * self.models as f64 - 1e-9).ceil() as usize;
+        needed.saturating_sub(self.tested())
+    }
+
+    pub fn passes(&self) -> bool {
+        self.missing() == 0
+    }
+
+    /// What a failing directory is reported with
+    pub fn message(&self) -> String {
+        format!(
+            "Test coverage of {} is {:.0}% ({} of {} models), below the required {:.0}%; add tests to {} more of: {}",
+            self.path,
+            self.coverage() * 100.0,
+            self.tested(),
+            self.models,
+            self.required * 100.0,
+            self.missing(),
+            self.untested
+                .iter()
+                .map(|model| model.model_id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+/// Coverage of every `require_tests` directory, in path order
+pub fn evaluate(
+    require_tests: &BTreeMap<String, f64>,
+    models: &SqlModelCollection,
+) -> Result<Vec<TestCoverage>> {
+    let sorted = models.models_by_id();
+    require_tests
+        .iter()
+        .map(|(path, &required)| {
+            if !(0.0..=1.0).contains(&required) {
+                bail!(
+                    "require_tests.{} must be between 0 and 1, got {}",
+                    path,
+                    required
+                );
+            }
+            let under: Vec<&&SqlModel> = sorted
+                .iter()
+                .filter(|model| is_under(&model.relative_file_path, path))
+                .collect();
+            Ok(TestCoverage {
+                path: path.clone(),
+                required,
+                models: under.len(),
+                untested: under
+                    .into_iter()
+                    .filter(|model| !policies::meets(model, Requirement::Tests))
+                    .map(|model| UntestedModel {
+                        model_id: model.unique_id.clone(),
+                        path: model.relative_file_path.clone(),
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn is_under(relative_file_path: &Path, dir: &str) -> bool {
+    matches!(dir, "" | ".") || relative_file_path.starts_with(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::checksum::Sha256Hash;
+    use sqlparser::dialect::DuckDbDialect;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_coverage_per_directory() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let mut collection = SqlModelCollection::new();
+        for (path, tested) in [
+            ("marts/fct_orders", true),
+            ("marts/fct_payments", false),
+            ("marts/dim_customers", true),
+            ("staging/stg_orders", false),
+        ] {
+            let model_dir = root.join(path);
+            fs::create_dir_all(&model_dir).unwrap();
+            let name = model_dir.file_name().unwrap().to_string_lossy().to_string();
+            let file = model_dir.join(format!("{}.sql", name));
+            fs::write(&file, "SELECT 1 AS id").unwrap();
+            let tests = if tested {
+                "\n        tests: [unique]"
+            } else {
+                ""
+            };
+            fs::write(
+                model_dir.join(format!("{}.yml", name)),
+                format!(
+                    "version: 2\nmodels:\n  - name: {}\n    columns:\n      - name: id{}\n",
+                    name, tests
+                ),
+            )
+            .unwrap();
+            collection.add_model(
+                SqlModel::from_path(&file, root, "duckdb", &DuckDbDialect {}, &Sha256Hash).unwrap(),
+            );
+        }
+
+        let require = BTreeMap::from([
+            (".".to_string(), 0.5),
+            ("marts".to_string(), 0.8),
+            ("seeds".to_string(), 1.0),
+        ]);
+        let coverage = evaluate(&require, &collection).unwrap();
+        let summary: Vec<(&str, usize, usize, bool)> = coverage
+            .iter()
+            .map(|c| (c.path.as_str(), c.tested(), c.models, c.passes()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (".", 2, 4, true),
+                ("marts", 2, 3, false),
+                ("seeds", 0, 0, true),
+            ]
+        );
+        assert_eq!(coverage[1].missing(), 1);
+        assert_eq!(
+            coverage[1].message(),
+            "Test coverage of marts is 67% (2 of 3 models), below the required 80%; add tests to 1 more of: model.marts.fct_payments.fct_payments"
+        );
+
+        let err =
+            evaluate(&BTreeMap::from([("marts".to_string(), 80.0)]), &collection).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "require_tests.marts must be between 0 and 1, got 80"
+        );
+    }
+}
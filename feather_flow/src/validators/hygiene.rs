@@ -0,0 +1,255 @@
+//! Directory hygiene: YAML and directories left behind once they stop matching any model
+//!
+//! A model's metadata is only read from the `models:` entry named after it in its own
+//! `<model>/<model>.yml`. Entries anywhere else are silently ignored by `ff parse`, as are
+//! directories emptied by deleting a model.
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::error::codes;
+
+/// Kinds of leftovers reported by the hygiene check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HygieneKind {
+    /// A YAML entry naming a model that has no SQL anywhere in the project
+    OrphanedYamlEntry,
+    /// A YAML entry for an existing model, outside the file its metadata is read from
+    UnboundYamlModel,
+    /// A directory without any files left in it
+    EmptyDirectory,
+}
+
+impl HygieneKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            HygieneKind::OrphanedYamlEntry => "Orphaned YAML entry",
+            HygieneKind::UnboundYamlModel => "YAML model never bound to SQL",
+            HygieneKind::EmptyDirectory => "Empty model directory",
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            HygieneKind::OrphanedYamlEntry => codes::ORPHANED_YAML_ENTRY,
+            HygieneKind::UnboundYamlModel => codes::UNBOUND_YAML_MODEL,
+            HygieneKind::EmptyDirectory => codes::EMPTY_DIRECTORY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HygieneIssue {
+    pub kind: HygieneKind,
+    pub path: PathBuf,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct ModelsYaml {
+    models: Option<Vec<ModelsYamlEntry>>,
+}
+
+#[derive(Deserialize)]
+struct ModelsYamlEntry {
+    name: String,
+}
+
+/// Every orphaned YAML entry, unbound YAML model and empty directory under `models_dir`
+pub fn check_models_directory(models_dir: &Path) -> Vec<HygieneIssue> {
+    let mut issues = Vec::new();
+    if !models_dir.is_dir() {
+        return issues;
+    }
+
+    let files: Vec<PathBuf> = WalkDir::new(models_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && !is_imports_path(path))
+        .collect();
+
+    let model_names: HashSet<String> = files
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .filter_map(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .collect();
+
+    for yaml_path in files
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yml"))
+    {
+        check_yaml_file(yaml_path, &model_names, &mut issues);
+    }
+
+    collect_empty_directories(models_dir, true, &mut issues);
+    issues
+}
+
+fn check_yaml_file(
+    yaml_path: &Path,
+    model_names: &HashSet<String>,
+    issues: &mut Vec<HygieneIssue>,
+) {
+    let Ok(content) = fs::read_to_string(yaml_path) else {
+        return;
+    };
+    // Malformed YAML is reported by `ff parse`
+    let Ok(yaml) = serde_yaml::from_str::<ModelsYaml>(&content) else {
+        return;
+    };
+
+    let stem = yaml_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let has_sibling_sql = yaml_path.with_extension("sql").is_file();
+
+    for entry in yaml.models.unwrap_or_default() {
+        if entry.name == stem && has_sibling_sql {
+            continue;
+        }
+
+        let issue = if model_names.contains(&entry.name) {
+            HygieneIssue {
+                kind: HygieneKind::UnboundYamlModel,
+                path: yaml_path.to_path_buf(),
+                message: format!(
+                    "'{}' is documented here, but its metadata is only read from {}/{}.yml",
+                    entry.name, entry.name, entry.name
+                ),
+            }
+        } else {
+            HygieneIssue {
+                kind: HygieneKind::OrphanedYamlEntry,
+                path: yaml_path.to_path_buf(),
+                message: format!("'{}' does not match any SQL model", entry.name),
+            }
+        };
+        issues.push(issue);
+    }
+}
+
+/// Whether `dir` holds any file. Empty subdirectories of a directory that does are reported;
+/// an entirely empty tree is reported once, at its top.
+fn collect_empty_directories(dir: &Path, is_root: bool, issues: &mut Vec<HygieneIssue>) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return true;
+    };
+
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    let mut has_files = false;
+    let mut empty_children = Vec::new();
+    for path in paths {
+        if !path.is_dir() || collect_empty_directories(&path, false, issues) {
+            has_files = true;
+        } else {
+            empty_children.push(path);
+        }
+    }
+
+    if has_files || is_root {
+        for path in empty_children {
+            issues.push(HygieneIssue {
+                kind: HygieneKind::EmptyDirectory,
+                path,
+                message: "directory contains no files".to_string(),
+            });
+        }
+    }
+
+    has_files
+}
+
+fn is_imports_path(path: &Path) -> bool {
+    path.components()
+        .any(|component| component.as_os_str() == "imports")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, relative: &str, content: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_reports_orphaned_and_unbound_yaml_entries() {
+        let temp_dir = tempdir().unwrap();
+        let models = temp_dir.path();
+        write(models, "staging/stg_orders/stg_orders.sql", "SELECT 1");
+        write(
+            models,
+            "staging/stg_orders/stg_orders.yml",
+            "version: 2\nmodels:\n  - name: stg_orders\n  - name: stg_payments\n",
+        );
+        write(models, "staging/stg_payments/stg_payments.sql", "SELECT 1");
+        write(
+            models,
+            "staging/stg_payments/stg_payments.yml",
+            "version: 2\nmodels:\n  - name: stg_payments\n",
+        );
+        write(
+            models,
+            "staging/stg_refunds/stg_refunds.yml",
+            "version: 2\nmodels:\n  - name: stg_refunds\n",
+        );
+
+        let issues = check_models_directory(models);
+        let kinds: Vec<(HygieneKind, &str)> = issues
+            .iter()
+            .map(|issue| (issue.kind, issue.message.as_str()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    HygieneKind::UnboundYamlModel,
+                    "'stg_payments' is documented here, but its metadata is only read from stg_payments/stg_payments.yml"
+                ),
+                (
+                    HygieneKind::OrphanedYamlEntry,
+                    "'stg_refunds' does not match any SQL model"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_topmost_empty_directory() {
+        let temp_dir = tempdir().unwrap();
+        let models = temp_dir.path();
+        write(models, "marts/orders/orders.sql", "SELECT 1");
+        write(
+            models,
+            "marts/orders/orders.yml",
+            "version: 2\nmodels:\n  - name: orders\n",
+        );
+        fs::create_dir_all(models.join("marts/old_orders/archive")).unwrap();
+        fs::create_dir_all(models.join("legacy/customers")).unwrap();
+
+        let issues = check_models_directory(models);
+        let paths: Vec<PathBuf> = issues
+            .iter()
+            .filter(|issue| issue.kind == HygieneKind::EmptyDirectory)
+            .map(|issue| issue.path.strip_prefix(models).unwrap().to_path_buf())
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("marts/old_orders"), PathBuf::from("legacy")]
+        );
+    }
+}
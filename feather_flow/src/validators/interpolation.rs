@@ -0,0 +1,100 @@
+//! Raw interpolation: template expressions writing untrusted values into model SQL unquoted
+//!
+//! `{{ var('name') }}` and `{{ env_var('NAME') }}` render their values as is. That is safe for
+//! dates, integers, lists and strings limited to `allowed_values`, which are checked before
+//! compiling, but an environment variable or a free-form string variable can hold any text,
+//! quotes included. Those belong in `literal()` or `identifier()`, which escape them for the
+//! project's dialect.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::vars::VarDefinition;
+use crate::sql_engine::template::{self, RawInterpolation};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterpolationIssue {
+    /// The model's SQL file
+    pub path: PathBuf,
+    pub raw: RawInterpolation,
+}
+
+impl InterpolationIssue {
+    pub fn message(&self) -> String {
+        let kind = if self.raw.value.starts_with("env_var") {
+            "an environment variable"
+        } else {
+            "a free-form string variable"
+        };
+        format!(
+            "{{{{ {} }}}} writes {}, {}, into the SQL unquoted; wrap it in literal() or identifier()",
+            self.raw.expression, self.raw.value, kind
+        )
+    }
+}
+
+/// Every raw interpolation of an environment variable or free-form string variable in the
+/// SQL files under `models_dir`
+pub fn check_models_directory(
+    models_dir: &Path,
+    vars: &[VarDefinition],
+) -> Vec<InterpolationIssue> {
+    let untrusted = |name: &str| {
+        vars.iter()
+            .any(|var| var.name == name && var.is_free_form())
+    };
+    WalkDir::new(models_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "sql"))
+        .flat_map(|path| {
+            // Unreadable files are reported by `ff parse`
+            let sql = fs::read_to_string(&path).unwrap_or_default();
+            template::raw_interpolations(&sql, untrusted)
+                .into_iter()
+                .map(move |raw| InterpolationIssue {
+                    path: path.clone(),
+                    raw,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_flags_untrusted_values_only() {
+        let dir = tempdir().unwrap();
+        let model_dir = dir.path().join("orders");
+        fs::create_dir(&model_dir).unwrap();
+        fs::write(
+            model_dir.join("orders.sql"),
+            "SELECT * FROM raw.orders\n\
+             WHERE region = '{{ var('region') }}'\n\
+             AND customer = '{{ var('customer') }}'\n\
+             AND channel = {{ literal(env_var('CHANNEL', 'web')) }}",
+        )
+        .unwrap();
+        let vars: Vec<VarDefinition> = serde_yaml::from_str(
+            "- {name: region, type: string, allowed_values: [us, eu]}\n\
+             - {name: customer, type: string}",
+        )
+        .unwrap();
+
+        let issues = check_models_directory(dir.path(), &vars);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, model_dir.join("orders.sql"));
+        assert_eq!(issues[0].raw.line, 3);
+        assert_eq!(
+            issues[0].message(),
+            "{{ var('customer') }} writes var('customer'), a free-form string variable, into the SQL unquoted; wrap it in literal() or identifier()"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Model `meta` checked against the built-in keys and the project's `meta_schema:`
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::config::meta::{self, MetaKeyConfig};
+
+struct CompiledKey {
+    config: MetaKeyConfig,
+    pattern: Option<Regex>,
+}
+
+pub struct MetaSchema {
+    keys: BTreeMap<String, CompiledKey>,
+}
+
+impl MetaSchema {
+    /// The built-in keys with the project's declarations on top
+    pub fn compile(declared: &BTreeMap<String, MetaKeyConfig>) -> Result<Self> {
+        let mut keys: BTreeMap<String, MetaKeyConfig> = meta::built_in_keys()
+            .into_iter()
+            .map(|(key, config)| (key.to_string(), config))
+            .collect();
+
+        for (key, config) in declared {
+            if let Some(built_in) = keys.get(key) {
+                if built_in.kind != config.kind {
+                    bail!(
+                        "meta_schema: '{}' is a built-in {} key and cannot be declared as {}",
+                        key,
+                        built_in.kind.as_str(),
+                        config.kind.as_str()
+                    );
+                }
+                if let Some(value) = config
+                    .values
+                    .iter()
+                    .find(|value| !built_in.values.is_empty() && !built_in.values.contains(value))
+                {
+                    bail!(
+                        "meta_schema: '{}' cannot be '{}'; it is one of {}",
+                        key,
+                        value,
+                        built_in.values.join(", ")
+                    );
+                }
+            }
+            if !config.values.is_empty() && config.kind != meta::MetaType::String {
+                bail!("meta_schema: 'values' of '{}' needs type string", key);
+            }
+            let mut config = config.clone();
+            if config.values.is_empty() {
+                if let Some(built_in) = keys.get(key) {
+                    config.values = built_in.values.clone();
+                }
+            }
+            keys.insert(key.clone(), config);
+        }
+
+        let keys = keys
+            .into_iter()
+            .map(|(key, config)| {
+                let pattern = config
+                    .pattern
+                    .as_deref()
+                    .map(|pattern| {
+                        Regex::new(pattern).with_context(|| {
+                            format!("Invalid pattern '{}' in meta_schema for '{}'", pattern, key)
+                        })
+                    })
+                    .transpose()?;
+                Ok((key, CompiledKey { config, pattern }))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { keys })
+    }
+
+    /// Problems with one model's `meta`
+    pub fn check(&self, meta: &HashMap<String, Value>) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (key, compiled) in &self.keys {
+            let config = &compiled.config;
+            let value = match meta.get(key) {
+                None | Some(Value::Null) => {
+                    if config.required {
+                        problems.push(format!("meta.{} is required", key));
+                    }
+                    continue;
+                }
+                Some(value) => value,
+            };
+
+            if !config.kind.accepts(value) {
+                problems.push(format!(
+                    "meta.{} should be of type {}, not {}",
+                    key,
+                    config.kind.as_str(),
+                    value
+                ));
+                continue;
+            }
+            let Some(text) = value.as_str() else {
+                continue;
+            };
+            if !config.values.is_empty() && !config.values.iter().any(|allowed| allowed == text) {
+                problems.push(format!(
+                    "meta.{} is '{}', expected one of {}",
+                    key,
+                    text,
+                    config.values.join(", ")
+                ));
+            }
+            if let Some(pattern) = &compiled.pattern {
+                if !pattern.is_match(text) {
+                    problems.push(format!(
+                        "meta.{} '{}' does not match {}",
+                        key,
+                        text,
+                        pattern.as_str()
+                    ));
+                }
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn meta(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_built_in_and_declared_keys() {
+        let declared: BTreeMap<String, MetaKeyConfig> = serde_yaml::from_str(
+            "owner: {type: string, required: true}\n\
+             slack_channel: {type: string, pattern: '^#'}\n\
+             cost_center: {type: integer}\n",
+        )
+        .unwrap();
+        let schema = MetaSchema::compile(&declared).unwrap();
+
+        assert!(schema
+            .check(&meta(json!({"owner": "finance", "criticality": "p1", "slack_channel": "#fin", "cost_center": 12, "extra": [1]})))
+            .is_empty());
+        assert_eq!(
+            schema.check(&meta(json!({"criticality": "urgent", "slack_channel": "fin", "cost_center": "12", "team": 3}))),
+            vec![
+                "meta.cost_center should be of type integer, not \"12\"",
+                "meta.criticality is 'urgent', expected one of p1, p2, p3",
+                "meta.owner is required",
+                "meta.slack_channel 'fin' does not match ^#",
+                "meta.team should be of type string, not 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_built_in_keys_keep_their_types() {
+        let declared: BTreeMap<String, MetaKeyConfig> =
+            serde_yaml::from_str("owner: {type: list}\n").unwrap();
+        assert!(MetaSchema::compile(&declared).is_err());
+
+        let declared: BTreeMap<String, MetaKeyConfig> =
+            serde_yaml::from_str("criticality: {type: string, values: [p1, p4]}\n").unwrap();
+        assert!(MetaSchema::compile(&declared).is_err());
+    }
+}
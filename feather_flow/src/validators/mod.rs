@@ -1,8 +1,19 @@
 //! Validation utilities for FeatherFlow
 
+pub mod columns;
+pub mod coverage;
+pub mod hygiene;
+pub mod interpolation;
+pub mod meta;
+pub mod naming;
+pub mod policies;
+pub mod yaml_schema;
+
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::error::FeatherFlowError;
+
 /// Result of a file structure validation
 #[derive(Debug, Clone, PartialEq)]
 pub struct ValidationResult {
@@ -11,7 +22,7 @@ pub struct ValidationResult {
     /// Path that was validated
     pub path: PathBuf,
     /// List of validation errors
-    pub errors: Vec<String>,
+    pub errors: Vec<FeatherFlowError>,
 }
 
 impl ValidationResult {
@@ -25,7 +36,7 @@ impl ValidationResult {
     }
 
     /// Create a new invalid result with errors
-    pub fn invalid(path: PathBuf, errors: Vec<String>) -> Self {
+    pub fn invalid(path: PathBuf, errors: Vec<FeatherFlowError>) -> Self {
         Self {
             is_valid: false,
             path,
@@ -34,7 +45,7 @@ impl ValidationResult {
     }
 
     /// Add an error to the result
-    pub fn add_error(&mut self, error: String) {
+    pub fn add_error(&mut self, error: FeatherFlowError) {
         self.is_valid = false;
         self.errors.push(error);
     }
@@ -46,13 +57,16 @@ impl ValidationResult {
 /// - The directory contains exactly one .yml file with the same name as the directory
 /// - Special case: 'imports' directory and its subdirectories only require .yml files
 ///
-/// Example: models/staging/stg_customers/stg_customers.sql and models/staging/stg_customers/stg_customers.yml
+/// Example: models/staging/stg_customers/stg_customers.sql and
+/// models/staging/stg_customers/stg_customers.yml
 pub fn validate_model_structure(path: &Path) -> ValidationResult {
     let mut result = ValidationResult::valid(path.to_path_buf());
 
     // Check that the path is a directory
     if !path.is_dir() {
-        result.add_error(format!("Path is not a directory: {}", path.display()));
+        result.add_error(FeatherFlowError::NotADirectory {
+            path: path.to_path_buf(),
+        });
         return result;
     }
 
@@ -60,10 +74,9 @@ pub fn validate_model_structure(path: &Path) -> ValidationResult {
     let dir_name = match path.file_name() {
         Some(name) => name.to_string_lossy().to_string(),
         None => {
-            result.add_error(format!(
-                "Could not get directory name for: {}",
-                path.display()
-            ));
+            result.add_error(FeatherFlowError::NotADirectory {
+                path: path.to_path_buf(),
+            });
             return result;
         }
     };
@@ -78,33 +91,30 @@ pub fn validate_model_structure(path: &Path) -> ValidationResult {
         // Check if we have a SQL file matching the directory name
         let sql_file_path = path.join(format!("{}.sql", dir_name));
         if !sql_file_path.exists() {
-            result.add_error(format!(
-                "Missing SQL file: {} (expected at {})",
-                dir_name,
-                sql_file_path.display()
-            ));
+            result.add_error(FeatherFlowError::MissingSqlFile {
+                name: dir_name.clone(),
+                expected: sql_file_path,
+            });
         }
     }
 
     // Check if we have a YAML file matching the directory name
     let yaml_file_path = path.join(format!("{}.yml", dir_name));
     if !yaml_file_path.exists() {
-        result.add_error(format!(
-            "Missing YAML file: {} (expected at {})",
-            dir_name,
-            yaml_file_path.display()
-        ));
+        result.add_error(FeatherFlowError::MissingYamlFile {
+            name: dir_name.clone(),
+            expected: yaml_file_path,
+        });
     }
 
     // Check for other unexpected files
     let entries = match fs::read_dir(path) {
         Ok(entries) => entries,
         Err(e) => {
-            result.add_error(format!(
-                "Failed to read directory {}: {}",
-                path.display(),
-                e
-            ));
+            result.add_error(FeatherFlowError::UnreadableDirectory {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            });
             return result;
         }
     };
@@ -127,17 +137,16 @@ pub fn validate_model_structure(path: &Path) -> ValidationResult {
                 file_name == expected_yml || (!is_imports && file_name == expected_sql);
 
             if !is_valid_file {
-                if is_imports {
-                    result.add_error(format!(
-                        "Unexpected file in imports directory: {} (only {} is expected)",
-                        file_name, expected_yml
-                    ));
+                let expected = if is_imports {
+                    vec![expected_yml]
                 } else {
-                    result.add_error(format!(
-                        "Unexpected file in model directory: {} (only {} and {} are expected)",
-                        file_name, expected_sql, expected_yml
-                    ));
-                }
+                    vec![expected_sql, expected_yml]
+                };
+                result.add_error(FeatherFlowError::UnexpectedFile {
+                    file: file_name,
+                    expected,
+                    imports: is_imports,
+                });
             }
         }
     }
@@ -153,7 +162,9 @@ pub fn validate_models_directory(models_dir: &Path) -> Vec<ValidationResult> {
     if !models_dir.is_dir() {
         let result = ValidationResult::invalid(
             models_dir.to_path_buf(),
-            vec![format!("Path is not a directory: {}", models_dir.display())],
+            vec![FeatherFlowError::NotADirectory {
+                path: models_dir.to_path_buf(),
+            }],
         );
         results.push(result);
         return results;
@@ -250,6 +261,7 @@ fn collect_model_directories(dir: &Path, results: &mut Vec<ValidationResult>) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::codes;
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
@@ -296,7 +308,7 @@ mod tests {
 
         assert!(!result.is_valid);
         assert_eq!(result.errors.len(), 1);
-        assert!(result.errors[0].contains("Missing SQL file"));
+        assert!(result.errors[0].to_string().contains("Missing SQL file"));
     }
 
     #[test]
@@ -316,7 +328,7 @@ mod tests {
 
         assert!(!result.is_valid);
         assert_eq!(result.errors.len(), 1);
-        assert!(result.errors[0].contains("Missing YAML file"));
+        assert!(result.errors[0].to_string().contains("Missing YAML file"));
     }
 
     #[test]
@@ -349,7 +361,7 @@ mod tests {
 
         assert!(!result.is_valid);
         assert_eq!(result.errors.len(), 1);
-        assert!(result.errors[0].contains("Unexpected file"));
+        assert!(result.errors[0].to_string().contains("Unexpected file"));
     }
 
     #[test]
@@ -375,8 +387,10 @@ mod tests {
 
         assert!(!result.is_valid);
         assert_eq!(result.errors.len(), 2); // Missing correct SQL file + unexpected file
-        assert!(result.errors[0].contains("Missing SQL file"));
-        assert!(result.errors[1].contains("Unexpected file"));
+        assert!(result.errors[0].to_string().contains("Missing SQL file"));
+        assert!(result.errors[1].to_string().contains("Unexpected file"));
+        assert_eq!(result.errors[0].code(), codes::MISSING_SQL_FILE);
+        assert_eq!(result.errors[1].code(), codes::UNEXPECTED_FILE);
     }
 
     #[test]
@@ -460,6 +474,8 @@ mod tests {
         // Imports directory should fail validation due to unexpected file
         assert!(!imports_result.is_valid);
         assert_eq!(imports_result.errors.len(), 1);
-        assert!(imports_result.errors[0].contains("Unexpected file in imports directory"));
+        assert!(imports_result.errors[0]
+            .to_string()
+            .contains("Unexpected file in imports directory"));
     }
 }
@@ -0,0 +1,360 @@
+//! Enforcement of the project's naming-convention rules
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::naming::NamingRule;
+
+/// A name that breaks one of the configured rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamingViolation {
+    /// Model directory the violation was found in
+    pub path: PathBuf,
+    pub message: String,
+    /// A compliant name, when one can be derived automatically
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NameKind {
+    Model,
+    Column,
+}
+
+#[derive(Debug)]
+struct Check {
+    kind: NameKind,
+    regex: Regex,
+    must_match: bool,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    path: Option<PathBuf>,
+    checks: Vec<Check>,
+}
+
+/// Naming rules with their patterns compiled
+#[derive(Debug, Default)]
+pub struct NamingRules {
+    rules: Vec<CompiledRule>,
+}
+
+impl NamingRules {
+    pub fn compile(rules: &[NamingRule]) -> Result<Self> {
+        let mut compiled = Vec::with_capacity(rules.len());
+
+        for rule in rules {
+            let scope = rule.path.as_deref().unwrap_or("all models");
+            let mut checks = Vec::new();
+
+            for (kind, pattern, must_match) in [
+                (NameKind::Model, &rule.models, true),
+                (NameKind::Model, &rule.models_must_not, false),
+                (NameKind::Column, &rule.columns, true),
+                (NameKind::Column, &rule.columns_must_not, false),
+            ] {
+                if let Some(pattern) = pattern {
+                    let regex = Regex::new(pattern).with_context(|| {
+                        format!("Invalid pattern '{}' in naming rule for {}", pattern, scope)
+                    })?;
+                    checks.push(Check {
+                        kind,
+                        regex,
+                        must_match,
+                    });
+                }
+            }
+
+            compiled.push(CompiledRule {
+                path: rule.path.as_ref().map(PathBuf::from),
+                checks,
+            });
+        }
+
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Check a model's name and columns; `relative_dir` is the model directory relative to
+    /// the model path
+    pub fn check_model(
+        &self,
+        relative_dir: &Path,
+        model_name: &str,
+        columns: &[String],
+    ) -> Vec<NamingViolation> {
+        let mut violations = Vec::new();
+
+        let applicable = self.rules.iter().filter(|rule| {
+            rule.path
+                .as_ref()
+                .is_none_or(|path| relative_dir.starts_with(path))
+        });
+
+        for rule in applicable {
+            for check in &rule.checks {
+                match check.kind {
+                    NameKind::Model => {
+                        if let Some(violation) = check_name(check, relative_dir, model_name, || {
+                            format!("Model '{}'", model_name)
+                        }) {
+                            violations.push(violation);
+                        }
+                    }
+                    NameKind::Column => {
+                        for column in columns {
+                            if let Some(violation) = check_name(check, relative_dir, column, || {
+                                format!("Column '{}' of model '{}'", column, model_name)
+                            }) {
+                                violations.push(violation);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Check every model directory under `models_dir`, reading column names from each
+    /// model's YAML file
+    pub fn check_models_directory(&self, models_dir: &Path) -> Vec<NamingViolation> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut violations = Vec::new();
+
+        for entry in WalkDir::new(models_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "sql") {
+                continue;
+            }
+
+            let Some(model_dir) = path.parent() else {
+                continue;
+            };
+            let model_name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let relative_dir = model_dir.strip_prefix(models_dir).unwrap_or(model_dir);
+            let columns = read_yaml_columns(&model_dir.join(format!("{}.yml", model_name)));
+
+            violations.extend(self.check_model(relative_dir, &model_name, &columns));
+        }
+
+        violations
+    }
+}
+
+fn check_name(
+    check: &Check,
+    relative_dir: &Path,
+    name: &str,
+    describe: impl Fn() -> String,
+) -> Option<NamingViolation> {
+    if check.regex.is_match(name) == check.must_match {
+        return None;
+    }
+
+    let requirement = if check.must_match {
+        "does not match"
+    } else {
+        "must not match"
+    };
+
+    Some(NamingViolation {
+        path: relative_dir.to_path_buf(),
+        message: format!(
+            "{} {} naming rule '{}'",
+            describe(),
+            requirement,
+            check.regex.as_str()
+        ),
+        suggestion: suggest_name(check, name),
+    })
+}
+
+/// Derive a compliant name: snake_case, then adding a required literal prefix, then
+/// removing the forbidden part
+fn suggest_name(check: &Check, name: &str) -> Option<String> {
+    let snake = to_snake_case(name);
+    let mut candidates = vec![snake.clone()];
+
+    if check.must_match {
+        if let Some(prefix) = literal_prefix(check.regex.as_str()) {
+            candidates.push(format!("{}{}", prefix, snake));
+        }
+    } else {
+        candidates.push(check.regex.replace(&snake, "").to_string());
+    }
+
+    candidates.into_iter().find(|candidate| {
+        !candidate.is_empty()
+            && candidate != name
+            && check.regex.is_match(candidate) == check.must_match
+    })
+}
+
+/// `^stg_` style patterns: the literal text a name must start with
+fn literal_prefix(pattern: &str) -> Option<&str> {
+    let prefix = pattern.strip_prefix('^')?;
+    let is_literal = !prefix.is_empty()
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_literal.then_some(prefix)
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit()) {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else if c == '-' || c == ' ' {
+            snake.push('_');
+        } else {
+            snake.push(c);
+        }
+        previous = Some(c);
+    }
+
+    snake
+}
+
+#[derive(Deserialize)]
+struct ColumnsYaml {
+    models: Option<Vec<ColumnsYamlModel>>,
+}
+
+#[derive(Deserialize)]
+struct ColumnsYamlModel {
+    columns: Option<Vec<ColumnsYamlColumn>>,
+}
+
+#[derive(Deserialize)]
+struct ColumnsYamlColumn {
+    name: String,
+}
+
+fn read_yaml_columns(yaml_path: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(yaml_path) else {
+        return Vec::new();
+    };
+    let Ok(yaml) = serde_yaml::from_str::<ColumnsYaml>(&content) else {
+        return Vec::new();
+    };
+
+    yaml.models
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|model| model.columns.unwrap_or_default())
+        .map(|column| column.name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn rules() -> NamingRules {
+        NamingRules::compile(&[
+            NamingRule {
+                path: Some("staging".to_string()),
+                models: Some("^stg_".to_string()),
+                ..Default::default()
+            },
+            NamingRule {
+                path: Some("marts".to_string()),
+                models_must_not: Some("^stg_".to_string()),
+                ..Default::default()
+            },
+            NamingRule {
+                columns: Some("^[a-z][a-z0-9_]*$".to_string()),
+                ..Default::default()
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rules_are_scoped_by_directory() {
+        let rules = rules();
+
+        assert!(rules
+            .check_model(Path::new("staging/stg_orders"), "stg_orders", &[])
+            .is_empty());
+
+        let violations = rules.check_model(Path::new("staging/orders"), "orders", &[]);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].suggestion.as_deref(), Some("stg_orders"));
+
+        let violations = rules.check_model(Path::new("marts/stg_revenue"), "stg_revenue", &[]);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].message.contains("must not match"));
+        assert_eq!(violations[0].suggestion.as_deref(), Some("revenue"));
+    }
+
+    #[test]
+    fn test_column_rule_suggests_snake_case() {
+        let violations = rules().check_model(
+            Path::new("marts/revenue"),
+            "revenue",
+            &["order_id".to_string(), "CustomerID".to_string()],
+        );
+
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].message.contains("Column 'CustomerID'"));
+        assert_eq!(violations[0].suggestion.as_deref(), Some("customer_id"));
+    }
+
+    #[test]
+    fn test_check_models_directory_reads_yaml_columns() {
+        let temp_dir = tempdir().unwrap();
+        let model_dir = temp_dir.path().join("staging").join("customers");
+        fs::create_dir_all(&model_dir).unwrap();
+        fs::write(model_dir.join("customers.sql"), "SELECT 1 AS id").unwrap();
+        fs::write(
+            model_dir.join("customers.yml"),
+            "version: 2\nmodels:\n  - name: customers\n    columns:\n      - name: Email\n",
+        )
+        .unwrap();
+
+        let violations = rules().check_models_directory(temp_dir.path());
+        assert_eq!(violations.len(), 2);
+        assert!(violations
+            .iter()
+            .all(|v| v.path == Path::new("staging/customers")));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_reported() {
+        let err = NamingRules::compile(&[NamingRule {
+            path: Some("staging".to_string()),
+            models: Some("^stg_(".to_string()),
+            ..Default::default()
+        }])
+        .unwrap_err();
+        assert!(err.to_string().contains("naming rule for staging"));
+    }
+}
@@ -0,0 +1,270 @@
+//! Evaluation of the project's governance policies against the model graph
+
+use std::path::{Path, PathBuf};
+
+use crate::config::policies::{PolicyConfig, PolicyLevel, Requirement};
+use crate::sql_engine::sql_model::{SqlModel, SqlModelCollection};
+
+/// Outcome of a policy across every model it applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl PolicyStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PolicyStatus::Pass => "pass",
+            PolicyStatus::Warn => "warn",
+            PolicyStatus::Fail => "fail",
+        }
+    }
+}
+
+/// A model breaking a policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyViolation {
+    pub model_id: String,
+    /// Model file, relative to the model path
+    pub path: PathBuf,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyResult {
+    pub name: String,
+    pub level: PolicyLevel,
+    /// Number of models the policy applies to
+    pub checked: usize,
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl PolicyResult {
+    pub fn status(&self) -> PolicyStatus {
+        match (self.violations.is_empty(), self.level) {
+            (true, _) => PolicyStatus::Pass,
+            (false, PolicyLevel::Warn) => PolicyStatus::Warn,
+            (false, PolicyLevel::Fail) => PolicyStatus::Fail,
+        }
+    }
+}
+
+/// Evaluate every policy against the models and snapshots of `models`, which must have its
+/// dependency graph built
+pub fn evaluate(policies: &[PolicyConfig], models: &SqlModelCollection) -> Vec<PolicyResult> {
+    let mut sorted: Vec<&SqlModel> = models.models().collect();
+    sorted.sort_by(|a, b| a.unique_id.cmp(&b.unique_id));
+
+    policies
+        .iter()
+        .map(|policy| {
+            let applicable: Vec<&SqlModel> = sorted
+                .iter()
+                .copied()
+                .filter(|model| applies_to(policy, model))
+                .collect();
+            let violations = applicable
+                .iter()
+                .flat_map(|model| {
+                    check_model(policy, model, models)
+                        .into_iter()
+                        .map(|message| PolicyViolation {
+                            model_id: model.unique_id.clone(),
+                            path: model.relative_file_path.clone(),
+                            message,
+                        })
+                })
+                .collect();
+
+            PolicyResult {
+                name: policy.name.clone(),
+                level: policy.level,
+                checked: applicable.len(),
+                violations,
+            }
+        })
+        .collect()
+}
+
+fn applies_to(policy: &PolicyConfig, model: &SqlModel) -> bool {
+    let in_path = policy
+        .path
+        .as_ref()
+        .is_none_or(|path| model.relative_file_path.starts_with(path));
+    let tagged = policy.tags.is_empty() || policy.tags.iter().any(|tag| model.tags.contains(tag));
+    in_path && tagged
+}
+
+/// Messages for each way `model` breaks `policy`
+fn check_model(
+    policy: &PolicyConfig,
+    model: &SqlModel,
+    models: &SqlModelCollection,
+) -> Vec<String> {
+    let mut messages = Vec::new();
+
+    if let Some(allowed) = &policy.allowed_upstream_paths {
+        let mut upstream: Vec<&SqlModel> = model
+            .upstream_models
+            .iter()
+            .filter_map(|id| models.get_model(id))
+            .collect();
+        upstream.sort_by(|a, b| a.unique_id.cmp(&b.unique_id));
+
+        for parent in upstream {
+            if !allowed
+                .iter()
+                .any(|path| parent.relative_file_path.starts_with(path))
+            {
+                messages.push(format!(
+                    "depends on {} ({}), outside {}",
+                    parent.name,
+                    display_dir(&parent.relative_file_path),
+                    allowed.join(", ")
+                ));
+            }
+        }
+    }
+
+    for requirement in &policy.require {
+        if !meets(model, *requirement) {
+            messages.push(format!("has no {}", requirement.as_str()));
+        }
+    }
+
+    if let Some(max) = policy.max_source_references {
+        let count = model.external_sources.len();
+        if count > max {
+            let mut sources: Vec<&str> =
+                model.external_sources.iter().map(String::as_str).collect();
+            sources.sort_unstable();
+            messages.push(format!(
+                "reads {} raw sources ({}), more than {}",
+                count,
+                sources.join(", "),
+                max
+            ));
+        }
+    }
+
+    messages
+}
+
+/// Whether `model` has the property `requirement` asks for
+pub fn meets(model: &SqlModel, requirement: Requirement) -> bool {
+    match requirement {
+        Requirement::Owner => model.owner().is_some(),
+        Requirement::Description => model
+            .description
+            .as_deref()
+            .is_some_and(|description| !description.trim().is_empty()),
+        Requirement::Tests => model
+            .columns
+            .values()
+            .any(|column| !column.tests.is_empty()),
+    }
+}
+
+/// Directory holding a model's own directory, e.g. `staging` for `staging/stg_a/stg_a.sql`
+fn display_dir(relative_file_path: &Path) -> String {
+    relative_file_path
+        .parent()
+        .and_then(Path::parent)
+        .map(|dir| dir.display().to_string())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_engine::fixtures::write_model_in;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_status_follows_level() {
+        let mut result = PolicyResult {
+            name: "p".to_string(),
+            level: PolicyLevel::Warn,
+            checked: 1,
+            violations: Vec::new(),
+        };
+        assert_eq!(result.status(), PolicyStatus::Pass);
+
+        result.violations.push(PolicyViolation {
+            model_id: "model.a.a".to_string(),
+            path: PathBuf::from("a/a.sql"),
+            message: "has no owner".to_string(),
+        });
+        assert_eq!(result.status(), PolicyStatus::Warn);
+        result.level = PolicyLevel::Fail;
+        assert_eq!(result.status(), PolicyStatus::Fail);
+    }
+
+    #[test]
+    fn test_display_dir() {
+        assert_eq!(display_dir(Path::new("staging/stg_a/stg_a.sql")), "staging");
+        assert_eq!(display_dir(Path::new("stg_a/stg_a.sql")), ".");
+    }
+
+    #[test]
+    fn test_policies_check_upstream_paths_requirements_and_sources() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let mut collection = SqlModelCollection::new();
+        collection.add_model(write_model_in(
+            root,
+            "staging",
+            "stg_orders",
+            "SELECT id FROM raw.orders",
+            None,
+        ));
+        collection.add_model(write_model_in(
+            root,
+            "intermediate",
+            "int_payments",
+            "SELECT id FROM raw.payments JOIN raw.refunds USING (id) JOIN raw.fees USING (id)",
+            None,
+        ));
+        collection.add_model(write_model_in(
+            root,
+            "marts",
+            "fct_orders",
+            "SELECT o.id FROM public.stg_orders o JOIN public.int_payments p USING (id)",
+            Some("version: 2\nmodels:\n  - name: fct_orders\n    meta:\n      owner: finance\n      tags: [pii]\n"),
+        ));
+        collection.build_dependency_graph();
+
+        let policies: Vec<PolicyConfig> = serde_yaml::from_str(
+            "- name: marts_read_staging\n  path: marts\n  allowed_upstream_paths: [staging, marts]\n\
+             - name: pii_is_owned_and_tested\n  tags: [pii]\n  require: [owner, tests]\n\
+             - name: few_raw_sources\n  level: warn\n  max_source_references: 2\n",
+        )
+        .unwrap();
+        let results = evaluate(&policies, &collection);
+
+        let summary: Vec<(&str, usize, PolicyStatus)> = results
+            .iter()
+            .map(|result| (result.name.as_str(), result.checked, result.status()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("marts_read_staging", 1, PolicyStatus::Fail),
+                ("pii_is_owned_and_tested", 1, PolicyStatus::Fail),
+                ("few_raw_sources", 3, PolicyStatus::Warn),
+            ]
+        );
+        assert_eq!(
+            results[0].violations[0].message,
+            "depends on int_payments (intermediate), outside staging, marts"
+        );
+        assert_eq!(results[1].violations[0].message, "has no tests");
+        assert_eq!(
+            results[2].violations[0].model_id,
+            "model.intermediate.int_payments.int_payments"
+        );
+    }
+}
@@ -77,11 +77,11 @@ pub mod directory_structure_tests {
         
         let daily_trends_result = results.iter().find(|r| r.path == daily_trends_dir).unwrap();
         assert!(!daily_trends_result.is_valid);
-        assert!(daily_trends_result.errors[0].contains("Missing YAML file"));
+        assert!(daily_trends_result.errors[0].to_string().contains("Missing YAML file"));
         
         let spending_categories_result = results.iter().find(|r| r.path == spending_categories_dir).unwrap();
         assert!(!spending_categories_result.is_valid);
-        assert!(spending_categories_result.errors[0].contains("Unexpected file"));
+        assert!(spending_categories_result.errors[0].to_string().contains("Unexpected file"));
     }
 
     #[test]
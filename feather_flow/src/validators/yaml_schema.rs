@@ -0,0 +1,571 @@
+//! Strict checks of model and source YAML
+//!
+//! Model YAML is read leniently: keys FeatherFlow does not know are ignored, and a file that
+//! does not deserialize loses all of its metadata without a word. `--strict-yaml` checks every
+//! `.yml` file under the model path against the structure FeatherFlow reads instead, and
+//! reports each unknown key, value of the wrong type, missing required key (such as an entry's
+//! `name`) and duplicate entry with the line it is on.
+
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::error::codes;
+use crate::yaml_edit::{Step, YamlDocument};
+
+/// Kinds of findings of the strict YAML check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YamlIssueKind {
+    /// Not YAML at all
+    Unparsable,
+    UnknownKey,
+    WrongType,
+    MissingKey,
+    /// Two entries of a list of named entries with the same name
+    DuplicateEntry,
+}
+
+impl YamlIssueKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            YamlIssueKind::Unparsable => codes::YAML_PARSE_FAILURE,
+            YamlIssueKind::UnknownKey => codes::UNKNOWN_YAML_KEY,
+            YamlIssueKind::WrongType => codes::WRONG_YAML_TYPE,
+            YamlIssueKind::MissingKey => codes::MISSING_YAML_KEY,
+            YamlIssueKind::DuplicateEntry => codes::DUPLICATE_YAML_ENTRY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct YamlIssue {
+    pub kind: YamlIssueKind,
+    pub path: PathBuf,
+    /// Line of the offending key or entry, counting from 1
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for YamlIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// What a value must look like
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Any,
+    String,
+    Integer,
+    /// A list whose items all have this shape
+    Sequence(&'static Shape),
+    /// A mapping with keys of the user's choosing and values of this shape
+    Map(&'static Shape),
+    /// A mapping with these keys only
+    Record(&'static [Field]),
+    /// A list of records identified by their `name`, which must be unique
+    Named(&'static [Field]),
+    /// A column name or a list of them
+    Columns,
+    /// A test name, or a test name mapped to its arguments
+    Test,
+}
+
+impl Shape {
+    fn expected(self) -> &'static str {
+        match self {
+            Shape::Any => "anything",
+            Shape::String => "a string",
+            Shape::Integer => "an integer",
+            Shape::Sequence(_) | Shape::Named(_) => "a list",
+            Shape::Map(_) | Shape::Record(_) => "a mapping",
+            Shape::Columns => "a column name or a list of column names",
+            Shape::Test => "a test name or a test name mapped to its arguments",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    key: &'static str,
+    shape: Shape,
+    required: bool,
+}
+
+const fn optional(key: &'static str, shape: Shape) -> Field {
+    Field {
+        key,
+        shape,
+        required: false,
+    }
+}
+
+const fn required(key: &'static str, shape: Shape) -> Field {
+    Field {
+        key,
+        shape,
+        required: true,
+    }
+}
+
+const COLUMN: &[Field] = &[
+    required("name", Shape::String),
+    optional("description", Shape::String),
+    optional("data_type", Shape::String),
+    optional("tests", Shape::Sequence(&Shape::Test)),
+    optional("meta", Shape::Map(&Shape::Any)),
+];
+
+const TARGET_OVERRIDE: &[Field] = &[
+    optional("schema", Shape::String),
+    optional("database", Shape::String),
+];
+
+const PARTITION_RANGE: &[Field] = &[
+    required("start", Shape::Integer),
+    required("end", Shape::Integer),
+    required("interval", Shape::Integer),
+];
+
+const PARTITION_BY: &[Field] = &[
+    required("field", Shape::String),
+    optional("data_type", Shape::String),
+    optional("granularity", Shape::String),
+    optional("range", Shape::Record(PARTITION_RANGE)),
+];
+
+const MODEL_CONFIG: &[Field] = &[
+    optional("materialized", Shape::String),
+    optional("targets", Shape::Map(&Shape::Record(TARGET_OVERRIDE))),
+    optional("partition_by", Shape::Record(PARTITION_BY)),
+    optional("cluster_by", Shape::Columns),
+    optional("unique_key", Shape::Columns),
+    optional("strategy", Shape::String),
+    optional("updated_at", Shape::String),
+    optional("check_cols", Shape::Columns),
+    optional("criticality", Shape::String),
+    optional("session", Shape::Map(&Shape::Any)),
+    optional("expected_runtime", Shape::String),
+];
+
+const MODEL: &[Field] = &[
+    required("name", Shape::String),
+    optional("description", Shape::String),
+    optional("meta", Shape::Map(&Shape::Any)),
+    optional("config", Shape::Record(MODEL_CONFIG)),
+    optional("database_name", Shape::String),
+    optional("schema_name", Shape::String),
+    optional("object_name", Shape::String),
+    optional("alias", Shape::String),
+    optional("loaded_at_field", Shape::String),
+    optional("columns", Shape::Named(COLUMN)),
+];
+
+const FRESHNESS_THRESHOLD: &[Field] = &[
+    required("count", Shape::Integer),
+    required("period", Shape::String),
+];
+
+const FRESHNESS: &[Field] = &[
+    optional("warn_after", Shape::Record(FRESHNESS_THRESHOLD)),
+    optional("error_after", Shape::Record(FRESHNESS_THRESHOLD)),
+];
+
+const AWAIT: &[Field] = &[
+    optional("file", Shape::String),
+    optional("sql", Shape::String),
+    optional("http", Shape::String),
+    optional("timeout_secs", Shape::Integer),
+    optional("poll_interval_secs", Shape::Integer),
+];
+
+const SOURCE_TABLE: &[Field] = &[
+    required("name", Shape::String),
+    optional("description", Shape::String),
+    optional("loaded_at_field", Shape::String),
+    optional("freshness", Shape::Record(FRESHNESS)),
+    optional("await", Shape::Record(AWAIT)),
+    optional("columns", Shape::Named(COLUMN)),
+];
+
+const SOURCE: &[Field] = &[
+    required("name", Shape::String),
+    optional("description", Shape::String),
+    required("database", Shape::String),
+    optional("loaded_at_field", Shape::String),
+    optional("freshness", Shape::Record(FRESHNESS)),
+    optional("await", Shape::Record(AWAIT)),
+    required("tables", Shape::Named(SOURCE_TABLE)),
+];
+
+const EXPOSURE: &[Field] = &[
+    required("name", Shape::String),
+    optional("description", Shape::String),
+    optional("owner", Shape::String),
+    optional("depends_on", Shape::Sequence(&Shape::String)),
+    optional("tags", Shape::Sequence(&Shape::String)),
+];
+
+const FILE: &[Field] = &[
+    required("version", Shape::Integer),
+    optional("models", Shape::Named(MODEL)),
+    optional("sources", Shape::Named(SOURCE)),
+    optional("exposures", Shape::Named(EXPOSURE)),
+];
+
+/// Every finding in the `.yml` files under `models_dir`, file by file in path order
+pub fn check_models_directory(models_dir: &Path) -> Vec<YamlIssue> {
+    WalkDir::new(models_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "yml"))
+        .flat_map(|path| match fs::read_to_string(&path) {
+            Ok(content) => check_file(&path, &content),
+            Err(err) => vec![YamlIssue {
+                kind: YamlIssueKind::Unparsable,
+                path,
+                line: None,
+                message: format!("Failed to read YAML file: {}", err),
+            }],
+        })
+        .collect()
+}
+
+/// Every finding in `content`, the text of the model or source YAML file at `path`
+pub fn check_file(path: &Path, content: &str) -> Vec<YamlIssue> {
+    let value: Value = match serde_yaml::from_str(content) {
+        Ok(value) => value,
+        Err(err) => {
+            return vec![YamlIssue {
+                kind: YamlIssueKind::Unparsable,
+                path: path.to_path_buf(),
+                line: err.location().map(|location| location.line()),
+                message: format!("Invalid YAML: {}", err),
+            }]
+        }
+    };
+    let mut checker = Checker {
+        path,
+        document: YamlDocument::parse(content).ok(),
+        issues: Vec::new(),
+    };
+    // An empty file is an empty mapping, still missing its version
+    let empty = Value::Mapping(Default::default());
+    let value = if value.is_null() { &empty } else { &value };
+    checker.check(value, Shape::Record(FILE), &mut Vec::new(), "");
+    checker.issues
+}
+
+struct Checker<'a> {
+    path: &'a Path,
+    document: Option<YamlDocument>,
+    issues: Vec<YamlIssue>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, kind: YamlIssueKind, at: &[Step], message: String) {
+        let line = self
+            .document
+            .as_ref()
+            .and_then(|document| document.line(at));
+        self.issues.push(YamlIssue {
+            kind,
+            path: self.path.to_path_buf(),
+            line,
+            message,
+        });
+    }
+
+    /// Check `value`, found at `at`, which `label` names in messages
+    fn check<'v>(&mut self, value: &'v Value, shape: Shape, at: &mut Vec<Step<'v>>, label: &str) {
+        let fits = match (shape, value) {
+            (Shape::Any, _) => true,
+            (Shape::String, Value::String(_)) => true,
+            (Shape::Integer, Value::Number(number)) => number.is_i64() || number.is_u64(),
+            (Shape::Sequence(item), Value::Sequence(items)) => {
+                for (index, value) in items.iter().enumerate() {
+                    at.push(Step::Index(index));
+                    self.check(value, *item, at, &format!("{}[{}]", label, index));
+                    at.pop();
+                }
+                true
+            }
+            (Shape::Map(item), Value::Mapping(mapping)) => {
+                for (key, value) in mapping {
+                    let Some(key) = key.as_str() else {
+                        self.report(
+                            YamlIssueKind::WrongType,
+                            at,
+                            format!("Keys of {} must be strings", place(label)),
+                        );
+                        continue;
+                    };
+                    at.push(Step::Key(key));
+                    self.check(value, *item, at, &child(label, key));
+                    at.pop();
+                }
+                true
+            }
+            (Shape::Record(fields), Value::Mapping(_)) => {
+                self.check_record(value, fields, at, label);
+                true
+            }
+            (Shape::Named(fields), Value::Sequence(items)) => {
+                self.check_named(items, fields, at, label);
+                true
+            }
+            (Shape::Columns, Value::String(_)) => true,
+            (Shape::Columns, Value::Sequence(items)) => items.iter().all(Value::is_string),
+            (Shape::Test, Value::String(_)) => true,
+            (Shape::Test, Value::Mapping(mapping)) => {
+                mapping.len() == 1
+                    && mapping.iter().all(|(name, args)| {
+                        name.is_string() && (args.is_mapping() || args.is_null())
+                    })
+            }
+            _ => false,
+        };
+        if !fits {
+            self.report(
+                YamlIssueKind::WrongType,
+                at,
+                format!(
+                    "{} should be {}, found {}",
+                    place(label),
+                    shape.expected(),
+                    found(value)
+                ),
+            );
+        }
+    }
+
+    fn check_record<'v>(
+        &mut self,
+        value: &'v Value,
+        fields: &'static [Field],
+        at: &mut Vec<Step<'v>>,
+        label: &str,
+    ) {
+        let Value::Mapping(mapping) = value else {
+            return;
+        };
+        for (key, value) in mapping {
+            let Some(key) = key.as_str() else {
+                self.report(
+                    YamlIssueKind::WrongType,
+                    at,
+                    format!("Keys of {} must be strings", place(label)),
+                );
+                continue;
+            };
+            at.push(Step::Key(key));
+            match fields.iter().find(|field| field.key == key) {
+                // Left empty, as if it were not there
+                Some(field) if value.is_null() && !field.required => {}
+                Some(field) => self.check(value, field.shape, at, &child(label, key)),
+                None => self.report(
+                    YamlIssueKind::UnknownKey,
+                    at,
+                    format!("Unknown key '{}' in {}", key, place(label)),
+                ),
+            }
+            at.pop();
+        }
+        for field in fields.iter().filter(|field| field.required) {
+            if !mapping.contains_key(field.key) {
+                self.report(
+                    YamlIssueKind::MissingKey,
+                    at,
+                    format!("{} is missing '{}'", place(label), field.key),
+                );
+            }
+        }
+    }
+
+    fn check_named<'v>(
+        &mut self,
+        items: &'v [Value],
+        fields: &'static [Field],
+        at: &mut Vec<Step<'v>>,
+        label: &str,
+    ) {
+        // Line of the first entry with each name
+        let mut seen: HashMap<&str, Option<usize>> = HashMap::new();
+        for (index, item) in items.iter().enumerate() {
+            at.push(Step::Index(index));
+            let name = item.get("name").and_then(Value::as_str);
+            let item_label = match name {
+                Some(name) => format!("{}[{}]", label, name),
+                None => format!("{}[{}]", label, index),
+            };
+            self.check(item, Shape::Record(fields), at, &item_label);
+            if let Some(name) = name {
+                let line = self
+                    .document
+                    .as_ref()
+                    .and_then(|document| document.line(at));
+                match seen.get(name) {
+                    Some(first) => {
+                        let first = first
+                            .map(|line| format!(" on line {}", line))
+                            .unwrap_or_default();
+                        self.report(
+                            YamlIssueKind::DuplicateEntry,
+                            at,
+                            format!(
+                                "Duplicate entry '{}' in {}, first defined{}",
+                                name,
+                                place(label),
+                                first
+                            ),
+                        );
+                    }
+                    None => {
+                        seen.insert(name, line);
+                    }
+                }
+            }
+            at.pop();
+        }
+    }
+}
+
+fn child(label: &str, key: &str) -> String {
+    if label.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", label, key)
+    }
+}
+
+fn place(label: &str) -> String {
+    if label.is_empty() {
+        "The file".to_string()
+    } else {
+        format!("'{}'", label)
+    }
+}
+
+fn found(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "nothing",
+        Value::Bool(_) => "a boolean",
+        Value::Number(number) if number.is_f64() => "a number",
+        Value::Number(_) => "an integer",
+        Value::String(_) => "a string",
+        Value::Sequence(_) => "a list",
+        Value::Mapping(_) => "a mapping",
+        Value::Tagged(_) => "a tagged value",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_schema_violations_with_lines() {
+        let yaml = "\
+version: 2
+models:
+  - name: orders
+    descripton: Orders
+    config:
+      materialized: [table]
+    columns:
+      - name: id
+      - description: no name
+      - name: id
+  - name: orders
+";
+        let issues = check_file(Path::new("orders.yml"), yaml);
+        let found: Vec<(YamlIssueKind, Option<usize>, &str)> = issues
+            .iter()
+            .map(|issue| (issue.kind, issue.line, issue.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    YamlIssueKind::UnknownKey,
+                    Some(4),
+                    "Unknown key 'descripton' in 'models[orders]'"
+                ),
+                (
+                    YamlIssueKind::WrongType,
+                    Some(6),
+                    "'models[orders].config.materialized' should be a string, found a list"
+                ),
+                (
+                    YamlIssueKind::MissingKey,
+                    Some(9),
+                    "'models[orders].columns[1]' is missing 'name'"
+                ),
+                (
+                    YamlIssueKind::DuplicateEntry,
+                    Some(10),
+                    "Duplicate entry 'id' in 'models[orders].columns', first defined on line 8"
+                ),
+                (
+                    YamlIssueKind::DuplicateEntry,
+                    Some(11),
+                    "Duplicate entry 'orders' in 'models', first defined on line 3"
+                ),
+            ]
+        );
+        assert_eq!(issues[1].kind.code(), "FF203");
+        assert_eq!(
+            issues[0].to_string(),
+            "orders.yml:4: Unknown key 'descripton' in 'models[orders]'"
+        );
+    }
+
+    #[test]
+    fn test_accepts_what_featherflow_reads() {
+        let yaml = "\
+version: 2
+models:
+  - name: orders
+    description:
+    meta: {owner: finance, tags: [finance]}
+    config:
+      materialized: table
+      cluster_by: [customer_id, region]
+      partition_by: {field: ordered_at, granularity: day}
+      targets:
+        prod: {schema: analytics}
+    columns:
+      - name: id
+        tests:
+          - unique
+          - accepted_values:
+              values: [1, 2]
+sources:
+  - name: raw
+    database: raw_data
+    tables:
+      - name: orders
+exposures:
+  - name: dashboard
+    depends_on: [orders]
+";
+        assert_eq!(check_file(Path::new("orders.yml"), yaml), Vec::new());
+
+        let missing = check_file(Path::new("orders.yml"), "models: []\n");
+        assert_eq!(missing[0].kind, YamlIssueKind::MissingKey);
+        assert_eq!(missing[0].message, "The file is missing 'version'");
+
+        let broken = check_file(Path::new("orders.yml"), "models:\n  - name: [\n");
+        assert_eq!(broken[0].kind, YamlIssueKind::Unparsable);
+        assert!(broken[0].line.is_some());
+    }
+}
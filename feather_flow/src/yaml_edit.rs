@@ -0,0 +1,626 @@
+//! Editing YAML in place, keeping comments, key order and formatting
+//!
+//! A serde_yaml round trip drops comments and reformats every value, so programmatic
+//! changes to hand-written YAML (codegen, refactors, autofixes) go through a `YamlDocument`
+//! instead. It edits the block-style YAML of FeatherFlow projects line by line: only the
+//! lines of the entry being changed are rewritten, new entries go after their siblings, and
+//! an edit that would leave the document unparsable is rolled back.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_yaml::Value;
+use std::fmt;
+
+/// One step of a path into a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step<'a> {
+    /// The value of a mapping key
+    Key(&'a str),
+    /// The item of a sequence whose `name` is this, e.g. a model or a column
+    Named(&'a str),
+    /// The item of a sequence at this position, counting from 0
+    Index(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct YamlDocument {
+    lines: Vec<String>,
+    trailing_newline: bool,
+}
+
+/// Lines `start..end` holding the entries of a mapping whose keys start at column `indent`,
+/// or the items of a sequence whose dashes are at column `indent`
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    start: usize,
+    end: usize,
+    indent: usize,
+}
+
+/// A line that is neither blank nor a comment
+struct Content<'a> {
+    indent: usize,
+    /// Starts a sequence item
+    dash: bool,
+    /// Where the text after any `- ` starts
+    column: usize,
+    text: &'a str,
+}
+
+fn content(line: &str) -> Option<Content<'_>> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = &line[indent..];
+    if rest.trim().is_empty()
+        || rest.starts_with('#')
+        || (indent == 0 && (rest.starts_with("---") || rest.starts_with("...")))
+    {
+        return None;
+    }
+    if rest == "-" || rest.starts_with("- ") {
+        let text = rest[1..].trim_start_matches(' ');
+        return Some(Content {
+            indent,
+            dash: true,
+            column: line.len() - text.len(),
+            text,
+        });
+    }
+    Some(Content {
+        indent,
+        dash: false,
+        column: indent,
+        text: rest,
+    })
+}
+
+/// `text` without its trailing comment, and the comment with the whitespace before it
+fn split_comment(text: &str) -> (&str, &str) {
+    let mut quote = None;
+    let mut after_space = true;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'') && after_space => quote = Some(c),
+            None if c == '#' && after_space => {
+                let value = text[..i].trim_end();
+                return (value, &text[value.len()..]);
+            }
+            None => {}
+        }
+        after_space = c.is_whitespace();
+    }
+    (text, "")
+}
+
+/// A `key: value` line: the key, the offset of its colon and the inline value
+struct Entry<'a> {
+    key: &'a str,
+    colon: usize,
+    value: &'a str,
+}
+
+fn entry(text: &str) -> Option<Entry<'_>> {
+    let (text, _) = split_comment(text);
+    let (key, colon) = match text.chars().next() {
+        Some(q @ ('"' | '\'')) => {
+            let close = text[1..].find(q)? + 1;
+            let colon = close + 1;
+            (&text[1..close], colon)
+        }
+        _ => {
+            let colon = text
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| text[i + 1..].is_empty() || text[i + 1..].starts_with(' '))?;
+            (text[..colon].trim_end(), colon)
+        }
+    };
+    let rest = text[colon..].strip_prefix(':')?;
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some(Entry {
+        key,
+        colon,
+        value: rest.trim(),
+    })
+}
+
+/// A key as written in YAML, quoted when it is not a plain word
+fn key_text(key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'));
+    if plain {
+        key.to_string()
+    } else {
+        format!("{:?}", key)
+    }
+}
+
+/// A value rendered for the entry of a key at `column`: the text following `key:` and the
+/// lines below it
+fn render(value: &Value, column: usize) -> Result<(String, Vec<String>)> {
+    let text = serde_yaml::to_string(value)?;
+    let nested = match value {
+        Value::Mapping(mapping) => !mapping.is_empty(),
+        Value::Sequence(items) => !items.is_empty(),
+        _ => false,
+    };
+    let mut lines = text.lines();
+    let first = if nested {
+        String::new()
+    } else {
+        lines.next().unwrap_or_default().to_string()
+    };
+    let pad = " ".repeat(column + 2);
+    let rest = lines
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", pad, line)
+            }
+        })
+        .collect();
+    Ok((first, rest))
+}
+
+/// A sequence of scalars in flow style, e.g. `[finance, pii]`
+fn flow(items: &[Value]) -> Result<String> {
+    let rendered = items
+        .iter()
+        .map(|item| match item {
+            Value::Mapping(_) | Value::Sequence(_) => {
+                bail!("Only scalars can be added to a flow sequence")
+            }
+            _ => Ok(serde_yaml::to_string(item)?.trim_end().to_string()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!("[{}]", rendered.join(", ")))
+}
+
+impl YamlDocument {
+    pub fn parse(text: &str) -> Result<Self> {
+        serde_yaml::from_str::<Value>(text).context("Invalid YAML")?;
+        Ok(Self {
+            lines: text.lines().map(str::to_string).collect(),
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+        })
+    }
+
+    /// Line, counting from 1, of the value at `path`: the line of its key, or the first line
+    /// of a sequence item. A value inside a flow collection such as `[a, b]`, or missing from
+    /// the document, is placed at the deepest part of `path` that is written in block style.
+    pub fn line(&self, path: &[Step]) -> Option<usize> {
+        let mut block = self.root();
+        let mut found = None;
+        for step in path {
+            let next = match *step {
+                Step::Key(key) => self
+                    .find_key(block, key)
+                    .map(|line| (line, self.value_block(line, block.end))),
+                Step::Named(name) => self.find_named(block, name).map(|item| (item.start, item)),
+                Step::Index(index) => self.items(block).get(index).map(|item| (item.start, *item)),
+            };
+            let Some((line, next)) = next else {
+                break;
+            };
+            found = Some(line + 1);
+            block = next;
+        }
+        found
+    }
+
+    /// Set `key` of the mapping at `path`, creating the key and any missing mappings on the
+    /// way. Values equal to the current one leave the document as written.
+    pub fn set<T: Serialize>(&mut self, path: &[Step], key: &str, value: &T) -> Result<()> {
+        let value = serde_yaml::to_value(value)?;
+        self.edit(|doc| {
+            let block = doc.resolve(path, true)?.expect("created on the way");
+            match doc.find_key(block, key) {
+                Some(line) => {
+                    let end = doc.value_end(line, block.end);
+                    if doc.entry_value(line, end).as_ref() == Some(&value) {
+                        return Ok(());
+                    }
+                    let column = doc.column(line);
+                    let (first, rest) = render(&value, column)?;
+                    doc.rewrite_value(line, &first);
+                    doc.lines.splice(line + 1..end, rest);
+                }
+                None => {
+                    let (first, rest) = render(&value, block.indent)?;
+                    let separator = if first.is_empty() { "" } else { " " };
+                    let mut lines = vec![format!(
+                        "{}{}:{}{}",
+                        " ".repeat(block.indent),
+                        key_text(key),
+                        separator,
+                        first
+                    )];
+                    lines.extend(rest);
+                    let at = insertion_point(block);
+                    doc.lines.splice(at..at, lines);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Append `item` to the sequence under `key` of the mapping at `path`, creating it if
+    /// missing. Flow sequences such as `tags: [a, b]` stay in flow style.
+    pub fn append<T: Serialize>(&mut self, path: &[Step], key: &str, item: &T) -> Result<()> {
+        let item = serde_yaml::to_value(item)?;
+        self.edit(|doc| {
+            let block = doc.resolve(path, true)?.expect("created on the way");
+            let (line, limit) = match doc.find_key(block, key) {
+                Some(line) => (line, block.end),
+                None => {
+                    let at = insertion_point(block);
+                    let line = format!("{}{}:", " ".repeat(block.indent), key_text(key));
+                    doc.lines.insert(at, line);
+                    (at, at + 1)
+                }
+            };
+
+            let value = doc.inline_value(line).to_string();
+            if value.starts_with('[') {
+                let Value::Sequence(mut items) = serde_yaml::from_str(&value)? else {
+                    bail!("'{}' is not a sequence", key);
+                };
+                items.push(item);
+                doc.rewrite_value(line, &flow(&items)?);
+                return Ok(());
+            }
+            if !value.is_empty() && value != "~" && value != "null" {
+                bail!("'{}' is not a sequence", key);
+            }
+            doc.rewrite_value(line, "");
+
+            let sequence = doc.value_block(line, limit);
+            let first = (sequence.start..sequence.end).find_map(|i| content(&doc.lines[i]));
+            if first.is_some_and(|first| !first.dash) {
+                bail!("'{}' is not a sequence", key);
+            }
+            let pad = " ".repeat(sequence.indent);
+            let lines: Vec<String> = serde_yaml::to_string(&item)?
+                .lines()
+                .enumerate()
+                .map(|(n, text)| {
+                    let marker = if n == 0 { "- " } else { "  " };
+                    format!("{}{}{}", pad, marker, text)
+                })
+                .collect();
+            let at = insertion_point(sequence);
+            doc.lines.splice(at..at, lines);
+            Ok(())
+        })
+    }
+
+    /// Run `change`, undoing it when it fails or leaves invalid YAML
+    fn edit(&mut self, change: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        let before = self.lines.clone();
+        let result = change(self).and_then(|()| {
+            serde_yaml::from_str::<Value>(&self.to_string())
+                .map(|_| ())
+                .context("Edit would produce invalid YAML")
+        });
+        if result.is_err() {
+            self.lines = before;
+        }
+        result
+    }
+
+    fn root(&self) -> Block {
+        let end = self.trimmed_end(0, self.lines.len());
+        let indent = (0..end)
+            .find_map(|i| content(&self.lines[i]))
+            .map_or(0, |line| line.indent);
+        Block {
+            start: 0,
+            end,
+            indent,
+        }
+    }
+
+    /// The block at `path`, created on the way when `create` is set
+    fn resolve(&mut self, path: &[Step], create: bool) -> Result<Option<Block>> {
+        let mut block = self.root();
+        for step in path {
+            block = match *step {
+                Step::Key(key) => match self.find_key(block, key) {
+                    Some(line) => {
+                        let value = self.inline_value(line);
+                        if !value.is_empty() {
+                            if !(create && value == "{}") {
+                                bail!("'{}' is not a block mapping or sequence", key);
+                            }
+                            self.rewrite_value(line, "");
+                        }
+                        self.value_block(line, block.end)
+                    }
+                    None if create => {
+                        let at = insertion_point(block);
+                        let line = format!("{}{}:", " ".repeat(block.indent), key_text(key));
+                        self.lines.insert(at, line);
+                        self.value_block(at, at + 1)
+                    }
+                    None => return Ok(None),
+                },
+                Step::Named(name) => match self.find_named(block, name) {
+                    Some(item) => item,
+                    None if create => bail!("No item named '{}'", name),
+                    None => return Ok(None),
+                },
+                Step::Index(index) => match self.items(block).get(index) {
+                    Some(item) => *item,
+                    None if create => bail!("No item {}", index),
+                    None => return Ok(None),
+                },
+            };
+        }
+        Ok(Some(block))
+    }
+
+    /// `end` moved back over trailing blank and comment lines
+    fn trimmed_end(&self, start: usize, mut end: usize) -> usize {
+        while end > start && content(&self.lines[end - 1]).is_none() {
+            end -= 1;
+        }
+        end
+    }
+
+    fn column(&self, line: usize) -> usize {
+        content(&self.lines[line]).map_or(0, |line| line.column)
+    }
+
+    fn inline_value(&self, line: usize) -> &str {
+        content(&self.lines[line])
+            .and_then(|line| entry(line.text))
+            .map_or("", |entry| entry.value)
+    }
+
+    fn find_key(&self, block: Block, key: &str) -> Option<usize> {
+        (block.start..block.end).find(|&i| {
+            content(&self.lines[i]).is_some_and(|line| {
+                line.column == block.indent && entry(line.text).is_some_and(|e| e.key == key)
+            })
+        })
+    }
+
+    /// End of the value of the entry at `line`, within `limit`
+    fn value_end(&self, line: usize, limit: usize) -> usize {
+        let column = self.column(line);
+        let mut end = line + 1;
+        while end < limit {
+            if let Some(next) = content(&self.lines[end]) {
+                if next.indent < column || (next.indent == column && !next.dash) {
+                    break;
+                }
+            }
+            end += 1;
+        }
+        self.trimmed_end(line + 1, end)
+    }
+
+    fn value_block(&self, line: usize, limit: usize) -> Block {
+        let end = self.value_end(line, limit);
+        let indent = (line + 1..end)
+            .find_map(|i| content(&self.lines[i]))
+            .map_or(self.column(line) + 2, |first| first.indent);
+        Block {
+            start: line + 1,
+            end,
+            indent,
+        }
+    }
+
+    /// The items of the sequence `block`, in order
+    fn items(&self, block: Block) -> Vec<Block> {
+        let starts: Vec<usize> = (block.start..block.end)
+            .filter(|&i| {
+                content(&self.lines[i]).is_some_and(|line| line.dash && line.indent == block.indent)
+            })
+            .collect();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(n, &start)| {
+                let end = starts.get(n + 1).copied().unwrap_or(block.end);
+                Block {
+                    start,
+                    end: self.trimmed_end(start, end),
+                    indent: self.column(start),
+                }
+            })
+            .collect()
+    }
+
+    /// The item of the sequence `block` whose `name` is `name`
+    fn find_named(&self, block: Block, name: &str) -> Option<Block> {
+        self.items(block).into_iter().find(|&item| {
+            self.find_key(item, "name").is_some_and(|line| {
+                serde_yaml::from_str::<Value>(self.inline_value(line))
+                    .is_ok_and(|value| value.as_str() == Some(name))
+            })
+        })
+    }
+
+    /// Current value of the entry at `line`, whose value ends at `end`
+    fn entry_value(&self, line: usize, end: usize) -> Option<Value> {
+        let mut text = self.inline_value(line).to_string();
+        for below in &self.lines[line + 1..end] {
+            text.push('\n');
+            text.push_str(below);
+        }
+        serde_yaml::from_str(&text).ok()
+    }
+
+    /// Replace the inline value of the entry at `line`, keeping its key and comment
+    fn rewrite_value(&mut self, line: usize, value: &str) {
+        let text = &self.lines[line];
+        let Some(column) = content(text).map(|line| line.column) else {
+            return;
+        };
+        let Some(colon) = entry(&text[column..]).map(|entry| entry.colon) else {
+            return;
+        };
+        let (_, comment) = split_comment(&text[column..]);
+        let separator = if value.is_empty() { "" } else { " " };
+        self.lines[line] = format!(
+            "{}{}{}{}",
+            &text[..column + colon + 1],
+            separator,
+            value,
+            comment
+        );
+    }
+}
+
+/// Where a new entry of `block` goes: after its last entry
+fn insertion_point(block: Block) -> usize {
+    block.end.max(block.start)
+}
+
+impl fmt::Display for YamlDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))?;
+        if self.trailing_newline && !self.lines.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const MODEL_YAML: &str = "\
+# Orders, owned by finance
+version: 2
+
+models:
+  - name: orders
+    description: Orders  # one row per order
+    meta:
+      owner: finance
+    tags: [finance]
+    columns:
+      - name: id  # primary key
+        tests: [unique]
+
+      - name: amount
+";
+
+    #[test]
+    fn test_edits_keep_comments_and_order() {
+        let mut doc = YamlDocument::parse(MODEL_YAML).unwrap();
+        let orders = [Step::Key("models"), Step::Named("orders")];
+
+        doc.set(&orders, "description", &"All orders").unwrap();
+        doc.set(
+            &[
+                Step::Key("models"),
+                Step::Named("orders"),
+                Step::Key("config"),
+            ],
+            "materialized",
+            &"table",
+        )
+        .unwrap();
+        doc.append(&orders, "tags", &"pii").unwrap();
+        doc.append(
+            &orders,
+            "columns",
+            &BTreeMap::from([("name", "ordered_at"), ("data_type", "timestamp")]),
+        )
+        .unwrap();
+        doc.set(
+            &[
+                Step::Key("models"),
+                Step::Named("orders"),
+                Step::Key("columns"),
+                Step::Named("amount"),
+            ],
+            "data_type",
+            &"decimal(18, 2)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.to_string(),
+            "\
+# Orders, owned by finance
+version: 2
+
+models:
+  - name: orders
+    description: All orders  # one row per order
+    meta:
+      owner: finance
+    tags: [finance, pii]
+    columns:
+      - name: id  # primary key
+        tests: [unique]
+
+      - name: amount
+        data_type: decimal(18, 2)
+      - data_type: timestamp
+        name: ordered_at
+    config:
+      materialized: table
+"
+        );
+    }
+
+    #[test]
+    fn test_unchanged_values_keep_their_formatting() {
+        let yaml = "models:\n  - name: orders\n    description: \"Orders\"\n";
+        let mut doc = YamlDocument::parse(yaml).unwrap();
+        doc.set(
+            &[Step::Key("models"), Step::Named("orders")],
+            "description",
+            &"Orders",
+        )
+        .unwrap();
+        assert_eq!(doc.to_string(), yaml);
+    }
+
+    #[test]
+    fn test_failed_edit_leaves_the_document_unchanged() {
+        let mut doc = YamlDocument::parse(MODEL_YAML).unwrap();
+        assert!(doc.set(&[Step::Key("version")], "x", &1).is_err());
+        assert_eq!(doc.to_string(), MODEL_YAML);
+    }
+
+    #[test]
+    fn test_line_of_path() {
+        let doc = YamlDocument::parse(MODEL_YAML).unwrap();
+        let columns = [Step::Key("models"), Step::Index(0), Step::Key("columns")];
+        assert_eq!(doc.line(&[Step::Key("version")]), Some(2));
+        assert_eq!(
+            doc.line(&[&columns[..], &[Step::Index(1)]].concat()),
+            Some(14)
+        );
+        assert_eq!(
+            doc.line(&[&columns[..], &[Step::Named("id"), Step::Key("tests")]].concat()),
+            Some(12)
+        );
+        // Inside a flow sequence, the key holding it
+        assert_eq!(
+            doc.line(&[
+                Step::Key("models"),
+                Step::Index(0),
+                Step::Key("tags"),
+                Step::Index(1)
+            ]),
+            Some(9)
+        );
+        assert_eq!(doc.line(&[Step::Key("sources")]), None);
+    }
+}